@@ -0,0 +1,60 @@
+#![no_std]
+#![no_main]
+#![deny(warnings)]
+
+/// Crates included to show a tiny web client on a W5500 Ethernet shield.
+use rustduino::com::usart::println_string;
+use rustduino::hal::watchdog::WatchDog;
+use rustduino::net::dhcp::request_lease;
+use rustduino::net::w5500::{Protocol, W5500};
+
+const MAC: [u8; 6] = [0xDE, 0xAD, 0xBE, 0xEF, 0xFE, 0xED];
+const SOCKET: u8 = 0;
+// Address of the web server to be asked, here example.com.
+const SERVER: [u8; 4] = [93, 184, 216, 34];
+
+#[no_mangle]
+pub fn main() {
+    // Disable watchdog
+    let watchdog = unsafe { WatchDog::new() };
+    watchdog.disable();
+
+    // The Ethernet shield has its chip select on digital pin 10.
+    let mut w5500 = W5500::new(10).unwrap();
+    w5500.set_mac(MAC);
+    if request_lease(&mut w5500, 1, 5000).is_err() {
+        println_string("DHCP failed");
+        loop {}
+    }
+
+    // Fetches the page every 10 seconds and prints it through USART.
+    loop {
+        w5500.open(SOCKET, Protocol::Tcp, 49152).unwrap();
+        if w5500.connect(SOCKET, SERVER, 80).is_ok() {
+            let _ = w5500.send(
+                SOCKET,
+                b"GET / HTTP/1.1\r\nHost: example.com\r\nConnection: close\r\n\r\n",
+            );
+            let mut buffer = [0u8; 64];
+            // The server closes the connection after the page.
+            while let Ok(n) = w5500.recv(SOCKET, &mut buffer) {
+                if let Ok(text) = core::str::from_utf8(&buffer[..n]) {
+                    if n > 0 {
+                        println_string(text);
+                    }
+                }
+            }
+        } else {
+            println_string("Connection failed");
+        }
+        let _ = w5500.disconnect(SOCKET);
+
+        rustduino::delay::delay_ms(10000);
+    }
+}
+
+/// This function is called on panic.
+#[panic_handler]
+fn panic(_info: &core::panic::PanicInfo) -> ! {
+    loop {}
+}
@@ -0,0 +1,86 @@
+#![no_std]
+#![no_main]
+#![deny(warnings)]
+
+/// Crates included to show a tiny web server on a W5500 Ethernet shield.
+use rustduino::hal::watchdog::WatchDog;
+use rustduino::net::dhcp::request_lease;
+use rustduino::net::w5500::{Protocol, SocketStatus, W5500};
+
+const MAC: [u8; 6] = [0xDE, 0xAD, 0xBE, 0xEF, 0xFE, 0xED];
+const SOCKET: u8 = 0;
+
+#[no_mangle]
+pub fn main() {
+    // Disable watchdog
+    let watchdog = unsafe { WatchDog::new() };
+    watchdog.disable();
+
+    // The Ethernet shield has its chip select on digital pin 10.
+    let mut w5500 = W5500::new(10).unwrap();
+    w5500.set_mac(MAC);
+
+    // Ask the router for an address, falling back to a fixed one.
+    if request_lease(&mut w5500, 1, 5000).is_err() {
+        w5500.configure(
+            MAC,
+            [192, 168, 1, 177],
+            [255, 255, 255, 0],
+            [192, 168, 1, 1],
+        );
+    }
+
+    let mut requests: u32 = 0;
+    loop {
+        match w5500.status(SOCKET) {
+            SocketStatus::Closed => {
+                // Wait for the next client on port 80.
+                w5500.open(SOCKET, Protocol::Tcp, 80).unwrap();
+                w5500.listen(SOCKET).unwrap();
+            }
+            SocketStatus::Established => {
+                // Answer once the request has arrived, whatever it asks for.
+                let mut request = [0u8; 64];
+                if let Ok(n) = w5500.recv(SOCKET, &mut request) {
+                    if n > 0 {
+                        // Drop the rest of the request.
+                        while let Ok(n) = w5500.recv(SOCKET, &mut request) {
+                            if n == 0 {
+                                break;
+                            }
+                        }
+                        requests += 1;
+                        let _ = w5500.send(
+                            SOCKET,
+                            b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nConnection: close\r\n\r\n",
+                        );
+                        let _ = w5500.send(SOCKET, b"Hello from RustDuino, request number ");
+                        let mut digits = [0u8; 10];
+                        let mut i = digits.len();
+                        let mut value = requests;
+                        loop {
+                            i -= 1;
+                            digits[i] = b'0' + (value % 10) as u8;
+                            value /= 10;
+                            if value == 0 {
+                                break;
+                            }
+                        }
+                        let _ = w5500.send(SOCKET, &digits[i..]);
+                        let _ = w5500.disconnect(SOCKET);
+                    }
+                }
+            }
+            SocketStatus::CloseWait => {
+                let _ = w5500.disconnect(SOCKET);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// This function is called on panic.
+#[panic_handler]
+fn panic(_info: &core::panic::PanicInfo) -> ! {
+    loop {}
+}
@@ -7,14 +7,18 @@ use rustduino::sensors::*;
 
 #[no_mangle]
 fn main() {
-    let sensor = AHT10::new(&mut AHT10::get());
+    let mut sensor = match AHT10::new() {
+        Ok(sensor) => sensor,
+        // The sensor did not answer or could not be calibrated.
+        Err(_) => loop {},
+    };
 
     loop {
         // Get relative humidity.
-        sensor.relative_humidity();
+        let _humidity = sensor.relative_humidity();
 
         // Get temperature
-        sensor.temperature();
+        let _temperature = sensor.temperature();
 
         // Waiting for 2 seconds.
         rustduino::delay::delay_ms(2000);
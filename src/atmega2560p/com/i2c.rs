@@ -20,7 +20,10 @@
 //!  with the attached peripheral devices.
 //!* This has been implemented according to the chip ATMEGA2560P here.
 
+use crate::atmega2560p::hal::interrupts::without_interrupts;
+use crate::atmega2560p::hal::power::{Peripherals, Power};
 use crate::delay::delay_ms;
+use crate::error::Error;
 use bit_field::BitField;
 use core::ptr::read_volatile;
 use fixed_slice_vec::FixedSliceVec;
@@ -31,60 +34,60 @@ use volatile::Volatile;
 /// it according to the data sheet.
 #[repr(C, packed)]
 pub struct Twi {
-    _twbr: Volatile<u8>,
-    twcr: Volatile<u8>,
+    twbr: Volatile<u8>,
     twsr: Volatile<u8>,
+    twar: Volatile<u8>,
     twdr: Volatile<u8>,
-    _twar: Volatile<u8>,
-    _twamr: Volatile<u8>,
+    twcr: Volatile<u8>,
+    twamr: Volatile<u8>,
 }
 
 // TWCR register's bits definitions
-const TWINT: u8 = 0;
-const TWEA: u8 = 1;
-const TWSTA: u8 = 2;
-const TWSTO: u8 = 3;
-const _TWWC: u8 = 4;
-const TWEN: u8 = 5;
-const _TWIE: u8 = 7;
+const TWINT: u8 = 7;
+const TWEA: u8 = 6;
+const TWSTA: u8 = 5;
+const TWSTO: u8 = 4;
+const _TWWC: u8 = 3;
+const TWEN: u8 = 2;
+const TWIE: u8 = 0;
 
 static TWI_FREQUENCY: u32 = 100000;
 
 ///* This function reads the device clock freequency setup and provide
 ///  the details in form of boolean numbers and a 8 bit unsigned integer to
 ///  check the settings of the I2C carefully.
-///* If the clock freequency in very low then the function panics as the I2C protocol cannot be activated
-///  properly at such low freequencies.
+///* If 100 kHz can not be reached from the clock freequency, `Error::InvalidParam` is returned
+///  as the I2C protocol cannot be activated properly.
 ///  # Returns
-///  * `a tuple` - Consisting of the following 3 Items -
+///  * `a Result of a tuple` - Consisting of the following 3 Items -
 ///     * `a u8` - Which is a 2's exponent till 64 which defines the bandwidth rate for TWI I2C initialization.
 ///     * `a boolean` - Which denotes the TWPS bit 1 settings.
 ///     * `a boolean` - Which denotes the TWPS bit 2 settings.
-pub fn prescaler() -> (u8, bool, bool) {
-    if (crate::config::CPU_FREQUENCY_HZ / TWI_FREQUENCY - 16) / (2 * 1) >= 10
-        && (crate::config::CPU_FREQUENCY_HZ / TWI_FREQUENCY - 16) / (2 * 1) <= 0xFF
+pub fn prescaler() -> Result<(u8, bool, bool), Error> {
+    if (crate::config::CPU_FREQUENCY_HZ / TWI_FREQUENCY).saturating_sub(16) / (2 * 1) >= 10
+        && (crate::config::CPU_FREQUENCY_HZ / TWI_FREQUENCY).saturating_sub(16) / (2 * 1) <= 0xFF
     {
-        return (1, false, false);
-    } else if (crate::config::CPU_FREQUENCY_HZ / TWI_FREQUENCY - 16) / (2 * 4) >= 10
-        && (crate::config::CPU_FREQUENCY_HZ / TWI_FREQUENCY - 16) / (2 * 4) <= 0xFF
+        return Ok((1, false, false));
+    } else if (crate::config::CPU_FREQUENCY_HZ / TWI_FREQUENCY).saturating_sub(16) / (2 * 4) >= 10
+        && (crate::config::CPU_FREQUENCY_HZ / TWI_FREQUENCY).saturating_sub(16) / (2 * 4) <= 0xFF
     {
-        return (4, true, false);
-    } else if (crate::config::CPU_FREQUENCY_HZ / TWI_FREQUENCY - 16) / (2 * 16) >= 10
-        && (crate::config::CPU_FREQUENCY_HZ / TWI_FREQUENCY - 16) / (2 * 16) <= 0xFF
+        return Ok((4, true, false));
+    } else if (crate::config::CPU_FREQUENCY_HZ / TWI_FREQUENCY).saturating_sub(16) / (2 * 16) >= 10
+        && (crate::config::CPU_FREQUENCY_HZ / TWI_FREQUENCY).saturating_sub(16) / (2 * 16) <= 0xFF
     {
-        return (16, false, true);
-    } else if (crate::config::CPU_FREQUENCY_HZ / TWI_FREQUENCY - 16) / (2 * 64) >= 10
-        && (crate::config::CPU_FREQUENCY_HZ / TWI_FREQUENCY - 16) / (2 * 64) <= 0xFF
+        return Ok((16, false, true));
+    } else if (crate::config::CPU_FREQUENCY_HZ / TWI_FREQUENCY).saturating_sub(16) / (2 * 64) >= 10
+        && (crate::config::CPU_FREQUENCY_HZ / TWI_FREQUENCY).saturating_sub(16) / (2 * 64) <= 0xFF
     {
-        return (64, true, true);
+        return Ok((64, true, true));
     } else {
-        panic!("TWI_FREQUENCY too low!");
+        return Err(Error::InvalidParam);
     }
 }
 
-// TWCR register's bits definitions
-const TWPS1: u8 = 6;
-const TWPS0: u8 = 7;
+// TWSR register's bits definitions
+const TWPS1: u8 = 1;
+const TWPS0: u8 = 0;
 
 // TWSR status codes
 // Master
@@ -94,39 +97,39 @@ const REP_START: u8 = 0x10;
 
 // Master Transmitter
 const MT_SLA_ACK: u8 = 0x18;
-const _MT_SLA_NACK: u8 = 0x20;
+const MT_SLA_NACK: u8 = 0x20;
 const MT_DATA_ACK: u8 = 0x28;
-const _MT_DATA_NACK: u8 = 0x30;
-const _MT_ARB_LOST: u8 = 0x38;
+const MT_DATA_NACK: u8 = 0x30;
+const ARB_LOST: u8 = 0x38;
 
 // Master Receiver
 const _MR_ARB_LOST: u8 = 0x38;
 const MR_SLA_ACK: u8 = 0x40;
-const _MR_SLA_NACK: u8 = 0x48;
+const MR_SLA_NACK: u8 = 0x48;
 const MR_DATA_ACK: u8 = 0x50;
 const MR_DATA_NACK: u8 = 0x58;
 
 // Slave Transmitter
-const _ST_SLA_ACK: u8 = 0xA8;
-const _ST_ARB_LOST_SLA_ACK: u8 = 0xB0;
-const _ST_DATA_ACK: u8 = 0xB8;
-const _ST_DATA_NACK: u8 = 0xC0;
-const _ST_LAST_DATA: u8 = 0xC8;
+const ST_SLA_ACK: u8 = 0xA8;
+const ST_ARB_LOST_SLA_ACK: u8 = 0xB0;
+const ST_DATA_ACK: u8 = 0xB8;
+const ST_DATA_NACK: u8 = 0xC0;
+const ST_LAST_DATA: u8 = 0xC8;
 
 // Slave Receiver
-const _SR_SLA_ACK: u8 = 0x60;
-const _SR_ARB_LOST_SLA_ACK: u8 = 0x68;
-const _SR_GCALL_ACK: u8 = 0x70;
-const _SR_ARB_LOST_GCALL_ACK: u8 = 0x78;
-const _SR_DATA_ACK: u8 = 0x80;
+const SR_SLA_ACK: u8 = 0x60;
+const SR_ARB_LOST_SLA_ACK: u8 = 0x68;
+const SR_GCALL_ACK: u8 = 0x70;
+const SR_ARB_LOST_GCALL_ACK: u8 = 0x78;
+const SR_DATA_ACK: u8 = 0x80;
 const _SR_DATA_NACK: u8 = 0x88;
-const _SR_GCALL_DATA_ACK: u8 = 0x90;
+const SR_GCALL_DATA_ACK: u8 = 0x90;
 const _SR_GCALL_DATA_NACK: u8 = 0x98;
-const _SR_STOP: u8 = 0xA0;
+const SR_STOP: u8 = 0xA0;
 
 // Miscellaneous
 const _NO_INFO: u8 = 0xF8;
-const _BUS_ERROR: u8 = 0x00;
+const BUS_ERROR: u8 = 0x00;
 
 // Defines and constants
 const _TWCR_CMD_MASK: u8 = 0x0F;
@@ -135,7 +138,10 @@ const TWSR_STATUS_MASK: u8 = 0xF8;
 // return values
 const _I2C_OK: u8 = 0x00;
 const _I2C_ERROR_NODEV: u8 = 0x01;
-const I2C_TIMEOUT: u32 = 100;
+const I2C_TIMEOUT: u32 = 10000;
+
+/// Size of the buffers used in the slave mode.
+pub const I2C_BUFFER_SIZE: usize = 32;
 
 /// Sets DDRC to write direction.
 pub fn write_sda() {
@@ -155,6 +161,21 @@ pub fn read_sda() {
     }
 }
 
+/// Gives the value of TWBR and the prescaler bits of TWSR for the given SCL frequency.
+fn bit_rate(frequency: u32) -> Result<(u8, u8), Error> {
+    let mut twps = 0;
+    while twps < 4 {
+        // SCL frequency = CPU frequency / (16 + 2 * TWBR * 4^TWPS)
+        let twbr =
+            (crate::config::CPU_FREQUENCY_HZ / frequency).saturating_sub(16) / (2 << (2 * twps));
+        if twbr <= 0xFF {
+            return Ok((twbr as u8, twps));
+        }
+        twps += 1;
+    }
+    Err(Error::InvalidParam)
+}
+
 impl Twi {
     /// Creates a pointer to TWI structure objects.
     /// # Returns
@@ -163,72 +184,100 @@ impl Twi {
         unsafe { &mut *(0xB8 as *mut Self) }
     }
 
-    /// Waits for the TWI bus to be ready.
+    /// Waits for the current operation of the TWI bus to complete.
+    /// Times out if TWINT is not set after `I2C_TIMEOUT` checks.
+    /// # Arguments
+    /// * `status` - a u8, the status code expected in TWSR after the operation.
     /// # Returns
-    /// * `a boolean` - Which is true if the TWI is ready, false otherwise.
-    pub fn wait_to_complete(&mut self, start: u8) -> bool {
+    /// * `a Result<(), Error>` - Which is an error if the operation timed out or ended with another status.
+    pub fn wait_to_complete(&mut self, status: u8) -> Result<(), Error> {
         let mut i: u32 = 0;
         //Waiting for TWINT flag set.
-        //This indicates that start condition has been transmitted.
-        while !self.twcr.read().get_bit(TWINT) || i <= I2C_TIMEOUT {
+        //This indicates that the current operation is complete.
+        while !self.twcr.read().get_bit(TWINT) {
+            if i >= I2C_TIMEOUT {
+                return Err(Error::Timeout);
+            }
             unsafe {
                 llvm_asm!("nop");
             }
             i += 1;
         }
-        // if TWSR_STATUS_MASK is different from start, error.
-        if self.twsr.read() & TWSR_STATUS_MASK != start || i >= I2C_TIMEOUT {
-            return false;
-        } else {
-            return true;
+        self.check_status(status)
+    }
+
+    // Compares the status in TWSR with the one expected after the operation.
+    fn check_status(&mut self, status: u8) -> Result<(), Error> {
+        // if TWSR_STATUS_MASK is different from status, error.
+        match self.twsr.read() & TWSR_STATUS_MASK {
+            s if s == status => Ok(()),
+            MT_SLA_NACK | MT_DATA_NACK | MR_SLA_NACK => Err(Error::Nack),
+            ARB_LOST => Err(Error::ArbitrationLost),
+            _ => Err(Error::Bus),
         }
     }
 
-    /// Initiates the TWI Bus.
+    /// Initiates the TWI Bus with an SCL frequency of 100 kHz.
     pub fn init(&mut self) {
+        unsafe {
+            Power::new().enable_clocks(Peripherals::TWI);
+        }
+        // 100 kHz can be reached from any system clock.
+        let _ = self.set_frequency(TWI_FREQUENCY);
+        self.twcr.write(1 << TWEN);
+    }
+
+    /// Disables the TWI and gates its clock to save power, `init` enables it again.
+    pub fn shutdown(&mut self) {
+        self.twcr.write(0);
+        unsafe {
+            PENDING = None;
+        }
+        unsafe {
+            Power::new().disable_clocks(Peripherals::TWI);
+        }
+    }
+
+    /// Sets the SCL frequency used in the master modes.
+    /// # Arguments
+    /// * `frequency` - a u32, the SCL frequency in Hz, normally 100 kHz or 400 kHz.
+    /// # Returns
+    /// * `a Result<(), Error>` - Which is an error if the frequency is too low for the system clock.
+    pub fn set_frequency(&mut self, frequency: u32) -> Result<(), Error> {
+        let (twbr, twps) = bit_rate(frequency)?;
         self.twsr.update(|sr| {
-            sr.set_bit(TWPS0, prescaler().1);
-            sr.set_bit(TWPS1, prescaler().2);
+            sr.set_bit(TWPS0, twps.get_bit(0));
+            sr.set_bit(TWPS1, twps.get_bit(1));
         });
-        self.twcr.update(|cr| {
-            cr.set_bit(TWEN, true);
-        })
+        self.twbr.write(twbr);
+        Ok(())
     }
 
     /// Sends a Start Signal for TWI.
     /// # Returns
-    /// * `a boolean` - Which is true if process is successful, false otherwise.
-    pub fn start(&mut self) -> bool {
+    /// * `a Result<(), Error>` - Which is an error if the start condition could not be sent.
+    pub fn start(&mut self) -> Result<(), Error> {
         write_sda();
-        self.twcr.update(|x| {
-            // TWCR: Enable TWI module
-            x.set_bit(TWSTA, true);
-            x.set_bit(TWINT, true);
-            x.set_bit(TWEN, true);
-        });
+        // TWCR: Enable TWI module and send the start condition.
+        self.twcr.write(1 << TWINT | 1 << TWSTA | 1 << TWEN);
         return self.wait_to_complete(START);
     }
 
     /// Stops the TWI Bus.
     pub fn stop(&mut self) {
-        self.twcr.update(|x| {
-            // TWCR: Disable TWI module
-            x.set_bit(TWSTO, true);
-            x.set_bit(TWINT, true);
-            x.set_bit(TWEN, true);
-        });
+        // TWCR: Send the stop condition and release the bus.
+        self.twcr.write(1 << TWINT | 1 << TWSTO | 1 << TWEN);
+        unsafe {
+            PENDING = None;
+        }
     }
 
     /// Sends the Repeated Start Signal.
     /// # Returns
-    /// * `a boolean` - Which is true if process is successful, false otherwise.
-    pub fn rep_start(&mut self) -> bool {
-        self.twcr.update(|x| {
-            // TWCR: Enable TWI module
-            x.set_bit(TWSTA, true);
-            x.set_bit(TWINT, true);
-            x.set_bit(TWEN, true);
-        });
+    /// * `a Result<(), Error>` - Which is an error if the start condition could not be sent.
+    pub fn rep_start(&mut self) -> Result<(), Error> {
+        // TWCR: Send the start condition again without releasing the bus.
+        self.twcr.write(1 << TWINT | 1 << TWSTA | 1 << TWEN);
         return self.wait_to_complete(REP_START);
     }
 
@@ -236,14 +285,11 @@ impl Twi {
     /// # Arguments
     /// * `address` - It is passed into the function and  is a seven bit integer used for location of implementation.
     /// # Returns
-    /// * `a boolean` - Which is true if the checking process is sucessful otherwise false.
-    pub fn address_write(&mut self, address: u8) -> bool {
+    /// * `a Result<(), Error>` - Which is an error if the slave did not acknowledge its address.
+    pub fn address_write(&mut self, address: u8) -> Result<(), Error> {
         self.twdr.write(address << 1);
-        self.twcr.update(|x| {
-            // TWCR: Enables TWI to pass address
-            x.set_bit(TWINT, true);
-            x.set_bit(TWEN, true);
-        });
+        // TWCR: Enables TWI to pass address
+        self.twcr.write(1 << TWINT | 1 << TWEN);
         return self.wait_to_complete(MT_SLA_ACK);
     }
 
@@ -251,61 +297,51 @@ impl Twi {
     /// # Arguments
     /// * `address` - It is passed into the function and  is a seven bit integer used for location of implementation.
     /// # Returns
-    /// * `a boolean` - Which is true if the checking process is sucessful otherwise false.
-    pub fn address_read(&mut self, address: u8) -> bool {
+    /// * `a Result<(), Error>` - Which is an error if the slave did not acknowledge its address.
+    pub fn address_read(&mut self, address: u8) -> Result<(), Error> {
         self.twdr.write(address << 1 | 0x01);
-        self.twcr.update(|x| {
-            x.set_bit(TWINT, true);
-            x.set_bit(TWEN, true);
-        });
+        self.twcr.write(1 << TWINT | 1 << TWEN);
         return self.wait_to_complete(MR_SLA_ACK);
     }
 
-    /// Appends the value in TWCR to the given vector.
+    /// Reads a byte, acknowledges it and appends it to the given vector.
     /// # Arguments
     /// * `data` - a sliced vector consisting of u8, which will be filled with the data read.
     /// # Returns
-    /// * `a boolean` - Which is true if process is completed otherwise false.
-    pub fn read_ack(&mut self, data: &mut FixedSliceVec<u8>) -> bool {
-        self.twcr.update(|x| {
-            x.set_bit(TWINT, true);
-            x.set_bit(TWEA, true);
-            x.set_bit(TWEN, true);
-        });
+    /// * `a Result<(), Error>` - Which is an error if the byte could not be read.
+    pub fn read_ack(&mut self, data: &mut FixedSliceVec<u8>) -> Result<(), Error> {
+        self.twcr.write(1 << TWINT | 1 << TWEA | 1 << TWEN);
+        self.wait_to_complete(MR_DATA_ACK)?;
         data.push(self.twdr.read());
-        return self.wait_to_complete(MR_DATA_ACK);
+        return Ok(());
     }
 
-    /// Appends the value in TWCR to the given vector.
+    /// Reads consecutive bytes with acknowledgement and appends them to the given vector.
     /// # Arguments
     /// * `data` - a sliced vector consisting of u8, which is filled with the data read.
     /// * `length` - a usize integer, which is the theoretically set value of length of the sliced vector `data`.
     /// # Returns
-    /// * `a usize integer` - Which gives the critical length of the data bus above which no data exists.
+    /// * `a usize integer` - Which gives the number of bytes read.
     pub fn read_ack_burst(&mut self, data: &mut FixedSliceVec<u8>, length: usize) -> usize {
         let mut x: usize = 0;
         while x < length {
-            if !self.read_ack(data) {
+            if self.read_ack(data).is_err() {
                 break;
             }
             x += 1;
         }
-        return x + 1;
+        return x;
     }
 
     /// Writes one byte of data to the Slave.
     /// # Arguments
     /// * `data` - a u8, the integer which is to be written.
     /// # Returns
-    /// * `a boolean` - Whcih is true if process is successful otherwise false.
-    pub fn write(&mut self, data: u8) -> bool {
-        delay_ms(1);
+    /// * `a Result<(), Error>` - Which is an error if the slave did not acknowledge the byte.
+    pub fn write(&mut self, data: u8) -> Result<(), Error> {
         self.twdr.write(data);
-        self.twcr.update(|x| {
-            // TWCR: Enables TWI module to pass data to slave.
-            x.set_bit(TWINT, true);
-            x.set_bit(TWEN, true);
-        });
+        // TWCR: Enables TWI module to pass data to slave.
+        self.twcr.write(1 << TWINT | 1 << TWEN);
         return self.wait_to_complete(MT_DATA_ACK);
     }
 
@@ -313,48 +349,47 @@ impl Twi {
     /// # Arguments
     /// * `data` - a sliced vector consisting of u8, which is filled with the data read.
     /// # Returns
-    /// * `a usize integer` - Which gives the critical length of the data bus above which no data could be written.
+    /// * `a usize integer` - Which gives the number of bytes written.
     pub fn write_burst(&mut self, data: &FixedSliceVec<u8>) -> usize {
         let mut x: usize = 0;
         while x < data.len() {
-            if !self.write(data[x]) {
+            if self.write(data[x]).is_err() {
                 break;
             }
             x += 1;
         }
-        return x + 1;
+        return x;
     }
 
-    /// Reads the nack value data in TWCR to the given vector.
+    /// Reads a byte without acknowledging it and appends it to the given vector.
+    /// This is used for the last byte read from the slave.
     /// # Arguments
     /// * `data` - a sliced vector consisting of u8, which will be filled with the data read.
     /// # Returns
-    /// * `a boolean` - Which is true if process is completed otherwise false.
-    pub fn read_nack(&mut self, data: &mut FixedSliceVec<u8>) -> bool {
-        self.twcr.update(|x| {
-            x.set_bit(TWINT, true);
-            x.set_bit(TWEN, true);
-        });
+    /// * `a Result<(), Error>` - Which is an error if the byte could not be read.
+    pub fn read_nack(&mut self, data: &mut FixedSliceVec<u8>) -> Result<(), Error> {
+        self.twcr.write(1 << TWINT | 1 << TWEN);
+        self.wait_to_complete(MR_DATA_NACK)?;
         data.push(self.twdr.read());
-        return self.wait_to_complete(MR_DATA_NACK);
+        return Ok(());
     }
 
-    /// Reads the nack value data in TWCR to the given vector.
+    /// Reads consecutive bytes without acknowledgement and appends them to the given vector.
     /// # Arguments
     /// * `data` - a sliced vector consisting of u8, which is filled with the data read.
     /// * `length` - a usize integer, which is the theoretically set value of length of the sliced vector `data`.
     /// # Returns
-    /// * `a usize integer` - Which gives the critical length of the data bus above which no data exists.
+    /// * `a usize integer` - Which gives the number of bytes read.
     pub fn read_nack_burst(&mut self, data: &mut FixedSliceVec<u8>, length: usize) -> usize {
         let mut x: usize = 0;
 
         while x < length {
-            if !self.read_nack(data) {
+            if self.read_nack(data).is_err() {
                 break;
             }
             x += 1;
         }
-        return x + 1;
+        return x;
     }
 
     /// Reads consecutive Data bytes from slave.
@@ -364,56 +399,477 @@ impl Twi {
     /// * `length` - a usize integer, showing the number of bytes to read.
     /// * `data` - a sliced vector consisting of u8, where the data will be stored after reading.
     /// # Returns
-    /// * `a boolean` - Which is true if process is completed otherwise false and aborts the process if any of the steps, i.e
-    /// start, reading address, reading ACK or reading NACK fails.
+    /// * `a Result<(), Error>` - Which is the error of the first step which failed, i.e
+    /// start, reading address, reading ACK or reading NACK, after which the process is aborted.
     pub fn read_from_slave(
         &mut self,
         address: u8,
         length: usize,
         data: &mut FixedSliceVec<u8>,
-    ) -> bool {
+    ) -> Result<(), Error> {
         delay_ms(1);
         read_sda();
 
-        if !self.start() {
-            return false;
-        }
-        if !self.address_read(address) {
-            self.stop();
-            return false;
-        }
-        if length > 1 && self.read_ack_burst(data, length - 1) != length - 1 {
-            self.stop();
-            return false;
-        }
-        if length > 0 && self.read_nack(data) {
-            self.stop();
-            return false;
+        self.start()?;
+        let mut result = self.address_read(address);
+        let mut count = 0;
+        // Every byte is acknowledged but the last one.
+        while result.is_ok() && count < length {
+            result = if count + 1 < length {
+                self.read_ack(data)
+            } else {
+                self.read_nack(data)
+            };
+            count += 1;
         }
 
         self.stop();
 
-        return true;
+        return result;
     }
 
     /// Writes consecutive Data bytes to slave.
     /// Also sends a stop signal if either of the steps fail or writing is successful.
     /// # Returns
-    /// * `a boolean` - Which is true if process is completed and aborts if any of the steps, i.e start, setting address or writing fails.
-    pub fn write_to_slave(&mut self, address: u8, data: &FixedSliceVec<u8>) -> bool {
+    /// * `a Result<(), Error>` - Which is the error of the first step which failed, i.e start,
+    /// setting address or writing, after which the process is aborted.
+    pub fn write_to_slave(&mut self, address: u8, data: &FixedSliceVec<u8>) -> Result<(), Error> {
         delay_ms(1);
-        if !self.start() {
-            return false;
+        self.start()?;
+        let mut result = self.address_write(address);
+        for byte in data.iter() {
+            if result.is_err() {
+                break;
+            }
+            result = self.write(*byte);
+        }
+        self.stop();
+        return result;
+    }
+}
+
+impl Twi {
+    /// Reads one byte from the slave once its address has been sent for reading.
+    /// # Arguments
+    /// * `ack` - a boolean, which is true if more bytes are to be read after this one.
+    /// # Returns
+    /// * `a Result<u8, Error>` - which is the byte read, or the error of the transfer.
+    pub fn read_byte(&mut self, ack: bool) -> Result<u8, Error> {
+        if ack {
+            self.twcr.write(1 << TWINT | 1 << TWEA | 1 << TWEN);
+        } else {
+            self.twcr.write(1 << TWINT | 1 << TWEN);
+        }
+        let status = if ack { MR_DATA_ACK } else { MR_DATA_NACK };
+        self.wait_to_complete(status)?;
+        Ok(self.twdr.read())
+    }
+}
+
+// Status expected at the end of the operation started by one of the `try_` functions,
+// while that operation is in progress.
+static mut PENDING: Option<u8> = None;
+
+impl Twi {
+    /// Sends a start condition without waiting, for polling loops and `nb::block!`.
+    /// Like the other `try_` functions, it starts the operation on the first call and
+    /// gives `WouldBlock` until the operation is complete, so it must be called again
+    /// with the same arguments until it gives something else.
+    /// # Returns
+    /// * `a nb::Result<(), Error>` - which is an error if the start condition could not be sent.
+    pub fn try_start(&mut self) -> nb::Result<(), Error> {
+        write_sda();
+        self.poll(None, 1 << TWINT | 1 << TWSTA | 1 << TWEN, START)
+    }
+
+    /// Sends a repeated start condition without waiting.
+    /// # Returns
+    /// * `a nb::Result<(), Error>` - which is an error if the start condition could not be sent.
+    pub fn try_rep_start(&mut self) -> nb::Result<(), Error> {
+        self.poll(None, 1 << TWINT | 1 << TWSTA | 1 << TWEN, REP_START)
+    }
+
+    /// Sends the address of the slave for writing without waiting.
+    /// # Arguments
+    /// * `address` - a u8, the seven bit address of the slave.
+    /// # Returns
+    /// * `a nb::Result<(), Error>` - which is an error if the slave did not acknowledge its address.
+    pub fn try_address_write(&mut self, address: u8) -> nb::Result<(), Error> {
+        self.poll(Some(address << 1), 1 << TWINT | 1 << TWEN, MT_SLA_ACK)
+    }
+
+    /// Sends the address of the slave for reading without waiting.
+    /// # Arguments
+    /// * `address` - a u8, the seven bit address of the slave.
+    /// # Returns
+    /// * `a nb::Result<(), Error>` - which is an error if the slave did not acknowledge its address.
+    pub fn try_address_read(&mut self, address: u8) -> nb::Result<(), Error> {
+        self.poll(
+            Some(address << 1 | 0x01),
+            1 << TWINT | 1 << TWEN,
+            MR_SLA_ACK,
+        )
+    }
+
+    /// Sends a byte to the slave without waiting.
+    /// # Arguments
+    /// * `data` - a u8, the byte to be sent.
+    /// # Returns
+    /// * `a nb::Result<(), Error>` - which is an error if the slave did not acknowledge the byte.
+    pub fn try_write(&mut self, data: u8) -> nb::Result<(), Error> {
+        self.poll(Some(data), 1 << TWINT | 1 << TWEN, MT_DATA_ACK)
+    }
+
+    /// Reads a byte from the slave without waiting.
+    /// # Arguments
+    /// * `ack` - a boolean, which is true if more bytes are to be read after this one.
+    /// # Returns
+    /// * `a nb::Result<u8, Error>` - which is the byte read, or the error of the transfer.
+    pub fn try_read(&mut self, ack: bool) -> nb::Result<u8, Error> {
+        if ack {
+            self.poll(None, 1 << TWINT | 1 << TWEA | 1 << TWEN, MR_DATA_ACK)?;
+        } else {
+            self.poll(None, 1 << TWINT | 1 << TWEN, MR_DATA_NACK)?;
+        }
+        Ok(self.twdr.read())
+    }
+
+    // Starts an operation by writing TWDR and TWCR if none is in progress, and
+    // otherwise checks whether the one in progress has ended with the expected status.
+    fn poll(&mut self, data: Option<u8>, twcr: u8, status: u8) -> nb::Result<(), Error> {
+        match unsafe { PENDING } {
+            None => {
+                if let Some(data) = data {
+                    self.twdr.write(data);
+                }
+                self.twcr.write(twcr);
+                unsafe {
+                    PENDING = Some(status);
+                }
+                Err(nb::Error::WouldBlock)
+            }
+            Some(_) if !self.twcr.read().get_bit(TWINT) => Err(nb::Error::WouldBlock),
+            Some(expected) => {
+                unsafe {
+                    PENDING = None;
+                }
+                self.check_status(expected).map_err(nb::Error::Other)
+            }
+        }
+    }
+}
+
+/// Events reported by `poll_slave` when the TWI works as a slave without interrupts.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SlaveEvent {
+    /// A master has written bytes to this device, they can be read with `slave_read`.
+    Received { length: usize, general_call: bool },
+    /// A master has read the bytes given earlier with `slave_write`.
+    Requested,
+}
+
+// Data exchanged in the slave mode, shared with the interrupt service routine.
+static mut SLAVE_RX: [u8; I2C_BUFFER_SIZE] = [0; I2C_BUFFER_SIZE];
+static mut SLAVE_RX_LENGTH: usize = 0;
+static mut SLAVE_TX: [u8; I2C_BUFFER_SIZE] = [0; I2C_BUFFER_SIZE];
+static mut SLAVE_TX_LENGTH: usize = 0;
+static mut SLAVE_TX_INDEX: usize = 0;
+static mut GENERAL_CALL: bool = false;
+static mut RECEIVE_HANDLER: Option<fn(&[u8], bool)> = None;
+static mut REQUEST_HANDLER: Option<fn(&mut [u8]) -> usize> = None;
+
+impl Twi {
+    /// Sets the address to which the TWI responds in the slave modes.
+    /// # Arguments
+    /// * `address` - a u8, the seven bit address of this device.
+    /// * `general_call` - a boolean, which is true if the general call address 0x00 is also to be recognised.
+    pub fn set_slave_address(&mut self, address: u8, general_call: bool) {
+        self.twar.write(address << 1 | general_call as u8);
+    }
+
+    /// Sets the bits of the slave address which are ignored, so that
+    /// a range of addresses can be answered.
+    /// # Arguments
+    /// * `mask` - a u8, the seven bit mask where a one ignores the corresponding address bit.
+    pub fn set_address_mask(&mut self, mask: u8) {
+        self.twamr.write(mask << 1);
+    }
+
+    /// Sets the function called with the bytes written by a master, and whether
+    /// they were sent to the general call address.
+    /// # Arguments
+    /// * `handler` - a function, which is called from the interrupt service routine.
+    pub fn on_receive(&mut self, handler: fn(&[u8], bool)) {
+        unsafe {
+            RECEIVE_HANDLER = Some(handler);
+        }
+    }
+
+    /// Sets the function called when a master reads from this device.
+    /// It fills the buffer with the reply and returns the number of bytes filled.
+    /// # Arguments
+    /// * `handler` - a function, which is called from the interrupt service routine.
+    pub fn on_request(&mut self, handler: fn(&mut [u8]) -> usize) {
+        unsafe {
+            REQUEST_HANDLER = Some(handler);
+        }
+    }
+
+    /// Starts listening to the bus as a slave, the address must be set first.
+    /// # Arguments
+    /// * `interrupt` - a boolean, which is true if the events are handled by the interrupt
+    ///   service routine and the handlers, and false if `poll_slave` is to be called instead.
+    pub fn enable_slave(&mut self, interrupt: bool) {
+        unsafe {
+            SLAVE_RX_LENGTH = 0;
+            SLAVE_TX_INDEX = 0;
+        }
+        self.twcr
+            .write(1 << TWEA | 1 << TWEN | (interrupt as u8) << TWIE);
+    }
+
+    /// Stops responding to the slave address.
+    pub fn disable_slave(&mut self) {
+        self.twcr.write(1 << TWEN);
+    }
+
+    /// Handles the current state of the bus in the slave mode, if any.
+    /// This has to be called often when the slave mode is used without interrupts,
+    /// since the bus is held low until the state is handled.
+    /// # Returns
+    /// * `a Option<SlaveEvent>` - the event which finished with this state, if any.
+    pub fn poll_slave(&mut self) -> Option<SlaveEvent> {
+        if self.twcr.read().get_bit(TWINT) {
+            unsafe { self.slave_step() }
+        } else {
+            None
         }
-        if !self.address_write(address) {
-            return false;
+    }
+
+    /// Copies the bytes last written by a master into the buffer.
+    /// # Arguments
+    /// * `buffer` - a mutable slice of u8, which will be filled with the data received.
+    /// # Returns
+    /// * `a usize` - the number of bytes copied.
+    pub fn slave_read(&mut self, buffer: &mut [u8]) -> usize {
+        without_interrupts(|| unsafe {
+            let length = SLAVE_RX_LENGTH.min(buffer.len());
+            buffer[..length].copy_from_slice(&SLAVE_RX[..length]);
+            length
+        })
+    }
+
+    /// Sets the bytes sent when a master reads from this device without a request handler.
+    /// # Arguments
+    /// * `data` - a slice of u8, the bytes to be sent.
+    /// # Returns
+    /// * `a usize` - the number of bytes stored, at most `I2C_BUFFER_SIZE`.
+    pub fn slave_write(&mut self, data: &[u8]) -> usize {
+        without_interrupts(|| unsafe {
+            let length = data.len().min(I2C_BUFFER_SIZE);
+            SLAVE_TX[..length].copy_from_slice(&data[..length]);
+            SLAVE_TX_LENGTH = length;
+            SLAVE_TX_INDEX = 0;
+            length
+        })
+    }
+
+    /// Responds to the status in TWSR once TWINT is set in the slave mode.
+    unsafe fn slave_step(&mut self) -> Option<SlaveEvent> {
+        let mut event = None;
+        // TWINT is cleared to continue, TWEA keeps the slave addressable.
+        let mut twcr = 1 << TWINT | 1 << TWEA | 1 << TWEN | (self.twcr.read() & 1 << TWIE);
+        match self.twsr.read() & TWSR_STATUS_MASK {
+            SR_SLA_ACK | SR_ARB_LOST_SLA_ACK => {
+                SLAVE_RX_LENGTH = 0;
+                GENERAL_CALL = false;
+            }
+            SR_GCALL_ACK | SR_ARB_LOST_GCALL_ACK => {
+                SLAVE_RX_LENGTH = 0;
+                GENERAL_CALL = true;
+            }
+            SR_DATA_ACK | SR_GCALL_DATA_ACK => {
+                if SLAVE_RX_LENGTH < I2C_BUFFER_SIZE {
+                    SLAVE_RX[SLAVE_RX_LENGTH] = self.twdr.read();
+                    SLAVE_RX_LENGTH += 1;
+                }
+                // The next byte is not acknowledged once the buffer is full.
+                if SLAVE_RX_LENGTH >= I2C_BUFFER_SIZE {
+                    twcr &= !(1 << TWEA);
+                }
+            }
+            SR_STOP => {
+                let data = &SLAVE_RX[..SLAVE_RX_LENGTH];
+                match RECEIVE_HANDLER {
+                    Some(handler) => handler(data, GENERAL_CALL),
+                    None => {
+                        event = Some(SlaveEvent::Received {
+                            length: SLAVE_RX_LENGTH,
+                            general_call: GENERAL_CALL,
+                        })
+                    }
+                }
+            }
+            ST_SLA_ACK | ST_ARB_LOST_SLA_ACK => {
+                if let Some(handler) = REQUEST_HANDLER {
+                    SLAVE_TX_LENGTH = handler(&mut SLAVE_TX).min(I2C_BUFFER_SIZE);
+                }
+                SLAVE_TX_INDEX = 0;
+                let byte = self.next_slave_byte();
+                self.twdr.write(byte);
+            }
+            ST_DATA_ACK => {
+                let byte = self.next_slave_byte();
+                self.twdr.write(byte);
+            }
+            ST_DATA_NACK | ST_LAST_DATA => {
+                if REQUEST_HANDLER.is_none() {
+                    event = Some(SlaveEvent::Requested);
+                }
+            }
+            BUS_ERROR => {
+                // Releases the bus after an illegal start or stop condition.
+                twcr |= 1 << TWSTO;
+            }
+            _ => {}
+        }
+        self.twcr.write(twcr);
+        event
+    }
+
+    /// Gives the next byte to be sent in the slave transmitter mode,
+    /// or 0xFF once all the bytes have been sent.
+    unsafe fn next_slave_byte(&mut self) -> u8 {
+        if SLAVE_TX_INDEX < SLAVE_TX_LENGTH {
+            SLAVE_TX_INDEX += 1;
+            SLAVE_TX[SLAVE_TX_INDEX - 1]
+        } else {
+            0xFF
         }
+    }
+}
 
-        if self.write_burst(data) != data.len() {
+#[cfg(feature = "embedded")]
+impl Twi {
+    /// Sends the stop condition if a step of a transfer failed.
+    fn stop_on_error(&mut self, result: Result<(), Error>) -> Result<(), Error> {
+        if result.is_err() {
             self.stop();
-            return false;
         }
+        result
+    }
+
+    /// Sends the bytes to the slave once its address has been sent for writing.
+    fn send_bytes(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        for byte in bytes {
+            let result = Twi::write(self, *byte);
+            self.stop_on_error(result)?;
+        }
+        Ok(())
+    }
+
+    /// Fills the buffer with bytes from the slave, not acknowledging the last one.
+    fn receive_bytes(&mut self, buffer: &mut [u8]) -> Result<(), Error> {
+        let length = buffer.len();
+        for (i, byte) in buffer.iter_mut().enumerate() {
+            match self.read_byte(i + 1 < length) {
+                Ok(data) => *byte = data,
+                Err(error) => {
+                    self.stop();
+                    return Err(error);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "embedded")]
+impl embedded_hal::blocking::i2c::Write for Twi {
+    type Error = Error;
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.start()?;
+        let result = self.address_write(address);
+        self.stop_on_error(result)?;
+        self.send_bytes(bytes)?;
         self.stop();
-        return true;
+        Ok(())
+    }
+}
+
+#[cfg(feature = "embedded")]
+impl embedded_hal::blocking::i2c::Read for Twi {
+    type Error = Error;
+
+    fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        self.start()?;
+        let result = self.address_read(address);
+        self.stop_on_error(result)?;
+        self.receive_bytes(buffer)?;
+        self.stop();
+        Ok(())
+    }
+}
+
+#[cfg(feature = "embedded")]
+impl embedded_hal::blocking::i2c::WriteRead for Twi {
+    type Error = Error;
+
+    fn write_read(
+        &mut self,
+        address: u8,
+        bytes: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        self.start()?;
+        let result = self.address_write(address);
+        self.stop_on_error(result)?;
+        self.send_bytes(bytes)?;
+        let result = self.rep_start();
+        self.stop_on_error(result)?;
+        let result = self.address_read(address);
+        self.stop_on_error(result)?;
+        self.receive_bytes(buffer)?;
+        self.stop();
+        Ok(())
+    }
+}
+
+/// TWI, handles the slave mode when it is used with interrupts.
+#[cfg(target_arch = "avr")]
+#[no_mangle]
+pub unsafe extern "avr-interrupt" fn __vector_39() {
+    Twi::new().slave_step();
+}
+
+/// Lets the drivers written against `TwiOps` use the TWI.
+impl crate::common::TwiOps for Twi {
+    fn start(&mut self) -> Result<(), Error> {
+        Twi::start(self)
+    }
+
+    fn rep_start(&mut self) -> Result<(), Error> {
+        Twi::rep_start(self)
+    }
+
+    fn stop(&mut self) {
+        Twi::stop(self);
+    }
+
+    fn address_write(&mut self, address: u8) -> Result<(), Error> {
+        Twi::address_write(self, address)
+    }
+
+    fn address_read(&mut self, address: u8) -> Result<(), Error> {
+        Twi::address_read(self, address)
+    }
+
+    fn write(&mut self, data: u8) -> Result<(), Error> {
+        Twi::write(self, data)
+    }
+
+    fn read_byte(&mut self, ack: bool) -> Result<u8, Error> {
+        Twi::read_byte(self, ack)
     }
 }
@@ -0,0 +1,305 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Infrared remote control codes of the NEC, Sony (SIRC) and Philips RC5 protocols
+//! on ATMEGA2560P.
+//! A TSOP38238 or similar receiver gives low while it sees the 38 kHz carrier (a mark)
+//! and high otherwise (a space). The pin change interrupt of its pin records the length
+//! of each mark and space with `micros`, so `time::init` must be called, and a frame is
+//! decoded by `protocol::infrared` once the line has been idle for a while.
+//! Codes are sent by an IR LED on pin 9 (OC2B), with the carrier made by Timer2 in phase
+//! correct PWM mode and the marks and spaces timed by `delay_us`, so `tone` and PWM on
+//! pins 9 and 10 can not be used at the same time.
+//! See `<https://www.sbprojects.net/knowledge/ir/index.php>`.
+
+// Source codes required.
+use crate::atmega2560p::hal::analog::{Timer8, TimerNo8};
+use crate::atmega2560p::hal::interrupts::{
+    attach_pin_change, digital_pin_to_pcint, without_interrupts,
+};
+use crate::atmega2560p::hal::pin::make_pin;
+use crate::atmega2560p::hal::power::{Peripherals, Power};
+use crate::config::CPU_FREQUENCY_HZ;
+use crate::delay::delay_us;
+use crate::error::Error;
+use crate::protocol::infrared::{
+    decode, NEC_BIT_MARK, NEC_LEADER_MARK, NEC_LEADER_SPACE, NEC_ONE_SPACE, NEC_REPEAT_SPACE,
+    NEC_ZERO_SPACE, RC5_HALF_BIT, SONY_LEADER_MARK, SONY_ONE_MARK, SONY_SPACE, SONY_ZERO_MARK,
+};
+pub use crate::protocol::infrared::{IrCode, IrProtocol};
+use crate::time::micros;
+
+use core::ptr::read_volatile;
+
+/// Digital pin of the IR LED, the output OC2B of Timer2.
+pub const IR_SEND_PIN: u32 = 9;
+
+/// Maximum number of marks and spaces in a frame, enough for NEC.
+const MAX_DURATIONS: usize = 80;
+
+/// Microseconds of idle line which end a frame.
+const GAP_US: u32 = 8000;
+
+// Bits of TCCR2A and TCCR2B.
+const COM2B1: u8 = 1 << 5;
+const WGM20: u8 = 1 << 0;
+const WGM22: u8 = 1 << 3;
+const CS20: u8 = 1 << 0;
+
+/// Marks and spaces of the frame being received, shared with the interrupt routine.
+struct Frame {
+    pin: *const u8,
+    mask: u8,
+    durations: [u16; MAX_DURATIONS],
+    count: usize,
+    last_edge: u32,
+}
+
+static mut FRAME: Option<Frame> = None;
+
+/// Used to receive codes from an IR receiver.
+/// # Elements
+/// * `last` - a `Option<IrCode>`, the last NEC code, given again for repeat frames.
+pub struct IrReceiver {
+    last: Option<IrCode>,
+}
+
+impl IrReceiver {
+    /// Starts receiving on a pin connected to the output of an IR receiver.
+    /// Global interrupts must be enabled.
+    /// # Arguments
+    /// * `pin` - a u32, the digital pin, which must have a pin change interrupt.
+    /// The other pins of its pin change group can not have another handler.
+    /// # Returns
+    /// * `a Result<IrReceiver, Error>` - the receiver, or `Error::InvalidParam` if the pin
+    ///   has no pin change interrupt.
+    pub fn new(pin: u32) -> Result<IrReceiver, Error> {
+        let (group, bit) = digital_pin_to_pcint(pin).ok_or(Error::InvalidParam)?;
+        let mut input = make_pin(pin);
+        input.set_input();
+        input.high();
+        without_interrupts(|| unsafe {
+            FRAME = Some(Frame {
+                pin: &(*input.port).pin as *const u8,
+                mask: 1u8 << input.pin,
+                durations: [0; MAX_DURATIONS],
+                count: 0,
+                last_edge: micros(),
+            });
+        });
+        attach_pin_change(group, 1 << bit, edge)?;
+        Ok(IrReceiver { last: None })
+    }
+
+    /// Decodes the last frame once the line has been idle long enough.
+    /// This should be called at least every 30 ms, before the next frame starts.
+    /// # Returns
+    /// * `a Option<IrCode>` - the code, None if no frame has ended or it was not understood.
+    pub fn decode(&mut self) -> Option<IrCode> {
+        let mut durations = [0u16; MAX_DURATIONS];
+        let count = without_interrupts(|| unsafe {
+            let frame = FRAME.as_mut()?;
+            if frame.count == 0
+                || micros().wrapping_sub(frame.last_edge) < GAP_US
+                || read_volatile(frame.pin) & frame.mask == 0
+            {
+                return None;
+            }
+            let count = frame.count;
+            durations[..count].copy_from_slice(&frame.durations[..count]);
+            frame.count = 0;
+            Some(count)
+        })?;
+        let durations = &durations[..count];
+
+        let code = decode(durations, self.last)?;
+        if code.protocol == IrProtocol::Nec {
+            self.last = Some(code);
+        }
+        Some(code)
+    }
+}
+
+/// Records the length of the mark or space which has just ended, called by the
+/// pin change interrupt.
+fn edge() {
+    unsafe {
+        let frame = match FRAME.as_mut() {
+            Some(frame) => frame,
+            None => return,
+        };
+        let now = micros();
+        let duration = now.wrapping_sub(frame.last_edge);
+        frame.last_edge = now;
+        let mark_started = read_volatile(frame.pin) & frame.mask == 0;
+        if mark_started && duration >= GAP_US {
+            // A new frame, the old one is dropped if it has not been decoded.
+            frame.count = 0;
+            return;
+        }
+        if frame.count == 0 && mark_started {
+            // The end of a mark whose start was missed.
+            return;
+        }
+        if frame.count < MAX_DURATIONS {
+            frame.durations[frame.count] = duration.min(0xFFFF) as u16;
+            frame.count += 1;
+        }
+    }
+}
+
+/// Used to send codes with an IR LED on `IR_SEND_PIN`.
+pub struct IrSender {}
+
+impl IrSender {
+    /// Sets the pin and Timer2 to make the 38 kHz carrier, which is off until a mark is sent.
+    /// # Returns
+    /// * `a IrSender object` - which will be used to send the codes.
+    pub fn new() -> IrSender {
+        let mut pin = make_pin(IR_SEND_PIN);
+        pin.set_output();
+        pin.low();
+        unsafe {
+            Power::new().enable_clocks(Peripherals::TIMER2);
+
+            // Phase correct PWM with OCR2A as TOP and no prescaler, the output
+            // toggling twice per period, with a duty cycle of a third.
+            let timer = Timer8::new(TimerNo8::Timer2);
+            let top = (CPU_FREQUENCY_HZ / 2 / 38_000) as u8;
+            timer.tccra.write(WGM20);
+            timer.tccrb.write(WGM22 | CS20);
+            timer.ocra.write(top);
+            timer.ocrb.write(top / 3);
+            timer.tcnt.write(0);
+        }
+        IrSender {}
+    }
+
+    /// Sends the carrier for some time.
+    fn mark(&mut self, us: u16) {
+        let timer = Timer8::new(TimerNo8::Timer2);
+        timer.tccra.write(WGM20 | COM2B1);
+        delay_us(us as u32);
+    }
+
+    /// Stops the carrier for some time, the pin is left low.
+    fn space(&mut self, us: u16) {
+        let timer = Timer8::new(TimerNo8::Timer2);
+        timer.tccra.write(WGM20);
+        delay_us(us as u32);
+    }
+
+    /// Sends marks and spaces, starting with a mark.
+    /// # Arguments
+    /// * `durations` - a slice of u16, the lengths in microseconds.
+    pub fn send_raw(&mut self, durations: &[u16]) {
+        for (i, &duration) in durations.iter().enumerate() {
+            if i % 2 == 0 {
+                self.mark(duration);
+            } else {
+                self.space(duration);
+            }
+        }
+        self.space(0);
+    }
+
+    /// Sends a NEC code.
+    /// # Arguments
+    /// * `address` - a u16, the address, sent with its inverse if it fits in 8 bits.
+    /// * `command` - a u8, the command.
+    pub fn send_nec(&mut self, address: u16, command: u8) {
+        let address = if address <= 0xFF {
+            [address as u8, !(address as u8)]
+        } else {
+            address.to_le_bytes()
+        };
+        let data = u32::from_le_bytes([address[0], address[1], command, !command]);
+        self.mark(NEC_LEADER_MARK);
+        self.space(NEC_LEADER_SPACE);
+        for i in 0..32 {
+            self.mark(NEC_BIT_MARK);
+            if data & (1 << i) != 0 {
+                self.space(NEC_ONE_SPACE);
+            } else {
+                self.space(NEC_ZERO_SPACE);
+            }
+        }
+        self.mark(NEC_BIT_MARK);
+        self.space(0);
+    }
+
+    /// Sends the NEC repeat frame, every 108 ms while a key is held.
+    pub fn send_nec_repeat(&mut self) {
+        self.mark(NEC_LEADER_MARK);
+        self.space(NEC_REPEAT_SPACE);
+        self.mark(NEC_BIT_MARK);
+        self.space(0);
+    }
+
+    /// Sends a Sony code three times, as the devices expect.
+    /// # Arguments
+    /// * `address` - a u16, the address.
+    /// * `command` - a u8, the command of 7 bits.
+    /// * `bits` - a u8, the length of the code, 12, 15 or 20.
+    pub fn send_sony(&mut self, address: u16, command: u8, bits: u8) {
+        let data = (address as u32) << 7 | (command & 0x7F) as u32;
+        for _ in 0..3 {
+            let start = micros();
+            self.mark(SONY_LEADER_MARK);
+            for i in 0..bits {
+                self.space(SONY_SPACE);
+                if data & (1 << i) != 0 {
+                    self.mark(SONY_ONE_MARK);
+                } else {
+                    self.mark(SONY_ZERO_MARK);
+                }
+            }
+            // The frames start every 45 ms.
+            let elapsed = micros().wrapping_sub(start);
+            self.space(45_000u32.saturating_sub(elapsed) as u16);
+        }
+    }
+
+    /// Sends a RC5 code.
+    /// # Arguments
+    /// * `address` - a u8, the address of 5 bits.
+    /// * `command` - a u8, the command of 7 bits, above 63 for RC5X.
+    /// * `toggle` - a bool, which should change at each new press of a key.
+    pub fn send_rc5(&mut self, address: u8, command: u8, toggle: bool) {
+        let field = command & 0x40 == 0;
+        let data: u16 = 1 << 13
+            | (field as u16) << 12
+            | (toggle as u16) << 11
+            | ((address & 0x1F) as u16) << 6
+            | (command & 0x3F) as u16;
+        for bit in (0..14).rev() {
+            if data & (1 << bit) != 0 {
+                self.space(RC5_HALF_BIT);
+                self.mark(RC5_HALF_BIT);
+            } else {
+                self.mark(RC5_HALF_BIT);
+                self.space(RC5_HALF_BIT);
+            }
+        }
+        self.space(0);
+    }
+}
+
+impl Default for IrSender {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,49 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! MIDI over one of the USARTs of ATMEGA2560P, at 31250 baud with 8 data bits, no parity and one stop bit.
+//! The messages, the parser and the port are in `protocol::midi`, this module creates the
+//! port on a `BufferedSerial`, so global interrupts are enabled.
+//! See the MIDI 1.0 Detailed Specification, and the section 22 of ATMEGA2560P datasheet.
+
+// Source codes required.
+use crate::atmega2560p::com::usart_initialize::UsartNum;
+use crate::atmega2560p::com::usart_interrupt::BufferedSerial;
+pub use crate::protocol::midi::{Midi, MidiMessage, MidiParser, MIDI_BAUD};
+
+impl Midi {
+    /// Creates the MIDI port, sending every status byte.
+    /// # Arguments
+    /// * `num` - a `UsartNum` object, the USART to be used.
+    /// # Returns
+    /// * `a Midi object` - which will be used to send and receive messages.
+    /// # Safety
+    /// The USART must not be used by anything else, as for `BufferedSerial::new`.
+    pub unsafe fn new(num: UsartNum) -> Midi {
+        Midi::with_serial(BufferedSerial::new(num))
+    }
+
+    /// Initializes the USART at the baud rate of MIDI.
+    pub fn begin(&mut self) {
+        self.serial.begin(MIDI_BAUD);
+        self.last_status = 0;
+    }
+
+    /// Waits until all the messages have been sent.
+    pub fn flush(&mut self) {
+        self.serial.flush();
+    }
+}
@@ -0,0 +1,287 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Bit-banged 1-Wire bus on any digital pin of ATMEGA2560P.
+//! The bus is only ever pulled low by the master or a device, and an external
+//! pull-up resistor (4.7 kOhm) brings it high, so the pin is switched between a low
+//! output and an input. Every bit is a time slot started by the master, and the slots
+//! are timed with `delay_us` while interrupts are disabled.
+//! Every device has a unique 64 bit ROM code, which is found with `search`.
+//! See `<https://www.maximintegrated.com/en/design/technical-documents/app-notes/1/126.html>`
+//! and `<https://www.maximintegrated.com/en/design/technical-documents/app-notes/1/187.html>`.
+
+// Source codes required.
+use crate::atmega2560p::hal::interrupts::without_interrupts;
+use crate::atmega2560p::hal::pin::make_pin;
+use crate::atmega2560p::hal::port::Pin;
+use crate::delay::delay_us;
+
+/// ROM commands common to all 1-Wire devices.
+pub const ONEWIRE_READ_ROM: u8 = 0x33;
+pub const ONEWIRE_MATCH_ROM: u8 = 0x55;
+pub const ONEWIRE_SKIP_ROM: u8 = 0xCC;
+pub const ONEWIRE_SEARCH_ROM: u8 = 0xF0;
+
+/// Gives the Dallas/Maxim CRC8 of a block of bytes, see `util::crc::crc8_maxim`.
+pub use crate::util::crc::crc8_maxim as crc8;
+
+/// Structure to control a 1-Wire bus on one pin.
+/// # Elements
+/// * `pin` - a `Pin` object, the pin connected to the bus.
+/// * `rom` - an array of 8 u8, the ROM code found by the last search.
+/// * `last_discrepancy` - a u8, the bit where the last search took the 0 path.
+/// * `last_device` - a boolean, true when the last search found the last device.
+pub struct OneWire {
+    pin: Pin,
+    rom: [u8; 8],
+    last_discrepancy: u8,
+    last_device: bool,
+}
+
+impl OneWire {
+    /// Creates a bus on a digital pin, which is released.
+    /// # Arguments
+    /// * `pin` - a u32, the digital pin number.
+    /// # Returns
+    /// * `a OneWire object` - which will be used to talk to the devices.
+    pub fn new(pin: u32) -> OneWire {
+        let mut bus = OneWire {
+            pin: make_pin(pin),
+            rom: [0; 8],
+            last_discrepancy: 0,
+            last_device: false,
+        };
+        bus.release();
+        bus.pin.low();
+        bus
+    }
+
+    /// Pulls the bus low, PORTxn is always left cleared.
+    fn drive_low(&mut self) {
+        self.pin.set_output();
+    }
+
+    /// Lets the pull-up resistor bring the bus high.
+    fn release(&mut self) {
+        self.pin.set_input();
+    }
+
+    /// Sends a reset pulse and checks whether any device answered with a presence pulse.
+    /// # Returns
+    /// * `a boolean` - which is true if a device is present on the bus.
+    pub fn reset(&mut self) -> bool {
+        // The bus must be high before the reset, otherwise it is shorted.
+        let mut retries = 125;
+        while !self.pin.is_high() {
+            if retries == 0 {
+                return false;
+            }
+            retries -= 1;
+            delay_us(2);
+        }
+
+        self.drive_low();
+        delay_us(480);
+        let present = without_interrupts(|| {
+            self.release();
+            delay_us(70);
+            !self.pin.is_high()
+        });
+        delay_us(410);
+        present
+    }
+
+    /// Writes one bit in a time slot of about 70 us.
+    /// # Arguments
+    /// * `bit` - a boolean, the bit to be written.
+    pub fn write_bit(&mut self, bit: bool) {
+        without_interrupts(|| {
+            self.drive_low();
+            if bit {
+                delay_us(6);
+                self.release();
+                delay_us(64);
+            } else {
+                delay_us(60);
+                self.release();
+                delay_us(10);
+            }
+        });
+    }
+
+    /// Reads one bit in a time slot of about 70 us.
+    /// # Returns
+    /// * `a boolean` - the bit sent by the device.
+    pub fn read_bit(&mut self) -> bool {
+        let bit = without_interrupts(|| {
+            self.drive_low();
+            delay_us(6);
+            self.release();
+            delay_us(9);
+            self.pin.is_high()
+        });
+        delay_us(55);
+        bit
+    }
+
+    /// Writes a byte, least significant bit first.
+    /// # Arguments
+    /// * `byte` - a u8, the byte to be written.
+    pub fn write_byte(&mut self, byte: u8) {
+        for i in 0..8 {
+            self.write_bit(byte & (1 << i) != 0);
+        }
+    }
+
+    /// Reads a byte, least significant bit first.
+    /// # Returns
+    /// * `a u8` - the byte read.
+    pub fn read_byte(&mut self) -> u8 {
+        let mut byte = 0;
+        for i in 0..8 {
+            if self.read_bit() {
+                byte |= 1 << i;
+            }
+        }
+        byte
+    }
+
+    /// Writes a block of bytes.
+    /// # Arguments
+    /// * `data` - a slice of u8, the bytes to be written.
+    pub fn write_bytes(&mut self, data: &[u8]) {
+        for byte in data.iter() {
+            self.write_byte(*byte);
+        }
+    }
+
+    /// Reads a block of bytes.
+    /// # Arguments
+    /// * `buffer` - a mutable slice of u8, which will be filled with the bytes read.
+    pub fn read_bytes(&mut self, buffer: &mut [u8]) {
+        for byte in buffer.iter_mut() {
+            *byte = self.read_byte();
+        }
+    }
+
+    /// Addresses one device, after which a function command can be sent to it.
+    /// # Arguments
+    /// * `rom` - a reference to an array of 8 u8, the ROM code of the device.
+    /// # Returns
+    /// * `a boolean` - which is true if a device was present.
+    pub fn select(&mut self, rom: &[u8; 8]) -> bool {
+        if !self.reset() {
+            return false;
+        }
+        self.write_byte(ONEWIRE_MATCH_ROM);
+        self.write_bytes(rom);
+        true
+    }
+
+    /// Addresses all devices at once, or the only device of the bus.
+    /// # Returns
+    /// * `a boolean` - which is true if a device was present.
+    pub fn skip(&mut self) -> bool {
+        if !self.reset() {
+            return false;
+        }
+        self.write_byte(ONEWIRE_SKIP_ROM);
+        true
+    }
+
+    /// Reads the ROM code when there is only one device on the bus.
+    /// # Returns
+    /// * `a Option<[u8; 8]>` - the ROM code, or None if no device answered or the CRC is wrong.
+    pub fn read_rom(&mut self) -> Option<[u8; 8]> {
+        if !self.reset() {
+            return None;
+        }
+        self.write_byte(ONEWIRE_READ_ROM);
+        let mut rom = [0; 8];
+        self.read_bytes(&mut rom);
+        if crc8(&rom) == 0 {
+            Some(rom)
+        } else {
+            None
+        }
+    }
+
+    /// Restarts the search so that the next `search` gives the first device.
+    pub fn reset_search(&mut self) {
+        self.rom = [0; 8];
+        self.last_discrepancy = 0;
+        self.last_device = false;
+    }
+
+    /// Finds the next device on the bus with the ROM search of the 1-Wire protocol.
+    /// In every step all devices send a bit of their ROM code and its complement,
+    /// and the master picks the branch to follow when the devices disagree.
+    /// # Returns
+    /// * `a Option<[u8; 8]>` - the ROM code of the next device, or None after the last one.
+    pub fn search(&mut self) -> Option<[u8; 8]> {
+        if self.last_device || !self.reset() {
+            self.reset_search();
+            return None;
+        }
+        self.write_byte(ONEWIRE_SEARCH_ROM);
+
+        let mut last_zero = 0;
+        for bit in 0..64 {
+            let id_bit_number = bit + 1;
+            let byte = (bit / 8) as usize;
+            let mask = 1 << (bit % 8);
+
+            let id_bit = self.read_bit();
+            let cmp_id_bit = self.read_bit();
+            let direction = if id_bit && cmp_id_bit {
+                // No device is taking part in the search.
+                self.reset_search();
+                return None;
+            } else if id_bit != cmp_id_bit {
+                // All devices have the same bit here.
+                id_bit
+            } else {
+                // Devices with both values, the 0 branch is taken first.
+                let direction = if id_bit_number < self.last_discrepancy {
+                    self.rom[byte] & mask != 0
+                } else {
+                    id_bit_number == self.last_discrepancy
+                };
+                if !direction {
+                    last_zero = id_bit_number;
+                }
+                direction
+            };
+
+            if direction {
+                self.rom[byte] |= mask;
+            } else {
+                self.rom[byte] &= !mask;
+            }
+            self.write_bit(direction);
+        }
+
+        if crc8(&self.rom) != 0 {
+            self.reset_search();
+            return None;
+        }
+        self.last_discrepancy = last_zero;
+        if last_zero == 0 {
+            self.last_device = true;
+        }
+        Some(self.rom)
+    }
+}
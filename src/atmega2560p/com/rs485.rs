@@ -0,0 +1,49 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Half-duplex RS-485 over one of the USARTs of ATMEGA2560P and a transceiver such as the MAX485.
+//! The transport is `protocol::rs485::Rs485`, this module creates it on a `BufferedSerial`,
+//! whose flush waits for the Transmit Complete flag (TXCn) before the bus is released.
+//! The bytes are sent and received through `BufferedSerial`, so global interrupts are enabled.
+//! See the section 22 of ATMEGA2560P datasheet.
+
+// Source codes required.
+use crate::atmega2560p::com::usart_initialize::UsartNum;
+use crate::atmega2560p::com::usart_interrupt::BufferedSerial;
+use crate::atmega2560p::hal::pin::make_pin;
+pub use crate::protocol::rs485::Rs485;
+
+impl Rs485 {
+    /// Creates the transport, with the transceiver in receive mode.
+    /// # Arguments
+    /// * `num` - a `UsartNum` object, the USART to be used.
+    /// * `direction` - a u32, the digital pin connected to DE and RE.
+    /// # Returns
+    /// * `a Rs485 object` - which will be used to send and receive on the bus.
+    /// # Safety
+    /// The USART must not be used by anything else, as for `BufferedSerial::new`.
+    pub unsafe fn new(num: UsartNum, direction: u32) -> Rs485 {
+        Rs485::with_serial(BufferedSerial::new(num), make_pin(direction), 9600)
+    }
+
+    /// Initializes the USART with the given baud rate, 8 data bits, no parity and one stop bit.
+    /// # Arguments
+    /// * `baud` - a u32, the baud rate of the bus.
+    pub fn begin(&mut self, baud: u32) {
+        self.baud = baud;
+        self.serial.begin(baud as i64);
+    }
+}
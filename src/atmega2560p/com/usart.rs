@@ -59,7 +59,7 @@ impl UsartObject {
         self.disable();
         self.transmit_enable();
         self.recieve_enable();
-        self.initialize(MODE, BAUD, STOP, SIZE, PARITY);
+        let _ = self.initialize(MODE, BAUD, STOP, SIZE, PARITY);
     }
 
     /// Can be use to initialize with given baud rate and remaining settings will be set to default.
@@ -69,12 +69,12 @@ impl UsartObject {
         self.disable();
         self.transmit_enable();
         self.recieve_enable();
-        self.initialize(MODE, baud1, STOP, SIZE, PARITY);
+        let _ = self.initialize(MODE, baud1, STOP, SIZE, PARITY);
     }
 
     /// Can be used to stop the functioning of initialized USART.
     pub unsafe fn end(&mut self) {
-        self.transmit_disable();
+        let _ = self.transmit_disable();
         self.recieve_disable();
         self.reset();
         self.enable();
@@ -93,9 +93,9 @@ pub fn println_string(data: &'static str) {
         let mut u: UsartObject = UsartObject::new(NUM);
         u.disable();
         u.transmit_enable();
-        u.initialize(MODE, BAUD, STOP, SIZE, PARITY);
-        u.write_string(data);
-        u.transmit_disable();
+        let _ = u.initialize(MODE, BAUD, STOP, SIZE, PARITY);
+        let _ = u.write_string(data);
+        let _ = u.transmit_disable();
         u.reset();
         u.enable();
     }
@@ -113,9 +113,9 @@ pub fn println_integer(data: u32) {
         let mut u: UsartObject = UsartObject::new(NUM);
         u.disable();
         u.transmit_enable();
-        u.initialize(MODE, BAUD, STOP, SIZE, PARITY);
-        u.write_integer(data);
-        u.transmit_disable();
+        let _ = u.initialize(MODE, BAUD, STOP, SIZE, PARITY);
+        let _ = u.write_integer(data);
+        let _ = u.transmit_disable();
         u.reset();
         u.enable();
     }
@@ -134,9 +134,9 @@ pub fn println_float(data: f64, precision: u32) {
         let mut u: UsartObject = UsartObject::new(NUM);
         u.disable();
         u.transmit_enable();
-        u.initialize(MODE, BAUD, STOP, SIZE, PARITY);
-        u.write_float(data, precision);
-        u.transmit_disable();
+        let _ = u.initialize(MODE, BAUD, STOP, SIZE, PARITY);
+        let _ = u.write_float(data, precision);
+        let _ = u.transmit_disable();
         u.reset();
         u.enable();
     }
@@ -154,9 +154,9 @@ pub fn println_set_baud(data: &'static str, baud1: i64) {
         let mut u: UsartObject = UsartObject::new(NUM);
         u.disable();
         u.transmit_enable();
-        u.initialize(MODE, baud1, STOP, SIZE, PARITY);
-        u.write_string(data);
-        u.transmit_disable();
+        let _ = u.initialize(MODE, baud1, STOP, SIZE, PARITY);
+        let _ = u.write_string(data);
+        let _ = u.transmit_disable();
         u.reset();
         u.enable();
     }
@@ -181,9 +181,9 @@ pub fn println_set_frame(
         let mut u: UsartObject = UsartObject::new(NUM);
         u.disable();
         u.transmit_enable();
-        u.initialize(MODE, BAUD, stop1, size1, parity1);
-        u.write_string(data);
-        u.transmit_disable();
+        let _ = u.initialize(MODE, BAUD, stop1, size1, parity1);
+        let _ = u.write_string(data);
+        let _ = u.transmit_disable();
         u.reset();
         u.enable();
     }
@@ -214,9 +214,9 @@ pub fn println_detail(
         let mut u: UsartObject = UsartObject::new(num1);
         u.disable();
         u.transmit_enable();
-        u.initialize(mode1, baud1, stop1, size1, parity1);
-        u.write_string(data);
-        u.transmit_disable();
+        let _ = u.initialize(mode1, baud1, stop1, size1, parity1);
+        let _ = u.write_string(data);
+        let _ = u.transmit_disable();
         u.reset();
         u.enable();
     }
@@ -25,10 +25,13 @@
 use crate::atmega2560p::hal::interrupts;
 use crate::atmega2560p::hal::port;
 use crate::atmega2560p::hal::power;
+use crate::sync::{self, AtomicByte, AtomicFlag};
 
 // Crates which would be used in the implementation.
 // We will be using standard volatile and bit_field crates now for a better read and write.
-use crate::delay::delay_ms;
+use crate::atmega2560p::com::usart_transmit::TRANSMIT_TIMEOUT_US;
+use crate::delay::wait_until;
+use crate::error::Error;
 use bit_field::BitField;
 use core::ptr::write_volatile;
 use core::{f64, u32, u8};
@@ -40,9 +43,20 @@ const USART0_XCK: u8 = 2;
 const USART1_XCK: u8 = 5;
 const USART2_XCK: u8 = 2;
 const USART3_XCK: u8 = 2;
-// System Clock Crystal Oscillator Frequency in mHz.
-const F_OSC: f64 = 1.0000;
-const MULTIPLY: f64 = 1000000.00;
+
+// Number of calls of `disable` not yet matched by `enable`, and whether global
+// interrupts were enabled before the first of them.
+static DISABLE_DEPTH: AtomicByte = AtomicByte::new(0);
+static WERE_ENABLED: AtomicFlag = AtomicFlag::new(false);
+
+// Baud rate and mode set on each USART, to set them again when the system clock changes.
+static mut BAUD_RATES: [Option<(i64, UsartModes)>; 4] = [None; 4];
+const USARTS: [UsartNum; 4] = [
+    UsartNum::Usart0,
+    UsartNum::Usart1,
+    UsartNum::Usart2,
+    UsartNum::Usart3,
+];
 
 /// Selection of which USART is to be used.
 #[derive(Clone, Copy)]
@@ -170,18 +184,35 @@ impl UsartObject {
 
 impl UsartObject {
     /// Disable global interrupts for smooth non-interrupted functioning of USART.
+    /// The calls can be nested, each one is matched by a call of `enable`.
     pub fn disable(&self) {
+        let enabled = sync::interrupts_enabled();
         unsafe {
             // Disable global interrupts.
             interrupts::Interrupt::disable(&mut interrupts::Interrupt::new());
         }
+        if DISABLE_DEPTH.fetch_add(1) == 0 {
+            if enabled {
+                WERE_ENABLED.set();
+            } else {
+                WERE_ENABLED.clear();
+            }
+        }
     }
 
-    /// Re-enable global interrupts.
+    /// Re-enable global interrupts, if they were enabled before the first `disable`
+    /// which is not yet matched.
     pub fn enable(&self) {
-        unsafe {
-            // Enable global interrupts.
-            interrupts::Interrupt::enable(&mut interrupts::Interrupt::new());
+        let depth = DISABLE_DEPTH.load();
+        if depth == 0 {
+            return;
+        }
+        DISABLE_DEPTH.store(depth - 1);
+        if depth == 1 && WERE_ENABLED.is_set() {
+            unsafe {
+                // Enable global interrupts.
+                interrupts::Interrupt::enable(&mut interrupts::Interrupt::new());
+            }
         }
     }
 
@@ -337,6 +368,26 @@ impl UsartObject {
         }
     }
 
+    /// Disables the receiver and the transmitter and gates the clock of the USART to save
+    /// power, `initialize` enables it again.
+    pub fn shutdown(&mut self) {
+        unsafe {
+            (*self.usart).ucsrb.update(|ucsrb| {
+                ucsrb.set_bit(4, false);
+                ucsrb.set_bit(3, false);
+            });
+        }
+        let peripheral = match self.name {
+            UsartNum::Usart0 => power::Peripherals::USART0,
+            UsartNum::Usart1 => power::Peripherals::USART1,
+            UsartNum::Usart2 => power::Peripherals::USART2,
+            UsartNum::Usart3 => power::Peripherals::USART3,
+        };
+        unsafe {
+            power::Power::new().disable_clocks(peripheral);
+        }
+    }
+
     /// Sets the interrupt bits in UCSRB so that ongoing data transfers can be tracked.
     unsafe fn _check(&mut self) {
         (*self.usart).ucsrb.update(|srb| {
@@ -345,16 +396,13 @@ impl UsartObject {
         });
     }
 
-    /// Checks for any currently undergoing recieval or transmission in the USART.
+    /// Checks that no byte is waiting in the transmit buffer. The receive buffer is not
+    /// waited for, as it is cleared when the USART is set up again.
     /// # Returns
     /// * `a boolean` - Which is false if USART is busy otherwise true.
     unsafe fn check_ongoing(&self) -> bool {
         let ucsra = (*self.usart).ucsra.read();
-        if ucsra.get_bit(6) == false && ucsra.get_bit(7) == false {
-            true
-        } else {
-            false
-        }
+        ucsra.get_bit(5)
     }
 
     /// Set the appropriate bits for flushing out transmission and recieval.
@@ -380,16 +428,20 @@ impl UsartObject {
     /// * `baud` - a i64, containing the baud rate frame to be set.
     /// * `mode` - a `UsartModes` object,
     fn set_clock(&mut self, baud: i64, mode: UsartModes) {
+        let f_osc = crate::clock::system_clock_hz() as f64;
+        unsafe {
+            BAUD_RATES[self.name as usize] = Some((baud, mode));
+        }
         let ubrr: u32;
         match mode {
             UsartModes::Normasync => {
-                ubrr = (((F_OSC * MULTIPLY) / (16.00 * baud as f64)) - 1.00) as u32;
+                ubrr = ((f_osc / (16.00 * baud as f64)) - 1.00) as u32;
             }
             UsartModes::Douasync => {
-                ubrr = (((F_OSC * MULTIPLY) / (8.00 * baud as f64)) - 1.00) as u32;
+                ubrr = ((f_osc / (8.00 * baud as f64)) - 1.00) as u32;
             }
             UsartModes::Mastersync => {
-                ubrr = (((F_OSC * MULTIPLY) / (2.00 * baud as f64)) - 1.00) as u32;
+                ubrr = ((f_osc / (2.00 * baud as f64)) - 1.00) as u32;
             }
             _ => unreachable!(),
         }
@@ -520,6 +572,8 @@ impl UsartObject {
     /// * `size` - a `UsartDatSize` object, the size of set of bits to transmit.
     /// * `parity` - a `UsartParity` object, which gives the Parity bit mode for USART.
     /// * `stop` - a `UsartStop` object, which will be used to set the stop bits of data frame.
+    /// # Returns
+    /// * `a Result<(), Error>` - which is `Error::Timeout` if the byte being sent was never taken.
     pub unsafe fn initialize(
         &mut self,
         mode: UsartModes,
@@ -527,18 +581,9 @@ impl UsartObject {
         stop: UsartStop,
         size: UsartDataSize,
         parity: UsartParity,
-    ) {
-        // Check that recieve and transmit buffers are completely cleared
-        // and no transmission or recieve of data is already in process.
-        let mut i: i32 = 100;
-        while self.check_ongoing() == false {
-            if i != 0 {
-                delay_ms(1000);
-                i = i - 1;
-            } else {
-                unreachable!()
-            }
-        }
+    ) -> Result<(), Error> {
+        // Check that no transmission is already in process.
+        wait_until(TRANSMIT_TIMEOUT_US, || self.check_ongoing())?;
 
         let num: UsartNum = (*self.usart).name();
 
@@ -557,5 +602,18 @@ impl UsartObject {
 
         //  Set the frame format according to input.
         self.set_frame(stop, size, parity);
+        Ok(())
+    }
+}
+
+/// Sets again the baud rate of every USART initialized, from the present frequency of
+/// the system clock. It is called by `crate::clock` after the clock has changed, and
+/// the bytes being transmitted or received meanwhile are lost.
+pub fn clock_changed() {
+    for num in USARTS.iter() {
+        if let Some((baud, mode)) = unsafe { BAUD_RATES[*num as usize] } {
+            let mut usart = unsafe { UsartObject::new(*num) };
+            usart.set_clock(baud, mode);
+        }
     }
 }
@@ -0,0 +1,330 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Devansh Kumar Jha, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! This file contains the interrupt driven mode of the USARTs of ATMEGA2560P.
+//! Bytes received are stored in a ring buffer by the RX Complete interrupt, so
+//! nothing is lost while the application is busy, and bytes to be sent are queued
+//! in another ring buffer which is emptied by the Data Register Empty interrupt.
+//! Each buffer has room for `SERIAL_BUFFER_SIZE - 1` bytes. The interrupt service routines
+//! and the program use opposite ends of the buffers, so they need no critical section.
+//! See the section 22 of ATMEGA2560P datasheet.
+
+// Other source code files to be used.
+use crate::atmega2560p::com::usart_initialize::{Usart, UsartNum, UsartObject};
+use crate::atmega2560p::hal::interrupts::{without_interrupts, Interrupt};
+use crate::collections::RingBuffer;
+use crate::print::SerialWrite;
+
+// Crates which would be used in the implementation.
+use bit_field::BitField;
+
+/// Size of the receive and transmit buffers, which hold one byte less.
+pub const SERIAL_BUFFER_SIZE: usize = 64;
+
+// Bits of UCSRnA.
+const TXC: u8 = 6;
+const UDRE: u8 = 5;
+
+// Bits of UCSRnB.
+const RXCIE: u8 = 7;
+const UDRIE: u8 = 5;
+
+// Buffers of the four USARTs, shared with the interrupt service routines.
+static RX_BUFFERS: [RingBuffer<u8, SERIAL_BUFFER_SIZE>; 4] = [
+    RingBuffer::new(),
+    RingBuffer::new(),
+    RingBuffer::new(),
+    RingBuffer::new(),
+];
+static TX_BUFFERS: [RingBuffer<u8, SERIAL_BUFFER_SIZE>; 4] = [
+    RingBuffer::new(),
+    RingBuffer::new(),
+    RingBuffer::new(),
+    RingBuffer::new(),
+];
+// Set once a byte has been written, so that `flush` knows TXCn will be set.
+static mut WRITTEN: [bool; 4] = [false; 4];
+
+/// Gives the index of the USART in the buffer arrays.
+fn index(num: UsartNum) -> usize {
+    match num {
+        UsartNum::Usart0 => 0,
+        UsartNum::Usart1 => 1,
+        UsartNum::Usart2 => 2,
+        UsartNum::Usart3 => 3,
+    }
+}
+
+/// Interrupt driven serial port using ring buffers for receiving and transmitting,
+/// with an interface similar to the `Serial` of Arduino.
+/// # Example
+/// ```ignore
+/// let mut serial = unsafe { BufferedSerial::new(UsartNum::Usart0) };
+/// serial.begin(9600);
+/// serial.write(b"Hello");
+/// while let Some(byte) = serial.read() {
+///     serial.write_byte(byte);
+/// }
+/// ```
+pub struct BufferedSerial {
+    num: UsartNum,
+}
+
+impl BufferedSerial {
+    /// Creates the buffered serial port for a USART, emptying its buffers.
+    /// # Arguments
+    /// * `num` - a `UsartNum` object, the USART to be used.
+    /// # Returns
+    /// * `a BufferedSerial object` - which will be used to control the USART.
+    /// # Safety
+    /// Only one `BufferedSerial` should exist for each USART.
+    pub unsafe fn new(num: UsartNum) -> BufferedSerial {
+        let i = index(num);
+        without_interrupts(|| {
+            RX_BUFFERS[i].clear();
+            TX_BUFFERS[i].clear();
+            WRITTEN[i] = false;
+        });
+        BufferedSerial { num }
+    }
+
+    /// Initializes the USART with the given baud rate and default frame settings
+    /// (8 data bits, no parity, one stop bit), enables the RX Complete interrupt
+    /// and then enables global interrupts.
+    /// # Arguments
+    /// * `baud` - a i64, the baud rate of USART the user wants to set.
+    pub fn begin(&mut self, baud: i64) {
+        unsafe {
+            UsartObject::new(self.num).begin_set_baud(baud);
+            Usart::new(self.num).ucsrb.update(|ucsrb| {
+                ucsrb.set_bit(RXCIE, true);
+                ucsrb.set_bit(UDRIE, false);
+            });
+            Interrupt::new().enable();
+        }
+    }
+
+    /// Gives the number of bytes received and waiting to be read.
+    /// # Returns
+    /// * `a usize` - the number of bytes in the receive buffer.
+    pub fn available(&mut self) -> usize {
+        let i = index(self.num);
+        RX_BUFFERS[i].len()
+    }
+
+    /// Reads the oldest received byte.
+    /// # Returns
+    /// * `a Option<u8>` - which is None if no byte is available.
+    pub fn read(&mut self) -> Option<u8> {
+        let i = index(self.num);
+        RX_BUFFERS[i].pop()
+    }
+
+    /// Gives the oldest received byte without removing it from the buffer.
+    /// # Returns
+    /// * `a Option<u8>` - which is None if no byte is available.
+    pub fn peek(&mut self) -> Option<u8> {
+        let i = index(self.num);
+        RX_BUFFERS[i].peek()
+    }
+
+    /// Queues a byte for transmission.
+    /// This returns immediately unless the transmit buffer is full, in which
+    /// case it waits for a place to be freed.
+    /// # Arguments
+    /// * `byte` - a u8, the byte to be transmitted.
+    pub fn write_byte(&mut self, byte: u8) {
+        let i = index(self.num);
+        let usart = unsafe { Usart::new(self.num) };
+        loop {
+            if TX_BUFFERS[i].push(byte).is_ok() {
+                break;
+            }
+            // The buffer is full, send a byte from here if the interrupt cannot run.
+            let enabled = unsafe { Interrupt::new().is_enabled() };
+            if !enabled && usart.ucsra.read().get_bit(UDRE) {
+                unsafe { data_register_empty(self.num) };
+            }
+        }
+        without_interrupts(|| unsafe {
+            WRITTEN[i] = true;
+            usart.ucsrb.update(|ucsrb| {
+                ucsrb.set_bit(UDRIE, true);
+            });
+        });
+    }
+
+    /// Queues all the bytes of the slice for transmission.
+    /// # Arguments
+    /// * `data` - a slice of u8, the bytes to be transmitted.
+    pub fn write(&mut self, data: &[u8]) {
+        for byte in data {
+            self.write_byte(*byte);
+        }
+    }
+
+    /// Queues a string for transmission.
+    /// # Arguments
+    /// * `data` - a string slice, which is to be transmitted.
+    pub fn write_str(&mut self, data: &str) {
+        self.write(data.as_bytes());
+    }
+
+    /// Waits until all the queued bytes have been transmitted completely.
+    pub fn flush(&mut self) {
+        let i = index(self.num);
+        let usart = unsafe { Usart::new(self.num) };
+        if unsafe { !WRITTEN[i] } {
+            return;
+        }
+        while usart.ucsrb.read().get_bit(UDRIE) || !usart.ucsra.read().get_bit(TXC) {
+            let enabled = unsafe { Interrupt::new().is_enabled() };
+            if !enabled && usart.ucsra.read().get_bit(UDRE) {
+                unsafe { data_register_empty(self.num) };
+            }
+        }
+    }
+
+    /// Discards all the received bytes which have not been read.
+    pub fn clear(&mut self) {
+        let i = index(self.num);
+        RX_BUFFERS[i].clear();
+    }
+
+    /// Waits for the transmission to complete and then stops the USART.
+    pub fn end(&mut self) {
+        self.flush();
+        unsafe {
+            Usart::new(self.num).ucsrb.update(|ucsrb| {
+                ucsrb.set_bit(RXCIE, false);
+                ucsrb.set_bit(UDRIE, false);
+            });
+            let mut usart = UsartObject::new(self.num);
+            let _ = usart.transmit_disable();
+            usart.recieve_disable();
+        }
+        self.clear();
+    }
+}
+
+/// Stores the byte received by the USART in its receive buffer.
+/// The byte is dropped if the buffer is full.
+#[cfg(target_arch = "avr")]
+unsafe fn receive_complete(num: UsartNum) {
+    let data = Usart::new(num).udr.read();
+    RX_BUFFERS[index(num)].push(data).ok();
+}
+
+/// Sends the next queued byte, or disables the interrupt once the buffer is empty.
+unsafe fn data_register_empty(num: UsartNum) {
+    let usart = Usart::new(num);
+    match TX_BUFFERS[index(num)].pop() {
+        Some(data) => {
+            // Clear TXCn by writing a one to it, so that `flush` can wait for it.
+            usart.ucsra.update(|ucsra| {
+                ucsra.set_bit(TXC, true);
+            });
+            usart.udr.write(data);
+        }
+        None => usart.ucsrb.update(|ucsrb| {
+            ucsrb.set_bit(UDRIE, false);
+        }),
+    }
+}
+
+/// USART0 RX Complete.
+#[cfg(target_arch = "avr")]
+#[no_mangle]
+pub unsafe extern "avr-interrupt" fn __vector_25() {
+    receive_complete(UsartNum::Usart0);
+}
+
+/// USART0 Data Register Empty.
+#[cfg(target_arch = "avr")]
+#[no_mangle]
+pub unsafe extern "avr-interrupt" fn __vector_26() {
+    data_register_empty(UsartNum::Usart0);
+}
+
+/// USART1 RX Complete.
+#[cfg(target_arch = "avr")]
+#[no_mangle]
+pub unsafe extern "avr-interrupt" fn __vector_36() {
+    receive_complete(UsartNum::Usart1);
+}
+
+/// USART1 Data Register Empty.
+#[cfg(target_arch = "avr")]
+#[no_mangle]
+pub unsafe extern "avr-interrupt" fn __vector_37() {
+    data_register_empty(UsartNum::Usart1);
+}
+
+/// USART2 RX Complete.
+#[cfg(target_arch = "avr")]
+#[no_mangle]
+pub unsafe extern "avr-interrupt" fn __vector_51() {
+    receive_complete(UsartNum::Usart2);
+}
+
+/// USART2 Data Register Empty.
+#[cfg(target_arch = "avr")]
+#[no_mangle]
+pub unsafe extern "avr-interrupt" fn __vector_52() {
+    data_register_empty(UsartNum::Usart2);
+}
+
+/// USART3 RX Complete.
+#[cfg(target_arch = "avr")]
+#[no_mangle]
+pub unsafe extern "avr-interrupt" fn __vector_54() {
+    receive_complete(UsartNum::Usart3);
+}
+
+/// USART3 Data Register Empty.
+#[cfg(target_arch = "avr")]
+#[no_mangle]
+pub unsafe extern "avr-interrupt" fn __vector_55() {
+    data_register_empty(UsartNum::Usart3);
+}
+
+impl SerialWrite for BufferedSerial {
+    fn write_byte(&mut self, byte: u8) {
+        BufferedSerial::write_byte(self, byte);
+    }
+}
+
+impl crate::common::UsartOps for BufferedSerial {
+    fn write_byte(&mut self, byte: u8) {
+        BufferedSerial::write_byte(self, byte);
+    }
+
+    fn available(&mut self) -> bool {
+        BufferedSerial::available(self) > 0
+    }
+
+    fn read_byte(&mut self) -> Option<u8> {
+        self.read()
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        self.write(bytes);
+    }
+
+    fn flush(&mut self) {
+        BufferedSerial::flush(self);
+    }
+}
@@ -20,10 +20,15 @@
 
 // Other source code files to be used.
 use crate::atmega2560p::com::usart_initialize::UsartObject;
+use crate::delay::wait_until;
+use crate::error::Error;
+
+/// Microseconds waited for a frame before giving up, longer than a frame at the lowest
+/// baud rate.
+const RECEIVE_TIMEOUT_US: u32 = 100_000;
 
 // Crates which would be used in the implementation.
 // We will be using standard volatile and bit_field crates now for a better read and write.
-use crate::delay::delay_ms;
 use bit_field::BitField;
 use core::u32;
 
@@ -51,33 +56,26 @@ impl UsartObject {
     /// Either 5 to 8 bits and 9 bits of data can be recieved from this function.
     /// In case of 5 to 8 bits this function returns u8.
     /// In case of 9 bits it retuns u32 of which first 9 bits are data recieved and remaining bits are insignificant.
-    /// In case if an frame error or parity error occurs, this function returns Nothing.
     /// # Returns
-    /// * `a Option<u32>` - which is NULL in case of wrong settings and read data u32 if valid input.
-    pub fn recieve_data(&mut self) -> Option<u32> {
+    /// * `a Result<u32, Error>` - the data, `Error::Timeout` if no frame arrived or
+    ///   `Error::InvalidData` in case of a frame or parity error.
+    pub fn recieve_data(&mut self) -> Result<u32, Error> {
         let ucsrc = unsafe { (*self.usart).ucsrc.read() };
         let ucsrb = unsafe { (*self.usart).ucsrb.read() };
 
-        let mut i: i32 = 10;
-        while self.available() == false {
-            if i != 0 {
-                delay_ms(1000);
-                i = i - 1;
-            } else {
-                unreachable!()
-            }
-        }
+        wait_until(RECEIVE_TIMEOUT_US, || self.available())?;
 
         //  Case when there is 9 bits mode.
         if ucsrc.get_bits(1..3) == 0b11 && ucsrb.get_bit(2) == true {
+            let ucsrb = unsafe { (*self.usart).ucsrb.read() };
             let ucsra = unsafe { (*self.usart).ucsra.read() };
             let mut udr: u32 = unsafe { (*self.usart).udr.read() as u32 };
             if ucsra.get_bits(2..5) != 0b000 {
-                None
+                Err(Error::InvalidData)
             } else {
                 let rxb8: u32 = ucsrb.get_bits(1..2) as u32;
                 udr.set_bits(8..9, rxb8);
-                Some(udr)
+                Ok(udr)
             }
         }
         //  Case when there is a case of 5 to 8 bits.
@@ -85,9 +83,9 @@ impl UsartObject {
             let ucsra = unsafe { (*self.usart).ucsra.read() };
             let udr: u32 = unsafe { (*self.usart).udr.read() as u32 };
             if ucsra.get_bits(2..5) != 0b000 {
-                None
+                Err(Error::InvalidData)
             } else {
-                Some(udr)
+                Ok(udr)
             }
         }
     }
@@ -124,24 +122,20 @@ impl UsartObject {
     }
 
     /// Clears the unread data in the receive buffer by flushing it
-    pub unsafe fn flush_recieve(&mut self) {
-        let mut _udr = (*self.usart).udr.read();
-        let mut ucsra = (*self.usart).ucsra.read();
-        let mut i: i32 = 100;
-        while ucsra.get_bit(7) == true {
-            ucsra = (*self.usart).ucsra.read();
-            _udr = (*self.usart).udr.read();
-            if i != 0 {
-                delay_ms(1000);
-                i = i - 1;
+    pub unsafe fn flush_recieve(&mut self) -> Result<(), Error> {
+        // Reading UDR takes the frames out of the receive buffer.
+        wait_until(RECEIVE_TIMEOUT_US, || {
+            if self.available() {
+                let _ = (*self.usart).udr.read();
+                false
             } else {
-                unreachable!()
+                true
             }
-        }
-
+        })?;
         (*self.usart).ucsra.update(|ucsra| {
             ucsra.set_bit(7, false);
         });
+        Ok(())
     }
 
     ///  This is used to recieve data of one frame.
@@ -149,42 +143,123 @@ impl UsartObject {
     ///  Either 5 to 8 bits and 9 bits of data can be recieved from this function.
     ///  In case of 5 to 8 bits this function returns u8.
     ///  In case of 9 bits it retuns u32 of which first 9 bits are data recieved and remaining bits are insignificant.
-    ///  In case ,if an frame error or parity error occurs, this function returns nothing.
     /// # Returns
-    /// * `a Option<u32>` - which is NULL in case of wrong settings and read data u32 if valid input.
-    pub fn read(&mut self) -> Option<u32> {
+    /// * `a Result<u32, Error>` - the data, `Error::Timeout` if no frame arrived or
+    ///   `Error::InvalidData` in case of a frame or parity error.
+    pub fn read(&mut self) -> Result<u32, Error> {
         let ucsrc = unsafe { (*self.usart).ucsrc.read() };
         let ucsrb = unsafe { (*self.usart).ucsrb.read() };
 
-        let mut i: i32 = 10;
-        while self.available() == false {
-            if i != 0 {
-                delay_ms(1000);
-                i = i - 1;
-            } else {
-                unreachable!()
-            }
-        }
+        wait_until(RECEIVE_TIMEOUT_US, || self.available())?;
 
         if ucsrc.get_bits(1..3) == 0b11 && ucsrb.get_bit(2) == true {
             let ucsra = unsafe { (*self.usart).ucsra.read() };
             let ucsrb = unsafe { (*self.usart).ucsrb.read() };
             let mut udr: u32 = unsafe { (*self.usart).udr.read() as u32 };
             if ucsra.get_bits(2..5) != 0b000 {
-                None
+                Err(Error::InvalidData)
             } else {
                 let rxb8: u32 = ucsrb.get_bits(1..2) as u32;
                 udr.set_bits(8..9, rxb8);
-                Some(udr)
+                Ok(udr)
             }
         } else {
             let ucsra = unsafe { (*self.usart).ucsra.read() };
             let udr: u32 = unsafe { (*self.usart).udr.read() as u32 };
             if ucsra.get_bits(2..5) != 0b000 {
-                None
+                Err(Error::InvalidData)
             } else {
-                Some(udr)
+                Ok(udr)
             }
         }
     }
+
+    /// Takes a received byte without waiting, for polling loops and `nb::block!`.
+    /// The status is read before UDR, as reading UDR moves the receive buffer to the next frame.
+    /// # Returns
+    /// * `a nb::Result<u8, Error>` - which is `WouldBlock` if no byte has been received,
+    /// `Error::Overflow` if bytes were lost before this one, `Error::InvalidData` for a
+    /// frame error and `Error::CrcMismatch` for a parity error.
+    pub fn try_read(&mut self) -> nb::Result<u8, Error> {
+        if !self.available() {
+            return Err(nb::Error::WouldBlock);
+        }
+        let (ucsra, data) = unsafe { ((*self.usart).ucsra.read(), (*self.usart).udr.read()) };
+        if ucsra.get_bit(3) {
+            Err(nb::Error::Other(Error::Overflow))
+        } else if ucsra.get_bit(4) {
+            Err(nb::Error::Other(Error::InvalidData))
+        } else if ucsra.get_bit(2) {
+            Err(nb::Error::Other(Error::CrcMismatch))
+        } else {
+            Ok(data)
+        }
+    }
+
+    /// Receives a frame of 9 data bits, for which the frame size must be `UsartDataSize::Nine`.
+    /// The status and the ninth bit in RXB8 are read before the low byte in UDR, as
+    /// reading UDR moves the receive buffer to the next frame.
+    /// # Returns
+    /// * `a Option<u16>` - the 9 bits received, or None in case of a frame, overrun or parity error.
+    pub fn read_nine_bit(&mut self) -> Option<u16> {
+        while !self.available() {}
+        let (ucsra, ucsrb, low) = unsafe {
+            (
+                (*self.usart).ucsra.read(),
+                (*self.usart).ucsrb.read(),
+                (*self.usart).udr.read(),
+            )
+        };
+        if ucsra.get_bits(2..5) != 0b000 {
+            None
+        } else {
+            Some((ucsrb.get_bit(1) as u16) << 8 | low as u16)
+        }
+    }
+
+    /// Enables the multi-processor communication mode (MPCM), in which the receiver
+    /// ignores the frames whose ninth bit is clear, so that a slave is only interrupted
+    /// by the address frames sent with `write_address`.
+    pub fn mpcm_enable(&mut self) {
+        unsafe {
+            (*self.usart).ucsra.update(|sra| {
+                sra.set_bit(0, true);
+            });
+        }
+    }
+
+    /// Disables the multi-processor communication mode, every frame is received.
+    pub fn mpcm_disable(&mut self) {
+        unsafe {
+            (*self.usart).ucsra.update(|sra| {
+                sra.set_bit(0, false);
+            });
+        }
+    }
+
+    /// Waits in multi-processor communication mode for the address frame of this slave,
+    /// then disables MPCM so that the data frames which follow are received.
+    /// `mpcm_enable` must be called once the message is received, to wait for the next address.
+    /// # Arguments
+    /// * `address` - a u8, the address of this slave.
+    pub fn wait_for_address(&mut self, address: u8) {
+        self.mpcm_enable();
+        loop {
+            if let Some(frame) = self.read_nine_bit() {
+                if frame.get_bit(8) && frame as u8 == address {
+                    break;
+                }
+            }
+        }
+        self.mpcm_disable();
+    }
+}
+
+#[cfg(feature = "embedded")]
+impl embedded_hal::serial::Read<u8> for UsartObject {
+    type Error = Error;
+
+    fn read(&mut self) -> nb::Result<u8, Self::Error> {
+        self.try_read()
+    }
 }
@@ -20,12 +20,17 @@
 // Crates which would be used in the implementation.
 // We will be using standard volatile and bit_field crates now for a better read and write.
 use bit_field::BitField;
-use core::{f64, u8, usize};
-use fixed_slice_vec::FixedSliceVec;
+use core::convert::Infallible;
 
 // Other source code files to be used.
 use crate::atmega2560p::com::usart_initialize::{UsartDataSize, UsartObject};
-use crate::delay::delay_ms;
+use crate::delay::wait_until;
+use crate::error::Error;
+use crate::print::{Print, SerialWrite, Spec};
+
+/// Microseconds given to the transmitter to take a byte or finish sending, longer than
+/// a frame at the lowest baud rate.
+pub(crate) const TRANSMIT_TIMEOUT_US: u32 = 100_000;
 
 impl UsartObject {
     /// Enables the Transmitter, once it is enabled it takes control of the TXDn pin as a transmitting output.   
@@ -39,20 +44,12 @@ impl UsartObject {
     /// # Arguments
     /// * `data` - a u32, the data to be transmitted.
     /// * `len` -  a `UsartDataSize` object, which contains the length of data frame of USART.
-    pub unsafe fn transmitting_data(&mut self, data: u32, len: UsartDataSize) {
+    pub unsafe fn transmitting_data(&mut self, data: u32, len: UsartDataSize) -> Result<(), Error> {
         // Checks if the Transmit buffer is empty to receive data.
         // If not the program waits till the time comes.
-        let mut i: i32 = 10;
-        while self.avai_write() == false {
-            if i != 0 {
-                delay_ms(1000);
-                i = i - 1;
-            } else {
-                unreachable!()
-            }
-        }
+        wait_until(TRANSMIT_TIMEOUT_US, || self.avai_write())?;
 
-        let mut udr = (*self.usart).udr.read();
+        let mut udr: u8 = 0;
 
         // If the frame is ready for transmission then the appropriate place is written.
         match len {
@@ -75,6 +72,8 @@ impl UsartObject {
                 udr.set_bits(0..8, data.get_bits(0..8) as u8);
             }
         }
+        (*self.usart).udr.write(udr);
+        Ok(())
     }
 
     /// Checks that transmission buffer if ready for transmission.
@@ -90,174 +89,201 @@ impl UsartObject {
     }
 
     /// This waits for the transmission to complete by checking the appropriate register.
-    pub unsafe fn flush_transmit(&mut self) {
-        let mut ucsra = (*self.usart).ucsra.read();
-        let mut i: i32 = 10;
-        while ucsra.get_bit(6) == false {
-            ucsra = (*self.usart).ucsra.read();
-            if i != 0 {
-                delay_ms(1000);
-                i = i - 1;
-            } else {
-                unreachable!()
-            }
-        }
+    pub unsafe fn flush_transmit(&mut self) -> Result<(), Error> {
+        wait_until(TRANSMIT_TIMEOUT_US, || {
+            (*self.usart).ucsra.read().get_bit(6)
+        })
     }
 
     /// This is used to disable the Transmitter and once disabled the pins used for USART
     /// return into their default I/O pin mode.
-    pub fn transmit_disable(&mut self) {
-        let ucsra = unsafe { (*self.usart).ucsra.read() };
-        let mut uscra6 = ucsra.get_bit(6);
-        let mut uscra5 = ucsra.get_bit(5);
-        let mut i: i32 = 100;
-
-        // Check for data in Transmit Buffer and Transmit shift register,
-        // if data is present in either then disabling of transmitter is not effective
-        while uscra6 == false || uscra5 == false {
-            uscra6 = ucsra.get_bit(6);
-            uscra5 = ucsra.get_bit(5);
-            if i != 0 {
-                delay_ms(1000);
-                i = i - 1;
-            } else {
-                unreachable!()
-            }
-        }
+    pub fn transmit_disable(&mut self) -> Result<(), Error> {
+        // The frame being shifted out is finished before the transmitter stops,
+        // the byte waiting in the buffer must have been taken first.
+        wait_until(TRANSMIT_TIMEOUT_US, || {
+            unsafe { (*self.usart).ucsra.read() }.get_bit(5)
+        })?;
 
         unsafe {
             (*self.usart).ucsrb.update(|srb| {
                 srb.set_bit(3, false);
             });
         }
+        Ok(())
     }
 
     /// Sends a character byte of 5,6,7 or 8 bits.
     /// # Arguments
     /// * `data` - a u8, consisting of the current data frame to send from USART.
-    pub fn transmit_data(&mut self, data: u8) {
-        let mut ucsra = unsafe { (*self.usart).ucsra.read() };
-        let mut udre = ucsra.get_bit(5);
+    pub fn transmit_data(&mut self, data: u8) -> Result<(), Error> {
+        wait_until(TRANSMIT_TIMEOUT_US, || {
+            unsafe { (*self.usart).ucsra.read() }.get_bit(5)
+        })?;
 
-        let mut i: i32 = 100;
-        while udre == false {
-            ucsra = unsafe { (*self.usart).ucsra.read() };
-            udre = ucsra.get_bit(5);
-
-            if i != 0 {
-                delay_ms(1000);
-                i = i - 1;
-            } else {
-                unreachable!();
-            }
+        unsafe {
+            self.set_txn();
+            (*self.usart).udr.write(data);
         }
+        Ok(())
+    }
 
+    /// Puts a byte in the transmit buffer without waiting, for polling loops and `nb::block!`.
+    /// # Arguments
+    /// * `data` - a u8, the byte to be sent.
+    /// # Returns
+    /// * `a nb::Result<(), Infallible>` - which is `WouldBlock` while the buffer is full.
+    pub fn try_write(&mut self, data: u8) -> nb::Result<(), Infallible> {
+        if !unsafe { (*self.usart).ucsra.read() }.get_bit(5) {
+            return Err(nb::Error::WouldBlock);
+        }
         unsafe {
+            // TXC is cleared, so that `try_flush` waits for this byte.
             self.set_txn();
-            (*self.usart).udr.write(data)
-        };
+            (*self.usart).udr.write(data);
+        }
+        Ok(())
     }
 
-    /// Send's data of type string byte by byte using USART.
+    /// Checks without waiting whether the bytes given to `try_write` have all been shifted out.
+    /// TXC is only set once a byte is complete, so nothing must have been sent in other ways
+    /// since the USART was enabled for the first call to be reliable.
+    /// # Returns
+    /// * `a nb::Result<(), Infallible>` - which is `WouldBlock` while a byte is being sent.
+    pub fn try_flush(&mut self) -> nb::Result<(), Infallible> {
+        let ucsra = unsafe { (*self.usart).ucsra.read() };
+        if ucsra.get_bit(5) && ucsra.get_bit(6) {
+            Ok(())
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+
+    /// Sends a frame of 9 data bits, for which the frame size must be `UsartDataSize::Nine`.
+    /// The ninth bit is written to TXB8 before the low byte is written to UDR, as the
+    /// transmitter takes both when UDR is written.
     /// # Arguments
-    /// * `data` - a static string object, which is to be transmitted using USART.
-    pub fn write_string(&mut self, data: &'static str) {
-        let mut vec: FixedSliceVec<u8> = FixedSliceVec::new(&mut []);
+    /// * `data` - a u16, of which the lower 9 bits are sent.
+    pub fn write_nine_bit(&mut self, data: u16) {
+        unsafe {
+            while !self.avai_write() {}
+            (*self.usart).ucsrb.update(|srb| {
+                srb.set_bit(0, data.get_bit(8));
+            });
+            (*self.usart).udr.write(data as u8);
+        }
+    }
+
+    /// Sends the address of a slave in multi-processor communication mode, as a frame
+    /// of 9 bits whose ninth bit is set. Every slave in MPCM receives it, and the one
+    /// with this address leaves MPCM to receive the data frames which follow.
+    /// # Arguments
+    /// * `address` - a u8, the address of the slave.
+    pub fn write_address(&mut self, address: u8) {
+        self.write_nine_bit(0x100 | address as u16);
+    }
 
-        for c in data.chars() {
-            vec.push(c as u8);
+    /// Sends a data byte in multi-processor communication mode, as a frame of 9 bits
+    /// whose ninth bit is clear, which is ignored by the slaves still in MPCM.
+    /// # Arguments
+    /// * `data` - a u8, the byte to be sent.
+    pub fn write_mpcm_data(&mut self, data: u8) {
+        self.write_nine_bit(data as u16);
+    }
+
+    /// Sends and receives a byte at the same time in synchronous mode, as a SPI master
+    /// with the clock on XCK. The transmitter and the receiver must both be enabled.
+    /// In master synchronous mode the byte received is the one shifted in by the clock
+    /// of this transmission.
+    /// # Arguments
+    /// * `data` - a u8, the byte to be sent.
+    /// # Returns
+    /// * `a u8` - the byte received.
+    pub fn transfer(&mut self, data: u8) -> u8 {
+        unsafe {
+            while !self.avai_write() {}
+            (*self.usart).udr.write(data);
         }
+        while !self.available() {}
+        unsafe { (*self.usart).udr.read() }
+    }
 
-        for i in 0..(vec.len()) {
-            self.transmit_data(vec[i]);
+    /// Sends a string byte by byte.
+    /// # Arguments
+    /// * `data` - a static string object, which is to be transmitted using USART.
+    /// # Returns
+    /// * `a Result<(), Error>` - which is `Error::Timeout` if the transmitter stopped taking bytes.
+    pub fn write_string(&mut self, data: &'static str) -> Result<(), Error> {
+        for byte in data.bytes() {
+            self.transmit_data(byte)?;
         }
+        Ok(())
     }
 
-    /// Send's data of type integer(u32) byte by byte.
+    /// Sends a integer in decimal, with the formatter of `serial_print!`.
     /// # Arguments
     /// * `data` - a u32, which is to be transmitted using USART.
     pub fn write_integer(&mut self, data: u32) {
-        let mut vec: FixedSliceVec<u8> = FixedSliceVec::new(&mut []);
-        let mut a = data;
-        while a != 0 {
-            let rem = a % 10;
-            a = a / 10;
-            match rem {
-                0 => vec.push('0' as u8),
-                1 => vec.push('1' as u8),
-                2 => vec.push('2' as u8),
-                3 => vec.push('3' as u8),
-                4 => vec.push('4' as u8),
-                5 => vec.push('5' as u8),
-                6 => vec.push('6' as u8),
-                7 => vec.push('7' as u8),
-                8 => vec.push('8' as u8),
-                9 => vec.push('9' as u8),
-                _ => unreachable!(),
-            }
-        }
-        for i in 0..(vec.len()) {
-            self.transmit_data(vec[vec.len() - 1 - i]);
-        }
+        let spec = Spec {
+            radix: 10,
+            ..Spec::default()
+        };
+        data.print(self, &spec);
     }
 
-    /// Send's data of type float(f64) byte by byte till the precision required.
+    /// Sends a float with the given number of decimals, at most 9, rounded at the last one,
+    /// with the formatter of `serial_print!`.
     /// # Arguments
-    /// * `data` - a f32, which is to be transmitted using USART.
+    /// * `data` - a f64, which is to be transmitted using USART.
     /// * `precision` - a u32, the number of decimal precision required in the transmission.
     pub fn write_float(&mut self, data: f64, precision: u32) {
-        let mut vec: FixedSliceVec<u8> = FixedSliceVec::new(&mut []);
-        let a: f64 = data;
-        let mut f: f64 = a % 1.0;
-        let mut i: i64 = (a - (a % 1.0)) as i64;
-        let mut x: u32 = precision;
-        let mut n: usize = 0;
-        while f != 0.00 && x != 0 {
-            let k: i64 = ((f * 10.0) - ((f * 10.0) % 1.0)) as i64; // gives you decimal digit of data one by one from left to right
-            match k {
-                0 => vec.push('0' as u8),
-                1 => vec.push('1' as u8),
-                2 => vec.push('2' as u8),
-                3 => vec.push('3' as u8),
-                4 => vec.push('4' as u8),
-                5 => vec.push('5' as u8),
-                6 => vec.push('6' as u8),
-                7 => vec.push('7' as u8),
-                8 => vec.push('8' as u8),
-                9 => vec.push('9' as u8),
-                _ => unreachable!(),
-            }
-            f = (f * 10.0) % 1.0; // then f loses its left most digit (in decimal part)
-            x = x - 1;
-            n = n + 1;
+        let spec = Spec {
+            precision: Some(precision.min(9) as u8),
+            ..Spec::default()
+        };
+        data.print(self, &spec);
+    }
+}
+
+/// Lets `serial_print!` and `serial_write!` print through the USART.
+impl SerialWrite for UsartObject {
+    /// Waits for the data register to be empty and sends the byte.
+    fn write_byte(&mut self, byte: u8) {
+        unsafe {
+            while !(*self.usart).ucsra.read().get_bit(5) {}
+            self.set_txn();
+            (*self.usart).udr.write(byte);
         }
+    }
+}
 
-        vec.push('.' as u8);
+/// Lets the drivers written against `UsartOps` use the USART.
+impl crate::common::UsartOps for UsartObject {
+    fn write_byte(&mut self, byte: u8) {
+        SerialWrite::write_byte(self, byte);
+    }
 
-        while i != 0 {
-            let rem = i % 10;
-            i = i / 10;
-            match rem {
-                0 => vec.push('0' as u8),
-                1 => vec.push('1' as u8),
-                2 => vec.push('2' as u8),
-                3 => vec.push('3' as u8),
-                4 => vec.push('4' as u8),
-                5 => vec.push('5' as u8),
-                6 => vec.push('6' as u8),
-                7 => vec.push('7' as u8),
-                8 => vec.push('8' as u8),
-                9 => vec.push('9' as u8),
-                _ => (),
-            }
-        }
+    fn available(&mut self) -> bool {
+        UsartObject::available(self)
+    }
 
-        for ia in 0..(vec.len() - n - 1) {
-            self.transmit_data(vec[vec.len() - 1 - ia]);
+    fn read_byte(&mut self) -> Option<u8> {
+        if UsartObject::available(self) {
+            Some(unsafe { (*self.usart).udr.read() })
+        } else {
+            None
         }
+    }
+}
 
-        for ia in 0..n - 1 {
-            self.transmit_data(vec[ia]);
-        }
+#[cfg(feature = "embedded")]
+impl embedded_hal::serial::Write<u8> for UsartObject {
+    type Error = Infallible;
+
+    fn write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+        self.try_write(word)
+    }
+
+    fn flush(&mut self) -> nb::Result<(), Self::Error> {
+        self.try_flush()
     }
 }
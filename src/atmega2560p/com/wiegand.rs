@@ -0,0 +1,115 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Wiegand interface of RFID card and keypad readers on ATMEGA2560P.
+//! The reader has two lines, DATA0 and DATA1, which are high while idle. It sends a 0 by
+//! pulling DATA0 low for about 50 us and a 1 by pulling DATA1 low, with about 2 ms between
+//! the bits and the first bit first. The falling edges of the lines are caught by the
+//! external interrupts INT0 to INT5, so the lines must be on pins 2, 3, 18, 19, 20 or 21,
+//! and the global interrupts must be enabled.
+//! The bits are collected and the frames decoded by `protocol::wiegand`. A frame has
+//! ended once no bit has come for `WIEGAND_FRAME_GAP_US`, measured with `micros`, so
+//! `time::init` must be called.
+//! Most readers work at 5 V, and those at 12 V need a level shifter on their lines.
+//! See `<https://www.hidglobal.com/sites/default/files/hid-understanding_card_data_formats-wp-en.pdf>`.
+
+// Source codes required.
+use crate::atmega2560p::hal::interrupts::{
+    attach_interrupt, detach_interrupt, digital_pin_to_interrupt, without_interrupts, TriggerMode,
+};
+use crate::atmega2560p::hal::pin::make_pin;
+use crate::error::Error;
+use crate::protocol::wiegand::WiegandReceiver;
+pub use crate::protocol::wiegand::{WiegandCard, WiegandFrame, WIEGAND_FRAME_GAP_US};
+use crate::time::micros;
+
+/// Frame being received, shared with the interrupt routines.
+static mut RECEIVING: WiegandReceiver = WiegandReceiver::new();
+
+/// Used to read a Wiegand reader.
+/// # Elements
+/// * `data0` - a u8, the external interrupt of the DATA0 line.
+/// * `data1` - a u8, the external interrupt of the DATA1 line.
+pub struct WiegandReader {
+    data0: u8,
+    data1: u8,
+}
+
+impl WiegandReader {
+    /// Starts receiving from a reader. Only one reader can be used at a time.
+    /// # Arguments
+    /// * `data0` - a u32, the digital pin of the DATA0 line, with an external interrupt.
+    /// * `data1` - a u32, the digital pin of the DATA1 line, with an external interrupt.
+    /// # Returns
+    /// * `a Result<WiegandReader, Error>` - the reader, or `Error::InvalidParam` if the
+    ///   lines are not on two different pins with an external interrupt.
+    pub fn new(data0: u32, data1: u32) -> Result<WiegandReader, Error> {
+        let (int0, int1) = match (
+            digital_pin_to_interrupt(data0),
+            digital_pin_to_interrupt(data1),
+        ) {
+            (Some(int0), Some(int1)) if int0 != int1 => (int0, int1),
+            _ => return Err(Error::InvalidParam),
+        };
+        for pin in [data0, data1].iter() {
+            let mut line = make_pin(*pin);
+            line.set_input();
+            line.high();
+        }
+        without_interrupts(|| unsafe { RECEIVING.clear() });
+        attach_interrupt(int0, TriggerMode::Falling, zero)?;
+        attach_interrupt(int1, TriggerMode::Falling, one)?;
+        Ok(WiegandReader {
+            data0: int0,
+            data1: int1,
+        })
+    }
+
+    /// Gives the bits of the last frame once no bit has come for `WIEGAND_FRAME_GAP_US`.
+    /// This should be called more often than the frames come, as a frame which is not
+    /// read is lost when the next one starts. A frame of more than 64 bits is dropped.
+    /// # Returns
+    /// * `a Option<WiegandFrame>` - the frame, None if no frame has ended.
+    pub fn read_frame(&mut self) -> Option<WiegandFrame> {
+        without_interrupts(|| unsafe { RECEIVING.take(micros()) })
+    }
+
+    /// Reads the card of the last frame.
+    /// # Returns
+    /// * `a Result<Option<WiegandCard>, Error>` - the card, None if no frame has ended,
+    ///   or the error of `WiegandFrame::decode`.
+    pub fn read(&mut self) -> Result<Option<WiegandCard>, Error> {
+        self.read_frame().map(|frame| frame.decode()).transpose()
+    }
+}
+
+impl Drop for WiegandReader {
+    /// Disables the external interrupts of the lines.
+    fn drop(&mut self) {
+        let _ = detach_interrupt(self.data0);
+        let _ = detach_interrupt(self.data1);
+    }
+}
+
+/// Adds a 0, called by the interrupt of DATA0.
+fn zero() {
+    unsafe { RECEIVING.push(false, micros()) };
+}
+
+/// Adds a 1, called by the interrupt of DATA1.
+fn one() {
+    unsafe { RECEIVING.push(true, micros()) };
+}
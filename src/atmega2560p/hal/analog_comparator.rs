@@ -0,0 +1,229 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Analog comparator of ATMEGA2560P, which compares the voltages of two inputs
+//! continuously without any conversion. Its output is high when the positive input
+//! is above the negative input.
+//! The positive input is AIN0 (PE2, which is not connected on the Arduino Mega) or the
+//! internal 1.1V bandgap reference, and the negative input is AIN1 (digital pin 5) or any
+//! ADC channel through the ADC multiplexer, which is only possible while the ADC is
+//! switched off.
+//! A change of the output can call an interrupt or trigger the input capture of Timer1,
+//! which is useful for zero crossing detection and threshold sensing.
+//! See the section 25 of ATMEGA2560P datasheet.
+
+use crate::atmega2560p::hal::interrupts;
+use crate::atmega2560p::registers::{ACSR, ADCSRA, ADCSRB, ADMUX, DIDR1};
+
+/// Selection of the positive input of the comparator.
+/// * `Ain0` - the AIN0 pin, PE2.
+/// * `Bandgap` - the internal 1.1V bandgap reference.
+#[derive(Clone, Copy)]
+pub enum PositiveInput {
+    Ain0,
+    Bandgap,
+}
+
+/// Selection of the negative input of the comparator.
+/// * `Ain1` - the AIN1 pin, digital pin 5.
+/// * `Adc` - an ADC channel from 0 to 15, which switches off the ADC.
+#[derive(Clone, Copy)]
+pub enum NegativeInput {
+    Ain1,
+    Adc(u8),
+}
+
+/// Selection of the change of the output which sets the interrupt flag.
+/// * `Toggle` - any change of the output.
+/// * `Falling` - the output going low, when the positive input falls below the negative one.
+/// * `Rising` - the output going high, when the positive input rises above the negative one.
+#[derive(Clone, Copy)]
+pub enum ComparatorEdge {
+    Toggle,
+    Falling,
+    Rising,
+}
+
+impl ComparatorEdge {
+    /// Gives the value of the ACIS1:0 bits.
+    fn bits(&self) -> u8 {
+        match self {
+            ComparatorEdge::Toggle => 0b00,
+            ComparatorEdge::Falling => 0b10,
+            ComparatorEdge::Rising => 0b11,
+        }
+    }
+}
+
+// Function called from the analog comparator interrupt.
+static mut COMPARATOR_HANDLER: Option<fn()> = None;
+
+/// Structure to control the analog comparator.
+/// The registers are reached through the register map, so the structure holds nothing.
+pub struct AnalogComparator {
+    _private: (),
+}
+
+impl AnalogComparator {
+    /// Switches on the comparator with the given inputs, its interrupt disabled.
+    /// # Arguments
+    /// * `positive` - a `PositiveInput` object, the positive input.
+    /// * `negative` - a `NegativeInput` object, the negative input.
+    /// # Returns
+    /// * `a AnalogComparator object` - which will be used for further implementations.
+    pub fn new(positive: PositiveInput, negative: NegativeInput) -> AnalogComparator {
+        let mut comparator = AnalogComparator { _private: () };
+        ACSR.clear(ACSR::ACIE);
+        ACSR.clear(ACSR::ACD);
+        comparator.set_positive(positive);
+        comparator.set_negative(negative);
+        comparator.clear();
+        comparator
+    }
+
+    /// Selects the positive input.
+    /// The bandgap reference takes about 70 microseconds to settle when it is selected.
+    /// # Arguments
+    /// * `positive` - a `PositiveInput` object, the positive input.
+    pub fn set_positive(&mut self, positive: PositiveInput) {
+        match positive {
+            PositiveInput::Ain0 => {
+                DIDR1.set(DIDR1::AIN0D);
+                ACSR.clear(ACSR::ACBG);
+            }
+            PositiveInput::Bandgap => ACSR.set(ACSR::ACBG),
+        }
+    }
+
+    /// Selects the negative input.
+    /// # Arguments
+    /// * `negative` - a `NegativeInput` object, the negative input.
+    pub fn set_negative(&mut self, negative: NegativeInput) {
+        match negative {
+            NegativeInput::Ain1 => {
+                DIDR1.set(DIDR1::AIN1D);
+                ADCSRB.clear(ADCSRB::ACME);
+            }
+            NegativeInput::Adc(channel) => {
+                ADCSRA.clear(ADCSRA::ADEN);
+                ADMUX.write_field(ADMUX::MUX, channel & 0x07);
+                if channel & 0x08 != 0 {
+                    ADCSRB.set(ADCSRB::MUX5);
+                } else {
+                    ADCSRB.clear(ADCSRB::MUX5);
+                }
+                ADCSRB.set(ADCSRB::ACME);
+            }
+        }
+    }
+
+    /// Gives the output of the comparator.
+    /// # Returns
+    /// * `a boolean` - which is true if the positive input is above the negative input.
+    pub fn output(&self) -> bool {
+        ACSR.is_set(ACSR::ACO)
+    }
+
+    /// Checks whether the selected change of the output has happened since the last
+    /// call, which can be used instead of the interrupt.
+    /// Changing any other setting of the comparator also clears the flag, as ACSR is
+    /// read and written back with the flag set.
+    /// # Returns
+    /// * `a boolean` - which is true if the interrupt flag was set, the flag is then cleared.
+    pub fn has_triggered(&mut self) -> bool {
+        let triggered = ACSR.is_set(ACSR::ACI);
+        if triggered {
+            self.clear();
+        }
+        triggered
+    }
+
+    /// Clears the interrupt flag.
+    pub fn clear(&mut self) {
+        // The flag is cleared by writing a logic one to it.
+        ACSR.set(ACSR::ACI);
+    }
+
+    /// Selects the change of the output which sets the interrupt flag, without
+    /// enabling the interrupt.
+    /// The interrupt is disabled while the selection changes, as the change itself can set the flag.
+    /// # Arguments
+    /// * `edge` - a `ComparatorEdge` object, the change to be detected.
+    pub fn set_edge(&mut self, edge: ComparatorEdge) {
+        interrupts::without_interrupts(|| {
+            let enabled = ACSR.is_set(ACSR::ACIE);
+            ACSR.clear(ACSR::ACIE);
+            ACSR.write_field(ACSR::ACIS, edge.bits());
+            self.clear();
+            if enabled {
+                ACSR.set(ACSR::ACIE);
+            }
+        });
+    }
+
+    /// Enables the interrupt on the given change of the output.
+    /// The global interrupts must be enabled for the handler to be called.
+    /// # Arguments
+    /// * `edge` - a `ComparatorEdge` object, the change to be detected.
+    pub fn enable_interrupt(&mut self, edge: ComparatorEdge) {
+        self.set_edge(edge);
+        ACSR.set(ACSR::ACIE);
+    }
+
+    /// Disables the interrupt.
+    pub fn disable_interrupt(&mut self) {
+        ACSR.clear(ACSR::ACIE);
+    }
+
+    /// Sets the function called on every detected change of the output.
+    /// # Arguments
+    /// * `handler` - a function, which is called from the interrupt service routine.
+    pub fn on_change(&mut self, handler: fn()) {
+        interrupts::without_interrupts(|| unsafe {
+            COMPARATOR_HANDLER = Some(handler);
+        });
+    }
+
+    /// Connects the output to the input capture unit of Timer1 instead of the ICP1 pin,
+    /// so the time of each change is captured by the timer, see `capture::InputCapture`.
+    /// # Arguments
+    /// * `enable` - a boolean, true to trigger the input capture from the comparator.
+    pub fn set_input_capture(&mut self, enable: bool) {
+        if enable {
+            ACSR.set(ACSR::ACIC);
+        } else {
+            ACSR.clear(ACSR::ACIC);
+        }
+    }
+
+    /// Switches off the comparator to save power, and gives the pins back to digital use.
+    pub fn disable(&mut self) {
+        ACSR.clear(ACSR::ACIE);
+        ACSR.set(ACSR::ACD);
+        ADCSRB.clear(ADCSRB::ACME);
+        DIDR1.clear(DIDR1::AIN0D);
+        DIDR1.clear(DIDR1::AIN1D);
+    }
+}
+
+/// Analog Comparator Interrupt.
+#[cfg(target_arch = "avr")]
+#[no_mangle]
+pub unsafe extern "avr-interrupt" fn __vector_28() {
+    if let Some(handler) = COMPARATOR_HANDLER {
+        handler();
+    }
+}
@@ -16,10 +16,27 @@
 
 //! Global interrupts configured in the ATMEGA2560P chip is controlled here.
 //! Section 7.4 of the manual
+//! Handlers can also be attached to the external interrupts INT0 to INT7 and to the
+//! three pin change interrupt groups, like `attachInterrupt()` of Arduino.
+//! Section 15 of the manual
 
 // Crates required in the code for reading and writing to registers.
+use bit_field::BitField;
 use core::ptr::{read_volatile, write_volatile};
 
+// Source codes required.
+use crate::error::Error;
+use crate::sync::free;
+
+// Registers of the external and pin change interrupts.
+const PCIFR: *mut u8 = 0x3B as *mut u8;
+const EIFR: *mut u8 = 0x3C as *mut u8;
+const EIMSK: *mut u8 = 0x3D as *mut u8;
+const PCICR: *mut u8 = 0x68 as *mut u8;
+const EICRA: *mut u8 = 0x69 as *mut u8;
+const EICRB: *mut u8 = 0x6A as *mut u8;
+const PCMSK: [*mut u8; 3] = [0x6B as *mut u8, 0x6C as *mut u8, 0x6D as *mut u8];
+
 /// This contains the registers to be manipulated for controlling global interrupts setup.
 /// This represents struct for Globalinterrupts and is used to control sreg register.
 #[repr(C, packed)]
@@ -54,4 +71,494 @@ impl Interrupt {
             write_volatile(&mut self.sreg, ctrl_sreg);
         }
     }
+
+    /// Checks whether global interrupts are enabled.
+    /// # Returns
+    /// * `a boolean` - which is true if the I bit of SREG is set.
+    pub fn is_enabled(&mut self) -> bool {
+        let ctrl_sreg = unsafe { read_volatile(&self.sreg) };
+        ctrl_sreg & (1 << 7) != 0
+    }
+}
+
+/// Executes the given closure with global interrupts disabled, so that data shared
+/// with an interrupt service routine can be accessed safely.
+/// This is `sync::free` without the `CriticalSection` token, which should be used
+/// instead in new code, and kept for the code written before it.
+/// # Arguments
+/// * `f` - a closure, which is to be executed without interruption.
+/// # Returns
+/// * `the value returned by the closure`.
+pub fn without_interrupts<F, R>(f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    free(|_| f())
+}
+
+/// Selection of the condition which triggers an external interrupt.
+/// * `Low` - as long as the pin is low.
+/// * `Change` - on any logical change of the pin.
+/// * `Falling` - on the falling edge.
+/// * `Rising` - on the rising edge.
+#[derive(Clone, Copy)]
+pub enum TriggerMode {
+    Low,
+    Change,
+    Falling,
+    Rising,
+}
+
+// Handlers attached to INT0 to INT7 and to the pin change groups PCINT0 to PCINT2.
+static mut EXTERNAL_HANDLERS: [Option<fn()>; 8] = [None; 8];
+static mut PIN_CHANGE_HANDLERS: [Option<fn()>; 3] = [None; 3];
+
+/// Gives the external interrupt connected to a digital pin of the Arduino Mega.
+/// INT6 and INT7 are not connected to any pin of the board.
+/// # Arguments
+/// * `pin` - a u32, the digital pin number.
+/// # Returns
+/// * `a Option<u8>` - the number of the external interrupt, if the pin has one.
+pub fn digital_pin_to_interrupt(pin: u32) -> Option<u8> {
+    match pin {
+        21 => Some(0),
+        20 => Some(1),
+        19 => Some(2),
+        18 => Some(3),
+        2 => Some(4),
+        3 => Some(5),
+        _ => None,
+    }
+}
+
+/// Gives the pin change interrupt group and bit of a digital pin of the Arduino Mega.
+/// # Arguments
+/// * `pin` - a u32, the digital pin number.
+/// # Returns
+/// * `a Option<(u8, u8)>` - the group (0 to 2) and the bit in its mask register,
+///   if the pin has a pin change interrupt.
+pub fn digital_pin_to_pcint(pin: u32) -> Option<(u8, u8)> {
+    match pin {
+        53 => Some((0, 0)),
+        52 => Some((0, 1)),
+        51 => Some((0, 2)),
+        50 => Some((0, 3)),
+        10..=13 => Some((0, (pin - 6) as u8)),
+        0 => Some((1, 0)),
+        15 => Some((1, 1)),
+        14 => Some((1, 2)),
+        62..=69 => Some((2, (pin - 62) as u8)),
+        _ => None,
+    }
+}
+
+/// Attaches a handler to an external interrupt and enables it.
+/// Global interrupts must also be enabled for the handler to be called.
+/// # Arguments
+/// * `int` - a u8, the number of the external interrupt from 0 to 7.
+/// * `mode` - a `TriggerMode` object, the condition which calls the handler.
+/// * `handler` - a function, which is called from the interrupt service routine.
+/// # Returns
+/// * `a Result<(), Error>` - which is `Error::InvalidParam` if the chip has no such interrupt.
+pub fn attach_interrupt(int: u8, mode: TriggerMode, handler: fn()) -> Result<(), Error> {
+    if int > 7 {
+        return Err(Error::InvalidParam);
+    }
+    let sense = match mode {
+        TriggerMode::Low => 0b00,
+        TriggerMode::Change => 0b01,
+        TriggerMode::Falling => 0b10,
+        TriggerMode::Rising => 0b11,
+    };
+    let (eicr, shift) = if int < 4 {
+        (EICRA, 2 * int)
+    } else {
+        (EICRB, 2 * (int - 4))
+    };
+
+    without_interrupts(|| unsafe {
+        EXTERNAL_HANDLERS[int as usize] = Some(handler);
+
+        let mut eimsk = read_volatile(EIMSK);
+        eimsk.set_bit(int, false);
+        write_volatile(EIMSK, eimsk);
+
+        let mut control = read_volatile(eicr);
+        control.set_bits(shift..shift + 2, sense);
+        write_volatile(eicr, control);
+
+        // Changing the sense control may set the flag, which is cleared by writing one.
+        write_volatile(EIFR, 1 << int);
+        eimsk.set_bit(int, true);
+        write_volatile(EIMSK, eimsk);
+    });
+    Ok(())
+}
+
+/// Disables an external interrupt and removes its handler.
+/// # Arguments
+/// * `int` - a u8, the number of the external interrupt from 0 to 7.
+/// # Returns
+/// * `a Result<(), Error>` - which is `Error::InvalidParam` if the chip has no such interrupt.
+pub fn detach_interrupt(int: u8) -> Result<(), Error> {
+    if int > 7 {
+        return Err(Error::InvalidParam);
+    }
+    without_interrupts(|| unsafe {
+        let mut eimsk = read_volatile(EIMSK);
+        eimsk.set_bit(int, false);
+        write_volatile(EIMSK, eimsk);
+        EXTERNAL_HANDLERS[int as usize] = None;
+    });
+    Ok(())
+}
+
+/// Attaches a handler to a pin change interrupt group and enables it for the pins given.
+/// The handler is called when any of the pins changes, so it has to read the pins
+/// to find out which one has changed.
+/// # Arguments
+/// * `group` - a u8, the pin change interrupt group from 0 to 2.
+/// * `mask` - a u8, the pins of the group which trigger the interrupt.
+/// * `handler` - a function, which is called from the interrupt service routine.
+/// # Returns
+/// * `a Result<(), Error>` - which is `Error::InvalidParam` if the chip has no such group.
+pub fn attach_pin_change(group: u8, mask: u8, handler: fn()) -> Result<(), Error> {
+    if group > 2 {
+        return Err(Error::InvalidParam);
+    }
+    without_interrupts(|| unsafe {
+        PIN_CHANGE_HANDLERS[group as usize] = Some(handler);
+        write_volatile(PCMSK[group as usize], mask);
+        write_volatile(PCIFR, 1 << group);
+
+        let mut pcicr = read_volatile(PCICR);
+        pcicr.set_bit(group, true);
+        write_volatile(PCICR, pcicr);
+    });
+    Ok(())
+}
+
+/// Disables a pin change interrupt group and removes its handler.
+/// # Arguments
+/// * `group` - a u8, the pin change interrupt group from 0 to 2.
+/// # Returns
+/// * `a Result<(), Error>` - which is `Error::InvalidParam` if the chip has no such group.
+pub fn detach_pin_change(group: u8) -> Result<(), Error> {
+    if group > 2 {
+        return Err(Error::InvalidParam);
+    }
+    without_interrupts(|| unsafe {
+        let mut pcicr = read_volatile(PCICR);
+        pcicr.set_bit(group, false);
+        write_volatile(PCICR, pcicr);
+        write_volatile(PCMSK[group as usize], 0);
+        PIN_CHANGE_HANDLERS[group as usize] = None;
+    });
+    Ok(())
+}
+
+/// Calls the handler attached to an external interrupt.
+#[cfg(target_arch = "avr")]
+unsafe fn external_interrupt(int: usize) {
+    if let Some(handler) = EXTERNAL_HANDLERS[int] {
+        handler();
+    }
+}
+
+/// Calls the handler attached to a pin change interrupt group.
+#[cfg(target_arch = "avr")]
+unsafe fn pin_change_interrupt(group: usize) {
+    if let Some(handler) = PIN_CHANGE_HANDLERS[group] {
+        handler();
+    }
+}
+
+/// External Interrupt Request 0.
+#[cfg(target_arch = "avr")]
+#[no_mangle]
+pub unsafe extern "avr-interrupt" fn __vector_1() {
+    external_interrupt(0);
+}
+
+/// External Interrupt Request 1.
+#[cfg(target_arch = "avr")]
+#[no_mangle]
+pub unsafe extern "avr-interrupt" fn __vector_2() {
+    external_interrupt(1);
+}
+
+/// External Interrupt Request 2.
+#[cfg(target_arch = "avr")]
+#[no_mangle]
+pub unsafe extern "avr-interrupt" fn __vector_3() {
+    external_interrupt(2);
+}
+
+/// External Interrupt Request 3.
+#[cfg(target_arch = "avr")]
+#[no_mangle]
+pub unsafe extern "avr-interrupt" fn __vector_4() {
+    external_interrupt(3);
+}
+
+/// External Interrupt Request 4.
+#[cfg(target_arch = "avr")]
+#[no_mangle]
+pub unsafe extern "avr-interrupt" fn __vector_5() {
+    external_interrupt(4);
+}
+
+/// External Interrupt Request 5.
+#[cfg(target_arch = "avr")]
+#[no_mangle]
+pub unsafe extern "avr-interrupt" fn __vector_6() {
+    external_interrupt(5);
+}
+
+/// External Interrupt Request 6.
+#[cfg(target_arch = "avr")]
+#[no_mangle]
+pub unsafe extern "avr-interrupt" fn __vector_7() {
+    external_interrupt(6);
+}
+
+/// External Interrupt Request 7.
+#[cfg(target_arch = "avr")]
+#[no_mangle]
+pub unsafe extern "avr-interrupt" fn __vector_8() {
+    external_interrupt(7);
+}
+
+/// Pin Change Interrupt Request 0.
+#[cfg(target_arch = "avr")]
+#[no_mangle]
+pub unsafe extern "avr-interrupt" fn __vector_9() {
+    pin_change_interrupt(0);
+}
+
+/// Pin Change Interrupt Request 1.
+#[cfg(target_arch = "avr")]
+#[no_mangle]
+pub unsafe extern "avr-interrupt" fn __vector_10() {
+    pin_change_interrupt(1);
+}
+
+/// Pin Change Interrupt Request 2.
+#[cfg(target_arch = "avr")]
+#[no_mangle]
+pub unsafe extern "avr-interrupt" fn __vector_11() {
+    pin_change_interrupt(2);
+}
+
+/// Declares the interrupt service routine of an interrupt vector by its name in the
+/// datasheet, as `TIMER1_COMPA` or `PCINT0`, instead of the `__vector_N` symbol.
+/// A name which is not a vector of the ATMEGA2560P does not compile.
+/// The vectors already defined by the library, as the external and pin change
+/// interrupts or `TIMER0_OVF` for `time`, cannot be taken again, which fails when linking.
+/// # Example
+/// ```ignore
+/// avr_interrupt!(TIMER1_COMPA, fn on_compare() {
+///     TICKS.fetch_add(1);
+/// });
+/// ```
+#[macro_export]
+macro_rules! avr_interrupt {
+    ($vector:ident, fn $handler:ident() $body:block) => {
+        fn $handler() $body
+        $crate::__avr_vector!($vector, $handler);
+    };
+}
+
+/// Gives the symbol of a vector of the ATMEGA2560P, see `avr_interrupt!`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __avr_vector {
+    (INT0, $handler:ident) => {
+        $crate::__avr_isr!(__vector_1, $handler);
+    };
+    (INT1, $handler:ident) => {
+        $crate::__avr_isr!(__vector_2, $handler);
+    };
+    (INT2, $handler:ident) => {
+        $crate::__avr_isr!(__vector_3, $handler);
+    };
+    (INT3, $handler:ident) => {
+        $crate::__avr_isr!(__vector_4, $handler);
+    };
+    (INT4, $handler:ident) => {
+        $crate::__avr_isr!(__vector_5, $handler);
+    };
+    (INT5, $handler:ident) => {
+        $crate::__avr_isr!(__vector_6, $handler);
+    };
+    (INT6, $handler:ident) => {
+        $crate::__avr_isr!(__vector_7, $handler);
+    };
+    (INT7, $handler:ident) => {
+        $crate::__avr_isr!(__vector_8, $handler);
+    };
+    (PCINT0, $handler:ident) => {
+        $crate::__avr_isr!(__vector_9, $handler);
+    };
+    (PCINT1, $handler:ident) => {
+        $crate::__avr_isr!(__vector_10, $handler);
+    };
+    (PCINT2, $handler:ident) => {
+        $crate::__avr_isr!(__vector_11, $handler);
+    };
+    (WDT, $handler:ident) => {
+        $crate::__avr_isr!(__vector_12, $handler);
+    };
+    (TIMER2_COMPA, $handler:ident) => {
+        $crate::__avr_isr!(__vector_13, $handler);
+    };
+    (TIMER2_COMPB, $handler:ident) => {
+        $crate::__avr_isr!(__vector_14, $handler);
+    };
+    (TIMER2_OVF, $handler:ident) => {
+        $crate::__avr_isr!(__vector_15, $handler);
+    };
+    (TIMER1_CAPT, $handler:ident) => {
+        $crate::__avr_isr!(__vector_16, $handler);
+    };
+    (TIMER1_COMPA, $handler:ident) => {
+        $crate::__avr_isr!(__vector_17, $handler);
+    };
+    (TIMER1_COMPB, $handler:ident) => {
+        $crate::__avr_isr!(__vector_18, $handler);
+    };
+    (TIMER1_COMPC, $handler:ident) => {
+        $crate::__avr_isr!(__vector_19, $handler);
+    };
+    (TIMER1_OVF, $handler:ident) => {
+        $crate::__avr_isr!(__vector_20, $handler);
+    };
+    (TIMER0_COMPA, $handler:ident) => {
+        $crate::__avr_isr!(__vector_21, $handler);
+    };
+    (TIMER0_COMPB, $handler:ident) => {
+        $crate::__avr_isr!(__vector_22, $handler);
+    };
+    (TIMER0_OVF, $handler:ident) => {
+        $crate::__avr_isr!(__vector_23, $handler);
+    };
+    (SPI_STC, $handler:ident) => {
+        $crate::__avr_isr!(__vector_24, $handler);
+    };
+    (USART0_RX, $handler:ident) => {
+        $crate::__avr_isr!(__vector_25, $handler);
+    };
+    (USART0_UDRE, $handler:ident) => {
+        $crate::__avr_isr!(__vector_26, $handler);
+    };
+    (USART0_TX, $handler:ident) => {
+        $crate::__avr_isr!(__vector_27, $handler);
+    };
+    (ANALOG_COMP, $handler:ident) => {
+        $crate::__avr_isr!(__vector_28, $handler);
+    };
+    (ADC, $handler:ident) => {
+        $crate::__avr_isr!(__vector_29, $handler);
+    };
+    (EE_READY, $handler:ident) => {
+        $crate::__avr_isr!(__vector_30, $handler);
+    };
+    (TIMER3_CAPT, $handler:ident) => {
+        $crate::__avr_isr!(__vector_31, $handler);
+    };
+    (TIMER3_COMPA, $handler:ident) => {
+        $crate::__avr_isr!(__vector_32, $handler);
+    };
+    (TIMER3_COMPB, $handler:ident) => {
+        $crate::__avr_isr!(__vector_33, $handler);
+    };
+    (TIMER3_COMPC, $handler:ident) => {
+        $crate::__avr_isr!(__vector_34, $handler);
+    };
+    (TIMER3_OVF, $handler:ident) => {
+        $crate::__avr_isr!(__vector_35, $handler);
+    };
+    (USART1_RX, $handler:ident) => {
+        $crate::__avr_isr!(__vector_36, $handler);
+    };
+    (USART1_UDRE, $handler:ident) => {
+        $crate::__avr_isr!(__vector_37, $handler);
+    };
+    (USART1_TX, $handler:ident) => {
+        $crate::__avr_isr!(__vector_38, $handler);
+    };
+    (TWI, $handler:ident) => {
+        $crate::__avr_isr!(__vector_39, $handler);
+    };
+    (SPM_READY, $handler:ident) => {
+        $crate::__avr_isr!(__vector_40, $handler);
+    };
+    (TIMER4_CAPT, $handler:ident) => {
+        $crate::__avr_isr!(__vector_41, $handler);
+    };
+    (TIMER4_COMPA, $handler:ident) => {
+        $crate::__avr_isr!(__vector_42, $handler);
+    };
+    (TIMER4_COMPB, $handler:ident) => {
+        $crate::__avr_isr!(__vector_43, $handler);
+    };
+    (TIMER4_COMPC, $handler:ident) => {
+        $crate::__avr_isr!(__vector_44, $handler);
+    };
+    (TIMER4_OVF, $handler:ident) => {
+        $crate::__avr_isr!(__vector_45, $handler);
+    };
+    (TIMER5_CAPT, $handler:ident) => {
+        $crate::__avr_isr!(__vector_46, $handler);
+    };
+    (TIMER5_COMPA, $handler:ident) => {
+        $crate::__avr_isr!(__vector_47, $handler);
+    };
+    (TIMER5_COMPB, $handler:ident) => {
+        $crate::__avr_isr!(__vector_48, $handler);
+    };
+    (TIMER5_COMPC, $handler:ident) => {
+        $crate::__avr_isr!(__vector_49, $handler);
+    };
+    (TIMER5_OVF, $handler:ident) => {
+        $crate::__avr_isr!(__vector_50, $handler);
+    };
+    (USART2_RX, $handler:ident) => {
+        $crate::__avr_isr!(__vector_51, $handler);
+    };
+    (USART2_UDRE, $handler:ident) => {
+        $crate::__avr_isr!(__vector_52, $handler);
+    };
+    (USART2_TX, $handler:ident) => {
+        $crate::__avr_isr!(__vector_53, $handler);
+    };
+    (USART3_RX, $handler:ident) => {
+        $crate::__avr_isr!(__vector_54, $handler);
+    };
+    (USART3_UDRE, $handler:ident) => {
+        $crate::__avr_isr!(__vector_55, $handler);
+    };
+    (USART3_TX, $handler:ident) => {
+        $crate::__avr_isr!(__vector_56, $handler);
+    };
+    ($vector:ident, $handler:ident) => {
+        compile_error!(concat!(
+            "no interrupt vector ",
+            stringify!($vector),
+            " on the ATMEGA2560P"
+        ));
+    };
+}
+
+/// Defines the interrupt service routine calling the handler, see `avr_interrupt!`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __avr_isr {
+    ($symbol:ident, $handler:ident) => {
+        #[cfg(target_arch = "avr")]
+        #[no_mangle]
+        pub unsafe extern "avr-interrupt" fn $symbol() {
+            $handler();
+        }
+    };
 }
@@ -164,6 +164,31 @@ impl Pin {
     pub fn set_input(&mut self) {
         self.set_pin_mode(IOMode::Input);
     }
+
+    /// Toggles value of PORTxn, independent of value of DDRxn.
+    pub fn toggle(&mut self) {
+        // Set the bit at offset self.pin in PINxn register
+        unsafe { write_volatile(&mut (*self.port).pin, 0x1 << self.pin) }
+    }
+
+    /// Set pin to high by setting the bit of PORTxn.
+    pub fn high(&mut self) {
+        let port_val = unsafe { read_volatile(&mut (*self.port).port) };
+        unsafe { write_volatile(&mut (*self.port).port, port_val | (0x1 << self.pin)) }
+    }
+
+    /// Set pin to low by clearing the bit of PORTxn.
+    pub fn low(&mut self) {
+        let port_val = unsafe { read_volatile(&mut (*self.port).port) };
+        unsafe { write_volatile(&mut (*self.port).port, port_val & !(0x1 << self.pin)) }
+    }
+
+    /// Reads the level on the pin from PINxn register.
+    /// # Returns
+    /// * `a boolean` - which is true if the pin is high.
+    pub fn is_high(&self) -> bool {
+        unsafe { read_volatile(&(*self.port).pin) & (0x1 << self.pin) != 0 }
+    }
 }
 
 impl AnalogPin {
@@ -203,3 +228,100 @@ impl DigitalPin {
         }
     }
 }
+
+/// Lets the drivers written against `GpioOps` use the pin.
+impl crate::common::GpioOps for Pin {
+    fn set_output(&mut self) {
+        Pin::set_output(self);
+    }
+
+    fn set_input(&mut self) {
+        Pin::set_input(self);
+    }
+
+    fn high(&mut self) {
+        Pin::high(self);
+    }
+
+    fn low(&mut self) {
+        Pin::low(self);
+    }
+
+    fn toggle(&mut self) {
+        Pin::toggle(self);
+    }
+
+    fn is_high(&self) -> bool {
+        Pin::is_high(self)
+    }
+}
+
+/// Gives the address in the I/O space of the PINx, DDRx or PORTx register of a port
+/// of the ATMEGA2560P, see `fast_high!`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __port_io {
+    (A, PIN) => {
+        0x00
+    };
+    (A, DDR) => {
+        0x01
+    };
+    (A, PORT) => {
+        0x02
+    };
+    (B, PIN) => {
+        0x03
+    };
+    (B, DDR) => {
+        0x04
+    };
+    (B, PORT) => {
+        0x05
+    };
+    (C, PIN) => {
+        0x06
+    };
+    (C, DDR) => {
+        0x07
+    };
+    (C, PORT) => {
+        0x08
+    };
+    (D, PIN) => {
+        0x09
+    };
+    (D, DDR) => {
+        0x0A
+    };
+    (D, PORT) => {
+        0x0B
+    };
+    (E, PIN) => {
+        0x0C
+    };
+    (E, DDR) => {
+        0x0D
+    };
+    (E, PORT) => {
+        0x0E
+    };
+    (F, PIN) => {
+        0x0F
+    };
+    (F, DDR) => {
+        0x10
+    };
+    (F, PORT) => {
+        0x11
+    };
+    (G, PIN) => {
+        0x12
+    };
+    (G, DDR) => {
+        0x13
+    };
+    (G, PORT) => {
+        0x14
+    };
+}
@@ -15,9 +15,19 @@
 //     along with this program.  If not, see <https://www.gnu.org/licenses/>
 
 //! Generic implementation of power control through clock gating in ATMEGA2560P.
+//! The drivers enable the clock of their peripheral when they are created and gate it again
+//! in their `shutdown()`, and `Power::disable_all_unused()` gates every peripheral which
+//! is not switched on, for example before sleeping.
 //! Section 11.10.2 and 11.10.3 of the manual.
 //! Also references from Section 11.8.
 
+// Source codes required.
+use crate::atmega2560p::registers::{
+    ADCSRA, PRR0, PRR1, SPCR, TCCR0B, TCCR1B, TCCR2B, TCCR3B, TCCR4B, TCCR5B, TWCR, UCSR0B, UCSR1B,
+    UCSR2B, UCSR3B,
+};
+use crate::register::{Field, Register};
+
 // Crates required in the code for reading and writing to registers.
 use core::ptr::{read_volatile, write_volatile};
 
@@ -258,4 +268,63 @@ impl Power {
             },
         }
     }
+
+    /// Checks whether the clock of a peripheral is running.
+    /// # Arguments
+    /// * `mode` - a `Peripherals` object, the peripheral to be checked.
+    /// # Returns
+    /// * `a boolean` - which is true if the PRR0 or PRR1 bit of the peripheral is clear.
+    pub fn is_clock_enabled(mode: Peripherals) -> bool {
+        let (prr, bit) = Power::prr_bit(mode);
+        !prr.is_set(bit)
+    }
+
+    /// Gates the clock of every peripheral which is not switched on: the TWI, SPI and ADC
+    /// when their enable bit is clear, the USARTs when both their receiver and transmitter
+    /// are disabled, and the timers which have no clock source selected.
+    /// Timer0 counts `millis` once `time::init` has been called, so it is kept then.
+    pub fn disable_all_unused() {
+        // RXENn and TXENn are the bits 4 and 3 of every UCSRnB.
+        let usart_off = |ucsrb: Register<u8>| ucsrb.read() & 0x18 == 0;
+        let unused = [
+            (Peripherals::TWI, !TWCR.is_set(TWCR::TWEN)),
+            (Peripherals::TIMER2, TCCR2B.read_field(TCCR2B::CS2) == 0),
+            (Peripherals::TIMER0, TCCR0B.read_field(TCCR0B::CS0) == 0),
+            (Peripherals::TIMER1, TCCR1B.read_field(TCCR1B::CS1) == 0),
+            (Peripherals::SPI, !SPCR.is_set(SPCR::SPE)),
+            (Peripherals::USART0, usart_off(UCSR0B)),
+            (Peripherals::ADC, !ADCSRA.is_set(ADCSRA::ADEN)),
+            (Peripherals::TIMER5, TCCR5B.read_field(TCCR5B::CS5) == 0),
+            (Peripherals::TIMER4, TCCR4B.read_field(TCCR4B::CS4) == 0),
+            (Peripherals::TIMER3, TCCR3B.read_field(TCCR3B::CS3) == 0),
+            (Peripherals::USART3, usart_off(UCSR3B)),
+            (Peripherals::USART2, usart_off(UCSR2B)),
+            (Peripherals::USART1, usart_off(UCSR1B)),
+        ];
+        let power = unsafe { Power::new() };
+        for (peripheral, unused) in unused.iter() {
+            if *unused {
+                power.disable_clocks(*peripheral);
+            }
+        }
+    }
+
+    // Gives the register and the bit which gate the clock of a peripheral.
+    fn prr_bit(mode: Peripherals) -> (Register<u8>, Field) {
+        match mode {
+            Peripherals::TWI => (PRR0, PRR0::PRTWI),
+            Peripherals::TIMER2 => (PRR0, PRR0::PRTIM2),
+            Peripherals::TIMER0 => (PRR0, PRR0::PRTIM0),
+            Peripherals::TIMER1 => (PRR0, PRR0::PRTIM1),
+            Peripherals::SPI => (PRR0, PRR0::PRSPI),
+            Peripherals::USART0 => (PRR0, PRR0::PRUSART0),
+            Peripherals::ADC => (PRR0, PRR0::PRADC),
+            Peripherals::TIMER5 => (PRR1, PRR1::PRTIM5),
+            Peripherals::TIMER4 => (PRR1, PRR1::PRTIM4),
+            Peripherals::TIMER3 => (PRR1, PRR1::PRTIM3),
+            Peripherals::USART3 => (PRR1, PRR1::PRUSART3),
+            Peripherals::USART2 => (PRR1, PRR1::PRUSART2),
+            Peripherals::USART1 => (PRR1, PRR1::PRUSART1),
+        }
+    }
 }
@@ -0,0 +1,385 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021 Aniket Sharma, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Hardware PWM generation using the Timer/Counter units of ATMEGA2560P.
+//! The 8 bit timers 0 and 2 count up to a fixed TOP of 0xFF, while the 16 bit timers
+//! 1, 3, 4 and 5 use ICRn as TOP so that their frequency can be chosen freely.
+//! By default Timer0 runs in Fast PWM mode (980 Hz) and the other timers in
+//! Phase Correct mode (490 Hz), the same as the Arduino core.
+//! Once `time::init` has been called Timer0 counts `millis` and `micros`, so its mode and
+//! frequency are kept: its channels can only change their duty.
+//! Refer to section 16, 17, 18 and 20 of ATMEGA2560P datasheet.
+
+// Source codes required.
+use crate::atmega2560p::hal::analog::{Timer16, Timer8, TimerNo16, TimerNo8};
+use crate::atmega2560p::hal::pin::{make_pin, DigitalPin};
+use crate::atmega2560p::hal::power::{Peripherals, Power};
+use crate::config::CPU_FREQUENCY_HZ;
+use crate::time;
+
+// Crates to be used for the implementation.
+use bit_field::BitField;
+
+/// Selection of the waveform generation mode used for PWM.
+/// * `FastPwm` - single slope operation, twice the frequency of phase correct mode.
+/// * `PhaseCorrect` - dual slope operation, preferred for motor control.
+#[derive(Clone, Copy, PartialEq)]
+pub enum PwmMode {
+    FastPwm,
+    PhaseCorrect,
+}
+
+/// Output compare unit of a timer which drives the pin.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Channel {
+    A,
+    B,
+    C,
+}
+
+/// Timer which generates the waveform of a PWM channel.
+#[derive(Clone, Copy)]
+enum PwmTimer {
+    Timer8(TimerNo8),
+    Timer16(TimerNo16),
+}
+
+/// Structure to control one PWM capable pin.
+/// All the channels of a timer share the frequency and the mode, so changing
+/// these on one channel changes them for the other pins of the same timer too.
+#[derive(Clone, Copy)]
+pub struct PwmChannel {
+    timer: PwmTimer,
+    channel: Channel,
+    pin: u32,
+    mode: PwmMode,
+}
+
+/// Gives the timer and the output compare unit connected to a digital pin.
+/// # Arguments
+/// * `pin` - a u32, the digital pin number.
+/// # Returns
+/// * `a Option<(PwmTimer, Channel)>` - None if the pin has no PWM output.
+fn pin_map(pin: u32) -> Option<(PwmTimer, Channel)> {
+    match pin {
+        13 => Some((PwmTimer::Timer8(TimerNo8::Timer0), Channel::A)),
+        4 => Some((PwmTimer::Timer8(TimerNo8::Timer0), Channel::B)),
+        10 => Some((PwmTimer::Timer8(TimerNo8::Timer2), Channel::A)),
+        9 => Some((PwmTimer::Timer8(TimerNo8::Timer2), Channel::B)),
+        11 => Some((PwmTimer::Timer16(TimerNo16::Timer1), Channel::A)),
+        12 => Some((PwmTimer::Timer16(TimerNo16::Timer1), Channel::B)),
+        5 => Some((PwmTimer::Timer16(TimerNo16::Timer3), Channel::A)),
+        2 => Some((PwmTimer::Timer16(TimerNo16::Timer3), Channel::B)),
+        3 => Some((PwmTimer::Timer16(TimerNo16::Timer3), Channel::C)),
+        6 => Some((PwmTimer::Timer16(TimerNo16::Timer4), Channel::A)),
+        7 => Some((PwmTimer::Timer16(TimerNo16::Timer4), Channel::B)),
+        8 => Some((PwmTimer::Timer16(TimerNo16::Timer4), Channel::C)),
+        46 => Some((PwmTimer::Timer16(TimerNo16::Timer5), Channel::A)),
+        45 => Some((PwmTimer::Timer16(TimerNo16::Timer5), Channel::B)),
+        44 => Some((PwmTimer::Timer16(TimerNo16::Timer5), Channel::C)),
+        _ => None,
+    }
+}
+
+/// Gives the clock division factors available for a timer, in the order of
+/// their CSn2:0 values starting from 1.
+fn prescalers(timer: PwmTimer) -> &'static [u32] {
+    match timer {
+        PwmTimer::Timer8(TimerNo8::Timer2) => &[1, 8, 32, 64, 128, 256, 1024],
+        _ => &[1, 8, 64, 256, 1024],
+    }
+}
+
+/// Gives the CSn2:0 value which selects a division factor of 64 for the timer.
+fn default_clock_select(timer: PwmTimer) -> u8 {
+    match timer {
+        PwmTimer::Timer8(TimerNo8::Timer2) => 0b100,
+        _ => 0b011,
+    }
+}
+
+impl PwmChannel {
+    /// Creates the PWM channel of a digital pin.
+    /// The timer is not touched until `enable` is called.
+    /// # Arguments
+    /// * `pin` - a u32, the digital pin number (2 to 13 and 44 to 46).
+    /// # Returns
+    /// * `a Option<PwmChannel>` - None if the pin cannot generate PWM.
+    pub fn new(pin: u32) -> Option<PwmChannel> {
+        let (timer, channel) = pin_map(pin)?;
+        let mode = match timer {
+            PwmTimer::Timer8(TimerNo8::Timer0) => PwmMode::FastPwm,
+            _ => PwmMode::PhaseCorrect,
+        };
+        Some(PwmChannel {
+            timer,
+            channel,
+            pin,
+            mode,
+        })
+    }
+
+    /// Starts the timer in the given mode and connects the output compare unit
+    /// to the pin in non-inverting mode.
+    /// If the timer was stopped it is started with a prescaler of 64.
+    /// Timer0 stays in Fast PWM mode once `time::init` has been called, whatever the mode.
+    /// # Arguments
+    /// * `mode` - a `PwmMode` object, the waveform generation mode to be used.
+    pub fn enable(&mut self, mode: PwmMode) {
+        self.mode = match self.timer {
+            PwmTimer::Timer8(TimerNo8::Timer0) if time::is_running() => PwmMode::FastPwm,
+            _ => mode,
+        };
+        let mode = self.mode;
+        let power = unsafe { Power::new() };
+        match self.timer {
+            PwmTimer::Timer8(no) => {
+                match no {
+                    TimerNo8::Timer0 => power.enable_clocks(Peripherals::TIMER0),
+                    TimerNo8::Timer2 => power.enable_clocks(Peripherals::TIMER2),
+                }
+                let timer = Timer8::new(no);
+                // WGM2:0 is 011 for Fast PWM and 001 for Phase Correct PWM with TOP = 0xFF.
+                let wgm = match mode {
+                    PwmMode::FastPwm => 0b11,
+                    PwmMode::PhaseCorrect => 0b01,
+                };
+                timer.tccra.update(|tccra| {
+                    tccra.set_bits(0..2, wgm);
+                });
+                timer.tccrb.update(|tccrb| {
+                    tccrb.set_bit(3, false);
+                });
+                if timer.tccrb.read().get_bits(0..3) == 0 {
+                    let cs = default_clock_select(self.timer);
+                    timer.tccrb.update(|tccrb| {
+                        tccrb.set_bits(0..3, cs);
+                    });
+                }
+            }
+            PwmTimer::Timer16(no) => {
+                match no {
+                    TimerNo16::Timer1 => power.enable_clocks(Peripherals::TIMER1),
+                    TimerNo16::Timer3 => power.enable_clocks(Peripherals::TIMER3),
+                    TimerNo16::Timer4 => power.enable_clocks(Peripherals::TIMER4),
+                    TimerNo16::Timer5 => power.enable_clocks(Peripherals::TIMER5),
+                }
+                let timer = Timer16::new(no);
+                // WGMn3:0 is 1110 for Fast PWM and 1010 for Phase Correct PWM with TOP = ICRn.
+                let wgm = match mode {
+                    PwmMode::FastPwm => 0b11,
+                    PwmMode::PhaseCorrect => 0b10,
+                };
+                timer.tccra.update(|tccra| {
+                    tccra.set_bits(0..2, 0b10);
+                });
+                timer.tccrb.update(|tccrb| {
+                    tccrb.set_bits(3..5, wgm);
+                });
+                if timer.tccrb.read().get_bits(0..3) == 0 {
+                    timer.set_input_capture(0xFF);
+                    let cs = default_clock_select(self.timer);
+                    timer.tccrb.update(|tccrb| {
+                        tccrb.set_bits(0..3, cs);
+                    });
+                }
+            }
+        }
+        self.connect(true);
+        make_pin(self.pin).set_output();
+    }
+
+    /// Disconnects the output compare unit from the pin, which then works as
+    /// a normal digital pin again. The timer keeps running for the other channels.
+    pub fn disable(&mut self) {
+        self.connect(false);
+    }
+
+    /// Sets or clears the COMnx1 bit of the channel, COMnx0 is always kept clear.
+    fn connect(&mut self, connect: bool) {
+        let bit = match self.channel {
+            Channel::A => 6..8,
+            Channel::B => 4..6,
+            Channel::C => 2..4,
+        };
+        let com = if connect { 0b10 } else { 0b00 };
+        match self.timer {
+            PwmTimer::Timer8(no) => Timer8::new(no).tccra.update(|tccra| {
+                tccra.set_bits(bit, com);
+            }),
+            PwmTimer::Timer16(no) => Timer16::new(no).tccra.update(|tccra| {
+                tccra.set_bits(bit, com);
+            }),
+        }
+    }
+
+    /// Gives the largest duty value, which keeps the output always high.
+    /// # Returns
+    /// * `a u16` - 255 for the 8 bit timers and the value of ICRn for the 16 bit timers.
+    pub fn max_duty(&self) -> u16 {
+        match self.timer {
+            PwmTimer::Timer8(_) => 0xFF,
+            PwmTimer::Timer16(no) => Timer16::new(no).input_capture(),
+        }
+    }
+
+    /// Sets the duty cycle of the output.
+    /// # Arguments
+    /// * `duty` - a u16, from 0 to `max_duty()`, larger values are clamped.
+    pub fn set_duty(&mut self, duty: u16) {
+        let max = self.max_duty();
+        let duty = if duty > max { max } else { duty };
+        match self.timer {
+            PwmTimer::Timer8(no) => {
+                let timer = Timer8::new(no);
+                match self.channel {
+                    Channel::A => timer.ocra.write(duty as u8),
+                    _ => timer.ocrb.write(duty as u8),
+                }
+            }
+            PwmTimer::Timer16(no) => {
+                let timer = Timer16::new(no);
+                match self.channel {
+                    Channel::A => timer.set_compare_a(duty),
+                    Channel::B => timer.set_compare_b(duty),
+                    Channel::C => timer.set_compare_c(duty),
+                }
+            }
+        }
+    }
+
+    /// Changes the frequency of the timer driving this channel.
+    /// For the 16 bit timers the smallest prescaler which can reach the frequency
+    /// is used to get the best resolution, and TOP is changed, so the duty should
+    /// be set again afterwards. For the 8 bit timers only the prescaler can be
+    /// changed, so the nearest available frequency is chosen.
+    /// # Arguments
+    /// * `frequency` - a u32, the required frequency in hertz.
+    /// # Returns
+    /// * `a u32` - the frequency actually generated in hertz, which is left at 980 Hz for
+    ///   Timer0 once `time::init` has been called.
+    pub fn set_frequency(&mut self, frequency: u32) -> u32 {
+        if let PwmTimer::Timer8(TimerNo8::Timer0) = self.timer {
+            if time::is_running() {
+                // The prescaler of 64 set by `time::init` in Fast PWM mode.
+                return CPU_FREQUENCY_HZ / (64 * 256);
+            }
+        }
+        // Fast PWM: f = F_CPU / (N * (1 + TOP)), Phase Correct: f = F_CPU / (2 * N * TOP).
+        let slopes = match self.mode {
+            PwmMode::FastPwm => 1,
+            PwmMode::PhaseCorrect => 2,
+        };
+        let frequency = if frequency == 0 {
+            1
+        } else if frequency > CPU_FREQUENCY_HZ / 8 {
+            CPU_FREQUENCY_HZ / 8
+        } else {
+            frequency
+        };
+        let table = prescalers(self.timer);
+        match self.timer {
+            PwmTimer::Timer8(no) => {
+                let counts = match self.mode {
+                    PwmMode::FastPwm => 256,
+                    PwmMode::PhaseCorrect => 510,
+                };
+                let mut best = 0;
+                let mut best_error = u32::MAX;
+                for (i, n) in table.iter().enumerate() {
+                    let actual = CPU_FREQUENCY_HZ / (n * counts);
+                    let error = if actual > frequency {
+                        actual - frequency
+                    } else {
+                        frequency - actual
+                    };
+                    if error < best_error {
+                        best = i;
+                        best_error = error;
+                    }
+                }
+                Timer8::new(no).tccrb.update(|tccrb| {
+                    tccrb.set_bits(0..3, best as u8 + 1);
+                });
+                CPU_FREQUENCY_HZ / (table[best] * counts)
+            }
+            PwmTimer::Timer16(no) => {
+                let mut index = table.len() - 1;
+                for (i, n) in table.iter().enumerate() {
+                    if CPU_FREQUENCY_HZ / (slopes * n * frequency) <= 0xFFFF {
+                        index = i;
+                        break;
+                    }
+                }
+                let n = table[index];
+                let mut top = CPU_FREQUENCY_HZ / (slopes * n * frequency);
+                if self.mode == PwmMode::FastPwm && top > 0 {
+                    top -= 1;
+                }
+                if top > 0xFFFF {
+                    top = 0xFFFF;
+                }
+                if top < 3 {
+                    top = 3;
+                }
+                let timer = Timer16::new(no);
+                timer.set_input_capture(top as u16);
+                timer.tccrb.update(|tccrb| {
+                    tccrb.set_bits(0..3, index as u8 + 1);
+                });
+                match self.mode {
+                    PwmMode::FastPwm => CPU_FREQUENCY_HZ / (n * (top + 1)),
+                    PwmMode::PhaseCorrect => CPU_FREQUENCY_HZ / (2 * n * top),
+                }
+            }
+        }
+    }
+}
+
+/// Writes an analog value (PWM wave) to a pin in the same way as `analogWrite` of Arduino.
+/// A duty of 0 keeps the pin low and 255 keeps it high without any glitches.
+/// Pins which cannot generate PWM are set high for values above 127 and low otherwise.
+/// # Arguments
+/// * `pin` - a u32, the digital pin number (2 to 13 and 44 to 46 for PWM).
+/// * `duty` - a u8, the duty cycle from 0 (always low) to 255 (always high).
+pub fn analog_write(pin: u32, duty: u8) {
+    let mut digital = DigitalPin {
+        pin: make_pin(pin),
+        pinno: pin,
+    };
+    digital.set_output();
+    match PwmChannel::new(pin) {
+        Some(mut pwm) if duty != 0 && duty != 0xFF => {
+            pwm.enable(pwm.mode);
+            pwm.set_duty(duty as u16);
+        }
+        Some(mut pwm) => {
+            pwm.disable();
+            if duty == 0 {
+                digital.low();
+            } else {
+                digital.high();
+            }
+        }
+        None => {
+            if duty < 128 {
+                digital.low();
+            } else {
+                digital.high();
+            }
+        }
+    }
+}
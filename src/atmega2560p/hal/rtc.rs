@@ -0,0 +1,177 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Real time clock of ATMEGA2560P on Timer2, clocked asynchronously from a 32.768 kHz
+//! watch crystal on the TOSC1 and TOSC2 pins (PG4 and PG3), which are not connected to
+//! the headers of the Arduino Mega.
+//! With a prescaler of 128 Timer2 overflows once per second, and its overflow interrupt
+//! counts the seconds. Timer2 keeps running in the power save mode, so the chip can sleep
+//! between the seconds and be woken up by an alarm while drawing only a few microamperes.
+//! `tone` and the PWM outputs on pins 9 and 10 use Timer2 and can not be used with it.
+//! See the section 20.9 of ATMEGA2560P datasheet.
+
+// Source codes required.
+use crate::atmega2560p::hal::interrupts;
+use crate::atmega2560p::hal::low_power::{LowPower, WakeSource};
+use crate::atmega2560p::hal::sleep_mode::SleepMode;
+use crate::atmega2560p::registers::{ASSR, PRR0, TCCR2A, TCCR2B, TCNT2, TIFR2, TIMSK2};
+use crate::sync::AtomicFlag;
+
+// Seconds counted by the overflow interrupt.
+static mut SECONDS: u32 = 0;
+// Second at which the alarm goes off, and the function it calls.
+static mut ALARM: Option<u32> = None;
+static mut ALARM_HANDLER: Option<fn()> = None;
+// Set by the interrupt when the alarm has gone off.
+static ALARM_FIRED: AtomicFlag = AtomicFlag::new(false);
+
+/// Structure to control the real time clock.
+/// The registers are reached through the register map, so the structure holds nothing.
+pub struct Rtc {
+    _private: (),
+}
+
+impl Rtc {
+    /// Switches Timer2 to the crystal and starts counting the seconds from 0.
+    /// The crystal takes about a second to oscillate steadily after power up.
+    /// The global interrupts must be enabled for the seconds to be counted.
+    /// # Returns
+    /// * `a Rtc object` - which will be used for further implementations.
+    pub fn new() -> Rtc {
+        PRR0.clear(PRR0::PRTIM2);
+        // The sequence of section 20.9, as the registers may be corrupted while
+        // the clock of Timer2 is switched.
+        TIMSK2.write(0);
+        ASSR.set(ASSR::AS2);
+        TCNT2.write(0);
+        TCCR2A.write(0);
+        // CS22:0 = 101 for a prescaler of 128.
+        TCCR2B.write(0b101);
+        Self::wait_update();
+        TIFR2.write(TIFR2::TOV2.mask() as u8);
+        interrupts::without_interrupts(|| unsafe {
+            SECONDS = 0;
+            ALARM = None;
+        });
+        ALARM_FIRED.clear();
+        TIMSK2.set(TIMSK2::TOIE2);
+        Rtc { _private: () }
+    }
+
+    /// Gives the number of seconds counted.
+    /// # Returns
+    /// * `a u32` - the number of seconds.
+    pub fn seconds(&self) -> u32 {
+        interrupts::without_interrupts(|| unsafe { SECONDS })
+    }
+
+    /// Sets the number of seconds counted, and starts the current second again.
+    /// # Arguments
+    /// * `seconds` - a u32, the new number of seconds.
+    pub fn set_seconds(&mut self, seconds: u32) {
+        interrupts::without_interrupts(|| unsafe {
+            TCNT2.write(0);
+            SECONDS = seconds;
+        });
+        Self::wait_update();
+    }
+
+    /// Sets an alarm which goes off when the given number of seconds is reached,
+    /// replacing the former one.
+    /// # Arguments
+    /// * `at` - a u32, the number of seconds at which the alarm goes off.
+    /// * `handler` - a function, which is called from the interrupt service routine.
+    pub fn set_alarm(&mut self, at: u32, handler: fn()) {
+        interrupts::without_interrupts(|| unsafe {
+            ALARM = Some(at);
+            ALARM_HANDLER = Some(handler);
+        });
+        ALARM_FIRED.clear();
+    }
+
+    /// Sets an alarm which goes off after the given number of seconds.
+    /// # Arguments
+    /// * `seconds` - a u32, the number of seconds from now.
+    /// * `handler` - a function, which is called from the interrupt service routine.
+    pub fn set_alarm_in(&mut self, seconds: u32, handler: fn()) {
+        let at = self.seconds().wrapping_add(seconds);
+        self.set_alarm(at, handler);
+    }
+
+    /// Cancels the alarm, if it has not gone off yet.
+    pub fn cancel_alarm(&mut self) {
+        interrupts::without_interrupts(|| unsafe {
+            ALARM = None;
+        });
+    }
+
+    /// Checks whether the alarm has gone off since the last call.
+    /// # Returns
+    /// * `a boolean` - which is true if the alarm has gone off.
+    pub fn alarm_fired(&mut self) -> bool {
+        ALARM_FIRED.take()
+    }
+
+    /// Sleeps in the power save mode until the alarm goes off, waking up briefly on
+    /// every second. It returns at once if no alarm is set.
+    /// # Arguments
+    /// * `low_power` - a `LowPower` object, which gives the peripherals kept during sleep.
+    pub fn sleep_until_alarm(&mut self, low_power: &mut LowPower) {
+        loop {
+            if ALARM_FIRED.take() {
+                return;
+            }
+            if interrupts::without_interrupts(|| unsafe { ALARM.is_none() }) {
+                return;
+            }
+            let _ = low_power.sleep(SleepMode::PS, WakeSource::Timer2);
+        }
+    }
+
+    /// Stops the clock and gives Timer2 back to the system clock.
+    pub fn stop(&mut self) {
+        TIMSK2.clear(TIMSK2::TOIE2);
+        TCCR2B.write(0);
+        ASSR.clear(ASSR::AS2);
+    }
+
+    // Waits until the registers written are transferred to the clock domain of the crystal.
+    fn wait_update() {
+        while ASSR.is_set(ASSR::TCN2UB) || ASSR.is_set(ASSR::TCR2AUB) || ASSR.is_set(ASSR::TCR2BUB)
+        {
+        }
+    }
+}
+
+impl Default for Rtc {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Timer/Counter2 Overflow, once per second.
+#[cfg(target_arch = "avr")]
+#[no_mangle]
+pub unsafe extern "avr-interrupt" fn __vector_15() {
+    SECONDS = SECONDS.wrapping_add(1);
+    if ALARM == Some(SECONDS) {
+        ALARM = None;
+        ALARM_FIRED.set();
+        if let Some(handler) = ALARM_HANDLER {
+            handler();
+        }
+    }
+}
@@ -0,0 +1,153 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Square wave generation on any digital pin of ATMEGA2560P, like `tone()` and `noTone()` of Arduino.
+//! Timer2 runs in CTC mode and its compare match interrupt toggles the pin, so only one
+//! tone can be played at a time, and PWM on pins 9 and 10 does not work while it plays.
+//! Refer to section 20 of ATMEGA2560P datasheet.
+
+// Source codes required.
+use crate::atmega2560p::hal::analog::{Timer8, TimerNo8};
+use crate::atmega2560p::hal::interrupts::without_interrupts;
+use crate::atmega2560p::hal::pin::make_pin;
+use crate::atmega2560p::hal::port::Port;
+use crate::atmega2560p::hal::power::{Peripherals, Power};
+use crate::config::CPU_FREQUENCY_HZ;
+
+// Crates to be used for the implementation.
+use bit_field::BitField;
+use core::ptr::{read_volatile, write_volatile};
+
+// Timer/Counter2 Interrupt Mask Register and its compare match A interrupt enable bit.
+const TIMSK2: *mut u8 = 0x70 as *mut u8;
+const OCIE2A: u8 = 1;
+
+// Prescalers of Timer2 with the value of the CS22:0 bits for each.
+const PRESCALERS: [(u32, u8); 7] = [
+    (1, 1),
+    (8, 2),
+    (32, 3),
+    (64, 4),
+    (128, 5),
+    (256, 6),
+    (1024, 7),
+];
+
+// Port and bit of the pin playing the tone, and the number of toggles left,
+// which is negative if the tone plays until `no_tone` is called.
+static mut TONE_PORT: *mut Port = 0 as *mut Port;
+static mut TONE_MASK: u8 = 0;
+static mut TONE_TOGGLES: i32 = 0;
+
+/// Plays a square wave with 50% duty cycle on a pin.
+/// A tone which is already playing is replaced by the new one.
+/// Global interrupts must be enabled for the pin to toggle.
+/// # Arguments
+/// * `pin` - a u32, the digital pin number.
+/// * `frequency` - a u32, the frequency in Hz, from 31 Hz upwards.
+/// * `duration` - a u32, the duration in milliseconds, 0 to play until `no_tone` is called.
+pub fn tone(pin: u32, frequency: u32, duration: u32) {
+    if frequency == 0 {
+        no_tone(pin);
+        return;
+    }
+
+    // The pin is toggled twice per period, OCR2A = f_cpu / (2 * N * f) - 1.
+    let mut setting = PRESCALERS[6].1;
+    let mut ocr = 0xFF;
+    for (prescaler, cs) in PRESCALERS.iter() {
+        let top = CPU_FREQUENCY_HZ / (2 * prescaler * frequency);
+        if top >= 1 && top - 1 <= 0xFF {
+            setting = *cs;
+            ocr = (top - 1) as u8;
+            break;
+        }
+    }
+    let toggles = if duration == 0 {
+        -1
+    } else {
+        (2 * frequency * (duration / 1000) + 2 * frequency * (duration % 1000) / 1000) as i32
+    };
+
+    let mut digital = make_pin(pin);
+    digital.set_output();
+
+    unsafe {
+        Power::new().enable_clocks(Peripherals::TIMER2);
+    }
+    without_interrupts(|| unsafe {
+        // The previous pin is left low.
+        if !TONE_PORT.is_null() {
+            let port = read_volatile(&(*TONE_PORT).port);
+            write_volatile(&mut (*TONE_PORT).port, port & !TONE_MASK);
+        }
+        TONE_PORT = digital.port;
+        TONE_MASK = 1 << digital.pin;
+        TONE_TOGGLES = toggles;
+
+        let timer = Timer8::new(TimerNo8::Timer2);
+        // WGM22:0 = 010 for CTC mode, with the output compare pins disconnected.
+        timer.tccra.write(0b10);
+        timer.tccrb.write(setting);
+        timer.ocra.write(ocr);
+        timer.tcnt.write(0);
+
+        let mut timsk = read_volatile(TIMSK2);
+        timsk.set_bit(OCIE2A, true);
+        write_volatile(TIMSK2, timsk);
+    });
+}
+
+/// Stops the tone on a pin and leaves the pin low.
+/// # Arguments
+/// * `pin` - a u32, the digital pin number.
+pub fn no_tone(pin: u32) {
+    let digital = make_pin(pin);
+    without_interrupts(|| unsafe {
+        if TONE_PORT == digital.port && TONE_MASK == 1 << digital.pin {
+            stop();
+        }
+    });
+}
+
+/// Stops Timer2 and its interrupt and drives the pin low.
+unsafe fn stop() {
+    let mut timsk = read_volatile(TIMSK2);
+    timsk.set_bit(OCIE2A, false);
+    write_volatile(TIMSK2, timsk);
+    Timer8::new(TimerNo8::Timer2).tccrb.write(0);
+
+    if !TONE_PORT.is_null() {
+        let port = read_volatile(&(*TONE_PORT).port);
+        write_volatile(&mut (*TONE_PORT).port, port & !TONE_MASK);
+    }
+    TONE_PORT = 0 as *mut Port;
+}
+
+/// Timer/Counter2 Compare Match A, toggles the pin until the toggles run out.
+#[cfg(target_arch = "avr")]
+#[no_mangle]
+pub unsafe extern "avr-interrupt" fn __vector_13() {
+    if TONE_TOGGLES == 0 {
+        stop();
+        return;
+    }
+    // Writing a logic one to PINxn toggles PORTxn.
+    write_volatile(&mut (*TONE_PORT).pin, TONE_MASK);
+    if TONE_TOGGLES > 0 {
+        TONE_TOGGLES -= 1;
+    }
+}
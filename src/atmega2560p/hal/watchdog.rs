@@ -16,20 +16,20 @@
 
 //! Control on Watchdog timer in ATMEGA2560P
 //! Section 12.5 and 28.6 of manual
+//! The timer can reset the chip, or call an interrupt which wakes the chip up from sleep.
 
 // Crates required in the code for reading and writing to registers.
 use crate::atmega2560p::hal::interrupts;
-use core::ptr::{read_volatile, write_volatile};
+use crate::atmega2560p::registers::{MCUSR, WDTCSR};
+use crate::sync;
 
 /// Use interrupts to enable/disable global interrupts,
 /// prior to disabling watchdog, all interrupts must be disabled.
 /// A new struct of WatchDog can be created through new() function.
 /// Watchdog can be disabled by disable() function.
-#[repr(C, packed)]
+/// MCUSR and WDTCSR are reached through the register map, so the structure holds nothing.
 pub struct WatchDog {
-    pub mcusr: u8,
-    _pad: [u8; 11],
-    pub wdtcsr: u8,
+    _private: (),
 }
 
 impl WatchDog {
@@ -37,34 +37,138 @@ impl WatchDog {
     /// # Returns
     /// * `a reference to Watchdog structure` - for further implementations.
     pub unsafe fn new() -> &'static mut WatchDog {
-        &mut *(0x54 as *mut WatchDog)
+        &mut *(MCUSR.address() as *mut WatchDog)
     }
 
     /// This function disables WatchDog.
     /// Reset watchdog to stop its functioning at end of timer
+    /// Global interrupts are disabled meanwhile and left as they were before the call.
     pub fn disable(&mut self) {
-        unsafe {
-            // Disable global interrupts.
-            interrupts::Interrupt::disable(&mut interrupts::Interrupt::new());
-        }
-        // Clears WDRF in MCUSR.
+        sync::free(|_| {
+            // Clears WDRF in MCUSR.
+            MCUSR.clear(MCUSR::WDRF);
+
+            //Sets WDCE for changing WDE.
+            WDTCSR.modify(|wdtcsr| wdtcsr | (WDTCSR::WDCE.mask() | WDTCSR::WDE.mask()) as u8);
+            //Sets every bit to 0 including WDE and WDIE.
+            WDTCSR.write(0x00);
+        })
+    }
+}
+
+/// Selection of the action taken when the watchdog timer times out.
+/// * `Interrupt` - the watchdog interrupt is called, for example to wake up from sleep.
+/// * `Reset` - the chip is reset.
+/// * `InterruptReset` - the interrupt is called first and the chip is reset on the next
+///   time-out, unless the interrupt is enabled again with `enable_interrupt()`.
+#[derive(Clone, Copy)]
+pub enum WatchdogMode {
+    Interrupt,
+    Reset,
+    InterruptReset,
+}
 
-        let mut mcusr = unsafe { read_volatile(&self.mcusr) };
-        mcusr &= !(1 << 3);
-        unsafe {
-            write_volatile(&mut self.mcusr, mcusr);
+/// Selection of the time-out of the watchdog timer, from the prescaler of the
+/// 128 kHz watchdog oscillator.
+#[derive(Clone, Copy)]
+pub enum WatchdogPrescaler {
+    Ms16,
+    Ms32,
+    Ms64,
+    Ms125,
+    Ms250,
+    Ms500,
+    S1,
+    S2,
+    S4,
+    S8,
+}
+
+impl WatchdogPrescaler {
+    /// Gives the value of the WDP3:0 bits, with WDP3 at bit 5 of WDTCSR.
+    fn bits(&self) -> u8 {
+        let wdp = *self as u8;
+        (wdp & 0x08) << 2 | (wdp & 0x07)
+    }
+
+    /// Gives the approximate time-out in milliseconds.
+    /// # Returns
+    /// * `a u32` - the time-out, which may differ by about 10% with the voltage and temperature.
+    pub fn millis(&self) -> u32 {
+        match self {
+            WatchdogPrescaler::Ms16 => 16,
+            WatchdogPrescaler::Ms32 => 32,
+            WatchdogPrescaler::Ms64 => 64,
+            WatchdogPrescaler::Ms125 => 125,
+            WatchdogPrescaler::Ms250 => 250,
+            WatchdogPrescaler::Ms500 => 500,
+            WatchdogPrescaler::S1 => 1000,
+            WatchdogPrescaler::S2 => 2000,
+            WatchdogPrescaler::S4 => 4000,
+            WatchdogPrescaler::S8 => 8000,
         }
+    }
+}
 
-        let mut wdtcsr = unsafe { read_volatile(&self.wdtcsr) };
-        wdtcsr |= (1 << 4) | (1 << 3);
-        //Sets WDCE for changing WDE.
-        unsafe {
-            write_volatile(&mut self.wdtcsr, wdtcsr);
-            //Sets every bit to 0 including WDE and WDIE.
-            write_volatile(&mut self.wdtcsr, 0x00);
+// Function called from the watchdog interrupt.
+static mut WATCHDOG_HANDLER: Option<fn()> = None;
 
-            //Enables globalinterrupts again.
-            interrupts::Interrupt::enable(&mut interrupts::Interrupt::new());
+impl WatchDog {
+    /// Starts the watchdog timer in the given mode.
+    /// # Arguments
+    /// * `mode` - a `WatchdogMode` object, the action taken on time-out.
+    /// * `prescaler` - a `WatchdogPrescaler` object, the time-out of the timer.
+    pub fn enable(&mut self, mode: WatchdogMode, prescaler: WatchdogPrescaler) {
+        let mut wdtcsr = prescaler.bits() | WDTCSR::WDIF.mask() as u8;
+        match mode {
+            WatchdogMode::Interrupt => wdtcsr |= WDTCSR::WDIE.mask() as u8,
+            WatchdogMode::Reset => wdtcsr |= WDTCSR::WDE.mask() as u8,
+            WatchdogMode::InterruptReset => {
+                wdtcsr |= (WDTCSR::WDIE.mask() | WDTCSR::WDE.mask()) as u8
+            }
         }
+        interrupts::without_interrupts(|| unsafe {
+            llvm_asm!("wdr" :::: "volatile");
+            if let WatchdogMode::Interrupt = mode {
+                // WDE is forced to one as long as WDRF is set.
+                MCUSR.clear(MCUSR::WDRF);
+            }
+            // The new value must be written within four cycles of setting WDCE.
+            llvm_asm!("sts 0x60, $0
+                       sts 0x60, $1"
+                     :
+                     : "r"((WDTCSR::WDCE.mask() | WDTCSR::WDE.mask()) as u8), "r"(wdtcsr)
+                     :
+                     : "volatile");
+        });
+    }
+
+    /// Restarts the watchdog timer, which must be done before each time-out in the reset mode.
+    pub fn feed(&mut self) {
+        unsafe { llvm_asm!("wdr" :::: "volatile") }
+    }
+
+    /// Enables the watchdog interrupt again, which is disabled by hardware when it
+    /// is called in the `InterruptReset` mode.
+    pub fn enable_interrupt(&mut self) {
+        WDTCSR.set(WDTCSR::WDIE);
+    }
+
+    /// Sets the function called on every time-out in the interrupt modes.
+    /// # Arguments
+    /// * `handler` - a function, which is called from the interrupt service routine.
+    pub fn on_timeout(&mut self, handler: fn()) {
+        interrupts::without_interrupts(|| unsafe {
+            WATCHDOG_HANDLER = Some(handler);
+        });
+    }
+}
+
+/// Watchdog Time-out Interrupt.
+#[cfg(target_arch = "avr")]
+#[no_mangle]
+pub unsafe extern "avr-interrupt" fn __vector_12() {
+    if let Some(handler) = WATCHDOG_HANDLER {
+        handler();
     }
 }
@@ -0,0 +1,333 @@
+// RustDuino : A generic HAL implementation for Arduino Boards in Rust
+// Copyright (C) 2021 Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Register map of the ATMEGA2560P, section 34 (Register Summary) of the manual.
+//! Each register is a `Register` constant at its data space address, and its fields are
+//! in the module of the same name, as `WDTCSR` and `WDTCSR::WDE`.
+
+// Crates which would be used in the implementation.
+use crate::register::register_map;
+
+register_map! {
+    /// Port A Input Pins Address.
+    PINA: u8 @ 0x20 {}
+    /// Port A Data Direction Register.
+    DDRA: u8 @ 0x21 {}
+    /// Port A Data Register.
+    PORTA: u8 @ 0x22 {}
+    /// Port B Input Pins Address.
+    PINB: u8 @ 0x23 {}
+    /// Port B Data Direction Register.
+    DDRB: u8 @ 0x24 {}
+    /// Port B Data Register.
+    PORTB: u8 @ 0x25 {}
+    /// Port C Input Pins Address.
+    PINC: u8 @ 0x26 {}
+    /// Port C Data Direction Register.
+    DDRC: u8 @ 0x27 {}
+    /// Port C Data Register.
+    PORTC: u8 @ 0x28 {}
+    /// Port D Input Pins Address.
+    PIND: u8 @ 0x29 {}
+    /// Port D Data Direction Register.
+    DDRD: u8 @ 0x2A {}
+    /// Port D Data Register.
+    PORTD: u8 @ 0x2B {}
+    /// Port E Input Pins Address.
+    PINE: u8 @ 0x2C {}
+    /// Port E Data Direction Register.
+    DDRE: u8 @ 0x2D {}
+    /// Port E Data Register.
+    PORTE: u8 @ 0x2E {}
+    /// Port F Input Pins Address.
+    PINF: u8 @ 0x2F {}
+    /// Port F Data Direction Register.
+    DDRF: u8 @ 0x30 {}
+    /// Port F Data Register.
+    PORTF: u8 @ 0x31 {}
+    /// Port G Input Pins Address.
+    PING: u8 @ 0x32 {}
+    /// Port G Data Direction Register.
+    DDRG: u8 @ 0x33 {}
+    /// Port G Data Register.
+    PORTG: u8 @ 0x34 {}
+    /// Timer/Counter0 Interrupt Flag Register.
+    TIFR0: u8 @ 0x35 { OCF0B: 2, OCF0A: 1, TOV0: 0 }
+    /// Timer/Counter1 Interrupt Flag Register.
+    TIFR1: u8 @ 0x36 { ICF1: 5, OCF1C: 3, OCF1B: 2, OCF1A: 1, TOV1: 0 }
+    /// Timer/Counter2 Interrupt Flag Register.
+    TIFR2: u8 @ 0x37 { OCF2B: 2, OCF2A: 1, TOV2: 0 }
+    /// Timer/Counter3 Interrupt Flag Register.
+    TIFR3: u8 @ 0x38 { ICF3: 5, OCF3C: 3, OCF3B: 2, OCF3A: 1, TOV3: 0 }
+    /// Timer/Counter4 Interrupt Flag Register.
+    TIFR4: u8 @ 0x39 { ICF4: 5, OCF4C: 3, OCF4B: 2, OCF4A: 1, TOV4: 0 }
+    /// Timer/Counter5 Interrupt Flag Register.
+    TIFR5: u8 @ 0x3A { ICF5: 5, OCF5C: 3, OCF5B: 2, OCF5A: 1, TOV5: 0 }
+    /// Pin Change Interrupt Flag Register.
+    PCIFR: u8 @ 0x3B { PCIF2: 2, PCIF1: 1, PCIF0: 0 }
+    /// External Interrupt Flag Register.
+    EIFR: u8 @ 0x3C { INTF: 0..8 }
+    /// External Interrupt Mask Register.
+    EIMSK: u8 @ 0x3D { INT: 0..8 }
+    /// General Purpose I/O Register 0.
+    GPIOR0: u8 @ 0x3E {}
+    /// EEPROM Control Register.
+    EECR: u8 @ 0x3F { EEPM: 4..6, EERIE: 3, EEMPE: 2, EEPE: 1, EERE: 0 }
+    /// EEPROM Data Register.
+    EEDR: u8 @ 0x40 {}
+    /// EEPROM Address Register.
+    EEAR: u16 @ 0x41 {}
+    /// General Timer/Counter Control Register.
+    GTCCR: u8 @ 0x43 { TSM: 7, PSRASY: 1, PSRSYNC: 0 }
+    /// Timer/Counter0 Control Register A.
+    TCCR0A: u8 @ 0x44 { COM0A: 6..8, COM0B: 4..6, WGM0: 0..2 }
+    /// Timer/Counter0 Control Register B.
+    TCCR0B: u8 @ 0x45 { FOC0A: 7, FOC0B: 6, WGM02: 3, CS0: 0..3 }
+    /// Timer/Counter0.
+    TCNT0: u8 @ 0x46 {}
+    /// Output Compare Register 0 A.
+    OCR0A: u8 @ 0x47 {}
+    /// Output Compare Register 0 B.
+    OCR0B: u8 @ 0x48 {}
+    /// General Purpose I/O Register 1.
+    GPIOR1: u8 @ 0x4A {}
+    /// General Purpose I/O Register 2.
+    GPIOR2: u8 @ 0x4B {}
+    /// SPI Control Register.
+    SPCR: u8 @ 0x4C { SPIE: 7, SPE: 6, DORD: 5, MSTR: 4, CPOL: 3, CPHA: 2, SPR: 0..2 }
+    /// SPI Status Register.
+    SPSR: u8 @ 0x4D { SPIF: 7, WCOL: 6, SPI2X: 0 }
+    /// SPI Data Register.
+    SPDR: u8 @ 0x4E {}
+    /// Analog Comparator Control and Status Register.
+    ACSR: u8 @ 0x50 { ACD: 7, ACBG: 6, ACO: 5, ACI: 4, ACIE: 3, ACIC: 2, ACIS: 0..2 }
+    /// Sleep Mode Control Register.
+    SMCR: u8 @ 0x53 { SM: 1..4, SE: 0 }
+    /// MCU Status Register, the source of the last reset.
+    MCUSR: u8 @ 0x54 { JTRF: 4, WDRF: 3, BORF: 2, EXTRF: 1, PORF: 0 }
+    /// MCU Control Register.
+    MCUCR: u8 @ 0x55 { JTD: 7, PUD: 4, IVSEL: 1, IVCE: 0 }
+    /// Store Program Memory Control and Status Register.
+    SPMCSR: u8 @ 0x57 { SPMIE: 7, RWWSB: 6, SIGRD: 5, RWWSRE: 4, BLBSET: 3, PGWRT: 2, PGERS: 1, SPMEN: 0 }
+    /// Extended Z-pointer Register for ELPM/SPM.
+    RAMPZ: u8 @ 0x5B { RAMPZ: 0..2 }
+    /// Extended Indirect Register.
+    EIND: u8 @ 0x5C { EIND0: 0 }
+    /// Stack Pointer.
+    SP: u16 @ 0x5D {}
+    /// Status Register.
+    SREG: u8 @ 0x5F { I: 7, T: 6, H: 5, S: 4, V: 3, N: 2, Z: 1, C: 0 }
+    /// Watchdog Timer Control Register.
+    WDTCSR: u8 @ 0x60 { WDIF: 7, WDIE: 6, WDP3: 5, WDCE: 4, WDE: 3, WDP: 0..3 }
+    /// Clock Prescale Register.
+    CLKPR: u8 @ 0x61 { CLKPCE: 7, CLKPS: 0..4 }
+    /// Power Reduction Register 0.
+    PRR0: u8 @ 0x64 { PRTWI: 7, PRTIM2: 6, PRTIM0: 5, PRTIM1: 3, PRSPI: 2, PRUSART0: 1, PRADC: 0 }
+    /// Power Reduction Register 1.
+    PRR1: u8 @ 0x65 { PRTIM5: 5, PRTIM4: 4, PRTIM3: 3, PRUSART3: 2, PRUSART2: 1, PRUSART1: 0 }
+    /// Oscillator Calibration Register.
+    OSCCAL: u8 @ 0x66 {}
+    /// Pin Change Interrupt Control Register.
+    PCICR: u8 @ 0x68 { PCIE2: 2, PCIE1: 1, PCIE0: 0 }
+    /// External Interrupt Control Register A.
+    EICRA: u8 @ 0x69 { ISC3: 6..8, ISC2: 4..6, ISC1: 2..4, ISC0: 0..2 }
+    /// External Interrupt Control Register B.
+    EICRB: u8 @ 0x6A { ISC7: 6..8, ISC6: 4..6, ISC5: 2..4, ISC4: 0..2 }
+    /// Pin Change Mask Register 0.
+    PCMSK0: u8 @ 0x6B {}
+    /// Pin Change Mask Register 1.
+    PCMSK1: u8 @ 0x6C {}
+    /// Pin Change Mask Register 2.
+    PCMSK2: u8 @ 0x6D {}
+    /// Timer/Counter0 Interrupt Mask Register.
+    TIMSK0: u8 @ 0x6E { OCIE0B: 2, OCIE0A: 1, TOIE0: 0 }
+    /// Timer/Counter1 Interrupt Mask Register.
+    TIMSK1: u8 @ 0x6F { ICIE1: 5, OCIE1C: 3, OCIE1B: 2, OCIE1A: 1, TOIE1: 0 }
+    /// Timer/Counter2 Interrupt Mask Register.
+    TIMSK2: u8 @ 0x70 { OCIE2B: 2, OCIE2A: 1, TOIE2: 0 }
+    /// Timer/Counter3 Interrupt Mask Register.
+    TIMSK3: u8 @ 0x71 { ICIE3: 5, OCIE3C: 3, OCIE3B: 2, OCIE3A: 1, TOIE3: 0 }
+    /// Timer/Counter4 Interrupt Mask Register.
+    TIMSK4: u8 @ 0x72 { ICIE4: 5, OCIE4C: 3, OCIE4B: 2, OCIE4A: 1, TOIE4: 0 }
+    /// Timer/Counter5 Interrupt Mask Register.
+    TIMSK5: u8 @ 0x73 { ICIE5: 5, OCIE5C: 3, OCIE5B: 2, OCIE5A: 1, TOIE5: 0 }
+    /// ADC Data Register.
+    ADC: u16 @ 0x78 {}
+    /// ADC Control and Status Register A.
+    ADCSRA: u8 @ 0x7A { ADEN: 7, ADSC: 6, ADATE: 5, ADIF: 4, ADIE: 3, ADPS: 0..3 }
+    /// ADC Control and Status Register B.
+    ADCSRB: u8 @ 0x7B { ACME: 6, MUX5: 3, ADTS: 0..3 }
+    /// ADC Multiplexer Selection Register.
+    ADMUX: u8 @ 0x7C { REFS: 6..8, ADLAR: 5, MUX: 0..5 }
+    /// Digital Input Disable Register 2.
+    DIDR2: u8 @ 0x7D {}
+    /// Digital Input Disable Register 0.
+    DIDR0: u8 @ 0x7E {}
+    /// Digital Input Disable Register 1.
+    DIDR1: u8 @ 0x7F { AIN1D: 1, AIN0D: 0 }
+    /// Timer/Counter1 Control Register A.
+    TCCR1A: u8 @ 0x80 { COM1A: 6..8, COM1B: 4..6, COM1C: 2..4, WGM1: 0..2 }
+    /// Timer/Counter1 Control Register B.
+    TCCR1B: u8 @ 0x81 { ICNC1: 7, ICES1: 6, WGM13: 4, WGM12: 3, CS1: 0..3 }
+    /// Timer/Counter1 Control Register C.
+    TCCR1C: u8 @ 0x82 { FOC1A: 7, FOC1B: 6, FOC1C: 5 }
+    /// Timer/Counter1.
+    TCNT1: u16 @ 0x84 {}
+    /// Input Capture Register 1.
+    ICR1: u16 @ 0x86 {}
+    /// Output Compare Register 1 A.
+    OCR1A: u16 @ 0x88 {}
+    /// Output Compare Register 1 B.
+    OCR1B: u16 @ 0x8A {}
+    /// Output Compare Register 1 C.
+    OCR1C: u16 @ 0x8C {}
+    /// Timer/Counter3 Control Register A.
+    TCCR3A: u8 @ 0x90 { COM3A: 6..8, COM3B: 4..6, COM3C: 2..4, WGM3: 0..2 }
+    /// Timer/Counter3 Control Register B.
+    TCCR3B: u8 @ 0x91 { ICNC3: 7, ICES3: 6, WGM33: 4, WGM32: 3, CS3: 0..3 }
+    /// Timer/Counter3 Control Register C.
+    TCCR3C: u8 @ 0x92 { FOC3A: 7, FOC3B: 6, FOC3C: 5 }
+    /// Timer/Counter3.
+    TCNT3: u16 @ 0x94 {}
+    /// Input Capture Register 3.
+    ICR3: u16 @ 0x96 {}
+    /// Output Compare Register 3 A.
+    OCR3A: u16 @ 0x98 {}
+    /// Output Compare Register 3 B.
+    OCR3B: u16 @ 0x9A {}
+    /// Output Compare Register 3 C.
+    OCR3C: u16 @ 0x9C {}
+    /// Timer/Counter4 Control Register A.
+    TCCR4A: u8 @ 0xA0 { COM4A: 6..8, COM4B: 4..6, COM4C: 2..4, WGM4: 0..2 }
+    /// Timer/Counter4 Control Register B.
+    TCCR4B: u8 @ 0xA1 { ICNC4: 7, ICES4: 6, WGM43: 4, WGM42: 3, CS4: 0..3 }
+    /// Timer/Counter4 Control Register C.
+    TCCR4C: u8 @ 0xA2 { FOC4A: 7, FOC4B: 6, FOC4C: 5 }
+    /// Timer/Counter4.
+    TCNT4: u16 @ 0xA4 {}
+    /// Input Capture Register 4.
+    ICR4: u16 @ 0xA6 {}
+    /// Output Compare Register 4 A.
+    OCR4A: u16 @ 0xA8 {}
+    /// Output Compare Register 4 B.
+    OCR4B: u16 @ 0xAA {}
+    /// Output Compare Register 4 C.
+    OCR4C: u16 @ 0xAC {}
+    /// Timer/Counter2 Control Register A.
+    TCCR2A: u8 @ 0xB0 { COM2A: 6..8, COM2B: 4..6, WGM2: 0..2 }
+    /// Timer/Counter2 Control Register B.
+    TCCR2B: u8 @ 0xB1 { FOC2A: 7, FOC2B: 6, WGM22: 3, CS2: 0..3 }
+    /// Timer/Counter2.
+    TCNT2: u8 @ 0xB2 {}
+    /// Output Compare Register 2 A.
+    OCR2A: u8 @ 0xB3 {}
+    /// Output Compare Register 2 B.
+    OCR2B: u8 @ 0xB4 {}
+    /// Asynchronous Status Register.
+    ASSR: u8 @ 0xB6 { EXCLK: 6, AS2: 5, TCN2UB: 4, OCR2AUB: 3, OCR2BUB: 2, TCR2AUB: 1, TCR2BUB: 0 }
+    /// TWI Bit Rate Register.
+    TWBR: u8 @ 0xB8 {}
+    /// TWI Status Register.
+    TWSR: u8 @ 0xB9 { TWS: 3..8, TWPS: 0..2 }
+    /// TWI (Slave) Address Register.
+    TWAR: u8 @ 0xBA { TWA: 1..8, TWGCE: 0 }
+    /// TWI Data Register.
+    TWDR: u8 @ 0xBB {}
+    /// TWI Control Register.
+    TWCR: u8 @ 0xBC { TWINT: 7, TWEA: 6, TWSTA: 5, TWSTO: 4, TWWC: 3, TWEN: 2, TWIE: 0 }
+    /// TWI (Slave) Address Mask Register.
+    TWAMR: u8 @ 0xBD { TWAM: 1..8 }
+    /// USART0 Control and Status Register A.
+    UCSR0A: u8 @ 0xC0 { RXC0: 7, TXC0: 6, UDRE0: 5, FE0: 4, DOR0: 3, UPE0: 2, U2X0: 1, MPCM0: 0 }
+    /// USART0 Control and Status Register B.
+    UCSR0B: u8 @ 0xC1 { RXCIE0: 7, TXCIE0: 6, UDRIE0: 5, RXEN0: 4, TXEN0: 3, UCSZ02: 2, RXB80: 1, TXB80: 0 }
+    /// USART0 Control and Status Register C.
+    UCSR0C: u8 @ 0xC2 { UMSEL0: 6..8, UPM0: 4..6, USBS0: 3, UCSZ0: 1..3, UCPOL0: 0 }
+    /// USART0 Baud Rate Register.
+    UBRR0: u16 @ 0xC4 {}
+    /// USART0 I/O Data Register.
+    UDR0: u8 @ 0xC6 {}
+    /// USART1 Control and Status Register A.
+    UCSR1A: u8 @ 0xC8 { RXC1: 7, TXC1: 6, UDRE1: 5, FE1: 4, DOR1: 3, UPE1: 2, U2X1: 1, MPCM1: 0 }
+    /// USART1 Control and Status Register B.
+    UCSR1B: u8 @ 0xC9 { RXCIE1: 7, TXCIE1: 6, UDRIE1: 5, RXEN1: 4, TXEN1: 3, UCSZ12: 2, RXB81: 1, TXB81: 0 }
+    /// USART1 Control and Status Register C.
+    UCSR1C: u8 @ 0xCA { UMSEL1: 6..8, UPM1: 4..6, USBS1: 3, UCSZ1: 1..3, UCPOL1: 0 }
+    /// USART1 Baud Rate Register.
+    UBRR1: u16 @ 0xCC {}
+    /// USART1 I/O Data Register.
+    UDR1: u8 @ 0xCE {}
+    /// USART2 Control and Status Register A.
+    UCSR2A: u8 @ 0xD0 { RXC2: 7, TXC2: 6, UDRE2: 5, FE2: 4, DOR2: 3, UPE2: 2, U2X2: 1, MPCM2: 0 }
+    /// USART2 Control and Status Register B.
+    UCSR2B: u8 @ 0xD1 { RXCIE2: 7, TXCIE2: 6, UDRIE2: 5, RXEN2: 4, TXEN2: 3, UCSZ22: 2, RXB82: 1, TXB82: 0 }
+    /// USART2 Control and Status Register C.
+    UCSR2C: u8 @ 0xD2 { UMSEL2: 6..8, UPM2: 4..6, USBS2: 3, UCSZ2: 1..3, UCPOL2: 0 }
+    /// USART2 Baud Rate Register.
+    UBRR2: u16 @ 0xD4 {}
+    /// USART2 I/O Data Register.
+    UDR2: u8 @ 0xD6 {}
+    /// Port H Input Pins Address.
+    PINH: u8 @ 0x100 {}
+    /// Port H Data Direction Register.
+    DDRH: u8 @ 0x101 {}
+    /// Port H Data Register.
+    PORTH: u8 @ 0x102 {}
+    /// Port J Input Pins Address.
+    PINJ: u8 @ 0x103 {}
+    /// Port J Data Direction Register.
+    DDRJ: u8 @ 0x104 {}
+    /// Port J Data Register.
+    PORTJ: u8 @ 0x105 {}
+    /// Port K Input Pins Address.
+    PINK: u8 @ 0x106 {}
+    /// Port K Data Direction Register.
+    DDRK: u8 @ 0x107 {}
+    /// Port K Data Register.
+    PORTK: u8 @ 0x108 {}
+    /// Port L Input Pins Address.
+    PINL: u8 @ 0x109 {}
+    /// Port L Data Direction Register.
+    DDRL: u8 @ 0x10A {}
+    /// Port L Data Register.
+    PORTL: u8 @ 0x10B {}
+    /// Timer/Counter5 Control Register A.
+    TCCR5A: u8 @ 0x120 { COM5A: 6..8, COM5B: 4..6, COM5C: 2..4, WGM5: 0..2 }
+    /// Timer/Counter5 Control Register B.
+    TCCR5B: u8 @ 0x121 { ICNC5: 7, ICES5: 6, WGM53: 4, WGM52: 3, CS5: 0..3 }
+    /// Timer/Counter5 Control Register C.
+    TCCR5C: u8 @ 0x122 { FOC5A: 7, FOC5B: 6, FOC5C: 5 }
+    /// Timer/Counter5.
+    TCNT5: u16 @ 0x124 {}
+    /// Input Capture Register 5.
+    ICR5: u16 @ 0x126 {}
+    /// Output Compare Register 5 A.
+    OCR5A: u16 @ 0x128 {}
+    /// Output Compare Register 5 B.
+    OCR5B: u16 @ 0x12A {}
+    /// Output Compare Register 5 C.
+    OCR5C: u16 @ 0x12C {}
+    /// USART3 Control and Status Register A.
+    UCSR3A: u8 @ 0x130 { RXC3: 7, TXC3: 6, UDRE3: 5, FE3: 4, DOR3: 3, UPE3: 2, U2X3: 1, MPCM3: 0 }
+    /// USART3 Control and Status Register B.
+    UCSR3B: u8 @ 0x131 { RXCIE3: 7, TXCIE3: 6, UDRIE3: 5, RXEN3: 4, TXEN3: 3, UCSZ32: 2, RXB83: 1, TXB83: 0 }
+    /// USART3 Control and Status Register C.
+    UCSR3C: u8 @ 0x132 { UMSEL3: 6..8, UPM3: 4..6, USBS3: 3, UCSZ3: 1..3, UCPOL3: 0 }
+    /// USART3 Baud Rate Register.
+    UBRR3: u16 @ 0x134 {}
+    /// USART3 I/O Data Register.
+    UDR3: u8 @ 0x136 {}
+}
@@ -0,0 +1,279 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Bit-banged serial port (8 data bits, no parity, one stop bit) on any digital pins
+//! of ATMEGA328P, for when the USART is already used, up to 38400 baud.
+//! The bits are timed by counting cycles with interrupts disabled, so sending a byte
+//! blocks for its ten bits and nothing is received meanwhile.
+//! Receiving is driven by the pin change interrupt of the RX pin: the start bit calls
+//! the interrupt routine, which samples the byte in the middle of each bit and stores it
+//! in a ring buffer. The routine takes the whole pin change group of the RX pin, and
+//! only one port can receive at a time.
+
+// Source codes required.
+use crate::atmega328p::hal::interrupts::{
+    attach_pin_change, detach_pin_change, digital_pin_to_pcint, without_interrupts, Interrupt,
+};
+use crate::atmega328p::hal::pin::make_pin;
+use crate::collections::RingBuffer;
+use crate::config::CPU_FREQUENCY_HZ;
+use crate::delay::delay;
+use crate::error::Error;
+use crate::print::SerialWrite;
+
+use core::ptr::{read_volatile, write_volatile};
+
+const PCIFR: *mut u8 = 0x3B as *mut u8;
+
+// Approximate cycles spent outside of the delay loops, for each bit sent or received
+// and from the start bit to the start of the interrupt routine.
+const TX_OVERHEAD: u32 = 12;
+const RX_OVERHEAD: u32 = 10;
+const ISR_OVERHEAD: u32 = 80;
+
+/// State of the receiving port, shared with the interrupt service routine.
+struct Receiver {
+    pin: *const u8,
+    mask: u8,
+    group: u8,
+    center_delay: u32,
+    bit_delay: u32,
+    overflow: bool,
+}
+
+static mut RECEIVER: Option<Receiver> = None;
+
+/// Size of the receive buffer, which holds one byte less.
+pub const SOFT_SERIAL_BUFFER_SIZE: usize = 64;
+
+// Bytes received, pushed by the interrupt service routine.
+static RX_BUFFER: RingBuffer<u8, SOFT_SERIAL_BUFFER_SIZE> = RingBuffer::new();
+
+/// Serial port on any two digital pins, with an interface similar to the
+/// `SoftwareSerial` of Arduino.
+/// # Elements
+/// * `rx` - a u8, the digital pin receiving.
+/// * `tx` - a u8, the digital pin transmitting.
+/// * `tx_delay` - a u32, the number of delay loops for each bit sent.
+pub struct SoftSerial {
+    rx: u8,
+    tx: u8,
+    tx_delay: u32,
+}
+
+impl SoftSerial {
+    /// Creates the serial port, which can hold `SOFT_SERIAL_BUFFER_SIZE - 1` received bytes.
+    /// # Arguments
+    /// * `rx` - a u8, the digital pin receiving, which must have a pin change interrupt.
+    /// * `tx` - a u8, the digital pin transmitting.
+    /// # Returns
+    /// * `a Result<SoftSerial, Error>` - the port, or `Error::InvalidParam` if the RX pin
+    ///   has no pin change interrupt.
+    /// # Safety
+    /// Only one `SoftSerial` should exist at a time.
+    pub unsafe fn new(rx: u8, tx: u8) -> Result<SoftSerial, Error> {
+        let (group, bit) = digital_pin_to_pcint(rx).ok_or(Error::InvalidParam)?;
+        let rx_pin = make_pin(rx);
+        without_interrupts(|| {
+            RECEIVER = Some(Receiver {
+                pin: &(*rx_pin.port).pin as *const u8,
+                mask: 1 << bit,
+                group,
+                center_delay: 0,
+                bit_delay: 0,
+                overflow: false,
+            });
+        });
+        Ok(SoftSerial {
+            rx,
+            tx,
+            tx_delay: 0,
+        })
+    }
+
+    /// Sets the pins, starts receiving at the given baud rate and enables global interrupts.
+    /// # Arguments
+    /// * `baud` - a u32, the baud rate, up to 38400.
+    pub fn begin(&mut self, baud: u32) {
+        let bit_cycles = CPU_FREQUENCY_HZ / baud;
+        self.tx_delay = bit_cycles.saturating_sub(TX_OVERHEAD) / 4;
+
+        let mut tx = make_pin(self.tx);
+        tx.high();
+        tx.set_output();
+        // The pull-up keeps the line idle when nothing is connected.
+        let mut rx = make_pin(self.rx);
+        rx.set_input();
+        rx.high();
+
+        let (group, mask) = without_interrupts(|| unsafe {
+            match RECEIVER.as_mut() {
+                Some(receiver) => {
+                    // The first bit is sampled one and a half bits after the start edge.
+                    receiver.center_delay =
+                        (bit_cycles + bit_cycles / 2).saturating_sub(ISR_OVERHEAD) / 4;
+                    receiver.bit_delay = bit_cycles.saturating_sub(RX_OVERHEAD) / 4;
+                    (receiver.group, receiver.mask)
+                }
+                None => unreachable!(),
+            }
+        });
+        let _ = attach_pin_change(group, mask, receive);
+        unsafe { Interrupt::new().enable() };
+    }
+
+    /// Gives the number of bytes received and waiting to be read.
+    /// # Returns
+    /// * `a usize` - the number of bytes in the receive buffer.
+    pub fn available(&mut self) -> usize {
+        RX_BUFFER.len()
+    }
+
+    /// Reads the oldest received byte.
+    /// # Returns
+    /// * `a Option<u8>` - which is None if no byte is available.
+    pub fn read(&mut self) -> Option<u8> {
+        RX_BUFFER.pop()
+    }
+
+    /// Gives the oldest received byte without removing it from the buffer.
+    /// # Returns
+    /// * `a Option<u8>` - which is None if no byte is available.
+    pub fn peek(&mut self) -> Option<u8> {
+        RX_BUFFER.peek()
+    }
+
+    /// Checks whether bytes were dropped because the buffer was full, and clears the flag.
+    pub fn overflow(&mut self) -> bool {
+        without_interrupts(|| unsafe {
+            RECEIVER.as_mut().map_or(false, |r| {
+                let overflow = r.overflow;
+                r.overflow = false;
+                overflow
+            })
+        })
+    }
+
+    /// Discards all the received bytes which have not been read.
+    pub fn clear(&mut self) {
+        RX_BUFFER.clear();
+    }
+
+    /// Transmits a byte, returning once its stop bit has been sent.
+    /// # Arguments
+    /// * `byte` - a u8, the byte to be transmitted.
+    pub fn write_byte(&mut self, byte: u8) {
+        let pin = make_pin(self.tx);
+        let port = unsafe { &mut (*pin.port).port as *mut u8 };
+        let mask = 1u8 << pin.pin;
+        let bit_delay = self.tx_delay;
+
+        without_interrupts(|| unsafe {
+            let high = read_volatile(port) | mask;
+            let low = high & !mask;
+            // Start bit, the data bits from the least significant, and the stop bit.
+            write_volatile(port, low);
+            delay(bit_delay);
+            let mut data = byte;
+            for _ in 0..8 {
+                write_volatile(port, if data & 1 != 0 { high } else { low });
+                data >>= 1;
+                delay(bit_delay);
+            }
+            write_volatile(port, high);
+            delay(bit_delay);
+        });
+    }
+
+    /// Transmits all the bytes of the slice.
+    /// # Arguments
+    /// * `data` - a slice of u8, the bytes to be transmitted.
+    pub fn write(&mut self, data: &[u8]) {
+        for byte in data {
+            self.write_byte(*byte);
+        }
+    }
+
+    /// Transmits a string.
+    /// # Arguments
+    /// * `data` - a string slice, which is to be transmitted.
+    pub fn write_str(&mut self, data: &str) {
+        self.write(data.as_bytes());
+    }
+
+    /// Stops receiving and discards the received bytes.
+    pub fn end(&mut self) {
+        let group = without_interrupts(|| unsafe { RECEIVER.as_ref().map(|r| r.group) });
+        if let Some(group) = group {
+            let _ = detach_pin_change(group);
+        }
+        self.clear();
+    }
+}
+
+/// Receives a byte when the RX pin falls at the start bit, called by the pin change
+/// interrupt with interrupts disabled.
+fn receive() {
+    unsafe {
+        let receiver = match RECEIVER.as_mut() {
+            Some(receiver) => receiver,
+            None => return,
+        };
+        // Only a falling edge of the idle line is a start bit.
+        if read_volatile(receiver.pin) & receiver.mask != 0 {
+            return;
+        }
+        delay(receiver.center_delay);
+        let mut byte = 0u8;
+        for _ in 0..8 {
+            byte >>= 1;
+            if read_volatile(receiver.pin) & receiver.mask != 0 {
+                byte |= 0x80;
+            }
+            delay(receiver.bit_delay);
+        }
+        // Now in the middle of the stop bit.
+        if RX_BUFFER.push(byte).is_err() {
+            receiver.overflow = true;
+        }
+        // The edges of the data bits have set the flag again.
+        write_volatile(PCIFR, 1 << receiver.group);
+    }
+}
+
+impl SerialWrite for SoftSerial {
+    fn write_byte(&mut self, byte: u8) {
+        SoftSerial::write_byte(self, byte);
+    }
+}
+
+impl crate::common::UsartOps for SoftSerial {
+    fn write_byte(&mut self, byte: u8) {
+        SoftSerial::write_byte(self, byte);
+    }
+
+    fn available(&mut self) -> bool {
+        SoftSerial::available(self) > 0
+    }
+
+    fn read_byte(&mut self) -> Option<u8> {
+        self.read()
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        self.write(bytes);
+    }
+}
@@ -61,7 +61,7 @@ impl Usart {
     pub unsafe fn begin(&mut self) {
         self.transmit_enable();
         self.recieve_enable();
-        self.initialize(MODE, BAUD, STOP, SIZE, PARITY);
+        let _ = self.initialize(MODE, BAUD, STOP, SIZE, PARITY);
     }
 
     /// This function can be use to initialize with baud rate and remaining settings will be set to default
@@ -71,12 +71,12 @@ impl Usart {
     pub unsafe fn begin_set_baud(&mut self, baud1: i64) {
         self.transmit_enable();
         self.recieve_enable();
-        self.initialize(MODE, baud1, STOP, SIZE, PARITY);
+        let _ = self.initialize(MODE, baud1, STOP, SIZE, PARITY);
     }
 
     /// This function can be used to stop the functioning of USART.
     pub unsafe fn end(&mut self) {
-        self.transmit_disable();
+        let _ = self.transmit_disable();
         self.recieve_disable();
     }
 }
@@ -90,9 +90,9 @@ impl Usart {
 pub fn println_string(data: &'static str) {
     let u: &mut Usart = unsafe { Usart::new(NUM) };
     u.transmit_enable();
-    u.initialize(MODE, BAUD, STOP, SIZE, PARITY);
-    u.write_string(data);
-    u.transmit_disable();
+    let _ = u.initialize(MODE, BAUD, STOP, SIZE, PARITY);
+    let _ = u.write_string(data);
+    let _ = u.transmit_disable();
 }
 
 /// Main println() function for using USART according to default used values.
@@ -105,9 +105,9 @@ pub fn println_string(data: &'static str) {
 pub fn println_integer(data: u32) {
     let u: &mut Usart = unsafe { Usart::new(NUM) };
     u.transmit_enable();
-    u.initialize(MODE, BAUD, STOP, SIZE, PARITY);
-    u.write_integer(data);
-    u.transmit_disable();
+    let _ = u.initialize(MODE, BAUD, STOP, SIZE, PARITY);
+    let _ = u.write_integer(data);
+    let _ = u.transmit_disable();
 }
 
 /// Main println() function for using USART according to default used values.
@@ -121,9 +121,9 @@ pub fn println_integer(data: u32) {
 pub fn println_float(data: f64, precision: u32) {
     let u: &mut Usart = unsafe { Usart::new(NUM) };
     u.transmit_enable();
-    u.initialize(MODE, BAUD, STOP, SIZE, PARITY);
-    u.write_float(data, precision);
-    u.transmit_disable();
+    let _ = u.initialize(MODE, BAUD, STOP, SIZE, PARITY);
+    let _ = u.write_float(data, precision);
+    let _ = u.transmit_disable();
 }
 
 /// println() function for using USART according to default used values and user defined value of baud rate.
@@ -136,9 +136,9 @@ pub fn println_float(data: f64, precision: u32) {
 pub fn println_set_baud(data: &'static str, baud1: i64) {
     let u: &mut Usart = unsafe { Usart::new(NUM) };
     u.transmit_enable();
-    u.initialize(MODE, baud1, STOP, SIZE, PARITY);
-    u.write_string(data);
-    u.transmit_disable();
+    let _ = u.initialize(MODE, baud1, STOP, SIZE, PARITY);
+    let _ = u.write_string(data);
+    let _ = u.transmit_disable();
 }
 
 /// Main println() function for using USART according to default used values and user defined value of frame.
@@ -158,9 +158,9 @@ pub fn println_set_frame(
 ) {
     let u: &mut Usart = unsafe { Usart::new(NUM) };
     u.transmit_enable();
-    u.initialize(MODE, BAUD, stop1, size1, parity1);
-    u.write_string(data);
-    u.transmit_disable();
+    let _ = u.initialize(MODE, BAUD, stop1, size1, parity1);
+    let _ = u.write_string(data);
+    let _ = u.transmit_disable();
 }
 
 /// Main println() function for using USART according to user defined mode parameters.
@@ -186,7 +186,7 @@ pub fn println_detail(
 ) {
     let u: &mut Usart = unsafe { Usart::new(num1) };
     u.transmit_enable();
-    u.initialize(mode1, baud1, stop1, size1, parity1);
-    u.write_string(data);
-    u.transmit_disable();
+    let _ = u.initialize(mode1, baud1, stop1, size1, parity1);
+    let _ = u.write_string(data);
+    let _ = u.transmit_disable();
 }
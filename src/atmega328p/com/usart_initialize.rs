@@ -22,7 +22,9 @@
 //! See the section 19 of ATMEGA328P datasheet.
 
 // Standard crates to be used
-use crate::delay::delay_ms;
+use crate::atmega328p::com::usart_transmit::TRANSMIT_TIMEOUT_US;
+use crate::delay::wait_until;
+use crate::error::Error;
 use bit_field::BitField;
 use core::ptr::write_volatile;
 use core::{f64, u32, u8};
@@ -32,13 +34,20 @@ use volatile::Volatile;
 use crate::atmega328p::hal::interrupts;
 use crate::atmega328p::hal::port;
 use crate::atmega328p::hal::power;
+use crate::sync::{self, AtomicByte, AtomicFlag};
 
 // Some useful constants regarding bit manipulation for USART.
 // Position of clock mode adjuster (xck) bit.
 const USART0_XCK: u8 = 4;
-// System Clock Crystal Oscillator Frequency in mHz.
-const F_OSC: f64 = 1.0000;
-const MULTIPLY: f64 = 1000000.00;
+
+// Number of calls of `disable` not yet matched by `enable`, and whether global
+// interrupts were enabled before the first of them.
+static DISABLE_DEPTH: AtomicByte = AtomicByte::new(0);
+static WERE_ENABLED: AtomicFlag = AtomicFlag::new(false);
+
+// Baud rate and mode set on each USART, to set them again when the system clock changes.
+static mut BAUD_RATES: [Option<(i64, UsartModes)>; 1] = [None; 1];
+const USARTS: [UsartNum; 1] = [UsartNum::Usart0];
 
 /// Selection of which USART is to be used.
 #[derive(Clone, Copy)]
@@ -118,17 +127,33 @@ impl Usart {
 impl Usart {
     /// disables global interrupts for smooth non-interrupted functioning of USART.
     fn disable(&mut self) {
+        let enabled = sync::interrupts_enabled();
         unsafe {
             // Disable global interrupts.
             interrupts::Interrupt::disable(&mut interrupts::Interrupt::new());
         }
+        if DISABLE_DEPTH.fetch_add(1) == 0 {
+            if enabled {
+                WERE_ENABLED.set();
+            } else {
+                WERE_ENABLED.clear();
+            }
+        }
     }
 
-    ///  re-enables global interrupts.
+    ///  re-enables global interrupts, if they were enabled before the first `disable`
+    ///  which is not yet matched.
     fn enable(&mut self) {
-        unsafe {
-            // Enable global interrupts.
-            interrupts::Interrupt::enable(&mut interrupts::Interrupt::new());
+        let depth = DISABLE_DEPTH.load();
+        if depth == 0 {
+            return;
+        }
+        DISABLE_DEPTH.store(depth - 1);
+        if depth == 1 && WERE_ENABLED.is_set() {
+            unsafe {
+                // Enable global interrupts.
+                interrupts::Interrupt::enable(&mut interrupts::Interrupt::new());
+            }
         }
     }
 
@@ -264,16 +289,25 @@ impl Usart {
         }
     }
 
-    /// Checks for any currently undergoing recieval or transmission in the USART.
+    /// Disables the receiver and the transmitter and gates the clock of the USART to save
+    /// power, `initialize` enables it again.
+    pub fn shutdown(&mut self) {
+        self.ucsrb.update(|ucsrb| {
+            ucsrb.set_bit(4, false);
+            ucsrb.set_bit(3, false);
+        });
+        match self.get_num() {
+            UsartNum::Usart0 => power::Power::disable_clock(power::Peripherals::USART0),
+        }
+    }
+
+    /// Checks that no byte is waiting in the transmit buffer. The receive buffer is not
+    /// waited for, as it is cleared when the USART is set up again.
     /// # Returns
     /// * `a boolean` - Which is false if USART is busy otherwise true.
     fn check_ongoing(&self) -> bool {
         let ucsra = self.ucsra.read();
-        if ucsra.get_bit(6) == true && ucsra.get_bit(7) == false {
-            true
-        } else {
-            false
-        }
+        ucsra.get_bit(5)
     }
 
     /// Clock Generation is one of the initialization steps for the USART.
@@ -286,16 +320,20 @@ impl Usart {
     /// * `mode` - a `UsartModes` object, which defines the mode of USART to use.
     /// * `baud` - a i64, the baud rate of USART the user wants to set.
     fn set_clock(&mut self, baud: i64, mode: UsartModes) {
+        let f_osc = crate::clock::system_clock_hz() as f64;
+        unsafe {
+            BAUD_RATES[self.get_num() as usize] = Some((baud, mode));
+        }
         let ubrr: u32;
         match mode {
             UsartModes::Normasync => {
-                ubrr = (((F_OSC * MULTIPLY) / (16.00 * baud as f64)) - 1.00) as u32;
+                ubrr = ((f_osc / (16.00 * baud as f64)) - 1.00) as u32;
             }
             UsartModes::Douasync => {
-                ubrr = (((F_OSC * MULTIPLY) / (8.00 * baud as f64)) - 1.00) as u32;
+                ubrr = ((f_osc / (8.00 * baud as f64)) - 1.00) as u32;
             }
             UsartModes::Mastersync => {
-                ubrr = (((F_OSC * MULTIPLY) / (2.00 * baud as f64)) - 1.00) as u32;
+                ubrr = ((f_osc / (2.00 * baud as f64)) - 1.00) as u32;
             }
             _ => unreachable!(),
         }
@@ -424,6 +462,8 @@ impl Usart {
     /// * `size` - a `UsartDatSize` object, the size of set of bits to transmit.
     /// * `parity` - a `UsartParity` object, which gives the Parity bit mode for USART.
     /// * `stop` - a `UsartStop` object, which will be used to set the stop bits of data frame.
+    /// # Returns
+    /// * `a Result<(), Error>` - which is `Error::Timeout` if the byte being sent was never taken.
     pub fn initialize(
         &mut self,
         mode: UsartModes,
@@ -431,18 +471,9 @@ impl Usart {
         stop: UsartStop,
         size: UsartDataSize,
         parity: UsartParity,
-    ) {
-        // Check that recieve and transmit buffers are completely cleared
-        // and no transmission or recieve of data is already in process.
-        let mut i: i32 = 10;
-        while self.check_ongoing() == false {
-            if i != 0 {
-                delay_ms(1000);
-                i = i - 1;
-            } else {
-                unreachable!()
-            }
-        }
+    ) -> Result<(), Error> {
+        // Check that no transmission is already in process.
+        wait_until(TRANSMIT_TIMEOUT_US, || self.check_ongoing())?;
 
         self.disable(); //  Disable Global interrupts.
         let num: UsartNum = self.get_num();
@@ -463,5 +494,18 @@ impl Usart {
         self.set_frame(stop, size, parity);
 
         self.enable(); //  Enable Global interrupts.
+        Ok(())
+    }
+}
+
+/// Sets again the baud rate of every USART initialized, from the present frequency of
+/// the system clock. It is called by `crate::clock` after the clock has changed, and
+/// the bytes being transmitted or received meanwhile are lost.
+pub fn clock_changed() {
+    for num in USARTS.iter() {
+        if let Some((baud, mode)) = unsafe { BAUD_RATES[*num as usize] } {
+            let usart = unsafe { Usart::new(*num) };
+            usart.set_clock(baud, mode);
+        }
     }
 }
@@ -18,16 +18,21 @@
 //! This code is written taking into account the features available in ATMEGA328P.
 //! This code implements the Analog Read function to read from the buffer using analog signals.
 //! This code implements the Analog Write function to write into the buffer using analog signals.
+//! This code implements the `Adc` type for single and free running conversions on any channel.
 //! Refer to section 14,15,22 and 23 of ATMEGA328P datasheet.
 
 // Crates to be used for the implementation.
 use bit_field::BitField;
-use core::ptr::write_volatile;
+use core::ptr::{read_volatile, write_volatile};
 use volatile::Volatile;
 
 // Source codes to be used here.
+use crate::__nop;
 use crate::atmega328p::hal::pin::{AnalogPin, DigitalPin};
 use crate::atmega328p::hal::power::Power;
+use crate::atmega328p::hal::pwm::analog_write;
+use crate::delay::delay_ms;
+use crate::error::Error;
 
 /// Selection of reference type for the implementation of Analog Pins.
 #[derive(Clone, Copy)]
@@ -71,33 +76,36 @@ pub struct Analog {
     adcsra: Volatile<u8>,
     adcsrb: Volatile<u8>,
     admux: Volatile<u8>,
+    _pad0: u8,
     didr0: Volatile<u8>,
     didr1: Volatile<u8>,
 }
 
 /// Structure to control the timer of type 8 for Analog Write.
+#[repr(C, packed)]
 pub struct Timer8 {
-    tccra: Volatile<u8>,
-    tccrb: Volatile<u8>,
-    _tcnt: Volatile<u8>,
-    ocra: Volatile<u8>,
-    ocrb: Volatile<u8>,
+    pub(crate) tccra: Volatile<u8>,
+    pub(crate) tccrb: Volatile<u8>,
+    pub(crate) tcnt: Volatile<u8>,
+    pub(crate) ocra: Volatile<u8>,
+    pub(crate) ocrb: Volatile<u8>,
 }
 
 /// Structure to control the timer of type 16 for Analog Write.
+#[repr(C, packed)]
 pub struct Timer16 {
-    tccra: Volatile<u8>,
-    tccrb: Volatile<u8>,
+    pub(crate) tccra: Volatile<u8>,
+    pub(crate) tccrb: Volatile<u8>,
     _tccrc: Volatile<u8>,
     _pad0: u8,
-    _tcntl: Volatile<u8>,
-    _tcnth: Volatile<u8>,
-    _icrl: Volatile<u8>,
-    _icrh: Volatile<u8>,
-    ocral: Volatile<u8>,
-    _ocrah: Volatile<u8>,
-    ocrbl: Volatile<u8>,
-    _ocrbh: Volatile<u8>,
+    pub(crate) tcntl: Volatile<u8>,
+    pub(crate) tcnth: Volatile<u8>,
+    pub(crate) icrl: Volatile<u8>,
+    pub(crate) icrh: Volatile<u8>,
+    pub(crate) ocral: Volatile<u8>,
+    pub(crate) ocrah: Volatile<u8>,
+    pub(crate) ocrbl: Volatile<u8>,
+    pub(crate) ocrbh: Volatile<u8>,
 }
 
 // Structure to control the timer of type 8 for Analog Write.
@@ -127,6 +135,56 @@ impl Timer16 {
             TimerNo16::Timer1 => unsafe { &mut *(0x80 as *mut Timer16) },
         }
     }
+
+    /// Reads the 16 bit counter value, low byte first as required by the TEMP register.
+    /// # Returns
+    /// * `a u16` - the value of TCNTn.
+    pub fn counter(&mut self) -> u16 {
+        let low = self.tcntl.read() as u16;
+        let high = self.tcnth.read() as u16;
+        (high << 8) | low
+    }
+
+    /// Writes the 16 bit counter value, high byte first as required by the TEMP register.
+    /// # Arguments
+    /// * `value` - a u16, the value to be written in TCNTn.
+    pub fn set_counter(&mut self, value: u16) {
+        self.tcnth.write((value >> 8) as u8);
+        self.tcntl.write(value as u8);
+    }
+
+    /// Reads the 16 bit input capture register, low byte first.
+    /// # Returns
+    /// * `a u16` - the value of ICRn.
+    pub fn input_capture(&mut self) -> u16 {
+        let low = self.icrl.read() as u16;
+        let high = self.icrh.read() as u16;
+        (high << 8) | low
+    }
+
+    /// Writes the 16 bit input capture register, which is used as TOP in some modes.
+    /// # Arguments
+    /// * `value` - a u16, the value to be written in ICRn.
+    pub fn set_input_capture(&mut self, value: u16) {
+        self.icrh.write((value >> 8) as u8);
+        self.icrl.write(value as u8);
+    }
+
+    /// Writes the 16 bit output compare register A, high byte first.
+    /// # Arguments
+    /// * `value` - a u16, the value to be written in OCRnA.
+    pub fn set_compare_a(&mut self, value: u16) {
+        self.ocrah.write((value >> 8) as u8);
+        self.ocral.write(value as u8);
+    }
+
+    /// Writes the 16 bit output compare register B, high byte first.
+    /// # Arguments
+    /// * `value` - a u16, the value to be written in OCRnB.
+    pub fn set_compare_b(&mut self, value: u16) {
+        self.ocrbh.write((value >> 8) as u8);
+        self.ocrbl.write(value as u8);
+    }
 }
 
 impl AnalogComparator {
@@ -153,213 +211,18 @@ impl AnalogPin {
     /// # Returns
     /// `a u32` - Value read from the analog pin.
     pub fn read(&mut self) -> u32 {
-        let pin = self.pinno;
-        unsafe {
-            let analog = Analog::new();
-
-            analog.power_adc_disable(); //To enable ADC
-
-            analog.adc_enable();
-
-            analog.adc_auto_trig();
-
-            analog.analog_prescaler(2);
-
-            match pin {
-                0 => {
-                    analog.admux.update(|admux| {
-                        admux.set_bits(0..3, 0b000);
-                    });
-                    analog.didr0.update(|didr0| {
-                        didr0.set_bit(0, true);
-                    });
-                    analog.adcsrb.update(|mux| {
-                        mux.set_bit(3, false);
-                    });
-                }
-                1 => {
-                    analog.admux.update(|admux| {
-                        admux.set_bits(0..3, 0b001);
-                    });
-                    analog.didr0.update(|didr0| {
-                        didr0.set_bit(1, true);
-                    });
-                    analog.adcsrb.update(|mux| {
-                        mux.set_bit(3, false);
-                    });
-                }
-                2 => {
-                    analog.admux.update(|admux| {
-                        admux.set_bits(0..3, 0b010);
-                    });
-                    analog.didr0.update(|didr0| {
-                        didr0.set_bit(2, true);
-                    });
-                    analog.adcsrb.update(|mux| {
-                        mux.set_bit(3, false);
-                    });
-                }
-                3 => {
-                    analog.admux.update(|admux| {
-                        admux.set_bits(0..3, 0b011);
-                    });
-                    analog.didr0.update(|didr0| {
-                        didr0.set_bit(3, true);
-                    });
-                    analog.adcsrb.update(|mux| {
-                        mux.set_bit(3, false);
-                    });
-                }
-                4 => {
-                    analog.admux.update(|admux| {
-                        admux.set_bits(0..3, 0b100);
-                    });
-                    analog.didr0.update(|didr0| {
-                        didr0.set_bit(4, true);
-                    });
-                    analog.adcsrb.update(|mux| {
-                        mux.set_bit(3, false);
-                    });
-                }
-                5 => {
-                    analog.admux.update(|admux| {
-                        admux.set_bits(0..3, 0b101);
-                    });
-                    analog.didr0.update(|didr0| {
-                        didr0.set_bit(5, true);
-                    });
-                    analog.adcsrb.update(|mux| {
-                        mux.set_bit(3, false);
-                    });
-                }
-                6 => {
-                    analog.admux.update(|admux| {
-                        admux.set_bits(0..3, 0b110);
-                    });
-                    analog.didr0.update(|didr0| {
-                        didr0.set_bit(6, true);
-                    });
-                    analog.adcsrb.update(|mux| {
-                        mux.set_bit(3, false);
-                    });
-                }
-                7 => {
-                    analog.admux.update(|admux| {
-                        admux.set_bits(0..3, 0b111);
-                    });
-                    analog.didr0.update(|didr0| {
-                        didr0.set_bit(7, true);
-                    });
-                    analog.adcsrb.update(|mux| {
-                        mux.set_bit(3, false);
-                    });
-                }
-                _ => unreachable!(),
-            }
-
-            analog.adc_con_start();
-
-            // wait 25 ADC cycles
-            let mut a: u32 = 0;
-            a.set_bits(0..8, analog.adcl.read() as u32);
-
-            a.set_bits(8..10, analog.adch.read() as u32); // check logic syntax correctness
-
-            analog.adc_disable();
-
-            a
-        }
+        Adc::new().convert_channel(self.pinno as u8) as u32
     }
 }
 
 impl DigitalPin {
     /// This is used to write a PWM wave to a digital pin.
-    /// Only 2-13 and 44-46 digital pins can be used in this function, other pins will lead to crash.
-    /// All pin except 4 and 13 are set to give output at 490 hertz.
-    /// pin 4 and 13 will give output at 980 hertz.
+    /// Only 3, 5, 6, 9, 10 and 11 digital pins can generate PWM, see `hal::pwm` for details.
+    /// Pins 5 and 6 give output at 980 hertz and the others at 490 hertz.
     /// # Arguments
     /// * `value1` - a u8, value to be written on the analog pin for output.
     pub fn write(&mut self, value1: u8) {
-        self.pin.set_output();
-        let pin1 = self.pinno;
-        match pin1 {
-            5 | 6 => {
-                unsafe {
-                    let pow = Power::new();
-                    write_volatile(&mut pow.prr, pow.prr & (247));
-                }
-                let timer = Timer8::new(TimerNo8::Timer0);
-                timer.tccra.update(|ctrl| {
-                    ctrl.set_bits(0..2, 0b11);
-                });
-                timer.tccrb.update(|ctrl| {
-                    ctrl.set_bits(0..4, 0b0011);
-                });
-
-                if pin1 == 5 {
-                    timer.tccra.update(|ctrl| {
-                        ctrl.set_bits(4..8, 0b0010);
-                    });
-                    timer.ocrb.write(value1);
-                } else {
-                    timer.tccra.update(|ctrl| {
-                        ctrl.set_bits(4..8, 0b1000);
-                    });
-                    timer.ocra.write(value1);
-                }
-            }
-            11 | 3 => {
-                unsafe {
-                    let pow = Power::new();
-                    write_volatile(&mut pow.prr, pow.prr & (247));
-                }
-                let timer = Timer8::new(TimerNo8::Timer2);
-                timer.tccra.update(|ctrl| {
-                    ctrl.set_bits(0..2, 0b01);
-                });
-                timer.tccrb.update(|ctrl| {
-                    ctrl.set_bits(0..4, 0b0100);
-                });
-
-                if pin1 == 11 {
-                    timer.tccra.update(|ctrl| {
-                        ctrl.set_bits(4..8, 0b0010);
-                    });
-                    timer.ocrb.write(value1);
-                } else {
-                    timer.tccra.update(|ctrl| {
-                        ctrl.set_bits(4..8, 0b1000);
-                    });
-                    timer.ocra.write(value1);
-                }
-            }
-            9 | 10 => {
-                unsafe {
-                    let pow = Power::new();
-                    write_volatile(&mut pow.prr, pow.prr & (247));
-                }
-                let timer = Timer16::new(TimerNo16::Timer1);
-                timer.tccra.update(|ctrl| {
-                    ctrl.set_bits(0..2, 0b01);
-                });
-                timer.tccrb.update(|ctrl| {
-                    ctrl.set_bits(0..5, 0b00011);
-                });
-
-                if pin1 == 9 {
-                    timer.tccra.update(|ctrl| {
-                        ctrl.set_bits(4..8, 0b0010);
-                    });
-                    timer.ocrbl.write(value1);
-                } else if pin1 == 10 {
-                    timer.tccra.update(|ctrl| {
-                        ctrl.set_bits(4..8, 0b1000);
-                    });
-                    timer.ocral.write(value1);
-                }
-            }
-            _ => unreachable!(),
-        }
+        analog_write(self.pinno as u8, value1);
     }
 }
 
@@ -479,7 +342,7 @@ pub fn analog_reference(reftype: RefType) {
         }
         RefType::INTERNAL1V1 => {
             analog.admux.update(|admux| {
-                admux.set_bits(6..8, 0b10);
+                admux.set_bits(6..8, 0b11);
             });
         }
         RefType::EXTERNAL => {
@@ -489,3 +352,310 @@ pub fn analog_reference(reftype: RefType) {
         }
     }
 }
+
+/// Selection of the division factor between the system clock and the ADC clock.
+/// The ADC needs a clock between 50 kHz and 200 kHz for the full 10 bit resolution,
+/// so `Div128` should be used at 16 MHz.
+#[derive(Clone, Copy)]
+pub enum AdcPrescaler {
+    Div2,
+    Div4,
+    Div8,
+    Div16,
+    Div32,
+    Div64,
+    Div128,
+}
+
+/// Channel of the internal temperature sensor, see section 23.8 of the datasheet.
+pub const ADC_TEMPERATURE: u8 = 8;
+/// Channel of the internal 1.1V bandgap reference.
+pub const ADC_BANDGAP: u8 = 14;
+/// Channel connected to ground.
+pub const ADC_GND: u8 = 15;
+
+/// Calibration of the internal temperature sensor and bandgap reference, which differ
+/// from one chip to another. The default values are typical ones, which can be off by
+/// about 10 degrees and 10 percent.
+/// The temperature is `(ADC - temperature_offset) / temperature_gain`, as given in section
+/// 23.8.1 of the datasheet, so the offset is found by reading the raw value at a known
+/// temperature. The bandgap voltage is found by measuring Vcc with a multimeter while
+/// reading the raw value of `ADC_BANDGAP`, as `Vcc * ADC / 1024`.
+/// # Elements
+/// * `temperature_offset` - a f32, the raw value read at 0 degree Celsius.
+/// * `temperature_gain` - a f32, the increase of the raw value for each degree.
+/// * `bandgap_millivolts` - a u16, the voltage of the bandgap reference.
+#[derive(Clone, Copy)]
+pub struct SensorCalibration {
+    pub temperature_offset: f32,
+    pub temperature_gain: f32,
+    pub bandgap_millivolts: u16,
+}
+
+impl Default for SensorCalibration {
+    fn default() -> Self {
+        SensorCalibration {
+            temperature_offset: 324.31,
+            temperature_gain: 1.22,
+            bandgap_millivolts: 1100,
+        }
+    }
+}
+
+/// Structure to perform conversions with the Analog to Digital Converter.
+/// Any of the 8 single ended channels (ADC0 to ADC7) or the internal channels
+/// can be converted, either once or continuously in free running mode.
+pub struct Adc {
+    analog: &'static mut Analog,
+    pending: Option<u8>,
+}
+
+impl Adc {
+    /// Powers on and enables the ADC with AVcc as reference and a
+    /// prescaler of 128, which gives a 125 kHz ADC clock at 16 MHz.
+    /// # Returns
+    /// * `a Adc object` - which will be used for further conversions.
+    pub fn new() -> Adc {
+        unsafe {
+            let pow = Power::new();
+            write_volatile(&mut pow.prr, read_volatile(&pow.prr) & !0x01);
+        }
+        let mut adc = Adc {
+            analog: unsafe { Analog::new() },
+            pending: None,
+        };
+        adc.set_reference(RefType::DEFAULT);
+        adc.set_prescaler(AdcPrescaler::Div128);
+        adc.analog.adc_enable();
+        adc
+    }
+
+    /// Selects the reference voltage against which the input is measured.
+    /// # Arguments
+    /// * `reftype` - a `RefType` object, AVcc (`DEFAULT`), internal 1.1V or AREF.
+    pub fn set_reference(&mut self, reftype: RefType) {
+        let refs = match reftype {
+            RefType::EXTERNAL => 0b00,
+            RefType::DEFAULT => 0b01,
+            RefType::INTERNAL1V1 => 0b11,
+        };
+        self.analog.admux.update(|admux| {
+            admux.set_bits(6..8, refs);
+        });
+    }
+
+    /// Sets the division factor of the ADC clock.
+    /// # Arguments
+    /// * `prescaler` - a `AdcPrescaler` object, the division factor to be used.
+    pub fn set_prescaler(&mut self, prescaler: AdcPrescaler) {
+        let adps = match prescaler {
+            AdcPrescaler::Div2 => 0b001,
+            AdcPrescaler::Div4 => 0b010,
+            AdcPrescaler::Div8 => 0b011,
+            AdcPrescaler::Div16 => 0b100,
+            AdcPrescaler::Div32 => 0b101,
+            AdcPrescaler::Div64 => 0b110,
+            AdcPrescaler::Div128 => 0b111,
+        };
+        self.analog.adcsra.update(|adcsra| {
+            adcsra.set_bits(0..3, adps);
+        });
+    }
+
+    /// Connects the given channel to the ADC and disables the digital input
+    /// buffer of that pin to reduce power consumption.
+    /// # Arguments
+    /// * `channel` - a u8, the channel number from 0 to 7, or one of the internal channels.
+    /// # Returns
+    /// * `a Result<(), Error>` - which is `Error::InvalidParam` for an unknown channel.
+    pub fn select_channel(&mut self, channel: u8) -> Result<(), Error> {
+        check_channel(channel)?;
+        self.connect(channel);
+        Ok(())
+    }
+
+    // Connects a channel which exists to the ADC.
+    fn connect(&mut self, channel: u8) {
+        self.analog.admux.update(|admux| {
+            admux.set_bits(0..4, channel);
+        });
+        // ADC6 and ADC7 have no digital input buffer.
+        if channel < 6 {
+            self.analog.didr0.update(|didr0| {
+                didr0.set_bit(channel, true);
+            });
+        }
+    }
+
+    /// Performs a single conversion on the given channel and waits for the result.
+    /// # Arguments
+    /// * `channel` - a u8, the channel number from 0 to 7, or one of the internal channels.
+    /// # Returns
+    /// * `a Result<u16, Error>` - the 10 bit result of the conversion, or
+    ///   `Error::InvalidParam` for an unknown channel.
+    pub fn read_channel(&mut self, channel: u8) -> Result<u16, Error> {
+        check_channel(channel)?;
+        Ok(self.convert_channel(channel))
+    }
+
+    /// Performs a single conversion on a channel which exists, as the channel of a pin,
+    /// and waits for the result.
+    pub(crate) fn convert_channel(&mut self, channel: u8) -> u16 {
+        self.connect(channel);
+        self.analog.adc_auto_trig();
+        self.analog.adc_con_start();
+        while self.is_converting() {
+            __nop();
+        }
+        self.result()
+    }
+
+    /// Converts the given channel without waiting, for polling loops and `nb::block!`.
+    /// The first call starts the conversion and gives `WouldBlock`, as do the calls which
+    /// follow until the conversion is complete. A call for another channel starts a new
+    /// conversion once the one in progress is complete.
+    /// # Arguments
+    /// * `channel` - a u8, the channel number from 0 to 7, or one of the internal channels.
+    /// # Returns
+    /// * `a nb::Result<u16, Error>` - the 10 bit result, or `Error::InvalidParam` for an unknown channel.
+    pub fn try_read(&mut self, channel: u8) -> nb::Result<u16, Error> {
+        check_channel(channel)?;
+        match self.pending {
+            Some(_) if self.is_converting() => Err(nb::Error::WouldBlock),
+            Some(pending) if pending == channel => {
+                self.pending = None;
+                Ok(self.result())
+            }
+            _ => {
+                self.connect(channel);
+                self.analog.adc_auto_trig();
+                self.analog.adc_con_start();
+                self.pending = Some(channel);
+                Err(nb::Error::WouldBlock)
+            }
+        }
+    }
+
+    /// Starts free running mode on the given channel, in which a new conversion
+    /// is started as soon as the previous one completes.
+    /// The latest value can be read at any time with `result()`.
+    /// # Arguments
+    /// * `channel` - a u8, the channel number from 0 to 7, or one of the internal channels.
+    /// # Returns
+    /// * `a Result<(), Error>` - which is `Error::InvalidParam` for an unknown channel.
+    pub fn start_free_running(&mut self, channel: u8) -> Result<(), Error> {
+        self.select_channel(channel)?;
+        self.analog.adcsrb.update(|adcsrb| {
+            adcsrb.set_bits(0..3, 0b000);
+        });
+        self.analog.adcsra.update(|adcsra| {
+            adcsra.set_bit(5, true);
+        });
+        self.analog.adc_con_start();
+        Ok(())
+    }
+
+    /// Stops free running mode after the conversion in progress.
+    pub fn stop_free_running(&mut self) {
+        self.analog.adc_auto_trig();
+    }
+
+    /// Checks whether a conversion is still in progress.
+    /// # Returns
+    /// * `a boolean` - which is true while the ADSC bit is set.
+    pub fn is_converting(&self) -> bool {
+        self.analog.adcsra.read().get_bit(6)
+    }
+
+    /// Gives the result of the last completed conversion.
+    /// ADCL is read before ADCH, as required for the data registers to be updated.
+    /// # Returns
+    /// * `a u16` - the 10 bit result of the conversion.
+    pub fn result(&mut self) -> u16 {
+        let low = self.analog.adcl.read() as u16;
+        let high = self.analog.adch.read() as u16;
+        (high << 8) | low
+    }
+
+    /// Measures the temperature of the chip with the internal sensor, which is read
+    /// against the internal 1.1V reference. AVcc is the reference again afterwards.
+    /// # Arguments
+    /// * `calibration` - a `SensorCalibration` object, the calibration of the chip.
+    /// # Returns
+    /// * `a f32` - the temperature in degree Celsius.
+    pub fn read_temperature(&mut self, calibration: &SensorCalibration) -> f32 {
+        self.set_reference(RefType::INTERNAL1V1);
+        let raw = self.read_settled(ADC_TEMPERATURE);
+        self.set_reference(RefType::DEFAULT);
+        (raw as f32 - calibration.temperature_offset) / calibration.temperature_gain
+    }
+
+    /// Measures the supply voltage without any external part, by reading the
+    /// internal bandgap reference against AVcc.
+    /// # Arguments
+    /// * `calibration` - a `SensorCalibration` object, the calibration of the chip.
+    /// # Returns
+    /// * `a u16` - the supply voltage in millivolts.
+    pub fn read_vcc(&mut self, calibration: &SensorCalibration) -> u16 {
+        self.set_reference(RefType::DEFAULT);
+        let raw = self.read_settled(ADC_BANDGAP).max(1) as u32;
+        (calibration.bandgap_millivolts as u32 * 1024 / raw) as u16
+    }
+
+    /// Converts an internal channel after the reference and the input have settled,
+    /// discarding the first conversion, which is inaccurate after a change of reference.
+    fn read_settled(&mut self, channel: u8) -> u16 {
+        self.connect(channel);
+        delay_ms(2);
+        self.convert_channel(channel);
+        self.convert_channel(channel)
+    }
+
+    /// Disables the ADC and gates its clock as `disable` does, under the name used by the
+    /// other drivers. `Adc::new` enables it again.
+    pub fn shutdown(&mut self) {
+        self.pending = None;
+        self.disable();
+    }
+
+    /// Disables the ADC and switches off its clock to save power.
+    pub fn disable(&mut self) {
+        self.analog.adc_disable();
+        unsafe {
+            let pow = Power::new();
+            write_volatile(&mut pow.prr, read_volatile(&pow.prr) | 0x01);
+        }
+    }
+}
+
+/// Reads the value of an analog pin in the same way as `analogRead` of Arduino.
+/// # Arguments
+/// * `pin` - a u8, the analog pin number from 0 to 7.
+/// # Returns
+/// * `a Result<u16, Error>` - the 10 bit value read, where 1023 corresponds to the reference
+///   voltage, or `Error::InvalidParam` for an unknown pin.
+pub fn analog_read(pin: u8) -> Result<u16, Error> {
+    Adc::new().read_channel(pin)
+}
+
+// Checks that a channel exists.
+fn check_channel(channel: u8) -> Result<(), Error> {
+    match channel {
+        0..=7 | ADC_TEMPERATURE | ADC_BANDGAP | ADC_GND => Ok(()),
+        _ => Err(Error::InvalidParam),
+    }
+}
+
+/// Measures the temperature of the chip with the typical calibration.
+/// # Returns
+/// * `a f32` - the temperature in degree Celsius.
+pub fn read_internal_temperature() -> f32 {
+    Adc::new().read_temperature(&SensorCalibration::default())
+}
+
+/// Measures the supply voltage of the chip with the typical calibration.
+/// # Returns
+/// * `a u16` - the supply voltage in millivolts.
+pub fn read_vcc_millivolts() -> u16 {
+    Adc::new().read_vcc(&SensorCalibration::default())
+}
@@ -0,0 +1,256 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Measurement of pulses and periods on ATMEGA328P.
+//! The input capture unit of Timer1 copies the counter into ICR1 when the selected
+//! edge arrives on the ICP1 pin, which gives the time of the edge to a tick.
+//! ICP1 is digital pin 8 of the Arduino Uno.
+//! `pulse_in` measures a pulse on any digital pin using `time::micros()`, like `pulseIn()` of Arduino.
+//! Refer to section 16.6 of ATMEGA328P datasheet.
+
+// Source codes required.
+use crate::atmega328p::hal::analog::{Timer16, TimerNo16};
+use crate::atmega328p::hal::pin::make_pin;
+use crate::atmega328p::hal::power::Power;
+use crate::config::CPU_FREQUENCY_HZ;
+use crate::time::micros;
+
+// Crates to be used for the implementation.
+use bit_field::BitField;
+use core::ptr::{read_volatile, write_volatile};
+
+// Bits of TCCR1B for the noise canceler and the edge select, and of TIFR1 for the capture flag.
+const ICNC: u8 = 7;
+const ICES: u8 = 6;
+const ICF: u8 = 5;
+
+/// Selection of the edge which triggers a capture.
+#[derive(Clone, Copy, PartialEq)]
+pub enum CaptureEdge {
+    Falling,
+    Rising,
+}
+
+/// Selection of the division factor between the system clock and the timer.
+/// At 16 MHz `Div8` gives a resolution of 0.5 us and captures up to 32 ms apart.
+#[derive(Clone, Copy)]
+pub enum CapturePrescaler {
+    Div1,
+    Div8,
+    Div64,
+    Div256,
+    Div1024,
+}
+
+impl CapturePrescaler {
+    /// Gives the division factor.
+    pub fn factor(&self) -> u32 {
+        match self {
+            CapturePrescaler::Div1 => 1,
+            CapturePrescaler::Div8 => 8,
+            CapturePrescaler::Div64 => 64,
+            CapturePrescaler::Div256 => 256,
+            CapturePrescaler::Div1024 => 1024,
+        }
+    }
+
+    /// Gives the value of the CS12:0 bits.
+    fn bits(&self) -> u8 {
+        match self {
+            CapturePrescaler::Div1 => 1,
+            CapturePrescaler::Div8 => 2,
+            CapturePrescaler::Div64 => 3,
+            CapturePrescaler::Div256 => 4,
+            CapturePrescaler::Div1024 => 5,
+        }
+    }
+}
+
+/// Structure to use the input capture unit of Timer1.
+/// The timer runs freely in normal mode, so it can not be used for PWM at the same time.
+pub struct InputCapture {
+    prescaler: CapturePrescaler,
+}
+
+impl InputCapture {
+    /// Starts Timer1 in normal mode and prepares its input capture unit.
+    /// # Arguments
+    /// * `prescaler` - a `CapturePrescaler` object, the clock of the timer.
+    /// * `edge` - a `CaptureEdge` object, the edge to be captured.
+    /// # Returns
+    /// * `a InputCapture object` - which will be used to read the captures.
+    pub fn new(prescaler: CapturePrescaler, edge: CaptureEdge) -> InputCapture {
+        unsafe {
+            // PRTIM1 is bit 3 of PRR.
+            let power = Power::new();
+            write_volatile(&mut power.prr, read_volatile(&power.prr) & !(1 << 3));
+        }
+
+        let mut capture = InputCapture { prescaler };
+        let regs = capture.regs();
+        regs.tccra.write(0);
+        regs.tccrb.write(prescaler.bits());
+        regs.set_counter(0);
+        capture.set_edge(edge);
+        capture.clear();
+        capture
+    }
+
+    /// Gives the registers of the timer.
+    fn regs(&self) -> &'static mut Timer16 {
+        Timer16::new(TimerNo16::Timer1)
+    }
+
+    /// Gives the Timer/Counter1 Interrupt Flag Register.
+    fn tifr(&self) -> *mut u8 {
+        0x36 as *mut u8
+    }
+
+    /// Selects the edge to be captured.
+    /// Changing the edge may set the capture flag, so it is cleared afterwards.
+    /// # Arguments
+    /// * `edge` - a `CaptureEdge` object, the edge to be captured.
+    pub fn set_edge(&mut self, edge: CaptureEdge) {
+        self.regs().tccrb.update(|tccrb| {
+            tccrb.set_bit(ICES, edge == CaptureEdge::Rising);
+        });
+        self.clear();
+    }
+
+    /// Enables or disables the noise canceler, which needs four equal samples
+    /// of the pin before an edge is taken and so delays the capture by four clocks.
+    /// # Arguments
+    /// * `enable` - a boolean, true to enable the noise canceler.
+    pub fn set_noise_canceler(&mut self, enable: bool) {
+        self.regs().tccrb.update(|tccrb| {
+            tccrb.set_bit(ICNC, enable);
+        });
+    }
+
+    /// Clears a pending capture.
+    pub fn clear(&mut self) {
+        // The flag is cleared by writing a logic one to it.
+        unsafe { write_volatile(self.tifr(), 1 << ICF) }
+    }
+
+    /// Reads the counter value of the last edge, if an edge has arrived since the last read.
+    /// # Returns
+    /// * `a Option<u16>` - the value of ICR1, or None if no edge was captured.
+    pub fn poll(&mut self) -> Option<u16> {
+        if unsafe { read_volatile(self.tifr()) }.get_bit(ICF) {
+            let value = self.regs().input_capture();
+            self.clear();
+            Some(value)
+        } else {
+            None
+        }
+    }
+
+    /// Waits for the next edge.
+    /// # Returns
+    /// * `a u16` - the value of ICR1.
+    pub fn wait(&mut self) -> u16 {
+        loop {
+            if let Some(value) = self.poll() {
+                return value;
+            }
+        }
+    }
+
+    /// Measures the time between two consecutive edges of the selected kind.
+    /// # Returns
+    /// * `a u16` - the period in ticks of the timer.
+    pub fn period(&mut self) -> u16 {
+        self.clear();
+        let first = self.wait();
+        let second = self.wait();
+        second.wrapping_sub(first)
+    }
+
+    /// Measures the time between an edge and the opposite edge which follows it.
+    /// # Arguments
+    /// * `edge` - a `CaptureEdge` object, `Rising` to measure a high pulse.
+    /// # Returns
+    /// * `a u16` - the width of the pulse in ticks of the timer.
+    pub fn pulse_width(&mut self, edge: CaptureEdge) -> u16 {
+        self.set_edge(edge);
+        let start = self.wait();
+        self.set_edge(match edge {
+            CaptureEdge::Rising => CaptureEdge::Falling,
+            CaptureEdge::Falling => CaptureEdge::Rising,
+        });
+        let end = self.wait();
+        end.wrapping_sub(start)
+    }
+
+    /// Converts ticks of the timer into microseconds.
+    /// # Arguments
+    /// * `ticks` - a u16, the number of ticks.
+    /// # Returns
+    /// * `a u32` - the time in microseconds.
+    pub fn ticks_to_us(&self, ticks: u16) -> u32 {
+        (ticks as u64 * self.prescaler.factor() as u64 * 1_000_000 / CPU_FREQUENCY_HZ as u64) as u32
+    }
+
+    /// Converts a period in ticks of the timer into a frequency.
+    /// # Arguments
+    /// * `ticks` - a u16, the period in ticks.
+    /// # Returns
+    /// * `a u32` - the frequency in Hz, 0 if the period is 0.
+    pub fn frequency(&self, ticks: u16) -> u32 {
+        if ticks == 0 {
+            return 0;
+        }
+        CPU_FREQUENCY_HZ / self.prescaler.factor() / ticks as u32
+    }
+}
+
+/// Measures the length of a pulse on a digital pin, like `pulseIn()` of Arduino.
+/// If `level` is high, waits for the pin to go high, times how long it stays high and
+/// returns when it goes low again. The resolution is that of `time::micros()`, which
+/// must have been started with `time::init()`.
+/// # Arguments
+/// * `pin` - a u8, the digital pin number, which should be an input.
+/// * `level` - a boolean, true to measure a high pulse and false for a low pulse.
+/// * `timeout` - a u32, the time in microseconds to wait for the whole pulse.
+/// # Returns
+/// * `a u32` - the length of the pulse in microseconds, or 0 on time-out.
+pub fn pulse_in(pin: u8, level: bool, timeout: u32) -> u32 {
+    let digital = make_pin(pin);
+    let mask = 1 << digital.pin;
+    let read = || unsafe { (read_volatile(&(*digital.port).pin) & mask != 0) == level };
+    let start = micros();
+    let timed_out = || micros().wrapping_sub(start) > timeout;
+
+    // The pulse which is already going on is not counted.
+    while read() {
+        if timed_out() {
+            return 0;
+        }
+    }
+    while !read() {
+        if timed_out() {
+            return 0;
+        }
+    }
+    let pulse_start = micros();
+    while read() {
+        if timed_out() {
+            return 0;
+        }
+    }
+    micros().wrapping_sub(pulse_start)
+}
@@ -39,7 +39,7 @@ impl DigitalPin {
         let mut p = unsafe { read_volatile(&mut (*self.pin.port).port) }; // Reading the value of PORTxn.
         p = p & (1 << self.pin.pin);
         let ddr_value = unsafe { read_volatile(&mut (*self.pin.port).ddr) }; // Read the DDRxn register.
-        if p == 0 && ddr_value == (0x1 << self.pin.pin) {
+        if p == 0 && ddr_value & (0x1 << self.pin.pin) != 0 {
             // Toggling the value of PORTxn, if it isn't set to high.
             self.toggle();
         }
@@ -54,7 +54,7 @@ impl DigitalPin {
         let mut p = unsafe { read_volatile(&mut (*self.pin.port).port) }; //Reading the value of PORTxn.
         p = p & (1 << self.pin.pin);
         let ddr_value = unsafe { read_volatile(&mut (*self.pin.port).ddr) }; // Read the DDRxn register.
-        if p != 0 && ddr_value == (0x1 << self.pin.pin) {
+        if p != 0 && ddr_value & (0x1 << self.pin.pin) != 0 {
             //Toggling the value of PORTxn, if it isn't set to low.
             self.toggle();
         }
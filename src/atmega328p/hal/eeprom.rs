@@ -0,0 +1,270 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Devansh Kumar Jha, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! This source code contains the driver for the 1 KB EEPROM of ATMEGA328P.
+//! Bytes and blocks can be read and written, and the update functions write a
+//! byte only if it has changed, since every cell survives about 100,000 writes.
+//! `EepromCell` stores a value of any plain data type along with a checksum.
+//! See the section 8.4 of ATMEGA328P datasheet.
+
+// Other source code files to be used.
+use crate::atmega328p::hal::interrupts::without_interrupts;
+use crate::error::Error;
+use crate::util::crc::crc8;
+
+// Crates which would be used in the implementation.
+use bit_field::BitField;
+use core::marker::PhantomData;
+use core::mem::{size_of, MaybeUninit};
+use core::slice;
+use volatile::Volatile;
+
+/// Size of the EEPROM in bytes.
+pub const EEPROM_SIZE: usize = 1024;
+
+// Bits of EECR.
+const EEPE: u8 = 1;
+const EERE: u8 = 0;
+
+/// Contains the registers to access the EEPROM.
+///
+/// * **EECR**: *EEPROM Control Register*. Starts the read and write operations and
+/// selects the programming mode.
+///
+/// * **EEDR**: *EEPROM Data Register*. Holds the byte to be written or the byte read.
+///
+/// * **EEARL/EEARH**: *EEPROM Address Registers*. Select the byte to be accessed.
+#[repr(C, packed)]
+pub struct Eeprom {
+    eecr: Volatile<u8>,
+    eedr: Volatile<u8>,
+    eearl: Volatile<u8>,
+    eearh: Volatile<u8>,
+}
+
+impl Eeprom {
+    /// Creates a memory mapped IO for the EEPROM registers.
+    /// # Returns
+    /// * `a reference to Eeprom object` - which will be used for further implementations.
+    pub fn new() -> &'static mut Eeprom {
+        unsafe { &mut *(0x3F as *mut Eeprom) }
+    }
+
+    /// Waits for the previous write operation to complete and sets the address.
+    fn set_address(&mut self, address: usize) -> Result<(), Error> {
+        check_range(address, 1)?;
+        while self.eecr.read().get_bit(EEPE) {}
+        self.eearh.write((address >> 8) as u8);
+        self.eearl.write(address as u8);
+        Ok(())
+    }
+
+    /// Reads a byte from the EEPROM.
+    /// # Arguments
+    /// * `address` - a usize, the address of the byte from 0 to 1023.
+    /// # Returns
+    /// * `a Result<u8, Error>` - the byte stored at the address, or `Error::InvalidParam`
+    ///   if the address is outside of the EEPROM.
+    pub fn read_byte(&mut self, address: usize) -> Result<u8, Error> {
+        self.set_address(address)?;
+        self.eecr.update(|eecr| {
+            eecr.set_bit(EERE, true);
+        });
+        Ok(self.eedr.read())
+    }
+
+    /// Writes a byte to the EEPROM, which takes about 3.4 ms.
+    /// The function returns as soon as the write is started.
+    /// # Arguments
+    /// * `address` - a usize, the address of the byte from 0 to 1023.
+    /// * `data` - a u8, the byte to be written.
+    /// # Returns
+    /// * `a Result<(), Error>` - which is `Error::InvalidParam` if the address is outside
+    ///   of the EEPROM.
+    pub fn write_byte(&mut self, address: usize, data: u8) -> Result<(), Error> {
+        self.set_address(address)?;
+        self.eedr.write(data);
+        // EEPE must be set within four cycles of setting EEMPE, so no interrupt
+        // may come in between and the sbi instructions are used directly.
+        without_interrupts(|| unsafe {
+            llvm_asm!("sbi 0x1F, 2
+                       sbi 0x1F, 1"
+                     :
+                     :
+                     :
+                     : "volatile")
+        });
+        Ok(())
+    }
+
+    /// Writes a byte only if it differs from the byte stored, to save wear.
+    /// # Arguments
+    /// * `address` - a usize, the address of the byte from 0 to 1023.
+    /// * `data` - a u8, the byte to be written.
+    /// # Returns
+    /// * `a Result<bool, Error>` - true if the byte had to be written, or
+    ///   `Error::InvalidParam` if the address is outside of the EEPROM.
+    pub fn update_byte(&mut self, address: usize, data: u8) -> Result<bool, Error> {
+        if self.read_byte(address)? == data {
+            Ok(false)
+        } else {
+            self.write_byte(address, data)?;
+            Ok(true)
+        }
+    }
+
+    /// Reads consecutive bytes starting at the given address.
+    /// # Arguments
+    /// * `address` - a usize, the address of the first byte.
+    /// * `buffer` - a mutable slice of u8, which will be filled with the data read.
+    /// # Returns
+    /// * `a Result<(), Error>` - which is `Error::InvalidParam` if the block does not fit
+    ///   in the EEPROM, in which case nothing is read.
+    pub fn read_block(&mut self, address: usize, buffer: &mut [u8]) -> Result<(), Error> {
+        check_range(address, buffer.len())?;
+        for (i, byte) in buffer.iter_mut().enumerate() {
+            *byte = self.read_byte(address + i)?;
+        }
+        Ok(())
+    }
+
+    /// Writes consecutive bytes starting at the given address.
+    /// # Arguments
+    /// * `address` - a usize, the address of the first byte.
+    /// * `data` - a slice of u8, the bytes to be written.
+    /// # Returns
+    /// * `a Result<(), Error>` - which is `Error::InvalidParam` if the block does not fit
+    ///   in the EEPROM, in which case nothing is written.
+    pub fn write_block(&mut self, address: usize, data: &[u8]) -> Result<(), Error> {
+        check_range(address, data.len())?;
+        for (i, byte) in data.iter().enumerate() {
+            self.write_byte(address + i, *byte)?;
+        }
+        Ok(())
+    }
+
+    /// Writes consecutive bytes, skipping those which are already stored.
+    /// # Arguments
+    /// * `address` - a usize, the address of the first byte.
+    /// * `data` - a slice of u8, the bytes to be written.
+    /// # Returns
+    /// * `a Result<usize, Error>` - the number of bytes which had to be written, or
+    ///   `Error::InvalidParam` if the block does not fit in the EEPROM, in which case
+    ///   nothing is written.
+    pub fn update_block(&mut self, address: usize, data: &[u8]) -> Result<usize, Error> {
+        check_range(address, data.len())?;
+        let mut written = 0;
+        for (i, byte) in data.iter().enumerate() {
+            if self.update_byte(address + i, *byte)? {
+                written += 1;
+            }
+        }
+        Ok(written)
+    }
+
+    /// Waits for the last write operation to complete.
+    pub fn wait(&mut self) {
+        while self.eecr.read().get_bit(EEPE) {}
+    }
+}
+
+/// Checks that a block of bytes fits in the EEPROM.
+fn check_range(address: usize, length: usize) -> Result<(), Error> {
+    match address.checked_add(length) {
+        Some(end) if end <= EEPROM_SIZE => Ok(()),
+        _ => Err(Error::InvalidParam),
+    }
+}
+
+/// Gives the checksum of a block of bytes, the SMBus CRC8, which unlike a sum also
+/// catches bytes swapped or bits flipped in pairs.
+fn checksum(data: &[u8]) -> u8 {
+    crc8(data, 0x07, 0)
+}
+
+/// A value stored in the EEPROM at a fixed address, followed by a checksum byte.
+/// `T` should be a plain data type such as integers, floats, arrays or
+/// `#[repr(C)]` structures of them, because it is stored as its raw bytes.
+/// # Example
+/// ```ignore
+/// #[derive(Clone, Copy)]
+/// #[repr(C)]
+/// struct Calibration {
+///     offset: i16,
+///     scale: f32,
+/// }
+///
+/// const CALIBRATION: EepromCell<Calibration> = EepromCell::new(0);
+/// let calibration = CALIBRATION.load().unwrap_or(Calibration { offset: 0, scale: 1.0 });
+/// ```
+pub struct EepromCell<T> {
+    address: usize,
+    _type: PhantomData<T>,
+}
+
+impl<T> EepromCell<T> {
+    /// Creates a cell at the given address.
+    /// # Arguments
+    /// * `address` - a usize, the address of the first byte of the value.
+    /// # Returns
+    /// * `a EepromCell object` - which takes `size()` bytes of EEPROM.
+    pub const fn new(address: usize) -> EepromCell<T> {
+        EepromCell {
+            address,
+            _type: PhantomData,
+        }
+    }
+
+    /// Gives the number of bytes taken in the EEPROM, including the checksum.
+    pub const fn size() -> usize {
+        size_of::<T>() + 1
+    }
+}
+
+impl<T: Copy> EepromCell<T> {
+    /// Reads the value stored in the cell.
+    /// # Returns
+    /// * `a Option<T>` - which is None if the checksum does not match, for example
+    ///   if nothing was stored yet, or if the cell does not fit in the EEPROM.
+    pub fn load(&self) -> Option<T> {
+        let eeprom = Eeprom::new();
+        let mut value = MaybeUninit::<T>::uninit();
+        let bytes =
+            unsafe { slice::from_raw_parts_mut(value.as_mut_ptr() as *mut u8, size_of::<T>()) };
+        eeprom.read_block(self.address, bytes).ok()?;
+        if checksum(bytes) == eeprom.read_byte(self.address + size_of::<T>()).ok()? {
+            Some(unsafe { value.assume_init() })
+        } else {
+            None
+        }
+    }
+
+    /// Stores a value in the cell, writing only the bytes which have changed.
+    /// # Arguments
+    /// * `value` - a reference to T, the value to be stored.
+    /// # Returns
+    /// * `a Result<(), Error>` - which is `Error::InvalidParam` if the cell does not fit in
+    ///   the EEPROM, in which case nothing is written.
+    pub fn store(&self, value: &T) -> Result<(), Error> {
+        check_range(self.address, Self::size())?;
+        let eeprom = Eeprom::new();
+        let bytes =
+            unsafe { slice::from_raw_parts(value as *const T as *const u8, size_of::<T>()) };
+        eeprom.update_block(self.address, bytes)?;
+        eeprom.update_byte(self.address + size_of::<T>(), checksum(bytes))?;
+        Ok(())
+    }
+}
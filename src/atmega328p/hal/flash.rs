@@ -0,0 +1,374 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Self-programming of the flash memory of ATMEGA328P with SPM.
+//! The flash is written a page of 128 bytes at a time: the page is erased, filled
+//! through a temporary buffer a word at a time, then written. While a page of the
+//! read-while-write section, which holds the application, is erased or written that
+//! whole section can not be read, and it is enabled again with RWWSRE afterwards.
+//! SPM is only executed from the boot loader section, so there are two routes:
+//! * `SpmRoute::Optiboot` calls the `do_spm` entry of Optiboot 8 and later, 2 bytes after
+//!   the start of its 512 byte section, for programs uploaded with it.
+//! * `SpmRoute::Direct` executes SPM in place, for code placed in the boot loader section.
+//!
+//! `receive_update` stores an image received over a serial port in a staging area of
+//! the flash and checks it, and `apply_update` copies it over the application, which
+//! can only be done from the boot loader section.
+//! See the section 27 of ATMEGA328P datasheet.
+
+// Source codes required.
+use crate::atmega328p::hal::fuses;
+use crate::atmega328p::hal::reset::reboot;
+use crate::atmega328p::registers::{EECR, SPMCSR};
+use crate::common::UsartOps;
+use crate::error::Error;
+use crate::sync;
+use crate::util::crc::{crc16_xmodem, crc32_update};
+
+/// Size of a page of the flash, in bytes.
+pub const PAGE_SIZE: usize = 128;
+
+/// Size of the flash, in bytes.
+pub const FLASH_SIZE: u32 = 32 * 1024;
+
+// Byte address of the `do_spm` entry of Optiboot.
+const OPTIBOOT_DO_SPM: u16 = 0x7E02;
+
+// Values written to SPMCSR to fill the buffer, erase a page, write a page and
+// enable the read-while-write section again.
+const PAGE_FILL: u8 = 0x01;
+const PAGE_ERASE: u8 = 0x03;
+const PAGE_WRITE: u8 = 0x05;
+const RWW_ENABLE: u8 = 0x11;
+
+// Bytes of the update protocol, see `receive_update`.
+const READY: u8 = b'R';
+const ACK: u8 = b'K';
+const BAD_CRC: u8 = b'C';
+const ERROR: u8 = b'E';
+
+/// Way the SPM instruction is executed.
+/// * `Optiboot` - through the `do_spm` entry of Optiboot 8 or later.
+/// * `Direct` - in place, when the program runs from the boot loader section.
+#[derive(Clone, Copy, PartialEq)]
+pub enum SpmRoute {
+    Optiboot,
+    Direct,
+}
+
+/// Errors of the self-programming.
+/// * `Unaligned` - the address is not at the start of a page.
+/// * `OutOfRange` - the data would reach the boot loader section.
+/// * `Crc` - the CRC of a received page or image did not match.
+/// * `Aborted` - the sender aborted the update.
+/// * `Protocol` - an unexpected byte was received.
+/// * `NotInBootSection` - the operation needs `SpmRoute::Direct`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FlashError {
+    Unaligned,
+    OutOfRange,
+    Crc,
+    Aborted,
+    Protocol,
+    NotInBootSection,
+}
+
+impl From<FlashError> for Error {
+    fn from(error: FlashError) -> Error {
+        match error {
+            FlashError::Unaligned | FlashError::OutOfRange => Error::InvalidParam,
+            FlashError::Crc => Error::CrcMismatch,
+            FlashError::Aborted => Error::Closed,
+            FlashError::Protocol => Error::InvalidData,
+            FlashError::NotInBootSection => Error::Unsupported,
+        }
+    }
+}
+
+/// Structure to read and write the flash memory.
+/// # Elements
+/// * `route` - a `SpmRoute` object, the way SPM is executed.
+pub struct Flash {
+    route: SpmRoute,
+}
+
+impl Flash {
+    /// Creates the structure for the given route.
+    /// # Arguments
+    /// * `route` - a `SpmRoute` object, the way SPM is executed.
+    /// # Returns
+    /// * `a Flash object` - which will be used to read and write the flash.
+    pub fn new(route: SpmRoute) -> Flash {
+        Flash { route }
+    }
+
+    /// Gives the start of the boot loader section, from the BOOTSZ fuse bits.
+    /// The pages from there can not be written.
+    /// # Returns
+    /// * `a u16` - the byte address of the section.
+    pub fn boot_section_start() -> u16 {
+        let bootsz = (fuses::fuses().high >> 1) & 0x03;
+        (FLASH_SIZE - (512 << (3 - bootsz))) as u16
+    }
+
+    /// Reads a byte of the flash with LPM.
+    /// # Arguments
+    /// * `address` - a u16, the byte address.
+    /// # Returns
+    /// * `a u8` - the byte.
+    pub fn read_byte(&self, address: u16) -> u8 {
+        let value: u8;
+        unsafe {
+            llvm_asm!("lpm $0, Z"
+                     : "=r" (value)
+                     : "z" (address)
+                     :
+                     : "volatile");
+        }
+        value
+    }
+
+    /// Reads consecutive bytes of the flash.
+    /// # Arguments
+    /// * `address` - a u16, the byte address of the first byte.
+    /// * `data` - a mutable slice of u8, which is filled with the bytes.
+    pub fn read(&self, address: u16, data: &mut [u8]) {
+        for (i, byte) in data.iter_mut().enumerate() {
+            *byte = self.read_byte(address.wrapping_add(i as u16));
+        }
+    }
+
+    /// Checks whether the read-while-write section is still disabled after an erase or
+    /// a write, which is only the case with `SpmRoute::Direct` before it is enabled again.
+    /// # Returns
+    /// * `a boolean` - which is true if the section can not be read.
+    pub fn rww_busy(&self) -> bool {
+        SPMCSR.is_set(SPMCSR::RWWSB)
+    }
+
+    /// Erases a page, which then reads as 0xFF.
+    /// # Arguments
+    /// * `address` - a u16, the byte address of the start of the page.
+    /// # Returns
+    /// * `a Result<(), FlashError>` - which is an error if the page can not be erased.
+    pub fn erase_page(&mut self, address: u16) -> Result<(), FlashError> {
+        Self::check_page(address)?;
+        self.spm(address, PAGE_ERASE, 0);
+        Ok(())
+    }
+
+    /// Erases a page and writes it, the bytes after the data being left at 0xFF.
+    /// The CPU is halted for about 4 milliseconds for the erase and for the write
+    /// with `SpmRoute::Optiboot`.
+    /// # Arguments
+    /// * `address` - a u16, the byte address of the start of the page.
+    /// * `data` - a slice of u8, up to 128 bytes to be written.
+    /// # Returns
+    /// * `a Result<(), FlashError>` - which is an error if the page can not be written.
+    pub fn write_page(&mut self, address: u16, data: &[u8]) -> Result<(), FlashError> {
+        Self::check_page(address)?;
+        if data.len() > PAGE_SIZE {
+            return Err(FlashError::OutOfRange);
+        }
+        self.spm(address, PAGE_ERASE, 0);
+        for i in (0..PAGE_SIZE).step_by(2) {
+            let low = data.get(i).copied().unwrap_or(0xFF);
+            let high = data.get(i + 1).copied().unwrap_or(0xFF);
+            self.spm(
+                address + i as u16,
+                PAGE_FILL,
+                u16::from_le_bytes([low, high]),
+            );
+        }
+        self.spm(address, PAGE_WRITE, 0);
+        Ok(())
+    }
+
+    /// Receives an image over a serial port and writes it to the flash from `staging`.
+    /// The device sends `R` when it is ready, then the sender sends:
+    /// * `W`, the index of the page from `staging` (2 bytes), the 128 bytes of the page and
+    ///   their CRC16 XMODEM (2 bytes), answered by `K`, or `C` to send the page again;
+    /// * `F`, the length of the image (4 bytes) and its CRC32 (4 bytes), answered by `K`
+    ///   if the image written matches, which ends the update;
+    /// * `A` to abort the update.
+    ///
+    /// Numbers are sent high byte first, and `E` is answered to any error which ends the update.
+    /// # Arguments
+    /// * `serial` - a serial port, already initialized.
+    /// * `staging` - a u16, the byte address of the start of a page, after the running program.
+    /// # Returns
+    /// * `a Result<u32, FlashError>` - the length of the image, or the error which ended the update.
+    pub fn receive_update<S: UsartOps>(
+        &mut self,
+        serial: &mut S,
+        staging: u16,
+    ) -> Result<u32, FlashError> {
+        let result = self.receive_pages(serial, staging);
+        if let Err(error) = result {
+            if error != FlashError::Aborted {
+                serial.write_byte(ERROR);
+            }
+        }
+        result
+    }
+
+    /// Copies an image checked by `receive_update` to the start of the flash and resets
+    /// the chip. It overwrites the application, so it only works with `SpmRoute::Direct`
+    /// from code in the boot loader section, and it does not return then.
+    /// # Arguments
+    /// * `staging` - a u16, the byte address where the image was received.
+    /// * `length` - a u32, the length of the image.
+    /// # Returns
+    /// * `a FlashError` - the reason why the image could not be copied.
+    pub fn apply_update(&mut self, staging: u16, length: u32) -> FlashError {
+        if self.route != SpmRoute::Direct {
+            return FlashError::NotInBootSection;
+        }
+        let mut page = [0; PAGE_SIZE];
+        let mut offset: u16 = 0;
+        while (offset as u32) < length {
+            self.read(staging + offset, &mut page);
+            if let Err(error) = self.write_page(offset, &page) {
+                return error;
+            }
+            offset += PAGE_SIZE as u16;
+        }
+        reboot()
+    }
+
+    // Handles the commands of the update protocol until the image is checked.
+    fn receive_pages<S: UsartOps>(
+        &mut self,
+        serial: &mut S,
+        staging: u16,
+    ) -> Result<u32, FlashError> {
+        Self::check_page(staging)?;
+        let end = Self::boot_section_start() as u32;
+        let mut page = [0; PAGE_SIZE];
+        serial.write_byte(READY);
+        loop {
+            match next_byte(serial) {
+                b'W' => {
+                    let index = u16::from_be_bytes([next_byte(serial), next_byte(serial)]);
+                    for byte in page.iter_mut() {
+                        *byte = next_byte(serial);
+                    }
+                    let crc = u16::from_be_bytes([next_byte(serial), next_byte(serial)]);
+                    if crc != crc16_xmodem(&page) {
+                        serial.write_byte(BAD_CRC);
+                        continue;
+                    }
+                    let address = staging as u32 + index as u32 * PAGE_SIZE as u32;
+                    if address + PAGE_SIZE as u32 > end {
+                        return Err(FlashError::OutOfRange);
+                    }
+                    self.write_page(address as u16, &page)?;
+                    serial.write_byte(ACK);
+                }
+                b'F' => {
+                    let mut bytes = [0; 8];
+                    for byte in bytes.iter_mut() {
+                        *byte = next_byte(serial);
+                    }
+                    let length = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+                    let crc = u32::from_be_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]);
+                    if staging as u32 + length > end {
+                        return Err(FlashError::OutOfRange);
+                    }
+                    if self.crc32(staging, length) != crc {
+                        return Err(FlashError::Crc);
+                    }
+                    serial.write_byte(ACK);
+                    return Ok(length);
+                }
+                b'A' => return Err(FlashError::Aborted),
+                _ => return Err(FlashError::Protocol),
+            }
+        }
+    }
+
+    // Gives the CRC32 of the bytes of the flash from `address`.
+    fn crc32(&self, address: u16, length: u32) -> u32 {
+        let mut crc = 0;
+        let mut buffer = [0; 32];
+        let mut offset: u32 = 0;
+        while offset < length {
+            let count = (length - offset).min(buffer.len() as u32) as usize;
+            self.read(address + offset as u16, &mut buffer[..count]);
+            crc = crc32_update(crc, &buffer[..count]);
+            offset += count as u32;
+        }
+        crc
+    }
+
+    // Checks that the address is the start of a page before the boot loader section.
+    fn check_page(address: u16) -> Result<(), FlashError> {
+        if address as usize % PAGE_SIZE != 0 {
+            return Err(FlashError::Unaligned);
+        }
+        if address >= Self::boot_section_start() {
+            return Err(FlashError::OutOfRange);
+        }
+        Ok(())
+    }
+
+    // Executes SPM with the given command, Z holding the address and r1:r0 the data.
+    fn spm(&mut self, address: u16, command: u8, data: u16) {
+        sync::free(|_| {
+            // SPM must not run during an EEPROM write or another SPM.
+            while EECR.is_set(EECR::EEPE) || SPMCSR.is_set(SPMCSR::SPMEN) {}
+            match self.route {
+                SpmRoute::Optiboot => unsafe {
+                    // Function pointers hold word addresses. `do_spm` waits for the
+                    // operation and enables the read-while-write section again.
+                    let do_spm: extern "C" fn(u16, u8, u16) =
+                        core::mem::transmute((OPTIBOOT_DO_SPM >> 1) as usize);
+                    do_spm(address, command, data);
+                },
+                SpmRoute::Direct => {
+                    spm_direct(address, command, data);
+                    if command == PAGE_ERASE || command == PAGE_WRITE {
+                        while SPMCSR.is_set(SPMCSR::SPMEN) {}
+                        spm_direct(address, RWW_ENABLE, 0);
+                    }
+                }
+            }
+        })
+    }
+}
+
+// Writes the command to SPMCSR and executes SPM within 4 cycles.
+fn spm_direct(address: u16, command: u8, data: u16) {
+    unsafe {
+        llvm_asm!("movw r0, $2
+                   out 0x37, $1
+                   spm
+                   clr r1"
+                 :
+                 : "z" (address), "r" (command), "w" (data)
+                 : "r0"
+                 : "volatile");
+    }
+}
+
+// Waits for the next byte of a serial port.
+fn next_byte<S: UsartOps>(serial: &mut S) -> u8 {
+    loop {
+        if let Some(byte) = serial.read_byte() {
+            return byte;
+        }
+    }
+}
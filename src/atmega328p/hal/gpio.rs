@@ -0,0 +1,223 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Saurabh Singh,Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Type-state pins for ATMEGA328P, where the mode of a pin is a part of its type.
+//! A pin is a zero sized type holding the address of its port and its bit, and it can be
+//! written only after it is turned into an output with `into_output()`, or read only
+//! after it is turned into an input, which is checked when the program is compiled.
+//! The pins of the older `hal::pin` module can still be obtained with `downgrade()`.
+//! See the section 13.2.1 and 13.2.2 of ATmega328P datasheet.
+
+// Source codes required.
+use crate::atmega328p::hal::analog::Adc;
+use crate::atmega328p::hal::port::{Pin as PortPin, Port};
+
+// Crates required in the code for reading and writing to registers.
+use core::marker::PhantomData;
+use core::ptr::{read_volatile, write_volatile};
+
+/// Mode of a pin which is an input, with the pull-up resistor given by `PULL`.
+pub struct Input<PULL> {
+    _pull: PhantomData<PULL>,
+}
+
+/// Input without the pull-up resistor, which is the state of all pins after reset.
+pub struct Floating;
+
+/// Input with the internal pull-up resistor enabled.
+pub struct PullUp;
+
+/// Mode of a pin which drives its output high or low.
+pub struct Output;
+
+/// Mode of a pin which is used as an input of the ADC.
+pub struct Analog;
+
+/// A pin of the port at address `PORT` (the address of PINx) and bit `BIT`,
+/// whose mode is given by `MODE`.
+pub struct Pin<MODE, const PORT: usize, const BIT: u8> {
+    _mode: PhantomData<MODE>,
+}
+
+// Aliases for the pins of every port, so that PB7 is written as `PB<MODE, 7>`.
+pub type PB<MODE, const BIT: u8> = Pin<MODE, 0x23, BIT>;
+pub type PC<MODE, const BIT: u8> = Pin<MODE, 0x26, BIT>;
+pub type PD<MODE, const BIT: u8> = Pin<MODE, 0x29, BIT>;
+
+impl<const PORT: usize, const BIT: u8> Pin<Input<Floating>, PORT, BIT> {
+    /// Creates the pin in its reset state.
+    /// # Safety
+    /// Only one object must exist for each pin, otherwise the mode of the pin
+    /// could be changed without its type knowing it. `Peripherals::take()` gives
+    /// all the pins of a board safely.
+    pub unsafe fn steal() -> Self {
+        Pin { _mode: PhantomData }
+    }
+}
+
+impl<MODE, const PORT: usize, const BIT: u8> Pin<MODE, PORT, BIT> {
+    /// Gives the registers of the port of the pin.
+    fn port(&self) -> *mut Port {
+        PORT as *mut Port
+    }
+
+    /// Sets or clears the bit of the pin in DDRx and PORTx.
+    fn configure(&mut self, ddr: bool, port: bool) {
+        unsafe {
+            let port_reg = self.port();
+            let mut ddr_val = read_volatile(&(*port_reg).ddr);
+            let mut port_val = read_volatile(&(*port_reg).port);
+            ddr_val &= !(0x1 << BIT);
+            port_val &= !(0x1 << BIT);
+            ddr_val |= (ddr as u8) << BIT;
+            port_val |= (port as u8) << BIT;
+            write_volatile(&mut (*port_reg).port, port_val);
+            write_volatile(&mut (*port_reg).ddr, ddr_val);
+        }
+    }
+
+    /// Turns the pin into an output driving a low level.
+    /// # Returns
+    /// * `a Pin<Output> object` - which can be set high or low.
+    pub fn into_output(mut self) -> Pin<Output, PORT, BIT> {
+        self.configure(true, false);
+        Pin { _mode: PhantomData }
+    }
+
+    /// Turns the pin into an input without the pull-up resistor.
+    /// # Returns
+    /// * `a Pin<Input<Floating>> object` - which can be read.
+    pub fn into_floating_input(mut self) -> Pin<Input<Floating>, PORT, BIT> {
+        self.configure(false, false);
+        Pin { _mode: PhantomData }
+    }
+
+    /// Turns the pin into an input with the internal pull-up resistor.
+    /// # Returns
+    /// * `a Pin<Input<PullUp>> object` - which can be read.
+    pub fn into_pull_up_input(mut self) -> Pin<Input<PullUp>, PORT, BIT> {
+        self.configure(false, true);
+        Pin { _mode: PhantomData }
+    }
+
+    /// Gives a pin of the `hal::pin` module for functions which take one.
+    /// The mode of that pin is not checked anymore.
+    /// # Returns
+    /// * `a Pin object` - of the `hal::port` module.
+    pub fn downgrade(self) -> PortPin {
+        PortPin {
+            port: self.port(),
+            pin: BIT,
+        }
+    }
+}
+
+impl<const PORT: usize, const BIT: u8> Pin<Output, PORT, BIT> {
+    /// Drives the pin high.
+    pub fn set_high(&mut self) {
+        unsafe {
+            let port = read_volatile(&(*self.port()).port);
+            write_volatile(&mut (*self.port()).port, port | (0x1 << BIT));
+        }
+    }
+
+    /// Drives the pin low.
+    pub fn set_low(&mut self) {
+        unsafe {
+            let port = read_volatile(&(*self.port()).port);
+            write_volatile(&mut (*self.port()).port, port & !(0x1 << BIT));
+        }
+    }
+
+    /// Toggles the output by writing a logic one to PINxn.
+    pub fn toggle(&mut self) {
+        unsafe { write_volatile(&mut (*self.port()).pin, 0x1 << BIT) }
+    }
+
+    /// Checks whether the pin is being driven high.
+    /// # Returns
+    /// * `a boolean` - which is true if PORTxn is set.
+    pub fn is_set_high(&self) -> bool {
+        unsafe { read_volatile(&(*self.port()).port) & (0x1 << BIT) != 0 }
+    }
+}
+
+impl<PULL, const PORT: usize, const BIT: u8> Pin<Input<PULL>, PORT, BIT> {
+    /// Checks whether the level on the pin is high.
+    /// # Returns
+    /// * `a boolean` - which is true if PINxn is set.
+    pub fn is_high(&self) -> bool {
+        unsafe { read_volatile(&(*self.port()).pin) & (0x1 << BIT) != 0 }
+    }
+
+    /// Checks whether the level on the pin is low.
+    /// # Returns
+    /// * `a boolean` - which is true if PINxn is cleared.
+    pub fn is_low(&self) -> bool {
+        !self.is_high()
+    }
+}
+
+impl<MODE, const BIT: u8> PC<MODE, BIT> {
+    /// Turns the pin into the ADC input channel `BIT`.
+    /// # Returns
+    /// * `a Pin<Analog> object` - which can be read with the ADC.
+    pub fn into_analog(mut self) -> PC<Analog, BIT> {
+        self.configure(false, false);
+        Pin { _mode: PhantomData }
+    }
+}
+
+impl<const BIT: u8> PC<Analog, BIT> {
+    /// Reads the voltage on the pin.
+    /// # Returns
+    /// * `a u16` - the result of the conversion, from 0 to 1023.
+    pub fn analog_read(&self) -> u16 {
+        Adc::new().convert_channel(BIT)
+    }
+}
+
+#[cfg(feature = "embedded")]
+impl<const PORT: usize, const BIT: u8> embedded_hal::digital::v2::OutputPin
+    for Pin<Output, PORT, BIT>
+{
+    type Error = core::convert::Infallible;
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        Pin::set_high(self);
+        Ok(())
+    }
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        Pin::set_low(self);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "embedded")]
+impl<PULL, const PORT: usize, const BIT: u8> embedded_hal::digital::v2::InputPin
+    for Pin<Input<PULL>, PORT, BIT>
+{
+    type Error = core::convert::Infallible;
+
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        Ok(Pin::is_high(self))
+    }
+
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        Ok(Pin::is_low(self))
+    }
+}
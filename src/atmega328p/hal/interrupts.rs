@@ -16,9 +16,25 @@
 
 //! Global interrupts configured in the ATMEGA328P chip is controlled here.
 //! Section 6.3 of the manual
+//! Handlers can also be attached to the external interrupts INT0 and INT1 and to the
+//! three pin change interrupt groups, like `attachInterrupt()` of Arduino.
+//! Section 12 of the manual
 
+use bit_field::BitField;
 use core::ptr::{read_volatile, write_volatile};
 
+// Source codes required.
+use crate::error::Error;
+use crate::sync::free;
+
+// Registers of the external and pin change interrupts.
+const PCIFR: *mut u8 = 0x3B as *mut u8;
+const EIFR: *mut u8 = 0x3C as *mut u8;
+const EIMSK: *mut u8 = 0x3D as *mut u8;
+const PCICR: *mut u8 = 0x68 as *mut u8;
+const EICRA: *mut u8 = 0x69 as *mut u8;
+const PCMSK: [*mut u8; 3] = [0x6B as *mut u8, 0x6C as *mut u8, 0x6D as *mut u8];
+
 /// SREG (Status control Register)
 /// The status register contains information about the result of the most recently executed arithmetic instruction. This
 /// information can be used for altering program flow in order to perform conditional operations. Note that the status register is
@@ -54,8 +70,358 @@ impl Interrupt {
     pub fn enable(&mut self) {
         unsafe {
             let mut ctrl_sreg = read_volatile(&self.sreg);
-            ctrl_sreg &= 0xFF;
+            ctrl_sreg |= 0x80;
             write_volatile(&mut self.sreg, ctrl_sreg);
         }
     }
+
+    /// Checks whether global interrupts are enabled.
+    /// # Returns
+    /// * `a boolean` - which is true if the I bit of SREG is set.
+    pub fn is_enabled(&mut self) -> bool {
+        let ctrl_sreg = unsafe { read_volatile(&self.sreg) };
+        ctrl_sreg & (1 << 7) != 0
+    }
+}
+
+/// Executes the given closure with global interrupts disabled, so that data shared
+/// with an interrupt service routine can be accessed safely.
+/// This is `sync::free` without the `CriticalSection` token, which should be used
+/// instead in new code, and kept for the code written before it.
+/// # Arguments
+/// * `f` - a closure, which is to be executed without interruption.
+/// # Returns
+/// * `the value returned by the closure`.
+pub fn without_interrupts<F, R>(f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    free(|_| f())
+}
+
+/// Selection of the condition which triggers an external interrupt.
+/// * `Low` - as long as the pin is low.
+/// * `Change` - on any logical change of the pin.
+/// * `Falling` - on the falling edge.
+/// * `Rising` - on the rising edge.
+#[derive(Clone, Copy)]
+pub enum TriggerMode {
+    Low,
+    Change,
+    Falling,
+    Rising,
+}
+
+// Handlers attached to INT0 and INT1 and to the pin change groups PCINT0 to PCINT2.
+static mut EXTERNAL_HANDLERS: [Option<fn()>; 2] = [None; 2];
+static mut PIN_CHANGE_HANDLERS: [Option<fn()>; 3] = [None; 3];
+
+/// Gives the external interrupt connected to a digital pin of the Arduino Uno.
+/// # Arguments
+/// * `pin` - a u8, the digital pin number.
+/// # Returns
+/// * `a Option<u8>` - the number of the external interrupt, if the pin has one.
+pub fn digital_pin_to_interrupt(pin: u8) -> Option<u8> {
+    match pin {
+        2 => Some(0),
+        3 => Some(1),
+        _ => None,
+    }
+}
+
+/// Gives the pin change interrupt group and bit of a digital pin of the Arduino Uno.
+/// Pins 14 to 19 are the analog pins A0 to A5.
+/// # Arguments
+/// * `pin` - a u8, the digital pin number.
+/// # Returns
+/// * `a Option<(u8, u8)>` - the group (0 to 2) and the bit in its mask register,
+///   if the pin has a pin change interrupt.
+pub fn digital_pin_to_pcint(pin: u8) -> Option<(u8, u8)> {
+    match pin {
+        0..=7 => Some((2, pin)),
+        8..=13 => Some((0, pin - 8)),
+        14..=19 => Some((1, pin - 14)),
+        _ => None,
+    }
+}
+
+/// Attaches a handler to an external interrupt and enables it.
+/// Global interrupts must also be enabled for the handler to be called.
+/// # Arguments
+/// * `int` - a u8, the number of the external interrupt from 0 to 1.
+/// * `mode` - a `TriggerMode` object, the condition which calls the handler.
+/// * `handler` - a function, which is called from the interrupt service routine.
+/// # Returns
+/// * `a Result<(), Error>` - which is `Error::InvalidParam` if the chip has no such interrupt.
+pub fn attach_interrupt(int: u8, mode: TriggerMode, handler: fn()) -> Result<(), Error> {
+    if int > 1 {
+        return Err(Error::InvalidParam);
+    }
+    let sense = match mode {
+        TriggerMode::Low => 0b00,
+        TriggerMode::Change => 0b01,
+        TriggerMode::Falling => 0b10,
+        TriggerMode::Rising => 0b11,
+    };
+    let shift = 2 * int;
+
+    without_interrupts(|| unsafe {
+        EXTERNAL_HANDLERS[int as usize] = Some(handler);
+
+        let mut eimsk = read_volatile(EIMSK);
+        eimsk.set_bit(int, false);
+        write_volatile(EIMSK, eimsk);
+
+        let mut eicra = read_volatile(EICRA);
+        eicra.set_bits(shift..shift + 2, sense);
+        write_volatile(EICRA, eicra);
+
+        // Changing the sense control may set the flag, which is cleared by writing one.
+        write_volatile(EIFR, 1 << int);
+        eimsk.set_bit(int, true);
+        write_volatile(EIMSK, eimsk);
+    });
+    Ok(())
+}
+
+/// Disables an external interrupt and removes its handler.
+/// # Arguments
+/// * `int` - a u8, the number of the external interrupt from 0 to 1.
+/// # Returns
+/// * `a Result<(), Error>` - which is `Error::InvalidParam` if the chip has no such interrupt.
+pub fn detach_interrupt(int: u8) -> Result<(), Error> {
+    if int > 1 {
+        return Err(Error::InvalidParam);
+    }
+    without_interrupts(|| unsafe {
+        let mut eimsk = read_volatile(EIMSK);
+        eimsk.set_bit(int, false);
+        write_volatile(EIMSK, eimsk);
+        EXTERNAL_HANDLERS[int as usize] = None;
+    });
+    Ok(())
+}
+
+/// Attaches a handler to a pin change interrupt group and enables it for the pins given.
+/// The handler is called when any of the pins changes, so it has to read the pins
+/// to find out which one has changed.
+/// # Arguments
+/// * `group` - a u8, the pin change interrupt group from 0 to 2.
+/// * `mask` - a u8, the pins of the group which trigger the interrupt.
+/// * `handler` - a function, which is called from the interrupt service routine.
+/// # Returns
+/// * `a Result<(), Error>` - which is `Error::InvalidParam` if the chip has no such group.
+pub fn attach_pin_change(group: u8, mask: u8, handler: fn()) -> Result<(), Error> {
+    if group > 2 {
+        return Err(Error::InvalidParam);
+    }
+    without_interrupts(|| unsafe {
+        PIN_CHANGE_HANDLERS[group as usize] = Some(handler);
+        write_volatile(PCMSK[group as usize], mask);
+        write_volatile(PCIFR, 1 << group);
+
+        let mut pcicr = read_volatile(PCICR);
+        pcicr.set_bit(group, true);
+        write_volatile(PCICR, pcicr);
+    });
+    Ok(())
+}
+
+/// Disables a pin change interrupt group and removes its handler.
+/// # Arguments
+/// * `group` - a u8, the pin change interrupt group from 0 to 2.
+/// # Returns
+/// * `a Result<(), Error>` - which is `Error::InvalidParam` if the chip has no such group.
+pub fn detach_pin_change(group: u8) -> Result<(), Error> {
+    if group > 2 {
+        return Err(Error::InvalidParam);
+    }
+    without_interrupts(|| unsafe {
+        let mut pcicr = read_volatile(PCICR);
+        pcicr.set_bit(group, false);
+        write_volatile(PCICR, pcicr);
+        write_volatile(PCMSK[group as usize], 0);
+        PIN_CHANGE_HANDLERS[group as usize] = None;
+    });
+    Ok(())
+}
+
+/// Calls the handler attached to an external interrupt.
+#[cfg(target_arch = "avr")]
+unsafe fn external_interrupt(int: usize) {
+    if let Some(handler) = EXTERNAL_HANDLERS[int] {
+        handler();
+    }
+}
+
+/// Calls the handler attached to a pin change interrupt group.
+#[cfg(target_arch = "avr")]
+unsafe fn pin_change_interrupt(group: usize) {
+    if let Some(handler) = PIN_CHANGE_HANDLERS[group] {
+        handler();
+    }
+}
+
+/// External Interrupt Request 0.
+#[cfg(target_arch = "avr")]
+#[no_mangle]
+pub unsafe extern "avr-interrupt" fn __vector_1() {
+    external_interrupt(0);
+}
+
+/// External Interrupt Request 1.
+#[cfg(target_arch = "avr")]
+#[no_mangle]
+pub unsafe extern "avr-interrupt" fn __vector_2() {
+    external_interrupt(1);
+}
+
+/// Pin Change Interrupt Request 0.
+#[cfg(target_arch = "avr")]
+#[no_mangle]
+pub unsafe extern "avr-interrupt" fn __vector_3() {
+    pin_change_interrupt(0);
+}
+
+/// Pin Change Interrupt Request 1.
+#[cfg(target_arch = "avr")]
+#[no_mangle]
+pub unsafe extern "avr-interrupt" fn __vector_4() {
+    pin_change_interrupt(1);
+}
+
+/// Pin Change Interrupt Request 2.
+#[cfg(target_arch = "avr")]
+#[no_mangle]
+pub unsafe extern "avr-interrupt" fn __vector_5() {
+    pin_change_interrupt(2);
+}
+
+/// Declares the interrupt service routine of an interrupt vector by its name in the
+/// datasheet, as `TIMER1_COMPA` or `PCINT0`, instead of the `__vector_N` symbol.
+/// A name which is not a vector of the ATMEGA328P does not compile.
+/// The vectors already defined by the library, as the external and pin change
+/// interrupts or `TIMER0_OVF` for `time`, cannot be taken again, which fails when linking.
+/// # Example
+/// ```ignore
+/// avr_interrupt!(TIMER1_COMPA, fn on_compare() {
+///     TICKS.fetch_add(1);
+/// });
+/// ```
+#[macro_export]
+macro_rules! avr_interrupt {
+    ($vector:ident, fn $handler:ident() $body:block) => {
+        fn $handler() $body
+        $crate::__avr_vector!($vector, $handler);
+    };
+}
+
+/// Gives the symbol of a vector of the ATMEGA328P, see `avr_interrupt!`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __avr_vector {
+    (INT0, $handler:ident) => {
+        $crate::__avr_isr!(__vector_1, $handler);
+    };
+    (INT1, $handler:ident) => {
+        $crate::__avr_isr!(__vector_2, $handler);
+    };
+    (PCINT0, $handler:ident) => {
+        $crate::__avr_isr!(__vector_3, $handler);
+    };
+    (PCINT1, $handler:ident) => {
+        $crate::__avr_isr!(__vector_4, $handler);
+    };
+    (PCINT2, $handler:ident) => {
+        $crate::__avr_isr!(__vector_5, $handler);
+    };
+    (WDT, $handler:ident) => {
+        $crate::__avr_isr!(__vector_6, $handler);
+    };
+    (TIMER2_COMPA, $handler:ident) => {
+        $crate::__avr_isr!(__vector_7, $handler);
+    };
+    (TIMER2_COMPB, $handler:ident) => {
+        $crate::__avr_isr!(__vector_8, $handler);
+    };
+    (TIMER2_OVF, $handler:ident) => {
+        $crate::__avr_isr!(__vector_9, $handler);
+    };
+    (TIMER1_CAPT, $handler:ident) => {
+        $crate::__avr_isr!(__vector_10, $handler);
+    };
+    (TIMER1_COMPA, $handler:ident) => {
+        $crate::__avr_isr!(__vector_11, $handler);
+    };
+    (TIMER1_COMPB, $handler:ident) => {
+        $crate::__avr_isr!(__vector_12, $handler);
+    };
+    (TIMER1_OVF, $handler:ident) => {
+        $crate::__avr_isr!(__vector_13, $handler);
+    };
+    (TIMER0_COMPA, $handler:ident) => {
+        $crate::__avr_isr!(__vector_14, $handler);
+    };
+    (TIMER0_COMPB, $handler:ident) => {
+        $crate::__avr_isr!(__vector_15, $handler);
+    };
+    (TIMER0_OVF, $handler:ident) => {
+        $crate::__avr_isr!(__vector_16, $handler);
+    };
+    (SPI_STC, $handler:ident) => {
+        $crate::__avr_isr!(__vector_17, $handler);
+    };
+    (USART_RX, $handler:ident) => {
+        $crate::__avr_isr!(__vector_18, $handler);
+    };
+    (USART0_RX, $handler:ident) => {
+        $crate::__avr_isr!(__vector_18, $handler);
+    };
+    (USART_UDRE, $handler:ident) => {
+        $crate::__avr_isr!(__vector_19, $handler);
+    };
+    (USART0_UDRE, $handler:ident) => {
+        $crate::__avr_isr!(__vector_19, $handler);
+    };
+    (USART_TX, $handler:ident) => {
+        $crate::__avr_isr!(__vector_20, $handler);
+    };
+    (USART0_TX, $handler:ident) => {
+        $crate::__avr_isr!(__vector_20, $handler);
+    };
+    (ADC, $handler:ident) => {
+        $crate::__avr_isr!(__vector_21, $handler);
+    };
+    (EE_READY, $handler:ident) => {
+        $crate::__avr_isr!(__vector_22, $handler);
+    };
+    (ANALOG_COMP, $handler:ident) => {
+        $crate::__avr_isr!(__vector_23, $handler);
+    };
+    (TWI, $handler:ident) => {
+        $crate::__avr_isr!(__vector_24, $handler);
+    };
+    (SPM_READY, $handler:ident) => {
+        $crate::__avr_isr!(__vector_25, $handler);
+    };
+    ($vector:ident, $handler:ident) => {
+        compile_error!(concat!(
+            "no interrupt vector ",
+            stringify!($vector),
+            " on the ATMEGA328P"
+        ));
+    };
+}
+
+/// Defines the interrupt service routine calling the handler, see `avr_interrupt!`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __avr_isr {
+    ($symbol:ident, $handler:ident) => {
+        #[cfg(target_arch = "avr")]
+        #[no_mangle]
+        pub unsafe extern "avr-interrupt" fn $symbol() {
+            $handler();
+        }
+    };
 }
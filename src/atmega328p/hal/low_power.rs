@@ -0,0 +1,209 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Devansh Kumar Jha, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Low power framework for ATMEGA328P built on the sleep modes, the power reduction
+//! register and the watchdog timer.
+//! Before sleeping, the clocks of all the peripherals which are not kept are stopped
+//! and the ADC is switched off, and after waking up everything is restored as it was.
+//! The chip can be woken up by the watchdog, Timer2 on its crystal, an external interrupt
+//! or the USART receiving data.
+//! See the section 9 and 10 of ATMEGA328P datasheet.
+
+// Other source code files to be used.
+use crate::atmega328p::hal::fuses::sleep_without_bod;
+use crate::atmega328p::hal::interrupts::{
+    attach_interrupt, detach_interrupt, without_interrupts, TriggerMode,
+};
+use crate::atmega328p::hal::power::{Peripherals, Power};
+use crate::atmega328p::hal::sleep_mode::{enable_mode, Sleep, SleepMode};
+use crate::atmega328p::hal::watchdog::{WatchDog, WatchdogMode, WatchdogPrescaler};
+use crate::atmega328p::registers::{ASSR, TCCR2B};
+use crate::delay::delay_ms;
+use crate::error::Error;
+
+// Crates which would be used in the implementation.
+use core::ptr::{read_volatile, write_volatile};
+
+// ADC Control and Status Register A, and its enable bit.
+const ADCSRA: *mut u8 = 0x7A as *mut u8;
+const ADEN: u8 = 7;
+
+/// Selection of the event which wakes the chip up.
+/// * `Watchdog` - the time-out of the watchdog timer.
+/// * `External` - an external interrupt (0 or 1) with its trigger. Only a `Low` level
+///   can wake up from the power down, power save and standby modes.
+/// * `UsartRx` - a byte received by the USART through the interrupt driven serial.
+///   The USART only runs in the idle mode, so the chip is put into it instead of the mode given.
+/// * `Timer2` - an interrupt of Timer2 clocked from its crystal, which keeps running
+///   in the power save mode, see `rtc::Rtc`.
+#[derive(Clone, Copy)]
+pub enum WakeSource {
+    Watchdog(WatchdogPrescaler),
+    External(u8, TriggerMode),
+    UsartRx,
+    Timer2,
+}
+
+/// Gives the bit of a peripheral in PRR.
+fn prr_bit(peripheral: Peripherals) -> u8 {
+    match peripheral {
+        Peripherals::TWI => 7,
+        Peripherals::Timer2 => 6,
+        Peripherals::Timer0 => 5,
+        Peripherals::Timer1 => 3,
+        Peripherals::SPI => 2,
+        Peripherals::USART0 => 1,
+        Peripherals::ADC => 0,
+    }
+}
+
+/// Does nothing, the external interrupt is only needed to wake the chip up.
+fn wake_up() {}
+
+/// Saves the state of the peripherals, puts the chip to sleep and restores it afterwards.
+pub struct LowPower {
+    // Mask of the bits of PRR to be kept running during sleep.
+    keep: u8,
+    // Whether the brown-out detector is switched off during sleep.
+    bod_off: bool,
+}
+
+impl LowPower {
+    /// Creates a manager which stops all the peripherals during sleep.
+    /// # Returns
+    /// * `a LowPower object` - which will be used to sleep.
+    pub fn new() -> LowPower {
+        LowPower {
+            keep: 0,
+            bod_off: false,
+        }
+    }
+
+    /// Keeps a peripheral running during sleep, for example Timer0 to keep `millis()` running.
+    /// # Arguments
+    /// * `peripheral` - a `Peripherals` object, the peripheral to be kept.
+    pub fn keep(&mut self, peripheral: Peripherals) {
+        self.keep |= 1 << prr_bit(peripheral);
+    }
+
+    /// Switches the brown-out detector off during the sleeps in the power down and power
+    /// save modes, see `fuses::sleep_without_bod`.
+    /// # Arguments
+    /// * `disable` - a boolean, true to switch the detector off during sleep.
+    pub fn disable_bod(&mut self, disable: bool) {
+        self.bod_off = disable;
+    }
+
+    /// Puts the chip to sleep until the given source wakes it up.
+    /// Global interrupts are enabled, since the chip is woken up by an interrupt.
+    /// # Arguments
+    /// * `mode` - a `SleepMode` object, the sleep mode to be entered.
+    /// * `source` - a `WakeSource` object, the event which ends the sleep.
+    /// # Returns
+    /// * `a Result<(), Error>` - which is `Error::InvalidParam` if the source does not
+    ///   exist on the chip, in which case the chip does not sleep.
+    pub fn sleep(&mut self, mode: SleepMode, source: WakeSource) -> Result<(), Error> {
+        let mut keep = self.keep;
+        let mut mode = mode;
+        match source {
+            WakeSource::Watchdog(prescaler) => unsafe {
+                WatchDog::new().enable(WatchdogMode::Interrupt, prescaler);
+            },
+            WakeSource::External(int, trigger) => attach_interrupt(int, trigger, wake_up)?,
+            WakeSource::UsartRx => {
+                keep |= 1 << prr_bit(Peripherals::USART0);
+                mode = SleepMode::Idle;
+            }
+            WakeSource::Timer2 => {
+                keep |= 1 << prr_bit(Peripherals::Timer2);
+                Self::sync_timer2();
+            }
+        }
+
+        unsafe {
+            let power = Power::new();
+            let prr = read_volatile(&power.prr);
+            let adcsra = read_volatile(ADCSRA);
+
+            // The ADC must be switched off before its clock is stopped.
+            if keep & 1 == 0 {
+                write_volatile(ADCSRA, adcsra & !(1 << ADEN));
+            }
+            write_volatile(&mut power.prr, prr | (!keep & 0xEF));
+
+            without_interrupts(|| enable_mode(mode));
+            if self.bod_off {
+                sleep_without_bod();
+            } else {
+                // The instruction after SEI is executed before any interrupt is served,
+                // so a wake up interrupt can not be missed before SLEEP.
+                llvm_asm!("sei
+                           sleep"
+                         :
+                         :
+                         :
+                         : "volatile");
+            }
+            Sleep::new().disable();
+
+            write_volatile(&mut power.prr, prr);
+            write_volatile(ADCSRA, adcsra);
+        }
+
+        match source {
+            WakeSource::Watchdog(_) => unsafe { WatchDog::new().disable() },
+            WakeSource::External(int, _) => detach_interrupt(int)?,
+            WakeSource::UsartRx => {}
+            WakeSource::Timer2 => {}
+        }
+        Ok(())
+    }
+
+    // Writes TCCR2B again and waits for the write to reach the clock domain of the crystal,
+    // as the chip would otherwise sleep again before the interrupt logic of Timer2 is ready.
+    fn sync_timer2() {
+        TCCR2B.write(TCCR2B.read());
+        while ASSR.is_set(ASSR::TCR2BUB) {}
+    }
+
+    /// Sleeps in the power down mode for about the given time, woken up by the watchdog.
+    /// The watchdog oscillator may be off by about 10%, and times shorter than
+    /// 16 ms are waited out with a delay.
+    /// # Arguments
+    /// * `ms` - a u32, the number of milliseconds to sleep.
+    pub fn sleep_for_ms(&mut self, ms: u32) {
+        let prescalers = [
+            WatchdogPrescaler::S8,
+            WatchdogPrescaler::S4,
+            WatchdogPrescaler::S2,
+            WatchdogPrescaler::S1,
+            WatchdogPrescaler::Ms500,
+            WatchdogPrescaler::Ms250,
+            WatchdogPrescaler::Ms125,
+            WatchdogPrescaler::Ms64,
+            WatchdogPrescaler::Ms32,
+            WatchdogPrescaler::Ms16,
+        ];
+        let mut remaining = ms;
+        for prescaler in prescalers.iter() {
+            while remaining >= prescaler.millis() {
+                let _ = self.sleep(SleepMode::PowerDown, WakeSource::Watchdog(*prescaler));
+                remaining -= prescaler.millis();
+            }
+        }
+        delay_ms(remaining);
+    }
+}
@@ -144,10 +144,10 @@ impl Pins {
 
 /// This function returns digital pin corresponding to it's number.
 /// # Arguments
-/// * `a u32` - The pin number which is to be used.
+/// * `a u8` - The pin number which is to be used.
 /// # Returns
 /// * `a Pin object` - The memory mapped I/O object to control the Digital Pin.
-fn _make_pin(pin: u8) -> Pin {
+pub fn make_pin(pin: u8) -> Pin {
     match pin {
         0 => return Pin::new(PortName::D, 0).unwrap(),
         1 => return Pin::new(PortName::D, 1).unwrap(),
@@ -158,12 +158,12 @@ fn _make_pin(pin: u8) -> Pin {
         6 => return Pin::new(PortName::D, 6).unwrap(),
         7 => return Pin::new(PortName::D, 7).unwrap(),
 
-        8 => return Pin::new(PortName::B, 8).unwrap(),
-        9 => return Pin::new(PortName::B, 9).unwrap(),
-        10 => return Pin::new(PortName::B, 10).unwrap(),
-        11 => return Pin::new(PortName::B, 11).unwrap(),
-        12 => return Pin::new(PortName::B, 12).unwrap(),
-        13 => return Pin::new(PortName::B, 13).unwrap(),
+        8 => return Pin::new(PortName::B, 0).unwrap(),
+        9 => return Pin::new(PortName::B, 1).unwrap(),
+        10 => return Pin::new(PortName::B, 2).unwrap(),
+        11 => return Pin::new(PortName::B, 3).unwrap(),
+        12 => return Pin::new(PortName::B, 4).unwrap(),
+        13 => return Pin::new(PortName::B, 5).unwrap(),
 
         _ => unreachable!(),
     }
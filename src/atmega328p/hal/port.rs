@@ -200,6 +200,18 @@ impl Pin {
     pub fn set_output(&mut self) {
         self.set_mode(IOMode::Output);
     }
+
+    /// Change pin mode to input by changing the DDR bit of that pin to 0.
+    pub fn set_input(&mut self) {
+        self.set_mode(IOMode::Input);
+    }
+
+    /// Reads the level on the pin from PINxn register.
+    /// # Returns
+    /// * `a boolean` - which is true if the pin is high.
+    pub fn is_high(&self) -> bool {
+        unsafe { read_volatile(&(*self.port).pin) & (0x1 << self.pin) != 0 }
+    }
 }
 
 impl AnalogPin {
@@ -229,3 +241,64 @@ impl DigitalPin {
         }
     }
 }
+
+/// Lets the drivers written against `GpioOps` use the pin.
+impl crate::common::GpioOps for Pin {
+    fn set_output(&mut self) {
+        Pin::set_output(self);
+    }
+
+    fn set_input(&mut self) {
+        Pin::set_input(self);
+    }
+
+    fn high(&mut self) {
+        Pin::high(self);
+    }
+
+    fn low(&mut self) {
+        Pin::low(self);
+    }
+
+    fn toggle(&mut self) {
+        Pin::toggle(self);
+    }
+
+    fn is_high(&self) -> bool {
+        Pin::is_high(self)
+    }
+}
+
+/// Gives the address in the I/O space of the PINx, DDRx or PORTx register of a port
+/// of the ATMEGA328P, see `fast_high!`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __port_io {
+    (B, PIN) => {
+        0x03
+    };
+    (B, DDR) => {
+        0x04
+    };
+    (B, PORT) => {
+        0x05
+    };
+    (C, PIN) => {
+        0x06
+    };
+    (C, DDR) => {
+        0x07
+    };
+    (C, PORT) => {
+        0x08
+    };
+    (D, PIN) => {
+        0x09
+    };
+    (D, DDR) => {
+        0x0A
+    };
+    (D, PORT) => {
+        0x0B
+    };
+}
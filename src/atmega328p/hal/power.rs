@@ -15,8 +15,15 @@
 // along with this program.  If not, see <https://www.gnu.org/licenses/>
 
 //! Generic implementation of power control through clock gating in ATMEGA2560P.
+//! The drivers enable the clock of their peripheral when they are created and gate it again
+//! in their `shutdown()`, and `Power::disable_all_unused()` gates every peripheral which
+//! is not switched on, for example before sleeping.
 //! Section 9.11 of ATmega328p Datasheet
 
+// Source codes required.
+use crate::atmega328p::registers::{ADCSRA, PRR, SPCR, TCCR0B, TCCR1B, TCCR2B, TWCR, UCSR0B};
+use crate::register::Field;
+
 /// Power reduction for ATmega328p chip
 /// Each of the Peripherals below refers to a bit in the PRR
 /// Setting 7th bit shuts down the TWI(2-wire serial interface) by stopping the clock to the module.
@@ -130,4 +137,57 @@ impl Power {
             Peripherals::ADC => Power::adc(&mut Power::new()),
         }
     }
+
+    /// Enables the clock of a peripheral, which must be done before its registers are used.
+    /// # Arguments
+    /// * `mode` - a `Peripherals` object, the peripheral whose clock is enabled.
+    pub fn enable_clock(mode: Peripherals) {
+        PRR.clear(Power::prr_bit(mode));
+    }
+
+    /// Checks whether the clock of a peripheral is running.
+    /// # Arguments
+    /// * `mode` - a `Peripherals` object, the peripheral to be checked.
+    /// # Returns
+    /// * `a boolean` - which is true if the PRR bit of the peripheral is clear.
+    pub fn is_clock_enabled(mode: Peripherals) -> bool {
+        !PRR.is_set(Power::prr_bit(mode))
+    }
+
+    /// Gates the clock of every peripheral which is not switched on: the TWI, SPI and ADC
+    /// when their enable bit is clear, the USART when both its receiver and transmitter
+    /// are disabled, and the timers which have no clock source selected.
+    /// Timer0 counts `millis` once `time::init` has been called, so it is kept then.
+    pub fn disable_all_unused() {
+        let unused = [
+            (Peripherals::TWI, !TWCR.is_set(TWCR::TWEN)),
+            (Peripherals::Timer2, TCCR2B.read_field(TCCR2B::CS2) == 0),
+            (Peripherals::Timer0, TCCR0B.read_field(TCCR0B::CS0) == 0),
+            (Peripherals::Timer1, TCCR1B.read_field(TCCR1B::CS1) == 0),
+            (Peripherals::SPI, !SPCR.is_set(SPCR::SPE)),
+            (
+                Peripherals::USART0,
+                !UCSR0B.is_set(UCSR0B::RXEN0) && !UCSR0B.is_set(UCSR0B::TXEN0),
+            ),
+            (Peripherals::ADC, !ADCSRA.is_set(ADCSRA::ADEN)),
+        ];
+        for (peripheral, unused) in unused.iter() {
+            if *unused {
+                Power::disable_clock(*peripheral);
+            }
+        }
+    }
+
+    // Gives the bit of PRR which gates the clock of a peripheral.
+    fn prr_bit(mode: Peripherals) -> Field {
+        match mode {
+            Peripherals::TWI => PRR::PRTWI,
+            Peripherals::Timer2 => PRR::PRTIM2,
+            Peripherals::Timer0 => PRR::PRTIM0,
+            Peripherals::Timer1 => PRR::PRTIM1,
+            Peripherals::SPI => PRR::PRSPI,
+            Peripherals::USART0 => PRR::PRUSART0,
+            Peripherals::ADC => PRR::PRADC,
+        }
+    }
 }
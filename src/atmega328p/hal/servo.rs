@@ -0,0 +1,268 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Control of up to 12 hobby servos on any digital pins of ATMEGA328P.
+//! Timer1 counts in microsecond steps and its compare match A interrupt raises the
+//! pin of each servo in turn for its pulse width, so every servo gets one pulse
+//! in each 20 ms frame (50 Hz). PWM on pins 9 and 10 does not work while servos are attached.
+//! Refer to section 16 of ATMEGA328P datasheet.
+
+// Source codes required.
+use crate::atmega328p::hal::analog::{Timer16, TimerNo16};
+use crate::atmega328p::hal::interrupts::without_interrupts;
+use crate::atmega328p::hal::pin::make_pin;
+use crate::atmega328p::hal::port::Port;
+use crate::atmega328p::hal::power::Power;
+use crate::config::CPU_FREQUENCY_HZ;
+
+// Crates to be used for the implementation.
+use bit_field::BitField;
+use core::ptr::{read_volatile, write_volatile};
+
+/// Maximum number of servos which can be attached.
+pub const SERVOS_PER_TIMER: usize = 12;
+/// Default pulse width for the angle 0, in microseconds.
+pub const MIN_PULSE_WIDTH: u16 = 544;
+/// Default pulse width for the angle 180, in microseconds.
+pub const MAX_PULSE_WIDTH: u16 = 2400;
+/// Pulse width given to a servo when it is attached, in microseconds.
+pub const DEFAULT_PULSE_WIDTH: u16 = 1500;
+/// Time between two pulses of a servo, in microseconds.
+pub const REFRESH_INTERVAL: u32 = 20000;
+
+// Timer/Counter1 Interrupt Mask and Flag Registers, and the compare match A bits.
+const TIMSK1: *mut u8 = 0x6F as *mut u8;
+const TIFR1: *mut u8 = 0x36 as *mut u8;
+const OCIE1A: u8 = 1;
+const OCF1A: u8 = 1;
+
+// Timer1 runs with a prescaler of 8.
+const TICKS_PER_US: u32 = CPU_FREQUENCY_HZ / 8 / 1_000_000;
+
+/// The pin and the pulse width of an attached servo.
+#[derive(Clone, Copy)]
+struct Channel {
+    port: *mut Port,
+    mask: u8,
+    ticks: u16,
+}
+
+const EMPTY_CHANNEL: Channel = Channel {
+    port: 0 as *mut Port,
+    mask: 0,
+    ticks: 0,
+};
+
+// The channels, the number of them in use, and the channel whose pulse is
+// running, which is negative during the rest of the frame.
+static mut CHANNELS: [Channel; SERVOS_PER_TIMER] = [EMPTY_CHANNEL; SERVOS_PER_TIMER];
+static mut CHANNEL_COUNT: usize = 0;
+static mut CURRENT: isize = -1;
+
+/// Converts microseconds into timer ticks.
+fn us_to_ticks(us: u16) -> u16 {
+    (us as u32 * TICKS_PER_US) as u16
+}
+
+/// Converts timer ticks into microseconds.
+fn ticks_to_us(ticks: u16) -> u16 {
+    (ticks as u32 / TICKS_PER_US) as u16
+}
+
+/// Starts Timer1 in normal mode with the compare match A interrupt.
+unsafe fn start_timer() {
+    // PRTIM1 is bit 3 of PRR.
+    let power = Power::new();
+    write_volatile(&mut power.prr, read_volatile(&power.prr) & !(1 << 3));
+    let timer = Timer16::new(TimerNo16::Timer1);
+    timer.tccra.write(0);
+    // CS12:0 = 010 for a prescaler of 8.
+    timer.tccrb.write(0b010);
+    timer.set_counter(0);
+    timer.set_compare_a(us_to_ticks(REFRESH_INTERVAL as u16));
+    CURRENT = -1;
+
+    write_volatile(TIFR1, 1 << OCF1A);
+    let mut timsk = read_volatile(TIMSK1);
+    timsk.set_bit(OCIE1A, true);
+    write_volatile(TIMSK1, timsk);
+}
+
+/// Stops the compare match A interrupt of Timer1.
+unsafe fn stop_timer() {
+    let mut timsk = read_volatile(TIMSK1);
+    timsk.set_bit(OCIE1A, false);
+    write_volatile(TIMSK1, timsk);
+}
+
+/// A servo attached to a digital pin.
+pub struct Servo {
+    index: usize,
+    min: u16,
+    max: u16,
+}
+
+impl Servo {
+    /// Attaches a servo to a pin, which starts receiving pulses of `DEFAULT_PULSE_WIDTH`.
+    /// Global interrupts must be enabled for the pulses to be generated.
+    /// # Arguments
+    /// * `pin` - a u8, the digital pin number.
+    /// # Returns
+    /// * `a Option<Servo>` - which is None if `SERVOS_PER_TIMER` servos are already attached.
+    pub fn attach(pin: u8) -> Option<Servo> {
+        Servo::attach_with_limits(pin, MIN_PULSE_WIDTH, MAX_PULSE_WIDTH)
+    }
+
+    /// Attaches a servo to a pin with its own pulse widths for the angles 0 and 180.
+    /// # Arguments
+    /// * `pin` - a u8, the digital pin number.
+    /// * `min` - a u16, the pulse width for the angle 0, in microseconds.
+    /// * `max` - a u16, the pulse width for the angle 180, in microseconds.
+    /// # Returns
+    /// * `a Option<Servo>` - which is None if `SERVOS_PER_TIMER` servos are already attached.
+    pub fn attach_with_limits(pin: u8, min: u16, max: u16) -> Option<Servo> {
+        let mut digital = make_pin(pin);
+        digital.set_output();
+
+        without_interrupts(|| unsafe {
+            let index = CHANNELS.iter().position(|channel| channel.port.is_null())?;
+            CHANNELS[index] = Channel {
+                port: digital.port,
+                mask: 1 << digital.pin,
+                ticks: us_to_ticks(DEFAULT_PULSE_WIDTH),
+            };
+            if index >= CHANNEL_COUNT {
+                CHANNEL_COUNT = index + 1;
+            }
+            if CHANNELS
+                .iter()
+                .filter(|channel| !channel.port.is_null())
+                .count()
+                == 1
+            {
+                start_timer();
+            }
+            Some(Servo { index, min, max })
+        })
+    }
+
+    /// Changes the pulse widths for the angles 0 and 180.
+    /// # Arguments
+    /// * `min` - a u16, the pulse width for the angle 0, in microseconds.
+    /// * `max` - a u16, the pulse width for the angle 180, in microseconds.
+    pub fn set_limits(&mut self, min: u16, max: u16) {
+        self.min = min;
+        self.max = max;
+    }
+
+    /// Turns the servo to an angle.
+    /// # Arguments
+    /// * `angle` - a u8, the angle in degrees from 0 to 180.
+    pub fn write(&mut self, angle: u8) {
+        let angle = angle.min(180) as u32;
+        let span = (self.max - self.min) as u32;
+        self.write_microseconds(self.min + (angle * span / 180) as u16);
+    }
+
+    /// Sets the pulse width of the servo, limited to the calibrated range.
+    /// # Arguments
+    /// * `us` - a u16, the pulse width in microseconds.
+    pub fn write_microseconds(&mut self, us: u16) {
+        let us = if us < self.min {
+            self.min
+        } else if us > self.max {
+            self.max
+        } else {
+            us
+        };
+        let ticks = us_to_ticks(us);
+        without_interrupts(|| unsafe {
+            CHANNELS[self.index].ticks = ticks;
+        });
+    }
+
+    /// Gives the angle the servo was last turned to.
+    /// # Returns
+    /// * `a u8` - the angle in degrees from 0 to 180.
+    pub fn read(&self) -> u8 {
+        let us = self.read_microseconds() as u32;
+        let span = (self.max - self.min) as u32;
+        if span == 0 || us <= self.min as u32 {
+            return 0;
+        }
+        (((us - self.min as u32) * 180 + span / 2) / span) as u8
+    }
+
+    /// Gives the pulse width of the servo.
+    /// # Returns
+    /// * `a u16` - the pulse width in microseconds.
+    pub fn read_microseconds(&self) -> u16 {
+        let ticks = without_interrupts(|| unsafe { CHANNELS[self.index].ticks });
+        ticks_to_us(ticks)
+    }
+
+    /// Stops the pulses of the servo and frees its channel.
+    /// The interrupt of Timer1 is stopped once no servo is attached.
+    pub fn detach(self) {
+        without_interrupts(|| unsafe {
+            let channel = CHANNELS[self.index];
+            let port = read_volatile(&(*channel.port).port);
+            write_volatile(&mut (*channel.port).port, port & !channel.mask);
+            CHANNELS[self.index] = EMPTY_CHANNEL;
+
+            while CHANNEL_COUNT > 0 && CHANNELS[CHANNEL_COUNT - 1].port.is_null() {
+                CHANNEL_COUNT -= 1;
+            }
+            if CHANNEL_COUNT == 0 {
+                stop_timer();
+            }
+        });
+    }
+}
+
+/// Timer/Counter1 Compare Match A, ends the pulse of a servo and starts the next one.
+#[cfg(target_arch = "avr")]
+#[no_mangle]
+pub unsafe extern "avr-interrupt" fn __vector_11() {
+    let timer = Timer16::new(TimerNo16::Timer1);
+    if CURRENT < 0 {
+        // A new frame starts.
+        timer.set_counter(0);
+    } else {
+        let channel = CHANNELS[CURRENT as usize];
+        if !channel.port.is_null() {
+            let port = read_volatile(&(*channel.port).port);
+            write_volatile(&mut (*channel.port).port, port & !channel.mask);
+        }
+    }
+
+    CURRENT += 1;
+    if (CURRENT as usize) < CHANNEL_COUNT {
+        let channel = CHANNELS[CURRENT as usize];
+        let start = timer.counter();
+        timer.set_compare_a(start.wrapping_add(channel.ticks));
+        if !channel.port.is_null() {
+            let port = read_volatile(&(*channel.port).port);
+            write_volatile(&mut (*channel.port).port, port | channel.mask);
+        }
+    } else {
+        // Waits for the rest of the frame, at least a few microseconds.
+        let refresh = us_to_ticks(REFRESH_INTERVAL as u16);
+        let next = timer.counter() + us_to_ticks(4);
+        timer.set_compare_a(if next < refresh { refresh } else { next });
+        CURRENT = -1;
+    }
+}
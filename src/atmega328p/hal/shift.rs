@@ -14,7 +14,16 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>
 
-use crate::atmega328p::hal::pin::Pins;
+//! Shift registers driven bit by bit on any digital pins of ATMEGA328P.
+//! `shift_out` and `shift_in` move one byte, as in the Arduino library. `HC595` drives
+//! a chain of 74HC595 serial in, parallel out registers, giving 8 outputs for each
+//! register from three pins, and `HC165` reads a chain of 74HC165 parallel in, serial out
+//! registers, giving 8 inputs for each register from three pins.
+//! See `<https://www.ti.com/lit/ds/symlink/sn74hc595.pdf>` and
+//! `<https://www.ti.com/lit/ds/symlink/sn74hc165.pdf>`.
+
+use crate::atmega328p::hal::pin::{make_pin, Pins};
+use crate::atmega328p::hal::port::Pin;
 use core::usize;
 
 /// Enum for bit order of the value.
@@ -33,25 +42,21 @@ pub enum BitOrder {
 /// * `a u8` - The value stored in the shift register.
 pub fn shift_in(datapin: usize, clockpin: usize, bit_order: BitOrder) -> u8 {
     let mut value: u8 = 0;
-    let mut i: u8 = 0;
     let pins = Pins::new();
-    let mut data = pins.digital[datapin];
+    let data = pins.digital[datapin].pin;
     let mut clock = pins.digital[clockpin];
-    loop {
+    for i in 0..8 {
         clock.high();
 
+        let bit = data.is_high() as u8;
         match bit_order {
-            BitOrder::LSBFIRST => value |= data.read() << i,
-            BitOrder::MSBFIRST => value |= data.read() << (7 - i),
+            BitOrder::LSBFIRST => value |= bit << i,
+            BitOrder::MSBFIRST => value |= bit << (7 - i),
         }
 
         clock.low();
-
-        i += 1;
-        if i == 7 {
-            return value;
-        }
     }
+    value
 }
 
 /// Stores value in the Shift Register.
@@ -61,12 +66,11 @@ pub fn shift_in(datapin: usize, clockpin: usize, bit_order: BitOrder) -> u8 {
 /// * `bit_order` - a `BitOrder` object, to specify the order of bits in the shift register.
 /// * `value` - a mutable u8, which will store the value which is to be written.
 pub fn shift_out(datapin: usize, clockpin: usize, bit_order: BitOrder, mut value: u8) {
-    let mut i: u8 = 0;
     let pins = Pins::new();
     let mut data = pins.digital[datapin];
     let mut clock = pins.digital[clockpin];
 
-    loop {
+    for _ in 0..8 {
         match bit_order {
             BitOrder::LSBFIRST => {
                 if value & 1 == 1 {
@@ -88,10 +92,183 @@ pub fn shift_out(datapin: usize, clockpin: usize, bit_order: BitOrder, mut value
         }
         clock.high();
         clock.low();
+    }
+}
+
+/// Writes one bit on the data pin and shifts it in with a rising edge of the clock.
+fn clock_bit(data: &mut Pin, clock: &mut Pin, bit: bool) {
+    if bit {
+        data.high();
+    } else {
+        data.low();
+    }
+    clock.high();
+    clock.low();
+}
+
+/// Used to drive a chain of `N` 74HC595 registers, where the QH' output of each register
+/// goes to the SER input of the next one. Output enable is tied low and reset high.
+/// # Elements
+/// * `data` - a `Pin` object, connected to SER of the first register.
+/// * `clock` - a `Pin` object, connected to SRCLK of all the registers.
+/// * `latch` - a `Pin` object, connected to RCLK of all the registers.
+/// * `state` - a array of u8, the outputs of each register, the first register at index 0
+///   with QA as bit 0.
+pub struct HC595<const N: usize> {
+    data: Pin,
+    clock: Pin,
+    latch: Pin,
+    state: [u8; N],
+}
+
+impl<const N: usize> HC595<N> {
+    /// Sets the pins as outputs and turns all the outputs of the registers low.
+    /// # Arguments
+    /// * `data` - a u8, the digital pin connected to SER.
+    /// * `clock` - a u8, the digital pin connected to SRCLK.
+    /// * `latch` - a u8, the digital pin connected to RCLK.
+    /// # Returns
+    /// * `a HC595 object` - which will be used to set the outputs.
+    pub fn new(data: u8, clock: u8, latch: u8) -> HC595<N> {
+        let mut registers = HC595 {
+            data: make_pin(data),
+            clock: make_pin(clock),
+            latch: make_pin(latch),
+            state: [0; N],
+        };
+        registers.data.set_output();
+        registers.data.low();
+        registers.clock.set_output();
+        registers.clock.low();
+        registers.latch.set_output();
+        registers.latch.low();
+        registers.update();
+        registers
+    }
+
+    /// Sets the outputs of all the registers at once.
+    /// # Arguments
+    /// * `values` - a array of u8, the outputs of each register, the first register at index 0.
+    pub fn write(&mut self, values: [u8; N]) {
+        self.state = values;
+        self.update();
+    }
+
+    /// Sets one output.
+    /// # Arguments
+    /// * `output` - a usize, the output, 8 times the register plus the bit, QA being 0.
+    /// * `high` - a bool, the new level.
+    pub fn set_output(&mut self, output: usize, high: bool) {
+        self.set_output_deferred(output, high);
+        self.update();
+    }
+
+    /// Changes one output in memory only, so that several outputs can change at the same
+    /// time with a single `update`.
+    /// # Arguments
+    /// * `output` - a usize, the output, 8 times the register plus the bit, QA being 0.
+    /// * `high` - a bool, the new level.
+    pub fn set_output_deferred(&mut self, output: usize, high: bool) {
+        let mask = 1 << (output % 8);
+        if high {
+            self.state[output / 8] |= mask;
+        } else {
+            self.state[output / 8] &= !mask;
+        }
+    }
 
-        i += 1;
-        if i == 7 {
-            return;
+    /// Gives the level of one output, as last set.
+    pub fn output(&self, output: usize) -> bool {
+        self.state[output / 8] & (1 << (output % 8)) != 0
+    }
+
+    /// Gives the outputs of all the registers, as last set.
+    pub fn state(&self) -> [u8; N] {
+        self.state
+    }
+
+    /// Turns all the outputs low.
+    pub fn clear(&mut self) {
+        self.write([0; N]);
+    }
+
+    /// Shifts the state into the chain and latches it to the outputs, which all change
+    /// at the rising edge of the latch.
+    pub fn update(&mut self) {
+        // The last register of the chain is shifted first, and QH first in each register.
+        for &value in self.state.iter().rev() {
+            for bit in (0..8).rev() {
+                clock_bit(&mut self.data, &mut self.clock, value & (1 << bit) != 0);
+            }
+        }
+        self.latch.high();
+        self.latch.low();
+    }
+}
+
+/// Used to read a chain of `N` 74HC165 registers, where the QH output of each register
+/// goes to the SER input of the previous one. Clock inhibit is tied low.
+/// # Elements
+/// * `data` - a `Pin` object, connected to QH of the first register.
+/// * `clock` - a `Pin` object, connected to CLK of all the registers.
+/// * `load` - a `Pin` object, connected to SH/LD of all the registers.
+pub struct HC165<const N: usize> {
+    data: Pin,
+    clock: Pin,
+    load: Pin,
+}
+
+impl<const N: usize> HC165<N> {
+    /// Sets the clock and load pins as outputs and the data pin as input.
+    /// # Arguments
+    /// * `data` - a u8, the digital pin connected to QH.
+    /// * `clock` - a u8, the digital pin connected to CLK.
+    /// * `load` - a u8, the digital pin connected to SH/LD.
+    /// # Returns
+    /// * `a HC165 object` - which will be used to read the inputs.
+    pub fn new(data: u8, clock: u8, load: u8) -> HC165<N> {
+        let mut registers = HC165 {
+            data: make_pin(data),
+            clock: make_pin(clock),
+            load: make_pin(load),
+        };
+        registers.data.set_input();
+        registers.clock.set_output();
+        registers.clock.low();
+        registers.load.set_output();
+        registers.load.high();
+        registers
+    }
+
+    /// Latches the inputs of all the registers and reads them.
+    /// # Returns
+    /// * `a array of u8` - the inputs of each register, the first register at index 0
+    ///   with A as bit 0.
+    pub fn read(&mut self) -> [u8; N] {
+        // The inputs are loaded while SH/LD is low.
+        self.load.low();
+        self.load.high();
+
+        let mut values = [0; N];
+        for value in values.iter_mut() {
+            // H comes out first, and the next bit at each rising edge of the clock.
+            for bit in (0..8).rev() {
+                if self.data.is_high() {
+                    *value |= 1 << bit;
+                }
+                self.clock.high();
+                self.clock.low();
+            }
         }
+        values
+    }
+
+    /// Latches the inputs and reads one of them.
+    /// # Arguments
+    /// * `input` - a usize, the input, 8 times the register plus the bit, A being 0.
+    /// # Returns
+    /// * `a bool` - the level of the input.
+    pub fn read_input(&mut self, input: usize) -> bool {
+        self.read()[input / 8] & (1 << (input % 8)) != 0
     }
 }
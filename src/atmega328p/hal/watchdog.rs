@@ -16,9 +16,11 @@
 
 //! Control on Watchdog timer in ATMEGA328P
 //! Watchdog timer 10.9 of the manual.
+//! The timer can reset the chip, or call an interrupt which wakes the chip up from sleep.
 
 use crate::atmega328p::hal::interrupts;
-use core::ptr::{read_volatile, write_volatile};
+use crate::atmega328p::registers::{MCUSR, WDTCSR};
+use crate::sync;
 
 /// MCUSR (MCU Status Register)
 /// The MCU status register provides information on which reset source caused an MCU reset.
@@ -30,11 +32,10 @@ use core::ptr::{read_volatile, write_volatile};
 ///     Interrupt mode                                        Interrupt
 ///     System reset mode                                       Reset
 /// Interrupt and system reset mode         Interrupt, then go to system reset mode
-#[repr(C, packed)]
+///
+/// Both registers are reached through the register map, so the structure holds nothing.
 pub struct WatchDog {
-    mcusr: u8,
-    _pad: [u8; 10],
-    wdtcsr: u8,
+    _private: (),
 }
 
 impl WatchDog {
@@ -42,28 +43,138 @@ impl WatchDog {
     /// # Returns
     /// * `a reference to Watchdog structure` - for further implementations.
     pub unsafe fn new() -> &'static mut WatchDog {
-        &mut *(0x55 as *mut WatchDog)
+        &mut *(MCUSR.address() as *mut WatchDog)
     }
 
     /// Resets watchdog timer.
     pub fn reset_watchdog(&mut self) {
-        unsafe {
-            let mut ctrl_mcusr = read_volatile(&self.mcusr);
-            ctrl_mcusr &= 0x7;
-            write_volatile(&mut self.mcusr, ctrl_mcusr);
-        }
+        MCUSR.clear(MCUSR::WDRF);
     }
 
     /// Disables watchdog
+    /// Global interrupts are left as they were before the call.
     pub fn disable(&mut self) {
-        unsafe {
-            interrupts::Interrupt::disable(&mut interrupts::Interrupt::new());
-            WatchDog::reset_watchdog(&mut WatchDog::new());
-            let mut ctrl_wdtcsr = read_volatile(&self.wdtcsr);
-            ctrl_wdtcsr |= 0x18;
-            write_volatile(&mut self.wdtcsr, ctrl_wdtcsr);
-            write_volatile(&mut self.wdtcsr, 0x00);
-            interrupts::Interrupt::enable(&mut interrupts::Interrupt::new());
+        sync::free(|_| {
+            self.reset_watchdog();
+            WDTCSR.modify(|wdtcsr| wdtcsr | (WDTCSR::WDCE.mask() | WDTCSR::WDE.mask()) as u8);
+            WDTCSR.write(0x00);
+        })
+    }
+}
+
+/// Selection of the action taken when the watchdog timer times out.
+/// * `Interrupt` - the watchdog interrupt is called, for example to wake up from sleep.
+/// * `Reset` - the chip is reset.
+/// * `InterruptReset` - the interrupt is called first and the chip is reset on the next
+///   time-out, unless the interrupt is enabled again with `enable_interrupt()`.
+#[derive(Clone, Copy)]
+pub enum WatchdogMode {
+    Interrupt,
+    Reset,
+    InterruptReset,
+}
+
+/// Selection of the time-out of the watchdog timer, from the prescaler of the
+/// 128 kHz watchdog oscillator.
+#[derive(Clone, Copy)]
+pub enum WatchdogPrescaler {
+    Ms16,
+    Ms32,
+    Ms64,
+    Ms125,
+    Ms250,
+    Ms500,
+    S1,
+    S2,
+    S4,
+    S8,
+}
+
+impl WatchdogPrescaler {
+    /// Gives the value of the WDP3:0 bits, with WDP3 at bit 5 of WDTCSR.
+    fn bits(&self) -> u8 {
+        let wdp = *self as u8;
+        (wdp & 0x08) << 2 | (wdp & 0x07)
+    }
+
+    /// Gives the approximate time-out in milliseconds.
+    /// # Returns
+    /// * `a u32` - the time-out, which may differ by about 10% with the voltage and temperature.
+    pub fn millis(&self) -> u32 {
+        match self {
+            WatchdogPrescaler::Ms16 => 16,
+            WatchdogPrescaler::Ms32 => 32,
+            WatchdogPrescaler::Ms64 => 64,
+            WatchdogPrescaler::Ms125 => 125,
+            WatchdogPrescaler::Ms250 => 250,
+            WatchdogPrescaler::Ms500 => 500,
+            WatchdogPrescaler::S1 => 1000,
+            WatchdogPrescaler::S2 => 2000,
+            WatchdogPrescaler::S4 => 4000,
+            WatchdogPrescaler::S8 => 8000,
         }
     }
 }
+
+// Function called from the watchdog interrupt.
+static mut WATCHDOG_HANDLER: Option<fn()> = None;
+
+impl WatchDog {
+    /// Starts the watchdog timer in the given mode.
+    /// # Arguments
+    /// * `mode` - a `WatchdogMode` object, the action taken on time-out.
+    /// * `prescaler` - a `WatchdogPrescaler` object, the time-out of the timer.
+    pub fn enable(&mut self, mode: WatchdogMode, prescaler: WatchdogPrescaler) {
+        let mut wdtcsr = prescaler.bits() | WDTCSR::WDIF.mask() as u8;
+        match mode {
+            WatchdogMode::Interrupt => wdtcsr |= WDTCSR::WDIE.mask() as u8,
+            WatchdogMode::Reset => wdtcsr |= WDTCSR::WDE.mask() as u8,
+            WatchdogMode::InterruptReset => {
+                wdtcsr |= (WDTCSR::WDIE.mask() | WDTCSR::WDE.mask()) as u8
+            }
+        }
+        interrupts::without_interrupts(|| unsafe {
+            llvm_asm!("wdr" :::: "volatile");
+            if let WatchdogMode::Interrupt = mode {
+                // WDE is forced to one as long as WDRF is set.
+                MCUSR.clear(MCUSR::WDRF);
+            }
+            // The new value must be written within four cycles of setting WDCE.
+            llvm_asm!("sts 0x60, $0
+                       sts 0x60, $1"
+                     :
+                     : "r"((WDTCSR::WDCE.mask() | WDTCSR::WDE.mask()) as u8), "r"(wdtcsr)
+                     :
+                     : "volatile");
+        });
+    }
+
+    /// Restarts the watchdog timer, which must be done before each time-out in the reset mode.
+    pub fn feed(&mut self) {
+        unsafe { llvm_asm!("wdr" :::: "volatile") }
+    }
+
+    /// Enables the watchdog interrupt again, which is disabled by hardware when it
+    /// is called in the `InterruptReset` mode.
+    pub fn enable_interrupt(&mut self) {
+        WDTCSR.set(WDTCSR::WDIE);
+    }
+
+    /// Sets the function called on every time-out in the interrupt modes.
+    /// # Arguments
+    /// * `handler` - a function, which is called from the interrupt service routine.
+    pub fn on_timeout(&mut self, handler: fn()) {
+        interrupts::without_interrupts(|| unsafe {
+            WATCHDOG_HANDLER = Some(handler);
+        });
+    }
+}
+
+/// Watchdog Time-out Interrupt.
+#[cfg(target_arch = "avr")]
+#[no_mangle]
+pub unsafe extern "avr-interrupt" fn __vector_6() {
+    if let Some(handler) = WATCHDOG_HANDLER {
+        handler();
+    }
+}
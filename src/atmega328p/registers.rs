@@ -0,0 +1,183 @@
+// RustDuino : A generic HAL implementation for Arduino Boards in Rust
+// Copyright (C) 2021 Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Register map of the ATMEGA328P, section 30 (Register Summary) of the manual.
+//! Each register is a `Register` constant at its data space address, and its fields are
+//! in the module of the same name, as `WDTCSR` and `WDTCSR::WDE`.
+
+// Crates which would be used in the implementation.
+use crate::register::register_map;
+
+register_map! {
+    /// Port B Input Pins Address.
+    PINB: u8 @ 0x23 {}
+    /// Port B Data Direction Register.
+    DDRB: u8 @ 0x24 {}
+    /// Port B Data Register.
+    PORTB: u8 @ 0x25 {}
+    /// Port C Input Pins Address.
+    PINC: u8 @ 0x26 {}
+    /// Port C Data Direction Register.
+    DDRC: u8 @ 0x27 {}
+    /// Port C Data Register.
+    PORTC: u8 @ 0x28 {}
+    /// Port D Input Pins Address.
+    PIND: u8 @ 0x29 {}
+    /// Port D Data Direction Register.
+    DDRD: u8 @ 0x2A {}
+    /// Port D Data Register.
+    PORTD: u8 @ 0x2B {}
+    /// Timer/Counter0 Interrupt Flag Register.
+    TIFR0: u8 @ 0x35 { OCF0B: 2, OCF0A: 1, TOV0: 0 }
+    /// Timer/Counter1 Interrupt Flag Register.
+    TIFR1: u8 @ 0x36 { ICF1: 5, OCF1B: 2, OCF1A: 1, TOV1: 0 }
+    /// Timer/Counter2 Interrupt Flag Register.
+    TIFR2: u8 @ 0x37 { OCF2B: 2, OCF2A: 1, TOV2: 0 }
+    /// Pin Change Interrupt Flag Register.
+    PCIFR: u8 @ 0x3B { PCIF2: 2, PCIF1: 1, PCIF0: 0 }
+    /// External Interrupt Flag Register.
+    EIFR: u8 @ 0x3C { INTF1: 1, INTF0: 0 }
+    /// External Interrupt Mask Register.
+    EIMSK: u8 @ 0x3D { INT1: 1, INT0: 0 }
+    /// General Purpose I/O Register 0.
+    GPIOR0: u8 @ 0x3E {}
+    /// EEPROM Control Register.
+    EECR: u8 @ 0x3F { EEPM: 4..6, EERIE: 3, EEMPE: 2, EEPE: 1, EERE: 0 }
+    /// EEPROM Data Register.
+    EEDR: u8 @ 0x40 {}
+    /// EEPROM Address Register.
+    EEAR: u16 @ 0x41 {}
+    /// General Timer/Counter Control Register.
+    GTCCR: u8 @ 0x43 { TSM: 7, PSRASY: 1, PSRSYNC: 0 }
+    /// Timer/Counter0 Control Register A.
+    TCCR0A: u8 @ 0x44 { COM0A: 6..8, COM0B: 4..6, WGM0: 0..2 }
+    /// Timer/Counter0 Control Register B.
+    TCCR0B: u8 @ 0x45 { FOC0A: 7, FOC0B: 6, WGM02: 3, CS0: 0..3 }
+    /// Timer/Counter0.
+    TCNT0: u8 @ 0x46 {}
+    /// Output Compare Register 0 A.
+    OCR0A: u8 @ 0x47 {}
+    /// Output Compare Register 0 B.
+    OCR0B: u8 @ 0x48 {}
+    /// General Purpose I/O Register 1.
+    GPIOR1: u8 @ 0x4A {}
+    /// General Purpose I/O Register 2.
+    GPIOR2: u8 @ 0x4B {}
+    /// SPI Control Register.
+    SPCR: u8 @ 0x4C { SPIE: 7, SPE: 6, DORD: 5, MSTR: 4, CPOL: 3, CPHA: 2, SPR: 0..2 }
+    /// SPI Status Register.
+    SPSR: u8 @ 0x4D { SPIF: 7, WCOL: 6, SPI2X: 0 }
+    /// SPI Data Register.
+    SPDR: u8 @ 0x4E {}
+    /// Analog Comparator Control and Status Register.
+    ACSR: u8 @ 0x50 { ACD: 7, ACBG: 6, ACO: 5, ACI: 4, ACIE: 3, ACIC: 2, ACIS: 0..2 }
+    /// Sleep Mode Control Register.
+    SMCR: u8 @ 0x53 { SM: 1..4, SE: 0 }
+    /// MCU Status Register, the source of the last reset.
+    MCUSR: u8 @ 0x54 { WDRF: 3, BORF: 2, EXTRF: 1, PORF: 0 }
+    /// MCU Control Register.
+    MCUCR: u8 @ 0x55 { BODS: 6, BODSE: 5, PUD: 4, IVSEL: 1, IVCE: 0 }
+    /// Store Program Memory Control and Status Register.
+    SPMCSR: u8 @ 0x57 { SPMIE: 7, RWWSB: 6, SIGRD: 5, RWWSRE: 4, BLBSET: 3, PGWRT: 2, PGERS: 1, SPMEN: 0 }
+    /// Stack Pointer.
+    SP: u16 @ 0x5D {}
+    /// Status Register.
+    SREG: u8 @ 0x5F { I: 7, T: 6, H: 5, S: 4, V: 3, N: 2, Z: 1, C: 0 }
+    /// Watchdog Timer Control Register.
+    WDTCSR: u8 @ 0x60 { WDIF: 7, WDIE: 6, WDP3: 5, WDCE: 4, WDE: 3, WDP: 0..3 }
+    /// Clock Prescale Register.
+    CLKPR: u8 @ 0x61 { CLKPCE: 7, CLKPS: 0..4 }
+    /// Power Reduction Register.
+    PRR: u8 @ 0x64 { PRTWI: 7, PRTIM2: 6, PRTIM0: 5, PRTIM1: 3, PRSPI: 2, PRUSART0: 1, PRADC: 0 }
+    /// Oscillator Calibration Register.
+    OSCCAL: u8 @ 0x66 {}
+    /// Pin Change Interrupt Control Register.
+    PCICR: u8 @ 0x68 { PCIE2: 2, PCIE1: 1, PCIE0: 0 }
+    /// External Interrupt Control Register A.
+    EICRA: u8 @ 0x69 { ISC1: 2..4, ISC0: 0..2 }
+    /// Pin Change Mask Register 0.
+    PCMSK0: u8 @ 0x6B {}
+    /// Pin Change Mask Register 1.
+    PCMSK1: u8 @ 0x6C {}
+    /// Pin Change Mask Register 2.
+    PCMSK2: u8 @ 0x6D {}
+    /// Timer/Counter0 Interrupt Mask Register.
+    TIMSK0: u8 @ 0x6E { OCIE0B: 2, OCIE0A: 1, TOIE0: 0 }
+    /// Timer/Counter1 Interrupt Mask Register.
+    TIMSK1: u8 @ 0x6F { ICIE1: 5, OCIE1B: 2, OCIE1A: 1, TOIE1: 0 }
+    /// Timer/Counter2 Interrupt Mask Register.
+    TIMSK2: u8 @ 0x70 { OCIE2B: 2, OCIE2A: 1, TOIE2: 0 }
+    /// ADC Data Register.
+    ADC: u16 @ 0x78 {}
+    /// ADC Control and Status Register A.
+    ADCSRA: u8 @ 0x7A { ADEN: 7, ADSC: 6, ADATE: 5, ADIF: 4, ADIE: 3, ADPS: 0..3 }
+    /// ADC Control and Status Register B.
+    ADCSRB: u8 @ 0x7B { ACME: 6, ADTS: 0..3 }
+    /// ADC Multiplexer Selection Register.
+    ADMUX: u8 @ 0x7C { REFS: 6..8, ADLAR: 5, MUX: 0..4 }
+    /// Digital Input Disable Register 0.
+    DIDR0: u8 @ 0x7E {}
+    /// Digital Input Disable Register 1.
+    DIDR1: u8 @ 0x7F { AIN1D: 1, AIN0D: 0 }
+    /// Timer/Counter1 Control Register A.
+    TCCR1A: u8 @ 0x80 { COM1A: 6..8, COM1B: 4..6, WGM1: 0..2 }
+    /// Timer/Counter1 Control Register B.
+    TCCR1B: u8 @ 0x81 { ICNC1: 7, ICES1: 6, WGM13: 4, WGM12: 3, CS1: 0..3 }
+    /// Timer/Counter1 Control Register C.
+    TCCR1C: u8 @ 0x82 { FOC1A: 7, FOC1B: 6 }
+    /// Timer/Counter1.
+    TCNT1: u16 @ 0x84 {}
+    /// Input Capture Register 1.
+    ICR1: u16 @ 0x86 {}
+    /// Output Compare Register 1 A.
+    OCR1A: u16 @ 0x88 {}
+    /// Output Compare Register 1 B.
+    OCR1B: u16 @ 0x8A {}
+    /// Timer/Counter2 Control Register A.
+    TCCR2A: u8 @ 0xB0 { COM2A: 6..8, COM2B: 4..6, WGM2: 0..2 }
+    /// Timer/Counter2 Control Register B.
+    TCCR2B: u8 @ 0xB1 { FOC2A: 7, FOC2B: 6, WGM22: 3, CS2: 0..3 }
+    /// Timer/Counter2.
+    TCNT2: u8 @ 0xB2 {}
+    /// Output Compare Register 2 A.
+    OCR2A: u8 @ 0xB3 {}
+    /// Output Compare Register 2 B.
+    OCR2B: u8 @ 0xB4 {}
+    /// Asynchronous Status Register.
+    ASSR: u8 @ 0xB6 { EXCLK: 6, AS2: 5, TCN2UB: 4, OCR2AUB: 3, OCR2BUB: 2, TCR2AUB: 1, TCR2BUB: 0 }
+    /// TWI Bit Rate Register.
+    TWBR: u8 @ 0xB8 {}
+    /// TWI Status Register.
+    TWSR: u8 @ 0xB9 { TWS: 3..8, TWPS: 0..2 }
+    /// TWI (Slave) Address Register.
+    TWAR: u8 @ 0xBA { TWA: 1..8, TWGCE: 0 }
+    /// TWI Data Register.
+    TWDR: u8 @ 0xBB {}
+    /// TWI Control Register.
+    TWCR: u8 @ 0xBC { TWINT: 7, TWEA: 6, TWSTA: 5, TWSTO: 4, TWWC: 3, TWEN: 2, TWIE: 0 }
+    /// TWI (Slave) Address Mask Register.
+    TWAMR: u8 @ 0xBD { TWAM: 1..8 }
+    /// USART0 Control and Status Register A.
+    UCSR0A: u8 @ 0xC0 { RXC0: 7, TXC0: 6, UDRE0: 5, FE0: 4, DOR0: 3, UPE0: 2, U2X0: 1, MPCM0: 0 }
+    /// USART0 Control and Status Register B.
+    UCSR0B: u8 @ 0xC1 { RXCIE0: 7, TXCIE0: 6, UDRIE0: 5, RXEN0: 4, TXEN0: 3, UCSZ02: 2, RXB80: 1, TXB80: 0 }
+    /// USART0 Control and Status Register C.
+    UCSR0C: u8 @ 0xC2 { UMSEL0: 6..8, UPM0: 4..6, USBS0: 3, UCSZ0: 1..3, UCPOL0: 0 }
+    /// USART0 Baud Rate Register.
+    UBRR0: u16 @ 0xC4 {}
+    /// USART0 I/O Data Register.
+    UDR0: u8 @ 0xC6 {}
+}
@@ -0,0 +1,896 @@
+// RustDuino : A generic HAL implementation for Arduino Boards in Rust
+// Copyright (C) 2021  Akshit Verma, Indian Institute of Technology Kanpur
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//!* This source code contains the functions to control the I2C communication protocol for ATMEGA32U4 AVR Microcontroller.
+//!  The elements of I2C implementation are first enabling the I2C Two Wire Interface(TWI) to work on
+//!  the data bus and then using bits manipulation to exchange data and communicate
+//!  with the attached peripheral devices.
+//!* This has been implemented according to the chip ATMEGA32U4 here, SCL is digital pin 3
+//!  and SDA is digital pin 2 of the Leonardo.
+//!  Section 20.9 of ATmega32U4 datasheet.
+
+// Standard crates to be used
+use bit_field::BitField;
+use fixed_slice_vec::FixedSliceVec;
+use volatile::Volatile;
+
+// Source code crates required
+use crate::atmega32u4::hal::interrupts::without_interrupts;
+use crate::atmega32u4::hal::power::{Peripherals, Power};
+use crate::delay::delay_ms;
+use crate::error::Error;
+
+///  Contains registers fow TWI.
+///
+/// * **TWBR**: *TWI Bit Rate Register*. TWBR selects the division factor for the
+/// bit rate generator. The bit rate generator is a frequency divider which generates the
+/// SCL clock frequency in the master modes. See [Section 20.5.2 “Bit Rate Generator Unit”]
+///
+///  * **TWSR**: *TWI Status Register*. The first 5 bits of TWSR reflect the status
+/// of the TWI logic and te 2-wire Serial bus. The last 2 bits decide the bit
+/// rate prescaler.
+///
+///  * **TWAR**: *TWI (Slave) Address Register*. The TWAR should be loaded with
+/// the 7-bit slave address (in the seven most significant bits of TWAR) to
+/// which the TWI will respond when programmed as a slave transmitter or
+/// receiver, and not needed in the master modes.
+///
+///  * **TWDR**: *TWI Data Register*. n transmit mode, TWDR contains the next
+/// byte to be transmitted. In receive mode, the TWDR contains the last byte
+/// received. It is writable while the TWI is not in the process of shifting
+/// a byte. This occurs when the TWI interrupt flag (TWINT) is set by hardware.
+/// Note that the data register cannot be initialized by the user before the
+/// first interrupt occurs. The data in TWDR remains stable as long as TWINT
+/// is set. While data is shifted out, data on the bus is simultaneously shifted
+/// in. TWDR always contains the last byte present on the bus, except after a
+/// wake up from a sleep mode by the TWI interrupt. In this case, the contents
+/// of TWDR is undefined.
+///
+/// * **TWSR**: *TWI Status Register*. The TWCR is used to control the operation
+/// of the TWI. It is used to enable the TWI, to initiate a master access by
+/// applying a START condition to the bus, to generate a receiver acknowledge,
+/// to generate a stop condition, and to control halting of the bus while the
+/// data to be written to the bus are written to the TWDR. It also indicates
+/// a write collision if data is attempted written to TWDR while the register is inaccessible
+///
+/// * **TWAMR**: *TWI Address Mask*. The TWAMR can be loaded with a 7-bit slave
+/// address mask. Each of the bits in TWAMR can mask (disable) the corresponding
+/// address bits in the TWI address register (TWAR). If the mask bit is set to
+/// one then the address match logic ignores the compare between the incoming
+/// address bit and the corresponding bit in TWAR.
+#[repr(C, packed)]
+pub struct Twi {
+    twbr: Volatile<u8>,
+    twsr: Volatile<u8>,
+    twar: Volatile<u8>,
+    twdr: Volatile<u8>,
+    twcr: Volatile<u8>,
+    twamr: Volatile<u8>,
+}
+
+// for twcr
+const TWINT: u8 = 7;
+const TWEA: u8 = 6;
+const TWSTA: u8 = 5;
+const TWSTO: u8 = 4;
+const TWEN: u8 = 2;
+const TWIE: u8 = 0;
+
+// for twsr
+const TWPS1: u8 = 1;
+const TWPS0: u8 = 0;
+
+static TWI_FREQUENCY: u32 = 100000;
+
+///* This function reads the device clock freequency setup and provide
+///  the details in form of boolean numbers and a 8 bit unsigned integer to
+///  check the settings of the I2C carefully.
+///* If 100 kHz can not be reached from the clock freequency, `Error::InvalidParam` is returned
+///  as the I2C protocol cannot be activated properly.
+///  # Returns
+///  * `a Result of a tuple` - Consisting of the following 3 Items -
+///     * `a u8` - Which is a 2's exponent till 64 which defines the bandwidth rate for TWI I2C initialization.
+///     * `a boolean` - Which denotes the TWPS bit 1 settings.
+///     * `a boolean` - Which denotes the TWPS bit 2 settings.
+pub fn prescaler() -> Result<(u8, bool, bool), Error> {
+    if (crate::config::CPU_FREQUENCY_HZ / TWI_FREQUENCY).saturating_sub(16) / (2 * 1) >= 10
+        && (crate::config::CPU_FREQUENCY_HZ / TWI_FREQUENCY).saturating_sub(16) / (2 * 1) <= 0xFF
+    {
+        return Ok((1, false, false));
+    } else if (crate::config::CPU_FREQUENCY_HZ / TWI_FREQUENCY).saturating_sub(16) / (2 * 4) >= 10
+        && (crate::config::CPU_FREQUENCY_HZ / TWI_FREQUENCY).saturating_sub(16) / (2 * 4) <= 0xFF
+    {
+        return Ok((4, true, false));
+    } else if (crate::config::CPU_FREQUENCY_HZ / TWI_FREQUENCY).saturating_sub(16) / (2 * 16) >= 10
+        && (crate::config::CPU_FREQUENCY_HZ / TWI_FREQUENCY).saturating_sub(16) / (2 * 16) <= 0xFF
+    {
+        return Ok((16, false, true));
+    } else if (crate::config::CPU_FREQUENCY_HZ / TWI_FREQUENCY).saturating_sub(16) / (2 * 64) >= 10
+        && (crate::config::CPU_FREQUENCY_HZ / TWI_FREQUENCY).saturating_sub(16) / (2 * 64) <= 0xFF
+    {
+        return Ok((64, true, true));
+    } else {
+        return Err(Error::InvalidParam);
+    }
+}
+
+// TWSR values (not bits)
+// (taken from avr-libc twi.h)
+// Master
+const START: u8 = 0x08;
+const REP_START: u8 = 0x10;
+// Master Transmitter;
+const MT_SLA_ACK: u8 = 0x18;
+const MT_SLA_NACK: u8 = 0x20;
+const MT_DATA_ACK: u8 = 0x28;
+const MT_DATA_NACK: u8 = 0x30;
+const ARB_LOST: u8 = 0x38;
+// Master Receiver;
+const MR_SLA_ACK: u8 = 0x40;
+const MR_SLA_NACK: u8 = 0x48;
+const MR_DATA_ACK: u8 = 0x50;
+const MR_DATA_NACK: u8 = 0x58;
+// Slave Transmitter;
+const ST_SLA_ACK: u8 = 0xA8;
+const ST_ARB_LOST_SLA_ACK: u8 = 0xB0;
+const ST_DATA_ACK: u8 = 0xB8;
+const ST_DATA_NACK: u8 = 0xC0;
+const ST_LAST_DATA: u8 = 0xC8;
+// Slave Receiver;
+const SR_SLA_ACK: u8 = 0x60;
+const SR_ARB_LOST_SLA_ACK: u8 = 0x68;
+const SR_GCALL_ACK: u8 = 0x70;
+const SR_ARB_LOST_GCALL_ACK: u8 = 0x78;
+const SR_DATA_ACK: u8 = 0x80;
+const SR_GCALL_DATA_ACK: u8 = 0x90;
+const SR_STOP: u8 = 0xA0;
+// Miscellaneous;
+const BUS_ERROR: u8 = 0x00;
+// defines and constants;
+const TWSR_STATUS_MASK: u8 = 0xF8;
+
+// return values;
+const I2C_TIMEOUT: u32 = 10000;
+
+/// Size of the buffers used in the slave mode.
+pub const I2C_BUFFER_SIZE: usize = 32;
+
+/// Sets DDRD to write direction.
+pub fn write_sda() {
+    unsafe {
+        Volatile::new(*(0x2A as *mut u8)).update(|ddrd| {
+            ddrd.set_bit(1, true);
+        });
+    }
+}
+
+/// Sets DDRD to read direction.
+pub fn read_sda() {
+    unsafe {
+        Volatile::new(*(0x2A as *mut u8)).update(|ddrd| {
+            ddrd.set_bit(1, false);
+        });
+    }
+}
+
+/// Gives the value of TWBR and the prescaler bits of TWSR for the given SCL frequency.
+fn bit_rate(frequency: u32) -> Result<(u8, u8), Error> {
+    let mut twps = 0;
+    while twps < 4 {
+        // SCL frequency = CPU frequency / (16 + 2 * TWBR * 4^TWPS)
+        let twbr =
+            (crate::config::CPU_FREQUENCY_HZ / frequency).saturating_sub(16) / (2 << (2 * twps));
+        if twbr <= 0xFF {
+            return Ok((twbr as u8, twps));
+        }
+        twps += 1;
+    }
+    Err(Error::InvalidParam)
+}
+
+impl Twi {
+    /// Creates a pointer to TWI structure objects.
+    /// # Returns
+    /// * `a reference to Twi struct object` - Which would be used to control the implementation.
+    pub fn new() -> &'static mut Self {
+        unsafe { &mut *(0xB8 as *mut Self) }
+    }
+
+    /// Waits for the current operation of the TWI bus to complete.
+    /// Times out if TWINT is not set after `I2C_TIMEOUT` checks.
+    /// # Arguments
+    /// * `status` - a u8, the status code expected in TWSR after the operation.
+    /// # Returns
+    /// * `a Result<(), Error>` - Which is an error if the operation timed out or ended with another status.
+    pub fn wait_to_complete(&mut self, status: u8) -> Result<(), Error> {
+        let mut i: u32 = 0;
+        //Waiting for TWINT flag set.
+        //This indicates that the current operation is complete.
+        while !self.twcr.read().get_bit(TWINT) {
+            if i >= I2C_TIMEOUT {
+                return Err(Error::Timeout);
+            }
+            unsafe {
+                llvm_asm!("nop");
+            }
+            i += 1;
+        }
+        self.check_status(status)
+    }
+
+    // Compares the status in TWSR with the one expected after the operation.
+    fn check_status(&mut self, status: u8) -> Result<(), Error> {
+        // if TWSR_STATUS_MASK is different from status, error.
+        match self.twsr.read() & TWSR_STATUS_MASK {
+            s if s == status => Ok(()),
+            MT_SLA_NACK | MT_DATA_NACK | MR_SLA_NACK => Err(Error::Nack),
+            ARB_LOST => Err(Error::ArbitrationLost),
+            _ => Err(Error::Bus),
+        }
+    }
+
+    /// Initiates the TWI Bus with an SCL frequency of 100 kHz.
+    pub fn init(&mut self) {
+        Power::enable_clock(Peripherals::TWI);
+        // 100 kHz can be reached from any system clock.
+        let _ = self.set_frequency(TWI_FREQUENCY);
+        self.twcr.write(1 << TWEN);
+    }
+
+    /// Disables the TWI and gates its clock to save power, `init` enables it again.
+    pub fn shutdown(&mut self) {
+        self.twcr.write(0);
+        unsafe {
+            PENDING = None;
+        }
+        Power::disable_clock(Peripherals::TWI);
+    }
+
+    /// Sets the SCL frequency used in the master modes.
+    /// # Arguments
+    /// * `frequency` - a u32, the SCL frequency in Hz, normally 100 kHz or 400 kHz.
+    /// # Returns
+    /// * `a Result<(), Error>` - Which is an error if the frequency is too low for the system clock.
+    pub fn set_frequency(&mut self, frequency: u32) -> Result<(), Error> {
+        let (twbr, twps) = bit_rate(frequency)?;
+        self.twsr.update(|sr| {
+            sr.set_bit(TWPS0, twps.get_bit(0));
+            sr.set_bit(TWPS1, twps.get_bit(1));
+        });
+        self.twbr.write(twbr);
+        Ok(())
+    }
+
+    /// Sends a Start Signal for TWI.
+    /// # Returns
+    /// * `a Result<(), Error>` - Which is an error if the start condition could not be sent.
+    pub fn start(&mut self) -> Result<(), Error> {
+        write_sda();
+        // TWCR: Enable TWI module and send the start condition.
+        self.twcr.write(1 << TWINT | 1 << TWSTA | 1 << TWEN);
+        return self.wait_to_complete(START);
+    }
+
+    /// Stops the TWI Bus.
+    pub fn stop(&mut self) {
+        // TWCR: Send the stop condition and release the bus.
+        self.twcr.write(1 << TWINT | 1 << TWSTO | 1 << TWEN);
+        unsafe {
+            PENDING = None;
+        }
+    }
+
+    /// Sends the Repeated Start Signal.
+    /// # Returns
+    /// * `a Result<(), Error>` - Which is an error if the start condition could not be sent.
+    pub fn rep_start(&mut self) -> Result<(), Error> {
+        // TWCR: Send the start condition again without releasing the bus.
+        self.twcr.write(1 << TWINT | 1 << TWSTA | 1 << TWEN);
+        return self.wait_to_complete(REP_START);
+    }
+
+    /// Loads the address of the slave device on SDA.
+    /// # Arguments
+    /// * `address` - It is passed into the function and  is a seven bit integer used for location of implementation.
+    /// # Returns
+    /// * `a Result<(), Error>` - Which is an error if the slave did not acknowledge its address.
+    pub fn address_write(&mut self, address: u8) -> Result<(), Error> {
+        self.twdr.write(address << 1);
+        // TWCR: Enables TWI to pass address
+        self.twcr.write(1 << TWINT | 1 << TWEN);
+        return self.wait_to_complete(MT_SLA_ACK);
+    }
+
+    /// Loads the address of the slave device from SDA for other implementations.
+    /// # Arguments
+    /// * `address` - It is passed into the function and  is a seven bit integer used for location of implementation.
+    /// # Returns
+    /// * `a Result<(), Error>` - Which is an error if the slave did not acknowledge its address.
+    pub fn address_read(&mut self, address: u8) -> Result<(), Error> {
+        self.twdr.write(address << 1 | 0x01);
+        self.twcr.write(1 << TWINT | 1 << TWEN);
+        return self.wait_to_complete(MR_SLA_ACK);
+    }
+
+    /// Reads a byte, acknowledges it and appends it to the given vector.
+    /// # Arguments
+    /// * `data` - a sliced vector consisting of u8, which will be filled with the data read.
+    /// # Returns
+    /// * `a Result<(), Error>` - Which is an error if the byte could not be read.
+    pub fn read_ack(&mut self, data: &mut FixedSliceVec<u8>) -> Result<(), Error> {
+        self.twcr.write(1 << TWINT | 1 << TWEA | 1 << TWEN);
+        self.wait_to_complete(MR_DATA_ACK)?;
+        data.push(self.twdr.read());
+        return Ok(());
+    }
+
+    /// Reads consecutive bytes with acknowledgement and appends them to the given vector.
+    /// # Arguments
+    /// * `data` - a sliced vector consisting of u8, which is filled with the data read.
+    /// * `length` - a usize integer, which is the theoretically set value of length of the sliced vector `data`.
+    /// # Returns
+    /// * `a usize integer` - Which gives the number of bytes read.
+    pub fn read_ack_burst(&mut self, data: &mut FixedSliceVec<u8>, length: usize) -> usize {
+        let mut x: usize = 0;
+        while x < length {
+            if self.read_ack(data).is_err() {
+                break;
+            }
+            x += 1;
+        }
+        return x;
+    }
+
+    /// Writes one byte of data to the Slave.
+    /// # Arguments
+    /// * `data` - a u8, the integer which is to be written.
+    /// # Returns
+    /// * `a Result<(), Error>` - Which is an error if the slave did not acknowledge the byte.
+    pub fn write(&mut self, data: u8) -> Result<(), Error> {
+        self.twdr.write(data);
+        // TWCR: Enables TWI module to pass data to slave.
+        self.twcr.write(1 << TWINT | 1 << TWEN);
+        return self.wait_to_complete(MT_DATA_ACK);
+    }
+
+    /// Writes continuous bytes on the TWCR.
+    /// # Arguments
+    /// * `data` - a sliced vector consisting of u8, which is filled with the data read.
+    /// # Returns
+    /// * `a usize integer` - Which gives the number of bytes written.
+    pub fn write_burst(&mut self, data: &FixedSliceVec<u8>) -> usize {
+        let mut x: usize = 0;
+        while x < data.len() {
+            if self.write(data[x]).is_err() {
+                break;
+            }
+            x += 1;
+        }
+        return x;
+    }
+
+    /// Reads a byte without acknowledging it and appends it to the given vector.
+    /// This is used for the last byte read from the slave.
+    /// # Arguments
+    /// * `data` - a sliced vector consisting of u8, which will be filled with the data read.
+    /// # Returns
+    /// * `a Result<(), Error>` - Which is an error if the byte could not be read.
+    pub fn read_nack(&mut self, data: &mut FixedSliceVec<u8>) -> Result<(), Error> {
+        self.twcr.write(1 << TWINT | 1 << TWEN);
+        self.wait_to_complete(MR_DATA_NACK)?;
+        data.push(self.twdr.read());
+        return Ok(());
+    }
+
+    /// Reads consecutive bytes without acknowledgement and appends them to the given vector.
+    /// # Arguments
+    /// * `data` - a sliced vector consisting of u8, which is filled with the data read.
+    /// * `length` - a usize integer, which is the theoretically set value of length of the sliced vector `data`.
+    /// # Returns
+    /// * `a usize integer` - Which gives the number of bytes read.
+    pub fn read_nack_burst(&mut self, data: &mut FixedSliceVec<u8>, length: usize) -> usize {
+        let mut x: usize = 0;
+
+        while x < length {
+            if self.read_nack(data).is_err() {
+                break;
+            }
+            x += 1;
+        }
+        return x;
+    }
+
+    /// Reads consecutive Data bytes from slave.
+    /// Sends a stop signal if either of the steps fail or reading is successful.
+    /// # Arguments
+    /// * `address` - a u8, consisting the target address of the read implementation.
+    /// * `length` - a usize integer, showing the number of bytes to read.
+    /// * `data` - a sliced vector consisting of u8, where the data will be stored after reading.
+    /// # Returns
+    /// * `a Result<(), Error>` - Which is the error of the first step which failed, i.e
+    /// start, reading address, reading ACK or reading NACK, after which the process is aborted.
+    pub fn read_from_slave(
+        &mut self,
+        address: u8,
+        length: usize,
+        data: &mut FixedSliceVec<u8>,
+    ) -> Result<(), Error> {
+        delay_ms(1);
+        read_sda();
+
+        self.start()?;
+        let mut result = self.address_read(address);
+        let mut count = 0;
+        // Every byte is acknowledged but the last one.
+        while result.is_ok() && count < length {
+            result = if count + 1 < length {
+                self.read_ack(data)
+            } else {
+                self.read_nack(data)
+            };
+            count += 1;
+        }
+
+        self.stop();
+
+        return result;
+    }
+
+    /// Writes consecutive Data bytes to slave.
+    /// Also sends a stop signal if either of the steps fail or writing is successful.
+    /// # Returns
+    /// * `a Result<(), Error>` - Which is the error of the first step which failed, i.e start,
+    /// setting address or writing, after which the process is aborted.
+    pub fn write_to_slave(&mut self, address: u8, data: &FixedSliceVec<u8>) -> Result<(), Error> {
+        delay_ms(1);
+        self.start()?;
+        let mut result = self.address_write(address);
+        for byte in data.iter() {
+            if result.is_err() {
+                break;
+            }
+            result = self.write(*byte);
+        }
+        self.stop();
+        return result;
+    }
+}
+
+impl Twi {
+    /// Reads one byte from the slave once its address has been sent for reading.
+    /// # Arguments
+    /// * `ack` - a boolean, which is true if more bytes are to be read after this one.
+    /// # Returns
+    /// * `a Result<u8, Error>` - which is the byte read, or the error of the transfer.
+    pub fn read_byte(&mut self, ack: bool) -> Result<u8, Error> {
+        if ack {
+            self.twcr.write(1 << TWINT | 1 << TWEA | 1 << TWEN);
+        } else {
+            self.twcr.write(1 << TWINT | 1 << TWEN);
+        }
+        let status = if ack { MR_DATA_ACK } else { MR_DATA_NACK };
+        self.wait_to_complete(status)?;
+        Ok(self.twdr.read())
+    }
+}
+
+// Status expected at the end of the operation started by one of the `try_` functions,
+// while that operation is in progress.
+static mut PENDING: Option<u8> = None;
+
+impl Twi {
+    /// Sends a start condition without waiting, for polling loops and `nb::block!`.
+    /// Like the other `try_` functions, it starts the operation on the first call and
+    /// gives `WouldBlock` until the operation is complete, so it must be called again
+    /// with the same arguments until it gives something else.
+    /// # Returns
+    /// * `a nb::Result<(), Error>` - which is an error if the start condition could not be sent.
+    pub fn try_start(&mut self) -> nb::Result<(), Error> {
+        write_sda();
+        self.poll(None, 1 << TWINT | 1 << TWSTA | 1 << TWEN, START)
+    }
+
+    /// Sends a repeated start condition without waiting.
+    /// # Returns
+    /// * `a nb::Result<(), Error>` - which is an error if the start condition could not be sent.
+    pub fn try_rep_start(&mut self) -> nb::Result<(), Error> {
+        self.poll(None, 1 << TWINT | 1 << TWSTA | 1 << TWEN, REP_START)
+    }
+
+    /// Sends the address of the slave for writing without waiting.
+    /// # Arguments
+    /// * `address` - a u8, the seven bit address of the slave.
+    /// # Returns
+    /// * `a nb::Result<(), Error>` - which is an error if the slave did not acknowledge its address.
+    pub fn try_address_write(&mut self, address: u8) -> nb::Result<(), Error> {
+        self.poll(Some(address << 1), 1 << TWINT | 1 << TWEN, MT_SLA_ACK)
+    }
+
+    /// Sends the address of the slave for reading without waiting.
+    /// # Arguments
+    /// * `address` - a u8, the seven bit address of the slave.
+    /// # Returns
+    /// * `a nb::Result<(), Error>` - which is an error if the slave did not acknowledge its address.
+    pub fn try_address_read(&mut self, address: u8) -> nb::Result<(), Error> {
+        self.poll(
+            Some(address << 1 | 0x01),
+            1 << TWINT | 1 << TWEN,
+            MR_SLA_ACK,
+        )
+    }
+
+    /// Sends a byte to the slave without waiting.
+    /// # Arguments
+    /// * `data` - a u8, the byte to be sent.
+    /// # Returns
+    /// * `a nb::Result<(), Error>` - which is an error if the slave did not acknowledge the byte.
+    pub fn try_write(&mut self, data: u8) -> nb::Result<(), Error> {
+        self.poll(Some(data), 1 << TWINT | 1 << TWEN, MT_DATA_ACK)
+    }
+
+    /// Reads a byte from the slave without waiting.
+    /// # Arguments
+    /// * `ack` - a boolean, which is true if more bytes are to be read after this one.
+    /// # Returns
+    /// * `a nb::Result<u8, Error>` - which is the byte read, or the error of the transfer.
+    pub fn try_read(&mut self, ack: bool) -> nb::Result<u8, Error> {
+        if ack {
+            self.poll(None, 1 << TWINT | 1 << TWEA | 1 << TWEN, MR_DATA_ACK)?;
+        } else {
+            self.poll(None, 1 << TWINT | 1 << TWEN, MR_DATA_NACK)?;
+        }
+        Ok(self.twdr.read())
+    }
+
+    // Starts an operation by writing TWDR and TWCR if none is in progress, and
+    // otherwise checks whether the one in progress has ended with the expected status.
+    fn poll(&mut self, data: Option<u8>, twcr: u8, status: u8) -> nb::Result<(), Error> {
+        match unsafe { PENDING } {
+            None => {
+                if let Some(data) = data {
+                    self.twdr.write(data);
+                }
+                self.twcr.write(twcr);
+                unsafe {
+                    PENDING = Some(status);
+                }
+                Err(nb::Error::WouldBlock)
+            }
+            Some(_) if !self.twcr.read().get_bit(TWINT) => Err(nb::Error::WouldBlock),
+            Some(expected) => {
+                unsafe {
+                    PENDING = None;
+                }
+                self.check_status(expected).map_err(nb::Error::Other)
+            }
+        }
+    }
+}
+
+/// Events reported by `poll_slave` when the TWI works as a slave without interrupts.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SlaveEvent {
+    /// A master has written bytes to this device, they can be read with `slave_read`.
+    Received { length: usize, general_call: bool },
+    /// A master has read the bytes given earlier with `slave_write`.
+    Requested,
+}
+
+// Data exchanged in the slave mode, shared with the interrupt service routine.
+static mut SLAVE_RX: [u8; I2C_BUFFER_SIZE] = [0; I2C_BUFFER_SIZE];
+static mut SLAVE_RX_LENGTH: usize = 0;
+static mut SLAVE_TX: [u8; I2C_BUFFER_SIZE] = [0; I2C_BUFFER_SIZE];
+static mut SLAVE_TX_LENGTH: usize = 0;
+static mut SLAVE_TX_INDEX: usize = 0;
+static mut GENERAL_CALL: bool = false;
+static mut RECEIVE_HANDLER: Option<fn(&[u8], bool)> = None;
+static mut REQUEST_HANDLER: Option<fn(&mut [u8]) -> usize> = None;
+
+impl Twi {
+    /// Sets the address to which the TWI responds in the slave modes.
+    /// # Arguments
+    /// * `address` - a u8, the seven bit address of this device.
+    /// * `general_call` - a boolean, which is true if the general call address 0x00 is also to be recognised.
+    pub fn set_slave_address(&mut self, address: u8, general_call: bool) {
+        self.twar.write(address << 1 | general_call as u8);
+    }
+
+    /// Sets the bits of the slave address which are ignored, so that
+    /// a range of addresses can be answered.
+    /// # Arguments
+    /// * `mask` - a u8, the seven bit mask where a one ignores the corresponding address bit.
+    pub fn set_address_mask(&mut self, mask: u8) {
+        self.twamr.write(mask << 1);
+    }
+
+    /// Sets the function called with the bytes written by a master, and whether
+    /// they were sent to the general call address.
+    /// # Arguments
+    /// * `handler` - a function, which is called from the interrupt service routine.
+    pub fn on_receive(&mut self, handler: fn(&[u8], bool)) {
+        unsafe {
+            RECEIVE_HANDLER = Some(handler);
+        }
+    }
+
+    /// Sets the function called when a master reads from this device.
+    /// It fills the buffer with the reply and returns the number of bytes filled.
+    /// # Arguments
+    /// * `handler` - a function, which is called from the interrupt service routine.
+    pub fn on_request(&mut self, handler: fn(&mut [u8]) -> usize) {
+        unsafe {
+            REQUEST_HANDLER = Some(handler);
+        }
+    }
+
+    /// Starts listening to the bus as a slave, the address must be set first.
+    /// # Arguments
+    /// * `interrupt` - a boolean, which is true if the events are handled by the interrupt
+    ///   service routine and the handlers, and false if `poll_slave` is to be called instead.
+    pub fn enable_slave(&mut self, interrupt: bool) {
+        unsafe {
+            SLAVE_RX_LENGTH = 0;
+            SLAVE_TX_INDEX = 0;
+        }
+        self.twcr
+            .write(1 << TWEA | 1 << TWEN | (interrupt as u8) << TWIE);
+    }
+
+    /// Stops responding to the slave address.
+    pub fn disable_slave(&mut self) {
+        self.twcr.write(1 << TWEN);
+    }
+
+    /// Handles the current state of the bus in the slave mode, if any.
+    /// This has to be called often when the slave mode is used without interrupts,
+    /// since the bus is held low until the state is handled.
+    /// # Returns
+    /// * `a Option<SlaveEvent>` - the event which finished with this state, if any.
+    pub fn poll_slave(&mut self) -> Option<SlaveEvent> {
+        if self.twcr.read().get_bit(TWINT) {
+            unsafe { self.slave_step() }
+        } else {
+            None
+        }
+    }
+
+    /// Copies the bytes last written by a master into the buffer.
+    /// # Arguments
+    /// * `buffer` - a mutable slice of u8, which will be filled with the data received.
+    /// # Returns
+    /// * `a usize` - the number of bytes copied.
+    pub fn slave_read(&mut self, buffer: &mut [u8]) -> usize {
+        without_interrupts(|| unsafe {
+            let length = SLAVE_RX_LENGTH.min(buffer.len());
+            buffer[..length].copy_from_slice(&SLAVE_RX[..length]);
+            length
+        })
+    }
+
+    /// Sets the bytes sent when a master reads from this device without a request handler.
+    /// # Arguments
+    /// * `data` - a slice of u8, the bytes to be sent.
+    /// # Returns
+    /// * `a usize` - the number of bytes stored, at most `I2C_BUFFER_SIZE`.
+    pub fn slave_write(&mut self, data: &[u8]) -> usize {
+        without_interrupts(|| unsafe {
+            let length = data.len().min(I2C_BUFFER_SIZE);
+            SLAVE_TX[..length].copy_from_slice(&data[..length]);
+            SLAVE_TX_LENGTH = length;
+            SLAVE_TX_INDEX = 0;
+            length
+        })
+    }
+
+    /// Responds to the status in TWSR once TWINT is set in the slave mode.
+    unsafe fn slave_step(&mut self) -> Option<SlaveEvent> {
+        let mut event = None;
+        // TWINT is cleared to continue, TWEA keeps the slave addressable.
+        let mut twcr = 1 << TWINT | 1 << TWEA | 1 << TWEN | (self.twcr.read() & 1 << TWIE);
+        match self.twsr.read() & TWSR_STATUS_MASK {
+            SR_SLA_ACK | SR_ARB_LOST_SLA_ACK => {
+                SLAVE_RX_LENGTH = 0;
+                GENERAL_CALL = false;
+            }
+            SR_GCALL_ACK | SR_ARB_LOST_GCALL_ACK => {
+                SLAVE_RX_LENGTH = 0;
+                GENERAL_CALL = true;
+            }
+            SR_DATA_ACK | SR_GCALL_DATA_ACK => {
+                if SLAVE_RX_LENGTH < I2C_BUFFER_SIZE {
+                    SLAVE_RX[SLAVE_RX_LENGTH] = self.twdr.read();
+                    SLAVE_RX_LENGTH += 1;
+                }
+                // The next byte is not acknowledged once the buffer is full.
+                if SLAVE_RX_LENGTH >= I2C_BUFFER_SIZE {
+                    twcr &= !(1 << TWEA);
+                }
+            }
+            SR_STOP => {
+                let data = &SLAVE_RX[..SLAVE_RX_LENGTH];
+                match RECEIVE_HANDLER {
+                    Some(handler) => handler(data, GENERAL_CALL),
+                    None => {
+                        event = Some(SlaveEvent::Received {
+                            length: SLAVE_RX_LENGTH,
+                            general_call: GENERAL_CALL,
+                        })
+                    }
+                }
+            }
+            ST_SLA_ACK | ST_ARB_LOST_SLA_ACK => {
+                if let Some(handler) = REQUEST_HANDLER {
+                    SLAVE_TX_LENGTH = handler(&mut SLAVE_TX).min(I2C_BUFFER_SIZE);
+                }
+                SLAVE_TX_INDEX = 0;
+                let byte = self.next_slave_byte();
+                self.twdr.write(byte);
+            }
+            ST_DATA_ACK => {
+                let byte = self.next_slave_byte();
+                self.twdr.write(byte);
+            }
+            ST_DATA_NACK | ST_LAST_DATA => {
+                if REQUEST_HANDLER.is_none() {
+                    event = Some(SlaveEvent::Requested);
+                }
+            }
+            BUS_ERROR => {
+                // Releases the bus after an illegal start or stop condition.
+                twcr |= 1 << TWSTO;
+            }
+            _ => {}
+        }
+        self.twcr.write(twcr);
+        event
+    }
+
+    /// Gives the next byte to be sent in the slave transmitter mode,
+    /// or 0xFF once all the bytes have been sent.
+    unsafe fn next_slave_byte(&mut self) -> u8 {
+        if SLAVE_TX_INDEX < SLAVE_TX_LENGTH {
+            SLAVE_TX_INDEX += 1;
+            SLAVE_TX[SLAVE_TX_INDEX - 1]
+        } else {
+            0xFF
+        }
+    }
+}
+
+#[cfg(feature = "embedded")]
+impl Twi {
+    /// Sends the stop condition if a step of a transfer failed.
+    fn stop_on_error(&mut self, result: Result<(), Error>) -> Result<(), Error> {
+        if result.is_err() {
+            self.stop();
+        }
+        result
+    }
+
+    /// Sends the bytes to the slave once its address has been sent for writing.
+    fn send_bytes(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        for byte in bytes {
+            let result = Twi::write(self, *byte);
+            self.stop_on_error(result)?;
+        }
+        Ok(())
+    }
+
+    /// Fills the buffer with bytes from the slave, not acknowledging the last one.
+    fn receive_bytes(&mut self, buffer: &mut [u8]) -> Result<(), Error> {
+        let length = buffer.len();
+        for (i, byte) in buffer.iter_mut().enumerate() {
+            match self.read_byte(i + 1 < length) {
+                Ok(data) => *byte = data,
+                Err(error) => {
+                    self.stop();
+                    return Err(error);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "embedded")]
+impl embedded_hal::blocking::i2c::Write for Twi {
+    type Error = Error;
+
+    fn write(&mut self, address: u8, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.start()?;
+        let result = self.address_write(address);
+        self.stop_on_error(result)?;
+        self.send_bytes(bytes)?;
+        self.stop();
+        Ok(())
+    }
+}
+
+#[cfg(feature = "embedded")]
+impl embedded_hal::blocking::i2c::Read for Twi {
+    type Error = Error;
+
+    fn read(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), Self::Error> {
+        self.start()?;
+        let result = self.address_read(address);
+        self.stop_on_error(result)?;
+        self.receive_bytes(buffer)?;
+        self.stop();
+        Ok(())
+    }
+}
+
+#[cfg(feature = "embedded")]
+impl embedded_hal::blocking::i2c::WriteRead for Twi {
+    type Error = Error;
+
+    fn write_read(
+        &mut self,
+        address: u8,
+        bytes: &[u8],
+        buffer: &mut [u8],
+    ) -> Result<(), Self::Error> {
+        self.start()?;
+        let result = self.address_write(address);
+        self.stop_on_error(result)?;
+        self.send_bytes(bytes)?;
+        let result = self.rep_start();
+        self.stop_on_error(result)?;
+        let result = self.address_read(address);
+        self.stop_on_error(result)?;
+        self.receive_bytes(buffer)?;
+        self.stop();
+        Ok(())
+    }
+}
+
+/// TWI, handles the slave mode when it is used with interrupts.
+#[cfg(target_arch = "avr")]
+#[no_mangle]
+pub unsafe extern "avr-interrupt" fn __vector_36() {
+    Twi::new().slave_step();
+}
+
+/// Lets the drivers written against `TwiOps` use the TWI.
+impl crate::common::TwiOps for Twi {
+    fn start(&mut self) -> Result<(), Error> {
+        Twi::start(self)
+    }
+
+    fn rep_start(&mut self) -> Result<(), Error> {
+        Twi::rep_start(self)
+    }
+
+    fn stop(&mut self) {
+        Twi::stop(self);
+    }
+
+    fn address_write(&mut self, address: u8) -> Result<(), Error> {
+        Twi::address_write(self, address)
+    }
+
+    fn address_read(&mut self, address: u8) -> Result<(), Error> {
+        Twi::address_read(self, address)
+    }
+
+    fn write(&mut self, data: u8) -> Result<(), Error> {
+        Twi::write(self, data)
+    }
+
+    fn read_byte(&mut self, ack: bool) -> Result<u8, Error> {
+        Twi::read_byte(self, ack)
+    }
+}
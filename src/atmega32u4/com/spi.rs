@@ -0,0 +1,444 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Ayush Agarwal, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! This source code contains the master and slave mode driver for the Serial Peripheral Interface (SPI) of ATMEGA32U4.
+//! The SPI pins are fixed in hardware and only MOSI, MISO and SCK are on the ICSP header of the Leonardo,
+//! as the digital pins 16, 14 and 15. SS is digital pin 17, which drives the RX LED.
+//! The data mode (clock polarity and phase), the bit order and the clock divider can be
+//! configured, after which the transfers are done byte by byte with blocking functions.
+//! `try_send` and `try_read` do a transfer without waiting, for polling loops and the
+//! `FullDuplex` trait of embedded-hal.
+//! In slave mode the SPI interrupt takes each byte sent by an external master, and either
+//! gives it to a handler which chooses the reply, or keeps it in a buffer while the replies
+//! are taken from another one, so the chip can act as a SPI peripheral of a Raspberry Pi.
+//! See the section 17 of ATMEGA32U4 datasheet.
+
+// Other source code files to be used.
+use crate::atmega32u4::hal::interrupts::without_interrupts;
+use crate::atmega32u4::hal::pin::make_pin;
+use crate::atmega32u4::hal::power::{Peripherals, Power};
+use crate::collections::RingBuffer;
+use crate::error::Error;
+use crate::sync::{AtomicByte, AtomicFlag};
+
+// Crates which would be used in the implementation.
+use bit_field::BitField;
+use core::ptr::{read_volatile, write_volatile};
+use volatile::Volatile;
+
+// Digital pin numbers of the SPI pins.
+const SS: u8 = 17;
+const MOSI: u8 = 16;
+const MISO: u8 = 14;
+const SCK: u8 = 15;
+
+// Bits of SPCR.
+const SPIE: u8 = 7;
+const SPE: u8 = 6;
+const DORD: u8 = 5;
+const MSTR: u8 = 4;
+const CPOL: u8 = 3;
+const CPHA: u8 = 2;
+
+// Bits of SPSR.
+const SPIF: u8 = 7;
+const WCOL: u8 = 6;
+const SPI2X: u8 = 0;
+
+// Set while the byte received in a transfer started by `try_send` has not been read.
+static TRANSFERRING: AtomicFlag = AtomicFlag::new(false);
+
+/// Size of the receive and reply buffers of the slave mode, which hold one byte less.
+pub const SPI_SLAVE_BUFFER_SIZE: usize = 32;
+
+// Buffers of the slave mode, shared with the interrupt service routine.
+static SLAVE_RX: RingBuffer<u8, SPI_SLAVE_BUFFER_SIZE> = RingBuffer::new();
+static SLAVE_TX: RingBuffer<u8, SPI_SLAVE_BUFFER_SIZE> = RingBuffer::new();
+// Byte sent when no reply is queued, and the function called with each byte received.
+static SLAVE_DEFAULT: AtomicByte = AtomicByte::new(0xFF);
+static mut SLAVE_HANDLER: Option<fn(u8) -> Option<u8>> = None;
+
+/// Selection of the clock polarity (CPOL) and clock phase (CPHA) of the SPI bus.
+/// * `Mode0` - CPOL 0, CPHA 0, data sampled on the rising edge.
+/// * `Mode1` - CPOL 0, CPHA 1, data sampled on the falling edge.
+/// * `Mode2` - CPOL 1, CPHA 0, data sampled on the falling edge.
+/// * `Mode3` - CPOL 1, CPHA 1, data sampled on the rising edge.
+#[derive(Clone, Copy)]
+pub enum SpiMode {
+    Mode0,
+    Mode1,
+    Mode2,
+    Mode3,
+}
+
+/// Selection of the order in which the bits of a byte are shifted out.
+#[derive(Clone, Copy)]
+pub enum BitOrder {
+    MsbFirst,
+    LsbFirst,
+}
+
+/// Selection of the division factor between the system clock and SCK.
+#[derive(Clone, Copy)]
+pub enum ClockDivider {
+    Div2,
+    Div4,
+    Div8,
+    Div16,
+    Div32,
+    Div64,
+    Div128,
+}
+
+/// Contains the registers of the SPI.
+///
+/// * **SPCR**: *SPI Control Register*. Enables the SPI and its interrupt, and selects
+/// the master mode, the data order, the clock polarity and phase and the clock rate.
+///
+/// * **SPSR**: *SPI Status Register*. Holds the SPI interrupt flag which is set
+/// when a transfer is complete, and the double speed (SPI2X) bit.
+///
+/// * **SPDR**: *SPI Data Register*. Writing to it starts a transfer, and reading
+/// it gives the byte received in the last transfer.
+#[repr(C, packed)]
+pub struct Spi {
+    spcr: Volatile<u8>,
+    spsr: Volatile<u8>,
+    spdr: Volatile<u8>,
+}
+
+impl Spi {
+    /// Creates a memory mapped IO for the SPI registers.
+    /// # Returns
+    /// * `a reference to Spi object` - which will be used for further implementations.
+    pub fn new() -> &'static mut Spi {
+        unsafe { &mut *(0x4C as *mut Spi) }
+    }
+
+    /// Initializes the SPI in master mode.
+    /// SS, SCK and MOSI are set as outputs and MISO as input. SS is kept high
+    /// (no device selected) and must stay an output, otherwise a low level
+    /// on it would switch the SPI to slave mode.
+    /// # Arguments
+    /// * `mode` - a `SpiMode` object, the clock polarity and phase.
+    /// * `order` - a `BitOrder` object, the order in which bits are sent.
+    /// * `divider` - a `ClockDivider` object, the division factor of the SPI clock.
+    pub fn init(&mut self, mode: SpiMode, order: BitOrder, divider: ClockDivider) {
+        // Clear the PRSPI bit in PRR.
+        unsafe {
+            let power = Power::new();
+            write_volatile(&mut power.prr0, read_volatile(&power.prr0) & !0x04);
+        }
+
+        let mut ss = make_pin(SS);
+        ss.set_output();
+        ss.high();
+        make_pin(SCK).set_output();
+        make_pin(MOSI).set_output();
+        make_pin(MISO).set_input();
+
+        self.spcr.update(|spcr| {
+            spcr.set_bit(SPIE, false);
+            spcr.set_bit(MSTR, true);
+        });
+        self.set_mode(mode);
+        self.set_bit_order(order);
+        self.set_clock_divider(divider);
+        self.spcr.update(|spcr| {
+            spcr.set_bit(SPE, true);
+        });
+    }
+
+    /// Sets the clock polarity and phase.
+    /// # Arguments
+    /// * `mode` - a `SpiMode` object, the mode of the SPI bus.
+    pub fn set_mode(&mut self, mode: SpiMode) {
+        let (cpol, cpha) = match mode {
+            SpiMode::Mode0 => (false, false),
+            SpiMode::Mode1 => (false, true),
+            SpiMode::Mode2 => (true, false),
+            SpiMode::Mode3 => (true, true),
+        };
+        self.spcr.update(|spcr| {
+            spcr.set_bit(CPOL, cpol);
+            spcr.set_bit(CPHA, cpha);
+        });
+    }
+
+    /// Sets the order in which the bits are transferred.
+    /// # Arguments
+    /// * `order` - a `BitOrder` object, MSB first or LSB first.
+    pub fn set_bit_order(&mut self, order: BitOrder) {
+        self.spcr.update(|spcr| {
+            spcr.set_bit(DORD, matches!(order, BitOrder::LsbFirst));
+        });
+    }
+
+    /// Sets the SCK frequency as a division of the system clock.
+    /// # Arguments
+    /// * `divider` - a `ClockDivider` object, the division factor.
+    pub fn set_clock_divider(&mut self, divider: ClockDivider) {
+        // (SPI2X, SPR1:0) as given in Table 19-7 of the datasheet.
+        let (spi2x, spr) = match divider {
+            ClockDivider::Div2 => (true, 0b00),
+            ClockDivider::Div4 => (false, 0b00),
+            ClockDivider::Div8 => (true, 0b01),
+            ClockDivider::Div16 => (false, 0b01),
+            ClockDivider::Div32 => (true, 0b10),
+            ClockDivider::Div64 => (false, 0b10),
+            ClockDivider::Div128 => (false, 0b11),
+        };
+        self.spcr.update(|spcr| {
+            spcr.set_bits(0..2, spr);
+        });
+        self.spsr.update(|spsr| {
+            spsr.set_bit(SPI2X, spi2x);
+        });
+    }
+
+    /// Pulls the hardware SS pin low to select the device connected to it.
+    pub fn select(&mut self) {
+        make_pin(SS).low();
+    }
+
+    /// Pulls the hardware SS pin high to release the device connected to it.
+    pub fn deselect(&mut self) {
+        make_pin(SS).high();
+    }
+
+    /// Sends a byte and receives a byte at the same time.
+    /// # Arguments
+    /// * `data` - a u8, the byte to be sent.
+    /// # Returns
+    /// * `a u8` - the byte received from the device.
+    pub fn transfer(&mut self, data: u8) -> u8 {
+        self.spdr.write(data);
+        while !self.spsr.read().get_bit(SPIF) {}
+        self.spdr.read()
+    }
+
+    /// Sends all the bytes of the slice, replacing each with the byte received.
+    /// # Arguments
+    /// * `data` - a mutable slice of u8, the bytes to be exchanged.
+    pub fn transfer_in_place(&mut self, data: &mut [u8]) {
+        for byte in data.iter_mut() {
+            *byte = self.transfer(*byte);
+        }
+    }
+
+    /// Sends all the bytes of the slice, ignoring the bytes received.
+    /// # Arguments
+    /// * `data` - a slice of u8, the bytes to be sent.
+    pub fn write(&mut self, data: &[u8]) {
+        for byte in data {
+            self.transfer(*byte);
+        }
+    }
+
+    /// Fills the buffer with bytes received from the device, sending 0xFF for each.
+    /// # Arguments
+    /// * `buffer` - a mutable slice of u8, which will be filled with the data read.
+    pub fn read(&mut self, buffer: &mut [u8]) {
+        for byte in buffer.iter_mut() {
+            *byte = self.transfer(0xFF);
+        }
+    }
+
+    /// Starts a transfer without waiting for it, the byte received must then be taken
+    /// with `try_read` before the next byte is sent.
+    /// # Arguments
+    /// * `data` - a u8, the byte to be sent.
+    /// # Returns
+    /// * `a nb::Result<(), Error>` - which is `WouldBlock` while the byte received before has not been read.
+    pub fn try_send(&mut self, data: u8) -> nb::Result<(), Error> {
+        if TRANSFERRING.is_set() {
+            return Err(nb::Error::WouldBlock);
+        }
+        self.spdr.write(data);
+        TRANSFERRING.set();
+        Ok(())
+    }
+
+    /// Takes the byte received in the transfer started by `try_send`, without waiting.
+    /// # Returns
+    /// * `a nb::Result<u8, Error>` - which is `WouldBlock` until the transfer is complete,
+    /// and `Error::Busy` if SPDR was written while the transfer was in progress.
+    pub fn try_read(&mut self) -> nb::Result<u8, Error> {
+        let spsr = self.spsr.read();
+        if !spsr.get_bit(SPIF) {
+            return Err(nb::Error::WouldBlock);
+        }
+        TRANSFERRING.clear();
+        // Reading SPDR after SPSR clears SPIF and WCOL.
+        let data = self.spdr.read();
+        if spsr.get_bit(WCOL) {
+            Err(nb::Error::Other(Error::Busy))
+        } else {
+            Ok(data)
+        }
+    }
+
+    /// Initializes the SPI in slave mode, to answer an external master.
+    /// SS, SCK and MOSI are inputs driven by the master, and MISO is driven only while
+    /// SS is low. Each byte received is given to the handler set with `on_receive`, or
+    /// else kept for `slave_read`. The reply loaded after a byte is sent while the master
+    /// clocks the next one. The global interrupts must be enabled.
+    /// # Arguments
+    /// * `mode` - a `SpiMode` object, the clock polarity and phase used by the master.
+    /// * `order` - a `BitOrder` object, the order in which bits are sent.
+    pub fn init_slave(&mut self, mode: SpiMode, order: BitOrder) {
+        Power::enable_clock(Peripherals::SPI);
+
+        make_pin(SS).set_input();
+        make_pin(SCK).set_input();
+        make_pin(MOSI).set_input();
+        make_pin(MISO).set_output();
+
+        SLAVE_RX.clear();
+        SLAVE_TX.clear();
+        self.spcr.update(|spcr| {
+            spcr.set_bit(SPIE, false);
+            spcr.set_bit(MSTR, false);
+        });
+        self.set_mode(mode);
+        self.set_bit_order(order);
+        self.spcr.update(|spcr| {
+            spcr.set_bit(SPE, true);
+        });
+        // The byte sent during the first transfer.
+        self.spdr.write(SLAVE_DEFAULT.load());
+        self.spcr.update(|spcr| {
+            spcr.set_bit(SPIE, true);
+        });
+    }
+
+    /// Sets the byte sent to the master when no reply is queued, 0xFF at first.
+    /// # Arguments
+    /// * `byte` - a u8, the default reply.
+    pub fn set_default_response(&mut self, byte: u8) {
+        SLAVE_DEFAULT.store(byte);
+    }
+
+    /// Sets a function called from the interrupt service routine with each byte received
+    /// in slave mode, which is then not kept for `slave_read`. The function gives the
+    /// reply to be loaded, or None for the next queued reply or the default one.
+    /// # Arguments
+    /// * `handler` - an Option of a function, `None` to keep the bytes in the buffer again.
+    pub fn on_receive(&mut self, handler: Option<fn(u8) -> Option<u8>>) {
+        without_interrupts(|| unsafe {
+            SLAVE_HANDLER = handler;
+        });
+    }
+
+    /// Takes the oldest byte received in slave mode. Bytes are dropped while the
+    /// buffer is full.
+    /// # Returns
+    /// * `a Option<u8>` - the byte, None if no byte is waiting.
+    pub fn slave_read(&mut self) -> Option<u8> {
+        SLAVE_RX.pop()
+    }
+
+    /// Gives the number of bytes received in slave mode and not read yet.
+    pub fn slave_available(&self) -> usize {
+        SLAVE_RX.len()
+    }
+
+    /// Queues replies to be sent to the master in slave mode, one per byte it clocks.
+    /// # Arguments
+    /// * `data` - a slice of u8, the replies.
+    /// # Returns
+    /// * `a usize` - the number of bytes queued, fewer than given if the buffer is full.
+    pub fn slave_write(&mut self, data: &[u8]) -> usize {
+        data.iter()
+            .take_while(|byte| SLAVE_TX.push(**byte).is_ok())
+            .count()
+    }
+
+    /// Disables the SPI and its interrupt, the pins can be used as normal digital pins after this.
+    pub fn end(&mut self) {
+        self.spcr.update(|spcr| {
+            spcr.set_bit(SPIE, false);
+            spcr.set_bit(SPE, false);
+        });
+    }
+
+    /// Disables the SPI and gates its clock to save power, `init` enables it again.
+    pub fn shutdown(&mut self) {
+        self.end();
+        TRANSFERRING.clear();
+        Power::disable_clock(Peripherals::SPI);
+    }
+}
+
+#[cfg(feature = "embedded")]
+impl embedded_hal::blocking::spi::Transfer<u8> for Spi {
+    type Error = core::convert::Infallible;
+
+    fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], Self::Error> {
+        self.transfer_in_place(words);
+        Ok(words)
+    }
+}
+
+#[cfg(feature = "embedded")]
+impl embedded_hal::blocking::spi::Write<u8> for Spi {
+    type Error = core::convert::Infallible;
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        Spi::write(self, words);
+        Ok(())
+    }
+}
+
+#[cfg(feature = "embedded")]
+impl embedded_hal::spi::FullDuplex<u8> for Spi {
+    type Error = Error;
+
+    fn read(&mut self) -> nb::Result<u8, Self::Error> {
+        self.try_read()
+    }
+
+    fn send(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+        self.try_send(word)
+    }
+}
+
+/// SPI Serial Transfer Complete, only enabled in slave mode.
+/// Loads the reply sent while the master clocks the next byte.
+#[cfg(target_arch = "avr")]
+#[no_mangle]
+pub unsafe extern "avr-interrupt" fn __vector_24() {
+    let spi = Spi::new();
+    let data = spi.spdr.read();
+    let reply = match SLAVE_HANDLER {
+        Some(handler) => handler(data),
+        None => {
+            SLAVE_RX.push(data).ok();
+            None
+        }
+    };
+    let reply = reply
+        .or_else(|| SLAVE_TX.pop())
+        .unwrap_or_else(|| SLAVE_DEFAULT.load());
+    spi.spdr.write(reply);
+}
+
+/// Lets the drivers written against `SpiOps` use the SPI.
+impl crate::common::SpiOps for Spi {
+    fn transfer(&mut self, data: u8) -> u8 {
+        Spi::transfer(self, data)
+    }
+}
@@ -0,0 +1,261 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Kshitij Kaithal, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! This file contains the code for recieving data through a initialized USART.
+//! This has functions to put USART in reciever mode and then read the data from the appropriate location.
+//! See the section 18 of ATMEGA32U4 datasheet.
+
+// Source code crates required
+use crate::atmega32u4::com::usart_initialize::Usart;
+use crate::delay::wait_until;
+use crate::error::Error;
+
+/// Microseconds waited for a frame before giving up, longer than a frame at the lowest
+/// baud rate.
+const RECEIVE_TIMEOUT_US: u32 = 100_000;
+
+// Crates which would be used in the implementation.
+// We will be using standard volatile and bit_field crates now for a better read and write.
+use bit_field::BitField;
+use core::u32;
+
+impl Usart {
+    /// This function enables the reciever function of microcontroller, whithout enabling it no communication is possible.
+    pub fn recieve_enable(&mut self) {
+        self.ucsrb.update(|ucsrb| {
+            ucsrb.set_bit(4, true);
+        });
+    }
+
+    /// This function checks if the data is avialable for reading or not.
+    /// # Returns
+    /// * `a boolean` - Which is false for no reading data and true if everything fine.
+    pub fn available(&mut self) -> bool {
+        let ucsra = self.ucsra.read();
+        if ucsra.get_bit(7) == true {
+            true
+        } else {
+            false
+        }
+    }
+
+    /// This function is used to recieve data of one frame.
+    /// Either 5 to 8 bits and 9 bits of data can be recieved from this function.
+    /// In case of 5 to 8 bits this function returns u8.
+    /// In case of 9 bits it retuns u32 of which first 9 bits are data recieved and remaining bits are insignificant.
+    /// # Returns
+    /// * `a Result<u32, Error>` - the data, `Error::Timeout` if no frame arrived or
+    ///   `Error::InvalidData` in case of a frame or parity error.
+    pub fn recieve_data(&mut self) -> Result<u32, Error> {
+        let ucsrc = self.ucsrc.read();
+        let ucsrb = self.ucsrb.read();
+
+        wait_until(RECEIVE_TIMEOUT_US, || self.available())?;
+        //  Case when there is 9 bits mode.
+        if ucsrc.get_bits(1..3) == 0b11 && ucsrb.get_bit(2) == true {
+            let ucsrb = self.ucsrb.read();
+            let ucsra = self.ucsra.read();
+            let mut udr: u32 = self.udr.read() as u32;
+            if ucsra.get_bits(2..5) != 0b000 {
+                Err(Error::InvalidData)
+            } else {
+                let rxb8: u32 = ucsrb.get_bits(1..2) as u32;
+                udr.set_bits(8..9, rxb8);
+                Ok(udr)
+            }
+        }
+        //  when there is a case of 5 to 8 bits.
+        else {
+            let ucsra = self.ucsra.read();
+            let udr: u32 = self.udr.read() as u32;
+            if ucsra.get_bits(2..5) != 0b000 {
+                Err(Error::InvalidData)
+            } else {
+                Ok(udr)
+            }
+        }
+    }
+
+    /// This function can be used to check frame error,Data OverRun and Parity errors.
+    /// It returns true if error occurs,else false.
+    /// # Returns
+    /// * `a boolean` - Which is true if error occurs,else false.
+    pub fn error_check(&mut self) -> bool {
+        let ucsra = self.ucsra.read();
+        if ucsra.get_bits(3..5) != 0b00 {
+            true
+        } else {
+            false
+        }
+    }
+
+    /// This function can be used to check parity error.
+    /// It returns true if error occurs else false.
+    /// # Returns
+    /// * `a boolean` - Which is true if error occurs,else false.
+    pub fn parity_check(&mut self) -> bool {
+        let ucsra = self.ucsra.read();
+        if ucsra.get_bit(2) == true {
+            true
+        } else {
+            false
+        }
+    }
+
+    /// This function disables the reciever function of microcontroller.
+    pub fn recieve_disable(&mut self) {
+        self.ucsrb.update(|ucsrb| {
+            ucsrb.set_bit(4, false);
+        });
+    }
+
+    /// This function clears the unread data in the receive buffer by flushing it
+    /// # Returns
+    /// * `a Result<(), Error>` - which is `Error::Timeout` if frames kept arriving.
+    pub fn flush_recieve(&mut self) -> Result<(), Error> {
+        // Reading UDR takes the frames out of the receive buffer.
+        wait_until(RECEIVE_TIMEOUT_US, || {
+            if self.available() {
+                let _ = self.udr.read();
+                false
+            } else {
+                true
+            }
+        })?;
+        self.ucsra.update(|ucsra| {
+            ucsra.set_bit(7, false);
+        });
+        Ok(())
+    }
+
+    ///  This function is used to recieve data of one frame.
+    ///  But it only functions when already data is available for read.which can be checked by available function.
+    ///  Either 5 to 8 bits and 9 bits of data can be recieved from this function.
+    ///  In case of 5 to 8 bits this function returns u8.
+    ///  In case of 9 bits it retuns u32 of which first 9 bits are data recieved and remaining bits are insignificant.
+    /// # Returns
+    /// * `a Result<u32, Error>` - the data, `Error::Timeout` if no frame arrived or
+    ///   `Error::InvalidData` in case of a frame or parity error.
+    pub fn read(&mut self) -> Result<u32, Error> {
+        let ucsrc = self.ucsrc.read();
+        let ucsrb = self.ucsrb.read();
+
+        wait_until(RECEIVE_TIMEOUT_US, || self.available())?;
+
+        if ucsrc.get_bits(1..3) == 0b11 && ucsrb.get_bit(2) == true {
+            let ucsra = self.ucsra.read();
+            let ucsrb = self.ucsrb.read();
+            let mut udr: u32 = self.udr.read() as u32;
+            if ucsra.get_bits(2..5) != 0b000 {
+                Err(Error::InvalidData)
+            } else {
+                let rxb8: u32 = ucsrb.get_bits(1..2) as u32;
+                udr.set_bits(8..9, rxb8);
+                Ok(udr)
+            }
+        } else {
+            let ucsra = self.ucsra.read();
+            let udr: u32 = self.udr.read() as u32;
+            if ucsra.get_bits(2..5) != 0b000 {
+                Err(Error::InvalidData)
+            } else {
+                Ok(udr)
+            }
+        }
+    }
+
+    /// Takes a received byte without waiting, for polling loops and `nb::block!`.
+    /// The status is read before UDR, as reading UDR moves the receive buffer to the next frame.
+    /// # Returns
+    /// * `a nb::Result<u8, Error>` - which is `WouldBlock` if no byte has been received,
+    /// `Error::Overflow` if bytes were lost before this one, `Error::InvalidData` for a
+    /// frame error and `Error::CrcMismatch` for a parity error.
+    pub fn try_read(&mut self) -> nb::Result<u8, Error> {
+        if !self.available() {
+            return Err(nb::Error::WouldBlock);
+        }
+        let ucsra = self.ucsra.read();
+        let data = self.udr.read();
+        if ucsra.get_bit(3) {
+            Err(nb::Error::Other(Error::Overflow))
+        } else if ucsra.get_bit(4) {
+            Err(nb::Error::Other(Error::InvalidData))
+        } else if ucsra.get_bit(2) {
+            Err(nb::Error::Other(Error::CrcMismatch))
+        } else {
+            Ok(data)
+        }
+    }
+
+    /// Receives a frame of 9 data bits, for which the frame size must be `UsartDataSize::Nine`.
+    /// The status and the ninth bit in RXB8 are read before the low byte in UDR, as
+    /// reading UDR moves the receive buffer to the next frame.
+    /// # Returns
+    /// * `a Option<u16>` - the 9 bits received, or None in case of a frame, overrun or parity error.
+    pub fn read_nine_bit(&mut self) -> Option<u16> {
+        while !self.available() {}
+        let ucsra = self.ucsra.read();
+        let ucsrb = self.ucsrb.read();
+        let low = self.udr.read();
+        if ucsra.get_bits(2..5) != 0b000 {
+            None
+        } else {
+            Some((ucsrb.get_bit(1) as u16) << 8 | low as u16)
+        }
+    }
+
+    /// Enables the multi-processor communication mode (MPCM), in which the receiver
+    /// ignores the frames whose ninth bit is clear, so that a slave is only interrupted
+    /// by the address frames sent with `write_address`.
+    pub fn mpcm_enable(&mut self) {
+        self.ucsra.update(|sra| {
+            sra.set_bit(0, true);
+        });
+    }
+
+    /// Disables the multi-processor communication mode, every frame is received.
+    pub fn mpcm_disable(&mut self) {
+        self.ucsra.update(|sra| {
+            sra.set_bit(0, false);
+        });
+    }
+
+    /// Waits in multi-processor communication mode for the address frame of this slave,
+    /// then disables MPCM so that the data frames which follow are received.
+    /// `mpcm_enable` must be called once the message is received, to wait for the next address.
+    /// # Arguments
+    /// * `address` - a u8, the address of this slave.
+    pub fn wait_for_address(&mut self, address: u8) {
+        self.mpcm_enable();
+        loop {
+            if let Some(frame) = self.read_nine_bit() {
+                if frame.get_bit(8) && frame as u8 == address {
+                    break;
+                }
+            }
+        }
+        self.mpcm_disable();
+    }
+}
+
+#[cfg(feature = "embedded")]
+impl embedded_hal::serial::Read<u8> for Usart {
+    type Error = Error;
+
+    fn read(&mut self) -> nb::Result<u8, Self::Error> {
+        self.try_read()
+    }
+}
@@ -0,0 +1,345 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Richa Prakash Sachan, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! This file contains functions to enable transmission through the USART and do the transmission.
+//! Flushing data in case of error and writing string are some complex implementations provided.
+//! See the section 18 of ATMEGA32U4 datasheet.
+
+// Source code crates required
+use crate::atmega32u4::com::usart_initialize::{Usart, UsartDataSize};
+use crate::delay::wait_until;
+use crate::error::Error;
+
+// Crates which would be used in the implementation.
+// We will be using standard volatile and bit_field crates now for a better read and write.
+use bit_field::BitField;
+use core::convert::Infallible;
+use core::{f64, u8, usize};
+use fixed_slice_vec::FixedSliceVec;
+
+/// Microseconds given to the transmitter to take a byte or finish sending, longer than
+/// a frame at the lowest baud rate.
+pub(crate) const TRANSMIT_TIMEOUT_US: u32 = 100_000;
+
+// This is a implementation for Usart
+impl Usart {
+    /// Initialization setting begin function
+    /// This function is to enable the Transmitter
+    /// Once it is enabled it takes control of the TXDn pin as a transmitting output.   
+    pub fn transmit_enable(&mut self) {
+        self.ucsrb.update(|srb| {
+            srb.set_bit(3, true);
+        });
+    }
+
+    /// Storing data in Transmit Buffer which takes parameter as a u32 and and data bit length.
+    /// # Arguments
+    /// * `data` - a u32, the data to be transmitted.
+    /// * `len` -  a `UsartDataSize` object, which contains the length of data frame of USART.
+    pub fn transmitting_data(&mut self, data: u32, len: UsartDataSize) -> Result<(), Error> {
+        // Checks if the Transmit buffer is empty to receive data.
+        // If not the program waits till the time comes.
+        wait_until(TRANSMIT_TIMEOUT_US, || self.avai_write())?;
+
+        let mut udr: u8 = 0;
+
+        // If the frame is ready for transmission then the appropriate place is written.
+        match len {
+            UsartDataSize::Five => {
+                udr.set_bits(0..5, data.get_bits(0..5) as u8);
+            }
+            UsartDataSize::Six => {
+                udr.set_bits(0..6, data.get_bits(0..6) as u8);
+            }
+            UsartDataSize::Seven => {
+                udr.set_bits(0..7, data.get_bits(0..7) as u8);
+            }
+            UsartDataSize::Eight => {
+                udr.set_bits(0..8, data.get_bits(0..8) as u8);
+            }
+            UsartDataSize::Nine => {
+                self.ucsrb.update(|ctrl| {
+                    ctrl.set_bit(0, data.get_bit(8));
+                });
+                udr.set_bits(0..8, data.get_bits(0..8) as u8);
+            }
+        }
+        self.udr.write(udr);
+        Ok(())
+    }
+
+    /// Checks that transmission buffer if ready for transmission.
+    /// # Returns
+    /// * `a boolean` - Which is true if ready otherwise false.
+    pub fn avai_write(&mut self) -> bool {
+        let ucsra = self.ucsra.read();
+        if ucsra.get_bit(5) == true {
+            true
+        } else {
+            false
+        }
+    }
+
+    /// This functions waits for the transmission to complete by checking TXCn bit in the ucsrna register
+    /// TXCn is set 1 when the transmit is completed and it can start transmitting new data.
+    pub fn flush_transmit(&mut self) -> Result<(), Error> {
+        wait_until(TRANSMIT_TIMEOUT_US, || self.ucsra.read().get_bit(6))
+    }
+
+    /// This function is used to disable the Transmitter and once disabled the TXDn pin is no longer
+    /// used as the transmitter output pin and functions as a normal I/O pin.
+    pub fn transmit_disable(&mut self) -> Result<(), Error> {
+        // The frame being shifted out is finished before the transmitter stops,
+        // the byte waiting in the buffer must have been taken first.
+        wait_until(TRANSMIT_TIMEOUT_US, || self.ucsra.read().get_bit(5))?;
+
+        self.ucsrb.update(|srb| {
+            srb.set_bit(3, false);
+        });
+        Ok(())
+    }
+
+    /// This function sends a character byte of 5,6,7 or 8 bits
+    /// # Arguments
+    /// * `data` - a u8, consisting of the current data frame to send from USART.
+    pub fn transmit_data(&mut self, data: u8) -> Result<(), Error> {
+        wait_until(TRANSMIT_TIMEOUT_US, || self.ucsra.read().get_bit(5))?;
+
+        self.udr.write(data);
+        Ok(())
+    }
+
+    /// Puts a byte in the transmit buffer without waiting, for polling loops and `nb::block!`.
+    /// # Arguments
+    /// * `data` - a u8, the byte to be sent.
+    /// # Returns
+    /// * `a nb::Result<(), Infallible>` - which is `WouldBlock` while the buffer is full.
+    pub fn try_write(&mut self, data: u8) -> nb::Result<(), Infallible> {
+        if !self.ucsra.read().get_bit(5) {
+            return Err(nb::Error::WouldBlock);
+        }
+        // TXC is cleared by writing a one to it, so that `try_flush` waits for this byte.
+        self.ucsra.update(|ucsra| {
+            ucsra.set_bit(6, true);
+        });
+        self.udr.write(data);
+        Ok(())
+    }
+
+    /// Checks without waiting whether the bytes given to `try_write` have all been shifted out.
+    /// TXC is only set once a byte is complete, so nothing must have been sent in other ways
+    /// since the USART was enabled for the first call to be reliable.
+    /// # Returns
+    /// * `a nb::Result<(), Infallible>` - which is `WouldBlock` while a byte is being sent.
+    pub fn try_flush(&mut self) -> nb::Result<(), Infallible> {
+        let ucsra = self.ucsra.read();
+        if ucsra.get_bit(5) && ucsra.get_bit(6) {
+            Ok(())
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+
+    /// Sends a frame of 9 data bits, for which the frame size must be `UsartDataSize::Nine`.
+    /// The ninth bit is written to TXB8 before the low byte is written to UDR, as the
+    /// transmitter takes both when UDR is written.
+    /// # Arguments
+    /// * `data` - a u16, of which the lower 9 bits are sent.
+    pub fn write_nine_bit(&mut self, data: u16) {
+        while !self.avai_write() {}
+        self.ucsrb.update(|srb| {
+            srb.set_bit(0, data.get_bit(8));
+        });
+        self.udr.write(data as u8);
+    }
+
+    /// Sends the address of a slave in multi-processor communication mode, as a frame
+    /// of 9 bits whose ninth bit is set. Every slave in MPCM receives it, and the one
+    /// with this address leaves MPCM to receive the data frames which follow.
+    /// # Arguments
+    /// * `address` - a u8, the address of the slave.
+    pub fn write_address(&mut self, address: u8) {
+        self.write_nine_bit(0x100 | address as u16);
+    }
+
+    /// Sends a data byte in multi-processor communication mode, as a frame of 9 bits
+    /// whose ninth bit is clear, which is ignored by the slaves still in MPCM.
+    /// # Arguments
+    /// * `data` - a u8, the byte to be sent.
+    pub fn write_mpcm_data(&mut self, data: u8) {
+        self.write_nine_bit(data as u16);
+    }
+
+    /// Sends and receives a byte at the same time in synchronous mode, as a SPI master
+    /// with the clock on XCK. The transmitter and the receiver must both be enabled.
+    /// In master synchronous mode the byte received is the one shifted in by the clock
+    /// of this transmission.
+    /// # Arguments
+    /// * `data` - a u8, the byte to be sent.
+    /// # Returns
+    /// * `a u8` - the byte received.
+    pub fn transfer(&mut self, data: u8) -> u8 {
+        while !self.avai_write() {}
+        self.udr.write(data);
+        while !self.available() {}
+        self.udr.read()
+    }
+
+    /// This function send data type of string byte by byte.
+    /// # Arguments
+    /// * `data` - a static string object, which is to be transmitted using USART.
+    /// # Returns
+    /// * `a Result<(), Error>` - which is `Error::Timeout` if the transmitter stopped taking bytes.
+    pub fn write_string(&mut self, data: &'static str) -> Result<(), Error> {
+        let mut vec: FixedSliceVec<u8> = FixedSliceVec::new(&mut []);
+
+        for c in data.chars() {
+            vec.push(c as u8);
+        }
+
+        for i in 0..(vec.len()) {
+            self.transmit_data(vec[i])?;
+        }
+        Ok(())
+    }
+
+    /// This function send data type of int(u32) byte by byte.
+    /// # Arguments
+    /// * `data` - a u32, which is to be transmitted using USART.
+    /// # Returns
+    /// * `a Result<(), Error>` - which is `Error::Timeout` if the transmitter stopped taking bytes.
+    pub fn write_integer(&mut self, data: u32) -> Result<(), Error> {
+        let mut vec: FixedSliceVec<u8> = FixedSliceVec::new(&mut []);
+        let mut a = data;
+        while a != 0 {
+            let rem = a % 10;
+            a = a / 10;
+            match rem {
+                0 => vec.push('0' as u8),
+                1 => vec.push('1' as u8),
+                2 => vec.push('2' as u8),
+                3 => vec.push('3' as u8),
+                4 => vec.push('4' as u8),
+                5 => vec.push('5' as u8),
+                6 => vec.push('6' as u8),
+                7 => vec.push('7' as u8),
+                8 => vec.push('8' as u8),
+                9 => vec.push('9' as u8),
+                _ => unreachable!(),
+            }
+        }
+        for i in 0..(vec.len()) {
+            self.transmit_data(vec[vec.len() - 1 - i])?;
+        }
+        Ok(())
+    }
+
+    /// This function send data type of float(f32) byte by byte.
+    /// # Arguments
+    /// * `data` - a f64, which is to be transmitted using USART.
+    /// * `precision` - a u32, the number of decimal precision required in the transmission.
+    /// # Returns
+    /// * `a Result<(), Error>` - which is `Error::Timeout` if the transmitter stopped taking bytes.
+    pub fn write_float(&mut self, data: f64, precision: u32) -> Result<(), Error> {
+        let mut vec: FixedSliceVec<u8> = FixedSliceVec::new(&mut []);
+        let a: f64 = data;
+        let mut f: f64 = a % 1.0;
+        let mut i: i64 = (a - (a % 1.0)) as i64;
+        let mut x: u32 = precision;
+        let mut n: usize = 0;
+        while f != 0.00 && x != 0 {
+            let k: i64 = ((f * 10.0) - ((f * 10.0) % 1.0)) as i64; // gives you decimal digit of data one by one from left to right
+            match k {
+                0 => vec.push('0' as u8),
+                1 => vec.push('1' as u8),
+                2 => vec.push('2' as u8),
+                3 => vec.push('3' as u8),
+                4 => vec.push('4' as u8),
+                5 => vec.push('5' as u8),
+                6 => vec.push('6' as u8),
+                7 => vec.push('7' as u8),
+                8 => vec.push('8' as u8),
+                9 => vec.push('9' as u8),
+                _ => unreachable!(),
+            }
+            f = (f * 10.0) % 1.0; // then f loses its left most digit (in decimal part)
+            x = x - 1;
+            n = n + 1;
+        }
+
+        vec.push('.' as u8);
+
+        while i != 0 {
+            let rem = i % 10;
+            i = i / 10;
+            match rem {
+                0 => vec.push('0' as u8),
+                1 => vec.push('1' as u8),
+                2 => vec.push('2' as u8),
+                3 => vec.push('3' as u8),
+                4 => vec.push('4' as u8),
+                5 => vec.push('5' as u8),
+                6 => vec.push('6' as u8),
+                7 => vec.push('7' as u8),
+                8 => vec.push('8' as u8),
+                9 => vec.push('9' as u8),
+                _ => (),
+            }
+        }
+
+        for ia in 0..(vec.len() - n - 1) {
+            self.transmit_data(vec[vec.len() - 1 - ia])?;
+        }
+
+        for ia in 0..n - 1 {
+            vec.push(vec[ia]);
+        }
+        Ok(())
+    }
+}
+
+/// Lets the drivers written against `UsartOps` use the USART.
+impl crate::common::UsartOps for Usart {
+    fn write_byte(&mut self, byte: u8) {
+        while !self.ucsra.read().get_bit(5) {}
+        self.udr.write(byte);
+    }
+
+    fn available(&mut self) -> bool {
+        Usart::available(self)
+    }
+
+    fn read_byte(&mut self) -> Option<u8> {
+        if Usart::available(self) {
+            Some(self.udr.read())
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(feature = "embedded")]
+impl embedded_hal::serial::Write<u8> for Usart {
+    type Error = Infallible;
+
+    fn write(&mut self, word: u8) -> nb::Result<(), Self::Error> {
+        self.try_write(word)
+    }
+
+    fn flush(&mut self) -> nb::Result<(), Self::Error> {
+        self.try_flush()
+    }
+}
@@ -0,0 +1,475 @@
+// RustDuino : A generic HAL implementation for Arduino Boards in Rust
+// Copyright (C) 2021  Akshit Verma, Indian Institute of Technology Kanpur
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Global interrupts configured in the ATMEGA32U4 chip is controlled here.
+//! Section 4.3 of the manual
+//! Handlers can also be attached to the external interrupts INT0 to INT3 and INT6 and to
+//! the single pin change interrupt group of port B, like `attachInterrupt()` of Arduino.
+//! Section 11 of the manual
+
+use bit_field::BitField;
+use core::ptr::{read_volatile, write_volatile};
+
+// Source codes required.
+use crate::error::Error;
+use crate::sync::free;
+
+// Registers of the external and pin change interrupts.
+const PCIFR: *mut u8 = 0x3B as *mut u8;
+const EIFR: *mut u8 = 0x3C as *mut u8;
+const EIMSK: *mut u8 = 0x3D as *mut u8;
+const PCICR: *mut u8 = 0x68 as *mut u8;
+const EICRA: *mut u8 = 0x69 as *mut u8;
+const EICRB: *mut u8 = 0x6A as *mut u8;
+const PCMSK0: *mut u8 = 0x6B as *mut u8;
+
+/// SREG (Status control Register)
+/// The status register contains information about the result of the most recently executed arithmetic instruction. This
+/// information can be used for altering program flow in order to perform conditional operations. Note that the status register is
+/// updated after all ALU operations, as specified in the instruction set reference. This will in many cases remove the need for
+/// using the dedicated compare instructions, resulting in faster and more compact code.
+/// The status register is not automatically stored when entering an interrupt routine and restored when returning from an
+/// interrupt. This must be handled by software.
+///
+/// Toggling 8th bit to 0 or 1 can enable or disable interrupt respectively.
+#[repr(C, packed)]
+pub struct Interrupt {
+    sreg: u8,
+}
+
+impl Interrupt {
+    /// Creates a new struct of Global_Interrupts.
+    /// # Returns
+    /// * `a reference to Interrupt structure` - to control the global interrupt implementations.
+    pub unsafe fn new() -> &'static mut Interrupt {
+        &mut *(0x5F as *mut Interrupt)
+    }
+
+    /// Disables Interrupts.
+    pub fn disable(&mut self) {
+        unsafe {
+            let mut ctrl_sreg = read_volatile(&self.sreg);
+            ctrl_sreg &= 0x7F;
+            write_volatile(&mut self.sreg, ctrl_sreg);
+        }
+    }
+
+    /// Enables Interrupts
+    pub fn enable(&mut self) {
+        unsafe {
+            let mut ctrl_sreg = read_volatile(&self.sreg);
+            ctrl_sreg |= 0x80;
+            write_volatile(&mut self.sreg, ctrl_sreg);
+        }
+    }
+
+    /// Checks whether global interrupts are enabled.
+    /// # Returns
+    /// * `a boolean` - which is true if the I bit of SREG is set.
+    pub fn is_enabled(&mut self) -> bool {
+        let ctrl_sreg = unsafe { read_volatile(&self.sreg) };
+        ctrl_sreg & (1 << 7) != 0
+    }
+}
+
+/// Executes the given closure with global interrupts disabled, so that data shared
+/// with an interrupt service routine can be accessed safely.
+/// This is `sync::free` without the `CriticalSection` token, which should be used
+/// instead in new code, and kept for the code written before it.
+/// # Arguments
+/// * `f` - a closure, which is to be executed without interruption.
+/// # Returns
+/// * `the value returned by the closure`.
+pub fn without_interrupts<F, R>(f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    free(|_| f())
+}
+
+/// Selection of the condition which triggers an external interrupt.
+/// * `Low` - as long as the pin is low.
+/// * `Change` - on any logical change of the pin.
+/// * `Falling` - on the falling edge.
+/// * `Rising` - on the rising edge.
+#[derive(Clone, Copy)]
+pub enum TriggerMode {
+    Low,
+    Change,
+    Falling,
+    Rising,
+}
+
+// Handlers attached to INT0 to INT7, of which INT4, INT5 and INT7 do not exist,
+// and to the pin change group PCINT0.
+static mut EXTERNAL_HANDLERS: [Option<fn()>; 8] = [None; 8];
+static mut PIN_CHANGE_HANDLER: Option<fn()> = None;
+
+// Checks that an external interrupt exists on the ATMEGA32U4.
+fn is_external_interrupt(int: u8) -> bool {
+    int <= 3 || int == 6
+}
+
+/// Gives the external interrupt connected to a digital pin of the Arduino Leonardo.
+/// # Arguments
+/// * `pin` - a u8, the digital pin number.
+/// # Returns
+/// * `a Option<u8>` - the number of the external interrupt INTn, if the pin has one.
+pub fn digital_pin_to_interrupt(pin: u8) -> Option<u8> {
+    match pin {
+        3 => Some(0),
+        2 => Some(1),
+        0 => Some(2),
+        1 => Some(3),
+        7 => Some(6),
+        _ => None,
+    }
+}
+
+/// Gives the pin change interrupt group and bit of a digital pin of the Arduino Leonardo.
+/// Only the pins of port B have a pin change interrupt, which are the pins 8 to 11 and
+/// the pins 14 to 17 of the SPI header and the RX LED.
+/// # Arguments
+/// * `pin` - a u8, the digital pin number.
+/// # Returns
+/// * `a Option<(u8, u8)>` - the group, always 0, and the bit in its mask register,
+///   if the pin has a pin change interrupt.
+pub fn digital_pin_to_pcint(pin: u8) -> Option<(u8, u8)> {
+    match pin {
+        8..=11 => Some((0, pin - 4)),
+        14 => Some((0, 3)),
+        15 => Some((0, 1)),
+        16 => Some((0, 2)),
+        17 => Some((0, 0)),
+        _ => None,
+    }
+}
+
+/// Attaches a handler to an external interrupt and enables it.
+/// Global interrupts must also be enabled for the handler to be called.
+/// # Arguments
+/// * `int` - a u8, the number of the external interrupt, 0 to 3 or 6.
+/// * `mode` - a `TriggerMode` object, the condition which calls the handler.
+/// * `handler` - a function, which is called from the interrupt service routine.
+/// # Returns
+/// * `a Result<(), Error>` - which is `Error::InvalidParam` if the chip has no such interrupt.
+pub fn attach_interrupt(int: u8, mode: TriggerMode, handler: fn()) -> Result<(), Error> {
+    if !is_external_interrupt(int) {
+        return Err(Error::InvalidParam);
+    }
+    let sense = match mode {
+        TriggerMode::Low => 0b00,
+        TriggerMode::Change => 0b01,
+        TriggerMode::Falling => 0b10,
+        TriggerMode::Rising => 0b11,
+    };
+    let (eicr, shift) = if int < 4 {
+        (EICRA, 2 * int)
+    } else {
+        (EICRB, 2 * (int - 4))
+    };
+
+    without_interrupts(|| unsafe {
+        EXTERNAL_HANDLERS[int as usize] = Some(handler);
+
+        let mut eimsk = read_volatile(EIMSK);
+        eimsk.set_bit(int, false);
+        write_volatile(EIMSK, eimsk);
+
+        let mut control = read_volatile(eicr);
+        control.set_bits(shift..shift + 2, sense);
+        write_volatile(eicr, control);
+
+        // Changing the sense control may set the flag, which is cleared by writing one.
+        write_volatile(EIFR, 1 << int);
+        eimsk.set_bit(int, true);
+        write_volatile(EIMSK, eimsk);
+    });
+    Ok(())
+}
+
+/// Disables an external interrupt and removes its handler.
+/// # Arguments
+/// * `int` - a u8, the number of the external interrupt, 0 to 3 or 6.
+/// # Returns
+/// * `a Result<(), Error>` - which is `Error::InvalidParam` if the chip has no such interrupt.
+pub fn detach_interrupt(int: u8) -> Result<(), Error> {
+    if !is_external_interrupt(int) {
+        return Err(Error::InvalidParam);
+    }
+    without_interrupts(|| unsafe {
+        let mut eimsk = read_volatile(EIMSK);
+        eimsk.set_bit(int, false);
+        write_volatile(EIMSK, eimsk);
+        EXTERNAL_HANDLERS[int as usize] = None;
+    });
+    Ok(())
+}
+
+/// Attaches a handler to a pin change interrupt group and enables it for the pins given.
+/// The handler is called when any of the pins changes, so it has to read the pins
+/// to find out which one has changed.
+/// # Arguments
+/// * `group` - a u8, the pin change interrupt group, only 0 on the ATMEGA32U4.
+/// * `mask` - a u8, the pins of the group which trigger the interrupt.
+/// * `handler` - a function, which is called from the interrupt service routine.
+/// # Returns
+/// * `a Result<(), Error>` - which is `Error::InvalidParam` if the chip has no such group.
+pub fn attach_pin_change(group: u8, mask: u8, handler: fn()) -> Result<(), Error> {
+    if group > 0 {
+        return Err(Error::InvalidParam);
+    }
+    without_interrupts(|| unsafe {
+        PIN_CHANGE_HANDLER = Some(handler);
+        write_volatile(PCMSK0, mask);
+        write_volatile(PCIFR, 1 << group);
+
+        let mut pcicr = read_volatile(PCICR);
+        pcicr.set_bit(group, true);
+        write_volatile(PCICR, pcicr);
+    });
+    Ok(())
+}
+
+/// Disables a pin change interrupt group and removes its handler.
+/// # Arguments
+/// * `group` - a u8, the pin change interrupt group, only 0 on the ATMEGA32U4.
+/// # Returns
+/// * `a Result<(), Error>` - which is `Error::InvalidParam` if the chip has no such group.
+pub fn detach_pin_change(group: u8) -> Result<(), Error> {
+    if group > 0 {
+        return Err(Error::InvalidParam);
+    }
+    without_interrupts(|| unsafe {
+        let mut pcicr = read_volatile(PCICR);
+        pcicr.set_bit(group, false);
+        write_volatile(PCICR, pcicr);
+        write_volatile(PCMSK0, 0);
+        PIN_CHANGE_HANDLER = None;
+    });
+    Ok(())
+}
+
+/// Calls the handler attached to an external interrupt.
+#[cfg(target_arch = "avr")]
+unsafe fn external_interrupt(int: usize) {
+    if let Some(handler) = EXTERNAL_HANDLERS[int] {
+        handler();
+    }
+}
+
+/// Calls the handler attached to the pin change interrupt group.
+#[cfg(target_arch = "avr")]
+unsafe fn pin_change_interrupt() {
+    if let Some(handler) = PIN_CHANGE_HANDLER {
+        handler();
+    }
+}
+
+/// External Interrupt Request 0.
+#[cfg(target_arch = "avr")]
+#[no_mangle]
+pub unsafe extern "avr-interrupt" fn __vector_1() {
+    external_interrupt(0);
+}
+
+/// External Interrupt Request 1.
+#[cfg(target_arch = "avr")]
+#[no_mangle]
+pub unsafe extern "avr-interrupt" fn __vector_2() {
+    external_interrupt(1);
+}
+
+/// External Interrupt Request 2.
+#[cfg(target_arch = "avr")]
+#[no_mangle]
+pub unsafe extern "avr-interrupt" fn __vector_3() {
+    external_interrupt(2);
+}
+
+/// External Interrupt Request 3.
+#[cfg(target_arch = "avr")]
+#[no_mangle]
+pub unsafe extern "avr-interrupt" fn __vector_4() {
+    external_interrupt(3);
+}
+
+/// External Interrupt Request 6.
+#[cfg(target_arch = "avr")]
+#[no_mangle]
+pub unsafe extern "avr-interrupt" fn __vector_7() {
+    external_interrupt(6);
+}
+
+/// Pin Change Interrupt Request 0.
+#[cfg(target_arch = "avr")]
+#[no_mangle]
+pub unsafe extern "avr-interrupt" fn __vector_9() {
+    pin_change_interrupt();
+}
+
+/// Declares the interrupt service routine of an interrupt vector by its name in the
+/// datasheet, as `TIMER1_COMPA` or `PCINT0`, instead of the `__vector_N` symbol.
+/// A name which is not a vector of the ATMEGA32U4 does not compile.
+/// The vectors already defined by the library, as the external and pin change
+/// interrupts or `WDT` for the watchdog, cannot be taken again, which fails when linking.
+/// # Example
+/// ```ignore
+/// avr_interrupt!(TIMER1_COMPA, fn on_compare() {
+///     TICKS.fetch_add(1);
+/// });
+/// ```
+#[macro_export]
+macro_rules! avr_interrupt {
+    ($vector:ident, fn $handler:ident() $body:block) => {
+        fn $handler() $body
+        $crate::__avr_vector!($vector, $handler);
+    };
+}
+
+/// Gives the symbol of a vector of the ATMEGA32U4, see `avr_interrupt!`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __avr_vector {
+    (INT0, $handler:ident) => {
+        $crate::__avr_isr!(__vector_1, $handler);
+    };
+    (INT1, $handler:ident) => {
+        $crate::__avr_isr!(__vector_2, $handler);
+    };
+    (INT2, $handler:ident) => {
+        $crate::__avr_isr!(__vector_3, $handler);
+    };
+    (INT3, $handler:ident) => {
+        $crate::__avr_isr!(__vector_4, $handler);
+    };
+    (INT6, $handler:ident) => {
+        $crate::__avr_isr!(__vector_7, $handler);
+    };
+    (PCINT0, $handler:ident) => {
+        $crate::__avr_isr!(__vector_9, $handler);
+    };
+    (USB_GEN, $handler:ident) => {
+        $crate::__avr_isr!(__vector_10, $handler);
+    };
+    (USB_COM, $handler:ident) => {
+        $crate::__avr_isr!(__vector_11, $handler);
+    };
+    (WDT, $handler:ident) => {
+        $crate::__avr_isr!(__vector_12, $handler);
+    };
+    (TIMER1_CAPT, $handler:ident) => {
+        $crate::__avr_isr!(__vector_16, $handler);
+    };
+    (TIMER1_COMPA, $handler:ident) => {
+        $crate::__avr_isr!(__vector_17, $handler);
+    };
+    (TIMER1_COMPB, $handler:ident) => {
+        $crate::__avr_isr!(__vector_18, $handler);
+    };
+    (TIMER1_COMPC, $handler:ident) => {
+        $crate::__avr_isr!(__vector_19, $handler);
+    };
+    (TIMER1_OVF, $handler:ident) => {
+        $crate::__avr_isr!(__vector_20, $handler);
+    };
+    (TIMER0_COMPA, $handler:ident) => {
+        $crate::__avr_isr!(__vector_21, $handler);
+    };
+    (TIMER0_COMPB, $handler:ident) => {
+        $crate::__avr_isr!(__vector_22, $handler);
+    };
+    (TIMER0_OVF, $handler:ident) => {
+        $crate::__avr_isr!(__vector_23, $handler);
+    };
+    (SPI_STC, $handler:ident) => {
+        $crate::__avr_isr!(__vector_24, $handler);
+    };
+    (USART1_RX, $handler:ident) => {
+        $crate::__avr_isr!(__vector_25, $handler);
+    };
+    (USART1_UDRE, $handler:ident) => {
+        $crate::__avr_isr!(__vector_26, $handler);
+    };
+    (USART1_TX, $handler:ident) => {
+        $crate::__avr_isr!(__vector_27, $handler);
+    };
+    (ANALOG_COMP, $handler:ident) => {
+        $crate::__avr_isr!(__vector_28, $handler);
+    };
+    (ADC, $handler:ident) => {
+        $crate::__avr_isr!(__vector_29, $handler);
+    };
+    (EE_READY, $handler:ident) => {
+        $crate::__avr_isr!(__vector_30, $handler);
+    };
+    (TIMER3_CAPT, $handler:ident) => {
+        $crate::__avr_isr!(__vector_31, $handler);
+    };
+    (TIMER3_COMPA, $handler:ident) => {
+        $crate::__avr_isr!(__vector_32, $handler);
+    };
+    (TIMER3_COMPB, $handler:ident) => {
+        $crate::__avr_isr!(__vector_33, $handler);
+    };
+    (TIMER3_COMPC, $handler:ident) => {
+        $crate::__avr_isr!(__vector_34, $handler);
+    };
+    (TIMER3_OVF, $handler:ident) => {
+        $crate::__avr_isr!(__vector_35, $handler);
+    };
+    (TWI, $handler:ident) => {
+        $crate::__avr_isr!(__vector_36, $handler);
+    };
+    (SPM_READY, $handler:ident) => {
+        $crate::__avr_isr!(__vector_37, $handler);
+    };
+    (TIMER4_COMPA, $handler:ident) => {
+        $crate::__avr_isr!(__vector_38, $handler);
+    };
+    (TIMER4_COMPB, $handler:ident) => {
+        $crate::__avr_isr!(__vector_39, $handler);
+    };
+    (TIMER4_COMPD, $handler:ident) => {
+        $crate::__avr_isr!(__vector_40, $handler);
+    };
+    (TIMER4_OVF, $handler:ident) => {
+        $crate::__avr_isr!(__vector_41, $handler);
+    };
+    (TIMER4_FPF, $handler:ident) => {
+        $crate::__avr_isr!(__vector_42, $handler);
+    };
+    ($vector:ident, $handler:ident) => {
+        compile_error!(concat!(
+            "no interrupt vector ",
+            stringify!($vector),
+            " on the ATMEGA32U4"
+        ));
+    };
+}
+
+/// Defines the interrupt service routine calling the handler, see `avr_interrupt!`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __avr_isr {
+    ($symbol:ident, $handler:ident) => {
+        #[cfg(target_arch = "avr")]
+        #[no_mangle]
+        pub unsafe extern "avr-interrupt" fn $symbol() {
+            $handler();
+        }
+    };
+}
@@ -0,0 +1,226 @@
+// RustDuino : A generic HAL implementation for Arduino Boards in Rust
+// Copyright (C) 2021  Sanmati Pande, Indian Institute of Technology Kanpur
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Generic implementation of power control through clock gating in ATMEGA32U4.
+//! The drivers enable the clock of their peripheral when they are created and gate it again
+//! in their `shutdown()`, and `Power::disable_all_unused()` gates every peripheral which
+//! is not switched on, for example before sleeping.
+//! Section 7.10 of ATmega32U4 Datasheet
+
+// Source codes required.
+use crate::atmega32u4::registers::{
+    ADCSRA, PRR0, PRR1, SPCR, TCCR0B, TCCR1B, TCCR3B, TCCR4B, TWCR, UCSR1B, USBCON,
+};
+use crate::register::{Field, Register};
+
+/// Power reduction for ATmega32U4 chip
+/// Each of the Peripherals below refers to a bit in PRR0 or PRR1
+/// Setting 7th bit of PRR0 shuts down the TWI(2-wire serial interface) by stopping the clock to the module.
+/// Setting 5th bit of PRR0 shuts down the Timer/Counter0 module.
+/// Setting 3rd bit of PRR0 shuts down the Timer/Counter1 module.
+/// Setting 2nd bit of PRR0 shuts down the serial peripheral interface by stopping the clock to the module.
+/// Setting 0th bit of PRR0 shuts down the ADC.
+/// Setting 7th bit of PRR1 shuts down the USB controller, which must be detached first.
+/// Setting 4th bit of PRR1 shuts down the Timer/Counter4 module.
+/// Setting 3rd bit of PRR1 shuts down the Timer/Counter3 module.
+/// Setting 0th bit of PRR1 shuts down the USART1 by stopping the clock to the module.
+#[derive(Clone, Copy)]
+pub enum Peripherals {
+    TWI,
+    Timer0,
+    Timer1,
+    SPI, //serial peripheral interface
+    ADC,
+    USB,
+    Timer4,
+    Timer3,
+    USART1,
+}
+
+///registers controlling power management
+///
+///Power Reduction Registers control bits for power management.
+#[repr(C, packed)]
+pub struct Power {
+    pub prr0: u8,
+    pub prr1: u8,
+}
+
+impl Power {
+    /// Creates a new reference to the Power structure at a specified location.
+    /// # Returns
+    /// * `a reference Power` - used for further power implementations.
+    pub fn new() -> &'static mut Self {
+        unsafe { &mut *(0x64 as *mut Self) }
+    }
+
+    /// Power control for functioning of Two Wire Interface.
+    pub fn twi(&mut self) {
+        unsafe {
+            let mut ctrl_twi = core::ptr::read_volatile(&mut self.prr0);
+            ctrl_twi |= 0x80;
+            core::ptr::write_volatile(&mut self.prr0, ctrl_twi);
+        }
+    }
+
+    /// Power control for functioning of Timer 0.
+    pub fn timer0(&mut self) {
+        unsafe {
+            let mut ctrl_timer0 = core::ptr::read_volatile(&mut self.prr0);
+            ctrl_timer0 |= 0x20;
+            core::ptr::write_volatile(&mut self.prr0, ctrl_timer0);
+        }
+    }
+
+    /// Power control for functioning of Timer 1.
+    pub fn timer1(&mut self) {
+        unsafe {
+            let mut ctrl_timer1 = core::ptr::read_volatile(&mut self.prr0);
+            ctrl_timer1 |= 0x8;
+            core::ptr::write_volatile(&mut self.prr0, ctrl_timer1);
+        }
+    }
+
+    /// Power control for functioning of Serial Peripheral Interface.
+    pub fn spi(&mut self) {
+        unsafe {
+            let mut ctrl_spi = core::ptr::read_volatile(&mut self.prr0);
+            ctrl_spi |= 0x4;
+            core::ptr::write_volatile(&mut self.prr0, ctrl_spi);
+        }
+    }
+
+    /// Power control for functioning of Analog to Digital Converter.
+    pub fn adc(&mut self) {
+        unsafe {
+            let mut ctrl_adc = core::ptr::read_volatile(&mut self.prr0);
+            ctrl_adc |= 0x1;
+            core::ptr::write_volatile(&mut self.prr0, ctrl_adc);
+        }
+    }
+
+    /// Power control for functioning of USB controller.
+    pub fn usb(&mut self) {
+        unsafe {
+            let mut ctrl_usb = core::ptr::read_volatile(&mut self.prr1);
+            ctrl_usb |= 0x80;
+            core::ptr::write_volatile(&mut self.prr1, ctrl_usb);
+        }
+    }
+
+    /// Power control for functioning of Timer 4.
+    pub fn timer4(&mut self) {
+        unsafe {
+            let mut ctrl_timer4 = core::ptr::read_volatile(&mut self.prr1);
+            ctrl_timer4 |= 0x10;
+            core::ptr::write_volatile(&mut self.prr1, ctrl_timer4);
+        }
+    }
+
+    /// Power control for functioning of Timer 3.
+    pub fn timer3(&mut self) {
+        unsafe {
+            let mut ctrl_timer3 = core::ptr::read_volatile(&mut self.prr1);
+            ctrl_timer3 |= 0x8;
+            core::ptr::write_volatile(&mut self.prr1, ctrl_timer3);
+        }
+    }
+
+    /// Power control for functioning of USART1.
+    pub fn usart1(&mut self) {
+        unsafe {
+            let mut ctrl_usart1 = core::ptr::read_volatile(&mut self.prr1);
+            ctrl_usart1 |= 0x1;
+            core::ptr::write_volatile(&mut self.prr1, ctrl_usart1);
+        }
+    }
+
+    /// Disables the clock
+    /// # Arguments
+    /// * `mode` - a `Peripherals` object, to set the power mode to disable clocks in a specific defined mode.
+    pub fn disable_clock(mode: Peripherals) {
+        match mode {
+            Peripherals::TWI => Power::twi(&mut Power::new()),
+            Peripherals::Timer0 => Power::timer0(&mut Power::new()),
+            Peripherals::Timer1 => Power::timer1(&mut Power::new()),
+            Peripherals::SPI => Power::spi(&mut Power::new()),
+            Peripherals::ADC => Power::adc(&mut Power::new()),
+            Peripherals::USB => Power::usb(&mut Power::new()),
+            Peripherals::Timer4 => Power::timer4(&mut Power::new()),
+            Peripherals::Timer3 => Power::timer3(&mut Power::new()),
+            Peripherals::USART1 => Power::usart1(&mut Power::new()),
+        }
+    }
+
+    /// Enables the clock of a peripheral, which must be done before its registers are used.
+    /// # Arguments
+    /// * `mode` - a `Peripherals` object, the peripheral whose clock is enabled.
+    pub fn enable_clock(mode: Peripherals) {
+        let (prr, bit) = Power::prr_bit(mode);
+        prr.clear(bit);
+    }
+
+    /// Checks whether the clock of a peripheral is running.
+    /// # Arguments
+    /// * `mode` - a `Peripherals` object, the peripheral to be checked.
+    /// # Returns
+    /// * `a boolean` - which is true if the PRR0 or PRR1 bit of the peripheral is clear.
+    pub fn is_clock_enabled(mode: Peripherals) -> bool {
+        let (prr, bit) = Power::prr_bit(mode);
+        !prr.is_set(bit)
+    }
+
+    /// Gates the clock of every peripheral which is not switched on: the TWI, SPI, ADC and
+    /// USB controller when their enable bit is clear, the USART when both its receiver and
+    /// transmitter are disabled, and the timers which have no clock source selected.
+    /// Timer0 counts `millis` once `time::init` has been called, so it is kept then.
+    pub fn disable_all_unused() {
+        let unused = [
+            (Peripherals::TWI, !TWCR.is_set(TWCR::TWEN)),
+            (Peripherals::Timer0, TCCR0B.read_field(TCCR0B::CS0) == 0),
+            (Peripherals::Timer1, TCCR1B.read_field(TCCR1B::CS1) == 0),
+            (Peripherals::SPI, !SPCR.is_set(SPCR::SPE)),
+            (Peripherals::ADC, !ADCSRA.is_set(ADCSRA::ADEN)),
+            (Peripherals::USB, !USBCON.is_set(USBCON::USBE)),
+            (Peripherals::Timer4, TCCR4B.read_field(TCCR4B::CS4) == 0),
+            (Peripherals::Timer3, TCCR3B.read_field(TCCR3B::CS3) == 0),
+            (
+                Peripherals::USART1,
+                !UCSR1B.is_set(UCSR1B::RXEN1) && !UCSR1B.is_set(UCSR1B::TXEN1),
+            ),
+        ];
+        for (peripheral, unused) in unused.iter() {
+            if *unused {
+                Power::disable_clock(*peripheral);
+            }
+        }
+    }
+
+    // Gives the register and the bit which gate the clock of a peripheral.
+    fn prr_bit(mode: Peripherals) -> (Register<u8>, Field) {
+        match mode {
+            Peripherals::TWI => (PRR0, PRR0::PRTWI),
+            Peripherals::Timer0 => (PRR0, PRR0::PRTIM0),
+            Peripherals::Timer1 => (PRR0, PRR0::PRTIM1),
+            Peripherals::SPI => (PRR0, PRR0::PRSPI),
+            Peripherals::ADC => (PRR0, PRR0::PRADC),
+            Peripherals::USB => (PRR1, PRR1::PRUSB),
+            Peripherals::Timer4 => (PRR1, PRR1::PRTIM4),
+            Peripherals::Timer3 => (PRR1, PRR1::PRTIM3),
+            Peripherals::USART1 => (PRR1, PRR1::PRUSART1),
+        }
+    }
+}
@@ -0,0 +1,180 @@
+// RustDuino : A generic HAL implementation for Arduino Boards in Rust
+// Copyright (C) 2021  Akshit Verma, Indian Institute of Technology Kanpur
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Control on Watchdog timer in ATMEGA32U4
+//! Watchdog timer 8.9 of the manual.
+//! The timer can reset the chip, or call an interrupt which wakes the chip up from sleep.
+
+use crate::atmega32u4::hal::interrupts;
+use crate::atmega32u4::registers::{MCUSR, WDTCSR};
+use crate::sync;
+
+/// MCUSR (MCU Status Register)
+/// The MCU status register provides information on which reset source caused an MCU reset.
+///
+/// WDTCSR (Watchdog Timer Control Register).
+/// Used to control the action of timer on timeout.
+///       Mode Action                                        on Time-out
+///         Stopped                                             None
+///     Interrupt mode                                        Interrupt
+///     System reset mode                                       Reset
+/// Interrupt and system reset mode         Interrupt, then go to system reset mode
+///
+/// Both registers are reached through the register map, so the structure holds nothing.
+pub struct WatchDog {
+    _private: (),
+}
+
+impl WatchDog {
+    /// Creates new struct of Watchdog.
+    /// # Returns
+    /// * `a reference to Watchdog structure` - for further implementations.
+    pub unsafe fn new() -> &'static mut WatchDog {
+        &mut *(MCUSR.address() as *mut WatchDog)
+    }
+
+    /// Resets watchdog timer.
+    pub fn reset_watchdog(&mut self) {
+        MCUSR.clear(MCUSR::WDRF);
+    }
+
+    /// Disables watchdog
+    /// Global interrupts are left as they were before the call.
+    pub fn disable(&mut self) {
+        sync::free(|_| {
+            self.reset_watchdog();
+            WDTCSR.modify(|wdtcsr| wdtcsr | (WDTCSR::WDCE.mask() | WDTCSR::WDE.mask()) as u8);
+            WDTCSR.write(0x00);
+        })
+    }
+}
+
+/// Selection of the action taken when the watchdog timer times out.
+/// * `Interrupt` - the watchdog interrupt is called, for example to wake up from sleep.
+/// * `Reset` - the chip is reset.
+/// * `InterruptReset` - the interrupt is called first and the chip is reset on the next
+///   time-out, unless the interrupt is enabled again with `enable_interrupt()`.
+#[derive(Clone, Copy)]
+pub enum WatchdogMode {
+    Interrupt,
+    Reset,
+    InterruptReset,
+}
+
+/// Selection of the time-out of the watchdog timer, from the prescaler of the
+/// 128 kHz watchdog oscillator.
+#[derive(Clone, Copy)]
+pub enum WatchdogPrescaler {
+    Ms16,
+    Ms32,
+    Ms64,
+    Ms125,
+    Ms250,
+    Ms500,
+    S1,
+    S2,
+    S4,
+    S8,
+}
+
+impl WatchdogPrescaler {
+    /// Gives the value of the WDP3:0 bits, with WDP3 at bit 5 of WDTCSR.
+    fn bits(&self) -> u8 {
+        let wdp = *self as u8;
+        (wdp & 0x08) << 2 | (wdp & 0x07)
+    }
+
+    /// Gives the approximate time-out in milliseconds.
+    /// # Returns
+    /// * `a u32` - the time-out, which may differ by about 10% with the voltage and temperature.
+    pub fn millis(&self) -> u32 {
+        match self {
+            WatchdogPrescaler::Ms16 => 16,
+            WatchdogPrescaler::Ms32 => 32,
+            WatchdogPrescaler::Ms64 => 64,
+            WatchdogPrescaler::Ms125 => 125,
+            WatchdogPrescaler::Ms250 => 250,
+            WatchdogPrescaler::Ms500 => 500,
+            WatchdogPrescaler::S1 => 1000,
+            WatchdogPrescaler::S2 => 2000,
+            WatchdogPrescaler::S4 => 4000,
+            WatchdogPrescaler::S8 => 8000,
+        }
+    }
+}
+
+// Function called from the watchdog interrupt.
+static mut WATCHDOG_HANDLER: Option<fn()> = None;
+
+impl WatchDog {
+    /// Starts the watchdog timer in the given mode.
+    /// # Arguments
+    /// * `mode` - a `WatchdogMode` object, the action taken on time-out.
+    /// * `prescaler` - a `WatchdogPrescaler` object, the time-out of the timer.
+    pub fn enable(&mut self, mode: WatchdogMode, prescaler: WatchdogPrescaler) {
+        let mut wdtcsr = prescaler.bits() | WDTCSR::WDIF.mask() as u8;
+        match mode {
+            WatchdogMode::Interrupt => wdtcsr |= WDTCSR::WDIE.mask() as u8,
+            WatchdogMode::Reset => wdtcsr |= WDTCSR::WDE.mask() as u8,
+            WatchdogMode::InterruptReset => {
+                wdtcsr |= (WDTCSR::WDIE.mask() | WDTCSR::WDE.mask()) as u8
+            }
+        }
+        interrupts::without_interrupts(|| unsafe {
+            llvm_asm!("wdr" :::: "volatile");
+            if let WatchdogMode::Interrupt = mode {
+                // WDE is forced to one as long as WDRF is set.
+                MCUSR.clear(MCUSR::WDRF);
+            }
+            // The new value must be written within four cycles of setting WDCE.
+            llvm_asm!("sts 0x60, $0
+                       sts 0x60, $1"
+                     :
+                     : "r"((WDTCSR::WDCE.mask() | WDTCSR::WDE.mask()) as u8), "r"(wdtcsr)
+                     :
+                     : "volatile");
+        });
+    }
+
+    /// Restarts the watchdog timer, which must be done before each time-out in the reset mode.
+    pub fn feed(&mut self) {
+        unsafe { llvm_asm!("wdr" :::: "volatile") }
+    }
+
+    /// Enables the watchdog interrupt again, which is disabled by hardware when it
+    /// is called in the `InterruptReset` mode.
+    pub fn enable_interrupt(&mut self) {
+        WDTCSR.set(WDTCSR::WDIE);
+    }
+
+    /// Sets the function called on every time-out in the interrupt modes.
+    /// # Arguments
+    /// * `handler` - a function, which is called from the interrupt service routine.
+    pub fn on_timeout(&mut self, handler: fn()) {
+        interrupts::without_interrupts(|| unsafe {
+            WATCHDOG_HANDLER = Some(handler);
+        });
+    }
+}
+
+/// Watchdog Time-out Interrupt.
+#[cfg(target_arch = "avr")]
+#[no_mangle]
+pub unsafe extern "avr-interrupt" fn __vector_12() {
+    if let Some(handler) = WATCHDOG_HANDLER {
+        handler();
+    }
+}
@@ -0,0 +1,291 @@
+// RustDuino : A generic HAL implementation for Arduino Boards in Rust
+// Copyright (C) 2021 Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Register map of the ATMEGA32U4, from the Register Summary of the manual.
+//! Each register is a `Register` constant at its data space address, and its fields are
+//! in the module of the same name, as `WDTCSR` and `WDTCSR::WDE`.
+
+// Crates which would be used in the implementation.
+use crate::register::register_map;
+
+register_map! {
+    /// Port B Input Pins Address.
+    PINB: u8 @ 0x23 {}
+    /// Port B Data Direction Register.
+    DDRB: u8 @ 0x24 {}
+    /// Port B Data Register.
+    PORTB: u8 @ 0x25 {}
+    /// Port C Input Pins Address.
+    PINC: u8 @ 0x26 {}
+    /// Port C Data Direction Register.
+    DDRC: u8 @ 0x27 {}
+    /// Port C Data Register.
+    PORTC: u8 @ 0x28 {}
+    /// Port D Input Pins Address.
+    PIND: u8 @ 0x29 {}
+    /// Port D Data Direction Register.
+    DDRD: u8 @ 0x2A {}
+    /// Port D Data Register.
+    PORTD: u8 @ 0x2B {}
+    /// Port E Input Pins Address.
+    PINE: u8 @ 0x2C {}
+    /// Port E Data Direction Register.
+    DDRE: u8 @ 0x2D {}
+    /// Port E Data Register.
+    PORTE: u8 @ 0x2E {}
+    /// Port F Input Pins Address.
+    PINF: u8 @ 0x2F {}
+    /// Port F Data Direction Register.
+    DDRF: u8 @ 0x30 {}
+    /// Port F Data Register.
+    PORTF: u8 @ 0x31 {}
+    /// Timer/Counter0 Interrupt Flag Register.
+    TIFR0: u8 @ 0x35 { OCF0B: 2, OCF0A: 1, TOV0: 0 }
+    /// Timer/Counter1 Interrupt Flag Register.
+    TIFR1: u8 @ 0x36 { ICF1: 5, OCF1C: 3, OCF1B: 2, OCF1A: 1, TOV1: 0 }
+    /// Timer/Counter3 Interrupt Flag Register.
+    TIFR3: u8 @ 0x38 { ICF3: 5, OCF3C: 3, OCF3B: 2, OCF3A: 1, TOV3: 0 }
+    /// Timer/Counter4 Interrupt Flag Register.
+    TIFR4: u8 @ 0x39 { OCF4D: 7, OCF4A: 6, OCF4B: 5, TOV4: 2 }
+    /// Pin Change Interrupt Flag Register.
+    PCIFR: u8 @ 0x3B { PCIF0: 0 }
+    /// External Interrupt Flag Register.
+    EIFR: u8 @ 0x3C { INTF6: 6, INTF3: 3, INTF2: 2, INTF1: 1, INTF0: 0 }
+    /// External Interrupt Mask Register.
+    EIMSK: u8 @ 0x3D { INT6: 6, INT3: 3, INT2: 2, INT1: 1, INT0: 0 }
+    /// General Purpose I/O Register 0.
+    GPIOR0: u8 @ 0x3E {}
+    /// EEPROM Control Register.
+    EECR: u8 @ 0x3F { EEPM: 4..6, EERIE: 3, EEMPE: 2, EEPE: 1, EERE: 0 }
+    /// EEPROM Data Register.
+    EEDR: u8 @ 0x40 {}
+    /// EEPROM Address Register.
+    EEAR: u16 @ 0x41 {}
+    /// General Timer/Counter Control Register.
+    GTCCR: u8 @ 0x43 { TSM: 7, PSRASY: 1, PSRSYNC: 0 }
+    /// Timer/Counter0 Control Register A.
+    TCCR0A: u8 @ 0x44 { COM0A: 6..8, COM0B: 4..6, WGM0: 0..2 }
+    /// Timer/Counter0 Control Register B.
+    TCCR0B: u8 @ 0x45 { FOC0A: 7, FOC0B: 6, WGM02: 3, CS0: 0..3 }
+    /// Timer/Counter0.
+    TCNT0: u8 @ 0x46 {}
+    /// Output Compare Register 0 A.
+    OCR0A: u8 @ 0x47 {}
+    /// Output Compare Register 0 B.
+    OCR0B: u8 @ 0x48 {}
+    /// PLL Control and Status Register.
+    PLLCSR: u8 @ 0x49 { PINDIV: 4, PLLE: 1, PLOCK: 0 }
+    /// General Purpose I/O Register 1.
+    GPIOR1: u8 @ 0x4A {}
+    /// General Purpose I/O Register 2.
+    GPIOR2: u8 @ 0x4B {}
+    /// SPI Control Register.
+    SPCR: u8 @ 0x4C { SPIE: 7, SPE: 6, DORD: 5, MSTR: 4, CPOL: 3, CPHA: 2, SPR: 0..2 }
+    /// SPI Status Register.
+    SPSR: u8 @ 0x4D { SPIF: 7, WCOL: 6, SPI2X: 0 }
+    /// SPI Data Register.
+    SPDR: u8 @ 0x4E {}
+    /// Analog Comparator Control and Status Register.
+    ACSR: u8 @ 0x50 { ACD: 7, ACBG: 6, ACO: 5, ACI: 4, ACIE: 3, ACIC: 2, ACIS: 0..2 }
+    /// Sleep Mode Control Register.
+    SMCR: u8 @ 0x53 { SM: 1..4, SE: 0 }
+    /// MCU Status Register, the source of the last reset.
+    MCUSR: u8 @ 0x54 { JTRF: 4, WDRF: 3, BORF: 2, EXTRF: 1, PORF: 0 }
+    /// MCU Control Register.
+    MCUCR: u8 @ 0x55 { JTD: 7, PUD: 4, IVSEL: 1, IVCE: 0 }
+    /// Store Program Memory Control and Status Register.
+    SPMCSR: u8 @ 0x57 { SPMIE: 7, RWWSB: 6, SIGRD: 5, RWWSRE: 4, BLBSET: 3, PGWRT: 2, PGERS: 1, SPMEN: 0 }
+    /// Extended Z-pointer Register for ELPM/SPM.
+    RAMPZ: u8 @ 0x5B { RAMPZ: 0..2 }
+    /// Stack Pointer.
+    SP: u16 @ 0x5D {}
+    /// Status Register.
+    SREG: u8 @ 0x5F { I: 7, T: 6, H: 5, S: 4, V: 3, N: 2, Z: 1, C: 0 }
+    /// Watchdog Timer Control Register.
+    WDTCSR: u8 @ 0x60 { WDIF: 7, WDIE: 6, WDP3: 5, WDCE: 4, WDE: 3, WDP: 0..3 }
+    /// Clock Prescale Register.
+    CLKPR: u8 @ 0x61 { CLKPCE: 7, CLKPS: 0..4 }
+    /// Power Reduction Register 0.
+    PRR0: u8 @ 0x64 { PRTWI: 7, PRTIM0: 5, PRTIM1: 3, PRSPI: 2, PRADC: 0 }
+    /// Power Reduction Register 1.
+    PRR1: u8 @ 0x65 { PRUSB: 7, PRTIM4: 4, PRTIM3: 3, PRUSART1: 0 }
+    /// Oscillator Calibration Register.
+    OSCCAL: u8 @ 0x66 {}
+    /// Oscillator Control Register.
+    RCCTRL: u8 @ 0x67 { RCFREQ: 0 }
+    /// Pin Change Interrupt Control Register.
+    PCICR: u8 @ 0x68 { PCIE0: 0 }
+    /// External Interrupt Control Register A.
+    EICRA: u8 @ 0x69 { ISC3: 6..8, ISC2: 4..6, ISC1: 2..4, ISC0: 0..2 }
+    /// External Interrupt Control Register B.
+    EICRB: u8 @ 0x6A { ISC6: 4..6 }
+    /// Pin Change Mask Register 0.
+    PCMSK0: u8 @ 0x6B {}
+    /// Timer/Counter0 Interrupt Mask Register.
+    TIMSK0: u8 @ 0x6E { OCIE0B: 2, OCIE0A: 1, TOIE0: 0 }
+    /// Timer/Counter1 Interrupt Mask Register.
+    TIMSK1: u8 @ 0x6F { ICIE1: 5, OCIE1C: 3, OCIE1B: 2, OCIE1A: 1, TOIE1: 0 }
+    /// Timer/Counter3 Interrupt Mask Register.
+    TIMSK3: u8 @ 0x71 { ICIE3: 5, OCIE3C: 3, OCIE3B: 2, OCIE3A: 1, TOIE3: 0 }
+    /// Timer/Counter4 Interrupt Mask Register.
+    TIMSK4: u8 @ 0x72 { OCIE4D: 7, OCIE4A: 6, OCIE4B: 5, TOIE4: 2 }
+    /// ADC Data Register.
+    ADC: u16 @ 0x78 {}
+    /// ADC Control and Status Register A.
+    ADCSRA: u8 @ 0x7A { ADEN: 7, ADSC: 6, ADATE: 5, ADIF: 4, ADIE: 3, ADPS: 0..3 }
+    /// ADC Control and Status Register B.
+    ADCSRB: u8 @ 0x7B { ADHSM: 7, ACME: 6, MUX5: 5, ADTS: 0..4 }
+    /// ADC Multiplexer Selection Register.
+    ADMUX: u8 @ 0x7C { REFS: 6..8, ADLAR: 5, MUX: 0..5 }
+    /// Digital Input Disable Register 2.
+    DIDR2: u8 @ 0x7D {}
+    /// Digital Input Disable Register 0.
+    DIDR0: u8 @ 0x7E {}
+    /// Digital Input Disable Register 1.
+    DIDR1: u8 @ 0x7F { AIN1D: 1, AIN0D: 0 }
+    /// Timer/Counter1 Control Register A.
+    TCCR1A: u8 @ 0x80 { COM1A: 6..8, COM1B: 4..6, COM1C: 2..4, WGM1: 0..2 }
+    /// Timer/Counter1 Control Register B.
+    TCCR1B: u8 @ 0x81 { ICNC1: 7, ICES1: 6, WGM13: 4, WGM12: 3, CS1: 0..3 }
+    /// Timer/Counter1 Control Register C.
+    TCCR1C: u8 @ 0x82 { FOC1A: 7, FOC1B: 6, FOC1C: 5 }
+    /// Timer/Counter1.
+    TCNT1: u16 @ 0x84 {}
+    /// Input Capture Register 1.
+    ICR1: u16 @ 0x86 {}
+    /// Output Compare Register 1 A.
+    OCR1A: u16 @ 0x88 {}
+    /// Output Compare Register 1 B.
+    OCR1B: u16 @ 0x8A {}
+    /// Output Compare Register 1 C.
+    OCR1C: u16 @ 0x8C {}
+    /// Timer/Counter3 Control Register A.
+    TCCR3A: u8 @ 0x90 { COM3A: 6..8, COM3B: 4..6, COM3C: 2..4, WGM3: 0..2 }
+    /// Timer/Counter3 Control Register B.
+    TCCR3B: u8 @ 0x91 { ICNC3: 7, ICES3: 6, WGM33: 4, WGM32: 3, CS3: 0..3 }
+    /// Timer/Counter3 Control Register C.
+    TCCR3C: u8 @ 0x92 { FOC3A: 7, FOC3B: 6, FOC3C: 5 }
+    /// Timer/Counter3.
+    TCNT3: u16 @ 0x94 {}
+    /// Input Capture Register 3.
+    ICR3: u16 @ 0x96 {}
+    /// Output Compare Register 3 A.
+    OCR3A: u16 @ 0x98 {}
+    /// Output Compare Register 3 B.
+    OCR3B: u16 @ 0x9A {}
+    /// Output Compare Register 3 C.
+    OCR3C: u16 @ 0x9C {}
+    /// TWI Bit Rate Register.
+    TWBR: u8 @ 0xB8 {}
+    /// TWI Status Register.
+    TWSR: u8 @ 0xB9 { TWS: 3..8, TWPS: 0..2 }
+    /// TWI (Slave) Address Register.
+    TWAR: u8 @ 0xBA { TWA: 1..8, TWGCE: 0 }
+    /// TWI Data Register.
+    TWDR: u8 @ 0xBB {}
+    /// TWI Control Register.
+    TWCR: u8 @ 0xBC { TWINT: 7, TWEA: 6, TWSTA: 5, TWSTO: 4, TWWC: 3, TWEN: 2, TWIE: 0 }
+    /// TWI (Slave) Address Mask Register.
+    TWAMR: u8 @ 0xBD { TWAM: 1..8 }
+    /// Timer/Counter4, the low byte.
+    TCNT4: u8 @ 0xBE {}
+    /// Timer/Counter4 High Byte, the bits 9:8 of the 10 bit registers of Timer/Counter4.
+    TC4H: u8 @ 0xBF { TC4H: 0..2 }
+    /// Timer/Counter4 Control Register A.
+    TCCR4A: u8 @ 0xC0 { COM4A: 6..8, COM4B: 4..6, FOC4A: 3, FOC4B: 2, PWM4A: 1, PWM4B: 0 }
+    /// Timer/Counter4 Control Register B.
+    TCCR4B: u8 @ 0xC1 { PWM4X: 7, PSR4: 6, DTPS4: 4..6, CS4: 0..4 }
+    /// Timer/Counter4 Control Register C.
+    TCCR4C: u8 @ 0xC2 { COM4D: 2..4, FOC4D: 1, PWM4D: 0 }
+    /// Timer/Counter4 Control Register D.
+    TCCR4D: u8 @ 0xC3 { FPIE4: 7, FPEN4: 6, WGM4: 0..2 }
+    /// Timer/Counter4 Control Register E.
+    TCCR4E: u8 @ 0xC4 { TLOCK4: 7, ENHC4: 6 }
+    /// Clock Selection Register 0.
+    CLKSEL0: u8 @ 0xC5 { RCE: 3, EXTE: 2, CLKS: 0 }
+    /// Clock Selection Register 1.
+    CLKSEL1: u8 @ 0xC6 {}
+    /// Clock Status Register.
+    CLKSTA: u8 @ 0xC7 { RCON: 1, EXTON: 0 }
+    /// USART1 Control and Status Register A.
+    UCSR1A: u8 @ 0xC8 { RXC1: 7, TXC1: 6, UDRE1: 5, FE1: 4, DOR1: 3, UPE1: 2, U2X1: 1, MPCM1: 0 }
+    /// USART1 Control and Status Register B.
+    UCSR1B: u8 @ 0xC9 { RXCIE1: 7, TXCIE1: 6, UDRIE1: 5, RXEN1: 4, TXEN1: 3, UCSZ12: 2, RXB81: 1, TXB81: 0 }
+    /// USART1 Control and Status Register C.
+    UCSR1C: u8 @ 0xCA { UMSEL1: 6..8, UPM1: 4..6, USBS1: 3, UCSZ1: 1..3, UCPOL1: 0 }
+    /// USART1 Control and Status Register D.
+    UCSR1D: u8 @ 0xCB { CTSEN: 1, RTSEN: 0 }
+    /// USART1 Baud Rate Register.
+    UBRR1: u16 @ 0xCC {}
+    /// USART1 I/O Data Register.
+    UDR1: u8 @ 0xCE {}
+    /// Output Compare Register 4 A.
+    OCR4A: u8 @ 0xCF {}
+    /// Output Compare Register 4 B.
+    OCR4B: u8 @ 0xD0 {}
+    /// Output Compare Register 4 C, the TOP of Timer/Counter4.
+    OCR4C: u8 @ 0xD1 {}
+    /// Output Compare Register 4 D.
+    OCR4D: u8 @ 0xD2 {}
+    /// Timer/Counter4 Dead Time Value.
+    DT4: u8 @ 0xD4 {}
+    /// USB Hardware Configuration Register.
+    UHWCON: u8 @ 0xD7 { UVREGE: 0 }
+    /// USB General Control Register.
+    USBCON: u8 @ 0xD8 { USBE: 7, FRZCLK: 5, OTGPADE: 4, VBUSTE: 0 }
+    /// USB General Status Register.
+    USBSTA: u8 @ 0xD9 { ID: 1, VBUS: 0 }
+    /// USB General Interrupt Register.
+    USBINT: u8 @ 0xDA { VBUSTI: 0 }
+    /// USB Device Control Register.
+    UDCON: u8 @ 0xE0 { RSTCPU: 3, LSM: 2, RMWKUP: 1, DETACH: 0 }
+    /// USB Device Interrupt Register.
+    UDINT: u8 @ 0xE1 { UPRSMI: 6, EORSMI: 5, WAKEUPI: 4, EORSTI: 3, SOFI: 2, SUSPI: 0 }
+    /// USB Device Interrupt Enable Register.
+    UDIEN: u8 @ 0xE2 { UPRSME: 6, EORSME: 5, WAKEUPE: 4, EORSTE: 3, SOFE: 2, SUSPE: 0 }
+    /// USB Device Address Register.
+    UDADDR: u8 @ 0xE3 { ADDEN: 7, UADD: 0..7 }
+    /// USB Device Frame Number Register.
+    UDFNUM: u16 @ 0xE4 {}
+    /// USB Device Frame Number CRC Error Register.
+    UDMFN: u8 @ 0xE6 { FNCERR: 4 }
+    /// USB Endpoint Interrupt Register.
+    UEINTX: u8 @ 0xE8 { FIFOCON: 7, NAKINI: 6, RWAL: 5, NAKOUTI: 4, RXSTPI: 3, RXOUTI: 2, STALLEDI: 1, TXINI: 0 }
+    /// USB Endpoint Number Register.
+    UENUM: u8 @ 0xE9 { EPNUM: 0..3 }
+    /// USB Endpoint Reset Register.
+    UERST: u8 @ 0xEA { EPRST: 0..7 }
+    /// USB Endpoint Control Register.
+    UECONX: u8 @ 0xEB { STALLRQ: 5, STALLRQC: 4, RSTDT: 3, EPEN: 0 }
+    /// USB Endpoint Configuration Register 0.
+    UECFG0X: u8 @ 0xEC { EPTYPE: 6..8, EPDIR: 0 }
+    /// USB Endpoint Configuration Register 1.
+    UECFG1X: u8 @ 0xED { EPSIZE: 4..7, EPBK: 2..4, ALLOC: 1 }
+    /// USB Endpoint Status Register 0.
+    UESTA0X: u8 @ 0xEE { CFGOK: 7, OVERFI: 6, UNDERFI: 5, DTSEQ: 2..4, NBUSYBK: 0..2 }
+    /// USB Endpoint Status Register 1.
+    UESTA1X: u8 @ 0xEF { CTRLDIR: 2, CURRBK: 0..2 }
+    /// USB Endpoint Interrupt Enable Register.
+    UEIENX: u8 @ 0xF0 { FLERRE: 7, NAKINE: 6, NAKOUTE: 4, RXSTPE: 3, RXOUTE: 2, STALLEDE: 1, TXINE: 0 }
+    /// USB Endpoint Data Register.
+    UEDATX: u8 @ 0xF1 {}
+    /// USB Endpoint Byte Count Register, the low byte.
+    UEBCLX: u8 @ 0xF2 {}
+    /// USB Endpoint Byte Count Register, the high bits.
+    UEBCHX: u8 @ 0xF3 {}
+    /// USB Endpoint Number Interrupt Register.
+    UEINT: u8 @ 0xF4 { EPINT: 0..7 }
+}
@@ -0,0 +1,251 @@
+// RustDuino : A generic HAL implementation for Arduino Boards in Rust
+// Copyright (C) 2021 Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! I2C master for ATTINY85, which has no TWI and uses the Universal Serial Interface
+//! (USI) in its two wire mode instead, as described in the Atmel application note AVR310.
+//! The USI only shifts the bits and holds SCL low after the start condition, so the
+//! start and stop conditions and the clock pulses are generated by the software,
+//! at the timing of the standard mode (100 kHz) of the bus.
+//! SDA is digital pin 0 (PB0) and SCL is digital pin 2 (PB2), both need pull up resistors.
+//! Section 15 of ATTINY85 datasheet.
+
+// Source codes required.
+use crate::attiny85::registers::{DDRB, PINB, PORTB, PRR, USICR, USIDR, USISR};
+use crate::delay::delay_us;
+use crate::error::Error;
+use crate::register::Field;
+
+// Bits of PORTB, DDRB and PINB used by the bus.
+const SDA: Field = Field::bit(0);
+const SCL: Field = Field::bit(2);
+
+// Low and high periods of SCL in microseconds, for the standard mode.
+const T_LOW: u32 = 5;
+const T_HIGH: u32 = 4;
+
+// USISR values clearing the flags and loading the counter, which overflows
+// after 16 edges of SCL for a byte and after 2 edges for the acknowledge bit.
+const USISR_BYTE: u8 = 0xF0;
+const USISR_BIT: u8 = 0xF0 | 0x0E;
+
+/// Structure to control the USI as a I2C master.
+/// The functions return `Error::Nack` when the slave does not acknowledge, as the `Twi` of the other chips.
+pub struct Twi {
+    _private: (),
+}
+
+impl Twi {
+    /// Creates the I2C master, `init` must be called before using it.
+    /// # Returns
+    /// * `a Twi object` - which will be used for further implementations.
+    pub fn new() -> Twi {
+        Twi { _private: () }
+    }
+
+    /// Powers on the USI, releases both lines and sets it in two wire mode
+    /// with the clock strobed by the software.
+    pub fn init(&mut self) {
+        PRR.clear(PRR::PRUSI);
+        PORTB.set(SDA);
+        PORTB.set(SCL);
+        DDRB.set(SDA);
+        DDRB.set(SCL);
+        USIDR.write(0xFF);
+        // Two wire mode, external positive edge clock strobed by USITC.
+        USICR.write(
+            (0b10 << USICR::USIWM.offset | 0b10 << USICR::USICS.offset) as u8
+                | USICR::USICLK.mask() as u8,
+        );
+        USISR.write(USISR_BYTE);
+    }
+
+    /// Sends the start condition, pulling SDA low while SCL is high.
+    /// # Returns
+    /// * `a Result<(), Error>` - which is an error if the start condition was not detected by the USI.
+    pub fn start(&mut self) -> Result<(), Error> {
+        PORTB.set(SCL);
+        while !PINB.is_set(SCL) {}
+        delay_us(T_LOW);
+        PORTB.clear(SDA);
+        delay_us(T_HIGH);
+        PORTB.clear(SCL);
+        PORTB.set(SDA);
+        if USISR.is_set(USISR::USISIF) {
+            Ok(())
+        } else {
+            Err(Error::Bus)
+        }
+    }
+
+    /// Sends the repeated start condition, which is the same as the start condition
+    /// once SCL is held low at the end of a byte.
+    /// # Returns
+    /// * `a Result<(), Error>` - which is an error if the start condition was not detected by the USI.
+    pub fn rep_start(&mut self) -> Result<(), Error> {
+        self.start()
+    }
+
+    /// Sends the stop condition, releasing SDA while SCL is high.
+    pub fn stop(&mut self) {
+        PORTB.clear(SDA);
+        PORTB.set(SCL);
+        while !PINB.is_set(SCL) {}
+        delay_us(T_HIGH);
+        PORTB.set(SDA);
+        delay_us(T_LOW);
+    }
+
+    // Clocks the bits out of and into USIDR until the counter overflows,
+    // and gives the bits received.
+    fn transfer(&mut self, usisr: u8) -> u8 {
+        USISR.write(usisr);
+        while !USISR.is_set(USISR::USIOIF) {
+            delay_us(T_LOW);
+            // Rising edge of SCL, which waits for a slave stretching the clock.
+            USICR.set(USICR::USITC);
+            while !PINB.is_set(SCL) {}
+            delay_us(T_HIGH);
+            // Falling edge of SCL.
+            USICR.set(USICR::USITC);
+        }
+        delay_us(T_LOW);
+        let data = USIDR.read();
+        USIDR.write(0xFF);
+        DDRB.set(SDA);
+        data
+    }
+
+    /// Writes a byte and reads the acknowledge bit of the slave.
+    /// # Arguments
+    /// * `data` - a u8, the byte to be sent.
+    /// # Returns
+    /// * `a Result<(), Error>` - which is an error if the slave did not acknowledge the byte.
+    pub fn write(&mut self, data: u8) -> Result<(), Error> {
+        PORTB.clear(SCL);
+        USIDR.write(data);
+        self.transfer(USISR_BYTE);
+        DDRB.clear(SDA);
+        if self.transfer(USISR_BIT) & 0x01 == 0 {
+            Ok(())
+        } else {
+            Err(Error::Nack)
+        }
+    }
+
+    /// Reads a byte once the address of the slave has been sent for reading.
+    /// # Arguments
+    /// * `ack` - a boolean, which is true if more bytes are to be read after this one.
+    /// # Returns
+    /// * `a u8` - the byte read.
+    pub fn read_byte(&mut self, ack: bool) -> u8 {
+        DDRB.clear(SDA);
+        let data = self.transfer(USISR_BYTE);
+        USIDR.write(if ack { 0x00 } else { 0xFF });
+        self.transfer(USISR_BIT);
+        data
+    }
+
+    /// Sends the address of the slave device for writing.
+    /// # Arguments
+    /// * `address` - a u8, the seven bit address of the slave.
+    /// # Returns
+    /// * `a Result<(), Error>` - which is an error if the slave did not acknowledge its address.
+    pub fn address_write(&mut self, address: u8) -> Result<(), Error> {
+        self.write(address << 1)
+    }
+
+    /// Sends the address of the slave device for reading.
+    /// # Arguments
+    /// * `address` - a u8, the seven bit address of the slave.
+    /// # Returns
+    /// * `a Result<(), Error>` - which is an error if the slave did not acknowledge its address.
+    pub fn address_read(&mut self, address: u8) -> Result<(), Error> {
+        self.write(address << 1 | 0x01)
+    }
+
+    /// Writes all the bytes to the slave in a single transaction.
+    /// # Arguments
+    /// * `address` - a u8, the seven bit address of the slave.
+    /// * `data` - a slice of u8, the bytes to be sent.
+    /// # Returns
+    /// * `a Result<(), Error>` - which is an error if the slave did not acknowledge its address or a byte.
+    pub fn write_to_slave(&mut self, address: u8, data: &[u8]) -> Result<(), Error> {
+        let mut result = self.start().and_then(|_| self.address_write(address));
+        for byte in data {
+            if result.is_err() {
+                break;
+            }
+            result = self.write(*byte);
+        }
+        self.stop();
+        result
+    }
+
+    /// Reads bytes from the slave in a single transaction, until the buffer is full.
+    /// # Arguments
+    /// * `address` - a u8, the seven bit address of the slave.
+    /// * `buffer` - a mutable slice of u8, which will be filled with the data read.
+    /// # Returns
+    /// * `a Result<(), Error>` - which is an error if the slave did not acknowledge its address.
+    pub fn read_from_slave(&mut self, address: u8, buffer: &mut [u8]) -> Result<(), Error> {
+        let result = self.start().and_then(|_| self.address_read(address));
+        if result.is_ok() {
+            let length = buffer.len();
+            for (i, byte) in buffer.iter_mut().enumerate() {
+                *byte = self.read_byte(i + 1 < length);
+            }
+        }
+        self.stop();
+        result
+    }
+}
+
+impl Default for Twi {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Lets the drivers written against `TwiOps` use the TWI.
+impl crate::common::TwiOps for Twi {
+    fn start(&mut self) -> Result<(), Error> {
+        Twi::start(self)
+    }
+
+    fn rep_start(&mut self) -> Result<(), Error> {
+        Twi::rep_start(self)
+    }
+
+    fn stop(&mut self) {
+        Twi::stop(self);
+    }
+
+    fn address_write(&mut self, address: u8) -> Result<(), Error> {
+        Twi::address_write(self, address)
+    }
+
+    fn address_read(&mut self, address: u8) -> Result<(), Error> {
+        Twi::address_read(self, address)
+    }
+
+    fn write(&mut self, data: u8) -> Result<(), Error> {
+        Twi::write(self, data)
+    }
+
+    fn read_byte(&mut self, ack: bool) -> Result<u8, Error> {
+        Ok(Twi::read_byte(self, ack))
+    }
+}
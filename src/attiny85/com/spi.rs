@@ -0,0 +1,193 @@
+// RustDuino : A generic HAL implementation for Arduino Boards in Rust
+// Copyright (C) 2021 Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! SPI master for ATTINY85, which has no SPI and uses the Universal Serial Interface
+//! (USI) in its three wire mode instead. The clock is strobed by the software, so a
+//! byte takes about 16 instructions per bit and there is no clock divider to set.
+//! The USI always shifts the most significant bit first and samples on the rising
+//! edge of its clock, so only the modes 0 and 1 are supported, with the bytes
+//! reversed by the software when the least significant bit must be sent first.
+//! DO (MOSI) is digital pin 1 (PB1), DI (MISO) is digital pin 0 (PB0) and
+//! USCK (SCK) is digital pin 2 (PB2). Any other pin can be used as slave select.
+//! Section 15 of ATTINY85 datasheet.
+
+// Source codes required.
+use crate::attiny85::registers::{DDRB, PORTB, PRR, USICR, USIDR, USISR};
+use crate::register::Field;
+
+// Bits of PORTB and DDRB used by the bus.
+const DI: Field = Field::bit(0);
+const DO: Field = Field::bit(1);
+const USCK: Field = Field::bit(2);
+
+/// Selection of the clock phase of the SPI bus, the clock is low when idle.
+/// * `Mode0` - CPOL 0, CPHA 0, data sampled on the rising edge.
+/// * `Mode1` - CPOL 0, CPHA 1, data sampled on the falling edge.
+#[derive(Clone, Copy)]
+pub enum SpiMode {
+    Mode0,
+    Mode1,
+}
+
+/// Selection of the order in which the bits of a byte are shifted out.
+#[derive(Clone, Copy)]
+pub enum BitOrder {
+    MsbFirst,
+    LsbFirst,
+}
+
+/// Structure to control the USI as a SPI master.
+pub struct Spi {
+    mode: SpiMode,
+    order: BitOrder,
+}
+
+impl Spi {
+    /// Creates the SPI master, `init` must be called before using it.
+    /// # Returns
+    /// * `a Spi object` - which will be used for further implementations.
+    pub fn new() -> Spi {
+        Spi {
+            mode: SpiMode::Mode0,
+            order: BitOrder::MsbFirst,
+        }
+    }
+
+    /// Powers on the USI and sets it in three wire mode.
+    /// USCK and DO are set as outputs and DI as input.
+    /// # Arguments
+    /// * `mode` - a `SpiMode` object, the clock phase.
+    /// * `order` - a `BitOrder` object, the order in which bits are sent.
+    pub fn init(&mut self, mode: SpiMode, order: BitOrder) {
+        PRR.clear(PRR::PRUSI);
+        PORTB.clear(USCK);
+        DDRB.set(USCK);
+        DDRB.set(DO);
+        DDRB.clear(DI);
+        self.set_mode(mode);
+        self.set_bit_order(order);
+    }
+
+    /// Sets the clock phase.
+    /// # Arguments
+    /// * `mode` - a `SpiMode` object, the mode of the SPI bus.
+    pub fn set_mode(&mut self, mode: SpiMode) {
+        self.mode = mode;
+    }
+
+    /// Sets the order in which the bits are transferred.
+    /// # Arguments
+    /// * `order` - a `BitOrder` object, MSB first or LSB first.
+    pub fn set_bit_order(&mut self, order: BitOrder) {
+        self.order = order;
+    }
+
+    /// Sends a byte and receives a byte at the same time.
+    /// # Arguments
+    /// * `data` - a u8, the byte to be sent.
+    /// # Returns
+    /// * `a u8` - the byte received from the device.
+    pub fn transfer(&mut self, data: u8) -> u8 {
+        let lsb_first = matches!(self.order, BitOrder::LsbFirst);
+        // Three wire mode, clock strobed by USITC, on the positive edge for mode 0
+        // and on the negative edge for mode 1.
+        let usics: u8 = match self.mode {
+            SpiMode::Mode0 => 0b10,
+            SpiMode::Mode1 => 0b11,
+        };
+        let usicr = (0b01 << USICR::USIWM.offset | (usics as u16) << USICR::USICS.offset) as u8
+            | USICR::USICLK.mask() as u8
+            | USICR::USITC.mask() as u8;
+
+        USIDR.write(if lsb_first { data.reverse_bits() } else { data });
+        USISR.write(USISR::USIOIF.mask() as u8);
+        while !USISR.is_set(USISR::USIOIF) {
+            USICR.write(usicr);
+        }
+        let received = USIDR.read();
+        if lsb_first {
+            received.reverse_bits()
+        } else {
+            received
+        }
+    }
+
+    /// Sends all the bytes of the slice, replacing each with the byte received.
+    /// # Arguments
+    /// * `data` - a mutable slice of u8, the bytes to be exchanged.
+    pub fn transfer_in_place(&mut self, data: &mut [u8]) {
+        for byte in data.iter_mut() {
+            *byte = self.transfer(*byte);
+        }
+    }
+
+    /// Sends all the bytes of the slice, ignoring the bytes received.
+    /// # Arguments
+    /// * `data` - a slice of u8, the bytes to be sent.
+    pub fn write(&mut self, data: &[u8]) {
+        for byte in data {
+            self.transfer(*byte);
+        }
+    }
+
+    /// Fills the buffer with bytes received from the device, sending 0xFF for each.
+    /// # Arguments
+    /// * `buffer` - a mutable slice of u8, which will be filled with the data read.
+    pub fn read(&mut self, buffer: &mut [u8]) {
+        for byte in buffer.iter_mut() {
+            *byte = self.transfer(0xFF);
+        }
+    }
+
+    /// Disables the USI, the pins can be used as normal digital pins after this.
+    pub fn end(&mut self) {
+        USICR.write(0);
+        PRR.set(PRR::PRUSI);
+    }
+}
+
+impl Default for Spi {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "embedded")]
+impl embedded_hal::blocking::spi::Transfer<u8> for Spi {
+    type Error = core::convert::Infallible;
+
+    fn transfer<'w>(&mut self, words: &'w mut [u8]) -> Result<&'w [u8], Self::Error> {
+        self.transfer_in_place(words);
+        Ok(words)
+    }
+}
+
+#[cfg(feature = "embedded")]
+impl embedded_hal::blocking::spi::Write<u8> for Spi {
+    type Error = core::convert::Infallible;
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        Spi::write(self, words);
+        Ok(())
+    }
+}
+
+/// Lets the drivers written against `SpiOps` use the SPI.
+impl crate::common::SpiOps for Spi {
+    fn transfer(&mut self, data: u8) -> u8 {
+        Spi::transfer(self, data)
+    }
+}
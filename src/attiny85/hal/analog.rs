@@ -0,0 +1,327 @@
+// RustDuino : A generic HAL implementation for Arduino Boards in Rust
+// Copyright (C) 2021 Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Analog to Digital Converter of ATTINY85, with the same `Adc` type as the other chips.
+//! The four single ended channels ADC0 to ADC3 are the analog pins A0 to A3, on digital
+//! pins 5, 2, 4 and 3. The reference is Vcc, the external AREF on digital pin 0, or
+//! one of the internal 1.1V and 2.56V references.
+//! Refer to section 17 of ATTINY85 datasheet.
+
+// Source codes required.
+use crate::attiny85::registers::{ADC, ADCSRA, ADCSRB, ADMUX, DIDR0, PRR};
+use crate::delay::delay_ms;
+use crate::error::Error;
+
+/// Selection of the reference voltage of the ADC.
+/// * `DEFAULT` - Vcc.
+/// * `EXTERNAL` - the voltage on AREF, digital pin 0, which can not be used as a pin then.
+/// * `INTERNAL1V1` - the internal 1.1V reference.
+/// * `INTERNAL2V56` - the internal 2.56V reference, which needs Vcc above 3V.
+#[derive(Clone, Copy)]
+pub enum RefType {
+    DEFAULT,
+    EXTERNAL,
+    INTERNAL1V1,
+    INTERNAL2V56,
+}
+
+/// Selection of the division factor between the system clock and the ADC clock.
+/// The ADC needs a clock between 50 kHz and 200 kHz for the full 10 bit resolution,
+/// so `Div64` should be used at 8 MHz and `Div128` at 16 MHz.
+#[derive(Clone, Copy)]
+pub enum AdcPrescaler {
+    Div2,
+    Div4,
+    Div8,
+    Div16,
+    Div32,
+    Div64,
+    Div128,
+}
+
+/// Channel of the internal temperature sensor, see section 17.12 of the datasheet.
+pub const ADC_TEMPERATURE: u8 = 15;
+/// Channel of the internal 1.1V bandgap reference.
+pub const ADC_BANDGAP: u8 = 12;
+/// Channel connected to ground.
+pub const ADC_GND: u8 = 13;
+
+/// Calibration of the internal temperature sensor and bandgap reference, which differ
+/// from one chip to another. The default values are the typical ones of table 17-2 of
+/// the datasheet, which can be off by about 10 degrees and 10 percent.
+/// The temperature is `(ADC - temperature_offset) / temperature_gain`, so the offset is
+/// found by reading the raw value at a known temperature. The bandgap voltage is found
+/// by measuring Vcc with a multimeter while reading the raw value of `ADC_BANDGAP`,
+/// as `Vcc * ADC / 1024`.
+/// # Elements
+/// * `temperature_offset` - a f32, the raw value read at 0 degree Celsius.
+/// * `temperature_gain` - a f32, the increase of the raw value for each degree.
+/// * `bandgap_millivolts` - a u16, the voltage of the bandgap reference.
+#[derive(Clone, Copy)]
+pub struct SensorCalibration {
+    pub temperature_offset: f32,
+    pub temperature_gain: f32,
+    pub bandgap_millivolts: u16,
+}
+
+impl Default for SensorCalibration {
+    fn default() -> Self {
+        SensorCalibration {
+            temperature_offset: 272.0,
+            temperature_gain: 1.12,
+            bandgap_millivolts: 1100,
+        }
+    }
+}
+
+/// Structure to perform conversions with the Analog to Digital Converter.
+/// Any of the 4 single ended channels (ADC0 to ADC3) or the internal channels
+/// can be converted, either once or continuously in free running mode.
+pub struct Adc {
+    pending: Option<u8>,
+}
+
+impl Adc {
+    /// Powers on and enables the ADC with Vcc as reference and a
+    /// prescaler of 128, which gives a 125 kHz ADC clock at 16 MHz.
+    /// # Returns
+    /// * `a Adc object` - which will be used for further conversions.
+    pub fn new() -> Adc {
+        PRR.clear(PRR::PRADC);
+        let mut adc = Adc { pending: None };
+        adc.set_reference(RefType::DEFAULT);
+        adc.set_prescaler(AdcPrescaler::Div128);
+        ADCSRA.set(ADCSRA::ADEN);
+        adc
+    }
+
+    /// Selects the reference voltage against which the input is measured.
+    /// # Arguments
+    /// * `reftype` - a `RefType` object, Vcc (`DEFAULT`), AREF or an internal reference.
+    pub fn set_reference(&mut self, reftype: RefType) {
+        let (refs, refs2) = match reftype {
+            RefType::DEFAULT => (0b00, false),
+            RefType::EXTERNAL => (0b01, false),
+            RefType::INTERNAL1V1 => (0b10, false),
+            RefType::INTERNAL2V56 => (0b10, true),
+        };
+        ADMUX.modify(|admux| {
+            let admux = admux & !(ADMUX::REFS.mask() | ADMUX::REFS2.mask()) as u8;
+            admux | refs << ADMUX::REFS.offset | (refs2 as u8) << ADMUX::REFS2.offset
+        });
+    }
+
+    /// Sets the division factor of the ADC clock.
+    /// # Arguments
+    /// * `prescaler` - a `AdcPrescaler` object, the division factor to be used.
+    pub fn set_prescaler(&mut self, prescaler: AdcPrescaler) {
+        ADCSRA.write_field(ADCSRA::ADPS, prescaler as u8 + 1);
+    }
+
+    /// Connects the given channel to the ADC and disables the digital input
+    /// buffer of that pin to reduce power consumption.
+    /// # Arguments
+    /// * `channel` - a u8, the channel number from 0 to 3, or one of the internal channels.
+    /// # Returns
+    /// * `a Result<(), Error>` - which is `Error::InvalidParam` for an unknown channel.
+    pub fn select_channel(&mut self, channel: u8) -> Result<(), Error> {
+        check_channel(channel)?;
+        self.connect(channel);
+        Ok(())
+    }
+
+    // Connects a channel which exists to the ADC.
+    fn connect(&mut self, channel: u8) {
+        let didr = match channel {
+            0 => DIDR0::ADC0D,
+            1 => DIDR0::ADC1D,
+            2 => DIDR0::ADC2D,
+            3 => DIDR0::ADC3D,
+            _ => {
+                ADMUX.write_field(ADMUX::MUX, channel);
+                return;
+            }
+        };
+        ADMUX.write_field(ADMUX::MUX, channel);
+        DIDR0.set(didr);
+    }
+
+    /// Performs a single conversion on the given channel and waits for the result.
+    /// # Arguments
+    /// * `channel` - a u8, the channel number from 0 to 3, or one of the internal channels.
+    /// # Returns
+    /// * `a Result<u16, Error>` - the 10 bit result of the conversion, or
+    ///   `Error::InvalidParam` for an unknown channel.
+    pub fn read_channel(&mut self, channel: u8) -> Result<u16, Error> {
+        check_channel(channel)?;
+        Ok(self.convert_channel(channel))
+    }
+
+    /// Performs a single conversion on a channel which exists, as the channel of a pin,
+    /// and waits for the result.
+    pub(crate) fn convert_channel(&mut self, channel: u8) -> u16 {
+        self.connect(channel);
+        ADCSRA.clear(ADCSRA::ADATE);
+        ADCSRA.set(ADCSRA::ADSC);
+        while self.is_converting() {}
+        self.result()
+    }
+
+    /// Converts the given channel without waiting, for polling loops and `nb::block!`.
+    /// The first call starts the conversion and gives `WouldBlock`, as do the calls which
+    /// follow until the conversion is complete. A call for another channel starts a new
+    /// conversion once the one in progress is complete.
+    /// # Arguments
+    /// * `channel` - a u8, the channel number from 0 to 3, or one of the internal channels.
+    /// # Returns
+    /// * `a nb::Result<u16, Error>` - the 10 bit result, or `Error::InvalidParam` for an unknown channel.
+    pub fn try_read(&mut self, channel: u8) -> nb::Result<u16, Error> {
+        check_channel(channel)?;
+        match self.pending {
+            Some(_) if self.is_converting() => Err(nb::Error::WouldBlock),
+            Some(pending) if pending == channel => {
+                self.pending = None;
+                Ok(self.result())
+            }
+            _ => {
+                self.connect(channel);
+                ADCSRA.clear(ADCSRA::ADATE);
+                ADCSRA.set(ADCSRA::ADSC);
+                self.pending = Some(channel);
+                Err(nb::Error::WouldBlock)
+            }
+        }
+    }
+
+    /// Starts free running mode on the given channel, in which a new conversion
+    /// is started as soon as the previous one completes.
+    /// The latest value can be read at any time with `result()`.
+    /// # Arguments
+    /// * `channel` - a u8, the channel number from 0 to 3, or one of the internal channels.
+    /// # Returns
+    /// * `a Result<(), Error>` - which is `Error::InvalidParam` for an unknown channel.
+    pub fn start_free_running(&mut self, channel: u8) -> Result<(), Error> {
+        self.select_channel(channel)?;
+        ADCSRB.write_field(ADCSRB::ADTS, 0b000);
+        ADCSRA.set(ADCSRA::ADATE);
+        ADCSRA.set(ADCSRA::ADSC);
+        Ok(())
+    }
+
+    /// Stops free running mode after the conversion in progress.
+    pub fn stop_free_running(&mut self) {
+        ADCSRA.clear(ADCSRA::ADATE);
+    }
+
+    /// Checks whether a conversion is still in progress.
+    /// # Returns
+    /// * `a boolean` - which is true while the ADSC bit is set.
+    pub fn is_converting(&self) -> bool {
+        ADCSRA.is_set(ADCSRA::ADSC)
+    }
+
+    /// Gives the result of the last completed conversion.
+    /// # Returns
+    /// * `a u16` - the 10 bit result of the conversion.
+    pub fn result(&mut self) -> u16 {
+        ADC.read()
+    }
+
+    /// Measures the temperature of the chip with the internal sensor, which is read
+    /// against the internal 1.1V reference. Vcc is the reference again afterwards.
+    /// # Arguments
+    /// * `calibration` - a `SensorCalibration` object, the calibration of the chip.
+    /// # Returns
+    /// * `a f32` - the temperature in degree Celsius.
+    pub fn read_temperature(&mut self, calibration: &SensorCalibration) -> f32 {
+        self.set_reference(RefType::INTERNAL1V1);
+        let raw = self.read_settled(ADC_TEMPERATURE);
+        self.set_reference(RefType::DEFAULT);
+        (raw as f32 - calibration.temperature_offset) / calibration.temperature_gain
+    }
+
+    /// Measures the supply voltage without any external part, by reading the
+    /// internal bandgap reference against Vcc.
+    /// # Arguments
+    /// * `calibration` - a `SensorCalibration` object, the calibration of the chip.
+    /// # Returns
+    /// * `a u16` - the supply voltage in millivolts.
+    pub fn read_vcc(&mut self, calibration: &SensorCalibration) -> u16 {
+        self.set_reference(RefType::DEFAULT);
+        let raw = self.read_settled(ADC_BANDGAP).max(1) as u32;
+        (calibration.bandgap_millivolts as u32 * 1024 / raw) as u16
+    }
+
+    /// Converts an internal channel after the reference and the input have settled,
+    /// discarding the first conversion, which is inaccurate after a change of reference.
+    fn read_settled(&mut self, channel: u8) -> u16 {
+        self.connect(channel);
+        delay_ms(2);
+        self.convert_channel(channel);
+        self.convert_channel(channel)
+    }
+
+    /// Disables the ADC and gates its clock as `disable` does, under the name used by the
+    /// other drivers. `Adc::new` enables it again.
+    pub fn shutdown(&mut self) {
+        self.pending = None;
+        self.disable();
+    }
+
+    /// Disables the ADC and switches off its clock to save power.
+    pub fn disable(&mut self) {
+        ADCSRA.clear(ADCSRA::ADEN);
+        PRR.set(PRR::PRADC);
+    }
+}
+
+impl Default for Adc {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Reads the value of an analog pin in the same way as `analogRead` of Arduino.
+/// # Arguments
+/// * `pin` - a u8, the analog pin number from 0 to 3.
+/// # Returns
+/// * `a Result<u16, Error>` - the 10 bit value read, where 1023 corresponds to the reference
+///   voltage, or `Error::InvalidParam` for an unknown pin.
+pub fn analog_read(pin: u8) -> Result<u16, Error> {
+    Adc::new().read_channel(pin)
+}
+
+// Checks that a channel exists.
+fn check_channel(channel: u8) -> Result<(), Error> {
+    match channel {
+        0..=3 | ADC_TEMPERATURE | ADC_BANDGAP | ADC_GND => Ok(()),
+        _ => Err(Error::InvalidParam),
+    }
+}
+
+/// Measures the temperature of the chip with the typical calibration.
+/// # Returns
+/// * `a f32` - the temperature in degree Celsius.
+pub fn read_internal_temperature() -> f32 {
+    Adc::new().read_temperature(&SensorCalibration::default())
+}
+
+/// Measures the supply voltage of the chip with the typical calibration.
+/// # Returns
+/// * `a u16` - the supply voltage in millivolts.
+pub fn read_vcc_millivolts() -> u16 {
+    Adc::new().read_vcc(&SensorCalibration::default())
+}
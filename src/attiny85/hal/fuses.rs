@@ -0,0 +1,156 @@
+// RustDuino : A generic HAL implementation for Arduino Boards in Rust
+// Copyright (C) 2021 Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Fuses, lock bits and signature of ATTINY85 read from software, and the brown-out
+//! detector.
+//! The fuse and lock bits are read by LPM right after BLBSET and SPMEN are set in SPMCSR,
+//! and the signature row by LPM right after SIGRD and SPMEN are set, within 3 cycles,
+//! so both are read in assembly with the interrupts disabled.
+//! A fuse bit is programmed when it reads 0.
+//! The brown-out detector draws about 20 microamperes. ATTINY85 can switch it off during
+//! a sleep in the power down or power save mode, with a timed write of BODS and BODSE in
+//! MCUCR which must be followed by SLEEP within 3 cycles, see `sleep_without_bod`.
+//! See the sections 7.2 and 20.3 of ATTINY85 datasheet.
+
+// Source codes required.
+use crate::attiny85::registers::SPMCSR;
+use crate::sync;
+
+/// Signature of ATTINY85, as given by `signature`.
+pub const SIGNATURE: [u8; 3] = [0x1E, 0x93, 0x0B];
+
+// Values written to SPMCSR to read the fuse and lock bits, and the signature row.
+const READ_FUSES: u8 = 0x09;
+const READ_SIGNATURE: u8 = 0x21;
+
+/// Fuse bytes of the chip.
+/// # Elements
+/// * `low` - a u8, the low fuse byte, with the clock source and CKDIV8.
+/// * `high` - a u8, the high fuse byte.
+/// * `extended` - a u8, the extended fuse byte.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Fuses {
+    pub low: u8,
+    pub high: u8,
+    pub extended: u8,
+}
+
+impl Fuses {
+    /// Gives the level of the brown-out detector, from BODLEVEL2:0 of the high fuse byte.
+    /// # Returns
+    /// * `a Option<u16>` - the typical level in millivolts, which is None if the detector is disabled.
+    pub fn brown_out_level(&self) -> Option<u16> {
+        match self.high & 0x07 {
+            0b110 => Some(1800),
+            0b101 => Some(2700),
+            0b100 => Some(4300),
+            _ => None,
+        }
+    }
+
+    /// Checks whether the clock is divided by 8 at start-up, by the CKDIV8 fuse bit.
+    /// # Returns
+    /// * `a boolean` - which is true if CKDIV8 is programmed.
+    pub fn clock_divided_by_8(&self) -> bool {
+        self.low & 0x80 == 0
+    }
+}
+
+/// Reads the fuse bytes.
+/// # Returns
+/// * `a Fuses object` - the low, high and extended fuse bytes.
+pub fn fuses() -> Fuses {
+    Fuses {
+        low: read(READ_FUSES, 0x0000),
+        high: read(READ_FUSES, 0x0003),
+        extended: read(READ_FUSES, 0x0002),
+    }
+}
+
+/// Reads the lock bits.
+/// # Returns
+/// * `a u8` - the lock bits, with the unused bits read as 1.
+pub fn lock_bits() -> u8 {
+    read(READ_FUSES, 0x0001)
+}
+
+/// Reads the three bytes of the device signature.
+/// # Returns
+/// * `a [u8; 3]` - the signature, the first byte being 0x1E for Atmel.
+pub fn signature() -> [u8; 3] {
+    [
+        read(READ_SIGNATURE, 0x0000),
+        read(READ_SIGNATURE, 0x0002),
+        read(READ_SIGNATURE, 0x0004),
+    ]
+}
+
+/// Checks whether the program runs on the chip it was built for.
+/// # Returns
+/// * `a boolean` - which is true if the signature is the one of ATTINY85.
+pub fn is_expected_chip() -> bool {
+    signature() == SIGNATURE
+}
+
+/// Reads the calibration byte of the internal RC oscillator written at the factory,
+/// which is loaded into OSCCAL at every reset.
+/// # Returns
+/// * `a u8` - the calibration byte.
+pub fn factory_calibration() -> u8 {
+    read(READ_SIGNATURE, 0x0001)
+}
+
+/// Sleeps with the brown-out detector switched off, which saves power in the power down
+/// and power save modes only. The sleep mode must already be selected and enabled, and
+/// the global interrupts are enabled, as the chip is woken up by an interrupt.
+/// The detector is switched on again when the chip wakes up, and takes about 60
+/// microseconds to be ready.
+pub fn sleep_without_bod() {
+    unsafe {
+        // BODS and BODSE are set together, then BODSE is cleared within 4 cycles,
+        // and SLEEP follows within 3 cycles. The instruction after SEI is executed
+        // before any interrupt is served, so a wake up interrupt can not be missed.
+        llvm_asm!("in r24, 0x35
+                   ori r24, 0x84
+                   out 0x35, r24
+                   andi r24, 0xFB
+                   out 0x35, r24
+                   sei
+                   sleep"
+                 :
+                 :
+                 : "r24"
+                 : "volatile");
+    }
+}
+
+// Reads a byte with LPM right after the command is written to SPMCSR.
+fn read(command: u8, address: u16) -> u8 {
+    sync::free(|_| {
+        // A self-programming operation must not be in progress.
+        while SPMCSR.is_set(SPMCSR::SPMEN) {}
+        let value: u8;
+        unsafe {
+            llvm_asm!("out 0x37, $1
+                       lpm $0, Z"
+                     : "=r" (value)
+                     : "r" (command), "z" (address)
+                     :
+                     : "volatile");
+        }
+        value
+    })
+}
@@ -0,0 +1,322 @@
+// RustDuino : A generic HAL implementation for Arduino Boards in Rust
+// Copyright (C) 2021  Akshit Verma, Indian Institute of Technology Kanpur
+
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Global interrupts configured in the ATTINY85 chip is controlled here.
+//! Section 4.3 of the manual
+//! Handlers can also be attached to the external interrupt INT0 and to the pin change
+//! interrupt of port B, like `attachInterrupt()` of Arduino.
+//! Section 9 of the manual
+
+use crate::attiny85::registers::{GIFR, GIMSK, MCUCR, PCMSK};
+use crate::error::Error;
+use crate::sync::free;
+use core::ptr::{read_volatile, write_volatile};
+
+/// SREG (Status control Register)
+/// The status register contains information about the result of the most recently executed arithmetic instruction. This
+/// information can be used for altering program flow in order to perform conditional operations. Note that the status register is
+/// updated after all ALU operations, as specified in the instruction set reference. This will in many cases remove the need for
+/// using the dedicated compare instructions, resulting in faster and more compact code.
+/// The status register is not automatically stored when entering an interrupt routine and restored when returning from an
+/// interrupt. This must be handled by software.
+///
+/// Toggling 8th bit to 0 or 1 can enable or disable interrupt respectively.
+#[repr(C, packed)]
+pub struct Interrupt {
+    sreg: u8,
+}
+
+impl Interrupt {
+    /// Creates a new struct of Global_Interrupts.
+    /// # Returns
+    /// * `a reference to Interrupt structure` - to control the global interrupt implementations.
+    pub unsafe fn new() -> &'static mut Interrupt {
+        &mut *(0x5F as *mut Interrupt)
+    }
+
+    /// Disables Interrupts.
+    pub fn disable(&mut self) {
+        unsafe {
+            let mut ctrl_sreg = read_volatile(&self.sreg);
+            ctrl_sreg &= 0x7F;
+            write_volatile(&mut self.sreg, ctrl_sreg);
+        }
+    }
+
+    /// Enables Interrupts
+    pub fn enable(&mut self) {
+        unsafe {
+            let mut ctrl_sreg = read_volatile(&self.sreg);
+            ctrl_sreg |= 0x80;
+            write_volatile(&mut self.sreg, ctrl_sreg);
+        }
+    }
+
+    /// Checks whether global interrupts are enabled.
+    /// # Returns
+    /// * `a boolean` - which is true if the I bit of SREG is set.
+    pub fn is_enabled(&mut self) -> bool {
+        let ctrl_sreg = unsafe { read_volatile(&self.sreg) };
+        ctrl_sreg & (1 << 7) != 0
+    }
+}
+
+/// Executes the given closure with global interrupts disabled, so that data shared
+/// with an interrupt service routine can be accessed safely.
+/// This is `sync::free` without the `CriticalSection` token, which should be used
+/// instead in new code, and kept for the code written before it.
+/// # Arguments
+/// * `f` - a closure, which is to be executed without interruption.
+/// # Returns
+/// * `the value returned by the closure`.
+pub fn without_interrupts<F, R>(f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    free(|_| f())
+}
+
+/// Selection of the condition which triggers an external interrupt.
+/// * `Low` - as long as the pin is low.
+/// * `Change` - on any logical change of the pin.
+/// * `Falling` - on the falling edge.
+/// * `Rising` - on the rising edge.
+#[derive(Clone, Copy)]
+pub enum TriggerMode {
+    Low,
+    Change,
+    Falling,
+    Rising,
+}
+
+// Handlers attached to INT0 and to the pin change interrupt.
+static mut EXTERNAL_HANDLER: Option<fn()> = None;
+static mut PIN_CHANGE_HANDLER: Option<fn()> = None;
+
+/// Gives the external interrupt connected to a digital pin, INT0 is on pin 2.
+/// # Arguments
+/// * `pin` - a u8, the digital pin number.
+/// # Returns
+/// * `a Option<u8>` - the number of the external interrupt, if the pin has one.
+pub fn digital_pin_to_interrupt(pin: u8) -> Option<u8> {
+    match pin {
+        2 => Some(0),
+        _ => None,
+    }
+}
+
+/// Gives the pin change interrupt group and bit of a digital pin, all the pins have one.
+/// # Arguments
+/// * `pin` - a u8, the digital pin number.
+/// # Returns
+/// * `a Option<(u8, u8)>` - the group, always 0, and the bit in its mask register,
+///   if the pin has a pin change interrupt.
+pub fn digital_pin_to_pcint(pin: u8) -> Option<(u8, u8)> {
+    match pin {
+        0..=5 => Some((0, pin)),
+        _ => None,
+    }
+}
+
+/// Attaches a handler to an external interrupt and enables it.
+/// Global interrupts must also be enabled for the handler to be called.
+/// # Arguments
+/// * `int` - a u8, the number of the external interrupt, only 0 on the ATTINY85.
+/// * `mode` - a `TriggerMode` object, the condition which calls the handler.
+/// * `handler` - a function, which is called from the interrupt service routine.
+/// # Returns
+/// * `a Result<(), Error>` - which is `Error::InvalidParam` if the chip has no such interrupt.
+pub fn attach_interrupt(int: u8, mode: TriggerMode, handler: fn()) -> Result<(), Error> {
+    if int > 0 {
+        return Err(Error::InvalidParam);
+    }
+    let sense = match mode {
+        TriggerMode::Low => 0b00,
+        TriggerMode::Change => 0b01,
+        TriggerMode::Falling => 0b10,
+        TriggerMode::Rising => 0b11,
+    };
+
+    without_interrupts(|| unsafe {
+        EXTERNAL_HANDLER = Some(handler);
+        GIMSK.clear(GIMSK::INT0);
+        MCUCR.write_field(MCUCR::ISC0, sense);
+        // Changing the sense control may set the flag, which is cleared by writing one.
+        GIFR.write(GIFR::INTF0.mask() as u8);
+        GIMSK.set(GIMSK::INT0);
+    });
+    Ok(())
+}
+
+/// Disables an external interrupt and removes its handler.
+/// # Arguments
+/// * `int` - a u8, the number of the external interrupt, only 0 on the ATTINY85.
+/// # Returns
+/// * `a Result<(), Error>` - which is `Error::InvalidParam` if the chip has no such interrupt.
+pub fn detach_interrupt(int: u8) -> Result<(), Error> {
+    if int > 0 {
+        return Err(Error::InvalidParam);
+    }
+    without_interrupts(|| unsafe {
+        GIMSK.clear(GIMSK::INT0);
+        EXTERNAL_HANDLER = None;
+    });
+    Ok(())
+}
+
+/// Attaches a handler to a pin change interrupt group and enables it for the pins given.
+/// The handler is called when any of the pins changes, so it has to read the pins
+/// to find out which one has changed.
+/// # Arguments
+/// * `group` - a u8, the pin change interrupt group, only 0 on the ATTINY85.
+/// * `mask` - a u8, the pins of the group which trigger the interrupt.
+/// * `handler` - a function, which is called from the interrupt service routine.
+/// # Returns
+/// * `a Result<(), Error>` - which is `Error::InvalidParam` if the chip has no such group.
+pub fn attach_pin_change(group: u8, mask: u8, handler: fn()) -> Result<(), Error> {
+    if group > 0 {
+        return Err(Error::InvalidParam);
+    }
+    without_interrupts(|| unsafe {
+        PIN_CHANGE_HANDLER = Some(handler);
+        PCMSK.write(mask);
+        GIFR.write(GIFR::PCIF.mask() as u8);
+        GIMSK.set(GIMSK::PCIE);
+    });
+    Ok(())
+}
+
+/// Disables a pin change interrupt group and removes its handler.
+/// # Arguments
+/// * `group` - a u8, the pin change interrupt group, only 0 on the ATTINY85.
+/// # Returns
+/// * `a Result<(), Error>` - which is `Error::InvalidParam` if the chip has no such group.
+pub fn detach_pin_change(group: u8) -> Result<(), Error> {
+    if group > 0 {
+        return Err(Error::InvalidParam);
+    }
+    without_interrupts(|| unsafe {
+        GIMSK.clear(GIMSK::PCIE);
+        PCMSK.write(0);
+        PIN_CHANGE_HANDLER = None;
+    });
+    Ok(())
+}
+
+/// External Interrupt Request 0.
+#[cfg(target_arch = "avr")]
+#[no_mangle]
+pub unsafe extern "avr-interrupt" fn __vector_1() {
+    if let Some(handler) = EXTERNAL_HANDLER {
+        handler();
+    }
+}
+
+/// Pin Change Interrupt Request 0.
+#[cfg(target_arch = "avr")]
+#[no_mangle]
+pub unsafe extern "avr-interrupt" fn __vector_2() {
+    if let Some(handler) = PIN_CHANGE_HANDLER {
+        handler();
+    }
+}
+
+/// Declares the interrupt service routine of an interrupt vector by its name in the
+/// datasheet, as `TIMER1_COMPA` or `PCINT0`, instead of the `__vector_N` symbol.
+/// A name which is not a vector of the ATTINY85 does not compile.
+/// The vectors already defined by the library, as the external and pin change
+/// interrupts or `WDT` for the watchdog, cannot be taken again, which fails when linking.
+/// # Example
+/// ```ignore
+/// avr_interrupt!(TIMER1_COMPA, fn on_compare() {
+///     TICKS.fetch_add(1);
+/// });
+/// ```
+#[macro_export]
+macro_rules! avr_interrupt {
+    ($vector:ident, fn $handler:ident() $body:block) => {
+        fn $handler() $body
+        $crate::__avr_vector!($vector, $handler);
+    };
+}
+
+/// Gives the symbol of a vector of the ATTINY85, see `avr_interrupt!`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __avr_vector {
+    (INT0, $handler:ident) => {
+        $crate::__avr_isr!(__vector_1, $handler);
+    };
+    (PCINT0, $handler:ident) => {
+        $crate::__avr_isr!(__vector_2, $handler);
+    };
+    (TIMER1_COMPA, $handler:ident) => {
+        $crate::__avr_isr!(__vector_3, $handler);
+    };
+    (TIMER1_OVF, $handler:ident) => {
+        $crate::__avr_isr!(__vector_4, $handler);
+    };
+    (TIMER0_OVF, $handler:ident) => {
+        $crate::__avr_isr!(__vector_5, $handler);
+    };
+    (EE_READY, $handler:ident) => {
+        $crate::__avr_isr!(__vector_6, $handler);
+    };
+    (ANALOG_COMP, $handler:ident) => {
+        $crate::__avr_isr!(__vector_7, $handler);
+    };
+    (ADC, $handler:ident) => {
+        $crate::__avr_isr!(__vector_8, $handler);
+    };
+    (TIMER1_COMPB, $handler:ident) => {
+        $crate::__avr_isr!(__vector_9, $handler);
+    };
+    (TIMER0_COMPA, $handler:ident) => {
+        $crate::__avr_isr!(__vector_10, $handler);
+    };
+    (TIMER0_COMPB, $handler:ident) => {
+        $crate::__avr_isr!(__vector_11, $handler);
+    };
+    (WDT, $handler:ident) => {
+        $crate::__avr_isr!(__vector_12, $handler);
+    };
+    (USI_START, $handler:ident) => {
+        $crate::__avr_isr!(__vector_13, $handler);
+    };
+    (USI_OVF, $handler:ident) => {
+        $crate::__avr_isr!(__vector_14, $handler);
+    };
+    ($vector:ident, $handler:ident) => {
+        compile_error!(concat!(
+            "no interrupt vector ",
+            stringify!($vector),
+            " on the ATTINY85"
+        ));
+    };
+}
+
+/// Defines the interrupt service routine calling the handler, see `avr_interrupt!`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __avr_isr {
+    ($symbol:ident, $handler:ident) => {
+        #[cfg(target_arch = "avr")]
+        #[no_mangle]
+        pub unsafe extern "avr-interrupt" fn $symbol() {
+            $handler();
+        }
+    };
+}
@@ -0,0 +1,132 @@
+// RustDuino : A generic HAL implementation for Arduino Boards in Rust
+// Copyright (C) 2021 Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Pins implementation for ATTINY85 where all pins are packed in a single structure.
+//! The digital pins 0 to 5 are the pins PB0 to PB5, and the analog pins A0 to A3 are
+//! PB5, PB2, PB4 and PB3, whose ADC channel is the number of the analog pin.
+//! Section 10.2.1 and 10.2.2 of ATtiny85 datasheet.
+
+use crate::attiny85::hal::port::*;
+
+/// All pins inside a single struct.
+#[repr(C, packed)]
+#[derive(Clone, Copy)]
+pub struct Pins {
+    /// All four analog pins.
+    pub analog: [AnalogPin; 4],
+
+    /// All six digital I/O pins.
+    pub digital: [DigitalPin; 6],
+}
+
+/// This struct contains the Pin struct and its analog pin number.
+#[repr(C, packed)]
+#[derive(Clone, Copy)]
+pub struct AnalogPin {
+    pub pin: Pin,
+    pub pinno: u32,
+}
+
+/// Structure to represent one digital pin with Pin structure and pin number.
+#[repr(C, packed)]
+#[derive(Clone, Copy)]
+pub struct DigitalPin {
+    pub pin: Pin,
+    pub pinno: usize,
+}
+
+impl Pins {
+    /// Returns all pins at once as a single struct.
+    /// No new memory is created, just the already created space is given
+    /// a name so it is a memory mapped I/O.
+    /// # Returns
+    /// * `a Pins object` - used to control all pins of AVR chip at one place.
+    pub fn new() -> Pins {
+        Pins {
+            analog: [
+                AnalogPin {
+                    pin: Pin::new(PortName::B, 5).unwrap(),
+                    pinno: 0,
+                },
+                AnalogPin {
+                    pin: Pin::new(PortName::B, 2).unwrap(),
+                    pinno: 1,
+                },
+                AnalogPin {
+                    pin: Pin::new(PortName::B, 4).unwrap(),
+                    pinno: 2,
+                },
+                AnalogPin {
+                    pin: Pin::new(PortName::B, 3).unwrap(),
+                    pinno: 3,
+                },
+            ],
+            digital: [
+                DigitalPin {
+                    pin: Pin::new(PortName::B, 0).unwrap(),
+                    pinno: 0,
+                },
+                DigitalPin {
+                    pin: Pin::new(PortName::B, 1).unwrap(),
+                    pinno: 1,
+                },
+                DigitalPin {
+                    pin: Pin::new(PortName::B, 2).unwrap(),
+                    pinno: 2,
+                },
+                DigitalPin {
+                    pin: Pin::new(PortName::B, 3).unwrap(),
+                    pinno: 3,
+                },
+                DigitalPin {
+                    pin: Pin::new(PortName::B, 4).unwrap(),
+                    pinno: 4,
+                },
+                DigitalPin {
+                    pin: Pin::new(PortName::B, 5).unwrap(),
+                    pinno: 5,
+                },
+            ],
+        }
+    }
+}
+
+/// This function returns digital pin corresponding to it's number.
+/// # Arguments
+/// * `a u8` - The pin number which is to be used.
+/// # Returns
+/// * `a Pin object` - The memory mapped I/O object to control the Digital Pin.
+pub fn make_pin(pin: u8) -> Pin {
+    match pin {
+        0..=5 => Pin::new(PortName::B, pin).unwrap(),
+        _ => unreachable!(),
+    }
+}
+
+/// Gives the digital pin of an analog pin.
+/// # Arguments
+/// * `a u8` - The analog pin number, from 0 to 3.
+/// # Returns
+/// * `a u8` - The digital pin number, which is also the bit of the pin in port B.
+pub fn analog_to_digital(pin: u8) -> u8 {
+    match pin {
+        0 => 5,
+        1 => 2,
+        2 => 4,
+        3 => 3,
+        _ => unreachable!(),
+    }
+}
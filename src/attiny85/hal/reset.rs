@@ -0,0 +1,140 @@
+// RustDuino : A generic HAL implementation for Arduino Boards in Rust
+// Copyright (C) 2021 Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Cause of the last reset of ATTINY85, from the flags of MCUSR, and a software reset
+//! through the watchdog timer.
+//! The flags are only cleared by a power-on reset or by writing them, so `reset_flags`
+//! reads them once and clears them, and the later calls give the value saved then.
+//! It should be called at the start of the program, as after a watchdog reset the
+//! watchdog stays enabled with its shortest time-out until WDRF is cleared, so it is
+//! disabled there too.
+//! See the section 8 of ATTINY85 datasheet.
+
+// Source codes required.
+use crate::attiny85::hal::watchdog::{WatchDog, WatchdogMode, WatchdogPrescaler};
+use crate::attiny85::registers::MCUSR;
+use crate::sync;
+
+/// Cause of the last reset, the first one of this list whose flag is set.
+/// * `PowerOn` - the supply voltage rose above the power-on reset threshold.
+/// * `BrownOut` - the supply voltage fell below the brown-out detection level.
+/// * `External` - the RESET pin was held low.
+/// * `Watchdog` - the watchdog timer timed out in the system reset mode, as after `reboot`.
+/// * `Unknown` - no flag was set, for example after a bootloader which cleared them.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ResetCause {
+    PowerOn,
+    BrownOut,
+    External,
+    Watchdog,
+    Unknown,
+}
+
+/// Flags of MCUSR at start-up. Several of them can be set, as a flag is kept through
+/// the resets of other causes.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct ResetFlags {
+    bits: u8,
+}
+
+impl ResetFlags {
+    /// Gives the raw value of MCUSR.
+    pub fn bits(&self) -> u8 {
+        self.bits
+    }
+
+    /// Checks the power-on reset flag.
+    pub fn power_on(&self) -> bool {
+        self.bits & MCUSR::PORF.mask() as u8 != 0
+    }
+
+    /// Checks the brown-out reset flag.
+    pub fn brown_out(&self) -> bool {
+        self.bits & MCUSR::BORF.mask() as u8 != 0
+    }
+
+    /// Checks the external reset flag.
+    pub fn external(&self) -> bool {
+        self.bits & MCUSR::EXTRF.mask() as u8 != 0
+    }
+
+    /// Checks the watchdog reset flag.
+    pub fn watchdog(&self) -> bool {
+        self.bits & MCUSR::WDRF.mask() as u8 != 0
+    }
+
+    /// Gives the cause of the reset.
+    /// # Returns
+    /// * `a ResetCause object` - the cause whose flag is set first in the order of `ResetCause`.
+    pub fn cause(&self) -> ResetCause {
+        if self.power_on() {
+            ResetCause::PowerOn
+        } else if self.brown_out() {
+            ResetCause::BrownOut
+        } else if self.external() {
+            ResetCause::External
+        } else if self.watchdog() {
+            ResetCause::Watchdog
+        } else {
+            ResetCause::Unknown
+        }
+    }
+}
+
+// MCUSR as read by the first call of `reset_flags`.
+static mut SAVED_FLAGS: Option<u8> = None;
+
+/// Gives the flags of the last reset. The first call reads and clears MCUSR and disables
+/// the watchdog if it caused the reset, the later calls give the same flags.
+/// # Returns
+/// * `a ResetFlags object` - the flags of MCUSR at start-up.
+pub fn reset_flags() -> ResetFlags {
+    sync::free(|_| unsafe {
+        let bits = match SAVED_FLAGS {
+            Some(bits) => bits,
+            None => {
+                let bits = MCUSR.read();
+                MCUSR.write(0);
+                if bits & MCUSR::WDRF.mask() as u8 != 0 {
+                    WatchDog::new().disable();
+                }
+                SAVED_FLAGS = Some(bits);
+                bits
+            }
+        };
+        ResetFlags { bits }
+    })
+}
+
+/// Gives the cause of the last reset, see `reset_flags`.
+/// # Returns
+/// * `a ResetCause object` - the cause of the reset.
+pub fn reset_cause() -> ResetCause {
+    reset_flags().cause()
+}
+
+/// Resets the chip with the watchdog timer in the system reset mode, which resets all
+/// the registers as the RESET pin does. The reset happens after about 16 milliseconds,
+/// and `reset_cause` gives `Watchdog` after it.
+pub fn reboot() -> ! {
+    // The flags of this start-up are cleared first, or they would still be set after
+    // the watchdog reset.
+    reset_flags();
+    unsafe { WatchDog::new() }.enable(WatchdogMode::Reset, WatchdogPrescaler::Ms16);
+    loop {
+        core::hint::spin_loop();
+    }
+}
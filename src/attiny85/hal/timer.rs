@@ -0,0 +1,396 @@
+// RustDuino : A generic HAL implementation for Arduino Boards in Rust
+// Copyright (C) 2021 Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! The two 8 bit Timer/Counters of ATTINY85, and hardware PWM generated with them.
+//! Timer0 has two compare units, with the outputs OC0A and OC0B on digital pins 0 and 1.
+//! Timer1 has its own prescaler, from 1 to 16384, can be clocked by the 64 MHz PLL and
+//! counts up to the TOP set in OCR1C. Its output OC1B is on digital pin 4, while OC1A
+//! shares pin 1 with OC0B and is left to Timer0 by the PWM functions.
+//! Refer to section 11 and 12 of ATTINY85 datasheet.
+
+// Source codes required.
+use crate::attiny85::hal::pin::make_pin;
+use crate::attiny85::registers::{
+    GTCCR, OCR0A, OCR0B, OCR1A, OCR1B, OCR1C, PLLCSR, PRR, TCCR0A, TCCR0B, TCCR1, TCNT0, TCNT1,
+    TIMSK,
+};
+use crate::delay::delay_us;
+use crate::error::Error;
+
+/// Selection of the clock of Timer0.
+/// * `Stopped` - no clock, the timer does not count.
+/// * `Div1` to `Div1024` - the system clock divided by the prescaler.
+/// * `ExternalFalling`, `ExternalRising` - the edges of the T0 pin, digital pin 2.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Timer0Clock {
+    Stopped,
+    Div1,
+    Div8,
+    Div64,
+    Div256,
+    Div1024,
+    ExternalFalling,
+    ExternalRising,
+}
+
+/// Selection of the waveform generation mode of Timer0.
+/// * `Normal` - counts up to 0xFF and overflows.
+/// * `Ctc` - counts up to OCR0A and is cleared.
+/// * `FastPwm` - single slope PWM with a TOP of 0xFF.
+/// * `PhaseCorrect` - dual slope PWM with a TOP of 0xFF.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Timer0Mode {
+    Normal,
+    Ctc,
+    FastPwm,
+    PhaseCorrect,
+}
+
+/// Timer/Counter0.
+pub struct Timer0 {
+    _private: (),
+}
+
+impl Timer0 {
+    /// Switches on the clock of Timer0, the timer keeps its settings.
+    /// # Returns
+    /// * `a Timer0 object` - used to control the timer.
+    pub fn new() -> Timer0 {
+        PRR.clear(PRR::PRTIM0);
+        Timer0 { _private: () }
+    }
+
+    /// Sets the waveform generation mode.
+    /// # Arguments
+    /// * `mode` - a `Timer0Mode` object, the way the timer counts.
+    pub fn set_mode(&mut self, mode: Timer0Mode) {
+        let wgm = match mode {
+            Timer0Mode::Normal => 0b00,
+            Timer0Mode::PhaseCorrect => 0b01,
+            Timer0Mode::Ctc => 0b10,
+            Timer0Mode::FastPwm => 0b11,
+        };
+        TCCR0A.write_field(TCCR0A::WGM0, wgm);
+        TCCR0B.clear(TCCR0B::WGM02);
+    }
+
+    /// Starts the timer with the given clock, or stops it with `Timer0Clock::Stopped`.
+    /// # Arguments
+    /// * `clock` - a `Timer0Clock` object, the clock of the timer.
+    pub fn start(&mut self, clock: Timer0Clock) {
+        TCCR0B.write_field(TCCR0B::CS0, clock as u8);
+    }
+
+    /// Stops the timer, which keeps its count.
+    pub fn stop(&mut self) {
+        self.start(Timer0Clock::Stopped);
+    }
+
+    /// Gives the count of the timer.
+    pub fn counter(&self) -> u8 {
+        TCNT0.read()
+    }
+
+    /// Sets the count of the timer.
+    pub fn set_counter(&mut self, value: u8) {
+        TCNT0.write(value);
+    }
+
+    /// Sets the value compared with the count by the compare unit A.
+    pub fn set_compare_a(&mut self, value: u8) {
+        OCR0A.write(value);
+    }
+
+    /// Sets the value compared with the count by the compare unit B.
+    pub fn set_compare_b(&mut self, value: u8) {
+        OCR0B.write(value);
+    }
+
+    /// Enables or disables the interrupts of the timer, TIMER0_OVF, TIMER0_COMPA and
+    /// TIMER0_COMPB, whose handlers are declared with `avr_interrupt!`.
+    /// # Arguments
+    /// * `overflow` - a bool, true to enable the overflow interrupt.
+    /// * `compare_a` - a bool, true to enable the interrupt of the compare unit A.
+    /// * `compare_b` - a bool, true to enable the interrupt of the compare unit B.
+    pub fn enable_interrupts(&mut self, overflow: bool, compare_a: bool, compare_b: bool) {
+        TIMSK.modify(|timsk| {
+            let mask = (TIMSK::TOIE0.mask() | TIMSK::OCIE0A.mask() | TIMSK::OCIE0B.mask()) as u8;
+            let mut bits = 0;
+            if overflow {
+                bits |= TIMSK::TOIE0.mask() as u8;
+            }
+            if compare_a {
+                bits |= TIMSK::OCIE0A.mask() as u8;
+            }
+            if compare_b {
+                bits |= TIMSK::OCIE0B.mask() as u8;
+            }
+            timsk & !mask | bits
+        });
+    }
+}
+
+impl Default for Timer0 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Selection of the division factor of the clock of Timer1, a power of two from 1 to 16384.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Timer1Prescaler {
+    Stopped,
+    Div1,
+    Div2,
+    Div4,
+    Div8,
+    Div16,
+    Div32,
+    Div64,
+    Div128,
+    Div256,
+    Div512,
+    Div1024,
+    Div2048,
+    Div4096,
+    Div8192,
+    Div16384,
+}
+
+/// Timer/Counter1.
+pub struct Timer1 {
+    _private: (),
+}
+
+impl Timer1 {
+    /// Switches on the clock of Timer1, the timer keeps its settings.
+    /// # Returns
+    /// * `a Timer1 object` - used to control the timer.
+    pub fn new() -> Timer1 {
+        PRR.clear(PRR::PRTIM1);
+        Timer1 { _private: () }
+    }
+
+    /// Clocks the timer from the 64 MHz PLL instead of the system clock, or back.
+    /// The PLL needs 100 microseconds to start and lock.
+    /// # Arguments
+    /// * `enable` - a bool, true to use the PLL.
+    pub fn use_pll(&mut self, enable: bool) {
+        if enable {
+            PLLCSR.set(PLLCSR::PLLE);
+            delay_us(100);
+            while !PLLCSR.is_set(PLLCSR::PLOCK) {}
+            PLLCSR.set(PLLCSR::PCKE);
+        } else {
+            PLLCSR.clear(PLLCSR::PCKE);
+        }
+    }
+
+    /// Makes the timer count up to `top` and start again from zero, or up to 0xFF.
+    /// In the PWM modes the TOP is always OCR1C.
+    /// # Arguments
+    /// * `top` - a `Option<u8>`, the largest count, None to count up to 0xFF.
+    pub fn set_top(&mut self, top: Option<u8>) {
+        match top {
+            Some(top) => {
+                OCR1C.write(top);
+                TCCR1.set(TCCR1::CTC1);
+            }
+            None => {
+                OCR1C.write(0xFF);
+                TCCR1.clear(TCCR1::CTC1);
+            }
+        }
+    }
+
+    /// Starts the timer with the given prescaler, or stops it with `Timer1Prescaler::Stopped`.
+    /// # Arguments
+    /// * `prescaler` - a `Timer1Prescaler` object, the division factor of the clock.
+    pub fn start(&mut self, prescaler: Timer1Prescaler) {
+        TCCR1.write_field(TCCR1::CS1, prescaler as u8);
+    }
+
+    /// Stops the timer, which keeps its count.
+    pub fn stop(&mut self) {
+        self.start(Timer1Prescaler::Stopped);
+    }
+
+    /// Gives the count of the timer.
+    pub fn counter(&self) -> u8 {
+        TCNT1.read()
+    }
+
+    /// Sets the count of the timer.
+    pub fn set_counter(&mut self, value: u8) {
+        TCNT1.write(value);
+    }
+
+    /// Sets the value compared with the count by the compare unit A.
+    pub fn set_compare_a(&mut self, value: u8) {
+        OCR1A.write(value);
+    }
+
+    /// Sets the value compared with the count by the compare unit B.
+    pub fn set_compare_b(&mut self, value: u8) {
+        OCR1B.write(value);
+    }
+
+    /// Enables or disables the interrupts of the timer, TIMER1_OVF, TIMER1_COMPA and
+    /// TIMER1_COMPB, whose handlers are declared with `avr_interrupt!`.
+    /// # Arguments
+    /// * `overflow` - a bool, true to enable the overflow interrupt.
+    /// * `compare_a` - a bool, true to enable the interrupt of the compare unit A.
+    /// * `compare_b` - a bool, true to enable the interrupt of the compare unit B.
+    pub fn enable_interrupts(&mut self, overflow: bool, compare_a: bool, compare_b: bool) {
+        TIMSK.modify(|timsk| {
+            let mask = (TIMSK::TOIE1.mask() | TIMSK::OCIE1A.mask() | TIMSK::OCIE1B.mask()) as u8;
+            let mut bits = 0;
+            if overflow {
+                bits |= TIMSK::TOIE1.mask() as u8;
+            }
+            if compare_a {
+                bits |= TIMSK::OCIE1A.mask() as u8;
+            }
+            if compare_b {
+                bits |= TIMSK::OCIE1B.mask() as u8;
+            }
+            timsk & !mask | bits
+        });
+    }
+}
+
+impl Default for Timer1 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Output compare unit of a timer which drives the pin.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Channel {
+    A,
+    B,
+}
+
+/// Structure to control one PWM capable pin, digital pin 0 or 1 with Timer0 in
+/// Fast PWM mode, or digital pin 4 with Timer1 and a TOP of 0xFF.
+/// The channels of a timer share its frequency, about 980 Hz at 16 MHz as with Arduino.
+#[derive(Clone, Copy)]
+pub struct PwmChannel {
+    timer1: bool,
+    channel: Channel,
+    pin: u8,
+}
+
+impl PwmChannel {
+    /// Creates the PWM channel of a digital pin.
+    /// The timer is not touched until `enable` is called.
+    /// # Arguments
+    /// * `pin` - a u8, the digital pin number (0, 1 or 4).
+    /// # Returns
+    /// * `a Option<PwmChannel>` - None if the pin cannot generate PWM.
+    pub fn new(pin: u8) -> Option<PwmChannel> {
+        let (timer1, channel) = match pin {
+            0 => (false, Channel::A),
+            1 => (false, Channel::B),
+            4 => (true, Channel::B),
+            _ => return None,
+        };
+        Some(PwmChannel {
+            timer1,
+            channel,
+            pin,
+        })
+    }
+
+    /// Connects the compare unit to the pin and starts the timer with a prescaler of 64
+    /// if it is stopped.
+    pub fn enable(&mut self) {
+        make_pin(self.pin).set_output();
+        if self.timer1 {
+            let mut timer = Timer1::new();
+            OCR1C.write(0xFF);
+            GTCCR.set(GTCCR::PWM1B);
+            GTCCR.write_field(GTCCR::COM1B, 0b10);
+            if TCCR1.read_field(TCCR1::CS1) == 0 {
+                timer.start(Timer1Prescaler::Div64);
+            }
+        } else {
+            let mut timer = Timer0::new();
+            timer.set_mode(Timer0Mode::FastPwm);
+            match self.channel {
+                Channel::A => TCCR0A.write_field(TCCR0A::COM0A, 0b10),
+                Channel::B => TCCR0A.write_field(TCCR0A::COM0B, 0b10),
+            }
+            if TCCR0B.read_field(TCCR0B::CS0) == 0 {
+                timer.start(Timer0Clock::Div64);
+            }
+        }
+    }
+
+    /// Disconnects the compare unit, the pin is a normal output again.
+    pub fn disable(&mut self) {
+        if self.timer1 {
+            GTCCR.write_field(GTCCR::COM1B, 0b00);
+            GTCCR.clear(GTCCR::PWM1B);
+        } else {
+            match self.channel {
+                Channel::A => TCCR0A.write_field(TCCR0A::COM0A, 0b00),
+                Channel::B => TCCR0A.write_field(TCCR0A::COM0B, 0b00),
+            }
+        }
+    }
+
+    /// Sets the duty cycle.
+    /// # Arguments
+    /// * `duty` - a u8, 0 is always low and 255 always high.
+    pub fn set_duty(&mut self, duty: u8) {
+        match (self.timer1, self.channel) {
+            (false, Channel::A) => OCR0A.write(duty),
+            (false, Channel::B) => OCR0B.write(duty),
+            (true, _) => OCR1B.write(duty),
+        }
+    }
+}
+
+/// Writes a PWM wave to a pin in the same way as `analogWrite` of Arduino.
+/// The pin is simply set low for 0 and high for 255, as the PWM would still
+/// give a narrow spike at these values.
+/// # Arguments
+/// * `pin` - a u8, the digital pin number (0, 1 or 4).
+/// * `duty` - a u8, the duty cycle from 0 to 255.
+/// # Returns
+/// * `a Result<(), Error>` - which is `Error::InvalidParam` if the pin has no PWM.
+pub fn analog_write(pin: u8, duty: u8) -> Result<(), Error> {
+    let mut channel = PwmChannel::new(pin).ok_or(Error::InvalidParam)?;
+    match duty {
+        0 | 255 => {
+            channel.disable();
+            let mut output = make_pin(pin);
+            output.set_output();
+            if duty == 0 {
+                output.low();
+            } else {
+                output.high();
+            }
+        }
+        _ => {
+            channel.set_duty(duty);
+            channel.enable();
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,123 @@
+// RustDuino : A generic HAL implementation for Arduino Boards in Rust
+// Copyright (C) 2021 Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Register map of the ATTINY85, from the Register Summary of the manual.
+//! Each register is a `Register` constant at its data space address, and its fields are
+//! in the module of the same name, as `WDTCR` and `WDTCR::WDE`.
+
+// Crates which would be used in the implementation.
+use crate::register::register_map;
+
+register_map! {
+    /// ADC Control and Status Register B.
+    ADCSRB: u8 @ 0x23 { BIN: 7, ACME: 6, IPR: 5, ADTS: 0..3 }
+    /// ADC Data Register.
+    ADC: u16 @ 0x24 {}
+    /// ADC Control and Status Register A.
+    ADCSRA: u8 @ 0x26 { ADEN: 7, ADSC: 6, ADATE: 5, ADIF: 4, ADIE: 3, ADPS: 0..3 }
+    /// ADC Multiplexer Selection Register, REFS2 is the third bit of the reference.
+    ADMUX: u8 @ 0x27 { REFS: 6..8, ADLAR: 5, REFS2: 4, MUX: 0..4 }
+    /// Analog Comparator Control and Status Register.
+    ACSR: u8 @ 0x28 { ACD: 7, ACBG: 6, ACO: 5, ACI: 4, ACIE: 3, ACIS: 0..2 }
+    /// USI Control Register.
+    USICR: u8 @ 0x2D { USISIE: 7, USIOIE: 6, USIWM: 4..6, USICS: 2..4, USICLK: 1, USITC: 0 }
+    /// USI Status Register.
+    USISR: u8 @ 0x2E { USISIF: 7, USIOIF: 6, USIPF: 5, USIDC: 4, USICNT: 0..4 }
+    /// USI Data Register.
+    USIDR: u8 @ 0x2F {}
+    /// USI Buffer Register.
+    USIBR: u8 @ 0x30 {}
+    /// General Purpose I/O Register 0.
+    GPIOR0: u8 @ 0x31 {}
+    /// General Purpose I/O Register 1.
+    GPIOR1: u8 @ 0x32 {}
+    /// General Purpose I/O Register 2.
+    GPIOR2: u8 @ 0x33 {}
+    /// Digital Input Disable Register 0.
+    DIDR0: u8 @ 0x34 { ADC0D: 5, ADC2D: 4, ADC3D: 3, ADC1D: 2, AIN1D: 1, AIN0D: 0 }
+    /// Pin Change Mask Register.
+    PCMSK: u8 @ 0x35 {}
+    /// Port B Input Pins Address.
+    PINB: u8 @ 0x36 {}
+    /// Port B Data Direction Register.
+    DDRB: u8 @ 0x37 {}
+    /// Port B Data Register.
+    PORTB: u8 @ 0x38 {}
+    /// EEPROM Control Register.
+    EECR: u8 @ 0x3C { EEPM: 4..6, EERIE: 3, EEMPE: 2, EEPE: 1, EERE: 0 }
+    /// EEPROM Data Register.
+    EEDR: u8 @ 0x3D {}
+    /// EEPROM Address Register.
+    EEAR: u16 @ 0x3E {}
+    /// Power Reduction Register.
+    PRR: u8 @ 0x40 { PRTIM1: 3, PRTIM0: 2, PRUSI: 1, PRADC: 0 }
+    /// Watchdog Timer Control Register.
+    WDTCR: u8 @ 0x41 { WDIF: 7, WDIE: 6, WDP3: 5, WDCE: 4, WDE: 3, WDP: 0..3 }
+    /// debugWIRE Data Register.
+    DWDR: u8 @ 0x42 {}
+    /// Timer/Counter1 Dead Time Prescaler Register.
+    DTPS1: u8 @ 0x43 { DTPS1: 0..2 }
+    /// Timer/Counter1 Dead Time B.
+    DT1B: u8 @ 0x44 {}
+    /// Timer/Counter1 Dead Time A.
+    DT1A: u8 @ 0x45 {}
+    /// Clock Prescale Register.
+    CLKPR: u8 @ 0x46 { CLKPCE: 7, CLKPS: 0..4 }
+    /// PLL Control and Status Register.
+    PLLCSR: u8 @ 0x47 { LSM: 7, PCKE: 2, PLLE: 1, PLOCK: 0 }
+    /// Output Compare Register 0 B.
+    OCR0B: u8 @ 0x48 {}
+    /// Output Compare Register 0 A.
+    OCR0A: u8 @ 0x49 {}
+    /// Timer/Counter0 Control Register A.
+    TCCR0A: u8 @ 0x4A { COM0A: 6..8, COM0B: 4..6, WGM0: 0..2 }
+    /// Output Compare Register 1 B.
+    OCR1B: u8 @ 0x4B {}
+    /// General Timer/Counter Control Register.
+    GTCCR: u8 @ 0x4C { TSM: 7, PWM1B: 6, COM1B: 4..6, FOC1B: 3, FOC1A: 2, PSR1: 1, PSR0: 0 }
+    /// Output Compare Register 1 C, the TOP of Timer/Counter1.
+    OCR1C: u8 @ 0x4D {}
+    /// Output Compare Register 1 A.
+    OCR1A: u8 @ 0x4E {}
+    /// Timer/Counter1.
+    TCNT1: u8 @ 0x4F {}
+    /// Timer/Counter1 Control Register.
+    TCCR1: u8 @ 0x50 { CTC1: 7, PWM1A: 6, COM1A: 4..6, CS1: 0..4 }
+    /// Timer/Counter0.
+    TCNT0: u8 @ 0x52 {}
+    /// Timer/Counter0 Control Register B.
+    TCCR0B: u8 @ 0x53 { FOC0A: 7, FOC0B: 6, WGM02: 3, CS0: 0..3 }
+    /// MCU Status Register, the source of the last reset.
+    MCUSR: u8 @ 0x54 { WDRF: 3, BORF: 2, EXTRF: 1, PORF: 0 }
+    /// MCU Control Register, with the sleep mode and the sense of INT0.
+    MCUCR: u8 @ 0x55 { BODS: 7, PUD: 6, SE: 5, SM: 3..5, BODSE: 2, ISC0: 0..2 }
+    /// Store Program Memory Control and Status Register.
+    SPMCSR: u8 @ 0x57 { RSIG: 5, CTPB: 4, RFLB: 3, PGWRT: 2, PGERS: 1, SPMEN: 0 }
+    /// Timer/Counter Interrupt Flag Register.
+    TIFR: u8 @ 0x58 { OCF1A: 6, OCF1B: 5, OCF0A: 4, OCF0B: 3, TOV1: 2, TOV0: 1 }
+    /// Timer/Counter Interrupt Mask Register.
+    TIMSK: u8 @ 0x59 { OCIE1A: 6, OCIE1B: 5, OCIE0A: 4, OCIE0B: 3, TOIE1: 2, TOIE0: 1 }
+    /// General Interrupt Flag Register.
+    GIFR: u8 @ 0x5A { INTF0: 6, PCIF: 5 }
+    /// General Interrupt Mask Register.
+    GIMSK: u8 @ 0x5B { INT0: 6, PCIE: 5 }
+    /// Stack Pointer.
+    SP: u16 @ 0x5D {}
+    /// Status Register.
+    SREG: u8 @ 0x5F { I: 7, T: 6, H: 5, S: 4, V: 3, N: 2, Z: 1, C: 0 }
+    /// Oscillator Calibration Register.
+    OSCCAL: u8 @ 0x66 {}
+}
@@ -0,0 +1,311 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Pins and peripherals of the Arduino Mega 2560.
+//! The pins are named as printed on the board, D0 to D53 and A0 to A15,
+//! and each is a type-state pin of `hal::gpio` on the port and bit it is wired to,
+//! so that `pins.d13` is PB7 and `pins.a8` is PK0.
+
+// Source codes required.
+use crate::atmega2560p::hal::eeprom::Eeprom;
+use crate::atmega2560p::hal::gpio::*;
+use crate::atmega2560p::hal::interrupts::without_interrupts;
+use crate::atmega2560p::hal::power::Power;
+use crate::atmega2560p::hal::sleep_mode::Sleep;
+use crate::atmega2560p::hal::watchdog::WatchDog;
+
+#[cfg(feature = "com")]
+use crate::atmega2560p::com::{i2c::Twi, serial::Serial, spi::Spi};
+
+// Aliases of the pins by the names printed on the board.
+pub type D0<MODE> = PE<MODE, 0>;
+pub type D1<MODE> = PE<MODE, 1>;
+pub type D2<MODE> = PE<MODE, 4>;
+pub type D3<MODE> = PE<MODE, 5>;
+pub type D4<MODE> = PG<MODE, 5>;
+pub type D5<MODE> = PE<MODE, 3>;
+pub type D6<MODE> = PH<MODE, 3>;
+pub type D7<MODE> = PH<MODE, 4>;
+pub type D8<MODE> = PH<MODE, 5>;
+pub type D9<MODE> = PH<MODE, 6>;
+pub type D10<MODE> = PB<MODE, 4>;
+pub type D11<MODE> = PB<MODE, 5>;
+pub type D12<MODE> = PB<MODE, 6>;
+pub type D13<MODE> = PB<MODE, 7>;
+pub type D14<MODE> = PJ<MODE, 0>;
+pub type D15<MODE> = PJ<MODE, 1>;
+pub type D16<MODE> = PH<MODE, 1>;
+pub type D17<MODE> = PH<MODE, 0>;
+pub type D18<MODE> = PD<MODE, 3>;
+pub type D19<MODE> = PD<MODE, 2>;
+pub type D20<MODE> = PD<MODE, 1>;
+pub type D21<MODE> = PD<MODE, 0>;
+pub type D22<MODE> = PA<MODE, 0>;
+pub type D23<MODE> = PA<MODE, 1>;
+pub type D24<MODE> = PA<MODE, 2>;
+pub type D25<MODE> = PA<MODE, 3>;
+pub type D26<MODE> = PA<MODE, 4>;
+pub type D27<MODE> = PA<MODE, 5>;
+pub type D28<MODE> = PA<MODE, 6>;
+pub type D29<MODE> = PA<MODE, 7>;
+pub type D30<MODE> = PC<MODE, 7>;
+pub type D31<MODE> = PC<MODE, 6>;
+pub type D32<MODE> = PC<MODE, 5>;
+pub type D33<MODE> = PC<MODE, 4>;
+pub type D34<MODE> = PC<MODE, 3>;
+pub type D35<MODE> = PC<MODE, 2>;
+pub type D36<MODE> = PC<MODE, 1>;
+pub type D37<MODE> = PC<MODE, 0>;
+pub type D38<MODE> = PD<MODE, 7>;
+pub type D39<MODE> = PG<MODE, 2>;
+pub type D40<MODE> = PG<MODE, 1>;
+pub type D41<MODE> = PG<MODE, 0>;
+pub type D42<MODE> = PL<MODE, 7>;
+pub type D43<MODE> = PL<MODE, 6>;
+pub type D44<MODE> = PL<MODE, 5>;
+pub type D45<MODE> = PL<MODE, 4>;
+pub type D46<MODE> = PL<MODE, 3>;
+pub type D47<MODE> = PL<MODE, 2>;
+pub type D48<MODE> = PL<MODE, 1>;
+pub type D49<MODE> = PL<MODE, 0>;
+pub type D50<MODE> = PB<MODE, 3>;
+pub type D51<MODE> = PB<MODE, 2>;
+pub type D52<MODE> = PB<MODE, 1>;
+pub type D53<MODE> = PB<MODE, 0>;
+pub type A0<MODE> = PF<MODE, 0>;
+pub type A1<MODE> = PF<MODE, 1>;
+pub type A2<MODE> = PF<MODE, 2>;
+pub type A3<MODE> = PF<MODE, 3>;
+pub type A4<MODE> = PF<MODE, 4>;
+pub type A5<MODE> = PF<MODE, 5>;
+pub type A6<MODE> = PF<MODE, 6>;
+pub type A7<MODE> = PF<MODE, 7>;
+pub type A8<MODE> = PK<MODE, 0>;
+pub type A9<MODE> = PK<MODE, 1>;
+pub type A10<MODE> = PK<MODE, 2>;
+pub type A11<MODE> = PK<MODE, 3>;
+pub type A12<MODE> = PK<MODE, 4>;
+pub type A13<MODE> = PK<MODE, 5>;
+pub type A14<MODE> = PK<MODE, 6>;
+pub type A15<MODE> = PK<MODE, 7>;
+
+/// All the pins of the Arduino Mega 2560 in their reset state, as floating inputs.
+pub struct Pins {
+    pub d0: D0<Input<Floating>>,
+    pub d1: D1<Input<Floating>>,
+    pub d2: D2<Input<Floating>>,
+    pub d3: D3<Input<Floating>>,
+    pub d4: D4<Input<Floating>>,
+    pub d5: D5<Input<Floating>>,
+    pub d6: D6<Input<Floating>>,
+    pub d7: D7<Input<Floating>>,
+    pub d8: D8<Input<Floating>>,
+    pub d9: D9<Input<Floating>>,
+    pub d10: D10<Input<Floating>>,
+    pub d11: D11<Input<Floating>>,
+    pub d12: D12<Input<Floating>>,
+    pub d13: D13<Input<Floating>>,
+    pub d14: D14<Input<Floating>>,
+    pub d15: D15<Input<Floating>>,
+    pub d16: D16<Input<Floating>>,
+    pub d17: D17<Input<Floating>>,
+    pub d18: D18<Input<Floating>>,
+    pub d19: D19<Input<Floating>>,
+    pub d20: D20<Input<Floating>>,
+    pub d21: D21<Input<Floating>>,
+    pub d22: D22<Input<Floating>>,
+    pub d23: D23<Input<Floating>>,
+    pub d24: D24<Input<Floating>>,
+    pub d25: D25<Input<Floating>>,
+    pub d26: D26<Input<Floating>>,
+    pub d27: D27<Input<Floating>>,
+    pub d28: D28<Input<Floating>>,
+    pub d29: D29<Input<Floating>>,
+    pub d30: D30<Input<Floating>>,
+    pub d31: D31<Input<Floating>>,
+    pub d32: D32<Input<Floating>>,
+    pub d33: D33<Input<Floating>>,
+    pub d34: D34<Input<Floating>>,
+    pub d35: D35<Input<Floating>>,
+    pub d36: D36<Input<Floating>>,
+    pub d37: D37<Input<Floating>>,
+    pub d38: D38<Input<Floating>>,
+    pub d39: D39<Input<Floating>>,
+    pub d40: D40<Input<Floating>>,
+    pub d41: D41<Input<Floating>>,
+    pub d42: D42<Input<Floating>>,
+    pub d43: D43<Input<Floating>>,
+    pub d44: D44<Input<Floating>>,
+    pub d45: D45<Input<Floating>>,
+    pub d46: D46<Input<Floating>>,
+    pub d47: D47<Input<Floating>>,
+    pub d48: D48<Input<Floating>>,
+    pub d49: D49<Input<Floating>>,
+    pub d50: D50<Input<Floating>>,
+    pub d51: D51<Input<Floating>>,
+    pub d52: D52<Input<Floating>>,
+    pub d53: D53<Input<Floating>>,
+    pub a0: A0<Input<Floating>>,
+    pub a1: A1<Input<Floating>>,
+    pub a2: A2<Input<Floating>>,
+    pub a3: A3<Input<Floating>>,
+    pub a4: A4<Input<Floating>>,
+    pub a5: A5<Input<Floating>>,
+    pub a6: A6<Input<Floating>>,
+    pub a7: A7<Input<Floating>>,
+    pub a8: A8<Input<Floating>>,
+    pub a9: A9<Input<Floating>>,
+    pub a10: A10<Input<Floating>>,
+    pub a11: A11<Input<Floating>>,
+    pub a12: A12<Input<Floating>>,
+    pub a13: A13<Input<Floating>>,
+    pub a14: A14<Input<Floating>>,
+    pub a15: A15<Input<Floating>>,
+}
+
+impl Pins {
+    /// Creates all the pins.
+    /// # Safety
+    /// Only one `Pins` object must exist, it is given safely by `Peripherals::take()`.
+    pub unsafe fn steal() -> Pins {
+        Pins {
+            d0: Pin::steal(),
+            d1: Pin::steal(),
+            d2: Pin::steal(),
+            d3: Pin::steal(),
+            d4: Pin::steal(),
+            d5: Pin::steal(),
+            d6: Pin::steal(),
+            d7: Pin::steal(),
+            d8: Pin::steal(),
+            d9: Pin::steal(),
+            d10: Pin::steal(),
+            d11: Pin::steal(),
+            d12: Pin::steal(),
+            d13: Pin::steal(),
+            d14: Pin::steal(),
+            d15: Pin::steal(),
+            d16: Pin::steal(),
+            d17: Pin::steal(),
+            d18: Pin::steal(),
+            d19: Pin::steal(),
+            d20: Pin::steal(),
+            d21: Pin::steal(),
+            d22: Pin::steal(),
+            d23: Pin::steal(),
+            d24: Pin::steal(),
+            d25: Pin::steal(),
+            d26: Pin::steal(),
+            d27: Pin::steal(),
+            d28: Pin::steal(),
+            d29: Pin::steal(),
+            d30: Pin::steal(),
+            d31: Pin::steal(),
+            d32: Pin::steal(),
+            d33: Pin::steal(),
+            d34: Pin::steal(),
+            d35: Pin::steal(),
+            d36: Pin::steal(),
+            d37: Pin::steal(),
+            d38: Pin::steal(),
+            d39: Pin::steal(),
+            d40: Pin::steal(),
+            d41: Pin::steal(),
+            d42: Pin::steal(),
+            d43: Pin::steal(),
+            d44: Pin::steal(),
+            d45: Pin::steal(),
+            d46: Pin::steal(),
+            d47: Pin::steal(),
+            d48: Pin::steal(),
+            d49: Pin::steal(),
+            d50: Pin::steal(),
+            d51: Pin::steal(),
+            d52: Pin::steal(),
+            d53: Pin::steal(),
+            a0: Pin::steal(),
+            a1: Pin::steal(),
+            a2: Pin::steal(),
+            a3: Pin::steal(),
+            a4: Pin::steal(),
+            a5: Pin::steal(),
+            a6: Pin::steal(),
+            a7: Pin::steal(),
+            a8: Pin::steal(),
+            a9: Pin::steal(),
+            a10: Pin::steal(),
+            a11: Pin::steal(),
+            a12: Pin::steal(),
+            a13: Pin::steal(),
+            a14: Pin::steal(),
+            a15: Pin::steal(),
+        }
+    }
+}
+
+/// All the peripherals of the Arduino Mega 2560, which can be taken only once so that
+/// no two parts of the program control the same hardware.
+pub struct Peripherals {
+    pub pins: Pins,
+    pub eeprom: &'static mut Eeprom,
+    pub power: &'static mut Power,
+    pub sleep: &'static mut Sleep,
+    pub watchdog: &'static mut WatchDog,
+    #[cfg(feature = "com")]
+    pub serial: Serial,
+    #[cfg(feature = "com")]
+    pub spi: &'static mut Spi,
+    #[cfg(feature = "com")]
+    pub twi: &'static mut Twi,
+}
+
+// Set once the peripherals have been taken.
+static mut TAKEN: bool = false;
+
+impl Peripherals {
+    /// Gives all the peripherals the first time it is called.
+    /// # Returns
+    /// * `a Option<Peripherals>` - which is None if the peripherals were already taken.
+    pub fn take() -> Option<Peripherals> {
+        without_interrupts(|| unsafe {
+            if TAKEN {
+                None
+            } else {
+                Some(Peripherals::steal())
+            }
+        })
+    }
+
+    /// Gives all the peripherals even if they were already taken.
+    /// # Safety
+    /// The peripherals given earlier must not be used anymore.
+    pub unsafe fn steal() -> Peripherals {
+        TAKEN = true;
+        Peripherals {
+            pins: Pins::steal(),
+            eeprom: Eeprom::new(),
+            power: Power::new(),
+            sleep: Sleep::new(),
+            watchdog: WatchDog::new(),
+            #[cfg(feature = "com")]
+            serial: Serial::new(),
+            #[cfg(feature = "com")]
+            spi: Spi::new(),
+            #[cfg(feature = "com")]
+            twi: Twi::new(),
+        }
+    }
+}
@@ -0,0 +1,26 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Arduino boards, where the pins are named as printed on the board and all the
+//! peripherals of the chip are given once by `Peripherals::take()`.
+
+/// Arduino Mega 2560 with the ATMEGA2560P chip.
+#[cfg(feature = "atmega2560p")]
+pub mod mega2560;
+
+/// Arduino Uno with the ATMEGA328P chip.
+#[cfg(feature = "atmega328p")]
+pub mod uno;
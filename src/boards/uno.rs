@@ -0,0 +1,161 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Pins and peripherals of the Arduino Uno.
+//! The pins are named as printed on the board, D0 to D13 and A0 to A5,
+//! and each is a type-state pin of `hal::gpio` on the port and bit it is wired to,
+//! so that `pins.d13` is PB5 and `pins.a0` is PC0.
+
+// Source codes required.
+use crate::atmega328p::hal::eeprom::Eeprom;
+use crate::atmega328p::hal::gpio::*;
+use crate::atmega328p::hal::interrupts::without_interrupts;
+use crate::atmega328p::hal::power::Power;
+use crate::atmega328p::hal::sleep_mode::Sleep;
+use crate::atmega328p::hal::watchdog::WatchDog;
+
+#[cfg(feature = "com")]
+use crate::atmega328p::com::{i2c::Twi, serial::Serial, spi::Spi};
+
+// Aliases of the pins by the names printed on the board.
+pub type D0<MODE> = PD<MODE, 0>;
+pub type D1<MODE> = PD<MODE, 1>;
+pub type D2<MODE> = PD<MODE, 2>;
+pub type D3<MODE> = PD<MODE, 3>;
+pub type D4<MODE> = PD<MODE, 4>;
+pub type D5<MODE> = PD<MODE, 5>;
+pub type D6<MODE> = PD<MODE, 6>;
+pub type D7<MODE> = PD<MODE, 7>;
+pub type D8<MODE> = PB<MODE, 0>;
+pub type D9<MODE> = PB<MODE, 1>;
+pub type D10<MODE> = PB<MODE, 2>;
+pub type D11<MODE> = PB<MODE, 3>;
+pub type D12<MODE> = PB<MODE, 4>;
+pub type D13<MODE> = PB<MODE, 5>;
+pub type A0<MODE> = PC<MODE, 0>;
+pub type A1<MODE> = PC<MODE, 1>;
+pub type A2<MODE> = PC<MODE, 2>;
+pub type A3<MODE> = PC<MODE, 3>;
+pub type A4<MODE> = PC<MODE, 4>;
+pub type A5<MODE> = PC<MODE, 5>;
+
+/// All the pins of the Arduino Uno in their reset state, as floating inputs.
+pub struct Pins {
+    pub d0: D0<Input<Floating>>,
+    pub d1: D1<Input<Floating>>,
+    pub d2: D2<Input<Floating>>,
+    pub d3: D3<Input<Floating>>,
+    pub d4: D4<Input<Floating>>,
+    pub d5: D5<Input<Floating>>,
+    pub d6: D6<Input<Floating>>,
+    pub d7: D7<Input<Floating>>,
+    pub d8: D8<Input<Floating>>,
+    pub d9: D9<Input<Floating>>,
+    pub d10: D10<Input<Floating>>,
+    pub d11: D11<Input<Floating>>,
+    pub d12: D12<Input<Floating>>,
+    pub d13: D13<Input<Floating>>,
+    pub a0: A0<Input<Floating>>,
+    pub a1: A1<Input<Floating>>,
+    pub a2: A2<Input<Floating>>,
+    pub a3: A3<Input<Floating>>,
+    pub a4: A4<Input<Floating>>,
+    pub a5: A5<Input<Floating>>,
+}
+
+impl Pins {
+    /// Creates all the pins.
+    /// # Safety
+    /// Only one `Pins` object must exist, it is given safely by `Peripherals::take()`.
+    pub unsafe fn steal() -> Pins {
+        Pins {
+            d0: Pin::steal(),
+            d1: Pin::steal(),
+            d2: Pin::steal(),
+            d3: Pin::steal(),
+            d4: Pin::steal(),
+            d5: Pin::steal(),
+            d6: Pin::steal(),
+            d7: Pin::steal(),
+            d8: Pin::steal(),
+            d9: Pin::steal(),
+            d10: Pin::steal(),
+            d11: Pin::steal(),
+            d12: Pin::steal(),
+            d13: Pin::steal(),
+            a0: Pin::steal(),
+            a1: Pin::steal(),
+            a2: Pin::steal(),
+            a3: Pin::steal(),
+            a4: Pin::steal(),
+            a5: Pin::steal(),
+        }
+    }
+}
+
+/// All the peripherals of the Arduino Uno, which can be taken only once so that
+/// no two parts of the program control the same hardware.
+pub struct Peripherals {
+    pub pins: Pins,
+    pub eeprom: &'static mut Eeprom,
+    pub power: &'static mut Power,
+    pub sleep: &'static mut Sleep,
+    pub watchdog: &'static mut WatchDog,
+    #[cfg(feature = "com")]
+    pub serial: Serial,
+    #[cfg(feature = "com")]
+    pub spi: &'static mut Spi,
+    #[cfg(feature = "com")]
+    pub twi: &'static mut Twi,
+}
+
+// Set once the peripherals have been taken.
+static mut TAKEN: bool = false;
+
+impl Peripherals {
+    /// Gives all the peripherals the first time it is called.
+    /// # Returns
+    /// * `a Option<Peripherals>` - which is None if the peripherals were already taken.
+    pub fn take() -> Option<Peripherals> {
+        without_interrupts(|| unsafe {
+            if TAKEN {
+                None
+            } else {
+                Some(Peripherals::steal())
+            }
+        })
+    }
+
+    /// Gives all the peripherals even if they were already taken.
+    /// # Safety
+    /// The peripherals given earlier must not be used anymore.
+    pub unsafe fn steal() -> Peripherals {
+        TAKEN = true;
+        Peripherals {
+            pins: Pins::steal(),
+            eeprom: Eeprom::new(),
+            power: Power::new(),
+            sleep: Sleep::new(),
+            watchdog: WatchDog::new(),
+            #[cfg(feature = "com")]
+            serial: Serial::new(),
+            #[cfg(feature = "com")]
+            spi: Spi::new(),
+            #[cfg(feature = "com")]
+            twi: Twi::new(),
+        }
+    }
+}
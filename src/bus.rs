@@ -0,0 +1,515 @@
+// RustDuino : A generic HAL implementation for Arduino Boards in Rust
+// Copyright (C) 2021 Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Sharing of the I2C and SPI buses between several devices.
+//! Each device gets a handle, `I2cDevice` with its address and SCL frequency, or
+//! `SpiDevice` with its chip select pin, mode, bit order and SCK frequency. A transfer
+//! through a handle takes the bus, sets it up for the device, selects it, and gives the
+//! bus back at the end, so the drivers of the devices do not have to know about each other.
+//! The handles work over any `TwiOps` or `SpiOps`. By default they use `SharedTwi` and
+//! `SharedSpi`, the buses of the chip, which are taken in a critical section: a handle
+//! used from an interrupt routine while the main program is in the middle of a transfer
+//! gets `Error::Busy` instead of breaking the transfer. The transfers themselves run with
+//! the interrupts enabled. The buses of the chip are initialized by the first transfer.
+//! With the `mock` feature and no chip, `SharedTwi` and `SharedSpi` are the fakes of
+//! `mock`, and the tests give each handle its own fake with `with_bus`.
+//! # Example
+//! ```ignore
+//! use rustduino::bus::I2cDevice;
+//!
+//! static mut RTC: I2cDevice = I2cDevice::new(0x68, 100_000);
+//!
+//! let mut seconds = [0];
+//! unsafe { RTC.read_registers(0x00, &mut seconds) }?;
+//! ```
+
+// Source codes required.
+use crate::common::{SpiOps, TwiOps};
+use crate::error::Error;
+use crate::hal::pin::make_pin;
+
+cfg_if::cfg_if! {
+    if #[cfg(any(feature = "atmega2560p", feature = "atmega328p", feature = "atmega32u4"))] {
+        use crate::com::i2c::Twi;
+        use crate::com::spi::{BitOrder, ClockDivider, Spi, SpiMode};
+        use crate::config::CPU_FREQUENCY_HZ;
+        use crate::sync::{free, AtomicFlag};
+
+        // Locks of the buses, and whether they have been initialized.
+        static I2C_LOCK: AtomicFlag = AtomicFlag::new(false);
+        static SPI_LOCK: AtomicFlag = AtomicFlag::new(false);
+        static I2C_READY: AtomicFlag = AtomicFlag::new(false);
+        static SPI_READY: AtomicFlag = AtomicFlag::new(false);
+
+        // SCL frequency the TWI is set to, only changed while the bus is taken.
+        static mut I2C_FREQUENCY: u32 = 0;
+
+        /// Takes a bus, which must be given back by clearing its lock.
+        fn acquire(lock: &AtomicFlag) -> Result<(), Error> {
+            free(|_| {
+                if lock.is_set() {
+                    Err(Error::Busy)
+                } else {
+                    lock.set();
+                    Ok(())
+                }
+            })
+        }
+
+        /// Checks whether a transfer is going on on the I2C bus.
+        pub fn i2c_busy() -> bool {
+            I2C_LOCK.is_set()
+        }
+
+        /// Checks whether a transfer is going on on the SPI bus.
+        pub fn spi_busy() -> bool {
+            SPI_LOCK.is_set()
+        }
+
+        /// The TWI of the chip, shared by all the `I2cDevice` handles which use it.
+        #[derive(Clone, Copy, PartialEq, Debug, Default)]
+        pub struct SharedTwi;
+
+        impl SharedTwi {
+            /// Gives the TWI of the chip.
+            pub const fn new() -> SharedTwi {
+                SharedTwi
+            }
+        }
+
+        impl TwiOps for SharedTwi {
+            fn start(&mut self) -> Result<(), Error> {
+                Twi::new().start()
+            }
+
+            fn rep_start(&mut self) -> Result<(), Error> {
+                Twi::new().rep_start()
+            }
+
+            fn stop(&mut self) {
+                Twi::new().stop();
+            }
+
+            fn address_write(&mut self, address: u8) -> Result<(), Error> {
+                Twi::new().address_write(address)
+            }
+
+            fn address_read(&mut self, address: u8) -> Result<(), Error> {
+                Twi::new().address_read(address)
+            }
+
+            fn write(&mut self, data: u8) -> Result<(), Error> {
+                Twi::new().write(data)
+            }
+
+            fn read_byte(&mut self, ack: bool) -> Result<u8, Error> {
+                Twi::new().read_byte(ack)
+            }
+
+            fn acquire(&mut self) -> Result<(), Error> {
+                acquire(&I2C_LOCK)?;
+                if !I2C_READY.is_set() {
+                    Twi::new().init();
+                    I2C_READY.set();
+                }
+                Ok(())
+            }
+
+            fn release(&mut self) {
+                I2C_LOCK.clear();
+            }
+
+            fn set_frequency(&mut self, frequency: u32) -> Result<(), Error> {
+                unsafe {
+                    if I2C_FREQUENCY != frequency {
+                        Twi::new().set_frequency(frequency)?;
+                        I2C_FREQUENCY = frequency;
+                    }
+                }
+                Ok(())
+            }
+        }
+
+        /// The SPI of the chip, shared by all the `SpiDevice` handles which use it.
+        #[derive(Clone, Copy, PartialEq, Debug, Default)]
+        pub struct SharedSpi;
+
+        impl SharedSpi {
+            /// Gives the SPI of the chip.
+            pub const fn new() -> SharedSpi {
+                SharedSpi
+            }
+        }
+
+        impl SpiOps for SharedSpi {
+            fn transfer(&mut self, data: u8) -> u8 {
+                Spi::new().transfer(data)
+            }
+
+            fn acquire(&mut self) -> Result<(), Error> {
+                acquire(&SPI_LOCK)?;
+                if !SPI_READY.is_set() {
+                    Spi::new().init(SpiMode::Mode0, BitOrder::MsbFirst, ClockDivider::Div4);
+                    SPI_READY.set();
+                }
+                Ok(())
+            }
+
+            fn release(&mut self) {
+                SPI_LOCK.clear();
+            }
+
+            fn configure(&mut self, mode: u8, lsb_first: bool, frequency: u32) {
+                let spi = Spi::new();
+                spi.set_mode(match mode {
+                    0 => SpiMode::Mode0,
+                    1 => SpiMode::Mode1,
+                    2 => SpiMode::Mode2,
+                    _ => SpiMode::Mode3,
+                });
+                spi.set_bit_order(if lsb_first {
+                    BitOrder::LsbFirst
+                } else {
+                    BitOrder::MsbFirst
+                });
+                spi.set_clock_divider(divider(frequency));
+            }
+        }
+
+        /// Gives the smallest division of the system clock keeping SCK at or below
+        /// `frequency`, or the largest one if none does.
+        fn divider(frequency: u32) -> ClockDivider {
+            let dividers = [
+                ClockDivider::Div2,
+                ClockDivider::Div4,
+                ClockDivider::Div8,
+                ClockDivider::Div16,
+                ClockDivider::Div32,
+                ClockDivider::Div64,
+            ];
+            for (i, divider) in dividers.iter().enumerate() {
+                if CPU_FREQUENCY_HZ >> (i + 1) <= frequency {
+                    return *divider;
+                }
+            }
+            ClockDivider::Div128
+        }
+    } else {
+        pub use crate::mock::{MockSpi as SharedSpi, MockTwi as SharedTwi};
+    }
+}
+
+/// Handle of a device on the I2C bus.
+/// # Elements
+/// * `bus` - a `TwiOps` object, the bus the device is on.
+/// * `address` - a u8, the seven bit address of the device.
+/// * `frequency` - a u32, the SCL frequency in Hz used with the device.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct I2cDevice<T = SharedTwi> {
+    bus: T,
+    address: u8,
+    frequency: u32,
+}
+
+impl I2cDevice {
+    /// Creates the handle of a device on the TWI of the chip.
+    /// # Arguments
+    /// * `address` - a u8, the seven bit address of the device.
+    /// * `frequency` - a u32, the SCL frequency in Hz, normally 100 kHz or 400 kHz.
+    /// # Returns
+    /// * `a I2cDevice object` - which will be used to talk to the device.
+    pub const fn new(address: u8, frequency: u32) -> I2cDevice {
+        I2cDevice {
+            bus: SharedTwi::new(),
+            address,
+            frequency,
+        }
+    }
+}
+
+impl<T: TwiOps> I2cDevice<T> {
+    /// Creates the handle of a device on the given bus.
+    /// # Arguments
+    /// * `bus` - a `TwiOps` object, the bus the device is on.
+    /// * `address` - a u8, the seven bit address of the device.
+    /// * `frequency` - a u32, the SCL frequency in Hz, normally 100 kHz or 400 kHz.
+    /// # Returns
+    /// * `a I2cDevice object` - which will be used to talk to the device.
+    pub fn with_bus(bus: T, address: u8, frequency: u32) -> I2cDevice<T> {
+        I2cDevice {
+            bus,
+            address,
+            frequency,
+        }
+    }
+
+    /// Gives the address of the device.
+    pub fn address(&self) -> u8 {
+        self.address
+    }
+
+    /// Gives the bus back, dropping the handle.
+    pub fn into_bus(self) -> T {
+        self.bus
+    }
+
+    /// Runs a transfer with the bus set to the frequency of the device.
+    /// The closure gets the bus and must leave it stopped.
+    /// # Arguments
+    /// * `f` - a closure, which gets the bus and the address of the device.
+    /// # Returns
+    /// * `a Result<R, Error>` - the result of the closure, or `Error::Busy` if the bus is
+    ///   taken by a transfer that this one has interrupted.
+    pub fn transaction<R, F>(&mut self, f: F) -> Result<R, Error>
+    where
+        F: FnOnce(&mut T, u8) -> Result<R, Error>,
+    {
+        self.bus.acquire()?;
+        let address = self.address;
+        let result = self
+            .bus
+            .set_frequency(self.frequency)
+            .and_then(|_| f(&mut self.bus, address));
+        self.bus.release();
+        result
+    }
+
+    /// Writes bytes to the device.
+    /// # Arguments
+    /// * `bytes` - a slice of u8, the bytes to be written.
+    /// # Returns
+    /// * `a Result<(), Error>` - which is an error if the device did not acknowledge every byte.
+    pub fn write(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        self.write_read(bytes, &mut [])
+    }
+
+    /// Reads bytes from the device.
+    /// # Arguments
+    /// * `buffer` - a mutable slice of u8, which will be filled with the bytes read.
+    /// # Returns
+    /// * `a Result<(), Error>` - which is an error if the transfer failed.
+    pub fn read(&mut self, buffer: &mut [u8]) -> Result<(), Error> {
+        self.write_read(&[], buffer)
+    }
+
+    /// Writes bytes to the device, then reads bytes after a repeated start.
+    /// # Arguments
+    /// * `bytes` - a slice of u8, the bytes to be written, none to only read.
+    /// * `buffer` - a mutable slice of u8, which will be filled with the bytes read,
+    ///   empty to only write.
+    /// # Returns
+    /// * `a Result<(), Error>` - which is an error if the transfer failed.
+    pub fn write_read(&mut self, bytes: &[u8], buffer: &mut [u8]) -> Result<(), Error> {
+        self.transaction(|twi, address| {
+            let result = write_read(twi, address, bytes, buffer);
+            twi.stop();
+            result
+        })
+    }
+
+    /// Writes a register of the device.
+    /// # Arguments
+    /// * `register` - a u8, the address of the register in the device.
+    /// * `value` - a u8, the value to be written.
+    /// # Returns
+    /// * `a Result<(), Error>` - which is an error if the device did not acknowledge every byte.
+    pub fn write_register(&mut self, register: u8, value: u8) -> Result<(), Error> {
+        self.transaction(|twi, address| twi.write_register(address, register, value))
+    }
+
+    /// Reads consecutive registers of the device, starting from `register`.
+    /// # Arguments
+    /// * `register` - a u8, the address of the first register in the device.
+    /// * `buffer` - a mutable slice of u8, which will be filled with the registers read.
+    /// # Returns
+    /// * `a Result<(), Error>` - which is an error if the transfer failed.
+    pub fn read_registers(&mut self, register: u8, buffer: &mut [u8]) -> Result<(), Error> {
+        self.transaction(|twi, address| twi.read_registers(address, register, buffer))
+    }
+}
+
+// Sends the bytes and receives the buffer, leaving the stop condition to the caller.
+fn write_read<T: TwiOps>(
+    twi: &mut T,
+    address: u8,
+    bytes: &[u8],
+    buffer: &mut [u8],
+) -> Result<(), Error> {
+    twi.start()?;
+    if !bytes.is_empty() || buffer.is_empty() {
+        twi.address_write(address)?;
+        for byte in bytes {
+            twi.write(*byte)?;
+        }
+        if buffer.is_empty() {
+            return Ok(());
+        }
+        twi.rep_start()?;
+    }
+    twi.address_read(address)?;
+    let length = buffer.len();
+    for (i, byte) in buffer.iter_mut().enumerate() {
+        *byte = twi.read_byte(i + 1 < length)?;
+    }
+    Ok(())
+}
+
+/// Handle of a device on the SPI bus, selected by its own chip select pin.
+/// # Elements
+/// * `bus` - a `SpiOps` object, the bus the device is on.
+/// * `cs` - a u8, the digital pin connected to the chip select of the device.
+/// * `mode` - a u8, the SPI mode from 0 to 3, setting the clock polarity and phase.
+/// * `lsb_first` - a boolean, true if the device sends the least significant bit first.
+/// * `frequency` - a u32, the highest SCK frequency in Hz used with the device.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SpiDevice<T = SharedSpi> {
+    bus: T,
+    cs: u8,
+    mode: u8,
+    lsb_first: bool,
+    frequency: u32,
+}
+
+impl SpiDevice {
+    /// Creates the handle of a device on the SPI of the chip. Its chip select pin is
+    /// set up by `init`, or by the first transfer.
+    /// # Arguments
+    /// * `cs` - a u8, the digital pin connected to the chip select of the device.
+    /// * `mode` - a u8, the SPI mode from 0 to 3, setting the clock polarity and phase.
+    /// * `lsb_first` - a boolean, true if the device sends the least significant bit first.
+    /// * `frequency` - a u32, the highest SCK frequency in Hz the device accepts.
+    /// # Returns
+    /// * `a SpiDevice object` - which will be used to talk to the device.
+    pub const fn new(cs: u8, mode: u8, lsb_first: bool, frequency: u32) -> SpiDevice {
+        SpiDevice {
+            bus: SharedSpi::new(),
+            cs,
+            mode,
+            lsb_first,
+            frequency,
+        }
+    }
+}
+
+impl<T: SpiOps> SpiDevice<T> {
+    /// Creates the handle of a device on the given bus.
+    /// # Arguments
+    /// * `bus` - a `SpiOps` object, the bus the device is on.
+    /// * `cs` - a u8, the digital pin connected to the chip select of the device.
+    /// * `mode` - a u8, the SPI mode from 0 to 3, setting the clock polarity and phase.
+    /// * `lsb_first` - a boolean, true if the device sends the least significant bit first.
+    /// * `frequency` - a u32, the highest SCK frequency in Hz the device accepts.
+    /// # Returns
+    /// * `a SpiDevice object` - which will be used to talk to the device.
+    pub fn with_bus(bus: T, cs: u8, mode: u8, lsb_first: bool, frequency: u32) -> SpiDevice<T> {
+        SpiDevice {
+            bus,
+            cs,
+            mode,
+            lsb_first,
+            frequency,
+        }
+    }
+
+    /// Gives the bus back, dropping the handle.
+    pub fn into_bus(self) -> T {
+        self.bus
+    }
+
+    /// Changes the highest SCK frequency used with the device, for the devices which
+    /// start slow and speed up once they are initialized.
+    /// # Arguments
+    /// * `frequency` - a u32, the SCK frequency in Hz.
+    pub fn set_frequency(&mut self, frequency: u32) {
+        self.frequency = frequency;
+    }
+
+    /// Drives the chip select pin high, so that the device does not answer to the
+    /// transfers of the other devices. This should be done for all the devices of
+    /// the bus before the first transfer.
+    pub fn init(&mut self) {
+        let mut cs = make_pin(self.cs as _);
+        cs.high();
+        cs.set_output();
+    }
+
+    /// Runs a transfer with the bus set up for the device and the device selected.
+    /// # Arguments
+    /// * `f` - a closure, which gets the bus.
+    /// # Returns
+    /// * `a Result<R, Error>` - the result of the closure, or `Error::Busy` if the bus is
+    ///   taken by a transfer that this one has interrupted.
+    pub fn transaction<R, F>(&mut self, f: F) -> Result<R, Error>
+    where
+        F: FnOnce(&mut T) -> R,
+    {
+        self.run(true, f)
+    }
+
+    /// Sends bytes with the bus set up for the device but the device not selected, for
+    /// the devices which need clock pulses while they are not selected, like SD cards.
+    /// # Returns
+    /// * `a Result<(), Error>` - which is `Error::Busy` if the bus is taken.
+    pub fn write_deselected(&mut self, data: &[u8]) -> Result<(), Error> {
+        self.run(false, |spi| spi.write(data))
+    }
+
+    fn run<R, F>(&mut self, select: bool, f: F) -> Result<R, Error>
+    where
+        F: FnOnce(&mut T) -> R,
+    {
+        self.bus.acquire()?;
+        self.bus
+            .configure(self.mode, self.lsb_first, self.frequency);
+        self.init();
+        let mut cs = make_pin(self.cs as _);
+        if select {
+            cs.low();
+        }
+        let result = f(&mut self.bus);
+        cs.high();
+        self.bus.release();
+        Ok(result)
+    }
+
+    /// Sends all the bytes of the slice, replacing each with the byte received.
+    /// # Returns
+    /// * `a Result<(), Error>` - which is `Error::Busy` if the bus is taken.
+    pub fn transfer_in_place(&mut self, data: &mut [u8]) -> Result<(), Error> {
+        self.transaction(|spi| spi.transfer_in_place(data))
+    }
+
+    /// Sends all the bytes of the slice, ignoring the bytes received.
+    /// # Returns
+    /// * `a Result<(), Error>` - which is `Error::Busy` if the bus is taken.
+    pub fn write(&mut self, data: &[u8]) -> Result<(), Error> {
+        self.transaction(|spi| spi.write(data))
+    }
+
+    /// Sends a command, then fills the buffer with the answer of the device, with the
+    /// device selected during both.
+    /// # Arguments
+    /// * `command` - a slice of u8, the bytes sent first.
+    /// * `buffer` - a mutable slice of u8, which will be filled with the bytes received.
+    /// # Returns
+    /// * `a Result<(), Error>` - which is `Error::Busy` if the bus is taken.
+    pub fn write_read(&mut self, command: &[u8], buffer: &mut [u8]) -> Result<(), Error> {
+        self.transaction(|spi| {
+            spi.write(command);
+            spi.read(buffer);
+        })
+    }
+}
@@ -0,0 +1,350 @@
+// RustDuino : A generic HAL implementation for Arduino Boards in Rust
+// Copyright (C) 2021 Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! System clock of the chip: the prescaler dividing the clock source, the frequency
+//! after its changes, and the calibration of the internal RC oscillator.
+//! The prescaler divides the clock source of the whole chip by a power of two from 1
+//! to 256 to save power. CLKPR is only written when CLKPCE has been set less than 4
+//! cycles before, so the two writes are done in assembly with the interrupts disabled.
+//! `crate::config::CPU_FREQUENCY_HZ` is the frequency the program starts with, so the
+//! division set by the CKDIV8 fuse is already part of it. The division at start-up is
+//! recorded on the first change, and `system_clock_hz` gives the frequency after it.
+//! After every change the drivers depending on the frequency are notified: `crate::time`
+//! computes its tick again and the USARTs set their baud rates again, while `crate::delay`
+//! reads the division itself. Other drivers can register a function with `on_change`.
+//! See the sections 9.6 and 9.12 of ATMEGA328P datasheet, and the application note
+//! AVR053 for the calibration.
+
+// Source codes required.
+use crate::config::CPU_FREQUENCY_HZ;
+use crate::registers::CLKPR;
+use crate::sync;
+#[cfg(any(feature = "atmega2560p", feature = "atmega328p"))]
+use crate::{
+    hal::watchdog::{WatchDog, WatchdogMode, WatchdogPrescaler},
+    registers::{
+        ASSR, OSCCAL, TCCR1A, TCCR1B, TCCR2A, TCCR2B, TCNT1, TCNT2, TIFR1, TIMSK2, WDTCSR,
+    },
+};
+
+/// Largest number of functions registered with `on_change`.
+const MAX_LISTENERS: usize = 4;
+
+/// Ticks of the 32768 Hz crystal during which the system clock is counted.
+#[cfg(any(feature = "atmega2560p", feature = "atmega328p"))]
+const CRYSTAL_TICKS: u8 = 32;
+
+/// Division factor of the system clock.
+#[derive(Clone, Copy, PartialEq)]
+pub enum ClockDivision {
+    Div1,
+    Div2,
+    Div4,
+    Div8,
+    Div16,
+    Div32,
+    Div64,
+    Div128,
+    Div256,
+}
+
+impl ClockDivision {
+    /// Gives the value of the CLKPS3:0 bits, which is the power of two of the division.
+    fn bits(&self) -> u8 {
+        match self {
+            ClockDivision::Div1 => 0,
+            ClockDivision::Div2 => 1,
+            ClockDivision::Div4 => 2,
+            ClockDivision::Div8 => 3,
+            ClockDivision::Div16 => 4,
+            ClockDivision::Div32 => 5,
+            ClockDivision::Div64 => 6,
+            ClockDivision::Div128 => 7,
+            ClockDivision::Div256 => 8,
+        }
+    }
+
+    /// Gives the division factor.
+    /// # Returns
+    /// * `a u16` - the factor, from 1 to 256.
+    pub fn factor(&self) -> u16 {
+        1 << self.bits()
+    }
+}
+
+/// Reference against which the internal RC oscillator is calibrated.
+/// * `Watchdog` - the 128 kHz oscillator of the watchdog timer, which is only accurate
+///   to about 10%, so the calibration is coarse.
+/// * `Crystal` - a 32768 Hz watch crystal on the TOSC1 and TOSC2 pins, clocking Timer2.
+#[cfg(any(feature = "atmega2560p", feature = "atmega328p"))]
+#[derive(Clone, Copy, PartialEq)]
+pub enum CalibrationReference {
+    Watchdog,
+    Crystal,
+}
+
+// CLKPS3:0 at start-up, recorded before the first change, or 0xFF while unchanged.
+static mut START_BITS: u8 = 0xFF;
+
+// Division selected by the last change of the prescaler.
+static mut SELECTED: Option<ClockDivision> = None;
+
+// Functions called with the new frequency after every change of the system clock.
+static mut LISTENERS: [Option<fn(u32)>; MAX_LISTENERS] = [None; MAX_LISTENERS];
+
+/// Structure to control the system clock prescaler.
+/// The register is reached through the register map, so the structure holds nothing.
+pub struct Prescalar {
+    _private: (),
+}
+
+impl Prescalar {
+    /// Returns the prescaler, without changing the division.
+    /// # Returns
+    /// * `a Prescalar object` - which will be used to change the division.
+    pub fn new() -> Prescalar {
+        Prescalar { _private: () }
+    }
+
+    /// Divides the clock source by the given factor, and notifies the drivers which
+    /// depend on the frequency.
+    /// Everything clocked by the system clock slows down. The system tick and the baud
+    /// rates follow the change, the timers and buses set up by other drivers do not.
+    /// # Arguments
+    /// * `division` - a `ClockDivision` object, the division factor.
+    pub fn enable_clock(&mut self, division: ClockDivision) {
+        sync::free(|_| unsafe {
+            if START_BITS == 0xFF {
+                START_BITS = CLKPR.read_field(CLKPR::CLKPS);
+            }
+            SELECTED = Some(division);
+            let address = CLKPR.address() as u16;
+            let enable = CLKPR::CLKPCE.mask() as u8;
+            let bits = division.bits();
+            // Both stores take 2 cycles, so the second one is within the 4 cycles allowed.
+            llvm_asm!("st Z, $0
+                       st Z, $1"
+                     :
+                     : "r" (enable), "r" (bits), "z" (address)
+                     : "memory"
+                     : "volatile");
+        });
+        notify();
+    }
+
+    /// Gives the division selected with `enable_clock`.
+    /// # Returns
+    /// * `a Option<ClockDivision>` - which is None while the division of start-up is kept.
+    pub fn selected(&self) -> Option<ClockDivision> {
+        unsafe { SELECTED }
+    }
+
+    /// Gives the power of two by which the clock source is divided.
+    /// # Returns
+    /// * `a u16` - the division factor, from 1 to 256.
+    pub fn division(&self) -> u16 {
+        1 << CLKPR.read_field(CLKPR::CLKPS)
+    }
+}
+
+impl Default for Prescalar {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Gives the frequency of the system clock after the changes of the prescaler.
+/// # Returns
+/// * `a u32` - the frequency in Hertz.
+pub fn system_clock_hz() -> u32 {
+    let start = unsafe { START_BITS };
+    if start == 0xFF {
+        return CPU_FREQUENCY_HZ;
+    }
+    let current = CLKPR.read_field(CLKPR::CLKPS);
+    if current >= start {
+        CPU_FREQUENCY_HZ >> (current - start)
+    } else {
+        CPU_FREQUENCY_HZ << (start - current)
+    }
+}
+
+/// Gives the number of times the system clock has been halved since start-up, which is
+/// negative if it has been made faster.
+/// # Returns
+/// * `a i8` - the difference of the powers of two of the division.
+pub fn clock_shift() -> i8 {
+    let start = unsafe { START_BITS };
+    if start == 0xFF {
+        return 0;
+    }
+    CLKPR.read_field(CLKPR::CLKPS) as i8 - start as i8
+}
+
+/// Registers a function called with the new frequency in Hertz after every change of
+/// the system clock, for the drivers which compute their dividers from it.
+/// # Arguments
+/// * `listener` - a function, which is called after the change.
+/// # Returns
+/// * `a boolean` - which is false if the 4 places for functions are already taken.
+pub fn on_change(listener: fn(u32)) -> bool {
+    sync::free(|_| unsafe {
+        for slot in LISTENERS.iter_mut() {
+            if slot.is_none() {
+                *slot = Some(listener);
+                return true;
+            }
+        }
+        false
+    })
+}
+
+// Notifies the drivers depending on the frequency of the system clock.
+fn notify() {
+    let frequency = system_clock_hz();
+    #[cfg(any(feature = "atmega2560p", feature = "atmega328p"))]
+    crate::time::clock_changed(frequency);
+    #[cfg(all(
+        feature = "com",
+        any(
+            feature = "atmega2560p",
+            feature = "atmega328p",
+            feature = "atmega32u4"
+        )
+    ))]
+    crate::com::usart_initialize::clock_changed();
+    let listeners = unsafe { LISTENERS };
+    for listener in listeners.iter().flatten() {
+        listener(frequency);
+    }
+}
+
+/// Measures the frequency of the system clock against a reference, by counting the
+/// cycles of Timer1 during a known time. Timer1 is stopped afterwards and Timer2 is left
+/// counting the crystal. The interrupts are disabled during the measurement, which takes
+/// about 1 millisecond with the crystal and 32 milliseconds with the watchdog.
+/// The crystal must have been running for about a second, as it is slow to start.
+/// # Arguments
+/// * `reference` - a `CalibrationReference` object, the reference.
+/// # Returns
+/// * `a u32` - the frequency in Hertz.
+#[cfg(any(feature = "atmega2560p", feature = "atmega328p"))]
+pub fn measure_clock_hz(reference: CalibrationReference) -> u32 {
+    match reference {
+        // 32 ticks of the crystal last 1/1024 of a second.
+        CalibrationReference::Crystal => count_cycles(reference) << 10,
+        // The 16 millisecond time-out of the watchdog is 2048 cycles of 128 kHz.
+        CalibrationReference::Watchdog => count_cycles(reference) * 125 / 2,
+    }
+}
+
+/// Calibrates the internal RC oscillator, so that the system clock runs at the
+/// frequency given by `system_clock_hz`. OSCCAL is searched bit by bit within its present
+/// range, then the closest of the last two values is kept.
+/// This only has an effect when the chip runs from its internal RC oscillator, and the
+/// value found can be saved in the EEPROM to be written back at start-up.
+/// # Arguments
+/// * `reference` - a `CalibrationReference` object, the reference.
+/// # Returns
+/// * `a u8` - the value written to OSCCAL.
+#[cfg(any(feature = "atmega2560p", feature = "atmega328p"))]
+pub fn calibrate(reference: CalibrationReference) -> u8 {
+    let target = system_clock_hz();
+    let range = OSCCAL.read() & 0x80;
+    let mut value: u8 = 0;
+    for bit in (0..7).rev() {
+        let trial = value | 1 << bit;
+        OSCCAL.write(range | trial);
+        if measure_clock_hz(reference) <= target {
+            value = trial;
+        }
+    }
+    // `value` is the highest setting below the target, the next one may be closer.
+    OSCCAL.write(range | value);
+    let below = target - measure_clock_hz(reference).min(target);
+    if value < 0x7F {
+        OSCCAL.write(range | (value + 1));
+        let above = measure_clock_hz(reference).saturating_sub(target);
+        if above < below {
+            return range | (value + 1);
+        }
+        OSCCAL.write(range | value);
+    }
+    range | value
+}
+
+// Counts the cycles of the system clock during 32 ticks of the crystal or during one
+// time-out of the watchdog, with Timer1 running without prescaler.
+#[cfg(any(feature = "atmega2560p", feature = "atmega328p"))]
+fn count_cycles(reference: CalibrationReference) -> u32 {
+    sync::free(|_| {
+        TCCR1B.write(0);
+        TCCR1A.write(0);
+        TCNT1.write(0);
+        TIFR1.write(TIFR1::TOV1.mask() as u8);
+        let mut overflows: u32 = 0;
+        match reference {
+            CalibrationReference::Crystal => {
+                // Timer2 is switched to the crystal as given in section 18.9 of ATMEGA328P
+                // datasheet, then the count starts on a tick of the crystal.
+                TIMSK2.write(0);
+                ASSR.set(ASSR::AS2);
+                TCNT2.write(0);
+                TCCR2A.write(0);
+                TCCR2B.write(0b001);
+                while ASSR.is_set(ASSR::TCN2UB)
+                    || ASSR.is_set(ASSR::TCR2AUB)
+                    || ASSR.is_set(ASSR::TCR2BUB)
+                {}
+                let first = TCNT2.read();
+                while TCNT2.read() == first {}
+                TCCR1B.write_field(TCCR1B::CS1, 0b001);
+                let end = first.wrapping_add(1 + CRYSTAL_TICKS);
+                while TCNT2.read() != end {
+                    if TIFR1.is_set(TIFR1::TOV1) {
+                        TIFR1.write(TIFR1::TOV1.mask() as u8);
+                        overflows += 1;
+                    }
+                }
+            }
+            CalibrationReference::Watchdog => {
+                let watchdog = unsafe { WatchDog::new() };
+                // The first time-out only starts the count, as the watchdog is not
+                // synchronised with the system clock when it is enabled.
+                watchdog.enable(WatchdogMode::Interrupt, WatchdogPrescaler::Ms16);
+                while !WDTCSR.is_set(WDTCSR::WDIF) {}
+                WDTCSR.set(WDTCSR::WDIF);
+                TCCR1B.write_field(TCCR1B::CS1, 0b001);
+                while !WDTCSR.is_set(WDTCSR::WDIF) {
+                    if TIFR1.is_set(TIFR1::TOV1) {
+                        TIFR1.write(TIFR1::TOV1.mask() as u8);
+                        overflows += 1;
+                    }
+                }
+                watchdog.disable();
+            }
+        }
+        TCCR1B.write(0);
+        let mut cycles = TCNT1.read() as u32;
+        // An overflow after the last check is not counted yet.
+        if TIFR1.is_set(TIFR1::TOV1) && cycles < 0x8000 {
+            overflows += 1;
+        }
+        cycles += overflows << 16;
+        TIFR1.write(TIFR1::TOV1.mask() as u8);
+        cycles
+    })
+}
@@ -0,0 +1,29 @@
+// RustDuino : A generic HAL implementation for Arduino Boards in Rust
+// Copyright (C) 2021 Devansh Kumar Jha, Indian Institute of Technology Kanpur
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Collections of a fixed capacity, kept in statics or on the stack since there is no
+//! heap in no_std.
+//! `RingBuffer` is a queue which can be shared by an interrupt service routine and the
+//! main program, `Vec` and `String` are the vector and the string of `alloc` with a
+//! capacity given as a const generic.
+
+mod ring_buffer;
+mod string;
+mod vec;
+
+pub use ring_buffer::*;
+pub use string::*;
+pub use vec::*;
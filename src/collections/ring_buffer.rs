@@ -0,0 +1,160 @@
+// RustDuino : A generic HAL implementation for Arduino Boards in Rust
+// Copyright (C) 2021 Devansh Kumar Jha, Indian Institute of Technology Kanpur
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Queue in a static array, for one producer and one consumer, as an interrupt service
+//! routine and the main program.
+//! The producer only writes the head and the consumer only writes the tail, and both
+//! are single bytes, which the AVR chips read and write atomically. So the two sides
+//! need no critical section as long as each one is used from a single place.
+
+// Crates which would be used in the implementation.
+use core::cell::UnsafeCell;
+use core::mem::MaybeUninit;
+use core::ptr::{read_volatile, write_volatile};
+use core::sync::atomic::{compiler_fence, Ordering};
+
+/// Queue of `N - 1` elements, `N` being from 2 to 256.
+/// One place of the array is always kept empty to tell a full queue from an empty one.
+/// # Elements
+/// * `buffer` - a array of `N` elements, the storage.
+/// * `head` - a u8, the place of the next element pushed, written by the producer.
+/// * `tail` - a u8, the place of the next element popped, written by the consumer.
+/// # Example
+/// ```ignore
+/// static RX: RingBuffer<u8, 32> = RingBuffer::new();
+///
+/// // In the interrupt service routine.
+/// RX.push(byte).ok();
+/// // In the main program.
+/// while let Some(byte) = RX.pop() {}
+/// ```
+pub struct RingBuffer<T, const N: usize> {
+    buffer: UnsafeCell<MaybeUninit<[T; N]>>,
+    head: UnsafeCell<u8>,
+    tail: UnsafeCell<u8>,
+}
+
+impl<T, const N: usize> RingBuffer<T, N> {
+    /// Creates an empty queue, which can be put in a static.
+    pub const fn new() -> RingBuffer<T, N> {
+        RingBuffer {
+            buffer: UnsafeCell::new(MaybeUninit::uninit()),
+            head: UnsafeCell::new(0),
+            tail: UnsafeCell::new(0),
+        }
+    }
+}
+
+impl<T: Copy, const N: usize> RingBuffer<T, N> {
+    /// Gives the number of elements the queue can hold.
+    pub fn capacity(&self) -> usize {
+        N - 1
+    }
+
+    /// Gives the number of elements in the queue.
+    pub fn len(&self) -> usize {
+        let (head, tail) = (self.head() as usize, self.tail() as usize);
+        (head + N - tail) % N
+    }
+
+    /// Checks whether the queue is empty.
+    pub fn is_empty(&self) -> bool {
+        self.head() == self.tail()
+    }
+
+    /// Checks whether the queue is full.
+    pub fn is_full(&self) -> bool {
+        next::<N>(self.head()) == self.tail()
+    }
+
+    /// Appends an element, to be called by the producer only.
+    /// # Arguments
+    /// * `value` - a element to be put at the end of the queue.
+    /// # Returns
+    /// * `a Result<(), T>` - which gives back the element if the queue is full.
+    pub fn push(&self, value: T) -> Result<(), T> {
+        let head = self.head();
+        let next = next::<N>(head);
+        if next == self.tail() {
+            return Err(value);
+        }
+        unsafe {
+            write_volatile(self.slot(head), value);
+            // The element must be written before the consumer can see it.
+            compiler_fence(Ordering::SeqCst);
+            write_volatile(self.head.get(), next);
+        }
+        Ok(())
+    }
+
+    /// Removes the oldest element, to be called by the consumer only.
+    /// # Returns
+    /// * `a Option<T>` - the element, None if the queue is empty.
+    pub fn pop(&self) -> Option<T> {
+        let tail = self.tail();
+        let value = self.peek()?;
+        compiler_fence(Ordering::SeqCst);
+        unsafe { write_volatile(self.tail.get(), next::<N>(tail)) };
+        Some(value)
+    }
+
+    /// Gives the oldest element without removing it, to be called by the consumer only.
+    /// # Returns
+    /// * `a Option<T>` - the element, None if the queue is empty.
+    pub fn peek(&self) -> Option<T> {
+        let tail = self.tail();
+        if self.head() == tail {
+            return None;
+        }
+        compiler_fence(Ordering::SeqCst);
+        Some(unsafe { read_volatile(self.slot(tail)) })
+    }
+
+    /// Removes all the elements, to be called by the consumer only.
+    pub fn clear(&self) {
+        unsafe { write_volatile(self.tail.get(), self.head()) };
+    }
+
+    fn head(&self) -> u8 {
+        unsafe { read_volatile(self.head.get()) }
+    }
+
+    fn tail(&self) -> u8 {
+        unsafe { read_volatile(self.tail.get()) }
+    }
+
+    fn slot(&self, index: u8) -> *mut T {
+        unsafe { (self.buffer.get() as *mut T).add(index as usize) }
+    }
+}
+
+impl<T, const N: usize> Default for RingBuffer<T, N> {
+    fn default() -> RingBuffer<T, N> {
+        RingBuffer::new()
+    }
+}
+
+// The producer and the consumer each write their own index, see the module documentation.
+unsafe impl<T: Send, const N: usize> Sync for RingBuffer<T, N> {}
+
+// Gives the place after an index, going back to 0 after the last one.
+fn next<const N: usize>(index: u8) -> u8 {
+    if index as usize + 1 >= N {
+        0
+    } else {
+        index + 1
+    }
+}
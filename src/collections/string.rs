@@ -0,0 +1,143 @@
+// RustDuino : A generic HAL implementation for Arduino Boards in Rust
+// Copyright (C) 2021 Devansh Kumar Jha, Indian Institute of Technology Kanpur
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! String of a fixed capacity in bytes, which lives in a static or on the stack.
+//! It implements `core::fmt::Write`, so `write!` can format numbers into it.
+
+// Crates which would be used in the implementation.
+use super::vec::Vec;
+use core::fmt;
+use core::ops::Deref;
+use core::str;
+
+/// String of up to `N` bytes of UTF-8.
+/// # Elements
+/// * `bytes` - a `Vec` of `N` bytes, the text.
+#[derive(Clone, Default, PartialEq)]
+pub struct String<const N: usize> {
+    bytes: Vec<u8, N>,
+}
+
+impl<const N: usize> String<N> {
+    /// Creates an empty string, which can be put in a static.
+    pub const fn new() -> String<N> {
+        String { bytes: Vec::new() }
+    }
+
+    /// Creates a string holding a copy of a string slice.
+    /// # Returns
+    /// * `a Option<String>` - the string, None if the slice is longer than `N` bytes.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(text: &str) -> Option<String<N>> {
+        let mut string = String::new();
+        string.push_str(text).ok()?;
+        Some(string)
+    }
+
+    /// Gives the number of bytes the string can hold.
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Gives the length in bytes.
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    /// Checks whether the string is empty.
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Gives the text as a string slice.
+    pub fn as_str(&self) -> &str {
+        // Only whole characters are ever added.
+        unsafe { str::from_utf8_unchecked(self.bytes.as_slice()) }
+    }
+
+    /// Gives the text as bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        self.bytes.as_slice()
+    }
+
+    /// Appends a string slice, or nothing if it does not fit.
+    /// # Arguments
+    /// * `text` - a string slice, to be put at the end.
+    /// # Returns
+    /// * `a Result<(), ()>` - which is an error if the string has not enough room.
+    #[allow(clippy::result_unit_err)]
+    pub fn push_str(&mut self, text: &str) -> Result<(), ()> {
+        self.bytes.extend_from_slice(text.as_bytes())
+    }
+
+    /// Appends a character.
+    /// # Arguments
+    /// * `c` - a char, to be put at the end.
+    /// # Returns
+    /// * `a Result<(), ()>` - which is an error if the string has not enough room.
+    #[allow(clippy::result_unit_err)]
+    pub fn push(&mut self, c: char) -> Result<(), ()> {
+        self.push_str(c.encode_utf8(&mut [0; 4]))
+    }
+
+    /// Removes the last character.
+    /// # Returns
+    /// * `a Option<char>` - the character, None if the string is empty.
+    pub fn pop(&mut self) -> Option<char> {
+        let c = self.as_str().chars().next_back()?;
+        self.bytes.truncate(self.len() - c.len_utf8());
+        Some(c)
+    }
+
+    /// Keeps the first bytes and drops the others.
+    /// # Arguments
+    /// * `len` - a usize, the number of bytes to keep, on a character boundary.
+    pub fn truncate(&mut self, len: usize) {
+        if self.as_str().is_char_boundary(len.min(self.len())) {
+            self.bytes.truncate(len);
+        }
+    }
+
+    /// Removes all the text.
+    pub fn clear(&mut self) {
+        self.bytes.clear();
+    }
+}
+
+impl<const N: usize> Deref for String<N> {
+    type Target = str;
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl<const N: usize> fmt::Write for String<N> {
+    fn write_str(&mut self, text: &str) -> fmt::Result {
+        self.push_str(text).map_err(|_| fmt::Error)
+    }
+}
+
+impl<const N: usize> fmt::Display for String<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl<const N: usize> fmt::Debug for String<N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
@@ -0,0 +1,235 @@
+// RustDuino : A generic HAL implementation for Arduino Boards in Rust
+// Copyright (C) 2021 Devansh Kumar Jha, Indian Institute of Technology Kanpur
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Vector of a fixed capacity, which lives in a static or on the stack.
+//! The elements are plain data, which is copied in and out.
+
+// Crates which would be used in the implementation.
+use core::fmt;
+use core::mem::MaybeUninit;
+use core::ops::{Deref, DerefMut};
+use core::slice;
+
+/// Vector of up to `N` elements.
+/// # Elements
+/// * `buffer` - a array of `N` elements, the storage, of which the first `len` are set.
+/// * `len` - a usize, the number of elements.
+pub struct Vec<T, const N: usize> {
+    buffer: MaybeUninit<[T; N]>,
+    len: usize,
+}
+
+impl<T, const N: usize> Vec<T, N> {
+    /// Creates an empty vector, which can be put in a static.
+    pub const fn new() -> Vec<T, N> {
+        Vec {
+            buffer: MaybeUninit::uninit(),
+            len: 0,
+        }
+    }
+}
+
+impl<T: Copy, const N: usize> Vec<T, N> {
+    /// Creates a vector holding a copy of a slice.
+    /// # Arguments
+    /// * `values` - a slice, the elements.
+    /// # Returns
+    /// * `a Option<Vec>` - the vector, None if the slice is longer than `N`.
+    pub fn from_slice(values: &[T]) -> Option<Vec<T, N>> {
+        let mut vec = Vec::new();
+        vec.extend_from_slice(values).ok()?;
+        Some(vec)
+    }
+
+    /// Gives the number of elements the vector can hold.
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Gives the number of elements.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Checks whether the vector is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Checks whether the vector is full.
+    pub fn is_full(&self) -> bool {
+        self.len == N
+    }
+
+    /// Gives the elements as a slice.
+    pub fn as_slice(&self) -> &[T] {
+        unsafe { slice::from_raw_parts(self.buffer.as_ptr() as *const T, self.len) }
+    }
+
+    /// Gives the elements as a mutable slice.
+    pub fn as_mut_slice(&mut self) -> &mut [T] {
+        unsafe { slice::from_raw_parts_mut(self.buffer.as_mut_ptr() as *mut T, self.len) }
+    }
+
+    /// Appends an element.
+    /// # Arguments
+    /// * `value` - a element to be put at the end.
+    /// # Returns
+    /// * `a Result<(), T>` - which gives back the element if the vector is full.
+    pub fn push(&mut self, value: T) -> Result<(), T> {
+        if self.len == N {
+            return Err(value);
+        }
+        unsafe { self.slot(self.len).write(value) };
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Removes the last element.
+    /// # Returns
+    /// * `a Option<T>` - the element, None if the vector is empty.
+    pub fn pop(&mut self) -> Option<T> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        Some(unsafe { self.slot(self.len).read() })
+    }
+
+    /// Appends all the elements of a slice, or none if they do not fit.
+    /// # Arguments
+    /// * `values` - a slice, the elements to be put at the end.
+    /// # Returns
+    /// * `a Result<(), ()>` - which is an error if the vector has not enough room.
+    #[allow(clippy::result_unit_err)]
+    pub fn extend_from_slice(&mut self, values: &[T]) -> Result<(), ()> {
+        if values.len() > N - self.len {
+            return Err(());
+        }
+        for value in values {
+            unsafe { self.slot(self.len).write(*value) };
+            self.len += 1;
+        }
+        Ok(())
+    }
+
+    /// Inserts an element, moving the following ones to the right.
+    /// # Arguments
+    /// * `index` - a usize, the place of the element, at most the length.
+    /// * `value` - a element to be inserted.
+    /// # Returns
+    /// * `a Result<(), T>` - which gives back the element if the vector is full or the
+    ///   index is past the end.
+    pub fn insert(&mut self, index: usize, value: T) -> Result<(), T> {
+        if self.len == N || index > self.len {
+            return Err(value);
+        }
+        unsafe {
+            self.slot(index)
+                .copy_to(self.slot(index + 1), self.len - index);
+            self.slot(index).write(value);
+        }
+        self.len += 1;
+        Ok(())
+    }
+
+    /// Removes an element, moving the following ones to the left.
+    /// # Arguments
+    /// * `index` - a usize, the place of the element.
+    /// # Returns
+    /// * `a Option<T>` - the element, None if the index is past the end.
+    pub fn remove(&mut self, index: usize) -> Option<T> {
+        if index >= self.len {
+            return None;
+        }
+        let value = self.as_slice()[index];
+        unsafe {
+            self.slot(index + 1)
+                .copy_to(self.slot(index), self.len - index - 1);
+        }
+        self.len -= 1;
+        Some(value)
+    }
+
+    /// Removes an element and puts the last one in its place, which is faster than `remove`.
+    /// # Arguments
+    /// * `index` - a usize, the place of the element.
+    /// # Returns
+    /// * `a Option<T>` - the element, None if the index is past the end.
+    pub fn swap_remove(&mut self, index: usize) -> Option<T> {
+        if index >= self.len {
+            return None;
+        }
+        let last = self.len - 1;
+        self.as_mut_slice().swap(index, last);
+        self.pop()
+    }
+
+    /// Keeps the first elements and drops the others.
+    /// # Arguments
+    /// * `len` - a usize, the number of elements to keep.
+    pub fn truncate(&mut self, len: usize) {
+        self.len = self.len.min(len);
+    }
+
+    /// Removes all the elements.
+    pub fn clear(&mut self) {
+        self.len = 0;
+    }
+
+    fn slot(&mut self, index: usize) -> *mut T {
+        unsafe { (self.buffer.as_mut_ptr() as *mut T).add(index) }
+    }
+}
+
+impl<T, const N: usize> Default for Vec<T, N> {
+    fn default() -> Vec<T, N> {
+        Vec::new()
+    }
+}
+
+impl<T: Copy, const N: usize> Clone for Vec<T, N> {
+    fn clone(&self) -> Vec<T, N> {
+        let mut vec = Vec::new();
+        vec.extend_from_slice(self.as_slice()).ok();
+        vec
+    }
+}
+
+impl<T: Copy, const N: usize> Deref for Vec<T, N> {
+    type Target = [T];
+    fn deref(&self) -> &[T] {
+        self.as_slice()
+    }
+}
+
+impl<T: Copy, const N: usize> DerefMut for Vec<T, N> {
+    fn deref_mut(&mut self) -> &mut [T] {
+        self.as_mut_slice()
+    }
+}
+
+impl<T: Copy + PartialEq, const N: usize> PartialEq for Vec<T, N> {
+    fn eq(&self, other: &Vec<T, N>) -> bool {
+        self.as_slice() == other.as_slice()
+    }
+}
+
+impl<T: Copy + fmt::Debug, const N: usize> fmt::Debug for Vec<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.as_slice().fmt(f)
+    }
+}
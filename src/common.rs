@@ -0,0 +1,374 @@
+// RustDuino : A generic HAL implementation for Arduino Boards in Rust
+// Copyright (C) 2021 Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Operations which every chip gives in the same way, so the drivers of the sensors and
+//! displays can be written once against these traits instead of once per chip.
+//! Each chip implements `GpioOps` for its `Pin`, and with the `com` feature `UsartOps`,
+//! `TwiOps` and `SpiOps` for its USART, `Twi` and `Spi`, by calling its own functions.
+//! The traits only hold what a driver needs, the setup of each peripheral stays with
+//! the chip, as the USART and SPI modes differ between the chips. The buses also have
+//! provided hooks to take them and set them up for a device, which do nothing by default
+//! and are used by the handles of `bus`.
+//! # Example
+//! ```ignore
+//! use rustduino::common::TwiOps;
+//! use rustduino::error::Error;
+//!
+//! fn who_am_i<T: TwiOps>(bus: &mut T) -> Result<u8, Error> {
+//!     let mut id = [0];
+//!     bus.read_registers(0x68, 0x75, &mut id)?;
+//!     Ok(id[0])
+//! }
+//! ```
+
+// Source codes required.
+use crate::error::Error;
+
+/// Digital pin used as input or output.
+pub trait GpioOps {
+    /// Sets the pin as output.
+    fn set_output(&mut self);
+
+    /// Sets the pin as input.
+    fn set_input(&mut self);
+
+    /// Drives the pin high.
+    fn high(&mut self);
+
+    /// Drives the pin low.
+    fn low(&mut self);
+
+    /// Changes the level driven on the pin.
+    fn toggle(&mut self);
+
+    /// Checks whether the level on the pin is high.
+    fn is_high(&self) -> bool;
+
+    /// Drives the pin to the given level.
+    /// # Arguments
+    /// * `high` - a boolean, true to drive the pin high.
+    fn set_level(&mut self, high: bool) {
+        if high {
+            self.high();
+        } else {
+            self.low();
+        }
+    }
+}
+
+/// Serial port sending and receiving bytes, once it is initialized.
+pub trait UsartOps {
+    /// Waits for the transmit buffer to be empty and sends a byte.
+    fn write_byte(&mut self, byte: u8);
+
+    /// Checks whether a received byte is waiting to be read.
+    fn available(&mut self) -> bool;
+
+    /// Reads a received byte without waiting.
+    /// # Returns
+    /// * `a Option<u8>` - the byte, or None if no byte was received.
+    fn read_byte(&mut self) -> Option<u8>;
+
+    /// Sends all the bytes of a slice.
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.write_byte(*byte);
+        }
+    }
+
+    /// Waits until the bytes written have left the port. By default nothing is waited
+    /// for, which suits the ports sending each byte before `write_byte` returns.
+    fn flush(&mut self) {}
+}
+
+/// I2C master, with the steps of a transfer and the register access of most devices.
+/// The functions return `Error::Nack` when the slave does not acknowledge.
+pub trait TwiOps {
+    /// Sends the start condition.
+    fn start(&mut self) -> Result<(), Error>;
+
+    /// Sends a repeated start condition, keeping the bus.
+    fn rep_start(&mut self) -> Result<(), Error>;
+
+    /// Sends the stop condition.
+    fn stop(&mut self);
+
+    /// Sends the seven bit address of the slave for writing.
+    fn address_write(&mut self, address: u8) -> Result<(), Error>;
+
+    /// Sends the seven bit address of the slave for reading.
+    fn address_read(&mut self, address: u8) -> Result<(), Error>;
+
+    /// Writes a byte.
+    fn write(&mut self, data: u8) -> Result<(), Error>;
+
+    /// Reads a byte, acknowledging it if more bytes are to be read.
+    /// # Returns
+    /// * `a Result<u8, Error>` - the byte, or the error of the transfer.
+    fn read_byte(&mut self, ack: bool) -> Result<u8, Error>;
+
+    /// Writes a register of a device, as its address followed by the value.
+    /// # Arguments
+    /// * `address` - a u8, the seven bit address of the slave.
+    /// * `register` - a u8, the address of the register in the device.
+    /// * `value` - a u8, the value to be written.
+    /// # Returns
+    /// * `a Result<(), Error>` - which is an error if the device did not acknowledge every byte.
+    fn write_register(&mut self, address: u8, register: u8, value: u8) -> Result<(), Error> {
+        let result = self.start().and_then(|_| {
+            self.address_write(address)?;
+            self.write(register)?;
+            self.write(value)
+        });
+        self.stop();
+        result
+    }
+
+    /// Reads consecutive registers of a device, starting from `register`.
+    /// # Arguments
+    /// * `address` - a u8, the seven bit address of the slave.
+    /// * `register` - a u8, the address of the first register in the device.
+    /// * `buffer` - a mutable slice of u8, which will be filled with the registers read.
+    /// # Returns
+    /// * `a Result<(), Error>` - which is an error if the transfer failed.
+    fn read_registers(
+        &mut self,
+        address: u8,
+        register: u8,
+        buffer: &mut [u8],
+    ) -> Result<(), Error> {
+        let mut result = self.start().and_then(|_| {
+            self.address_write(address)?;
+            self.write(register)?;
+            self.rep_start()?;
+            self.address_read(address)
+        });
+        let length = buffer.len();
+        for (i, byte) in buffer.iter_mut().enumerate() {
+            if result.is_err() {
+                break;
+            }
+            match self.read_byte(i + 1 < length) {
+                Ok(value) => *byte = value,
+                Err(error) => result = Err(error),
+            }
+        }
+        self.stop();
+        result
+    }
+
+    /// Takes the bus before the transfers of a device, see `bus::I2cDevice`.
+    /// # Returns
+    /// * `a Result<(), Error>` - which is `Error::Busy` if the bus is already taken.
+    fn acquire(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Gives the bus back after the transfers of a device.
+    fn release(&mut self) {}
+
+    /// Sets the SCL frequency for the next transfers. By default the frequency is kept.
+    /// # Arguments
+    /// * `frequency` - a u32, the SCL frequency in Hz.
+    /// # Returns
+    /// * `a Result<(), Error>` - which is an error if the frequency cannot be reached.
+    fn set_frequency(&mut self, _frequency: u32) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// SPI master, once its mode and clock are set.
+pub trait SpiOps {
+    /// Sends a byte and receives a byte at the same time.
+    fn transfer(&mut self, data: u8) -> u8;
+
+    /// Sends all the bytes of the slice, replacing each with the byte received.
+    fn transfer_in_place(&mut self, data: &mut [u8]) {
+        for byte in data.iter_mut() {
+            *byte = self.transfer(*byte);
+        }
+    }
+
+    /// Sends all the bytes of the slice, ignoring the bytes received.
+    fn write(&mut self, data: &[u8]) {
+        for byte in data {
+            self.transfer(*byte);
+        }
+    }
+
+    /// Fills the buffer with bytes received from the device, sending 0xFF for each.
+    fn read(&mut self, buffer: &mut [u8]) {
+        for byte in buffer.iter_mut() {
+            *byte = self.transfer(0xFF);
+        }
+    }
+
+    /// Takes the bus before the transfers of a device, see `bus::SpiDevice`.
+    /// # Returns
+    /// * `a Result<(), Error>` - which is `Error::Busy` if the bus is already taken.
+    fn acquire(&mut self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    /// Gives the bus back after the transfers of a device.
+    fn release(&mut self) {}
+
+    /// Sets the bus up for the next transfers. By default the setup is kept.
+    /// # Arguments
+    /// * `mode` - a u8, the SPI mode from 0 to 3, setting the clock polarity and phase.
+    /// * `lsb_first` - a boolean, true to send the least significant bit first.
+    /// * `frequency` - a u32, the highest SCK frequency in Hz.
+    fn configure(&mut self, _mode: u8, _lsb_first: bool, _frequency: u32) {}
+}
+
+// A borrowed pin, bus or port works as the pin, bus or port itself, so that a driver
+// can be given a `&mut` to a pin or bus which is kept by the caller.
+impl<T: GpioOps + ?Sized> GpioOps for &mut T {
+    fn set_output(&mut self) {
+        (**self).set_output()
+    }
+
+    fn set_input(&mut self) {
+        (**self).set_input()
+    }
+
+    fn high(&mut self) {
+        (**self).high()
+    }
+
+    fn low(&mut self) {
+        (**self).low()
+    }
+
+    fn toggle(&mut self) {
+        (**self).toggle()
+    }
+
+    fn is_high(&self) -> bool {
+        (**self).is_high()
+    }
+
+    fn set_level(&mut self, high: bool) {
+        (**self).set_level(high)
+    }
+}
+
+impl<T: UsartOps + ?Sized> UsartOps for &mut T {
+    fn write_byte(&mut self, byte: u8) {
+        (**self).write_byte(byte)
+    }
+
+    fn available(&mut self) -> bool {
+        (**self).available()
+    }
+
+    fn read_byte(&mut self) -> Option<u8> {
+        (**self).read_byte()
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        (**self).write_bytes(bytes)
+    }
+
+    fn flush(&mut self) {
+        (**self).flush()
+    }
+}
+
+impl<T: TwiOps + ?Sized> TwiOps for &mut T {
+    fn start(&mut self) -> Result<(), Error> {
+        (**self).start()
+    }
+
+    fn rep_start(&mut self) -> Result<(), Error> {
+        (**self).rep_start()
+    }
+
+    fn stop(&mut self) {
+        (**self).stop()
+    }
+
+    fn address_write(&mut self, address: u8) -> Result<(), Error> {
+        (**self).address_write(address)
+    }
+
+    fn address_read(&mut self, address: u8) -> Result<(), Error> {
+        (**self).address_read(address)
+    }
+
+    fn write(&mut self, data: u8) -> Result<(), Error> {
+        (**self).write(data)
+    }
+
+    fn read_byte(&mut self, ack: bool) -> Result<u8, Error> {
+        (**self).read_byte(ack)
+    }
+
+    fn write_register(&mut self, address: u8, register: u8, value: u8) -> Result<(), Error> {
+        (**self).write_register(address, register, value)
+    }
+
+    fn read_registers(
+        &mut self,
+        address: u8,
+        register: u8,
+        buffer: &mut [u8],
+    ) -> Result<(), Error> {
+        (**self).read_registers(address, register, buffer)
+    }
+
+    fn acquire(&mut self) -> Result<(), Error> {
+        (**self).acquire()
+    }
+
+    fn release(&mut self) {
+        (**self).release()
+    }
+
+    fn set_frequency(&mut self, frequency: u32) -> Result<(), Error> {
+        (**self).set_frequency(frequency)
+    }
+}
+
+impl<T: SpiOps + ?Sized> SpiOps for &mut T {
+    fn transfer(&mut self, data: u8) -> u8 {
+        (**self).transfer(data)
+    }
+
+    fn transfer_in_place(&mut self, data: &mut [u8]) {
+        (**self).transfer_in_place(data)
+    }
+
+    fn write(&mut self, data: &[u8]) {
+        (**self).write(data)
+    }
+
+    fn read(&mut self, buffer: &mut [u8]) {
+        (**self).read(buffer)
+    }
+
+    fn acquire(&mut self) -> Result<(), Error> {
+        (**self).acquire()
+    }
+
+    fn release(&mut self) {
+        (**self).release()
+    }
+
+    fn configure(&mut self, mode: u8, lsb_first: bool, frequency: u32) {
+        (**self).configure(mode, lsb_first, frequency)
+    }
+}
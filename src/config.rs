@@ -44,10 +44,60 @@ const CPU_FREQUENCY_HZ_IMPL: u32 = value_from_env!("AVR_CPU_FREQUENCY_HZ": u32);
 #[cfg(not(target_arch = "avr"))]
 const CPU_FREQUENCY_HZ_IMPL: u32 = DEFAULT_CPU_FREQUENCY_WHEN_NOT_AVR_HZ;
 
+/// Size of the heap of the `alloc` feature, in bytes.
+///
+/// This value is derived from the `$AVR_HEAP_SIZE` environment variable at compilation
+/// time, and is 1024 bytes when it is not set. The heap is a static variable, so it is
+/// counted in the RAM used by the program.
+#[cfg(feature = "alloc")]
+pub const HEAP_SIZE: usize = parse_size(option_env!("AVR_HEAP_SIZE"), DEFAULT_HEAP_SIZE);
+
+/// The heap size used when `$AVR_HEAP_SIZE` is not set.
+#[cfg(feature = "alloc")]
+const DEFAULT_HEAP_SIZE: usize = 1024;
+
+/// Reads a size written in decimal, at compilation time.
+/// # Arguments
+/// * `text` - an Option of a str, the size.
+/// * `default` - a usize, the size given when there is no text.
+/// # Returns
+/// * `a usize` - the size. A text which is not a number stops the compilation.
+#[cfg(feature = "alloc")]
+const fn parse_size(text: Option<&str>, default: usize) -> usize {
+    let bytes = match text {
+        Some(text) => text.as_bytes(),
+        None => return default,
+    };
+    let mut value = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        let digit = bytes[i];
+        if !digit.is_ascii_digit() {
+            // A panic can not be used in a const fn with the toolchain of the crate, so
+            // the text is reported by reading out of an empty array.
+            return NOT_A_NUMBER[i];
+        }
+        value = value * 10 + (digit - b'0') as usize;
+        i += 1;
+    }
+    value
+}
+
+/// Read by `parse_size` when `$AVR_HEAP_SIZE` is not a number.
+#[cfg(feature = "alloc")]
+const NOT_A_NUMBER: [usize; 0] = [];
+
 #[cfg(test)]
 mod test {
     #[test]
     fn cpu_frequency_is_nonzero() {
         assert!(crate::config::CPU_FREQUENCY_HZ > 0);
     }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn heap_size_is_parsed() {
+        assert_eq!(super::parse_size(None, 1024), 1024);
+        assert_eq!(super::parse_size(Some("6144"), 1024), 6144);
+    }
 }
@@ -0,0 +1,325 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! A PID controller in fixed-point arithmetic, since the AVR chips have no floating
+//! point unit.
+//! The input, the setpoint and the output are integers in the units of the application,
+//! and the gains are fixed-point numbers with 16 fractional bits, `PID_ONE` being a gain
+//! of 1. The integral gain is per second and the derivative gain in seconds, so the
+//! gains do not change with the sample time.
+//! The derivative is taken on the input rather than on the error, so a change of the
+//! setpoint gives no kick, and the integral term is limited to the output range, so it
+//! does not wind up while the output is saturated.
+//! `compute` measures the sample time with `millis`, so `time::init` must be called.
+//! See `<http://brettbeauregard.com/blog/2011/04/improving-the-beginners-pid-introduction/>`.
+
+// Source codes required.
+use crate::time::millis;
+
+/// Number of fractional bits of the gains.
+pub const PID_FRACTION_BITS: u32 = 16;
+
+/// Gain of 1 in fixed-point.
+pub const PID_ONE: i32 = 1 << PID_FRACTION_BITS;
+
+/// Gives the fixed-point gain of a fraction, as `pid_gain(3, 2)` for 1.5.
+/// # Arguments
+/// * `numerator` - a i32, the numerator of the gain.
+/// * `denominator` - a i32, the denominator of the gain, not 0.
+/// # Returns
+/// * `a i32` - the gain with `PID_FRACTION_BITS` fractional bits.
+pub const fn pid_gain(numerator: i32, denominator: i32) -> i32 {
+    ((numerator as i64 * PID_ONE as i64) / denominator as i64) as i32
+}
+
+/// Direction of the process.
+/// * `Direct` - a larger output makes the input larger, as a heater.
+/// * `Reverse` - a larger output makes the input smaller, as a cooler.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PidDirection {
+    Direct,
+    Reverse,
+}
+
+/// Used to control a process with a PID loop.
+/// # Elements
+/// * `kp` - a i32, the proportional gain.
+/// * `ki` - a i32, the integral gain per second.
+/// * `kd` - a i32, the derivative gain in seconds.
+/// * `direction` - a `PidDirection` object, the direction of the process.
+/// * `setpoint` - a i32, the value the input should reach.
+/// * `min` - a i32, the smallest output.
+/// * `max` - a i32, the largest output.
+/// * `sample_time_ms` - a u16, the time between two computations by `compute`.
+/// * `automatic` - a bool, false while the output is set by hand.
+/// * `integral` - a i64, the integral term in output units with `PID_FRACTION_BITS`
+///   fractional bits.
+/// * `last_input` - a `Option<i32>`, the input of the last computation.
+/// * `last_time` - a u32, the time of the last computation in milliseconds.
+/// * `output` - a i32, the last output.
+pub struct Pid {
+    kp: i32,
+    ki: i32,
+    kd: i32,
+    direction: PidDirection,
+    setpoint: i32,
+    min: i32,
+    max: i32,
+    sample_time_ms: u16,
+    automatic: bool,
+    integral: i64,
+    last_input: Option<i32>,
+    last_time: u32,
+    output: i32,
+}
+
+impl Pid {
+    /// Creates a controller with an output from 0 to 255, as taken by `analog_write`,
+    /// and a sample time of 100 ms. It starts in automatic mode with a setpoint of 0.
+    /// # Arguments
+    /// * `kp` - a i32, the proportional gain, with `PID_FRACTION_BITS` fractional bits.
+    /// * `ki` - a i32, the integral gain per second, with `PID_FRACTION_BITS` fractional bits.
+    /// * `kd` - a i32, the derivative gain in seconds, with `PID_FRACTION_BITS` fractional bits.
+    /// # Returns
+    /// * `a Pid object` - which will be used to compute the output.
+    pub fn new(kp: i32, ki: i32, kd: i32) -> Pid {
+        Pid {
+            kp,
+            ki,
+            kd,
+            direction: PidDirection::Direct,
+            setpoint: 0,
+            min: 0,
+            max: 255,
+            sample_time_ms: 100,
+            automatic: true,
+            integral: 0,
+            last_input: None,
+            last_time: millis(),
+            output: 0,
+        }
+    }
+
+    /// Changes the gains, which can be done while the loop runs.
+    /// # Arguments
+    /// * `kp` - a i32, the proportional gain.
+    /// * `ki` - a i32, the integral gain per second.
+    /// * `kd` - a i32, the derivative gain in seconds.
+    pub fn set_gains(&mut self, kp: i32, ki: i32, kd: i32) {
+        self.kp = kp;
+        self.ki = ki;
+        self.kd = kd;
+    }
+
+    /// Gives the gains as (kp, ki, kd).
+    pub fn gains(&self) -> (i32, i32, i32) {
+        (self.kp, self.ki, self.kd)
+    }
+
+    /// Sets the direction of the process.
+    pub fn set_direction(&mut self, direction: PidDirection) {
+        self.direction = direction;
+    }
+
+    /// Sets the value the input should reach.
+    pub fn set_setpoint(&mut self, setpoint: i32) {
+        self.setpoint = setpoint;
+    }
+
+    /// Gives the value the input should reach.
+    pub fn setpoint(&self) -> i32 {
+        self.setpoint
+    }
+
+    /// Sets the range of the output, the integral term and the last output are brought
+    /// into it.
+    /// # Arguments
+    /// * `min` - a i32, the smallest output.
+    /// * `max` - a i32, the largest output, not smaller than `min`.
+    pub fn set_output_limits(&mut self, min: i32, max: i32) {
+        if min > max {
+            return;
+        }
+        self.min = min;
+        self.max = max;
+        self.output = self.output.clamp(min, max);
+        self.integral = self.clamp_integral(self.integral);
+    }
+
+    /// Sets the time between two computations by `compute`.
+    /// # Arguments
+    /// * `ms` - a u16, the sample time in milliseconds, at least 1.
+    pub fn set_sample_time_ms(&mut self, ms: u16) {
+        self.sample_time_ms = ms.max(1);
+    }
+
+    /// Switches between automatic mode, where `compute` sets the output, and manual mode,
+    /// where it does nothing. Going back to automatic mode starts from the present output
+    /// and input, so the output does not jump.
+    /// # Arguments
+    /// * `automatic` - a bool, true for automatic mode.
+    /// * `output` - a i32, the output set by hand, which the loop starts from.
+    /// * `input` - a i32, the present input.
+    pub fn set_automatic(&mut self, automatic: bool, output: i32, input: i32) {
+        if automatic && !self.automatic {
+            self.output = output.clamp(self.min, self.max);
+            self.integral = self.clamp_integral((self.output as i64) << PID_FRACTION_BITS);
+            self.last_input = Some(input);
+            self.last_time = millis();
+        }
+        self.automatic = automatic;
+    }
+
+    /// Checks whether the controller is in automatic mode.
+    pub fn is_automatic(&self) -> bool {
+        self.automatic
+    }
+
+    /// Gives the last output.
+    pub fn output(&self) -> i32 {
+        self.output
+    }
+
+    /// Clears the integral term and the last input, to start again from rest.
+    pub fn reset(&mut self) {
+        self.integral = 0;
+        self.last_input = None;
+        self.output = 0i32.clamp(self.min, self.max);
+        self.last_time = millis();
+    }
+
+    /// Computes a new output once the sample time has passed since the last one.
+    /// It should be called at least as often as the sample time.
+    /// # Arguments
+    /// * `input` - a i32, the present input.
+    /// # Returns
+    /// * `a Option<i32>` - the new output, None if the sample time has not passed or the
+    ///   controller is in manual mode.
+    pub fn compute(&mut self, input: i32) -> Option<i32> {
+        let now = millis();
+        let elapsed = now.wrapping_sub(self.last_time);
+        if !self.automatic || elapsed < self.sample_time_ms as u32 {
+            return None;
+        }
+        self.last_time = now;
+        Some(self.compute_with_dt(input, elapsed.min(u16::MAX as u32) as u16))
+    }
+
+    /// Computes a new output for a known time since the last computation, as when it is
+    /// called from a timer interrupt.
+    /// # Arguments
+    /// * `input` - a i32, the present input.
+    /// * `dt_ms` - a u16, the time since the last computation in milliseconds.
+    /// # Returns
+    /// * `a i32` - the new output.
+    pub fn compute_with_dt(&mut self, input: i32, dt_ms: u16) -> i32 {
+        let dt_ms = dt_ms.max(1) as i64;
+        let (kp, ki, kd) = match self.direction {
+            PidDirection::Direct => (self.kp, self.ki, self.kd),
+            PidDirection::Reverse => (-self.kp, -self.ki, -self.kd),
+        };
+        let error = self.setpoint as i64 - input as i64;
+
+        self.integral = self.clamp_integral(self.integral + ki as i64 * error * dt_ms / 1000);
+
+        let proportional = kp as i64 * error;
+        let derivative = match self.last_input {
+            Some(last) => -(kd as i64) * (input as i64 - last as i64) * 1000 / dt_ms,
+            None => 0,
+        };
+        self.last_input = Some(input);
+
+        let output = (proportional + self.integral + derivative) >> PID_FRACTION_BITS;
+        self.output = output.clamp(self.min as i64, self.max as i64) as i32;
+        self.output
+    }
+
+    fn clamp_integral(&self, integral: i64) -> i64 {
+        integral.clamp(
+            (self.min as i64) << PID_FRACTION_BITS,
+            (self.max as i64) << PID_FRACTION_BITS,
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn proportional_integral_derivative() {
+        let mut pid = Pid::new(2 * PID_ONE, 0, 0);
+        pid.set_setpoint(100);
+        assert_eq!(pid.compute_with_dt(90, 100), 20);
+
+        // 10 for half a second with a gain of 1 per second.
+        let mut pid = Pid::new(0, PID_ONE, 0);
+        pid.set_setpoint(100);
+        assert_eq!(pid.compute_with_dt(90, 500), 5);
+        assert_eq!(pid.compute_with_dt(90, 500), 10);
+
+        // The input rising by 10 in 100 ms with a gain of 0.1 second.
+        let mut pid = Pid::new(0, 0, pid_gain(1, 10));
+        pid.set_output_limits(-255, 255);
+        pid.set_setpoint(100);
+        assert_eq!(pid.compute_with_dt(0, 100), 0);
+        assert_eq!(pid.compute_with_dt(10, 100), -10);
+        // A change of the setpoint gives no kick.
+        pid.set_setpoint(0);
+        assert_eq!(pid.compute_with_dt(10, 100), 0);
+    }
+
+    #[test]
+    fn integral_does_not_wind_up() {
+        let mut pid = Pid::new(0, 10 * PID_ONE, 0);
+        pid.set_setpoint(100);
+        for _ in 0..100 {
+            assert!(pid.compute_with_dt(0, 100) <= 255);
+        }
+        assert_eq!(pid.output(), 255);
+        // The output leaves the limit as soon as the error changes sign.
+        assert_eq!(pid.compute_with_dt(110, 100), 245);
+
+        pid.set_output_limits(0, 100);
+        assert_eq!(pid.output(), 100);
+        assert_eq!(pid.compute_with_dt(110, 100), 90);
+    }
+
+    #[test]
+    fn reverse_direction() {
+        let mut pid = Pid::new(PID_ONE, 0, 0);
+        pid.set_direction(PidDirection::Reverse);
+        pid.set_setpoint(20);
+        assert_eq!(pid.compute_with_dt(30, 100), 10);
+        assert_eq!(pid.compute_with_dt(10, 100), 0);
+    }
+
+    #[test]
+    fn bumpless_switch_to_automatic() {
+        let mut pid = Pid::new(2 * PID_ONE, PID_ONE, PID_ONE);
+        pid.set_setpoint(50);
+        pid.set_automatic(false, 0, 0);
+        assert!(!pid.is_automatic());
+        assert_eq!(pid.compute(80), None);
+
+        // The loop starts from the output set by hand, with no kick from the derivative.
+        pid.set_automatic(true, 120, 50);
+        assert!(pid.is_automatic());
+        assert_eq!(pid.output(), 120);
+        assert_eq!(pid.compute_with_dt(50, 100), 120);
+        assert_eq!(pid.compute_with_dt(50, 100), 120);
+    }
+}
@@ -17,10 +17,24 @@
 //! This module contains the delay functions which would be used in
 //! various places in the library for pausing the program for the
 //! given amount of time.
+//! The delays are computed from `crate::config::CPU_FREQUENCY_HZ` and follow the
+//! changes of the system clock made with `crate::clock::Prescalar::enable_clock`.
+//! They are counted in passes of a 4 cycle loop, so they are accurate to a few cycles,
+//! and they are lengthened by the interrupts served meanwhile.
+
+// Source codes required.
+use crate::error::Error;
+
+/// Cycles taken by each pass of the busy-wait loop of `delay`.
+const CYCLES_PER_LOOP: u32 = 4;
+
+/// Cycles of the start-up clock in a microsecond, times 256.
+const CYCLES_PER_US_X256: u32 = (crate::config::CPU_FREQUENCY_HZ as u64 * 256 / 1_000_000) as u32;
 
 /// Internal function to implement a variable busy-wait loop.
 /// # Arguments
 /// * `count` - an i32, the number of times to cycle the loop.
+#[cfg(target_arch = "avr")]
 #[inline(always)]
 pub fn delay(count: u32) {
     // Our asm busy-wait takes a 16 bit word as an argument,
@@ -48,14 +62,29 @@ pub fn delay(count: u32) {
     }
 }
 
+// Off the AVR, as when the drivers are tested with the mock backend, the delays end at once.
+#[cfg(not(target_arch = "avr"))]
+#[inline(always)]
+pub fn delay(_count: u32) {}
+
+/// Busy-waits for a number of cycles of the system clock.
+/// # Arguments
+/// * `cycles` - an u32, the number of cycles, rounded down to a multiple of 4.
+#[inline(always)]
+pub fn delay_cycles(cycles: u32) {
+    let loops = cycles / CYCLES_PER_LOOP;
+    if loops > 0 {
+        // `delay` makes one more pass than its count.
+        delay(loops - 1);
+    }
+}
+
 ///delay for N seconds
 /// # Arguments
 /// * `s` - an u32, number of seconds to busy-wait
 #[inline(always)]
 pub fn delay_s(s: u32) {
-    // microseconds
-    let ms = s * 1000;
-    delay_ms(ms);
+    delay_ms(s * 1000);
 }
 
 ///delay for N miliseconds
@@ -63,9 +92,10 @@ pub fn delay_s(s: u32) {
 /// * `ms` - an u32, number of milliseconds to busy-wait
 #[inline(always)]
 pub fn delay_ms(ms: u32) {
-    // microseconds
-    let us = ms * 1000;
-    delay_us(us);
+    // One millisecond at a time, so that long delays do not overflow.
+    for _ in 0..ms {
+        delay_us(1000);
+    }
 }
 
 ///delay for N microseconds
@@ -73,9 +103,61 @@ pub fn delay_ms(ms: u32) {
 /// * `us` - an u32, number of microseconds to busy-wait
 #[inline(always)]
 pub fn delay_us(us: u32) {
-    // nanoseconds
-    let ns = us * 1000;
-    let ns_lp = 1000000000 / (crate::config::CPU_FREQUENCY_HZ / 4);
-    let loops = (ns / ns_lp) as u32;
-    delay(loops);
+    // Blocks of 65536 microseconds first, so that the number of cycles does not overflow.
+    for _ in 0..us >> 16 {
+        delay_cycles(scale((65536 * CYCLES_PER_US_X256) >> 8));
+    }
+    delay_cycles(scale(((us & 0xFFFF) * CYCLES_PER_US_X256) >> 8));
+}
+
+/// Polls a condition every 10 microseconds until it holds or the time runs out.
+/// # Arguments
+/// * `timeout_us` - an u32, the longest time to wait in microseconds.
+/// * `ready` - a closure, returning true once the wait is over.
+/// # Returns
+/// * `a Result<(), Error>` - which is `Error::Timeout` if the condition never held.
+pub fn wait_until<F: FnMut() -> bool>(timeout_us: u32, mut ready: F) -> Result<(), Error> {
+    let mut waited = 0;
+    while !ready() {
+        if waited >= timeout_us {
+            return Err(Error::Timeout);
+        }
+        delay_us(10);
+        waited += 10;
+    }
+    Ok(())
+}
+
+// Converts cycles of the start-up clock to cycles of the present system clock.
+#[inline(always)]
+fn scale(cycles: u32) -> u32 {
+    let shift = clock_shift();
+    if shift >= 0 {
+        cycles >> shift
+    } else {
+        cycles << -shift
+    }
+}
+
+#[cfg(any(
+    feature = "atmega2560p",
+    feature = "atmega328p",
+    feature = "atmega32u4",
+    feature = "attiny85"
+))]
+#[inline(always)]
+fn clock_shift() -> i8 {
+    crate::clock::clock_shift()
+}
+
+// Without a chip the prescaler cannot be changed.
+#[cfg(not(any(
+    feature = "atmega2560p",
+    feature = "atmega328p",
+    feature = "atmega32u4",
+    feature = "attiny85"
+)))]
+#[inline(always)]
+fn clock_shift() -> i8 {
+    0
 }
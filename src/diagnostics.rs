@@ -0,0 +1,191 @@
+// RustDuino : A generic HAL implementation for Arduino Boards in Rust
+// Copyright (C) 2021 Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Memory diagnostics, enabled with the `diagnostics` feature, to find out how close
+//! a program comes to running out of RAM, which on the 2 kB parts shows as random
+//! crashes when the stack grows into the static variables.
+//! The RAM holds the `.data` and `.bss` sections from its start, up to `__heap_start`,
+//! and the stack grows down from its end. Before `main`, in the `.init1` section, the
+//! free RAM between them is painted with `STACK_PAINT`, so the lowest address the stack
+//! has reached is the first byte above `__heap_start` which is not painted any more.
+//! The bytes just above `__heap_start` are a canary, which can be checked from the
+//! interrupt of the system tick with `watch_canary`.
+
+// Source codes required.
+use core::ptr::read_volatile;
+
+/// Byte the free RAM is painted with at start up.
+pub const STACK_PAINT: u8 = 0xC5;
+
+/// Number of bytes at the bottom of the free RAM checked as a canary.
+pub const CANARY_SIZE: usize = 4;
+
+/// First and last addresses of the RAM.
+#[cfg(feature = "atmega328p")]
+pub const RAM_START: usize = 0x0100;
+#[cfg(feature = "atmega328p")]
+pub const RAM_END: usize = 0x08FF;
+#[cfg(feature = "atmega2560p")]
+pub const RAM_START: usize = 0x0200;
+#[cfg(feature = "atmega2560p")]
+pub const RAM_END: usize = 0x21FF;
+#[cfg(feature = "atmega32u4")]
+pub const RAM_START: usize = 0x0100;
+#[cfg(feature = "atmega32u4")]
+pub const RAM_END: usize = 0x0AFF;
+#[cfg(feature = "attiny85")]
+pub const RAM_START: usize = 0x0060;
+#[cfg(feature = "attiny85")]
+pub const RAM_END: usize = 0x025F;
+
+// Address of the stack pointer, SPH:SPL, in the data space.
+const SP: *const u16 = 0x5D as *const u16;
+
+// Handler called once when the canary is found overwritten.
+#[cfg(any(feature = "atmega2560p", feature = "atmega328p"))]
+static mut CANARY_HANDLER: Option<fn()> = None;
+
+extern "C" {
+    // End of the static variables, given by the linker script.
+    static __heap_start: u8;
+}
+
+// Paints the RAM from `__heap_start` up to the top of the stack. It runs before the
+// stack pointer is set and r1 cleared, so it may use any register.
+global_asm!(
+    r#"
+    .section .init1,"ax",@progbits
+    ldi r30, lo8(__heap_start)
+    ldi r31, hi8(__heap_start)
+    ldi r24, 0xC5
+    ldi r25, hi8(__stack)
+1:
+    st Z+, r24
+    cpi r30, lo8(__stack)
+    cpc r31, r25
+    brlo 1b
+    .text
+"#
+);
+
+/// Gives the size of the RAM.
+/// # Returns
+/// * `a usize` - the number of bytes.
+pub fn ram_size() -> usize {
+    RAM_END + 1 - RAM_START
+}
+
+/// Gives the address of the end of the static variables, where the free RAM starts.
+/// # Returns
+/// * `a usize` - the address.
+pub fn heap_start() -> usize {
+    unsafe { &__heap_start as *const u8 as usize }
+}
+
+/// Gives the stack pointer, the address below the last byte pushed on the stack.
+/// # Returns
+/// * `a usize` - the address.
+pub fn stack_pointer() -> usize {
+    unsafe { read_volatile(SP) as usize }
+}
+
+/// Gives the RAM free between the static variables and the stack at this point.
+/// # Returns
+/// * `a usize` - the number of bytes.
+pub fn free_ram() -> usize {
+    stack_pointer().saturating_sub(heap_start())
+}
+
+/// Gives the RAM taken by the static variables.
+/// # Returns
+/// * `a usize` - the number of bytes.
+pub fn static_ram() -> usize {
+    heap_start() - RAM_START
+}
+
+/// Gives the RAM taken by the stack at this point.
+/// # Returns
+/// * `a usize` - the number of bytes.
+pub fn stack_used() -> usize {
+    RAM_END - stack_pointer()
+}
+
+/// Gives the most RAM the stack has taken since start up, found from the paint
+/// it has overwritten. A byte pushed with the value of the paint is not seen, so
+/// the mark may be a few bytes too low.
+/// # Returns
+/// * `a usize` - the number of bytes.
+pub fn stack_high_water() -> usize {
+    RAM_END + 1 - lowest_written()
+}
+
+/// Gives the free RAM which the stack has never reached since start up.
+/// # Returns
+/// * `a usize` - the number of bytes.
+pub fn never_used_ram() -> usize {
+    lowest_written() - heap_start()
+}
+
+/// Checks whether the canary at the bottom of the free RAM is still painted.
+/// # Returns
+/// * `a boolean` - which is false if the stack has reached the static variables.
+pub fn canary_intact() -> bool {
+    let start = heap_start();
+    (start..start + CANARY_SIZE)
+        .all(|address| unsafe { read_volatile(address as *const u8) } == STACK_PAINT)
+}
+
+/// Checks the canary on every overflow of the system tick, about once per millisecond,
+/// and calls the handler once from the interrupt if it is overwritten. The handler
+/// usually records the fault and resets the chip, as the static variables are corrupted.
+/// `crate::time::init` must have been called, and the overflow hook of `crate::time`
+/// is taken.
+/// # Arguments
+/// * `handler` - a function, which is called from the interrupt service routine.
+#[cfg(any(feature = "atmega2560p", feature = "atmega328p"))]
+pub fn watch_canary(handler: fn()) {
+    unsafe {
+        CANARY_HANDLER = Some(handler);
+    }
+    crate::time::set_overflow_hook(Some(check_canary));
+}
+
+/// Stops checking the canary from the system tick.
+#[cfg(any(feature = "atmega2560p", feature = "atmega328p"))]
+pub fn stop_watching_canary() {
+    crate::time::set_overflow_hook(None);
+}
+
+// Checks the canary from the interrupt of the system tick.
+#[cfg(any(feature = "atmega2560p", feature = "atmega328p"))]
+fn check_canary() {
+    if !canary_intact() {
+        crate::time::set_overflow_hook(None);
+        if let Some(handler) = unsafe { CANARY_HANDLER } {
+            handler();
+        }
+    }
+}
+
+// Gives the lowest address of the free RAM which is not painted any more.
+fn lowest_written() -> usize {
+    let end = stack_pointer();
+    let mut address = heap_start();
+    while address < end && unsafe { read_volatile(address as *const u8) } == STACK_PAINT {
+        address += 1;
+    }
+    address
+}
@@ -0,0 +1,358 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! This code controls the character LCDs with the HD44780 controller, such as the 16x2 and
+//! 20x4 displays, in the 4 bit mode.
+//! The display is either wired directly to six digital pins (RS, E and D4 to D7, with RW
+//! tied to ground), or through the common I2C backpack with a PCF8574 port expander,
+//! whose pins P0 to P7 are RS, RW, E, the backlight and D4 to D7.
+//! Every byte is sent as two nibbles, high nibble first, and the busy flag is never read,
+//! so each command is followed by the longest time it can take.
+//! See `<https://www.sparkfun.com/datasheets/LCD/HD44780.pdf>`.
+
+// Source codes required.
+use crate::bus::{I2cDevice, SharedTwi};
+use crate::common::TwiOps;
+use crate::delay::{delay_ms, delay_us};
+use crate::hal::pin::make_pin;
+
+// Crates which would be used in the implementation.
+use core::fmt;
+
+/// I2C address of most PCF8574 backpacks, 0x3F for the PCF8574A.
+pub const HD44780_I2C_ADDRESS: u8 = 0x27;
+
+// Commands of the HD44780.
+const CLEAR_DISPLAY: u8 = 0x01;
+const RETURN_HOME: u8 = 0x02;
+const ENTRY_MODE_SET: u8 = 0x04;
+const DISPLAY_CONTROL: u8 = 0x08;
+const CURSOR_SHIFT: u8 = 0x10;
+const FUNCTION_SET: u8 = 0x20;
+const SET_CGRAM_ADDRESS: u8 = 0x40;
+const SET_DDRAM_ADDRESS: u8 = 0x80;
+
+// Flags of the entry mode.
+const ENTRY_LEFT_TO_RIGHT: u8 = 0x02;
+const ENTRY_AUTOSCROLL: u8 = 0x01;
+
+// Flags of the display control.
+const DISPLAY_ON: u8 = 0x04;
+const CURSOR_ON: u8 = 0x02;
+const BLINK_ON: u8 = 0x01;
+
+// Flags of the cursor shift and the function set.
+const SHIFT_DISPLAY: u8 = 0x08;
+const SHIFT_RIGHT: u8 = 0x04;
+const TWO_LINES: u8 = 0x08;
+
+// Bits of the PCF8574 port on the backpack.
+const PCF_RS: u8 = 0x01;
+const PCF_EN: u8 = 0x04;
+const PCF_BACKLIGHT: u8 = 0x08;
+
+// SCL frequency of the PCF8574.
+const I2C_FREQUENCY: u32 = 100_000;
+
+/// Connection between the microcontroller and the display.
+enum Interface<T> {
+    Parallel { rs: u8, enable: u8, data: [u8; 4] },
+    I2c { device: I2cDevice<T>, backlight: u8 },
+}
+
+/// Used to control a HD44780 character LCD.
+/// # Elements
+/// * `interface` - a `Interface` object, the pins or the I2C backpack.
+/// * `columns` - a u8, the number of characters on a line.
+/// * `rows` - a u8, the number of lines.
+/// * `control` - a u8, the flags of the display control command.
+/// * `entry` - a u8, the flags of the entry mode command.
+/// * `row` - a u8, the line of the cursor, used to move to the next line.
+pub struct HD44780<T = SharedTwi> {
+    interface: Interface<T>,
+    columns: u8,
+    rows: u8,
+    control: u8,
+    entry: u8,
+    row: u8,
+}
+
+impl HD44780 {
+    /// Creates a display wired to six digital pins and initializes it.
+    /// # Arguments
+    /// * `rs` - a u8, the digital pin connected to RS.
+    /// * `enable` - a u8, the digital pin connected to E.
+    /// * `data` - an array of 4 u8, the digital pins connected to D4, D5, D6 and D7.
+    /// * `columns` - a u8, the number of characters on a line.
+    /// * `rows` - a u8, the number of lines.
+    /// # Returns
+    /// * `a HD44780 object` - which will be used to write on the display.
+    pub fn new_parallel(rs: u8, enable: u8, data: [u8; 4], columns: u8, rows: u8) -> HD44780 {
+        for pin in [rs, enable].iter().chain(data.iter()) {
+            let mut pin = make_pin(*pin as _);
+            pin.set_output();
+            pin.low();
+        }
+        HD44780::init(Interface::Parallel { rs, enable, data }, columns, rows)
+    }
+
+    /// Creates a display behind a PCF8574 backpack on the TWI of the chip and initializes
+    /// it, with the backlight on.
+    /// # Arguments
+    /// * `address` - a u8, the I2C address of the backpack, normally `HD44780_I2C_ADDRESS`.
+    /// * `columns` - a u8, the number of characters on a line.
+    /// * `rows` - a u8, the number of lines.
+    /// # Returns
+    /// * `a HD44780 object` - which will be used to write on the display.
+    pub fn new_i2c(address: u8, columns: u8, rows: u8) -> HD44780 {
+        HD44780::with_i2c(SharedTwi::new(), address, columns, rows)
+    }
+}
+
+impl<T: TwiOps> HD44780<T> {
+    /// Creates a display behind a PCF8574 backpack on the given I2C bus and initializes
+    /// it, with the backlight on.
+    /// # Arguments
+    /// * `bus` - a `TwiOps` object, the I2C bus of the backpack.
+    /// * `address` - a u8, the I2C address of the backpack, normally `HD44780_I2C_ADDRESS`.
+    /// * `columns` - a u8, the number of characters on a line.
+    /// * `rows` - a u8, the number of lines.
+    /// # Returns
+    /// * `a HD44780 object` - which will be used to write on the display.
+    pub fn with_i2c(bus: T, address: u8, columns: u8, rows: u8) -> HD44780<T> {
+        HD44780::init(
+            Interface::I2c {
+                device: I2cDevice::with_bus(bus, address, I2C_FREQUENCY),
+                backlight: PCF_BACKLIGHT,
+            },
+            columns,
+            rows,
+        )
+    }
+
+    /// Puts the controller into the 4 bit mode by the sequence of the datasheet,
+    /// which works whatever state the controller was left in.
+    fn init(interface: Interface<T>, columns: u8, rows: u8) -> HD44780<T> {
+        let mut lcd = HD44780 {
+            interface,
+            columns,
+            rows,
+            control: DISPLAY_ON,
+            entry: ENTRY_LEFT_TO_RIGHT,
+            row: 0,
+        };
+        // The controller needs 40 ms after the supply rises.
+        delay_ms(50);
+        lcd.write_nibble(0x03, false);
+        delay_us(4500);
+        lcd.write_nibble(0x03, false);
+        delay_us(4500);
+        lcd.write_nibble(0x03, false);
+        delay_us(150);
+        lcd.write_nibble(0x02, false);
+
+        let lines = if rows > 1 { TWO_LINES } else { 0 };
+        lcd.command(FUNCTION_SET | lines);
+        lcd.command(DISPLAY_CONTROL | lcd.control);
+        lcd.clear();
+        lcd.command(ENTRY_MODE_SET | lcd.entry);
+        lcd
+    }
+
+    /// Sends four bits to the controller and pulses E so that it takes them.
+    fn write_nibble(&mut self, nibble: u8, rs: bool) {
+        match &mut self.interface {
+            Interface::Parallel {
+                rs: rs_pin,
+                enable,
+                data,
+            } => {
+                let mut rs_pin = make_pin(*rs_pin as _);
+                if rs {
+                    rs_pin.high();
+                } else {
+                    rs_pin.low();
+                }
+                for (i, pin) in data.iter().enumerate() {
+                    let mut pin = make_pin(*pin as _);
+                    if nibble & (1 << i) != 0 {
+                        pin.high();
+                    } else {
+                        pin.low();
+                    }
+                }
+                let mut enable = make_pin(*enable as _);
+                enable.high();
+                delay_us(1);
+                enable.low();
+            }
+            Interface::I2c { device, backlight } => {
+                let value = (nibble << 4) | *backlight | if rs { PCF_RS } else { 0 };
+                let _ = device.write(&[value | PCF_EN, value]);
+            }
+        }
+        // Most commands take 37 us.
+        delay_us(50);
+    }
+
+    /// Sends a byte as two nibbles.
+    fn write_byte(&mut self, byte: u8, rs: bool) {
+        self.write_nibble(byte >> 4, rs);
+        self.write_nibble(byte & 0x0F, rs);
+    }
+
+    /// Sends a command to the controller.
+    /// # Arguments
+    /// * `command` - a u8, the command with its flags.
+    pub fn command(&mut self, command: u8) {
+        self.write_byte(command, false);
+    }
+
+    /// Writes a character at the cursor, which then moves on.
+    /// # Arguments
+    /// * `c` - a u8, the code of the character, 0 to 7 for the custom characters.
+    pub fn write_char(&mut self, c: u8) {
+        self.write_byte(c, true);
+    }
+
+    /// Clears the display and moves the cursor to the top left corner.
+    pub fn clear(&mut self) {
+        self.command(CLEAR_DISPLAY);
+        self.row = 0;
+        delay_ms(2);
+    }
+
+    /// Moves the cursor to the top left corner and undoes any scrolling.
+    pub fn home(&mut self) {
+        self.command(RETURN_HOME);
+        self.row = 0;
+        delay_ms(2);
+    }
+
+    /// Moves the cursor, positions outside the display are brought inside.
+    /// # Arguments
+    /// * `column` - a u8, the column from 0.
+    /// * `row` - a u8, the line from 0.
+    pub fn set_cursor(&mut self, column: u8, row: u8) {
+        // Lines 2 and 3 of a four line display continue lines 0 and 1.
+        let offsets = [0x00, 0x40, self.columns, 0x40 + self.columns];
+        let row = if row >= self.rows { self.rows - 1 } else { row };
+        let row = if row > 3 { 3 } else { row };
+        self.row = row;
+        self.command(SET_DDRAM_ADDRESS | (column + offsets[row as usize]));
+    }
+
+    /// Sets or clears a flag of the display control command.
+    fn set_control(&mut self, flag: u8, on: bool) {
+        if on {
+            self.control |= flag;
+        } else {
+            self.control &= !flag;
+        }
+        self.command(DISPLAY_CONTROL | self.control);
+    }
+
+    /// Switches the display on or off, the text is kept while it is off.
+    /// # Arguments
+    /// * `on` - a boolean, true to show the text.
+    pub fn display(&mut self, on: bool) {
+        self.set_control(DISPLAY_ON, on);
+    }
+
+    /// Shows or hides the underline cursor.
+    /// # Arguments
+    /// * `on` - a boolean, true to show the cursor.
+    pub fn cursor(&mut self, on: bool) {
+        self.set_control(CURSOR_ON, on);
+    }
+
+    /// Switches the blinking block cursor on or off.
+    /// # Arguments
+    /// * `on` - a boolean, true to blink.
+    pub fn blink(&mut self, on: bool) {
+        self.set_control(BLINK_ON, on);
+    }
+
+    /// Scrolls the whole display one character to the left.
+    pub fn scroll_left(&mut self) {
+        self.command(CURSOR_SHIFT | SHIFT_DISPLAY);
+    }
+
+    /// Scrolls the whole display one character to the right.
+    pub fn scroll_right(&mut self) {
+        self.command(CURSOR_SHIFT | SHIFT_DISPLAY | SHIFT_RIGHT);
+    }
+
+    /// Chooses the direction in which the cursor moves after a character.
+    /// # Arguments
+    /// * `left_to_right` - a boolean, true for left to right text.
+    pub fn left_to_right(&mut self, left_to_right: bool) {
+        if left_to_right {
+            self.entry |= ENTRY_LEFT_TO_RIGHT;
+        } else {
+            self.entry &= !ENTRY_LEFT_TO_RIGHT;
+        }
+        self.command(ENTRY_MODE_SET | self.entry);
+    }
+
+    /// Makes the display scroll with every character instead of the cursor moving.
+    /// # Arguments
+    /// * `on` - a boolean, true to scroll automatically.
+    pub fn autoscroll(&mut self, on: bool) {
+        if on {
+            self.entry |= ENTRY_AUTOSCROLL;
+        } else {
+            self.entry &= !ENTRY_AUTOSCROLL;
+        }
+        self.command(ENTRY_MODE_SET | self.entry);
+    }
+
+    /// Defines one of the 8 custom characters, which is written with `write_char(location)`.
+    /// The cursor has to be set again afterwards.
+    /// # Arguments
+    /// * `location` - a u8, the code of the character from 0 to 7.
+    /// * `rows` - an array of 8 u8, the rows from the top with the pixels in the lower 5 bits.
+    pub fn create_char(&mut self, location: u8, rows: &[u8; 8]) {
+        self.command(SET_CGRAM_ADDRESS | ((location & 0x07) << 3));
+        for row in rows.iter() {
+            self.write_char(*row);
+        }
+    }
+
+    /// Switches the backlight of an I2C backpack on or off, wired displays are not changed.
+    /// # Arguments
+    /// * `on` - a boolean, true to light the display.
+    pub fn backlight(&mut self, on: bool) {
+        if let Interface::I2c { device, backlight } = &mut self.interface {
+            *backlight = if on { PCF_BACKLIGHT } else { 0 };
+            let _ = device.write(&[*backlight]);
+        }
+    }
+}
+
+/// Text written with `write!` goes to the cursor, a new line moves to the next line.
+impl<T: TwiOps> fmt::Write for HD44780<T> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            match c {
+                '\n' => self.set_cursor(0, (self.row + 1) % self.rows),
+                ' '..='~' => self.write_char(c as u8),
+                _ => self.write_char(0xFF),
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,366 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! This code controls chains of MAX7219 LED drivers over SPI, each driving 8 digits
+//! of 7-segment displays or an 8x8 LED matrix.
+//! The modules are daisy-chained, DOUT of each going to DIN of the next one, and share
+//! the load (CS) pin. Module 0 is the one connected to the microcontroller.
+//! The 8 digit registers of each module are kept in a framebuffer. The 7-segment
+//! functions send a digit at once, while the matrix functions only draw in the
+//! framebuffer, which is sent by `display()`. On a matrix, row y is digit register y
+//! and column x is bit 7 - (x % 8) of module x / 8.
+//! Text is drawn with the 5x7 font of `display::font`.
+//! See `<https://datasheets.maximintegrated.com/en/ds/MAX7219-MAX7221.pdf>`.
+
+// Source codes required.
+use crate::bus::{SharedSpi, SpiDevice};
+use crate::common::SpiOps;
+use crate::display::font::{glyph, FONT_WIDTH};
+use crate::display::segments::{self, to_max7219};
+
+// Registers.
+const NO_OP: u8 = 0x00;
+const DIGIT_0: u8 = 0x01;
+const DECODE_MODE: u8 = 0x09;
+const INTENSITY: u8 = 0x0A;
+const SCAN_LIMIT: u8 = 0x0B;
+const SHUTDOWN: u8 = 0x0C;
+const DISPLAY_TEST: u8 = 0x0F;
+
+// Highest SCK frequency of the modules.
+const SPI_FREQUENCY: u32 = 10_000_000;
+
+/// Segment of the decimal point in a digit register.
+pub const SEGMENT_DP: u8 = 0x80;
+
+/// Gives the segments showing a character on a 7-segment digit.
+/// # Arguments
+/// * `c` - a char, a hexadecimal digit or one of the letters which can be shown.
+/// # Returns
+/// * `a u8` - the segments, with A as bit 6 down to G as bit 0, blank for other characters.
+pub fn seven_segment(c: char) -> u8 {
+    to_max7219(segments::encode(c))
+}
+
+/// Used to control a chain of `N` MAX7219 modules.
+/// # Elements
+/// * `device` - a `SpiDevice` object, the handle of the chain on the SPI bus, whose chip
+///   select is the LOAD (CS) pin.
+/// * `framebuffer` - a array of 8 u8 for each module, its digit registers.
+pub struct MAX7219<const N: usize, T = SharedSpi> {
+    device: SpiDevice<T>,
+    framebuffer: [[u8; 8]; N],
+}
+
+impl<const N: usize> MAX7219<N> {
+    /// Sets up the modules on the SPI of the chip, which scan 8 digits without decoding,
+    /// at a medium intensity, and are cleared.
+    /// # Arguments
+    /// * `cs` - a u8, the digital pin connected to LOAD (CS).
+    /// # Returns
+    /// * `a MAX7219 object` - which will be used to control the modules.
+    pub fn new(cs: u8) -> MAX7219<N> {
+        MAX7219::with_bus(SharedSpi::new(), cs)
+    }
+}
+
+impl<const N: usize, T: SpiOps> MAX7219<N, T> {
+    /// Sets up the modules on the given SPI bus, which scan 8 digits without decoding,
+    /// at a medium intensity, and are cleared.
+    /// # Arguments
+    /// * `bus` - a `SpiOps` object, the SPI bus of the modules.
+    /// * `cs` - a u8, the digital pin connected to LOAD (CS).
+    /// # Returns
+    /// * `a MAX7219 object` - which will be used to control the modules.
+    pub fn with_bus(bus: T, cs: u8) -> MAX7219<N, T> {
+        let mut device = SpiDevice::with_bus(bus, cs, 0, false, SPI_FREQUENCY);
+        device.init();
+
+        let mut modules = MAX7219 {
+            device,
+            framebuffer: [[0; 8]; N],
+        };
+        modules.command_all(DISPLAY_TEST, 0);
+        modules.command_all(SCAN_LIMIT, 7);
+        modules.command_all(DECODE_MODE, 0);
+        modules.command_all(INTENSITY, 7);
+        modules.clear();
+        modules.display();
+        modules.command_all(SHUTDOWN, 1);
+        modules
+    }
+
+    /// Gives the number of modules in the chain.
+    pub fn modules(&self) -> usize {
+        N
+    }
+
+    /// Writes a register of every module.
+    /// # Arguments
+    /// * `register` - a u8, the register address.
+    /// * `data` - a u8, the value to be written.
+    pub fn command_all(&mut self, register: u8, data: u8) {
+        let _ = self.device.transaction(|spi| {
+            for _ in 0..N {
+                spi.write(&[register, data]);
+            }
+        });
+    }
+
+    /// Writes a register of one module, the others get a no-op.
+    /// # Arguments
+    /// * `module` - a usize, the module, 0 being the one connected to the microcontroller.
+    /// * `register` - a u8, the register address.
+    /// * `data` - a u8, the value to be written.
+    pub fn command(&mut self, module: usize, register: u8, data: u8) {
+        let _ = self.device.transaction(|spi| {
+            // The first bytes sent are pushed to the end of the chain.
+            for m in (0..N).rev() {
+                if m == module {
+                    spi.write(&[register, data]);
+                } else {
+                    spi.write(&[NO_OP, 0]);
+                }
+            }
+        });
+    }
+
+    /// Sets the brightness of all the modules.
+    /// # Arguments
+    /// * `intensity` - a u8, from 0 (dimmest) to 15.
+    pub fn set_intensity(&mut self, intensity: u8) {
+        self.command_all(INTENSITY, intensity.min(15));
+    }
+
+    /// Sets the brightness of one module.
+    /// # Arguments
+    /// * `module` - a usize, the module.
+    /// * `intensity` - a u8, from 0 (dimmest) to 15.
+    pub fn set_module_intensity(&mut self, module: usize, intensity: u8) {
+        self.command(module, INTENSITY, intensity.min(15));
+    }
+
+    /// Sets the number of digits scanned, fewer digits are brighter.
+    /// # Arguments
+    /// * `digits` - a u8, from 1 to 8.
+    pub fn set_scan_limit(&mut self, digits: u8) {
+        self.command_all(SCAN_LIMIT, digits.clamp(1, 8) - 1);
+    }
+
+    /// Switches all the modules on or off, the digit registers are kept while off.
+    /// # Arguments
+    /// * `on` - a boolean, true to switch the modules on.
+    pub fn power(&mut self, on: bool) {
+        self.command_all(SHUTDOWN, on as u8);
+    }
+
+    /// Lights all the segments of all the modules while on, to test them.
+    /// # Arguments
+    /// * `on` - a boolean, true to light all the segments.
+    pub fn test(&mut self, on: bool) {
+        self.command_all(DISPLAY_TEST, on as u8);
+    }
+
+    /// Clears the framebuffer, `display()` sends it.
+    pub fn clear(&mut self) {
+        self.framebuffer = [[0; 8]; N];
+    }
+
+    /// Sends the framebuffer to the modules.
+    pub fn display(&mut self) {
+        let framebuffer = &self.framebuffer;
+        for digit in 0..8 {
+            let _ = self.device.transaction(|spi| {
+                for module in framebuffer.iter().rev() {
+                    spi.write(&[DIGIT_0 + digit as u8, module[digit]]);
+                }
+            });
+        }
+    }
+
+    /// Sends one digit register of one module.
+    fn send_digit(&mut self, module: usize, digit: usize) {
+        let data = self.framebuffer[module][digit];
+        self.command(module, DIGIT_0 + digit as u8, data);
+    }
+
+    /// Lights some segments of a 7-segment digit.
+    /// # Arguments
+    /// * `module` - a usize, the module.
+    /// * `digit` - a usize, the digit from 0 to 7, 0 being DIG0.
+    /// * `segments` - a u8, the segments with the decimal point as bit 7, A as bit 6
+    ///   down to G as bit 0.
+    pub fn set_segments(&mut self, module: usize, digit: usize, segments: u8) {
+        if module >= N || digit >= 8 {
+            return;
+        }
+        self.framebuffer[module][digit] = segments;
+        self.send_digit(module, digit);
+    }
+
+    /// Shows a hexadecimal digit on a 7-segment digit.
+    /// # Arguments
+    /// * `module` - a usize, the module.
+    /// * `digit` - a usize, the digit from 0 to 7.
+    /// * `value` - a u8, from 0 to 15.
+    /// * `dp` - a boolean, true to light the decimal point.
+    pub fn set_digit(&mut self, module: usize, digit: usize, value: u8, dp: bool) {
+        let segments = to_max7219(segments::hex(value));
+        self.set_segments(module, digit, segments | if dp { SEGMENT_DP } else { 0 });
+    }
+
+    /// Shows a character on a 7-segment digit, see `seven_segment`.
+    /// # Arguments
+    /// * `module` - a usize, the module.
+    /// * `digit` - a usize, the digit from 0 to 7.
+    /// * `c` - a char, the character.
+    /// * `dp` - a boolean, true to light the decimal point.
+    pub fn set_char(&mut self, module: usize, digit: usize, c: char, dp: bool) {
+        let segments = seven_segment(c);
+        self.set_segments(module, digit, segments | if dp { SEGMENT_DP } else { 0 });
+    }
+
+    /// Shows a number on the 8 digits of a module, aligned on DIG0, which is the digit
+    /// on the right of most modules.
+    /// # Arguments
+    /// * `module` - a usize, the module.
+    /// * `value` - a i32, the number.
+    /// # Returns
+    /// * `a boolean` - false if the number has more than 8 characters, in which case
+    ///   the digits show dashes.
+    pub fn show_number(&mut self, module: usize, value: i32) -> bool {
+        if module >= N {
+            return false;
+        }
+        let mut digits = [0u8; 8];
+        // The magnitude of i32::MIN fits a u32.
+        let mut rest = value.wrapping_abs() as u32;
+        let mut used = 0;
+        while used < 8 && (used == 0 || rest != 0) {
+            digits[used] = to_max7219(segments::hex((rest % 10) as u8));
+            used += 1;
+            rest /= 10;
+        }
+        let fits = rest == 0 && (value >= 0 || used < 8);
+        if !fits {
+            digits = [seven_segment('-'); 8];
+        } else if value < 0 {
+            digits[used] = seven_segment('-');
+        }
+        self.framebuffer[module] = digits;
+        self.display();
+        fits
+    }
+
+    /// Gives the width of the matrix in pixels.
+    pub fn width(&self) -> usize {
+        8 * N
+    }
+
+    /// Sets or clears a pixel of the matrix, pixels outside the matrix are ignored.
+    /// # Arguments
+    /// * `x` - a i16, the column from 0 at the left of module 0.
+    /// * `y` - a i16, the row from 0 at the top.
+    /// * `on` - a boolean, true to light the pixel.
+    pub fn draw_pixel(&mut self, x: i16, y: i16, on: bool) {
+        if x < 0 || y < 0 || x as usize >= 8 * N || y >= 8 {
+            return;
+        }
+        let mask = 0x80 >> (x as usize % 8);
+        let row = &mut self.framebuffer[x as usize / 8][y as usize];
+        if on {
+            *row |= mask;
+        } else {
+            *row &= !mask;
+        }
+    }
+
+    /// Checks whether a pixel is set in the framebuffer.
+    /// # Returns
+    /// * `a boolean` - which is true if the pixel is lit, false outside the matrix.
+    pub fn get_pixel(&self, x: i16, y: i16) -> bool {
+        if x < 0 || y < 0 || x as usize >= 8 * N || y >= 8 {
+            return false;
+        }
+        self.framebuffer[x as usize / 8][y as usize] & (0x80 >> (x as usize % 8)) != 0
+    }
+
+    /// Sets the 8 rows of one module of the matrix.
+    /// # Arguments
+    /// * `module` - a usize, the module.
+    /// * `rows` - a array of 8 u8, the rows from the top, the leftmost pixel in bit 7.
+    pub fn draw_bitmap(&mut self, module: usize, rows: [u8; 8]) {
+        if module < N {
+            self.framebuffer[module] = rows;
+        }
+    }
+
+    /// Draws a character of 5x7 pixels, clipped to the matrix.
+    /// # Arguments
+    /// * `x` - a i16, the column of the left of the character.
+    /// * `c` - a char, the character.
+    pub fn draw_char(&mut self, x: i16, c: char) {
+        for (i, &column) in glyph(c).iter().enumerate() {
+            for y in 0..8 {
+                self.draw_pixel(x + i as i16, y, column & (1 << y) != 0);
+            }
+        }
+    }
+
+    /// Draws a text, with a blank column between the characters.
+    /// A text wider than the matrix can be scrolled by drawing it at a smaller `x`
+    /// each time, after a `clear`.
+    /// # Arguments
+    /// * `x` - a i16, the column of the left of the text, negative to start before the matrix.
+    /// * `text` - a string slice, the text.
+    /// # Returns
+    /// * `a i16` - the column after the text.
+    pub fn draw_text(&mut self, x: i16, text: &str) -> i16 {
+        let mut x = x;
+        for c in text.chars() {
+            if x >= (8 * N) as i16 {
+                break;
+            }
+            if x + FONT_WIDTH as i16 > 0 {
+                self.draw_char(x, c);
+            }
+            x += FONT_WIDTH as i16 + 1;
+        }
+        x
+    }
+
+    /// Gives the width of a text drawn by `draw_text` in pixels.
+    pub fn text_width(text: &str) -> i16 {
+        text.chars().count() as i16 * (FONT_WIDTH as i16 + 1)
+    }
+
+    /// Moves the whole matrix one column to the left, the column at the right being
+    /// filled with the given pixels.
+    /// # Arguments
+    /// * `column` - a u8, the new rightmost column, the top pixel in bit 0.
+    pub fn scroll_left(&mut self, column: u8) {
+        for y in 0..8 {
+            for m in 0..N {
+                let carry = if m + 1 < N {
+                    self.framebuffer[m + 1][y] >> 7
+                } else {
+                    (column >> y) & 1
+                };
+                self.framebuffer[m][y] = self.framebuffer[m][y] << 1 | carry;
+            }
+        }
+    }
+}
@@ -0,0 +1,231 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Segments of 7-segment digits, shared by the drivers of 7-segment displays, and
+//! displays made of 74HC595 shift registers each driving a digit.
+//! The segments of a digit are a byte with A as bit 0 up to G as bit 6 and the decimal
+//! point as bit 7, the order of the TM1637 and of most 74HC595 boards. The MAX7219 takes
+//! them in the other order, given by `to_max7219`.
+//! ```text
+//!  --A--
+//! |     |
+//! F     B
+//! |     |
+//!  --G--
+//! |     |
+//! E     C
+//! |     |
+//!  --D--  DP
+//! ```
+//! A 74HC595 has its outputs QA to QG wired to the segments A to G and QH to the decimal
+//! point, through resistors. The registers are chained, QH' of each going to SER of the
+//! next one, with the clock (SRCLK) and latch (RCLK) pins shared. The digits are lit all
+//! the time, so no refresh is needed.
+
+// Source codes required.
+use crate::hal::pin::make_pin;
+use crate::hal::port::Pin;
+
+/// Segments of the hexadecimal digits.
+const HEX_SEGMENTS: [u8; 16] = [
+    0x3F, 0x06, 0x5B, 0x4F, 0x66, 0x6D, 0x7D, 0x07, 0x7F, 0x6F, 0x77, 0x7C, 0x39, 0x5E, 0x79, 0x71,
+];
+
+// Single segments.
+pub const SEGMENT_A: u8 = 0x01;
+pub const SEGMENT_B: u8 = 0x02;
+pub const SEGMENT_C: u8 = 0x04;
+pub const SEGMENT_D: u8 = 0x08;
+pub const SEGMENT_E: u8 = 0x10;
+pub const SEGMENT_F: u8 = 0x20;
+pub const SEGMENT_G: u8 = 0x40;
+pub const SEGMENT_DP: u8 = 0x80;
+
+/// Segments of the minus sign.
+pub const MINUS: u8 = SEGMENT_G;
+
+/// Gives the segments of a hexadecimal digit.
+/// # Arguments
+/// * `value` - a u8, the digit, only its 4 lower bits are used.
+pub fn hex(value: u8) -> u8 {
+    HEX_SEGMENTS[(value & 0x0F) as usize]
+}
+
+/// Gives the segments showing a character.
+/// # Arguments
+/// * `c` - a char, a hexadecimal digit or one of the letters which can be shown.
+/// # Returns
+/// * `a u8` - the segments, blank for other characters.
+pub fn encode(c: char) -> u8 {
+    match c {
+        '0'..='9' => HEX_SEGMENTS[c as usize - '0' as usize],
+        'a'..='f' => HEX_SEGMENTS[c as usize - 'a' as usize + 10],
+        'A'..='F' => HEX_SEGMENTS[c as usize - 'A' as usize + 10],
+        'H' | 'h' => 0x76,
+        'J' | 'j' => 0x1E,
+        'L' | 'l' => 0x38,
+        'n' => 0x54,
+        'o' => 0x5C,
+        'P' | 'p' => 0x73,
+        'r' => 0x50,
+        't' => 0x78,
+        'U' => 0x3E,
+        'u' => 0x1C,
+        'y' => 0x6E,
+        '-' => MINUS,
+        '_' => SEGMENT_D,
+        '=' => SEGMENT_D | SEGMENT_G,
+        _ => 0x00,
+    }
+}
+
+/// Gives segments in the order of the MAX7219, with A as bit 6 down to G as bit 0 and
+/// the decimal point as bit 7.
+pub fn to_max7219(segments: u8) -> u8 {
+    (segments & SEGMENT_DP) | (segments.reverse_bits() >> 1 & 0x7F)
+}
+
+/// Writes the segments of a number in decimal, aligned on the right of the digits.
+/// # Arguments
+/// * `value` - a i32, the number.
+/// * `digits` - a mutable slice of u8, the segments of the digits from the left.
+/// # Returns
+/// * `a boolean` - false if the number does not fit, in which case the digits show dashes.
+pub fn format_number(value: i32, digits: &mut [u8]) -> bool {
+    for digit in digits.iter_mut() {
+        *digit = 0;
+    }
+    // The magnitude of i32::MIN fits a u32.
+    let mut rest = value.wrapping_abs() as u32;
+    let mut place = digits.len();
+    while place > 0 && (place == digits.len() || rest != 0) {
+        place -= 1;
+        digits[place] = hex((rest % 10) as u8);
+        rest /= 10;
+    }
+    let fits = rest == 0 && (value >= 0 || place > 0);
+    if !fits {
+        for digit in digits.iter_mut() {
+            *digit = MINUS;
+        }
+    } else if value < 0 {
+        digits[place - 1] = MINUS;
+    }
+    fits
+}
+
+/// Used to show `N` digits driven by a chain of `N` 74HC595 shift registers.
+/// # Elements
+/// * `data` - a `Pin` object, connected to SER of the first register.
+/// * `clock` - a `Pin` object, connected to SRCLK.
+/// * `latch` - a `Pin` object, connected to RCLK.
+/// * `common_anode` - a boolean, true for digits with a common anode, lit by low outputs.
+/// * `digits` - a array of u8, the segments of the digits, digit 0 being the first register.
+pub struct ShiftRegisterDigits<const N: usize> {
+    data: Pin,
+    clock: Pin,
+    latch: Pin,
+    common_anode: bool,
+    digits: [u8; N],
+}
+
+impl<const N: usize> ShiftRegisterDigits<N> {
+    /// Creates the display and blanks it.
+    /// # Arguments
+    /// * `data` - a u8, the digital pin connected to SER of the first register.
+    /// * `clock` - a u8, the digital pin connected to SRCLK.
+    /// * `latch` - a u8, the digital pin connected to RCLK.
+    /// * `common_anode` - a boolean, true for digits with a common anode.
+    /// # Returns
+    /// * `a ShiftRegisterDigits object` - which will be used to show the digits.
+    pub fn new(data: u8, clock: u8, latch: u8, common_anode: bool) -> ShiftRegisterDigits<N> {
+        let mut display = ShiftRegisterDigits {
+            data: make_pin(data as _),
+            clock: make_pin(clock as _),
+            latch: make_pin(latch as _),
+            common_anode,
+            digits: [0; N],
+        };
+        display.data.set_output();
+        display.clock.set_output();
+        display.latch.set_output();
+        display.clock.low();
+        display.latch.low();
+        display.display();
+        display
+    }
+
+    /// Blanks all the digits.
+    pub fn clear(&mut self) {
+        self.digits = [0; N];
+        self.display();
+    }
+
+    /// Lights some segments of a digit, digits outside the display are ignored.
+    /// # Arguments
+    /// * `digit` - a usize, the digit, 0 being the first register of the chain.
+    /// * `segments` - a u8, the segments, see `encode`.
+    pub fn set_segments(&mut self, digit: usize, segments: u8) {
+        if digit < N {
+            self.digits[digit] = segments;
+            self.display();
+        }
+    }
+
+    /// Shows a character on a digit, see `encode`.
+    /// # Arguments
+    /// * `digit` - a usize, the digit, 0 being the first register of the chain.
+    /// * `c` - a char, the character.
+    /// * `dp` - a boolean, true to light the decimal point.
+    pub fn set_char(&mut self, digit: usize, c: char, dp: bool) {
+        self.set_segments(digit, encode(c) | if dp { SEGMENT_DP } else { 0 });
+    }
+
+    /// Shows a number, aligned on the last digit.
+    /// # Arguments
+    /// * `value` - a i32, the number.
+    /// # Returns
+    /// * `a boolean` - false if the number does not fit, in which case the digits
+    ///   show dashes.
+    pub fn show_number(&mut self, value: i32) -> bool {
+        let fits = format_number(value, &mut self.digits);
+        self.display();
+        fits
+    }
+
+    /// Sends the digits to the registers, the last digit first so that it ends
+    /// in the last register of the chain.
+    pub fn display(&mut self) {
+        for digit in (0..N).rev() {
+            let mut byte = self.digits[digit];
+            if self.common_anode {
+                byte = !byte;
+            }
+            // QH is shifted in first.
+            for bit in (0..8).rev() {
+                if byte & (1 << bit) != 0 {
+                    self.data.high();
+                } else {
+                    self.data.low();
+                }
+                self.clock.high();
+                self.clock.low();
+            }
+        }
+        self.latch.high();
+        self.latch.low();
+    }
+}
@@ -0,0 +1,359 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! This code controls the 128x64 and 128x32 monochrome OLED displays with the SSD1306
+//! controller over I2C.
+//! Drawing is done in a static framebuffer of 1 KB in RAM, where every byte is a column
+//! of 8 pixels of one page, and `display()` sends the whole framebuffer to the screen.
+//! Text is drawn with the 5x7 font of `display::font`, 21 characters on a line.
+//! See `<https://cdn-shop.adafruit.com/datasheets/SSD1306.pdf>`.
+
+// Source codes required.
+use crate::bus::{I2cDevice, SharedTwi};
+use crate::common::TwiOps;
+use crate::display::font::{glyph, FONT_WIDTH};
+use crate::error::Error;
+
+// Crates which would be used in the implementation.
+use core::fmt;
+
+/// Width of the display in pixels.
+pub const SSD1306_WIDTH: usize = 128;
+/// Largest height of the display in pixels.
+pub const SSD1306_MAX_HEIGHT: usize = 64;
+/// I2C address of most modules, 0x3D if the address pin is high.
+pub const SSD1306_ADDRESS: u8 = 0x3C;
+
+// Control bytes which tell whether the following bytes are commands or data.
+const CONTROL_COMMAND: u8 = 0x00;
+const CONTROL_DATA: u8 = 0x40;
+
+// SCL frequency used with the display.
+const I2C_FREQUENCY: u32 = 400_000;
+
+// The framebuffer, one bit per pixel in pages of 8 rows.
+static mut FRAMEBUFFER: [u8; SSD1306_WIDTH * SSD1306_MAX_HEIGHT / 8] =
+    [0; SSD1306_WIDTH * SSD1306_MAX_HEIGHT / 8];
+
+/// Selection of the panel connected to the controller.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Ssd1306Size {
+    W128H64,
+    W128H32,
+}
+
+/// Used to control a SSD1306 display.
+/// # Elements
+/// * `device` - a `I2cDevice` object, the handle of the display on the I2C bus.
+/// * `height` - a usize, the number of rows of the panel.
+/// * `cursor_x` - a i16, the column where `fmt::Write` puts the next character.
+/// * `cursor_y` - a i16, the row where `fmt::Write` puts the next character.
+pub struct SSD1306<T = SharedTwi> {
+    device: I2cDevice<T>,
+    height: usize,
+    cursor_x: i16,
+    cursor_y: i16,
+}
+
+impl SSD1306 {
+    /// Initializes the display on the TWI of the chip, run at 400 kHz, and clears it.
+    /// # Arguments
+    /// * `address` - a u8, the I2C address of the display, normally `SSD1306_ADDRESS`.
+    /// * `size` - a `Ssd1306Size` object, the panel connected.
+    /// # Returns
+    /// * `a SSD1306 object` - which will be used to draw on the display.
+    pub fn new(address: u8, size: Ssd1306Size) -> SSD1306 {
+        SSD1306::with_bus(SharedTwi::new(), address, size)
+    }
+}
+
+impl<T: TwiOps> SSD1306<T> {
+    /// Initializes the display on the given I2C bus, run at 400 kHz, and clears it.
+    /// # Arguments
+    /// * `bus` - a `TwiOps` object, the I2C bus of the display.
+    /// * `address` - a u8, the I2C address of the display, normally `SSD1306_ADDRESS`.
+    /// * `size` - a `Ssd1306Size` object, the panel connected.
+    /// # Returns
+    /// * `a SSD1306 object` - which will be used to draw on the display.
+    pub fn with_bus(bus: T, address: u8, size: Ssd1306Size) -> SSD1306<T> {
+        let height = match size {
+            Ssd1306Size::W128H64 => 64,
+            Ssd1306Size::W128H32 => 32,
+        };
+        let mut display = SSD1306 {
+            device: I2cDevice::with_bus(bus, address, I2C_FREQUENCY),
+            height,
+            cursor_x: 0,
+            cursor_y: 0,
+        };
+
+        let (multiplex, com_pins) = match size {
+            Ssd1306Size::W128H64 => (0x3F, 0x12),
+            Ssd1306Size::W128H32 => (0x1F, 0x02),
+        };
+        let _ = display.commands(&[
+            0xAE, // Display off.
+            0xD5, 0x80, // Clock divide ratio and oscillator frequency.
+            0xA8, multiplex, // Multiplex ratio, the number of rows - 1.
+            0xD3, 0x00, // No display offset.
+            0x40, // Start line 0.
+            0x8D, 0x14, // Internal charge pump on.
+            0x20, 0x00, // Horizontal addressing mode.
+            0xA1, // Column 127 is mapped to SEG0.
+            0xC8, // Rows scanned from the bottom.
+            0xDA, com_pins, // COM pins configuration.
+            0x81, 0xCF, // Contrast.
+            0xD9, 0xF1, // Pre-charge period.
+            0xDB, 0x40, // VCOMH deselect level.
+            0xA4, // Display follows the RAM.
+            0xA6, // Normal, not inverted.
+            0xAF, // Display on.
+        ]);
+        display.clear();
+        let _ = display.display();
+        display
+    }
+
+    /// Sends a list of commands to the controller.
+    /// # Arguments
+    /// * `commands` - a slice of u8, the commands and their arguments.
+    /// # Returns
+    /// * `a Result<(), Error>` - which is an error if the display did not acknowledge a byte.
+    pub fn commands(&mut self, commands: &[u8]) -> Result<(), Error> {
+        self.send(CONTROL_COMMAND, commands)
+    }
+
+    /// Sends a control byte followed by a block of bytes in one transfer.
+    fn send(&mut self, control: u8, data: &[u8]) -> Result<(), Error> {
+        self.device.transaction(|i2c, address| {
+            let result = i2c.start().and_then(|_| {
+                i2c.address_write(address)?;
+                i2c.write(control)?;
+                data.iter().try_for_each(|&byte| i2c.write(byte))
+            });
+            i2c.stop();
+            result
+        })
+    }
+
+    /// Gives the number of rows of the display.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Sends the framebuffer to the display.
+    /// # Returns
+    /// * `a Result<(), Error>` - which is an error if the transfer was not complete.
+    pub fn display(&mut self) -> Result<(), Error> {
+        let pages = self.height / 8;
+        self.commands(&[
+            0x21,
+            0,
+            (SSD1306_WIDTH - 1) as u8,
+            0x22,
+            0,
+            (pages - 1) as u8,
+        ])?;
+        let length = SSD1306_WIDTH * pages;
+        unsafe { self.send(CONTROL_DATA, &FRAMEBUFFER[..length]) }
+    }
+
+    /// Clears the framebuffer and moves the text cursor to the top left corner.
+    pub fn clear(&mut self) {
+        unsafe {
+            for byte in FRAMEBUFFER.iter_mut() {
+                *byte = 0;
+            }
+        }
+        self.cursor_x = 0;
+        self.cursor_y = 0;
+    }
+
+    /// Sets the contrast of the display.
+    /// # Arguments
+    /// * `contrast` - a u8, from 0 (dimmest) to 255.
+    pub fn set_contrast(&mut self, contrast: u8) {
+        let _ = self.commands(&[0x81, contrast]);
+    }
+
+    /// Shows the display inverted, with lit pixels for the cleared bits.
+    /// # Arguments
+    /// * `invert` - a boolean, true to invert the display.
+    pub fn invert(&mut self, invert: bool) {
+        let _ = self.commands(&[if invert { 0xA7 } else { 0xA6 }]);
+    }
+
+    /// Switches the display on or off, the RAM is kept while it is off.
+    /// # Arguments
+    /// * `on` - a boolean, true to switch the display on.
+    pub fn power(&mut self, on: bool) {
+        let _ = self.commands(&[if on { 0xAF } else { 0xAE }]);
+    }
+
+    /// Sets or clears a pixel, pixels outside the display are ignored.
+    /// # Arguments
+    /// * `x` - a i16, the column from 0 at the left.
+    /// * `y` - a i16, the row from 0 at the top.
+    /// * `on` - a boolean, true to light the pixel.
+    pub fn draw_pixel(&mut self, x: i16, y: i16, on: bool) {
+        if x < 0 || y < 0 || x as usize >= SSD1306_WIDTH || y as usize >= self.height {
+            return;
+        }
+        let index = (y as usize / 8) * SSD1306_WIDTH + x as usize;
+        let mask = 1 << (y as usize % 8);
+        unsafe {
+            if on {
+                FRAMEBUFFER[index] |= mask;
+            } else {
+                FRAMEBUFFER[index] &= !mask;
+            }
+        }
+    }
+
+    /// Checks whether a pixel is set in the framebuffer.
+    /// # Returns
+    /// * `a boolean` - which is true if the pixel is lit, false outside the display.
+    pub fn get_pixel(&self, x: i16, y: i16) -> bool {
+        if x < 0 || y < 0 || x as usize >= SSD1306_WIDTH || y as usize >= self.height {
+            return false;
+        }
+        let index = (y as usize / 8) * SSD1306_WIDTH + x as usize;
+        unsafe { FRAMEBUFFER[index] & (1 << (y as usize % 8)) != 0 }
+    }
+
+    /// Draws a line between two points with Bresenham's algorithm.
+    /// # Arguments
+    /// * `x0`, `y0` - i16, the first point.
+    /// * `x1`, `y1` - i16, the last point.
+    /// * `on` - a boolean, true to light the pixels.
+    pub fn draw_line(&mut self, x0: i16, y0: i16, x1: i16, y1: i16, on: bool) {
+        let dx = (x1 - x0).abs();
+        let dy = -(y1 - y0).abs();
+        let sx = if x0 < x1 { 1 } else { -1 };
+        let sy = if y0 < y1 { 1 } else { -1 };
+        let mut err = dx + dy;
+        let (mut x, mut y) = (x0, y0);
+        loop {
+            self.draw_pixel(x, y, on);
+            if x == x1 && y == y1 {
+                break;
+            }
+            let e2 = 2 * err;
+            if e2 >= dy {
+                err += dy;
+                x += sx;
+            }
+            if e2 <= dx {
+                err += dx;
+                y += sy;
+            }
+        }
+    }
+
+    /// Draws the outline of a rectangle.
+    /// # Arguments
+    /// * `x`, `y` - i16, the top left corner.
+    /// * `width`, `height` - i16, the size of the rectangle.
+    /// * `on` - a boolean, true to light the pixels.
+    pub fn draw_rect(&mut self, x: i16, y: i16, width: i16, height: i16, on: bool) {
+        if width <= 0 || height <= 0 {
+            return;
+        }
+        let (x1, y1) = (x + width - 1, y + height - 1);
+        self.draw_line(x, y, x1, y, on);
+        self.draw_line(x, y1, x1, y1, on);
+        self.draw_line(x, y, x, y1, on);
+        self.draw_line(x1, y, x1, y1, on);
+    }
+
+    /// Draws a filled rectangle.
+    /// # Arguments
+    /// * `x`, `y` - i16, the top left corner.
+    /// * `width`, `height` - i16, the size of the rectangle.
+    /// * `on` - a boolean, true to light the pixels.
+    pub fn fill_rect(&mut self, x: i16, y: i16, width: i16, height: i16, on: bool) {
+        for row in y..y + height {
+            for column in x..x + width {
+                self.draw_pixel(column, row, on);
+            }
+        }
+    }
+
+    /// Draws a character of the 5x7 font.
+    /// # Arguments
+    /// * `x`, `y` - i16, the top left corner of the character.
+    /// * `c` - a char, the character to be drawn.
+    /// * `on` - a boolean, true for lit text on a dark background.
+    pub fn draw_char(&mut self, x: i16, y: i16, c: char, on: bool) {
+        let columns = glyph(c);
+        for (i, column) in columns.iter().enumerate() {
+            for row in 0..8 {
+                self.draw_pixel(x + i as i16, y + row, (column >> row & 1 != 0) == on);
+            }
+        }
+        // The space between characters.
+        for row in 0..8 {
+            self.draw_pixel(x + FONT_WIDTH as i16, y + row, !on);
+        }
+    }
+
+    /// Draws a string on one line.
+    /// # Arguments
+    /// * `x`, `y` - i16, the top left corner of the first character.
+    /// * `text` - a string slice, the text to be drawn.
+    /// * `on` - a boolean, true for lit text on a dark background.
+    /// # Returns
+    /// * `a i16` - the column after the last character.
+    pub fn draw_str(&mut self, x: i16, y: i16, text: &str, on: bool) -> i16 {
+        let mut x = x;
+        for c in text.chars() {
+            self.draw_char(x, y, c, on);
+            x += FONT_WIDTH as i16 + 1;
+        }
+        x
+    }
+
+    /// Moves the cursor used by `write!` to a pixel position.
+    /// # Arguments
+    /// * `x`, `y` - i16, the top left corner of the next character.
+    pub fn set_cursor(&mut self, x: i16, y: i16) {
+        self.cursor_x = x;
+        self.cursor_y = y;
+    }
+}
+
+/// Text written with `write!` is drawn at the cursor, wrapping at the right edge.
+/// The framebuffer still has to be sent with `display()`.
+impl<T: TwiOps> fmt::Write for SSD1306<T> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let advance = FONT_WIDTH as i16 + 1;
+        for c in s.chars() {
+            if c == '\n' || self.cursor_x + advance > SSD1306_WIDTH as i16 {
+                self.cursor_x = 0;
+                self.cursor_y += 8;
+            }
+            match c {
+                '\n' => {}
+                '\r' => self.cursor_x = 0,
+                _ => {
+                    self.draw_char(self.cursor_x, self.cursor_y, c, true);
+                    self.cursor_x += advance;
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,268 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! This code controls the TM1637 LED driver of the common 4 digit 7-segment modules,
+//! most of which have a colon between the second and third digits.
+//! The TM1637 has a two wire interface with a start, a stop and an acknowledge like I2C,
+//! but it has no address and sends the bits with the least significant first, so it can
+//! not be used with the TWI hardware. The two lines, CLK and DIO, are driven by software
+//! on any two pins. They are open drain, pulled up by the module and by the pins, and
+//! only ever driven low.
+//! The segments of the digits are given as in `display::segments`, and the colon is the
+//! decimal point of digit 1.
+//! See `<https://www.mcielectronics.cl/website_MCI/static/documents/Datasheet_TM1637.pdf>`.
+
+// Source codes required.
+use crate::delay::delay_us;
+use crate::display::segments::{encode, format_number, hex, SEGMENT_DP};
+use crate::error::Error;
+use crate::hal::pin::make_pin;
+use crate::hal::port::Pin;
+
+/// Number of digits of the modules.
+pub const TM1637_DIGITS: usize = 4;
+
+// Commands.
+const DATA_AUTO_INCREMENT: u8 = 0x40;
+const ADDRESS: u8 = 0xC0;
+const DISPLAY_CONTROL: u8 = 0x80;
+const DISPLAY_ON: u8 = 0x08;
+
+/// Half of the period of the clock in microseconds, the TM1637 works up to 250 kHz.
+const BIT_DELAY_US: u32 = 5;
+
+/// Used to control a TM1637 module.
+/// # Elements
+/// * `clock` - a `Pin` object, connected to CLK.
+/// * `data` - a `Pin` object, connected to DIO.
+/// * `brightness` - a u8, from 0 to 7.
+/// * `on` - a boolean, false while the digits are off.
+/// * `colon` - a boolean, true while the colon is lit.
+/// * `digits` - a array of u8, the segments of the digits from the left.
+pub struct TM1637 {
+    clock: Pin,
+    data: Pin,
+    brightness: u8,
+    on: bool,
+    colon: bool,
+    digits: [u8; TM1637_DIGITS],
+}
+
+impl TM1637 {
+    /// Sets up a module, which is cleared and lit at brightness 3.
+    /// # Arguments
+    /// * `clock` - a u8, the digital pin connected to CLK.
+    /// * `data` - a u8, the digital pin connected to DIO.
+    /// # Returns
+    /// * `a Result<TM1637, Error>` - the module, or `Error::Nack` if it does not answer.
+    pub fn new(clock: u8, data: u8) -> Result<TM1637, Error> {
+        let mut module = TM1637 {
+            clock: make_pin(clock as _),
+            data: make_pin(data as _),
+            brightness: 3,
+            on: true,
+            colon: false,
+            digits: [0; TM1637_DIGITS],
+        };
+        release(&mut module.clock);
+        release(&mut module.data);
+        module.display()?;
+        Ok(module)
+    }
+
+    /// Sets the brightness of the digits.
+    /// # Arguments
+    /// * `brightness` - a u8, from 0 for 1/16 of the current to 7 for 14/16.
+    /// # Returns
+    /// * `a Result<(), Error>` - which is an error if the module did not answer.
+    pub fn set_brightness(&mut self, brightness: u8) -> Result<(), Error> {
+        self.brightness = brightness.min(7);
+        self.send_control()
+    }
+
+    /// Lights or turns off all the digits, which keep their segments.
+    /// # Arguments
+    /// * `on` - a boolean, true to light the digits.
+    /// # Returns
+    /// * `a Result<(), Error>` - which is an error if the module did not answer.
+    pub fn power(&mut self, on: bool) -> Result<(), Error> {
+        self.on = on;
+        self.send_control()
+    }
+
+    /// Lights or turns off the colon.
+    /// # Arguments
+    /// * `on` - a boolean, true to light the colon.
+    /// # Returns
+    /// * `a Result<(), Error>` - which is an error if the module did not answer.
+    pub fn set_colon(&mut self, on: bool) -> Result<(), Error> {
+        self.colon = on;
+        self.display()
+    }
+
+    /// Blanks all the digits and the colon.
+    /// # Returns
+    /// * `a Result<(), Error>` - which is an error if the module did not answer.
+    pub fn clear(&mut self) -> Result<(), Error> {
+        self.digits = [0; TM1637_DIGITS];
+        self.colon = false;
+        self.display()
+    }
+
+    /// Lights some segments of a digit, digits outside the module are ignored.
+    /// # Arguments
+    /// * `digit` - a usize, the digit from 0 on the left to 3.
+    /// * `segments` - a u8, the segments, see `display::segments`.
+    /// # Returns
+    /// * `a Result<(), Error>` - which is an error if the module did not answer.
+    pub fn set_segments(&mut self, digit: usize, segments: u8) -> Result<(), Error> {
+        if digit >= TM1637_DIGITS {
+            return Ok(());
+        }
+        self.digits[digit] = segments;
+        self.display()
+    }
+
+    /// Shows a character on a digit, see `display::segments::encode`.
+    /// # Arguments
+    /// * `digit` - a usize, the digit from 0 on the left to 3.
+    /// * `c` - a char, the character.
+    /// # Returns
+    /// * `a Result<(), Error>` - which is an error if the module did not answer.
+    pub fn set_char(&mut self, digit: usize, c: char) -> Result<(), Error> {
+        self.set_segments(digit, encode(c))
+    }
+
+    /// Shows a number from -999 to 9999, aligned on the right.
+    /// # Arguments
+    /// * `value` - a i32, the number.
+    /// # Returns
+    /// * `a Result<bool, Error>` - false if the number does not fit, in which case the
+    ///   digits show dashes, or an error if the module did not answer.
+    pub fn show_number(&mut self, value: i32) -> Result<bool, Error> {
+        let fits = format_number(value, &mut self.digits);
+        self.display()?;
+        Ok(fits)
+    }
+
+    /// Shows a time of a clock, as 12:34, with the colon lit.
+    /// # Arguments
+    /// * `left` - a u8, the hours or minutes, from 0 to 99.
+    /// * `right` - a u8, the minutes or seconds, from 0 to 99.
+    /// # Returns
+    /// * `a Result<(), Error>` - which is an error if the module did not answer.
+    pub fn show_time(&mut self, left: u8, right: u8) -> Result<(), Error> {
+        self.digits = [
+            hex(left / 10 % 10),
+            hex(left % 10),
+            hex(right / 10 % 10),
+            hex(right % 10),
+        ];
+        self.colon = true;
+        self.display()
+    }
+
+    /// Sends all the digits to the module.
+    /// # Returns
+    /// * `a Result<(), Error>` - which is an error if the module did not answer.
+    pub fn display(&mut self) -> Result<(), Error> {
+        self.start();
+        let result = self.write(DATA_AUTO_INCREMENT);
+        self.stop();
+        result?;
+
+        self.start();
+        let mut result = self.write(ADDRESS);
+        for digit in 0..TM1637_DIGITS {
+            let mut segments = self.digits[digit] & !SEGMENT_DP;
+            if digit == 1 && self.colon {
+                segments |= SEGMENT_DP;
+            }
+            if result.is_ok() {
+                result = self.write(segments);
+            }
+        }
+        self.stop();
+        result?;
+
+        self.send_control()
+    }
+
+    fn send_control(&mut self) -> Result<(), Error> {
+        let on = if self.on { DISPLAY_ON } else { 0 };
+        self.start();
+        let result = self.write(DISPLAY_CONTROL | on | self.brightness);
+        self.stop();
+        result
+    }
+
+    // DIO falls while CLK is high.
+    fn start(&mut self) {
+        pull_low(&mut self.data);
+        delay_us(BIT_DELAY_US);
+    }
+
+    // DIO rises while CLK is high.
+    fn stop(&mut self) {
+        pull_low(&mut self.clock);
+        pull_low(&mut self.data);
+        delay_us(BIT_DELAY_US);
+        release(&mut self.clock);
+        delay_us(BIT_DELAY_US);
+        release(&mut self.data);
+        delay_us(BIT_DELAY_US);
+    }
+
+    // Sends a byte, the least significant bit first, and reads the acknowledge.
+    fn write(&mut self, byte: u8) -> Result<(), Error> {
+        for bit in 0..8 {
+            pull_low(&mut self.clock);
+            if byte & (1 << bit) != 0 {
+                release(&mut self.data);
+            } else {
+                pull_low(&mut self.data);
+            }
+            delay_us(BIT_DELAY_US);
+            release(&mut self.clock);
+            delay_us(BIT_DELAY_US);
+        }
+        // The TM1637 pulls DIO low from the ninth falling edge of the clock.
+        pull_low(&mut self.clock);
+        release(&mut self.data);
+        delay_us(BIT_DELAY_US);
+        let acknowledged = !self.data.is_high();
+        release(&mut self.clock);
+        delay_us(BIT_DELAY_US);
+        pull_low(&mut self.clock);
+        if acknowledged {
+            Ok(())
+        } else {
+            Err(Error::Nack)
+        }
+    }
+}
+
+// Lets a line be pulled up.
+fn release(pin: &mut Pin) {
+    pin.set_input();
+    pin.high();
+}
+
+// Drives a line low.
+fn pull_low(pin: &mut Pin) {
+    pin.low();
+    pin.set_output();
+}
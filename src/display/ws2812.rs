@@ -0,0 +1,233 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! This code drives strips of WS2812B (NeoPixel) RGB LEDs from any digital pin.
+//! Each bit is a pulse of 1.25 us whose high time tells a 0 from a 1, which can only be
+//! made by counting cycles, so the bits are sent by inline assembly written for each
+//! supported CPU frequency, 16 MHz and 8 MHz, with the interrupts disabled.
+//! At 8 MHz the low part of each bit is longer than the datasheet asks, which the LEDs accept.
+//! See `<https://cdn-shop.adafruit.com/datasheets/WS2812B.pdf>`.
+
+// Source codes required.
+use crate::config::CPU_FREQUENCY_HZ;
+use crate::delay::delay_us;
+use crate::hal::interrupts::without_interrupts;
+use crate::hal::pin::make_pin;
+
+/// Color of one LED.
+/// The fields are kept in the green, red, blue order the LEDs expect,
+/// so that a slice of colors is sent as it is.
+#[repr(C)]
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Rgb {
+    pub g: u8,
+    pub r: u8,
+    pub b: u8,
+}
+
+impl Rgb {
+    /// Creates a color.
+    /// # Arguments
+    /// * `r` - a u8, the red part.
+    /// * `g` - a u8, the green part.
+    /// * `b` - a u8, the blue part.
+    pub const fn new(r: u8, g: u8, b: u8) -> Rgb {
+        Rgb { g, r, b }
+    }
+
+    /// Gives the color with its brightness scaled.
+    /// # Arguments
+    /// * `brightness` - a u8, from 0 for black to 255 for the same color.
+    pub fn scaled(&self, brightness: u8) -> Rgb {
+        let scale = |c: u8| ((c as u16 * (brightness as u16 + 1)) >> 8) as u8;
+        Rgb::new(scale(self.r), scale(self.g), scale(self.b))
+    }
+
+    /// Gives the color with each part corrected by `gamma8`.
+    pub fn gamma_corrected(&self) -> Rgb {
+        Rgb::new(gamma8(self.r), gamma8(self.g), gamma8(self.b))
+    }
+
+    /// Gives a color of the color wheel, going from red to green, blue and back to red.
+    /// # Arguments
+    /// * `position` - a u8, the position on the wheel.
+    pub fn wheel(position: u8) -> Rgb {
+        let p = position as u16 * 3;
+        match position {
+            0..=84 => Rgb::new(255 - p as u8, p as u8, 0),
+            85..=169 => {
+                let p = p - 255;
+                Rgb::new(0, 255 - p as u8, p as u8)
+            }
+            _ => {
+                let p = p - 510;
+                Rgb::new(p as u8, 0, 255 - p as u8)
+            }
+        }
+    }
+}
+
+/// Corrects a brightness for the eye, which sees small changes of dim light better
+/// than of bright light, with a gamma of about 2.5.
+/// It is computed instead of read from a table, to save 256 bytes of RAM.
+/// # Arguments
+/// * `value` - a u8, the brightness wanted.
+/// # Returns
+/// * `a u8` - the brightness to be sent to the LED.
+pub fn gamma8(value: u8) -> u8 {
+    let x = value as u32;
+    (x * x * (2 * x + 255) / (3 * 255 * 255)) as u8
+}
+
+/// Used to drive a strip of WS2812B LEDs.
+/// # Elements
+/// * `pin` - a u8, the digital pin connected to DIN of the first LED.
+pub struct WS2812 {
+    pin: u8,
+}
+
+impl WS2812 {
+    /// Creates the strip and sets its pin as a low output.
+    /// # Arguments
+    /// * `pin` - a u8, the digital pin connected to DIN of the first LED.
+    /// # Returns
+    /// * `a WS2812 object` - which will be used to show colors.
+    pub fn new(pin: u8) -> WS2812 {
+        let mut p = make_pin(pin as _);
+        p.set_output();
+        p.low();
+        WS2812 { pin }
+    }
+
+    /// Sends the colors to the strip, the first one to the LED nearest to the pin,
+    /// and waits for the LEDs to show them.
+    /// The interrupts are disabled for 30 us for each LED, so `millis` loses
+    /// time if the strip is long and shown often.
+    /// # Arguments
+    /// * `leds` - a slice of `Rgb` objects, the colors of the LEDs.
+    pub fn show(&mut self, leds: &[Rgb]) {
+        if leds.is_empty() {
+            return;
+        }
+        let pin = make_pin(self.pin as _);
+        let mask = 1u8 << pin.pin;
+        let port = unsafe { &mut (*pin.port).port as *mut u8 };
+        let data = leds.as_ptr() as *const u8;
+        let length = (leds.len() * 3) as u16;
+
+        without_interrupts(|| unsafe {
+            let level = core::ptr::read_volatile(port);
+            let high = level | mask;
+            let low = level & !mask;
+            match CPU_FREQUENCY_HZ {
+                16_000_000 => send_16mhz(port, high, low, data, length),
+                8_000_000 => send_8mhz(port, high, low, data, length),
+                _ => panic!("WS2812 timing needs a CPU of 16 or 8 MHz"),
+            }
+        });
+
+        // The LEDs show the new colors once the line stays low, which
+        // newer LEDs need for 280 us.
+        delay_us(300);
+    }
+}
+
+// Both loops keep the next level of the line in a register, so that each bit is made
+// by three stores: high, then low for a 0, then low for a 1. The counts of cycles
+// are from the start of the bit. `length` must not be zero.
+
+/// Sends the bytes with 20 cycles for each bit, high for 6 cycles for a 0
+/// and for 13 cycles for a 1.
+#[inline(always)]
+#[allow(unused_assignments)]
+unsafe fn send_16mhz(port: *mut u8, high: u8, low: u8, data: *const u8, length: u16) {
+    let mut pointer = data;
+    let mut count = length;
+    let mut byte: u8 = 0;
+    let mut bits: u8 = 8;
+    let mut next: u8 = 0;
+    llvm_asm!("ld   $2, X+
+               mov  $4, $6
+               sbrc $2, 7
+               mov  $4, $5
+           1:  st   Z, $5
+               lsl  $2
+               nop
+               nop
+               nop
+               st   Z, $4
+               dec  $3
+               nop
+               nop
+               nop
+               nop
+               st   Z, $6
+               mov  $4, $6
+               sbrc $2, 7
+               mov  $4, $5
+               brne 1b
+               sbiw $1, 1
+               breq 2f
+               ld   $2, X+
+               ldi  $3, 8
+               mov  $4, $6
+               sbrc $2, 7
+               mov  $4, $5
+               rjmp 1b
+           2:"
+             : "+x" (pointer), "+w" (count), "+r" (byte), "+d" (bits), "+r" (next)
+             : "r" (high), "r" (low), "z" (port)
+             : "memory"
+             : "volatile");
+}
+
+/// Sends the bytes with 13 cycles for each bit, high for 3 cycles for a 0
+/// and for 6 cycles for a 1.
+#[inline(always)]
+#[allow(unused_assignments)]
+unsafe fn send_8mhz(port: *mut u8, high: u8, low: u8, data: *const u8, length: u16) {
+    let mut pointer = data;
+    let mut count = length;
+    let mut byte: u8 = 0;
+    let mut bits: u8 = 8;
+    let mut next: u8 = 0;
+    llvm_asm!("ld   $2, X+
+               mov  $4, $6
+               sbrc $2, 7
+               mov  $4, $5
+           1:  st   Z, $5
+               lsl  $2
+               st   Z, $4
+               dec  $3
+               st   Z, $6
+               mov  $4, $6
+               sbrc $2, 7
+               mov  $4, $5
+               brne 1b
+               sbiw $1, 1
+               breq 2f
+               ld   $2, X+
+               ldi  $3, 8
+               mov  $4, $6
+               sbrc $2, 7
+               mov  $4, $5
+               rjmp 1b
+           2:"
+             : "+x" (pointer), "+w" (count), "+r" (byte), "+d" (bits), "+r" (next)
+             : "r" (high), "r" (low), "z" (port)
+             : "memory"
+             : "volatile");
+}
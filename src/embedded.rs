@@ -0,0 +1,189 @@
+// RustDuino : A generic HAL implementation for Arduino Boards in Rust
+// Copyright (C) 2021 Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Implementation of the `embedded-hal` traits for the pins and delay functions of rustduino.
+//! Any device driver crate written against `embedded-hal` can then be used with the pins
+//! and the delay given here.
+//! The I2C traits are implemented in the `com::i2c` module of each chip.
+//! `<https://docs.rs/embedded-hal/0.2.4/embedded_hal/>`
+
+// Source codes required.
+use crate::delay::{delay_ms, delay_us};
+use crate::hal::pin::DigitalPin;
+use crate::hal::port::Pin;
+
+// Crates required in the code for reading and writing to registers.
+use core::convert::Infallible;
+use core::ptr::{read_volatile, write_volatile};
+use embedded_hal::blocking::delay::{DelayMs, DelayUs};
+use embedded_hal::digital::v2::{InputPin, OutputPin, StatefulOutputPin, ToggleableOutputPin};
+
+impl OutputPin for Pin {
+    type Error = Infallible;
+
+    /// Sets the PORTxn bit of the pin so that it drives a high output.
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        unsafe {
+            let port = read_volatile(&(*self.port).port);
+            write_volatile(&mut (*self.port).port, port | (0x1 << self.pin));
+        }
+        Ok(())
+    }
+
+    /// Clears the PORTxn bit of the pin so that it drives a low output.
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        unsafe {
+            let port = read_volatile(&(*self.port).port);
+            write_volatile(&mut (*self.port).port, port & !(0x1 << self.pin));
+        }
+        Ok(())
+    }
+}
+
+impl StatefulOutputPin for Pin {
+    /// Checks the PORTxn bit of the pin to find the level being driven.
+    fn is_set_high(&self) -> Result<bool, Self::Error> {
+        let port = unsafe { read_volatile(&(*self.port).port) };
+        Ok(port & (0x1 << self.pin) != 0)
+    }
+
+    fn is_set_low(&self) -> Result<bool, Self::Error> {
+        self.is_set_high().map(|high| !high)
+    }
+}
+
+impl ToggleableOutputPin for Pin {
+    type Error = Infallible;
+
+    /// Writes a logic one to PINxn which toggles the value of PORTxn.
+    fn toggle(&mut self) -> Result<(), Self::Error> {
+        unsafe { write_volatile(&mut (*self.port).pin, 0x1 << self.pin) }
+        Ok(())
+    }
+}
+
+impl InputPin for Pin {
+    type Error = Infallible;
+
+    /// Reads the PINxn bit which gives the logic level present on the pin.
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        let pin = unsafe { read_volatile(&(*self.port).pin) };
+        Ok(pin & (0x1 << self.pin) != 0)
+    }
+
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        InputPin::is_high(self).map(|high| !high)
+    }
+}
+
+impl OutputPin for DigitalPin {
+    type Error = Infallible;
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        let mut pin = self.pin;
+        pin.set_high()
+    }
+
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        let mut pin = self.pin;
+        pin.set_low()
+    }
+}
+
+impl StatefulOutputPin for DigitalPin {
+    fn is_set_high(&self) -> Result<bool, Self::Error> {
+        let pin = self.pin;
+        pin.is_set_high()
+    }
+
+    fn is_set_low(&self) -> Result<bool, Self::Error> {
+        let pin = self.pin;
+        pin.is_set_low()
+    }
+}
+
+impl ToggleableOutputPin for DigitalPin {
+    type Error = Infallible;
+
+    fn toggle(&mut self) -> Result<(), Self::Error> {
+        let mut pin = self.pin;
+        ToggleableOutputPin::toggle(&mut pin)
+    }
+}
+
+impl InputPin for DigitalPin {
+    type Error = Infallible;
+
+    fn is_high(&self) -> Result<bool, Self::Error> {
+        let pin = self.pin;
+        InputPin::is_high(&pin)
+    }
+
+    fn is_low(&self) -> Result<bool, Self::Error> {
+        let pin = self.pin;
+        InputPin::is_low(&pin)
+    }
+}
+
+/// Busy-wait delay provider to be passed to the `embedded-hal` drivers.
+/// It uses the functions of the `delay` module, so the accuracy is the same.
+#[derive(Clone, Copy)]
+pub struct Delay;
+
+impl Delay {
+    /// Creates a new delay provider.
+    /// # Returns
+    /// * `a Delay object` - which can be given to any driver needing `DelayMs` or `DelayUs`.
+    pub fn new() -> Delay {
+        Delay
+    }
+}
+
+impl DelayMs<u32> for Delay {
+    fn delay_ms(&mut self, ms: u32) {
+        delay_ms(ms);
+    }
+}
+
+impl DelayMs<u16> for Delay {
+    fn delay_ms(&mut self, ms: u16) {
+        delay_ms(ms as u32);
+    }
+}
+
+impl DelayMs<u8> for Delay {
+    fn delay_ms(&mut self, ms: u8) {
+        delay_ms(ms as u32);
+    }
+}
+
+impl DelayUs<u32> for Delay {
+    fn delay_us(&mut self, us: u32) {
+        delay_us(us);
+    }
+}
+
+impl DelayUs<u16> for Delay {
+    fn delay_us(&mut self, us: u16) {
+        delay_us(us as u32);
+    }
+}
+
+impl DelayUs<u8> for Delay {
+    fn delay_us(&mut self, us: u8) {
+        delay_us(us as u32);
+    }
+}
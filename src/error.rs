@@ -0,0 +1,63 @@
+// RustDuino : A generic HAL implementation for Arduino Boards in Rust
+// Copyright (C) 2021 Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Error type shared by the peripherals and the drivers.
+//! The buses return it directly, while the drivers keep their own error enums, which
+//! tell more about what went wrong, and convert into it with `From` so that a program
+//! using several drivers can pass all of their errors on with `?`.
+//!
+//! ```ignore
+//! fn log_reading(rtc: &mut Rtc, sensor: &mut Bme280) -> rustduino::error::Result<()> {
+//!     let time = rtc.now()?;
+//!     let measurement = sensor.read()?;
+//!     ...
+//!     Ok(())
+//! }
+//! ```
+
+/// Errors of the peripherals and the drivers.
+/// * `Timeout` - the device or the bus did not answer in time.
+/// * `Nack` - the device did not acknowledge its address or the data.
+/// * `ArbitrationLost` - another master took the bus.
+/// * `Bus` - the bus was left in an unexpected state.
+/// * `Busy` - the peripheral or the device is in use.
+/// * `InvalidParam` - an argument is outside of what the peripheral or the device supports.
+/// * `CrcMismatch` - a checksum or CRC did not match the data.
+/// * `NotFound` - no device answered, or the item asked for does not exist.
+/// * `Overflow` - the data does not fit, or a measurement is out of range.
+/// * `InvalidData` - the data received does not follow the format expected.
+/// * `Device` - the device reported an error of its own.
+/// * `Closed` - the connection or transfer was ended by the other side.
+/// * `Unsupported` - the device or the chip can not do what was asked.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    Timeout,
+    Nack,
+    ArbitrationLost,
+    Bus,
+    Busy,
+    InvalidParam,
+    CrcMismatch,
+    NotFound,
+    Overflow,
+    InvalidData,
+    Device,
+    Closed,
+    Unsupported,
+}
+
+/// Result of the operations which can fail with an `Error`.
+pub type Result<T> = core::result::Result<T, Error>;
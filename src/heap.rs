@@ -0,0 +1,245 @@
+// RustDuino : A generic HAL implementation for Arduino Boards in Rust
+// Copyright (C) 2021 Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Heap for the `alloc` crate, enabled with the `alloc` feature, so that
+//! `alloc::vec::Vec`, `alloc::string::String` and `alloc::boxed::Box` can be used.
+//! Without the feature the library never allocates, and the fixed capacity types of
+//! `crate::collections` are the way to go on the 2 kB parts.
+//! The heap is a static array of `crate::config::HEAP_SIZE` bytes. Its free blocks form
+//! a list sorted by address, each starting with its size and the next block, so a
+//! block taken has no header. An allocation takes the first free block large enough,
+//! and a block given back is merged with the free blocks next to it.
+//! The list is changed with the interrupts disabled, so the heap can be used from
+//! interrupt service routines.
+//!
+//! ```ignore
+//! extern crate alloc;
+//! use alloc::vec::Vec;
+//!
+//! let mut readings = Vec::new();
+//! readings.push(analog_read(0)?);
+//! ```
+
+// Source codes required.
+use crate::config::HEAP_SIZE;
+use crate::sync;
+
+use core::alloc::{GlobalAlloc, Layout};
+use core::cell::UnsafeCell;
+use core::mem::{align_of, size_of};
+use core::ptr::null_mut;
+
+/// Heap of the program, used by the `alloc` crate.
+#[global_allocator]
+static HEAP: Heap = Heap::new();
+
+/// Called when an allocation fails, which panics.
+#[alloc_error_handler]
+fn out_of_memory(_layout: Layout) -> ! {
+    panic!("out of memory");
+}
+
+// Smallest block of the heap, which holds a free block.
+const MIN_BLOCK: usize = size_of::<Hole>();
+
+/// A free block of the heap.
+/// # Elements
+/// * `size` - a usize, the size of the block in bytes.
+/// * `next` - a pointer to the next free block, at a higher address, or null.
+struct Hole {
+    size: usize,
+    next: *mut Hole,
+}
+
+/// State of the heap, changed with the interrupts disabled.
+/// # Elements
+/// * `arena` - an array of u8, the memory of the heap.
+/// * `first` - a pointer to the free block at the lowest address, or null.
+/// * `ready` - a boolean, true once the arena is made one free block.
+/// * `used` - a usize, the number of bytes allocated.
+struct State {
+    arena: [u8; HEAP_SIZE],
+    first: *mut Hole,
+    ready: bool,
+    used: usize,
+}
+
+/// First fit allocator over a static array.
+pub struct Heap {
+    state: UnsafeCell<State>,
+}
+
+// The state is only reached with the interrupts disabled.
+unsafe impl Sync for Heap {}
+
+impl Heap {
+    /// Creates the heap, whose free block is made at the first allocation.
+    /// # Returns
+    /// * `a Heap object` - the empty heap.
+    const fn new() -> Heap {
+        Heap {
+            state: UnsafeCell::new(State {
+                arena: [0; HEAP_SIZE],
+                first: null_mut(),
+                ready: false,
+                used: 0,
+            }),
+        }
+    }
+}
+
+impl State {
+    // Makes the whole arena one free block.
+    unsafe fn prepare(&mut self) {
+        let start = align_up(self.arena.as_mut_ptr() as usize, align_of::<Hole>());
+        let end = self.arena.as_mut_ptr() as usize + HEAP_SIZE;
+        if end >= start + MIN_BLOCK {
+            let hole = start as *mut Hole;
+            hole.write(Hole {
+                size: end - start,
+                next: null_mut(),
+            });
+            self.first = hole;
+        }
+        self.ready = true;
+    }
+
+    // Takes a block from the first free block it fits in. The part of the free block
+    // before the aligned block and the part after it stay free.
+    unsafe fn allocate(&mut self, size: usize, align: usize) -> *mut u8 {
+        let mut link: *mut *mut Hole = &mut self.first;
+        while !(*link).is_null() {
+            let hole = *link;
+            let start = hole as usize;
+            let end = start + (*hole).size;
+            let mut address = align_up(start, align);
+            if address != start && address - start < MIN_BLOCK {
+                address = align_up(start + MIN_BLOCK, align);
+            }
+            let after = end.saturating_sub(address + size);
+            if address + size <= end && (after == 0 || after >= MIN_BLOCK) {
+                let mut next = (*hole).next;
+                if after != 0 {
+                    let rest = (address + size) as *mut Hole;
+                    rest.write(Hole { size: after, next });
+                    next = rest;
+                }
+                if address == start {
+                    *link = next;
+                } else {
+                    (*hole).size = address - start;
+                    (*hole).next = next;
+                }
+                self.used += size;
+                return address as *mut u8;
+            }
+            link = &mut (*hole).next;
+        }
+        null_mut()
+    }
+
+    // Gives a block back, merging it with the free blocks just before and after it.
+    unsafe fn release(&mut self, pointer: *mut u8, size: usize) {
+        let address = pointer as usize;
+        let mut previous: *mut Hole = null_mut();
+        let mut next = self.first;
+        while !next.is_null() && (next as usize) < address {
+            previous = next;
+            next = (*next).next;
+        }
+        let block = address as *mut Hole;
+        block.write(Hole { size, next });
+        if !next.is_null() && address + size == next as usize {
+            (*block).size += (*next).size;
+            (*block).next = (*next).next;
+        }
+        if previous.is_null() {
+            self.first = block;
+        } else if previous as usize + (*previous).size == address {
+            (*previous).size += (*block).size;
+            (*previous).next = (*block).next;
+        } else {
+            (*previous).next = block;
+        }
+        self.used -= size;
+    }
+}
+
+unsafe impl GlobalAlloc for Heap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let (size, align) = block_layout(layout);
+        sync::free(|_| {
+            let state = &mut *self.state.get();
+            if !state.ready {
+                state.prepare();
+            }
+            state.allocate(size, align)
+        })
+    }
+
+    unsafe fn dealloc(&self, pointer: *mut u8, layout: Layout) {
+        let (size, _) = block_layout(layout);
+        sync::free(|_| (*self.state.get()).release(pointer, size))
+    }
+}
+
+/// Gives the number of bytes of the heap which are allocated, the blocks smaller
+/// than a free block being counted as one.
+/// # Returns
+/// * `a usize` - the number of bytes.
+pub fn used() -> usize {
+    sync::free(|_| unsafe { (*HEAP.state.get()).used })
+}
+
+/// Gives the number of bytes of the heap which are free. They may be split in
+/// blocks too small for a large allocation.
+/// # Returns
+/// * `a usize` - the number of bytes.
+pub fn free() -> usize {
+    HEAP_SIZE - used()
+}
+
+/// Gives the largest block which can be allocated now.
+/// # Returns
+/// * `a usize` - the number of bytes.
+pub fn largest_free_block() -> usize {
+    sync::free(|_| unsafe {
+        let state = &mut *HEAP.state.get();
+        if !state.ready {
+            state.prepare();
+        }
+        let mut largest = 0;
+        let mut hole = state.first;
+        while !hole.is_null() {
+            largest = largest.max((*hole).size);
+            hole = (*hole).next;
+        }
+        largest
+    })
+}
+
+// Gives the size and alignment of the block holding an allocation, large enough
+// to hold a free block once it is given back.
+fn block_layout(layout: Layout) -> (usize, usize) {
+    let size = align_up(layout.size().max(MIN_BLOCK), align_of::<Hole>());
+    let align = layout.align().max(align_of::<Hole>());
+    (size, align)
+}
+
+// Rounds an address up to a multiple of the alignment, which is a power of 2.
+fn align_up(address: usize, align: usize) -> usize {
+    (address + align - 1) & !(align - 1)
+}
@@ -0,0 +1,317 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! This code debounces push buttons and turns their presses into events: pressed,
+//! released, click, double click and long press.
+//! A button is read each time `update` is called, which should be often, and its level
+//! has to stay the same for the debounce interval before it is taken. The times are
+//! measured with `millis`, so `time::init` must be called.
+//! A button can also be watched by its pin change interrupt, so that a short press
+//! between two calls of `update` is not missed. Up to `MAX_BUTTONS` buttons can be
+//! watched, and the pin change groups of their pins can only be shared with the encoders.
+
+// Source codes required.
+use super::pin_change::InputPin;
+use crate::hal::interrupts::without_interrupts;
+use crate::hal::pin::make_pin;
+use crate::time::millis;
+
+/// Maximum number of buttons watched by the pin change interrupts.
+pub const MAX_BUTTONS: usize = 4;
+
+/// Events of a button.
+/// * `Pressed` - the button has been pushed down.
+/// * `Released` - the button has been let go.
+/// * `Click` - a short press not followed by another one within the double click interval.
+/// * `DoubleClick` - two short presses within the double click interval.
+/// * `LongPress` - the button has been held for the long press interval, given while it is
+///   still held, and its release gives no click.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ButtonEvent {
+    Pressed,
+    Released,
+    Click,
+    DoubleClick,
+    LongPress,
+}
+
+/// Level of a watched button, recorded by the interrupt routine.
+struct Watched {
+    pin: InputPin,
+    active_low: bool,
+    debounce_ms: u16,
+    pressed: bool,
+    changed: u32,
+    presses: u8,
+}
+
+static mut WATCHED: [Option<Watched>; MAX_BUTTONS] = [None, None, None, None];
+
+/// Used to read a push button.
+/// # Elements
+/// * `pin` - a u8, the digital pin of the button.
+/// * `input` - a `InputPin` object, the register of the pin.
+/// * `active_low` - a bool, true if the button connects the pin to the ground.
+/// * `debounce_ms` - a u16, the time the level must stay the same to be taken.
+/// * `long_press_ms` - a u16, the time the button must be held for a long press.
+/// * `double_click_ms` - a u16, the longest time between two clicks of a double click.
+/// * `slot` - a `Option<usize>`, the place of the button in the list of watched buttons.
+/// * `raw` - a bool, the last level read, true when pressed.
+/// * `raw_changed` - a u32, the time the last level read started.
+/// * `pressed` - a bool, the debounced level, true when pressed.
+/// * `pressed_at` - a u32, the time of the last debounced press.
+/// * `released_at` - a u32, the time of the last debounced release.
+/// * `clicks` - a u8, the number of short presses waiting to be reported.
+/// * `long_reported` - a bool, true once the long press of the present press is reported.
+/// * `pending` - a `Option<ButtonEvent>`, an event to be given by the next `update`.
+pub struct Button {
+    pin: u8,
+    input: InputPin,
+    active_low: bool,
+    debounce_ms: u16,
+    long_press_ms: u16,
+    double_click_ms: u16,
+    slot: Option<usize>,
+    raw: bool,
+    raw_changed: u32,
+    pressed: bool,
+    pressed_at: u32,
+    released_at: u32,
+    clicks: u8,
+    long_reported: bool,
+    pending: Option<ButtonEvent>,
+}
+
+impl Button {
+    /// Creates a button connected between a pin and the ground, using the pull-up of the pin.
+    /// The intervals are 20 ms to debounce, 800 ms for a long press and 300 ms
+    /// between the clicks of a double click.
+    /// # Arguments
+    /// * `pin` - a u8, the digital pin of the button.
+    /// # Returns
+    /// * `a Button object` - which will be used to read the events.
+    pub fn new(pin: u8) -> Button {
+        Button {
+            pin,
+            input: InputPin::new(pin),
+            active_low: true,
+            debounce_ms: 20,
+            long_press_ms: 800,
+            double_click_ms: 300,
+            slot: None,
+            raw: false,
+            raw_changed: millis(),
+            pressed: false,
+            pressed_at: 0,
+            released_at: 0,
+            clicks: 0,
+            long_reported: false,
+            pending: None,
+        }
+    }
+
+    /// Sets whether the button connects the pin to the ground (the default) or to VCC,
+    /// with an external pull-down resistor.
+    pub fn set_active_low(&mut self, active_low: bool) {
+        self.active_low = active_low;
+        // The pull-up would hold a button to VCC pressed.
+        let mut pin = make_pin(self.pin as _);
+        if active_low {
+            pin.high();
+        } else {
+            pin.low();
+        }
+        self.with_watched(|w| w.active_low = active_low);
+    }
+
+    /// Sets the time the level must stay the same to be taken.
+    pub fn set_debounce_ms(&mut self, ms: u16) {
+        self.debounce_ms = ms;
+        self.with_watched(|w| w.debounce_ms = ms);
+    }
+
+    /// Sets the time the button must be held for a long press.
+    pub fn set_long_press_ms(&mut self, ms: u16) {
+        self.long_press_ms = ms;
+    }
+
+    /// Sets the longest time between two clicks of a double click. With 0, a click is
+    /// given as soon as the button is released and there are no double clicks.
+    pub fn set_double_click_ms(&mut self, ms: u16) {
+        self.double_click_ms = ms;
+    }
+
+    /// Watches the button with its pin change interrupt, so that presses shorter than
+    /// the time between two calls of `update` are seen. The pin must have a pin change interrupt.
+    /// # Returns
+    /// * `a bool` - false if `MAX_BUTTONS` buttons are already watched, or if the pin has
+    ///   no pin change interrupt.
+    pub fn watch(&mut self) -> bool {
+        if self.slot.is_some() {
+            return true;
+        }
+        let pin = self.pin;
+        let active_low = self.active_low;
+        let debounce_ms = self.debounce_ms;
+        let (raw, raw_changed) = (self.raw, self.raw_changed);
+        self.slot = without_interrupts(|| unsafe {
+            let slot = WATCHED.iter().position(|w| w.is_none())?;
+            WATCHED[slot] = Some(Watched {
+                pin: InputPin::watch(pin).ok()?,
+                active_low,
+                debounce_ms,
+                pressed: raw,
+                changed: raw_changed,
+                presses: 0,
+            });
+            Some(slot)
+        });
+        self.slot.is_some()
+    }
+
+    /// Checks whether the button is held down, after debouncing.
+    pub fn is_pressed(&self) -> bool {
+        self.pressed
+    }
+
+    /// Reads the button and gives its next event.
+    /// # Returns
+    /// * `a Option<ButtonEvent>` - the event, None if nothing happened.
+    pub fn update(&mut self) -> Option<ButtonEvent> {
+        if let Some(event) = self.pending.take() {
+            return Some(event);
+        }
+        let now = millis();
+
+        // The level and the presses seen by the interrupt routine, or the level read now.
+        let mut presses = 0;
+        match self.slot {
+            Some(slot) => without_interrupts(|| unsafe {
+                if let Some(watched) = WATCHED[slot].as_mut() {
+                    self.raw = watched.pressed;
+                    self.raw_changed = watched.changed;
+                    presses = watched.presses;
+                    watched.presses = 0;
+                }
+            }),
+            None => {
+                let raw = self.input.is_high() != self.active_low;
+                if raw != self.raw {
+                    self.raw = raw;
+                    self.raw_changed = now;
+                }
+            }
+        }
+
+        if self.clicks == 1
+            && !self.pressed
+            && now.wrapping_sub(self.released_at) >= self.double_click_ms as u32
+        {
+            self.clicks = 0;
+            return Some(ButtonEvent::Click);
+        }
+
+        if self.raw != self.pressed && now.wrapping_sub(self.raw_changed) >= self.debounce_ms as u32
+        {
+            return Some(if self.raw {
+                self.press(now)
+            } else {
+                self.release(now)
+            });
+        }
+
+        // A press which came and went between two calls.
+        if presses > 0 && !self.pressed && !self.raw {
+            let event = self.press(now);
+            self.pending = Some(self.release(now));
+            return Some(event);
+        }
+
+        if self.pressed
+            && !self.long_reported
+            && now.wrapping_sub(self.pressed_at) >= self.long_press_ms as u32
+        {
+            self.long_reported = true;
+            self.clicks = 0;
+            return Some(ButtonEvent::LongPress);
+        }
+        None
+    }
+
+    fn press(&mut self, now: u32) -> ButtonEvent {
+        self.pressed = true;
+        self.pressed_at = now;
+        self.long_reported = false;
+        ButtonEvent::Pressed
+    }
+
+    /// Takes the release and queues the click which it completes, if any.
+    fn release(&mut self, now: u32) -> ButtonEvent {
+        self.pressed = false;
+        self.released_at = now;
+        if !self.long_reported {
+            self.clicks += 1;
+            if self.clicks == 2 {
+                self.clicks = 0;
+                self.pending = Some(ButtonEvent::DoubleClick);
+            } else if self.double_click_ms == 0 {
+                self.clicks = 0;
+                self.pending = Some(ButtonEvent::Click);
+            }
+        }
+        ButtonEvent::Released
+    }
+
+    fn with_watched<F: FnOnce(&mut Watched)>(&mut self, f: F) {
+        if let Some(slot) = self.slot {
+            without_interrupts(|| unsafe {
+                if let Some(watched) = WATCHED[slot].as_mut() {
+                    f(watched);
+                }
+            });
+        }
+    }
+}
+
+impl Drop for Button {
+    /// Removes the button from the list of watched buttons.
+    /// Its pin still calls the interrupt routine, which ignores it.
+    fn drop(&mut self) {
+        if let Some(slot) = self.slot {
+            without_interrupts(|| unsafe {
+                WATCHED[slot] = None;
+            });
+        }
+    }
+}
+
+/// Records the level of all the watched buttons, called by the pin change interrupts.
+pub(super) fn update() {
+    let watched = unsafe { &mut WATCHED };
+    for button in watched.iter_mut().flatten() {
+        let pressed = button.pin.is_high() != button.active_low;
+        if pressed != button.pressed {
+            let now = millis();
+            // A press is only counted after the button has been released for the debounce
+            // interval, so the bounces of both edges are ignored.
+            if pressed && now.wrapping_sub(button.changed) >= button.debounce_ms as u32 {
+                button.presses = button.presses.saturating_add(1);
+            }
+            button.pressed = pressed;
+            button.changed = now;
+        }
+    }
+}
@@ -0,0 +1,230 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! This code reads rotary encoders with quadrature outputs, as the mechanical knobs
+//! with a push button sold for menus.
+//! The two outputs are read by the pin change interrupts, and each change goes through
+//! a state machine which only counts a step once the outputs have gone through the whole
+//! sequence between two detents, so the bounces of the contacts are ignored without
+//! any delay. The push button is debounced with `millis`, so `time::init` must be called.
+//! Up to `MAX_ENCODERS` encoders can be used. The pin change groups of their pins
+//! can only be shared with the buttons.
+//! See `<http://www.buxtronix.net/2011/10/rotary-encoders-done-properly.html>`.
+
+// Source codes required.
+use super::pin_change::InputPin;
+use crate::error::Error;
+use crate::hal::interrupts::without_interrupts;
+use crate::time::millis;
+
+/// Maximum number of encoders used at the same time.
+pub const MAX_ENCODERS: usize = 4;
+
+/// Milliseconds the push button has to stay released before another press is counted.
+const BUTTON_DEBOUNCE_MS: u32 = 20;
+
+// States of the decoder, from the rest position where both outputs are high.
+const START: u8 = 0x0;
+const CW_FINAL: u8 = 0x1;
+const CW_BEGIN: u8 = 0x2;
+const CW_NEXT: u8 = 0x3;
+const CCW_BEGIN: u8 = 0x4;
+const CCW_FINAL: u8 = 0x5;
+const CCW_NEXT: u8 = 0x6;
+
+// Flags of a completed step, returned with the next state.
+const STEP_CW: u8 = 0x10;
+const STEP_CCW: u8 = 0x20;
+
+/// Next state for each state and each level of the outputs (B << 1 | A).
+const TRANSITIONS: [[u8; 4]; 7] = [
+    // START
+    [START, CW_BEGIN, CCW_BEGIN, START],
+    // CW_FINAL
+    [CW_NEXT, START, CW_FINAL, START | STEP_CW],
+    // CW_BEGIN
+    [CW_NEXT, CW_BEGIN, START, START],
+    // CW_NEXT
+    [CW_NEXT, CW_BEGIN, CW_FINAL, START],
+    // CCW_BEGIN
+    [CCW_NEXT, START, CCW_BEGIN, START],
+    // CCW_FINAL
+    [CCW_NEXT, CCW_FINAL, START, START | STEP_CCW],
+    // CCW_NEXT
+    [CCW_NEXT, CCW_FINAL, CCW_BEGIN, START],
+];
+
+/// State of an encoder, shared with the interrupt service routine.
+struct EncoderState {
+    a: InputPin,
+    b: InputPin,
+    state: u8,
+    position: i32,
+    last_read: i32,
+    button: Option<InputPin>,
+    button_down: bool,
+    button_changed: u32,
+    presses: u8,
+}
+
+static mut ENCODERS: [Option<EncoderState>; MAX_ENCODERS] = [None, None, None, None];
+
+/// Used to read a rotary encoder.
+/// # Elements
+/// * `slot` - a usize, the place of the encoder in the list read by the interrupt routine.
+pub struct Encoder {
+    slot: usize,
+}
+
+impl Encoder {
+    /// Creates an encoder, sets its pins as inputs with pull-ups and enables
+    /// their pin change interrupts. The position starts at 0.
+    /// # Arguments
+    /// * `a` - a u8, the digital pin connected to output A, which must have a pin change interrupt.
+    /// * `b` - a u8, the digital pin connected to output B, which must have a pin change interrupt.
+    /// # Returns
+    /// * `a Result<Encoder, Error>` - the encoder, `Error::Busy` if `MAX_ENCODERS` encoders
+    ///   already exist, or `Error::InvalidParam` if a pin has no pin change interrupt.
+    pub fn new(a: u8, b: u8) -> Result<Encoder, Error> {
+        without_interrupts(|| unsafe {
+            let slot = ENCODERS
+                .iter()
+                .position(|e| e.is_none())
+                .ok_or(Error::Busy)?;
+            ENCODERS[slot] = Some(EncoderState {
+                a: InputPin::watch(a)?,
+                b: InputPin::watch(b)?,
+                state: START,
+                position: 0,
+                last_read: 0,
+                button: None,
+                button_down: false,
+                button_changed: 0,
+                presses: 0,
+            });
+            Ok(Encoder { slot })
+        })
+    }
+
+    /// Adds the push button of the encoder, connected between a pin and the ground.
+    /// # Arguments
+    /// * `pin` - a u8, the digital pin of the button, which must have a pin change interrupt.
+    /// # Returns
+    /// * `a Result<(), Error>` - which is `Error::InvalidParam` if the pin has no pin
+    ///   change interrupt.
+    pub fn set_button(&mut self, pin: u8) -> Result<(), Error> {
+        let button = InputPin::watch(pin)?;
+        let slot = self.slot;
+        without_interrupts(|| unsafe {
+            if let Some(encoder) = ENCODERS[slot].as_mut() {
+                encoder.button = Some(button);
+            }
+        });
+        Ok(())
+    }
+
+    /// Gives the position, the number of steps clockwise minus the steps counter clockwise.
+    pub fn position(&self) -> i32 {
+        self.with_state(|e| e.position)
+    }
+
+    /// Sets the position.
+    /// # Arguments
+    /// * `position` - a i32, the new position.
+    pub fn set_position(&mut self, position: i32) {
+        self.with_state(|e| {
+            e.position = position;
+            e.last_read = position;
+        });
+    }
+
+    /// Gives the number of steps since the last call, positive clockwise.
+    /// # Returns
+    /// * `a i32` - the change of the position.
+    pub fn delta(&mut self) -> i32 {
+        self.with_state(|e| {
+            let delta = e.position.wrapping_sub(e.last_read);
+            e.last_read = e.position;
+            delta
+        })
+    }
+
+    /// Checks whether the push button has been pressed since the last call.
+    pub fn button_pressed(&mut self) -> bool {
+        self.with_state(|e| {
+            let pressed = e.presses > 0;
+            e.presses = 0;
+            pressed
+        })
+    }
+
+    /// Checks whether the push button is held down now.
+    pub fn button_down(&self) -> bool {
+        self.with_state(|e| e.button_down)
+    }
+
+    fn with_state<F, R>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut EncoderState) -> R,
+    {
+        let slot = self.slot;
+        without_interrupts(|| unsafe {
+            match ENCODERS[slot].as_mut() {
+                Some(encoder) => f(encoder),
+                None => unreachable!(),
+            }
+        })
+    }
+}
+
+impl Drop for Encoder {
+    /// Removes the encoder from the list read by the interrupt routine.
+    /// Its pins still call the routine, which ignores them.
+    fn drop(&mut self) {
+        let slot = self.slot;
+        without_interrupts(|| unsafe {
+            ENCODERS[slot] = None;
+        });
+    }
+}
+
+/// Reads all the encoders, called by the pin change interrupts.
+pub(super) fn update() {
+    let encoders = unsafe { &mut ENCODERS };
+    for encoder in encoders.iter_mut().flatten() {
+        let levels = (encoder.b.is_high() as u8) << 1 | encoder.a.is_high() as u8;
+        encoder.state = TRANSITIONS[(encoder.state & 0x0F) as usize][levels as usize];
+        if encoder.state & STEP_CW != 0 {
+            encoder.position = encoder.position.wrapping_add(1);
+        } else if encoder.state & STEP_CCW != 0 {
+            encoder.position = encoder.position.wrapping_sub(1);
+        }
+
+        if let Some(button) = encoder.button {
+            let down = !button.is_high();
+            if down != encoder.button_down {
+                let now = millis();
+                // A press is only counted after the button has been released for a while,
+                // so the bounces of both edges are ignored.
+                if down && now.wrapping_sub(encoder.button_changed) >= BUTTON_DEBOUNCE_MS {
+                    encoder.presses = encoder.presses.saturating_add(1);
+                }
+                encoder.button_down = down;
+                encoder.button_changed = now;
+            }
+        }
+    }
+}
@@ -0,0 +1,219 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! This code scans matrix keypads, as the 4x3 and 4x4 membrane keypads.
+//! Each row is pulled low in turn while the columns are read with their pull-ups, so
+//! a pressed key reads low on its column. The whole matrix is debounced: a new state is
+//! taken once the scans have been the same for the debounce interval, measured with
+//! `millis`, so `time::init` must be called.
+//! Without a diode for each key, three keys pressed at the corners of a rectangle make
+//! the fourth corner look pressed too. Such scans are ambiguous and are not taken.
+
+// Source codes required.
+use crate::delay::delay_us;
+use crate::error::Error;
+use crate::hal::pin::make_pin;
+use crate::time::millis;
+
+/// Change of a key.
+/// # Elements
+/// * `row` - a u8, the row of the key.
+/// * `col` - a u8, the column of the key.
+/// * `pressed` - a bool, true if the key has been pressed, false if released.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct KeyEvent {
+    pub row: u8,
+    pub col: u8,
+    pub pressed: bool,
+}
+
+/// Used to scan a keypad of `R` rows and `C` columns, with at most 16 columns.
+/// # Elements
+/// * `rows` - a array of u8, the digital pins of the rows.
+/// * `cols` - a array of u8, the digital pins of the columns.
+/// * `keymap` - a `Option` of a array of char, the character of each key.
+/// * `debounce_ms` - a u16, the time the scans must stay the same to be taken.
+/// * `raw` - a array of u16, the columns pressed in each row at the last scan.
+/// * `raw_changed` - a u32, the time the last scan started to be the same.
+/// * `stable` - a array of u16, the debounced state.
+/// * `reported` - a array of u16, the state given by the events so far.
+/// * `ghosting` - a bool, true if the last scan was ambiguous.
+pub struct Keypad<const R: usize, const C: usize> {
+    rows: [u8; R],
+    cols: [u8; C],
+    keymap: Option<[[char; C]; R]>,
+    debounce_ms: u16,
+    raw: [u16; R],
+    raw_changed: u32,
+    stable: [u16; R],
+    reported: [u16; R],
+    ghosting: bool,
+}
+
+impl<const R: usize, const C: usize> Keypad<R, C> {
+    /// Creates the keypad, releases the rows and sets the columns as inputs with pull-ups.
+    /// The keys are debounced for 10 ms.
+    /// # Arguments
+    /// * `rows` - a array of u8, the digital pins of the rows.
+    /// * `cols` - a array of u8, the digital pins of the columns.
+    /// # Returns
+    /// * `a Result<Keypad, Error>` - the keypad, or `Error::InvalidParam` if it has more
+    ///   than 16 columns.
+    pub fn new(rows: [u8; R], cols: [u8; C]) -> Result<Keypad<R, C>, Error> {
+        if C > 16 {
+            return Err(Error::InvalidParam);
+        }
+        for &row in rows.iter() {
+            let mut pin = make_pin(row as _);
+            pin.set_input();
+            pin.low();
+        }
+        for &col in cols.iter() {
+            let mut pin = make_pin(col as _);
+            pin.set_input();
+            pin.high();
+        }
+        Ok(Keypad {
+            rows,
+            cols,
+            keymap: None,
+            debounce_ms: 10,
+            raw: [0; R],
+            raw_changed: millis(),
+            stable: [0; R],
+            reported: [0; R],
+            ghosting: false,
+        })
+    }
+
+    /// Sets the character of each key, given by `get_key`.
+    /// # Arguments
+    /// * `keymap` - a array of arrays of char, the characters row by row.
+    pub fn set_keymap(&mut self, keymap: [[char; C]; R]) {
+        self.keymap = Some(keymap);
+    }
+
+    /// Sets the time the scans must stay the same to be taken.
+    pub fn set_debounce_ms(&mut self, ms: u16) {
+        self.debounce_ms = ms;
+    }
+
+    /// Scans the keypad and takes the new state once it is debounced.
+    /// # Returns
+    /// * `a bool` - true if the debounced state has changed.
+    pub fn update(&mut self) -> bool {
+        let mut scan = [0u16; R];
+        for (r, &row) in self.rows.iter().enumerate() {
+            // PORTxn is left low, so the row is driven low as an output and floats as an input.
+            let mut pin = make_pin(row as _);
+            pin.set_output();
+            delay_us(5);
+            for (c, &col) in self.cols.iter().enumerate() {
+                if !make_pin(col as _).is_high() {
+                    scan[r] |= 1 << c;
+                }
+            }
+            pin.set_input();
+        }
+
+        let now = millis();
+        if scan != self.raw {
+            self.raw = scan;
+            self.raw_changed = now;
+            return false;
+        }
+        self.ghosting = is_ambiguous(&scan);
+        if self.ghosting
+            || scan == self.stable
+            || now.wrapping_sub(self.raw_changed) < self.debounce_ms as u32
+        {
+            return false;
+        }
+        self.stable = scan;
+        true
+    }
+
+    /// Checks whether the last scan was ambiguous, because keys at three corners
+    /// of a rectangle are pressed.
+    pub fn ghosting(&self) -> bool {
+        self.ghosting
+    }
+
+    /// Checks whether a key is pressed, after debouncing.
+    /// # Arguments
+    /// * `row` - a usize, the row of the key.
+    /// * `col` - a usize, the column of the key.
+    pub fn is_pressed(&self, row: usize, col: usize) -> bool {
+        self.stable[row] & (1 << col) != 0
+    }
+
+    /// Gives the number of keys pressed, after debouncing.
+    pub fn pressed_count(&self) -> usize {
+        self.stable.iter().map(|r| r.count_ones() as usize).sum()
+    }
+
+    /// Scans the keypad and gives the next key which has been pressed or released.
+    /// Several keys changing at once are given by successive calls.
+    /// # Returns
+    /// * `a Option<KeyEvent>` - the change, None if no key has changed.
+    pub fn next_event(&mut self) -> Option<KeyEvent> {
+        if self.stable == self.reported {
+            self.update();
+        }
+        let rows = self.stable.iter().zip(self.reported.iter_mut());
+        for (row, (&stable, reported)) in rows.enumerate() {
+            let changed = stable ^ *reported;
+            if changed != 0 {
+                let col = changed.trailing_zeros() as u8;
+                *reported ^= 1 << col;
+                return Some(KeyEvent {
+                    row: row as u8,
+                    col,
+                    pressed: stable & (1 << col) != 0,
+                });
+            }
+        }
+        None
+    }
+
+    /// Scans the keypad and gives the character of the next key which has been pressed.
+    /// The releases are skipped.
+    /// # Returns
+    /// * `a Option<char>` - the character of the key, None without keymap or new press.
+    pub fn get_key(&mut self) -> Option<char> {
+        while let Some(event) = self.next_event() {
+            if event.pressed {
+                return self.key_char(&event);
+            }
+        }
+        None
+    }
+
+    /// Gives the character of the key of an event from the keymap.
+    pub fn key_char(&self, event: &KeyEvent) -> Option<char> {
+        self.keymap
+            .as_ref()
+            .map(|keymap| keymap[event.row as usize][event.col as usize])
+    }
+}
+
+/// Checks whether two rows share two pressed columns, in which case a key can look
+/// pressed through the three others.
+fn is_ambiguous(scan: &[u16]) -> bool {
+    scan.iter()
+        .enumerate()
+        .any(|(i, &a)| scan[i + 1..].iter().any(|&b| (a & b).count_ones() >= 2))
+}
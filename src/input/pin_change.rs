@@ -0,0 +1,80 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Pin change interrupts shared by the input drivers.
+//! Each pin change group has a single handler, so the pins watched by the encoders and
+//! the buttons are collected here and one handler updates all of them.
+
+// Source codes required.
+use super::{button, encoder, ps2};
+use crate::error::Error;
+use crate::hal::interrupts::{attach_pin_change, digital_pin_to_pcint, without_interrupts};
+use crate::hal::pin::make_pin;
+
+use core::ptr::read_volatile;
+
+// Pins of each pin change group watched by the input drivers.
+static mut GROUP_MASKS: [u8; 3] = [0; 3];
+
+/// Input register and bit of a pin, which can be read from an interrupt routine.
+#[derive(Clone, Copy)]
+pub(crate) struct InputPin {
+    register: *const u8,
+    mask: u8,
+}
+
+impl InputPin {
+    /// Sets a pin as an input with its pull-up.
+    /// # Arguments
+    /// * `pin` - a u8, the digital pin number.
+    pub(crate) fn new(pin: u8) -> InputPin {
+        let mut p = make_pin(pin as _);
+        p.set_input();
+        p.high();
+        InputPin {
+            register: unsafe { &(*p.port).pin as *const u8 },
+            mask: 1u8 << p.pin,
+        }
+    }
+
+    /// Sets a pin as an input with its pull-up and enables its pin change interrupt.
+    /// # Arguments
+    /// * `pin` - a u8, the digital pin number, which must have a pin change interrupt.
+    /// # Returns
+    /// * `a Result<InputPin, Error>` - the pin, or `Error::InvalidParam` if it has no
+    ///   pin change interrupt.
+    pub(crate) fn watch(pin: u8) -> Result<InputPin, Error> {
+        let (group, bit) = digital_pin_to_pcint(pin as _).ok_or(Error::InvalidParam)?;
+        let input = InputPin::new(pin);
+        without_interrupts(|| unsafe {
+            GROUP_MASKS[group as usize] |= 1 << bit;
+            attach_pin_change(group, GROUP_MASKS[group as usize], update)
+        })?;
+        Ok(input)
+    }
+
+    /// Reads the level on the pin.
+    pub(crate) fn is_high(&self) -> bool {
+        unsafe { read_volatile(self.register) & self.mask != 0 }
+    }
+}
+
+/// Updates all the encoders, buttons and PS/2 devices, called by the pin change interrupts.
+fn update() {
+    encoder::update();
+    button::update();
+    ps2::update();
+}
@@ -0,0 +1,667 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! This code reads PS/2 keyboards and mice.
+//! The device drives the clock line at 10 to 17 kHz and each frame is a start bit, 8 data
+//! bits with the least significant first, an odd parity bit and a stop bit, read on the
+//! falling edges of the clock. The clock pin is watched by its pin change interrupt, which
+//! collects the bits and keeps the bytes received in a buffer, so it must have a pin change
+//! interrupt and the global interrupts must be enabled. The data pin can be any pin.
+//! Both lines are open collector, they are pulled up by the pins and only ever driven low.
+//! Commands are sent to the device by holding the clock low, after which the device clocks
+//! the bits in. This blocks for about 2 ms.
+//! `Ps2Keyboard` turns the scan codes of set 2 into key events, with the characters given
+//! by a `Keymap`, and `Ps2Mouse` decodes the movement packets of a mouse.
+//! Up to `MAX_PS2_PORTS` devices can be used. The times are measured with `micros`, so
+//! `time::init` must be called.
+//! See `<https://www.avrfreaks.net/sites/default/files/PS2%20Keyboard.pdf>`.
+
+// Source codes required.
+use super::pin_change::InputPin;
+use crate::collections::RingBuffer;
+use crate::delay::delay_us;
+use crate::error::Error;
+use crate::hal::interrupts::without_interrupts;
+use crate::hal::pin::make_pin;
+use crate::progmem::ProgMem;
+use crate::time::micros;
+
+use core::ptr::{read_volatile, write_volatile};
+
+/// Maximum number of PS/2 devices.
+pub const MAX_PS2_PORTS: usize = 2;
+
+/// Size of the buffer of the bytes received from a device, which holds one byte less.
+pub const PS2_BUFFER_SIZE: usize = 16;
+
+/// Longest time between two clock edges of a frame, after which a new frame starts.
+const BIT_TIMEOUT_US: u32 = 2000;
+
+/// Longest time the device may take to start clocking a command in.
+const START_TIMEOUT_US: u32 = 15_000;
+
+/// Longest time the device may take to answer a command.
+const REPLY_TIMEOUT_US: u32 = 25_000;
+
+// Replies of the devices.
+const ACK: u8 = 0xFA;
+const RESEND: u8 = 0xFE;
+
+/// Open collector line, pulled up by the pin or driven low.
+#[derive(Clone, Copy)]
+struct OpenDrain {
+    input: *const u8,
+    ddr: *mut u8,
+    port: *mut u8,
+    mask: u8,
+}
+
+impl OpenDrain {
+    fn new(pin: u8) -> OpenDrain {
+        let p = make_pin(pin as _);
+        let mut line = unsafe {
+            OpenDrain {
+                input: &(*p.port).pin as *const u8,
+                ddr: &mut (*p.port).ddr as *mut u8,
+                port: &mut (*p.port).port as *mut u8,
+                mask: 1u8 << p.pin,
+            }
+        };
+        line.release();
+        line
+    }
+
+    fn is_high(&self) -> bool {
+        unsafe { read_volatile(self.input) & self.mask != 0 }
+    }
+
+    fn release(&mut self) {
+        without_interrupts(|| unsafe {
+            write_volatile(self.ddr, read_volatile(self.ddr) & !self.mask);
+            write_volatile(self.port, read_volatile(self.port) | self.mask);
+        });
+    }
+
+    fn pull_low(&mut self) {
+        without_interrupts(|| unsafe {
+            write_volatile(self.port, read_volatile(self.port) & !self.mask);
+            write_volatile(self.ddr, read_volatile(self.ddr) | self.mask);
+        });
+    }
+}
+
+/// State of a device, updated by the interrupt routine.
+struct Port {
+    clock: InputPin,
+    data: OpenDrain,
+    clock_high: bool,
+    sending: bool,
+    frame: u16,
+    bits: u8,
+    last_edge: u32,
+    errors: u8,
+}
+
+static mut PORTS: [Option<Port>; MAX_PS2_PORTS] = [None, None];
+static RECEIVED: [RingBuffer<u8, PS2_BUFFER_SIZE>; MAX_PS2_PORTS] =
+    [RingBuffer::new(), RingBuffer::new()];
+
+/// Connection to a PS/2 device, shared by the keyboard and the mouse.
+/// # Elements
+/// * `slot` - a usize, the place of the device in the list of devices.
+/// * `clock` - a `OpenDrain` object, the clock line.
+/// * `data` - a `OpenDrain` object, the data line.
+struct Ps2 {
+    slot: usize,
+    clock: OpenDrain,
+    data: OpenDrain,
+}
+
+impl Ps2 {
+    fn new(clock: u8, data: u8) -> Result<Ps2, Error> {
+        let data_line = OpenDrain::new(data);
+        let clock_line = OpenDrain::new(clock);
+        let clock_input = InputPin::watch(clock)?;
+        let slot = without_interrupts(|| unsafe {
+            let slot = PORTS.iter().position(|port| port.is_none())?;
+            while RECEIVED[slot].pop().is_some() {}
+            PORTS[slot] = Some(Port {
+                clock: clock_input,
+                data: data_line,
+                clock_high: true,
+                sending: false,
+                frame: 0,
+                bits: 0,
+                last_edge: 0,
+                errors: 0,
+            });
+            Some(slot)
+        })
+        .ok_or(Error::Busy)?;
+        Ok(Ps2 {
+            slot,
+            clock: clock_line,
+            data: data_line,
+        })
+    }
+
+    fn read(&mut self) -> Option<u8> {
+        RECEIVED[self.slot].pop()
+    }
+
+    fn errors(&self) -> u8 {
+        without_interrupts(|| unsafe {
+            PORTS[self.slot]
+                .as_ref()
+                .map(|port| port.errors)
+                .unwrap_or(0)
+        })
+    }
+
+    // Sets whether the interrupt routine leaves the lines to the sending of a command.
+    fn set_sending(&mut self, sending: bool) {
+        without_interrupts(|| unsafe {
+            if let Some(port) = PORTS[self.slot].as_mut() {
+                port.sending = sending;
+                port.clock_high = true;
+                port.frame = 0;
+                port.bits = 0;
+            }
+        });
+    }
+
+    // Sends a byte to the device, which clocks in the bits after the clock is held low.
+    fn send(&mut self, byte: u8) -> Result<(), Error> {
+        self.set_sending(true);
+        let result = self.send_frame(byte);
+        self.data.release();
+        self.clock.release();
+        self.set_sending(false);
+        result
+    }
+
+    fn send_frame(&mut self, byte: u8) -> Result<(), Error> {
+        // The request to send: the clock is held low for 100 us, then the start bit
+        // is put on the data line and the clock is released.
+        self.clock.pull_low();
+        delay_us(120);
+        self.data.pull_low();
+        self.clock.release();
+
+        let parity = byte.count_ones() % 2 == 0;
+        let mut timeout = START_TIMEOUT_US;
+        for bit in 0..10 {
+            let high = match bit {
+                0..=7 => byte >> bit & 1 != 0,
+                8 => parity,
+                _ => true,
+            };
+            // The device reads each bit on the rising edge of the clock.
+            self.wait_clock(false, timeout)?;
+            if high {
+                self.data.release();
+            } else {
+                self.data.pull_low();
+            }
+            self.wait_clock(true, BIT_TIMEOUT_US)?;
+            timeout = BIT_TIMEOUT_US;
+        }
+        // The device acknowledges by pulling the data line low for one clock.
+        self.wait_clock(false, BIT_TIMEOUT_US)?;
+        let acknowledged = !self.data.is_high();
+        self.wait_clock(true, BIT_TIMEOUT_US)?;
+        if acknowledged {
+            Ok(())
+        } else {
+            Err(Error::Nack)
+        }
+    }
+
+    fn wait_clock(&self, high: bool, timeout: u32) -> Result<(), Error> {
+        let start = micros();
+        while self.clock.is_high() != high {
+            if micros().wrapping_sub(start) > timeout {
+                return Err(Error::Timeout);
+            }
+        }
+        Ok(())
+    }
+
+    // Sends a command and waits for its acknowledge, sending it again if asked to.
+    // The bytes received before the acknowledge are dropped.
+    fn command(&mut self, byte: u8) -> Result<(), Error> {
+        for _ in 0..3 {
+            self.send(byte)?;
+            let start = micros();
+            loop {
+                match self.read() {
+                    Some(ACK) => return Ok(()),
+                    Some(RESEND) => break,
+                    _ if micros().wrapping_sub(start) > REPLY_TIMEOUT_US => {
+                        return Err(Error::Timeout)
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Err(Error::Device)
+    }
+}
+
+impl Drop for Ps2 {
+    /// Removes the device from the list of devices.
+    /// Its clock pin still calls the interrupt routine, which ignores it.
+    fn drop(&mut self) {
+        let slot = self.slot;
+        without_interrupts(|| unsafe {
+            PORTS[slot] = None;
+        });
+    }
+}
+
+/// Collects the bits of all the devices, called by the pin change interrupts.
+pub(super) fn update() {
+    let ports = unsafe { &mut PORTS };
+    for (slot, port) in ports.iter_mut().enumerate() {
+        let port = match port {
+            Some(port) if !port.sending => port,
+            _ => continue,
+        };
+        let clock_high = port.clock.is_high();
+        let falling = port.clock_high && !clock_high;
+        port.clock_high = clock_high;
+        if !falling {
+            continue;
+        }
+        let now = micros();
+        if now.wrapping_sub(port.last_edge) > BIT_TIMEOUT_US {
+            port.frame = 0;
+            port.bits = 0;
+        }
+        port.last_edge = now;
+        if port.data.is_high() {
+            port.frame |= 1 << port.bits;
+        }
+        port.bits += 1;
+        if port.bits == 11 {
+            let frame = port.frame;
+            let start = frame & 0x001 == 0;
+            let stop = frame & 0x400 != 0;
+            // The data and parity bits have an odd number of ones.
+            let parity = (frame >> 1 & 0x1FF).count_ones() % 2 == 1;
+            if start && stop && parity {
+                RECEIVED[slot].push((frame >> 1) as u8).ok();
+            } else {
+                port.errors = port.errors.saturating_add(1);
+            }
+            port.frame = 0;
+            port.bits = 0;
+        }
+    }
+}
+
+/// Characters of the scan codes of set 2, without and with shift, 0 for the keys which
+/// give no character. The letters are lower case in `normal` and turned to upper case
+/// by caps lock.
+/// # Elements
+/// * `normal` - a reference to a `ProgMem` array of u8, the characters without shift.
+/// * `shifted` - a reference to a `ProgMem` array of u8, the characters with shift.
+#[derive(Clone, Copy)]
+pub struct Keymap {
+    pub normal: &'static ProgMem<[u8; 0x84]>,
+    pub shifted: &'static ProgMem<[u8; 0x84]>,
+}
+
+impl Keymap {
+    /// Gives the US layout.
+    pub fn us() -> Keymap {
+        Keymap {
+            normal: &KEYMAP_US_NORMAL,
+            shifted: &KEYMAP_US_SHIFTED,
+        }
+    }
+}
+
+crate::progmem! {
+    /// Characters of the US layout without shift, indexed by scan code.
+    pub static KEYMAP_US_NORMAL: [u8; 0x84] = [
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, b'\t', b'`', 0,
+        0, 0, 0, 0, 0, b'q', b'1', 0, 0, 0, b'z', b's', b'a', b'w', b'2', 0,
+        0, b'c', b'x', b'd', b'e', b'4', b'3', 0, 0, b' ', b'v', b'f', b't', b'r', b'5', 0,
+        0, b'n', b'b', b'h', b'g', b'y', b'6', 0, 0, 0, b'm', b'j', b'u', b'7', b'8', 0,
+        0, b',', b'k', b'i', b'o', b'0', b'9', 0, 0, b'.', b'/', b'l', b';', b'p', b'-', 0,
+        0, 0, b'\'', 0, b'[', b'=', 0, 0, 0, 0, b'\n', b']', 0, b'\\', 0, 0,
+        0, 0, 0, 0, 0, 0, 8, 0, 0, b'1', 0, b'4', b'7', 0, 0, 0,
+        b'0', b'.', b'2', b'5', b'6', b'8', 27, 0, 0, b'+', b'3', b'-', b'*', b'9', 0, 0,
+        0, 0, 0, 0,
+    ];
+
+    /// Characters of the US layout with shift, indexed by scan code.
+    pub static KEYMAP_US_SHIFTED: [u8; 0x84] = [
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, b'\t', b'~', 0,
+        0, 0, 0, 0, 0, b'Q', b'!', 0, 0, 0, b'Z', b'S', b'A', b'W', b'@', 0,
+        0, b'C', b'X', b'D', b'E', b'$', b'#', 0, 0, b' ', b'V', b'F', b'T', b'R', b'%', 0,
+        0, b'N', b'B', b'H', b'G', b'Y', b'^', 0, 0, 0, b'M', b'J', b'U', b'&', b'*', 0,
+        0, b'<', b'K', b'I', b'O', b')', b'(', 0, 0, b'>', b'?', b'L', b':', b'P', b'_', 0,
+        0, 0, b'"', 0, b'{', b'+', 0, 0, 0, 0, b'\n', b'}', 0, b'|', 0, 0,
+        0, 0, 0, 0, 0, 0, 8, 0, 0, b'1', 0, b'4', b'7', 0, 0, 0,
+        b'0', b'.', b'2', b'5', b'6', b'8', 27, 0, 0, b'+', b'3', b'-', b'*', b'9', 0, 0,
+        0, 0, 0, 0,
+    ];
+}
+
+// Scan codes of the modifier keys, the right Ctrl and Alt keys are the same after 0xE0.
+const LEFT_SHIFT: u8 = 0x12;
+const RIGHT_SHIFT: u8 = 0x59;
+const CTRL: u8 = 0x14;
+const ALT: u8 = 0x11;
+const CAPS_LOCK: u8 = 0x58;
+const NUM_LOCK: u8 = 0x77;
+const SCROLL_LOCK: u8 = 0x7E;
+
+/// State of the modifier and lock keys.
+/// * `shift` - a bool, true while a shift key is held.
+/// * `ctrl` - a bool, true while a Ctrl key is held.
+/// * `alt` - a bool, true while an Alt key is held.
+/// * `caps_lock` - a bool, toggled by the caps lock key.
+/// * `num_lock` - a bool, toggled by the num lock key.
+/// * `scroll_lock` - a bool, toggled by the scroll lock key.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct Modifiers {
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+    pub caps_lock: bool,
+    pub num_lock: bool,
+    pub scroll_lock: bool,
+}
+
+/// A key pressed or released.
+/// * `code` - a u8, the scan code of set 2.
+/// * `extended` - a bool, true for the keys whose code follows 0xE0, as the arrows.
+/// * `pressed` - a bool, true when pressed, false when released.
+/// * `character` - a `Option<u8>`, the ASCII character of a pressed key, with Ctrl
+///   giving the control characters.
+/// * `modifiers` - a `Modifiers` object, the state after the key.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct KeyEvent {
+    pub code: u8,
+    pub extended: bool,
+    pub pressed: bool,
+    pub character: Option<u8>,
+    pub modifiers: Modifiers,
+}
+
+/// Used to read a PS/2 keyboard.
+/// # Elements
+/// * `port` - a `Ps2` object, the connection to the keyboard.
+/// * `keymap` - a `Keymap` object, the characters of the keys.
+/// * `modifiers` - a `Modifiers` object, the state of the modifier keys.
+/// * `shifts` - a u8, bit 0 for the left shift key and bit 1 for the right one.
+/// * `release` - a bool, true after the 0xF0 prefix of a release.
+/// * `extended` - a bool, true after the 0xE0 prefix.
+/// * `skip` - a u8, the bytes of the pause key still to be dropped.
+pub struct Ps2Keyboard {
+    port: Ps2,
+    keymap: Keymap,
+    modifiers: Modifiers,
+    shifts: u8,
+    release: bool,
+    extended: bool,
+    skip: u8,
+}
+
+impl Ps2Keyboard {
+    /// Starts reading a keyboard with the US layout.
+    /// # Arguments
+    /// * `clock` - a u8, the digital pin of the clock line, with a pin change interrupt.
+    /// * `data` - a u8, the digital pin of the data line.
+    /// # Returns
+    /// * `a Result<Ps2Keyboard, Error>` - the keyboard, or `Error::Busy` if `MAX_PS2_PORTS`
+    ///   devices are already used.
+    pub fn new(clock: u8, data: u8) -> Result<Ps2Keyboard, Error> {
+        Ok(Ps2Keyboard {
+            port: Ps2::new(clock, data)?,
+            keymap: Keymap::us(),
+            modifiers: Modifiers::default(),
+            shifts: 0,
+            release: false,
+            extended: false,
+            skip: 0,
+        })
+    }
+
+    /// Sets the characters given by the keys.
+    /// # Arguments
+    /// * `keymap` - a `Keymap` object, the layout of the keyboard.
+    pub fn set_keymap(&mut self, keymap: Keymap) {
+        self.keymap = keymap;
+    }
+
+    /// Gives the state of the modifier and lock keys.
+    pub fn modifiers(&self) -> Modifiers {
+        self.modifiers
+    }
+
+    /// Gives the number of frames dropped for a wrong start, stop or parity bit.
+    pub fn errors(&self) -> u8 {
+        self.port.errors()
+    }
+
+    /// Lights the lock LEDs of the keyboard as the lock keys are, which the keyboard
+    /// does not do by itself.
+    /// # Returns
+    /// * `a Result<(), Error>` - which is an error if the keyboard did not take the command.
+    pub fn update_leds(&mut self) -> Result<(), Error> {
+        let leds = self.modifiers.scroll_lock as u8
+            | (self.modifiers.num_lock as u8) << 1
+            | (self.modifiers.caps_lock as u8) << 2;
+        self.port.command(0xED)?;
+        self.port.command(leds)
+    }
+
+    /// Decodes the bytes received and gives the next key event.
+    /// # Returns
+    /// * `a Option<KeyEvent>` - the event, None if no key was pressed or released.
+    pub fn update(&mut self) -> Option<KeyEvent> {
+        while let Some(byte) = self.port.read() {
+            if self.skip > 0 {
+                self.skip -= 1;
+                continue;
+            }
+            match byte {
+                0xE0 => self.extended = true,
+                0xF0 => self.release = true,
+                // The pause key sends E1 14 77 E1 F0 14 F0 77 and has no release.
+                0xE1 => self.skip = 7,
+                // The replies to the commands and the self-test result are no keys.
+                0x84..=0xFF | 0x00 => {}
+                code => return Some(self.key(code)),
+            }
+        }
+        None
+    }
+
+    /// Reads the next character typed, dropping the other key events.
+    /// # Returns
+    /// * `a Option<u8>` - the ASCII character, None if no character was typed.
+    pub fn read_char(&mut self) -> Option<u8> {
+        while let Some(event) = self.update() {
+            if event.character.is_some() {
+                return event.character;
+            }
+        }
+        None
+    }
+
+    fn key(&mut self, code: u8) -> KeyEvent {
+        let pressed = !self.release;
+        let extended = self.extended;
+        self.release = false;
+        self.extended = false;
+
+        match (code, extended) {
+            (LEFT_SHIFT, false) => self.shifts = set_bit(self.shifts, 0, pressed),
+            (RIGHT_SHIFT, false) => self.shifts = set_bit(self.shifts, 1, pressed),
+            (CTRL, _) => self.modifiers.ctrl = pressed,
+            (ALT, _) => self.modifiers.alt = pressed,
+            (CAPS_LOCK, false) if pressed => self.modifiers.caps_lock ^= true,
+            (NUM_LOCK, false) if pressed => self.modifiers.num_lock ^= true,
+            (SCROLL_LOCK, false) if pressed => self.modifiers.scroll_lock ^= true,
+            _ => {}
+        }
+        self.modifiers.shift = self.shifts != 0;
+
+        KeyEvent {
+            code,
+            extended,
+            pressed,
+            character: if pressed {
+                self.character(code, extended)
+            } else {
+                None
+            },
+            modifiers: self.modifiers,
+        }
+    }
+
+    fn character(&self, code: u8, extended: bool) -> Option<u8> {
+        if extended {
+            // Only the divide and enter keys of the keypad give a character.
+            return match code {
+                0x4A => Some(b'/'),
+                0x5A => Some(b'\n'),
+                _ => None,
+            };
+        }
+        let normal = self.keymap.normal.get(code as usize)?;
+        let mut character = if self.modifiers.shift {
+            self.keymap.shifted.get(code as usize)?
+        } else {
+            normal
+        };
+        if character == 0 {
+            return None;
+        }
+        if normal.is_ascii_lowercase() && self.modifiers.caps_lock {
+            character ^= 0x20;
+        }
+        if self.modifiers.ctrl && character.is_ascii_alphabetic() {
+            character &= 0x1F;
+        }
+        Some(character)
+    }
+}
+
+fn set_bit(value: u8, bit: u8, set: bool) -> u8 {
+    if set {
+        value | 1 << bit
+    } else {
+        value & !(1 << bit)
+    }
+}
+
+/// A movement report of a mouse.
+/// * `dx` - a i16, the movement to the right.
+/// * `dy` - a i16, the movement upwards.
+/// * `left` - a bool, true while the left button is held.
+/// * `right` - a bool, true while the right button is held.
+/// * `middle` - a bool, true while the middle button is held.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct MouseEvent {
+    pub dx: i16,
+    pub dy: i16,
+    pub left: bool,
+    pub right: bool,
+    pub middle: bool,
+}
+
+/// Used to read a PS/2 mouse in stream mode.
+/// # Elements
+/// * `port` - a `Ps2` object, the connection to the mouse.
+/// * `packet` - a array of u8, the bytes of the packet being received.
+/// * `length` - a usize, the number of bytes of the packet received.
+pub struct Ps2Mouse {
+    port: Ps2,
+    packet: [u8; 3],
+    length: usize,
+}
+
+impl Ps2Mouse {
+    /// Starts reading a mouse and enables its data reporting, which it does not do
+    /// after it is powered on.
+    /// # Arguments
+    /// * `clock` - a u8, the digital pin of the clock line, with a pin change interrupt.
+    /// * `data` - a u8, the digital pin of the data line.
+    /// # Returns
+    /// * `a Result<Ps2Mouse, Error>` - the mouse, or an error if the pins are used by other
+    ///   devices or the mouse did not take the command.
+    pub fn new(clock: u8, data: u8) -> Result<Ps2Mouse, Error> {
+        let mut mouse = Ps2Mouse {
+            port: Ps2::new(clock, data)?,
+            packet: [0; 3],
+            length: 0,
+        };
+        mouse.port.command(0xF4)?;
+        Ok(mouse)
+    }
+
+    /// Sets the number of reports per second.
+    /// # Arguments
+    /// * `rate` - a u8, 10, 20, 40, 60, 80, 100 or 200.
+    /// # Returns
+    /// * `a Result<(), Error>` - which is an error if the mouse did not take the command.
+    pub fn set_sample_rate(&mut self, rate: u8) -> Result<(), Error> {
+        self.port.command(0xF3)?;
+        self.port.command(rate)
+    }
+
+    /// Gives the number of frames dropped for a wrong start, stop or parity bit.
+    pub fn errors(&self) -> u8 {
+        self.port.errors()
+    }
+
+    /// Decodes the bytes received and gives the next movement report.
+    /// # Returns
+    /// * `a Option<MouseEvent>` - the report, None if no whole packet was received.
+    pub fn update(&mut self) -> Option<MouseEvent> {
+        while let Some(byte) = self.port.read() {
+            // Bit 3 of the first byte is always set, which finds the start of a packet.
+            if self.length == 0 && byte & 0x08 == 0 {
+                continue;
+            }
+            self.packet[self.length] = byte;
+            self.length += 1;
+            if self.length == 3 {
+                self.length = 0;
+                let [status, x, y] = self.packet;
+                // The movements are 9 bit two's complement numbers, with their sign
+                // bits in the first byte. A packet which overflowed is dropped.
+                if status & 0xC0 != 0 {
+                    continue;
+                }
+                return Some(MouseEvent {
+                    dx: x as i16 - ((status as i16 & 0x10) << 4),
+                    dy: y as i16 - ((status as i16 & 0x20) << 3),
+                    left: status & 0x01 != 0,
+                    right: status & 0x02 != 0,
+                    middle: status & 0x04 != 0,
+                });
+            }
+        }
+        None
+    }
+}
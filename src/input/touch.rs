@@ -0,0 +1,205 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! This code turns a bare pin wired to a pad of copper or foil into a touch button.
+//! The pad is discharged by driving the pin low, then the pin is made an input with its
+//! pull-up, and the time it takes the pull-up to charge the pad until the pin reads high
+//! is counted with the interrupts disabled. A finger adds some picofarads to the pad and
+//! makes the time longer. The count of a single charge is a few turns of the loop at
+//! 16 MHz, so the counts of several charges are added up.
+//! The count of the untouched pad, the baseline, follows the slow changes of humidity
+//! and temperature while the pad is not touched. The pad is touched when the count goes
+//! above the baseline by the threshold, and released when it falls back below three
+//! quarters of it.
+//! The pad should be covered by a thin insulator, and the pin must not be used for
+//! anything else.
+
+// Source codes required.
+use crate::delay::delay_us;
+use crate::hal::interrupts::without_interrupts;
+use crate::hal::pin::make_pin;
+
+use core::ptr::{read_volatile, write_volatile};
+
+/// Largest count of a single charge, reached when the pin is shorted to the ground.
+const MAX_COUNT: u16 = 1000;
+
+/// Events of a touch pad.
+/// * `Touched` - a finger has come on the pad.
+/// * `Released` - the finger has left the pad.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TouchEvent {
+    Touched,
+    Released,
+}
+
+/// Used to read a touch pad.
+/// # Elements
+/// * `input` - a pointer to u8, the input register of the port of the pin.
+/// * `ddr` - a pointer to u8, the data direction register of the port.
+/// * `port` - a pointer to u8, the output register of the port.
+/// * `mask` - a u8, the bit of the pin in the registers.
+/// * `samples` - a u8, the number of charges added up by a measure.
+/// * `threshold` - a u16, the rise of the count above the baseline for a touch.
+/// * `baseline` - a u32, the count of the untouched pad, times 16.
+/// * `touched` - a bool, true while the pad is touched.
+pub struct TouchPin {
+    input: *const u8,
+    ddr: *mut u8,
+    port: *mut u8,
+    mask: u8,
+    samples: u8,
+    threshold: u16,
+    baseline: u32,
+    touched: bool,
+}
+
+impl TouchPin {
+    /// Creates a touch pad on a pin and measures its baseline, so it must not be
+    /// touched while this is done. A measure adds up 16 charges and the threshold is 20.
+    /// # Arguments
+    /// * `pin` - a u8, the digital pin wired to the pad.
+    /// # Returns
+    /// * `a TouchPin object` - which will be used to read the pad.
+    pub fn new(pin: u8) -> TouchPin {
+        let p = make_pin(pin as _);
+        let mut touch = unsafe {
+            TouchPin {
+                input: &(*p.port).pin as *const u8,
+                ddr: &mut (*p.port).ddr as *mut u8,
+                port: &mut (*p.port).port as *mut u8,
+                mask: 1u8 << p.pin,
+                samples: 16,
+                threshold: 20,
+                baseline: 0,
+                touched: false,
+            }
+        };
+        touch.discharge();
+        touch.calibrate();
+        touch
+    }
+
+    /// Sets the number of charges added up by a measure, more give finer counts
+    /// and take longer. The baseline is measured again.
+    /// # Arguments
+    /// * `samples` - a u8, the number of charges, at least 1.
+    pub fn set_samples(&mut self, samples: u8) {
+        self.samples = samples.max(1);
+        self.calibrate();
+    }
+
+    /// Sets the rise of the count above the baseline for a touch.
+    /// It depends on the size of the pad and its cover, and is best found by printing
+    /// the `delta` of a touched pad.
+    /// # Arguments
+    /// * `threshold` - a u16, the rise of the count.
+    pub fn set_threshold(&mut self, threshold: u16) {
+        self.threshold = threshold;
+    }
+
+    /// Measures the baseline again, the pad must not be touched.
+    pub fn calibrate(&mut self) {
+        let mut sum: u32 = 0;
+        for _ in 0..4 {
+            sum += self.measure() as u32;
+        }
+        self.baseline = sum * 4;
+        self.touched = false;
+    }
+
+    /// Gives the count of the untouched pad.
+    pub fn baseline(&self) -> u16 {
+        (self.baseline / 16) as u16
+    }
+
+    /// Gives the rise of a count above the baseline.
+    /// # Arguments
+    /// * `count` - a u16, a count given by `measure`.
+    /// # Returns
+    /// * `a i32` - the rise, negative when the count is below the baseline.
+    pub fn delta(&self, count: u16) -> i32 {
+        count as i32 - self.baseline() as i32
+    }
+
+    /// Checks whether the pad is touched, as found by the last `update`.
+    pub fn is_touched(&self) -> bool {
+        self.touched
+    }
+
+    /// Adds up the times the pull-up takes to charge the pad.
+    /// # Returns
+    /// * `a u16` - the sum of the counts, larger when the pad is touched.
+    pub fn measure(&mut self) -> u16 {
+        let mut sum: u16 = 0;
+        for _ in 0..self.samples {
+            sum = sum.saturating_add(self.charge());
+        }
+        sum
+    }
+
+    /// Measures the pad and gives its next event. The baseline follows the count
+    /// while the pad is not touched.
+    /// # Returns
+    /// * `a Option<TouchEvent>` - the event, None if nothing happened.
+    pub fn update(&mut self) -> Option<TouchEvent> {
+        let count = self.measure();
+        let delta = self.delta(count);
+        if self.touched {
+            if delta < (self.threshold as i32 * 3) / 4 {
+                self.touched = false;
+                return Some(TouchEvent::Released);
+            }
+            return None;
+        }
+        if delta >= self.threshold as i32 {
+            self.touched = true;
+            return Some(TouchEvent::Touched);
+        }
+        // A moving average over 16 measures. Counts below the baseline are taken at once,
+        // so a pad touched while its baseline was measured works once it is let go.
+        if delta < 0 {
+            self.baseline = count as u32 * 16;
+        } else {
+            self.baseline = self.baseline - self.baseline / 16 + count as u32;
+        }
+        None
+    }
+
+    // Times one charge of the pad by the pull-up, then discharges it.
+    fn charge(&mut self) -> u16 {
+        let count = without_interrupts(|| unsafe {
+            write_volatile(self.ddr, read_volatile(self.ddr) & !self.mask);
+            write_volatile(self.port, read_volatile(self.port) | self.mask);
+            let mut count: u16 = 0;
+            while read_volatile(self.input) & self.mask == 0 && count < MAX_COUNT {
+                count += 1;
+            }
+            count
+        });
+        self.discharge();
+        count
+    }
+
+    // Drives the pin low, which keeps the pad at the ground between the measures.
+    fn discharge(&mut self) {
+        without_interrupts(|| unsafe {
+            write_volatile(self.port, read_volatile(self.port) & !self.mask);
+            write_volatile(self.ddr, read_volatile(self.ddr) | self.mask);
+        });
+        delay_us(1);
+    }
+}
@@ -2,6 +2,9 @@
 #![deny(warnings)]
 #![feature(asm)]
 #![feature(llvm_asm)]
+#![feature(global_asm)]
+#![feature(abi_avr_interrupt)]
+#![cfg_attr(feature = "alloc", feature(alloc_error_handler))]
 
 /// Library for AVR ATMEGA2560P Micro-controller
 /// For more information see the data sheet provided below
@@ -14,6 +17,12 @@ pub mod atmega2560p {
 
         pub mod watchdog;
 
+        pub mod reset;
+
+        pub mod fuses;
+
+        pub mod flash;
+
         pub mod sleep_mode;
 
         pub mod power;
@@ -29,11 +38,34 @@ pub mod atmega2560p {
         pub mod digital;
 
         pub mod shift;
+
+        pub mod pwm;
+
+        pub mod eeprom;
+
+        pub mod gpio;
+
+        pub mod low_power;
+
+        pub mod tone;
+
+        pub mod servo;
+
+        pub mod capture;
+
+        pub mod analog_comparator;
+
+        pub mod rtc;
     }
 
+    /// Register map with typed access to each register and its fields
+    pub mod registers;
+
     /// Communication Control Library
     #[cfg(feature = "com")]
     pub mod com {
+        pub use crate::bus;
+
         pub mod serial;
 
         pub mod usart;
@@ -45,6 +77,23 @@ pub mod atmega2560p {
         pub mod usart_recieve;
 
         pub mod i2c;
+
+        pub mod spi;
+
+        pub mod usart_interrupt;
+
+        pub mod onewire;
+
+        pub mod infrared;
+        pub mod soft_serial;
+
+        pub mod rs485;
+
+        pub use crate::protocol::modbus;
+
+        pub mod midi;
+
+        pub mod wiegand;
     }
 }
 
@@ -72,6 +121,12 @@ pub mod atmega328p {
 
         pub mod watchdog;
 
+        pub mod reset;
+
+        pub mod fuses;
+
+        pub mod flash;
+
         pub mod port;
 
         pub mod interrupts;
@@ -83,11 +138,34 @@ pub mod atmega328p {
         pub mod digital;
 
         pub mod shift;
+
+        pub mod pwm;
+
+        pub mod eeprom;
+
+        pub mod gpio;
+
+        pub mod low_power;
+
+        pub mod tone;
+
+        pub mod servo;
+
+        pub mod capture;
+
+        pub mod analog_comparator;
+
+        pub mod rtc;
     }
 
+    /// Register map with typed access to each register and its fields
+    pub mod registers;
+
     /// Communication Control Library
     #[cfg(feature = "com")]
     pub mod com {
+        pub use crate::bus;
+
         pub mod serial;
 
         pub mod usart;
@@ -99,6 +177,23 @@ pub mod atmega328p {
         pub mod usart_recieve;
 
         pub mod i2c;
+
+        pub mod spi;
+
+        pub mod usart_interrupt;
+
+        pub mod onewire;
+
+        pub mod infrared;
+        pub mod soft_serial;
+
+        pub mod rs485;
+
+        pub use crate::protocol::modbus;
+
+        pub mod midi;
+
+        pub mod wiegand;
     }
 }
 
@@ -106,14 +201,220 @@ pub mod atmega328p {
 #[doc(hidden)]
 pub use atmega328p::*;
 
+/// Library for AVR ATMEGA32U4 Micro-controller, of the Arduino Leonardo and Micro
+/// For more information see the data sheet provided below
+/// `<https://ww1.microchip.com/downloads/en/devicedoc/atmel-7766-8-bit-avr-atmega16u4-32u4_datasheet.pdf>`
+#[cfg(feature = "atmega32u4")]
+pub mod atmega32u4 {
+
+    /// Hardware Abstraction Library (HAL)
+    pub mod hal {
+        pub mod power;
+
+        pub mod watchdog;
+
+        pub mod reset;
+
+        pub mod fuses;
+
+        pub mod port;
+
+        pub mod interrupts;
+
+        pub mod pin;
+    }
+
+    /// Register map with typed access to each register and its fields
+    pub mod registers;
+
+    /// Communication Control Library
+    #[cfg(feature = "com")]
+    pub mod com {
+        pub use crate::bus;
+
+        pub mod serial;
+
+        pub mod usart;
+
+        pub mod usart_transmit;
+
+        pub mod usart_initialize;
+
+        pub mod usart_recieve;
+
+        pub mod i2c;
+
+        pub mod spi;
+    }
+}
+
+#[cfg(feature = "atmega32u4")]
+cfg_if::cfg_if! {
+    if #[cfg(doc)]{
+
+    }
+    else {
+        pub use atmega32u4::*;
+    }
+}
+
+/// Library for AVR ATTINY85 Micro-controller, with I2C and SPI through its USI
+/// For more information see the data sheet provided below
+/// `<https://ww1.microchip.com/downloads/en/DeviceDoc/Atmel-2586-AVR-8-bit-Microcontroller-ATtiny25-ATtiny45-ATtiny85_Datasheet.pdf>`
+#[cfg(feature = "attiny85")]
+pub mod attiny85 {
+
+    /// Hardware Abstraction Library (HAL)
+    pub mod hal {
+        pub mod watchdog;
+
+        pub mod reset;
+
+        pub mod fuses;
+
+        pub mod port;
+
+        pub mod interrupts;
+
+        pub mod pin;
+
+        pub mod timer;
+
+        pub mod analog;
+    }
+
+    /// Register map with typed access to each register and its fields
+    pub mod registers;
+
+    /// Communication Control Library
+    #[cfg(feature = "com")]
+    pub mod com {
+        pub mod i2c;
+
+        pub mod spi;
+    }
+}
+
+#[cfg(feature = "attiny85")]
+cfg_if::cfg_if! {
+    if #[cfg(doc)]{
+
+    }
+    else {
+        pub use attiny85::*;
+    }
+}
+
+/// Arduino boards with the pins named as on the board
+#[cfg(any(feature = "atmega2560p", feature = "atmega328p"))]
+pub mod boards;
+
 /// Sensor control for AVR Chips
 /// For more information see the following links.
 /// `<https://server4.eca.ir/eshop/AHT10/Aosong_AHT10_en_draft_0c.pdf>`
 /// `<https://invensense.tdk.com/wp-content/uploads/2015/02/MPU-6000-Datasheet1.pdf>`
 /// `<https://www.aranacorp.com/en/control-a-servo-with-arduino/>`
-#[cfg(feature = "sensors")]
+/// `<https://cdn.sparkfun.com/datasheets/Sensors/Proximity/HCSR04.pdf>`
+/// `<https://www.sparkfun.com/datasheets/Sensors/Temperature/DHT22.pdf>`
+/// `<https://datasheets.maximintegrated.com/en/ds/DS18B20.pdf>`
+/// `<https://www.bosch-sensortec.com/media/boschsensortec/downloads/datasheets/bst-bme280-ds002.pdf>`
+/// `<https://datasheets.maximintegrated.com/en/ds/DS3231.pdf>`
+/// `<https://cdn-shop.adafruit.com/datasheets/HMC5883L_3-Axis_Digital_Compass_IC.pdf>`
+/// `<https://www.gpsinformation.org/dale/nmea.htm>`
+#[cfg(all(
+    feature = "sensors",
+    any(feature = "atmega2560p", feature = "atmega328p", feature = "mock")
+))]
 pub mod sensors;
 
+/// Display drivers for AVR Chips
+/// For more information see the following links.
+/// `<https://cdn-shop.adafruit.com/datasheets/SSD1306.pdf>`
+/// `<https://www.sparkfun.com/datasheets/LCD/HD44780.pdf>`
+/// `<https://cdn-shop.adafruit.com/datasheets/WS2812B.pdf>`
+/// `<https://datasheets.maximintegrated.com/en/ds/MAX7219-MAX7221.pdf>`
+/// `<https://www.mcielectronics.cl/website_MCI/static/documents/Datasheet_TM1637.pdf>`
+#[cfg(all(
+    feature = "display",
+    any(
+        feature = "atmega2560p",
+        feature = "atmega328p",
+        feature = "atmega32u4"
+    )
+))]
+pub mod display;
+
+/// Storage drivers for AVR Chips
+/// For more information see the following links.
+/// `<https://www.sdcard.org/downloads/pls/>`
+/// `<https://academy.cba.mit.edu/classes/networking_communications/SD/FAT.pdf>`
+#[cfg(all(
+    feature = "storage",
+    any(
+        feature = "atmega2560p",
+        feature = "atmega328p",
+        feature = "atmega32u4",
+        feature = "mock"
+    )
+))]
+pub mod storage;
+
+/// Networking drivers for AVR Chips
+/// For more information see the following links.
+/// `<https://docs.wiznet.io/img/products/w5500/W5500_ds_v110e.pdf>`
+/// `<https://www.rfc-editor.org/rfc/rfc2131>`
+/// `<https://www.espressif.com/sites/default/files/documentation/4a-esp8266_at_instruction_set_en.pdf>`
+#[cfg(all(feature = "net", any(feature = "atmega2560p", feature = "atmega328p")))]
+pub mod net;
+
+/// Radio drivers for AVR Chips
+/// For more information see the following links.
+/// `<https://www.sparkfun.com/datasheets/Components/SMD/nRF24L01Pluss_Preliminary_Product_Specification_v1_0.pdf>`
+#[cfg(all(
+    feature = "radio",
+    any(
+        feature = "atmega2560p",
+        feature = "atmega328p",
+        feature = "atmega32u4"
+    )
+))]
+pub mod radio;
+
+/// Input drivers for AVR Chips
+/// For more information see the following links.
+/// `<http://www.buxtronix.net/2011/10/rotary-encoders-done-properly.html>`
+#[cfg(all(
+    feature = "input",
+    any(feature = "atmega2560p", feature = "atmega328p")
+))]
+pub mod input;
+
+/// Motion control drivers for AVR Chips
+/// For more information see the following links.
+/// `<https://www.allegromicro.com/-/media/files/datasheets/a4988-datasheet.pdf>`
+/// `<https://www.st.com/resource/en/datasheet/l298.pdf>`
+#[cfg(all(
+    feature = "motion",
+    any(feature = "atmega2560p", feature = "atmega328p")
+))]
+pub mod motion;
+
+/// Measurement of frequency and duty cycle for AVR Chips
+#[cfg(all(
+    feature = "measure",
+    any(feature = "atmega2560p", feature = "atmega328p")
+))]
+pub mod measure;
+
+/// Control loops for AVR Chips
+/// For more information see the following links.
+/// `<http://brettbeauregard.com/blog/2011/04/improving-the-beginners-pid-introduction/>`
+#[cfg(all(
+    feature = "control",
+    any(feature = "atmega2560p", feature = "atmega328p", feature = "mock")
+))]
+pub mod control;
+
 /// Math functions for assistance in implementation
 #[cfg(feature = "math")]
 pub mod math;
@@ -124,6 +425,157 @@ pub mod llvm;
 #[doc(hidden)]
 pub use llvm::*;
 
+/// Implementation of the embedded-hal traits
+#[cfg(feature = "embedded")]
+pub mod embedded;
+
 /// Configuration setup and time control
 pub mod config;
 pub mod delay;
+
+/// System tick with millis(), micros() and non-blocking time keeping
+#[cfg(any(feature = "atmega2560p", feature = "atmega328p"))]
+pub mod time;
+
+/// Cooperative scheduler running periodic tasks
+#[cfg(any(feature = "atmega2560p", feature = "atmega328p"))]
+pub mod scheduler;
+
+/// Task watchdog feeding the watchdog timer only while every supervised task checks in
+#[cfg(any(feature = "atmega2560p", feature = "atmega328p"))]
+pub mod task_watchdog;
+
+/// Typed register access, used by the register maps of the chips
+#[cfg(any(
+    feature = "atmega2560p",
+    feature = "atmega328p",
+    feature = "atmega32u4",
+    feature = "attiny85"
+))]
+pub mod register;
+
+/// Traits of the GPIO, USART, I2C and SPI implemented by every chip, for the drivers
+#[cfg(any(
+    feature = "atmega2560p",
+    feature = "atmega328p",
+    feature = "atmega32u4",
+    feature = "attiny85",
+    feature = "mock"
+))]
+pub mod common;
+
+/// Handles of the devices sharing the I2C and SPI buses
+#[cfg(all(
+    feature = "com",
+    any(
+        feature = "atmega2560p",
+        feature = "atmega328p",
+        feature = "atmega32u4",
+        feature = "mock"
+    )
+))]
+pub mod bus;
+
+/// Fake GPIO, USART, I2C and SPI for testing the drivers on the host
+#[cfg(feature = "mock")]
+pub mod mock;
+
+/// Without a chip, the drivers use the fakes of the mock backend
+#[cfg(all(
+    feature = "mock",
+    not(any(
+        feature = "atmega2560p",
+        feature = "atmega328p",
+        feature = "atmega32u4",
+        feature = "attiny85"
+    ))
+))]
+pub use mock::{com, hal, time};
+
+/// System clock prescaler, frequency tracking and calibration of the RC oscillator
+#[cfg(any(
+    feature = "atmega2560p",
+    feature = "atmega328p",
+    feature = "atmega32u4",
+    feature = "attiny85"
+))]
+pub mod clock;
+
+/// Constant tables and strings kept in the flash and read with LPM
+#[cfg(any(
+    feature = "atmega2560p",
+    feature = "atmega328p",
+    feature = "atmega32u4",
+    feature = "attiny85"
+))]
+pub mod progmem;
+
+/// Critical sections, interrupt-safe Mutex and atomic flags
+#[cfg(any(
+    feature = "atmega2560p",
+    feature = "atmega328p",
+    feature = "atmega32u4",
+    feature = "attiny85"
+))]
+pub mod sync;
+
+/// Ring buffer, Vec and String of a fixed capacity
+pub mod collections;
+
+/// Error type shared by the peripherals and the drivers
+pub mod error;
+
+/// CRC and other helpers shared by the drivers
+pub mod util;
+
+/// Serial protocols written once for all the chips
+#[cfg(all(
+    feature = "com",
+    any(
+        feature = "atmega2560p",
+        feature = "atmega328p",
+        feature = "atmega32u4",
+        feature = "attiny85",
+        feature = "mock"
+    )
+))]
+pub mod protocol;
+
+/// serial_print!, serial_println! and serial_write! macros without core::fmt
+#[cfg(all(feature = "com", any(feature = "atmega2560p", feature = "atmega328p")))]
+pub mod print;
+
+/// error!, warn!, info! and debug! macros with levels filtered when compiling
+#[cfg(all(feature = "log", any(feature = "atmega2560p", feature = "atmega328p")))]
+pub mod log;
+
+/// Heap allocator for the alloc crate
+#[cfg(all(
+    feature = "alloc",
+    any(
+        feature = "atmega2560p",
+        feature = "atmega328p",
+        feature = "atmega32u4",
+        feature = "attiny85"
+    )
+))]
+pub mod heap;
+
+/// Free RAM, stack painting and high-water mark, and stack canary checks
+#[cfg(all(
+    feature = "diagnostics",
+    any(
+        feature = "atmega2560p",
+        feature = "atmega328p",
+        feature = "atmega32u4",
+        feature = "attiny85"
+    )
+))]
+pub mod diagnostics;
+
+/// Panic handler printing the location over USART0 and blinking the LED
+#[cfg(all(
+    feature = "panic",
+    any(feature = "atmega2560p", feature = "atmega328p")
+))]
+pub mod panic;
@@ -19,3 +19,100 @@
 pub fn __nop() {
     unsafe { llvm_asm!("nop") }
 }
+
+/// Sets a bit of a register in the lower I/O space with the SBI instruction, see `fast_high!`.
+#[doc(hidden)]
+#[inline(always)]
+pub fn __sbi<const ADDRESS: u8, const BIT: u8>() {
+    unsafe { llvm_asm!("sbi $0, $1" :: "I"(ADDRESS), "I"(BIT) :: "volatile") }
+}
+
+/// Clears a bit of a register in the lower I/O space with the CBI instruction, see `fast_low!`.
+#[doc(hidden)]
+#[inline(always)]
+pub fn __cbi<const ADDRESS: u8, const BIT: u8>() {
+    unsafe { llvm_asm!("cbi $0, $1" :: "I"(ADDRESS), "I"(BIT) :: "volatile") }
+}
+
+/// Reads a bit of a register in the lower I/O space, see `fast_read!`.
+#[doc(hidden)]
+#[inline(always)]
+pub fn __read_bit<const ADDRESS: u8, const BIT: u8>() -> bool {
+    // The I/O space starts at 0x20 in the data memory.
+    let value = unsafe { core::ptr::read_volatile((ADDRESS as usize + 0x20) as *const u8) };
+    value & (1 << BIT) != 0
+}
+
+/// Drives a pin high with a single SBI instruction, which takes 2 cycles and cannot
+/// be broken by an interrupt, as the port and the bit are known when compiling.
+/// The pin is given by its port letter and bit, as `B, 5` for PB5, which is digital
+/// pin 13 of the Arduino Uno. Only the ports in the lower I/O space can be used, which
+/// are all the ports except H to L of ATMEGA2560P, and any other port does not compile.
+/// # Example
+/// ```ignore
+/// fast_output!(B, 5);
+/// loop {
+///     fast_high!(B, 5);
+///     fast_low!(B, 5);
+/// }
+/// ```
+#[macro_export]
+macro_rules! fast_high {
+    ($port:ident, $bit:expr) => {
+        $crate::__sbi::<{ $crate::__port_io!($port, PORT) }, { $bit }>()
+    };
+}
+
+/// Drives a pin low with a single CBI instruction, see `fast_high!`.
+#[macro_export]
+macro_rules! fast_low {
+    ($port:ident, $bit:expr) => {
+        $crate::__cbi::<{ $crate::__port_io!($port, PORT) }, { $bit }>()
+    };
+}
+
+/// Drives a pin to the given level, with a SBI or CBI instruction, see `fast_high!`.
+#[macro_export]
+macro_rules! fast_write {
+    ($port:ident, $bit:expr, $high:expr) => {
+        if $high {
+            $crate::fast_high!($port, $bit)
+        } else {
+            $crate::fast_low!($port, $bit)
+        }
+    };
+}
+
+/// Changes the level of a pin with a single instruction, as writing a one to the
+/// bit of PINx toggles the bit of PORTx, see `fast_high!`.
+#[macro_export]
+macro_rules! fast_toggle {
+    ($port:ident, $bit:expr) => {
+        $crate::__sbi::<{ $crate::__port_io!($port, PIN) }, { $bit }>()
+    };
+}
+
+/// Reads the level on a pin, see `fast_high!`.
+/// Gives a boolean, which is true if the pin is high.
+#[macro_export]
+macro_rules! fast_read {
+    ($port:ident, $bit:expr) => {
+        $crate::__read_bit::<{ $crate::__port_io!($port, PIN) }, { $bit }>()
+    };
+}
+
+/// Sets a pin as output with a single SBI instruction, see `fast_high!`.
+#[macro_export]
+macro_rules! fast_output {
+    ($port:ident, $bit:expr) => {
+        $crate::__sbi::<{ $crate::__port_io!($port, DDR) }, { $bit }>()
+    };
+}
+
+/// Sets a pin as input with a single CBI instruction, see `fast_high!`.
+#[macro_export]
+macro_rules! fast_input {
+    ($port:ident, $bit:expr) => {
+        $crate::__cbi::<{ $crate::__port_io!($port, DDR) }, { $bit }>()
+    };
+}
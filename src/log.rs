@@ -0,0 +1,219 @@
+// RustDuino : A generic HAL implementation for Arduino Boards in Rust
+// Copyright (C) 2021 Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Log messages of the program and the drivers, with the `error!`, `warn!`, `info!` and
+//! `debug!` macros, which take the format strings of `serial_println!`.
+//! Each message is printed on a line with the time from `millis()`, its level and the
+//! module it comes from, as `[   12345] WARN  rustduino::sensors::rtc: oscillator stopped`.
+//! The lines go to USART0 unless another sink is set with `set_sink`, and USART0 must
+//! be initialized with `begin()` first. `time::init` must be called for the time.
+//! The levels above `MAX_LEVEL` are removed when the program is compiled, with their
+//! format strings, so they take no flash. `MAX_LEVEL` is `Info`, or the level of the
+//! `log-error`, `log-warn`, `log-info` or `log-debug` feature, the most verbose one if
+//! several are enabled. `set_level` lowers the level further while the program runs.
+//! A message logged from an interrupt routine may be printed in the middle of a line
+//! of the main program.
+//! # Example
+//! ```ignore
+//! rustduino::info!("started, {} tasks", count);
+//! rustduino::warn!("retry {} of {}", retry, 3);
+//! rustduino::debug!("status = 0x{:02X}", status);
+//! ```
+
+// Source codes required.
+use crate::print::{serial, write_format, Print, SerialWrite};
+use crate::sync::AtomicByte;
+use crate::time::millis;
+
+/// Levels of the messages, from the most to the least important.
+/// * `Error` - something failed.
+/// * `Warn` - something went wrong but was handled.
+/// * `Info` - what the program is doing.
+/// * `Debug` - details to find a bug.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Level {
+    Error = 1,
+    Warn = 2,
+    Info = 3,
+    Debug = 4,
+}
+
+impl Level {
+    /// Gives the name of the level, padded to 5 characters.
+    pub fn name(self) -> &'static str {
+        match self {
+            Level::Error => "ERROR",
+            Level::Warn => "WARN ",
+            Level::Info => "INFO ",
+            Level::Debug => "DEBUG",
+        }
+    }
+}
+
+/// Most verbose level compiled in, set by the `log-*` features.
+#[cfg(feature = "log-debug")]
+pub const MAX_LEVEL: Level = Level::Debug;
+#[cfg(all(feature = "log-info", not(feature = "log-debug")))]
+pub const MAX_LEVEL: Level = Level::Info;
+#[cfg(all(
+    feature = "log-warn",
+    not(any(feature = "log-info", feature = "log-debug"))
+))]
+pub const MAX_LEVEL: Level = Level::Warn;
+#[cfg(all(
+    feature = "log-error",
+    not(any(feature = "log-warn", feature = "log-info", feature = "log-debug"))
+))]
+pub const MAX_LEVEL: Level = Level::Error;
+#[cfg(not(any(
+    feature = "log-error",
+    feature = "log-warn",
+    feature = "log-info",
+    feature = "log-debug"
+)))]
+pub const MAX_LEVEL: Level = Level::Info;
+
+// Most verbose level printed, 0 when the log is off.
+static LEVEL: AtomicByte = AtomicByte::new(MAX_LEVEL as u8);
+
+// Function receiving the bytes of the lines, None for USART0.
+static mut SINK: Option<fn(&[u8])> = None;
+
+/// Sets the most verbose level printed, which can not be above `MAX_LEVEL`.
+/// # Arguments
+/// * `level` - a `Option<Level>`, the level, None to print nothing.
+pub fn set_level(level: Option<Level>) {
+    let level = level.map(|level| level.min(MAX_LEVEL) as u8).unwrap_or(0);
+    LEVEL.store(level);
+}
+
+/// Gives the most verbose level printed.
+/// # Returns
+/// * `a Option<Level>` - the level, None when nothing is printed.
+pub fn level() -> Option<Level> {
+    match LEVEL.load() {
+        1 => Some(Level::Error),
+        2 => Some(Level::Warn),
+        3 => Some(Level::Info),
+        4 => Some(Level::Debug),
+        _ => None,
+    }
+}
+
+/// Checks whether the messages of a level are printed.
+pub fn enabled(level: Level) -> bool {
+    level <= MAX_LEVEL && level as u8 <= LEVEL.load()
+}
+
+/// Sends the lines to a function instead of USART0, as the write of another serial port
+/// or of a file.
+/// # Arguments
+/// * `sink` - a `Option<fn(&[u8])>`, the function receiving the bytes, None for USART0.
+pub fn set_sink(sink: Option<fn(&[u8])>) {
+    unsafe {
+        SINK = sink;
+    }
+}
+
+/// Port passing the bytes to the sink.
+struct Sink(fn(&[u8]));
+
+impl SerialWrite for Sink {
+    fn write_byte(&mut self, byte: u8) {
+        (self.0)(&[byte]);
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        (self.0)(bytes);
+    }
+}
+
+/// Prints a message, called by the macros.
+/// # Arguments
+/// * `level` - a `Level` object, the level of the message.
+/// * `module` - a string slice, the path of the module logging the message.
+/// * `format` - a string slice, the format string.
+/// * `args` - a slice of `Print` objects, the arguments.
+#[doc(hidden)]
+pub fn write_record(level: Level, module: &str, format: &str, args: &[&dyn Print]) {
+    if !enabled(level) {
+        return;
+    }
+    match unsafe { SINK } {
+        Some(sink) => write_line(&mut Sink(sink), level, module, format, args),
+        None => write_line(&mut serial(), level, module, format, args),
+    }
+}
+
+fn write_line(
+    out: &mut dyn SerialWrite,
+    level: Level,
+    module: &str,
+    format: &str,
+    args: &[&dyn Print],
+) {
+    write_format(out, "[{:8}] {} {}: ", &[&millis(), &level.name(), &module]);
+    write_format(out, format, args);
+    out.write_bytes(b"\r\n");
+}
+
+/// Logs a message at a level, the other macros call it.
+/// # Example
+/// ```ignore
+/// log!(Level::Warn, "{} retries", retries);
+/// ```
+#[macro_export]
+macro_rules! log {
+    ($level:expr, $format:expr $(, $arg:expr)* $(,)?) => {{
+        let level: $crate::log::Level = $level;
+        // The comparison with a constant removes the message from the program.
+        if level <= $crate::log::MAX_LEVEL {
+            $crate::log::write_record(level, module_path!(), $format, &[$(&$arg),*]);
+        }
+    }};
+}
+
+/// Logs a message at the `Error` level.
+#[macro_export]
+macro_rules! error {
+    ($format:expr $(, $arg:expr)* $(,)?) => {
+        $crate::log!($crate::log::Level::Error, $format $(, $arg)*)
+    };
+}
+
+/// Logs a message at the `Warn` level.
+#[macro_export]
+macro_rules! warn {
+    ($format:expr $(, $arg:expr)* $(,)?) => {
+        $crate::log!($crate::log::Level::Warn, $format $(, $arg)*)
+    };
+}
+
+/// Logs a message at the `Info` level.
+#[macro_export]
+macro_rules! info {
+    ($format:expr $(, $arg:expr)* $(,)?) => {
+        $crate::log!($crate::log::Level::Info, $format $(, $arg)*)
+    };
+}
+
+/// Logs a message at the `Debug` level.
+#[macro_export]
+macro_rules! debug {
+    ($format:expr $(, $arg:expr)* $(,)?) => {
+        $crate::log!($crate::log::Level::Debug, $format $(, $arg)*)
+    };
+}
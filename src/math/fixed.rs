@@ -0,0 +1,349 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Fixed-point numbers of 16 bits, which are much faster than the software floats on
+//! the AVR chips.
+//! `Q15` has 15 fractional bits and holds values from -1 to just below 1, as the
+//! samples of signals and the sine and cosine. `Q7_8` has 8 fractional bits and holds
+//! values from -128 to just below 128.
+//! The arithmetic saturates at the ends of the range instead of wrapping around, and the
+//! products are rounded to the nearest value.
+
+// Crates which would be used in the implementation.
+use core::ops::{Add, AddAssign, Div, Mul, MulAssign, Neg, Sub, SubAssign};
+
+/// Number from -1 to 1 - 2^-15, with 15 fractional bits.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Default)]
+pub struct Q15(pub i16);
+
+impl Q15 {
+    /// Number of fractional bits.
+    pub const FRACTION_BITS: u32 = 15;
+    /// Zero.
+    pub const ZERO: Q15 = Q15(0);
+    /// Largest value, 1 - 2^-15.
+    pub const MAX: Q15 = Q15(i16::MAX);
+    /// Smallest value, -1.
+    pub const MIN: Q15 = Q15(i16::MIN);
+
+    /// Gives the number nearest to a fraction, saturated to the range.
+    /// # Arguments
+    /// * `numerator` - a i32, the numerator.
+    /// * `denominator` - a i32, the denominator, not 0.
+    /// # Returns
+    /// * `a Q15 object` - the fraction.
+    pub fn from_ratio(numerator: i32, denominator: i32) -> Q15 {
+        Q15(saturate(divide_rounded(
+            (numerator as i64) << Self::FRACTION_BITS,
+            denominator as i64,
+        )))
+    }
+
+    /// Gives the number nearest to a float, saturated to the range.
+    pub fn from_f32(value: f32) -> Q15 {
+        Q15(saturate(round(value * (1 << Self::FRACTION_BITS) as f32)))
+    }
+
+    /// Gives the value as a float.
+    pub fn to_f32(self) -> f32 {
+        self.0 as f32 / (1 << Self::FRACTION_BITS) as f32
+    }
+
+    /// Scales an integer by this number.
+    /// # Arguments
+    /// * `value` - a i32, the integer.
+    /// # Returns
+    /// * `a i32` - the product, rounded to the nearest integer.
+    pub fn scale(self, value: i32) -> i32 {
+        ((self.0 as i64 * value as i64 + (1 << (Self::FRACTION_BITS - 1))) >> Self::FRACTION_BITS)
+            as i32
+    }
+
+    /// Gives the absolute value, 1 - 2^-15 for -1.
+    pub fn abs(self) -> Q15 {
+        Q15(self.0.saturating_abs())
+    }
+}
+
+impl Add for Q15 {
+    type Output = Q15;
+    fn add(self, other: Q15) -> Q15 {
+        Q15(self.0.saturating_add(other.0))
+    }
+}
+
+impl Sub for Q15 {
+    type Output = Q15;
+    fn sub(self, other: Q15) -> Q15 {
+        Q15(self.0.saturating_sub(other.0))
+    }
+}
+
+impl Mul for Q15 {
+    type Output = Q15;
+    fn mul(self, other: Q15) -> Q15 {
+        // Only -1 * -1 goes out of the range.
+        let product = self.0 as i32 * other.0 as i32 + (1 << (Self::FRACTION_BITS - 1));
+        Q15(saturate((product >> Self::FRACTION_BITS) as i64))
+    }
+}
+
+impl Neg for Q15 {
+    type Output = Q15;
+    fn neg(self) -> Q15 {
+        Q15(self.0.saturating_neg())
+    }
+}
+
+impl AddAssign for Q15 {
+    fn add_assign(&mut self, other: Q15) {
+        *self = *self + other;
+    }
+}
+
+impl SubAssign for Q15 {
+    fn sub_assign(&mut self, other: Q15) {
+        *self = *self - other;
+    }
+}
+
+impl MulAssign for Q15 {
+    fn mul_assign(&mut self, other: Q15) {
+        *self = *self * other;
+    }
+}
+
+/// Number from -128 to 128 - 2^-8, with 8 fractional bits.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug, Default)]
+pub struct Q7_8(pub i16);
+
+impl Q7_8 {
+    /// Number of fractional bits.
+    pub const FRACTION_BITS: u32 = 8;
+    /// Zero.
+    pub const ZERO: Q7_8 = Q7_8(0);
+    /// One.
+    pub const ONE: Q7_8 = Q7_8(1 << 8);
+    /// Largest value, 128 - 2^-8.
+    pub const MAX: Q7_8 = Q7_8(i16::MAX);
+    /// Smallest value, -128.
+    pub const MIN: Q7_8 = Q7_8(i16::MIN);
+
+    /// Gives an integer as a fixed-point number, saturated to the range.
+    pub fn from_int(value: i16) -> Q7_8 {
+        Q7_8(saturate((value as i64) << Self::FRACTION_BITS))
+    }
+
+    /// Gives the number nearest to a fraction, saturated to the range.
+    /// # Arguments
+    /// * `numerator` - a i32, the numerator.
+    /// * `denominator` - a i32, the denominator, not 0.
+    /// # Returns
+    /// * `a Q7_8 object` - the fraction.
+    pub fn from_ratio(numerator: i32, denominator: i32) -> Q7_8 {
+        Q7_8(saturate(divide_rounded(
+            (numerator as i64) << Self::FRACTION_BITS,
+            denominator as i64,
+        )))
+    }
+
+    /// Gives the number nearest to a float, saturated to the range.
+    pub fn from_f32(value: f32) -> Q7_8 {
+        Q7_8(saturate(round(value * (1 << Self::FRACTION_BITS) as f32)))
+    }
+
+    /// Gives the value as a float.
+    pub fn to_f32(self) -> f32 {
+        self.0 as f32 / (1 << Self::FRACTION_BITS) as f32
+    }
+
+    /// Gives the integer part, rounded towards minus infinity.
+    pub fn to_int(self) -> i16 {
+        self.0 >> Self::FRACTION_BITS
+    }
+
+    /// Gives the integer nearest to the value.
+    pub fn round(self) -> i16 {
+        ((self.0 as i32 + (1 << (Self::FRACTION_BITS - 1))) >> Self::FRACTION_BITS) as i16
+    }
+
+    /// Gives the fractional part, from 0 to 255 in units of 2^-8.
+    pub fn frac(self) -> u8 {
+        self.0 as u8
+    }
+
+    /// Scales an integer by this number.
+    /// # Arguments
+    /// * `value` - a i32, the integer.
+    /// # Returns
+    /// * `a i32` - the product, rounded to the nearest integer.
+    pub fn scale(self, value: i32) -> i32 {
+        ((self.0 as i64 * value as i64 + (1 << (Self::FRACTION_BITS - 1))) >> Self::FRACTION_BITS)
+            as i32
+    }
+
+    /// Gives the absolute value, 128 - 2^-8 for -128.
+    pub fn abs(self) -> Q7_8 {
+        Q7_8(self.0.saturating_abs())
+    }
+}
+
+impl Add for Q7_8 {
+    type Output = Q7_8;
+    fn add(self, other: Q7_8) -> Q7_8 {
+        Q7_8(self.0.saturating_add(other.0))
+    }
+}
+
+impl Sub for Q7_8 {
+    type Output = Q7_8;
+    fn sub(self, other: Q7_8) -> Q7_8 {
+        Q7_8(self.0.saturating_sub(other.0))
+    }
+}
+
+impl Mul for Q7_8 {
+    type Output = Q7_8;
+    fn mul(self, other: Q7_8) -> Q7_8 {
+        let product = self.0 as i32 * other.0 as i32 + (1 << (Self::FRACTION_BITS - 1));
+        Q7_8(saturate((product >> Self::FRACTION_BITS) as i64))
+    }
+}
+
+impl Div for Q7_8 {
+    type Output = Q7_8;
+    /// Divides, saturating when the divisor is 0.
+    fn div(self, other: Q7_8) -> Q7_8 {
+        if other.0 == 0 {
+            return if self.0 < 0 { Q7_8::MIN } else { Q7_8::MAX };
+        }
+        Q7_8(saturate(
+            ((self.0 as i64) << Self::FRACTION_BITS) / other.0 as i64,
+        ))
+    }
+}
+
+impl Neg for Q7_8 {
+    type Output = Q7_8;
+    fn neg(self) -> Q7_8 {
+        Q7_8(self.0.saturating_neg())
+    }
+}
+
+impl AddAssign for Q7_8 {
+    fn add_assign(&mut self, other: Q7_8) {
+        *self = *self + other;
+    }
+}
+
+impl SubAssign for Q7_8 {
+    fn sub_assign(&mut self, other: Q7_8) {
+        *self = *self - other;
+    }
+}
+
+impl MulAssign for Q7_8 {
+    fn mul_assign(&mut self, other: Q7_8) {
+        *self = *self * other;
+    }
+}
+
+impl From<Q15> for Q7_8 {
+    fn from(value: Q15) -> Q7_8 {
+        Q7_8(value.0 >> (Q15::FRACTION_BITS - Q7_8::FRACTION_BITS))
+    }
+}
+
+/// Gives the integer square root, the largest integer whose square is not above the value.
+/// # Arguments
+/// * `value` - a u32, the value.
+/// # Returns
+/// * `a u16` - the square root, rounded down.
+pub fn isqrt(value: u32) -> u16 {
+    // Digit by digit in base 4, with only shifts and additions.
+    let mut rest = value;
+    let mut root: u32 = 0;
+    let mut bit: u32 = 1 << 30;
+    while bit > value {
+        bit >>= 2;
+    }
+    while bit != 0 {
+        if rest >= root + bit {
+            rest -= root + bit;
+            root = (root >> 1) + bit;
+        } else {
+            root >>= 1;
+        }
+        bit >>= 2;
+    }
+    root as u16
+}
+
+// Divides, rounding halves away from zero.
+fn divide_rounded(numerator: i64, denominator: i64) -> i64 {
+    let (numerator, denominator) = if denominator < 0 {
+        (-numerator, -denominator)
+    } else {
+        (numerator, denominator)
+    };
+    if numerator < 0 {
+        (numerator - denominator / 2) / denominator
+    } else {
+        (numerator + denominator / 2) / denominator
+    }
+}
+
+fn saturate(value: i64) -> i16 {
+    value.clamp(i16::MIN as i64, i16::MAX as i64) as i16
+}
+
+fn round(value: f32) -> i64 {
+    if value < 0.0 {
+        (value - 0.5) as i64
+    } else {
+        (value + 0.5) as i64
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn ratio_is_rounded() {
+        assert_eq!(Q15::from_ratio(1, 3), Q15(10923));
+        assert_eq!(Q15::from_ratio(-1, 3), Q15(-10923));
+        assert_eq!(Q15::from_ratio(1, -3), Q15(-10923));
+        assert_eq!(Q15::from_ratio(2, 1), Q15::MAX);
+        assert_eq!(Q7_8::from_ratio(1, 3), Q7_8(85));
+        assert_eq!(Q7_8::from_ratio(2, 3), Q7_8(171));
+        assert_eq!(Q7_8::from_ratio(-2, 3), Q7_8(-171));
+        assert_eq!(Q7_8::from_ratio(1, 512), Q7_8(1));
+    }
+
+    #[test]
+    fn isqrt_is_exact() {
+        for value in (0..100_000).chain(u32::MAX - 100_000..=u32::MAX) {
+            let root = isqrt(value) as u64;
+            assert!(root * root <= value as u64);
+            assert!((root + 1) * (root + 1) > value as u64, "{}", value);
+        }
+        for root in 1..=u16::MAX as u32 {
+            assert_eq!(isqrt(root * root), root as u16);
+            assert_eq!(isqrt(root * root - 1), root as u16 - 1);
+        }
+    }
+}
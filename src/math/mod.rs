@@ -14,13 +14,20 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>
 
+mod fixed;
 mod map;
+mod trig;
 
+pub use fixed::*;
 pub use map::*;
 pub use micromath::*;
+pub use trig::*;
 
 cfg_if::cfg_if! {
-    if #[cfg(feature = "random")] {
+    if #[cfg(all(
+        feature = "random",
+        any(feature = "atmega2560p", feature = "atmega328p")
+    ))] {
         mod random;
 
         /// For more information
@@ -15,7 +15,12 @@
 //     along with this program.  If not, see <https://www.gnu.org/licenses/>
 
 use crate::delay::delay_ms;
+use crate::error::Error;
+use crate::hal::analog::analog_read;
+use crate::hal::interrupts::without_interrupts;
 use crate::hal::pin::Pins;
+use crate::hal::watchdog::{WatchDog, WatchdogMode, WatchdogPrescaler};
+use crate::registers::WDTCSR;
 
 use crate::sensors::*;
 use bit_field::BitField;
@@ -30,12 +35,11 @@ pub enum Generator {
 /// Controls the implementation of Random Number Generators.
 /// # Elements
 /// * `pins` - structure containing array to control all pins of micro-controller.
-/// * `mpu` - a static mutable reference to the pointer location to control MPU6050 gyroscope.
+/// * `mpu` - a `MPU6050` object, to control MPU6050 gyroscope.
 /// * `mode` - a `Generator` object, which stores the implementation method for random number generator.
-#[repr(C, packed)]
 pub struct RandomNumberGenerator {
     pins: Pins,
-    mpu: &'static mut MPU6050<'static>,
+    mpu: MPU6050,
     mode: Generator,
 }
 
@@ -214,7 +218,7 @@ pub fn push_right(val: u8, change: u8) -> u8 {
 /// # Returns
 /// * `a tuple of 6 u8's` - The x,y,z axes accelerations and gyroscopic detections by MPU6050 sensor respectively.
 pub fn generate_mpu() -> (u8, u8, u8, u8, u8, u8) {
-    let obj = RandomNumberGenerator::new(Generator::Mpu);
+    let mut obj = RandomNumberGenerator::new(Generator::Mpu);
 
     obj.mpu
         .begin(MPUdpsT::MPU6050Scale250DPS, MPURangeT::MPU6050Range2G);
@@ -233,3 +237,140 @@ pub fn generate_mpu() -> (u8, u8, u8, u8, u8, u8) {
     let c: u8 = obj.mpu.accel_output[2] as u8;
     (a, b, c, d, e, f)
 }
+
+/// Number of samples of each source of noise taken by `collect_entropy`.
+const ENTROPY_SAMPLES: u8 = 16;
+
+/// Mixes a sample into the seed, so every bit of the sample changes many bits of the seed.
+fn mix(seed: u32, sample: u32) -> u32 {
+    (seed.rotate_left(7) ^ sample).wrapping_mul(0x9E37_79B1)
+}
+
+/// Counts the iterations of a loop during one time-out of the watchdog timer.
+/// The watchdog runs from its own 128 kHz oscillator, which drifts against the
+/// system clock, so the lowest bits of the count change from one call to the next.
+/// # Returns
+/// * `a u16` - the number of iterations.
+pub fn watchdog_jitter() -> u16 {
+    let watchdog = unsafe { WatchDog::new() };
+    let mut count: u16 = 0;
+    // The interrupt must not run, as it would clear the flag which is polled.
+    without_interrupts(|| {
+        watchdog.enable(WatchdogMode::Interrupt, WatchdogPrescaler::Ms16);
+        while !WDTCSR.is_set(WDTCSR::WDIF) {
+            count = count.wrapping_add(1);
+        }
+        watchdog.disable();
+    });
+    count
+}
+
+/// Collects a seed from the noise of an unconnected analog pin and the jitter
+/// between the watchdog oscillator and the system clock.
+/// This takes about 300 milliseconds, during which the interrupts are mostly disabled,
+/// so the system tick of `time` falls behind. The watchdog timer is left disabled.
+/// # Arguments
+/// * `pin` - a u8, an analog pin which is not connected to anything.
+/// # Returns
+/// * `a Result<u32, Error>` - the seed, which is different on every call, or
+///   `Error::InvalidParam` if the pin is not an analog pin.
+pub fn collect_entropy(pin: u8) -> Result<u32, Error> {
+    let mut seed: u32 = 0;
+    for _ in 0..ENTROPY_SAMPLES {
+        seed = mix(seed, watchdog_jitter() as u32);
+        seed = mix(seed, analog_read(pin)? as u32);
+    }
+    Ok(seed)
+}
+
+/// Small and fast pseudo random number generator, the xorshift32 of George Marsaglia.
+/// It is not suited for cryptography, but is good enough for games, backoff delays and
+/// nonces once it is seeded with `collect_entropy`.
+/// See `<https://www.jstatsoft.org/article/view/v008i14>`.
+/// # Elements
+/// * `state` - a u32, the state of the generator, which is never 0.
+#[derive(Clone, Copy)]
+pub struct XorShift32 {
+    state: u32,
+}
+
+impl XorShift32 {
+    /// Creates the generator from a seed, the same seed giving the same numbers.
+    /// # Arguments
+    /// * `seed` - a u32, the seed, 0 being replaced by a fixed value.
+    /// # Returns
+    /// * `a XorShift32 object` - which will be used to generate the numbers.
+    pub fn new(seed: u32) -> XorShift32 {
+        XorShift32 {
+            state: if seed == 0 { 0x9E37_79B9 } else { seed },
+        }
+    }
+
+    /// Creates the generator seeded from the noise of the chip, see `collect_entropy`.
+    /// # Arguments
+    /// * `pin` - a u8, an analog pin which is not connected to anything.
+    /// # Returns
+    /// * `a Result<XorShift32, Error>` - the generator, or `Error::InvalidParam` if the pin
+    ///   is not an analog pin.
+    pub fn from_entropy(pin: u8) -> Result<XorShift32, Error> {
+        collect_entropy(pin).map(XorShift32::new)
+    }
+
+    /// Mixes more entropy into the state, without losing the entropy already collected.
+    /// # Arguments
+    /// * `entropy` - a u32, the value to be mixed in.
+    pub fn reseed(&mut self, entropy: u32) {
+        let state = mix(self.state, entropy);
+        *self = XorShift32::new(state);
+    }
+
+    /// Gives the next number.
+    /// # Returns
+    /// * `a u32` - the number, with all its bits random.
+    pub fn next_u32(&mut self) -> u32 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.state = x;
+        x
+    }
+
+    /// Gives the next number as a u16, from the upper bits which are the most random.
+    /// # Returns
+    /// * `a u16` - the number.
+    pub fn next_u16(&mut self) -> u16 {
+        (self.next_u32() >> 16) as u16
+    }
+
+    /// Gives the next number as a u8, from the upper bits which are the most random.
+    /// # Returns
+    /// * `a u8` - the number.
+    pub fn next_u8(&mut self) -> u8 {
+        (self.next_u32() >> 24) as u8
+    }
+
+    /// Gives a number in a range, in the same way as `random(min, max)` of Arduino.
+    /// # Arguments
+    /// * `min` - a u32, the lowest number which can be given.
+    /// * `max` - a u32, the number above the highest one which can be given.
+    /// # Returns
+    /// * `a u32` - the number, from `min` to `max - 1`, or `min` if the range is empty.
+    pub fn range(&mut self, min: u32, max: u32) -> u32 {
+        if max <= min {
+            return min;
+        }
+        let span = (max - min) as u64;
+        min + ((self.next_u32() as u64 * span) >> 32) as u32
+    }
+
+    /// Fills the buffer with random bytes.
+    /// # Arguments
+    /// * `buffer` - a mutable slice of u8, which will be filled.
+    pub fn fill(&mut self, buffer: &mut [u8]) {
+        for chunk in buffer.chunks_mut(4) {
+            let bytes = self.next_u32().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+}
@@ -0,0 +1,363 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! This code measures the frequency and the duty cycle of a square wave, as given by
+//! anemometers, flow meters and tachometers.
+//! `FrequencyCounter` counts the rising edges during a gate time. The wave clocks a
+//! 16 bit timer through its external clock input and the overflow interrupt counts the
+//! upper bits, so frequencies up to 2/5 of the system clock, 6.4 MHz at 16 MHz, can be
+//! counted to one edge per gate.
+//! `PeriodMeter` times one cycle with the input capture unit of the same timer, running
+//! at the system clock. It gives the period and the duty cycle to one clock, which is
+//! finer than counting at low frequencies, and the overflows are counted so periods of
+//! a few minutes can be timed.
+//! Timer1 is used on ATMEGA328P, with its inputs T1 on digital pin 5 and ICP1 on digital
+//! pin 8, and Timer5 on ATMEGA2560P, with T5 on digital pin 47 and ICP5 on digital pin 48.
+//! The timer can not be used for the servos or PWM at the same time, and only one counter
+//! or meter can be used at once.
+//! The times are measured with `time::micros`, so `time::init` must be called.
+//! See the sections 16.5 and 16.6 of ATMEGA328P datasheet.
+
+// Source codes required.
+use crate::config::CPU_FREQUENCY_HZ;
+use crate::hal::interrupts::without_interrupts;
+use crate::hal::pin::make_pin;
+use crate::register::Field;
+use crate::time::micros;
+
+#[cfg(all(feature = "atmega2560p", not(feature = "atmega328p")))]
+use crate::atmega2560p::registers::{
+    ICR5 as ICR, PRR1, TCCR5A as TCCRA, TCCR5B as TCCRB, TCNT5 as TCNT, TIFR5 as TIFR,
+    TIMSK5 as TIMSK,
+};
+#[cfg(feature = "atmega328p")]
+use crate::atmega328p::registers::{
+    ICR1 as ICR, PRR, TCCR1A as TCCRA, TCCR1B as TCCRB, TCNT1 as TCNT, TIFR1 as TIFR,
+    TIMSK1 as TIMSK,
+};
+
+// Pins and bits of the timer.
+#[cfg(feature = "atmega328p")]
+const CLOCK_PIN: u8 = 5;
+#[cfg(feature = "atmega328p")]
+const CAPTURE_PIN: u8 = 8;
+#[cfg(feature = "atmega328p")]
+const TOV: Field = TIFR::TOV1;
+#[cfg(feature = "atmega328p")]
+const ICF: Field = TIFR::ICF1;
+#[cfg(feature = "atmega328p")]
+const TOIE: Field = TIMSK::TOIE1;
+#[cfg(feature = "atmega328p")]
+const ICNC: Field = TCCRB::ICNC1;
+#[cfg(feature = "atmega328p")]
+const ICES: Field = TCCRB::ICES1;
+
+#[cfg(all(feature = "atmega2560p", not(feature = "atmega328p")))]
+const CLOCK_PIN: u8 = 47;
+#[cfg(all(feature = "atmega2560p", not(feature = "atmega328p")))]
+const CAPTURE_PIN: u8 = 48;
+#[cfg(all(feature = "atmega2560p", not(feature = "atmega328p")))]
+const TOV: Field = TIFR::TOV5;
+#[cfg(all(feature = "atmega2560p", not(feature = "atmega328p")))]
+const ICF: Field = TIFR::ICF5;
+#[cfg(all(feature = "atmega2560p", not(feature = "atmega328p")))]
+const TOIE: Field = TIMSK::TOIE5;
+#[cfg(all(feature = "atmega2560p", not(feature = "atmega328p")))]
+const ICNC: Field = TCCRB::ICNC5;
+#[cfg(all(feature = "atmega2560p", not(feature = "atmega328p")))]
+const ICES: Field = TCCRB::ICES5;
+
+// CS bits of TCCRnB for the system clock and for the rising edges of Tn.
+const CLOCK_SYSTEM: u8 = 0b001;
+const CLOCK_EXTERNAL_RISING: u8 = 0b111;
+
+// Overflows of the timer, the upper 16 bits of the count.
+static mut OVERFLOWS: u16 = 0;
+
+/// Used to count the edges of a wave on the external clock input of the timer.
+/// # Elements
+/// * `gate_us` - a u32, the gate time in microseconds.
+/// * `start_count` - a u32, the count at the start of the gate.
+/// * `start_us` - a u32, the time of the start of the gate in microseconds.
+pub struct FrequencyCounter {
+    gate_us: u32,
+    start_count: u32,
+    start_us: u32,
+}
+
+impl FrequencyCounter {
+    /// Starts counting the rising edges on T1 or T5, and the first gate.
+    /// # Arguments
+    /// * `gate_ms` - a u16, the gate time in milliseconds. One second gives the frequency to 1 Hz.
+    /// # Returns
+    /// * `a FrequencyCounter object` - which will be used to read the frequency.
+    pub fn new(gate_ms: u16) -> FrequencyCounter {
+        make_pin(CLOCK_PIN as _).set_input();
+        start_timer(CLOCK_EXTERNAL_RISING);
+        let mut counter = FrequencyCounter {
+            gate_us: gate_ms as u32 * 1000,
+            start_count: 0,
+            start_us: 0,
+        };
+        counter.restart();
+        counter
+    }
+
+    /// Sets the gate time, from the next gate.
+    /// # Arguments
+    /// * `gate_ms` - a u16, the gate time in milliseconds.
+    pub fn set_gate_ms(&mut self, gate_ms: u16) {
+        self.gate_us = gate_ms as u32 * 1000;
+    }
+
+    /// Starts a new gate, dropping the edges counted so far.
+    pub fn restart(&mut self) {
+        let (count, now) = without_interrupts(|| (count(), micros()));
+        self.start_count = count;
+        self.start_us = now;
+    }
+
+    /// Gives the number of edges counted since the start of the gate.
+    pub fn edges(&self) -> u32 {
+        without_interrupts(count).wrapping_sub(self.start_count)
+    }
+
+    /// Gives the frequency once the gate time has passed, and starts the next gate.
+    /// The edges are counted by the timer, so this only has to be called once per gate.
+    /// # Returns
+    /// * `a Option<u32>` - the frequency in Hz, None while the gate is open.
+    pub fn poll(&mut self) -> Option<u32> {
+        let (count, now) = without_interrupts(|| (count(), micros()));
+        let elapsed = now.wrapping_sub(self.start_us);
+        if elapsed < self.gate_us {
+            return None;
+        }
+        let edges = count.wrapping_sub(self.start_count);
+        self.start_count = count;
+        self.start_us = now;
+        Some((edges as u64 * 1_000_000 / elapsed as u64) as u32)
+    }
+
+    /// Counts the edges during a whole gate.
+    /// # Returns
+    /// * `a u32` - the frequency in Hz.
+    pub fn measure(&mut self) -> u32 {
+        self.restart();
+        loop {
+            if let Some(frequency) = self.poll() {
+                return frequency;
+            }
+        }
+    }
+
+    /// Stops the timer and its overflow interrupt.
+    pub fn stop(&mut self) {
+        stop_timer();
+    }
+}
+
+/// A cycle of the wave timed by `PeriodMeter`.
+/// # Elements
+/// * `period` - a u32, the time between two rising edges in clocks of the system.
+/// * `high` - a u32, the time the wave stays high in clocks of the system.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Cycle {
+    pub period: u32,
+    pub high: u32,
+}
+
+impl Cycle {
+    /// Gives the period in microseconds.
+    pub fn period_us(&self) -> u32 {
+        (self.period as u64 * 1_000_000 / CPU_FREQUENCY_HZ as u64) as u32
+    }
+
+    /// Gives the frequency in millihertz, for the slow waves of the anemometers.
+    /// # Returns
+    /// * `a u32` - the frequency, 0 if the period is 0.
+    pub fn frequency_millihertz(&self) -> u32 {
+        if self.period == 0 {
+            return 0;
+        }
+        (CPU_FREQUENCY_HZ as u64 * 1000 / self.period as u64) as u32
+    }
+
+    /// Gives the frequency in Hz, rounded down.
+    pub fn frequency(&self) -> u32 {
+        if self.period == 0 {
+            return 0;
+        }
+        CPU_FREQUENCY_HZ / self.period
+    }
+
+    /// Gives the part of the period the wave is high.
+    /// # Returns
+    /// * `a u16` - the duty cycle in tenths of a percent, from 0 to 1000.
+    pub fn duty_permille(&self) -> u16 {
+        if self.period == 0 {
+            return 0;
+        }
+        (self.high as u64 * 1000 / self.period as u64).min(1000) as u16
+    }
+}
+
+/// Used to time the cycles of a wave on the input capture pin of the timer.
+/// # Elements
+/// * `timeout_us` - a u32, the longest time waited for an edge in microseconds.
+pub struct PeriodMeter {
+    timeout_us: u32,
+}
+
+impl PeriodMeter {
+    /// Starts the timer at the system clock with the noise canceler of the input capture
+    /// unit, which delays the edges by four clocks and ignores the shorter spikes.
+    /// The time waited for an edge is one second.
+    /// # Returns
+    /// * `a PeriodMeter object` - which will be used to time the cycles.
+    pub fn new() -> PeriodMeter {
+        make_pin(CAPTURE_PIN as _).set_input();
+        start_timer(CLOCK_SYSTEM);
+        TCCRB.set(ICNC);
+        PeriodMeter {
+            timeout_us: 1_000_000,
+        }
+    }
+
+    /// Sets the longest time waited for an edge, which should be longer than the slowest
+    /// period to be timed.
+    /// # Arguments
+    /// * `timeout_ms` - a u32, the time in milliseconds.
+    pub fn set_timeout_ms(&mut self, timeout_ms: u32) {
+        self.timeout_us = timeout_ms.saturating_mul(1000);
+    }
+
+    /// Times the next cycle from a rising edge to the falling edge and the next rising edge.
+    /// The wave must stay high and low for a few microseconds, the time taken to switch the
+    /// edge which is captured.
+    /// # Returns
+    /// * `a Option<Cycle>` - the cycle, None if an edge did not come in time.
+    pub fn measure(&mut self) -> Option<Cycle> {
+        let start = micros();
+        let rise = self.wait_edge(true, start)?;
+        let fall = self.wait_edge(false, start)?;
+        let next = self.wait_edge(true, start)?;
+        Some(Cycle {
+            period: next.wrapping_sub(rise),
+            high: fall.wrapping_sub(rise),
+        })
+    }
+
+    /// Times the next cycle.
+    /// # Returns
+    /// * `a Option<u32>` - the frequency in millihertz, None if an edge did not come in time.
+    pub fn frequency_millihertz(&mut self) -> Option<u32> {
+        self.measure().map(|cycle| cycle.frequency_millihertz())
+    }
+
+    /// Stops the timer and its overflow interrupt.
+    pub fn stop(&mut self) {
+        stop_timer();
+    }
+
+    // Waits for an edge and gives its time in clocks of the system.
+    fn wait_edge(&mut self, rising: bool, start: u32) -> Option<u32> {
+        if rising {
+            TCCRB.set(ICES);
+        } else {
+            TCCRB.clear(ICES);
+        }
+        // Changing the edge may set the flag, which is cleared by writing a one.
+        TIFR.write(ICF.mask() as u8);
+        loop {
+            let time = without_interrupts(|| {
+                if TIFR.is_set(ICF) {
+                    let icr = ICR.read();
+                    TIFR.write(ICF.mask() as u8);
+                    Some(extend(icr))
+                } else {
+                    None
+                }
+            });
+            if time.is_some() {
+                return time;
+            }
+            if micros().wrapping_sub(start) > self.timeout_us {
+                return None;
+            }
+        }
+    }
+}
+
+impl Default for PeriodMeter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Starts the timer in the normal mode from the given clock, with its overflow interrupt.
+fn start_timer(clock: u8) {
+    #[cfg(feature = "atmega328p")]
+    PRR.clear(PRR::PRTIM1);
+    #[cfg(all(feature = "atmega2560p", not(feature = "atmega328p")))]
+    PRR1.clear(PRR1::PRTIM5);
+    without_interrupts(|| unsafe {
+        TCCRB.write(0);
+        TCCRA.write(0);
+        TCNT.write(0);
+        OVERFLOWS = 0;
+        TIFR.write((TOV.mask() | ICF.mask()) as u8);
+        TIMSK.write(TOIE.mask() as u8);
+        TCCRB.write(clock);
+    });
+}
+
+fn stop_timer() {
+    TCCRB.write(0);
+    TIMSK.clear(TOIE);
+}
+
+// Gives the count of the timer with the overflows, the interrupts must be disabled.
+fn count() -> u32 {
+    extend(TCNT.read())
+}
+
+// Adds the overflows to a value of the timer, the interrupts must be disabled.
+fn extend(low: u16) -> u32 {
+    let mut overflows = unsafe { OVERFLOWS };
+    // An overflow whose interrupt is still pending came before a value in the lower half.
+    if TIFR.is_set(TOV) && low < 0x8000 {
+        overflows = overflows.wrapping_add(1);
+    }
+    (overflows as u32) << 16 | low as u32
+}
+
+/// Timer/Counter1 Overflow of ATMEGA328P.
+#[cfg(all(target_arch = "avr", feature = "atmega328p"))]
+#[no_mangle]
+pub unsafe extern "avr-interrupt" fn __vector_13() {
+    OVERFLOWS = OVERFLOWS.wrapping_add(1);
+}
+
+/// Timer/Counter5 Overflow of ATMEGA2560P.
+#[cfg(all(
+    target_arch = "avr",
+    feature = "atmega2560p",
+    not(feature = "atmega328p")
+))]
+#[no_mangle]
+pub unsafe extern "avr-interrupt" fn __vector_50() {
+    OVERFLOWS = OVERFLOWS.wrapping_add(1);
+}
@@ -0,0 +1,885 @@
+// RustDuino : A generic HAL implementation for Arduino Boards in Rust
+// Copyright (C) 2021 Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Fake peripherals implementing the traits of `common`, which build on the host, so
+//! that the drivers written against the traits can be tested with `cargo test` without
+//! a board. Enable the `mock` feature and no chip feature.
+//! Each fake records the operations done on it, up to `MOCK_LOG_SIZE` of them, and
+//! answers with values given by the test:
+//! * `MockPin` gives the levels queued by the test, or a fixed level.
+//! * `MockUsart` keeps the bytes written and gives the bytes fed by the test.
+//! * `MockTwi` holds devices with 256 registers, read and written with an auto
+//!   incremented register pointer as most I2C devices do, and answers `Error::Nack`
+//!   for the other addresses. Bytes queued with `queue_read` are read first.
+//! * `MockSpi` keeps the bytes sent and answers with the bytes queued, then 0xFF.
+//!
+//! Without a chip feature, `crate::hal`, `crate::com` and `crate::time` are the modules
+//! `hal`, `com` and `time` below, so the drivers of `sensors` and `storage` build
+//! against the fakes: `Twi::new()` and `Spi::new()` give a new `MockTwi` and `MockSpi`
+//! each time, so every driver made by a test has its own bus, and `make_pin` gives pins
+//! driving the fakes reached with `with_mock_pin`. The pins are shared by all the tests,
+//! behind a lock, so tests running at the same time must use different pins.
+//! The tests of the drivers run with `cargo test --features mock,sensors,storage`.
+//!
+//! # Example
+//! ```ignore
+//! let mut twi = MockTwi::new();
+//! twi.add_device(0x68)?;
+//! twi.set_register(0x68, 0x75, 0x68);
+//! assert_eq!(who_am_i(&mut twi), Ok(0x68));
+//! ```
+
+// Source codes required.
+use crate::collections::{RingBuffer, Vec};
+use crate::common::{GpioOps, SpiOps, TwiOps, UsartOps};
+use crate::error::Error;
+use core::cell::UnsafeCell;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+/// Largest number of operations recorded by each fake.
+pub const MOCK_LOG_SIZE: usize = 128;
+
+/// Largest number of devices of a `MockTwi`.
+pub const MAX_MOCK_DEVICES: usize = 4;
+
+/// Size of the queues of the values given to the drivers, which hold one value less.
+const QUEUE_SIZE: usize = 256;
+
+/// Operations on a `MockPin`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PinOp {
+    SetOutput,
+    SetInput,
+    High,
+    Low,
+    Toggle,
+}
+
+/// Fake digital pin.
+/// # Elements
+/// * `output` - a bool, true while the pin is an output.
+/// * `driven` - a bool, the level driven, or the pull-up of an input.
+/// * `level` - a bool, the level read while no level is queued.
+/// * `queued` - a `RingBuffer` of bool, the levels read next.
+/// * `ops` - a `Vec` of `PinOp`, the operations done.
+pub struct MockPin {
+    output: bool,
+    driven: bool,
+    level: bool,
+    queued: RingBuffer<bool, QUEUE_SIZE>,
+    ops: Vec<PinOp, MOCK_LOG_SIZE>,
+}
+
+impl MockPin {
+    /// Creates a low input pin.
+    pub const fn new() -> MockPin {
+        MockPin {
+            output: false,
+            driven: false,
+            level: false,
+            queued: RingBuffer::new(),
+            ops: Vec::new(),
+        }
+    }
+
+    /// Sets the level read from the input once the queued levels are read.
+    pub fn set_level(&mut self, high: bool) {
+        self.level = high;
+    }
+
+    /// Queues levels, read one by one by `is_high`.
+    /// # Returns
+    /// * `a Result<(), Error>` - `Error::Overflow` if the queue is full.
+    pub fn queue_levels(&mut self, levels: &[bool]) -> Result<(), Error> {
+        for level in levels {
+            self.queued.push(*level).map_err(|_| Error::Overflow)?;
+        }
+        Ok(())
+    }
+
+    /// Checks whether the pin is an output.
+    pub fn is_output(&self) -> bool {
+        self.output
+    }
+
+    /// Gives the level driven by the pin, or its pull-up when it is an input.
+    pub fn driven(&self) -> bool {
+        self.driven
+    }
+
+    /// Gives the operations done on the pin.
+    pub fn ops(&self) -> &[PinOp] {
+        self.ops.as_slice()
+    }
+
+    /// Forgets the operations done.
+    pub fn clear_ops(&mut self) {
+        self.ops.clear();
+    }
+
+    fn record(&mut self, op: PinOp) {
+        self.ops.push(op).ok();
+    }
+}
+
+impl Default for MockPin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GpioOps for MockPin {
+    fn set_output(&mut self) {
+        self.output = true;
+        self.record(PinOp::SetOutput);
+    }
+
+    fn set_input(&mut self) {
+        self.output = false;
+        self.record(PinOp::SetInput);
+    }
+
+    fn high(&mut self) {
+        self.driven = true;
+        self.record(PinOp::High);
+    }
+
+    fn low(&mut self) {
+        self.driven = false;
+        self.record(PinOp::Low);
+    }
+
+    fn toggle(&mut self) {
+        self.driven = !self.driven;
+        self.record(PinOp::Toggle);
+    }
+
+    /// An output reads the level it drives.
+    fn is_high(&self) -> bool {
+        if self.output {
+            self.driven
+        } else {
+            self.queued.pop().unwrap_or(self.level)
+        }
+    }
+}
+
+/// Fake serial port.
+/// # Elements
+/// * `written` - a `Vec` of u8, the bytes written.
+/// * `received` - a `RingBuffer` of u8, the bytes read next.
+pub struct MockUsart {
+    written: Vec<u8, MOCK_LOG_SIZE>,
+    received: RingBuffer<u8, QUEUE_SIZE>,
+}
+
+impl MockUsart {
+    /// Creates a port with nothing to read.
+    pub fn new() -> MockUsart {
+        MockUsart {
+            written: Vec::new(),
+            received: RingBuffer::new(),
+        }
+    }
+
+    /// Gives bytes to be read, as if they were received.
+    /// # Returns
+    /// * `a Result<(), Error>` - `Error::Overflow` if the queue is full.
+    pub fn feed(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        for byte in bytes {
+            self.received.push(*byte).map_err(|_| Error::Overflow)?;
+        }
+        Ok(())
+    }
+
+    /// Gives the bytes written.
+    pub fn written(&self) -> &[u8] {
+        self.written.as_slice()
+    }
+
+    /// Forgets the bytes written.
+    pub fn clear_written(&mut self) {
+        self.written.clear();
+    }
+
+    /// Reads a byte fed by the test, as `read` of the serial ports of the chips.
+    pub fn read(&mut self) -> Option<u8> {
+        self.received.pop()
+    }
+}
+
+impl Default for MockUsart {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UsartOps for MockUsart {
+    fn write_byte(&mut self, byte: u8) {
+        self.written.push(byte).ok();
+    }
+
+    fn available(&mut self) -> bool {
+        !self.received.is_empty()
+    }
+
+    fn read_byte(&mut self) -> Option<u8> {
+        self.received.pop()
+    }
+}
+
+/// Operations on a `MockTwi`, with the bytes written and read.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TwiOp {
+    Start,
+    RepStart,
+    Stop,
+    AddressWrite(u8),
+    AddressRead(u8),
+    Write(u8),
+    Read(u8),
+}
+
+/// Fake I2C device with 256 registers.
+/// # Elements
+/// * `address` - a u8, the seven bit address.
+/// * `registers` - a array of u8, the registers.
+/// * `pointer` - a u8, the register read or written next.
+#[derive(Clone, Copy)]
+struct Device {
+    address: u8,
+    registers: [u8; 256],
+    pointer: u8,
+}
+
+/// Fake I2C master, with the devices on its bus.
+/// # Elements
+/// * `devices` - a array of `Option<Device>`, the devices on the bus.
+/// * `selected` - a `Option<usize>`, the device addressed since the last start.
+/// * `pointer_set` - a bool, true once the first byte written has set the register pointer.
+/// * `queued` - a `RingBuffer` of u8, the bytes read before the registers.
+/// * `ops` - a `Vec` of `TwiOp`, the operations done.
+pub struct MockTwi {
+    devices: [Option<Device>; MAX_MOCK_DEVICES],
+    selected: Option<usize>,
+    pointer_set: bool,
+    queued: RingBuffer<u8, QUEUE_SIZE>,
+    ops: Vec<TwiOp, MOCK_LOG_SIZE>,
+}
+
+impl MockTwi {
+    /// Creates a bus without devices.
+    pub const fn new() -> MockTwi {
+        MockTwi {
+            devices: [None; MAX_MOCK_DEVICES],
+            selected: None,
+            pointer_set: false,
+            queued: RingBuffer::new(),
+            ops: Vec::new(),
+        }
+    }
+
+    /// Adds a device with all its registers at 0.
+    /// # Arguments
+    /// * `address` - a u8, the seven bit address of the device.
+    /// # Returns
+    /// * `a Result<(), Error>` - `Error::Overflow` if there are `MAX_MOCK_DEVICES` devices.
+    pub fn add_device(&mut self, address: u8) -> Result<(), Error> {
+        if self.find(address).is_some() {
+            return Ok(());
+        }
+        let slot = self
+            .devices
+            .iter()
+            .position(|device| device.is_none())
+            .ok_or(Error::Overflow)?;
+        self.devices[slot] = Some(Device {
+            address,
+            registers: [0; 256],
+            pointer: 0,
+        });
+        Ok(())
+    }
+
+    /// Sets a register of a device, devices not on the bus are ignored.
+    pub fn set_register(&mut self, address: u8, register: u8, value: u8) {
+        self.set_registers(address, register, &[value]);
+    }
+
+    /// Sets consecutive registers of a device, starting from `register`.
+    pub fn set_registers(&mut self, address: u8, register: u8, values: &[u8]) {
+        if let Some(slot) = self.find(address) {
+            let device = self.devices[slot].as_mut().unwrap();
+            for (i, value) in values.iter().enumerate() {
+                device.registers[register.wrapping_add(i as u8) as usize] = *value;
+            }
+        }
+    }
+
+    /// Gives a register of a device.
+    /// # Returns
+    /// * `a Option<u8>` - the value, None if the device is not on the bus.
+    pub fn register(&self, address: u8, register: u8) -> Option<u8> {
+        let slot = self.find(address)?;
+        self.devices[slot]
+            .as_ref()
+            .map(|device| device.registers[register as usize])
+    }
+
+    /// Queues bytes, which are read before the registers of the devices.
+    /// # Returns
+    /// * `a Result<(), Error>` - `Error::Overflow` if the queue is full.
+    pub fn queue_read(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        for byte in bytes {
+            self.queued.push(*byte).map_err(|_| Error::Overflow)?;
+        }
+        Ok(())
+    }
+
+    /// Gives the operations done on the bus.
+    pub fn ops(&self) -> &[TwiOp] {
+        self.ops.as_slice()
+    }
+
+    /// Forgets the operations done.
+    pub fn clear_ops(&mut self) {
+        self.ops.clear();
+    }
+
+    fn find(&self, address: u8) -> Option<usize> {
+        self.devices
+            .iter()
+            .position(|device| matches!(device, Some(device) if device.address == address))
+    }
+
+    fn record(&mut self, op: TwiOp) {
+        self.ops.push(op).ok();
+    }
+
+    fn select(&mut self, address: u8) -> Result<(), Error> {
+        self.selected = self.find(address);
+        self.pointer_set = false;
+        self.selected.map(|_| ()).ok_or(Error::Nack)
+    }
+}
+
+impl Default for MockTwi {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TwiOps for MockTwi {
+    fn start(&mut self) -> Result<(), Error> {
+        self.record(TwiOp::Start);
+        Ok(())
+    }
+
+    fn rep_start(&mut self) -> Result<(), Error> {
+        self.record(TwiOp::RepStart);
+        Ok(())
+    }
+
+    fn stop(&mut self) {
+        self.record(TwiOp::Stop);
+        self.selected = None;
+    }
+
+    fn address_write(&mut self, address: u8) -> Result<(), Error> {
+        self.record(TwiOp::AddressWrite(address));
+        self.select(address)
+    }
+
+    fn address_read(&mut self, address: u8) -> Result<(), Error> {
+        self.record(TwiOp::AddressRead(address));
+        self.select(address)
+    }
+
+    /// The first byte written after the address sets the register pointer.
+    fn write(&mut self, data: u8) -> Result<(), Error> {
+        self.record(TwiOp::Write(data));
+        let slot = self.selected.ok_or(Error::Bus)?;
+        let device = self.devices[slot].as_mut().unwrap();
+        if self.pointer_set {
+            device.registers[device.pointer as usize] = data;
+            device.pointer = device.pointer.wrapping_add(1);
+        } else {
+            device.pointer = data;
+            self.pointer_set = true;
+        }
+        Ok(())
+    }
+
+    fn read_byte(&mut self, _ack: bool) -> Result<u8, Error> {
+        let slot = self.selected.ok_or(Error::Bus)?;
+        let data = match self.queued.pop() {
+            Some(data) => data,
+            None => {
+                let device = self.devices[slot].as_mut().unwrap();
+                let data = device.registers[device.pointer as usize];
+                device.pointer = device.pointer.wrapping_add(1);
+                data
+            }
+        };
+        self.record(TwiOp::Read(data));
+        Ok(data)
+    }
+}
+
+/// Fake SPI master.
+/// # Elements
+/// * `sent` - a `Vec` of u8, the bytes sent.
+/// * `responses` - a `RingBuffer` of u8, the bytes received next.
+pub struct MockSpi {
+    sent: Vec<u8, MOCK_LOG_SIZE>,
+    responses: RingBuffer<u8, QUEUE_SIZE>,
+}
+
+impl MockSpi {
+    /// Creates a bus which answers 0xFF.
+    pub const fn new() -> MockSpi {
+        MockSpi {
+            sent: Vec::new(),
+            responses: RingBuffer::new(),
+        }
+    }
+
+    /// Queues bytes received by the next transfers.
+    /// # Returns
+    /// * `a Result<(), Error>` - `Error::Overflow` if the queue is full.
+    pub fn queue_responses(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        for byte in bytes {
+            self.responses.push(*byte).map_err(|_| Error::Overflow)?;
+        }
+        Ok(())
+    }
+
+    /// Gives the bytes sent.
+    pub fn sent(&self) -> &[u8] {
+        self.sent.as_slice()
+    }
+
+    /// Forgets the bytes sent.
+    pub fn clear_sent(&mut self) {
+        self.sent.clear();
+    }
+}
+
+impl Default for MockSpi {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SpiOps for MockSpi {
+    fn transfer(&mut self, data: u8) -> u8 {
+        self.sent.push(data).ok();
+        self.responses.pop().unwrap_or(0xFF)
+    }
+}
+
+/// Value shared by the tests, which may run in parallel threads, and used by one of
+/// them at a time.
+/// # Elements
+/// * `locked` - an AtomicBool, true while the value is used.
+/// * `value` - an UnsafeCell, the value.
+struct Shared<T> {
+    locked: AtomicBool,
+    value: UnsafeCell<T>,
+}
+
+unsafe impl<T: Send> Sync for Shared<T> {}
+
+/// Gives the lock of a `Shared` back when dropped, even if the test panics.
+struct SharedGuard<'a> {
+    locked: &'a AtomicBool,
+}
+
+impl Drop for SharedGuard<'_> {
+    fn drop(&mut self) {
+        self.locked.store(false, Ordering::Release);
+    }
+}
+
+impl<T> Shared<T> {
+    const fn new(value: T) -> Shared<T> {
+        Shared {
+            locked: AtomicBool::new(false),
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    /// Waits for the value to be free and calls `f` on it.
+    fn with<R, F: FnOnce(&mut T) -> R>(&self, f: F) -> R {
+        while self
+            .locked
+            .compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+        {
+            core::hint::spin_loop();
+        }
+        let _guard = SharedGuard {
+            locked: &self.locked,
+        };
+        // The lock is held, so no other reference to the value exists.
+        f(unsafe { &mut *self.value.get() })
+    }
+}
+
+/// Pins of the mock backend, in place of the `hal` of the chips.
+pub mod hal {
+    /// Digital pins driving the `MockPin`s given by `mock_pin`.
+    pub mod port {
+        use crate::common::GpioOps;
+        use crate::mock::{MockPin, Shared};
+
+        /// Number of digital pins.
+        pub const PIN_COUNT: usize = 20;
+
+        const IDLE_PIN: MockPin = MockPin::new();
+        static PINS: Shared<[MockPin; PIN_COUNT]> = Shared::new([IDLE_PIN; PIN_COUNT]);
+
+        /// Calls `f` on the fake behind a digital pin, to set the levels it reads or
+        /// check the operations done on it. The pins are locked while `f` runs, so `f`
+        /// must not use a `Pin`.
+        /// # Arguments
+        /// * `pin` - a u8, the digital pin, below `PIN_COUNT`.
+        /// * `f` - a function, called with the fake.
+        /// # Returns
+        /// * `a R` - the value returned by `f`.
+        pub fn with_mock_pin<R, F: FnOnce(&mut MockPin) -> R>(pin: u8, f: F) -> R {
+            PINS.with(|pins| f(&mut pins[pin as usize]))
+        }
+
+        /// Digital pin of the mock backend.
+        /// # Elements
+        /// * `pin` - a u8, the digital pin.
+        pub struct Pin {
+            pin: u8,
+        }
+
+        impl Pin {
+            /// Creates the digital pin `pin`.
+            pub fn new(pin: u8) -> Pin {
+                Pin { pin }
+            }
+
+            /// Toggles the pin.
+            pub fn toggle(&mut self) {
+                with_mock_pin(self.pin, |pin| pin.toggle());
+            }
+
+            /// Drives the pin high.
+            pub fn high(&mut self) {
+                with_mock_pin(self.pin, |pin| pin.high());
+            }
+
+            /// Drives the pin low.
+            pub fn low(&mut self) {
+                with_mock_pin(self.pin, |pin| pin.low());
+            }
+
+            /// Makes the pin an output.
+            pub fn set_output(&mut self) {
+                with_mock_pin(self.pin, |pin| pin.set_output());
+            }
+
+            /// Makes the pin an input.
+            pub fn set_input(&mut self) {
+                with_mock_pin(self.pin, |pin| pin.set_input());
+            }
+
+            /// Reads the pin.
+            /// # Returns
+            /// * `a u8` - 1 if the pin is high, else 0.
+            pub fn read(&mut self) -> u8 {
+                with_mock_pin(self.pin, |pin| pin.is_high()) as u8
+            }
+        }
+    }
+
+    /// Digital pins by their number, as on the boards.
+    pub mod pin {
+        pub use super::port::Pin;
+
+        /// Gives the digital pin `pin`.
+        pub fn make_pin(pin: u8) -> Pin {
+            Pin::new(pin)
+        }
+    }
+}
+
+/// Buses of the mock backend, in place of the `com` of the chips.
+pub mod com {
+    /// I2C master made of a `MockTwi`.
+    pub mod i2c {
+        use crate::common::TwiOps;
+        use crate::error::Error;
+        use crate::mock::MockTwi;
+        use core::ops::{Deref, DerefMut};
+
+        /// I2C master of a driver, dereferencing to its `MockTwi`.
+        /// # Elements
+        /// * `bus` - a `MockTwi`, the bus and its devices.
+        pub struct Twi {
+            bus: MockTwi,
+        }
+
+        impl Twi {
+            /// Gives a new I2C master without devices, in place of `Twi::new` of the chips.
+            pub fn new() -> Twi {
+                Twi {
+                    bus: MockTwi::new(),
+                }
+            }
+
+            /// Does nothing, the fake needs no set up.
+            pub fn init(&mut self) {}
+        }
+
+        impl Deref for Twi {
+            type Target = MockTwi;
+
+            fn deref(&self) -> &MockTwi {
+                &self.bus
+            }
+        }
+
+        impl DerefMut for Twi {
+            fn deref_mut(&mut self) -> &mut MockTwi {
+                &mut self.bus
+            }
+        }
+
+        impl TwiOps for Twi {
+            fn start(&mut self) -> Result<(), Error> {
+                self.bus.start()
+            }
+
+            fn rep_start(&mut self) -> Result<(), Error> {
+                self.bus.rep_start()
+            }
+
+            fn stop(&mut self) {
+                self.bus.stop()
+            }
+
+            fn address_write(&mut self, address: u8) -> Result<(), Error> {
+                self.bus.address_write(address)
+            }
+
+            fn address_read(&mut self, address: u8) -> Result<(), Error> {
+                self.bus.address_read(address)
+            }
+
+            fn write(&mut self, data: u8) -> Result<(), Error> {
+                self.bus.write(data)
+            }
+
+            fn read_byte(&mut self, ack: bool) -> Result<u8, Error> {
+                self.bus.read_byte(ack)
+            }
+        }
+    }
+
+    /// SPI master made of a `MockSpi`.
+    pub mod spi {
+        use crate::common::SpiOps;
+        use crate::mock::MockSpi;
+        use core::ops::{Deref, DerefMut};
+
+        /// Selection of the clock polarity and phase, ignored by the fake.
+        #[derive(Clone, Copy, PartialEq, Debug)]
+        pub enum SpiMode {
+            Mode0,
+            Mode1,
+            Mode2,
+            Mode3,
+        }
+
+        /// Selection of the order of the bits, ignored by the fake.
+        #[derive(Clone, Copy, PartialEq, Debug)]
+        pub enum BitOrder {
+            MsbFirst,
+            LsbFirst,
+        }
+
+        /// Selection of the clock divider, ignored by the fake.
+        #[derive(Clone, Copy, PartialEq, Debug)]
+        pub enum ClockDivider {
+            Div2,
+            Div4,
+            Div8,
+            Div16,
+            Div32,
+            Div64,
+            Div128,
+        }
+
+        /// SPI master of a driver, dereferencing to its `MockSpi`.
+        /// # Elements
+        /// * `bus` - a `MockSpi`, the bus.
+        pub struct Spi {
+            bus: MockSpi,
+        }
+
+        impl Spi {
+            /// Gives a new SPI master, in place of `Spi::new` of the chips.
+            pub fn new() -> Spi {
+                Spi {
+                    bus: MockSpi::new(),
+                }
+            }
+
+            /// Does nothing, the fake needs no set up.
+            pub fn init(&mut self, _mode: SpiMode, _order: BitOrder, _divider: ClockDivider) {}
+
+            /// Does nothing, the fake has no clock.
+            pub fn set_clock_divider(&mut self, _divider: ClockDivider) {}
+
+            /// Sends a byte and receives a byte at the same time.
+            pub fn transfer(&mut self, data: u8) -> u8 {
+                self.bus.transfer(data)
+            }
+
+            /// Sends all the bytes of the slice, replacing each with the byte received.
+            pub fn transfer_in_place(&mut self, data: &mut [u8]) {
+                self.bus.transfer_in_place(data);
+            }
+
+            /// Sends all the bytes of the slice, ignoring the bytes received.
+            pub fn write(&mut self, data: &[u8]) {
+                self.bus.write(data);
+            }
+
+            /// Fills the buffer with bytes received, sending 0xFF for each.
+            pub fn read(&mut self, buffer: &mut [u8]) {
+                self.bus.read(buffer);
+            }
+        }
+
+        impl Deref for Spi {
+            type Target = MockSpi;
+
+            fn deref(&self) -> &MockSpi {
+                &self.bus
+            }
+        }
+
+        impl DerefMut for Spi {
+            fn deref_mut(&mut self) -> &mut MockSpi {
+                &mut self.bus
+            }
+        }
+    }
+
+    /// Interrupt driven USART, a `MockUsart`.
+    pub mod usart_interrupt {
+        pub use crate::mock::MockUsart as BufferedSerial;
+    }
+
+    /// Software serial port, a `MockUsart`.
+    pub mod soft_serial {
+        pub use crate::mock::MockUsart as SoftSerial;
+    }
+}
+
+/// Time of the mock backend, set by the test, in place of the `time` of the chips.
+pub mod time {
+    use core::sync::atomic::{AtomicU32, Ordering};
+
+    static MICROS: AtomicU32 = AtomicU32::new(0);
+
+    /// Gives the time set with `set_micros`.
+    /// # Returns
+    /// * `a u32` - the number of microseconds.
+    pub fn micros() -> u32 {
+        MICROS.load(Ordering::Relaxed)
+    }
+
+    /// Gives the time set with `set_micros`, in milliseconds.
+    /// # Returns
+    /// * `a u32` - the number of milliseconds.
+    pub fn millis() -> u32 {
+        micros() / 1000
+    }
+
+    /// Sets the time given by `micros` and `millis`.
+    /// # Arguments
+    /// * `micros` - a u32, the number of microseconds.
+    pub fn set_micros(micros: u32) {
+        MICROS.store(micros, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn twi_registers() {
+        let mut twi = MockTwi::new();
+        twi.add_device(0x68).unwrap();
+        twi.set_registers(0x68, 0x3B, &[1, 2, 3]);
+
+        let mut buffer = [0; 3];
+        twi.read_registers(0x68, 0x3B, &mut buffer).unwrap();
+        assert_eq!(buffer, [1, 2, 3]);
+        assert_eq!(
+            twi.ops()[..5],
+            [
+                TwiOp::Start,
+                TwiOp::AddressWrite(0x68),
+                TwiOp::Write(0x3B),
+                TwiOp::RepStart,
+                TwiOp::AddressRead(0x68),
+            ]
+        );
+        assert_eq!(twi.ops().last(), Some(&TwiOp::Stop));
+
+        twi.write_register(0x68, 0x6B, 0x80).unwrap();
+        assert_eq!(twi.register(0x68, 0x6B), Some(0x80));
+        assert_eq!(twi.write_register(0x77, 0x00, 0), Err(Error::Nack));
+    }
+
+    #[test]
+    fn pin_usart_and_spi() {
+        let mut pin = MockPin::new();
+        pin.queue_levels(&[true, false]).unwrap();
+        pin.set_level(true);
+        assert!(pin.is_high());
+        assert!(!pin.is_high());
+        assert!(pin.is_high());
+        pin.set_output();
+        pin.set_level(false);
+        assert_eq!(pin.ops(), &[PinOp::SetOutput]);
+        assert!(!pin.is_high());
+
+        let mut usart = MockUsart::new();
+        usart.write_bytes(b"AT\r\n");
+        usart.feed(b"OK").unwrap();
+        assert_eq!(usart.written(), b"AT\r\n");
+        assert_eq!(usart.read_byte(), Some(b'O'));
+        assert!(usart.available());
+
+        let mut spi = MockSpi::new();
+        spi.queue_responses(&[0x12]).unwrap();
+        let mut data = [0x9F, 0x00];
+        spi.transfer_in_place(&mut data);
+        assert_eq!(data, [0x12, 0xFF]);
+        assert_eq!(spi.sent(), &[0x9F, 0x00]);
+    }
+}
@@ -0,0 +1,222 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! This code drives brushed DC motors through a H-bridge with two direction inputs and
+//! a PWM speed input, as each channel of the L298N (IN1, IN2, ENA) and of the TB6612FNG
+//! (AIN1, AIN2, PWMA). The STBY pin of the TB6612FNG has to be held high.
+//! With both direction inputs low the bridge is off and the motor coasts, with both
+//! high the motor is shorted and brakes.
+//! The speed is set with the hardware PWM of `hal::pwm` in phase correct mode, so the
+//! other pin of the same timer has the same frequency.
+//! `DifferentialDrive` steers a robot with a motor on each side.
+
+// Source codes required.
+use crate::hal::pin::make_pin;
+use crate::hal::pwm::{PwmChannel, PwmMode};
+
+/// Used to drive a DC motor.
+/// # Elements
+/// * `in1` - a u8, the digital pin of the first direction input.
+/// * `in2` - a u8, the digital pin of the second direction input.
+/// * `pwm` - a `PwmChannel` object, the output driving the speed input.
+/// * `inverted` - a bool, true to swap forward and reverse.
+/// * `speed` - a i16, the last speed set, from -255 to 255.
+pub struct DcMotor {
+    in1: u8,
+    in2: u8,
+    pwm: PwmChannel,
+    inverted: bool,
+    speed: i16,
+}
+
+impl DcMotor {
+    /// Sets up the pins of a motor, which coasts.
+    /// # Arguments
+    /// * `in1` - a u8, the digital pin connected to IN1.
+    /// * `in2` - a u8, the digital pin connected to IN2.
+    /// * `pwm` - a u8, the digital pin connected to the enable or PWM input, which must
+    ///   have a PWM output.
+    /// # Returns
+    /// * `a Option<DcMotor>` - the motor, None if `pwm` has no PWM output.
+    pub fn new(in1: u8, in2: u8, pwm: u8) -> Option<DcMotor> {
+        let mut channel = PwmChannel::new(pwm as _)?;
+        for &pin in [in1, in2, pwm].iter() {
+            let mut output = make_pin(pin as _);
+            output.set_output();
+            output.low();
+        }
+        channel.set_duty(0);
+        channel.enable(PwmMode::PhaseCorrect);
+        Some(DcMotor {
+            in1,
+            in2,
+            pwm: channel,
+            inverted: false,
+            speed: 0,
+        })
+    }
+
+    /// Swaps forward and reverse, for a motor wired the other way round.
+    pub fn set_inverted(&mut self, inverted: bool) {
+        self.inverted = inverted;
+    }
+
+    /// Changes the frequency of the PWM, see `PwmChannel::set_frequency`.
+    /// # Arguments
+    /// * `frequency` - a u32, the frequency in hertz, at most 40 kHz for the L298N.
+    /// # Returns
+    /// * `a u32` - the frequency actually generated in hertz.
+    pub fn set_frequency(&mut self, frequency: u32) -> u32 {
+        let frequency = self.pwm.set_frequency(frequency);
+        self.set_duty(self.speed.wrapping_abs() as u8);
+        frequency
+    }
+
+    /// Turns the motor forward.
+    /// # Arguments
+    /// * `speed` - a u8, from 0 to 255 (full speed).
+    pub fn forward(&mut self, speed: u8) {
+        self.drive(speed as i16);
+    }
+
+    /// Turns the motor in reverse.
+    /// # Arguments
+    /// * `speed` - a u8, from 0 to 255 (full speed).
+    pub fn reverse(&mut self, speed: u8) {
+        self.drive(-(speed as i16));
+    }
+
+    /// Turns the motor at a signed speed, 0 making it coast.
+    /// # Arguments
+    /// * `speed` - a i16, from -255 (full speed in reverse) to 255 (full speed forward),
+    ///   larger values are clamped.
+    pub fn drive(&mut self, speed: i16) {
+        let speed = speed.clamp(-255, 255);
+        self.speed = speed;
+        if speed == 0 {
+            self.coast();
+            return;
+        }
+        let forward = (speed > 0) != self.inverted;
+        self.set_inputs(forward, !forward);
+        self.set_duty(speed.wrapping_abs() as u8);
+    }
+
+    /// Shorts the motor, which stops it quickly and holds it.
+    pub fn brake(&mut self) {
+        self.speed = 0;
+        self.set_inputs(true, true);
+        self.set_duty(0xFF);
+    }
+
+    /// Switches the bridge off, so the motor slows down freely.
+    pub fn coast(&mut self) {
+        self.speed = 0;
+        self.set_inputs(false, false);
+        self.set_duty(0);
+    }
+
+    /// Gives the last speed set, from -255 to 255, 0 when braking or coasting.
+    pub fn speed(&self) -> i16 {
+        self.speed
+    }
+
+    fn set_inputs(&mut self, in1: bool, in2: bool) {
+        for &(pin, high) in [(self.in1, in1), (self.in2, in2)].iter() {
+            let mut output = make_pin(pin as _);
+            if high {
+                output.high();
+            } else {
+                output.low();
+            }
+        }
+    }
+
+    fn set_duty(&mut self, speed: u8) {
+        let duty = speed as u32 * self.pwm.max_duty() as u32 / 0xFF;
+        self.pwm.set_duty(duty as u16);
+    }
+}
+
+/// Used to steer a robot with a motor on each side.
+/// # Elements
+/// * `left` - a `DcMotor` object, the motor on the left.
+/// * `right` - a `DcMotor` object, the motor on the right.
+pub struct DifferentialDrive {
+    left: DcMotor,
+    right: DcMotor,
+}
+
+impl DifferentialDrive {
+    /// Combines two motors, which should turn forward when the robot goes forward
+    /// (see `DcMotor::set_inverted`).
+    /// # Arguments
+    /// * `left` - a `DcMotor` object, the motor on the left.
+    /// * `right` - a `DcMotor` object, the motor on the right.
+    /// # Returns
+    /// * `a DifferentialDrive object` - which will be used to steer the robot.
+    pub fn new(left: DcMotor, right: DcMotor) -> DifferentialDrive {
+        DifferentialDrive { left, right }
+    }
+
+    /// Sets the speed of each side.
+    /// # Arguments
+    /// * `left` - a i16, the speed of the left motor from -255 to 255.
+    /// * `right` - a i16, the speed of the right motor from -255 to 255.
+    pub fn tank(&mut self, left: i16, right: i16) {
+        self.left.drive(left);
+        self.right.drive(right);
+    }
+
+    /// Drives from a speed and a rate of turn, as given by a joystick. When their sum is
+    /// more than full speed, both sides are scaled down so that the turn is kept.
+    /// # Arguments
+    /// * `throttle` - a i16, the speed from -255 (reverse) to 255 (forward).
+    /// * `turn` - a i16, the rate of turn from -255 (left) to 255 (right).
+    pub fn arcade(&mut self, throttle: i16, turn: i16) {
+        let throttle = throttle.clamp(-255, 255) as i32;
+        let turn = turn.clamp(-255, 255) as i32;
+        let (mut left, mut right) = (throttle + turn, throttle - turn);
+        let largest = left.abs().max(right.abs());
+        if largest > 255 {
+            left = left * 255 / largest;
+            right = right * 255 / largest;
+        }
+        self.tank(left as i16, right as i16);
+    }
+
+    /// Brakes both motors.
+    pub fn brake(&mut self) {
+        self.left.brake();
+        self.right.brake();
+    }
+
+    /// Lets both motors coast.
+    pub fn coast(&mut self) {
+        self.left.coast();
+        self.right.coast();
+    }
+
+    /// Gives the motor on the left.
+    pub fn left(&mut self) -> &mut DcMotor {
+        &mut self.left
+    }
+
+    /// Gives the motor on the right.
+    pub fn right(&mut self) -> &mut DcMotor {
+        &mut self.right
+    }
+}
@@ -0,0 +1,494 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! This code drives stepper motors, either with four outputs energizing the coils
+//! through a H-bridge or a darlington array (L298N, ULN2003), or through a driver board
+//! taking step and direction pulses (A4988, DRV8825).
+//! `run()` does not block: it takes at most one step each time it is called, when the
+//! step is due, so it should be called as often as possible from the main loop.
+//! Moves follow a trapezoidal speed profile with constant acceleration, where the time
+//! between steps is computed for each step with the approximation of D. Austin,
+//! "Generate stepper-motor speed profiles in real time", as in the AccelStepper library.
+//! The times are measured with `micros`, so `time::init` must be called.
+
+// Source codes required.
+use crate::delay::delay_us;
+use crate::hal::pin::make_pin;
+use crate::time::micros;
+
+/// Coils energized for each full step, on the outputs 1 to 4, where outputs 1 and 2
+/// drive one coil and outputs 3 and 4 the other.
+const FULL_STEPS: [[bool; 4]; 4] = [
+    [true, false, true, false],
+    [false, true, true, false],
+    [false, true, false, true],
+    [true, false, false, true],
+];
+
+/// Coils energized for each half step, on the outputs 1 to 4.
+const HALF_STEPS: [[bool; 4]; 8] = [
+    [true, false, false, false],
+    [true, false, true, false],
+    [false, false, true, false],
+    [false, true, true, false],
+    [false, true, false, false],
+    [false, true, false, true],
+    [false, false, false, true],
+    [true, false, false, true],
+];
+
+/// Sequence of the coils of a four wire motor.
+/// * `FullStep` - both coils always energized, for the largest torque.
+/// * `HalfStep` - one and two coils in turn, twice as many steps with a smoother motion.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum StepMode {
+    FullStep,
+    HalfStep,
+}
+
+/// Outputs of the motor.
+#[derive(Clone, Copy)]
+enum Outputs {
+    FourWire { pins: [u8; 4], mode: StepMode },
+    StepDir { step: u8, dir: u8 },
+}
+
+/// Used to drive a stepper motor.
+/// # Elements
+/// * `outputs` - a `Outputs` object, the pins driving the motor.
+/// * `enable` - a `Option<(u8, bool)>`, the enable pin of a driver board and whether it is
+///   active low.
+/// * `invert_direction` - a bool, true to swap the directions of the motor.
+/// * `pulse_width_us` - a u16, the length of the step pulses of a driver board.
+/// * `position` - a i32, the present position in steps.
+/// * `target` - a i32, the position to be reached.
+/// * `speed` - a f32, the present speed in steps per second, negative backwards.
+/// * `max_speed` - a f32, the highest speed in steps per second.
+/// * `acceleration` - a f32, the acceleration in steps per second squared, 0 for none.
+/// * `step_interval` - a u32, the microseconds between two steps, 0 when stopped.
+/// * `last_step` - a u32, the time of the last step in microseconds.
+/// * `n` - a i32, the step of the ramp, negative while slowing down.
+/// * `c0` - a f32, the interval of the first step of a ramp in microseconds.
+/// * `cn` - a f32, the interval of the last step in microseconds.
+/// * `cmin` - a f32, the interval at the highest speed in microseconds.
+/// * `forward` - a bool, true while the position increases.
+pub struct Stepper {
+    outputs: Outputs,
+    enable: Option<(u8, bool)>,
+    invert_direction: bool,
+    pulse_width_us: u16,
+    position: i32,
+    target: i32,
+    speed: f32,
+    max_speed: f32,
+    acceleration: f32,
+    step_interval: u32,
+    last_step: u32,
+    n: i32,
+    c0: f32,
+    cn: f32,
+    cmin: f32,
+    forward: bool,
+}
+
+impl Stepper {
+    /// Creates a motor driven by four outputs, which are set low.
+    /// For a 28BYJ-48 on a ULN2003 board, the pins of IN1, IN3, IN2 and IN4 are given
+    /// in this order.
+    /// # Arguments
+    /// * `pins` - a array of u8, the digital pins of the outputs 1 to 4, where outputs 1 and 2
+    ///   drive one coil and outputs 3 and 4 the other.
+    /// * `mode` - a `StepMode` object, the sequence of the coils.
+    /// # Returns
+    /// * `a Stepper object` - which will be used to move the motor.
+    pub fn four_wire(pins: [u8; 4], mode: StepMode) -> Stepper {
+        for &pin in pins.iter() {
+            let mut output = make_pin(pin as _);
+            output.set_output();
+            output.low();
+        }
+        Stepper::new(Outputs::FourWire { pins, mode })
+    }
+
+    /// Creates a motor driven by a driver board with step and direction inputs.
+    /// # Arguments
+    /// * `step` - a u8, the digital pin connected to STEP.
+    /// * `dir` - a u8, the digital pin connected to DIR.
+    /// # Returns
+    /// * `a Stepper object` - which will be used to move the motor.
+    pub fn step_dir(step: u8, dir: u8) -> Stepper {
+        for &pin in [step, dir].iter() {
+            let mut output = make_pin(pin as _);
+            output.set_output();
+            output.low();
+        }
+        Stepper::new(Outputs::StepDir { step, dir })
+    }
+
+    fn new(outputs: Outputs) -> Stepper {
+        let mut stepper = Stepper {
+            outputs,
+            enable: None,
+            invert_direction: false,
+            pulse_width_us: 2,
+            position: 0,
+            target: 0,
+            speed: 0.0,
+            max_speed: 1.0,
+            acceleration: 0.0,
+            step_interval: 0,
+            last_step: 0,
+            n: 0,
+            c0: 0.0,
+            cn: 0.0,
+            cmin: 1_000_000.0,
+            forward: true,
+        };
+        stepper.set_acceleration(1.0);
+        stepper
+    }
+
+    /// Sets the pin driving the enable input of a driver board, which is enabled.
+    /// # Arguments
+    /// * `pin` - a u8, the digital pin connected to ENABLE.
+    /// * `active_low` - a bool, true if the driver is enabled by a low level, as the A4988
+    ///   and DRV8825.
+    pub fn set_enable_pin(&mut self, pin: u8, active_low: bool) {
+        make_pin(pin as _).set_output();
+        self.enable = Some((pin, active_low));
+        self.enable_outputs();
+    }
+
+    /// Swaps the directions of the motor.
+    pub fn set_invert_direction(&mut self, invert: bool) {
+        self.invert_direction = invert;
+    }
+
+    /// Sets the length of the step pulses, 1 us for the A4988 and 2 us for the DRV8825.
+    /// The default is 2 us.
+    pub fn set_pulse_width_us(&mut self, us: u16) {
+        self.pulse_width_us = us;
+    }
+
+    /// Enables the driver board, or energizes the coils of a four wire motor again.
+    pub fn enable_outputs(&mut self) {
+        if let Some((pin, active_low)) = self.enable {
+            let mut enable = make_pin(pin as _);
+            if active_low {
+                enable.low();
+            } else {
+                enable.high();
+            }
+        }
+        if let Outputs::FourWire { .. } = self.outputs {
+            self.energize();
+        }
+    }
+
+    /// Disables the driver board, or releases all the coils of a four wire motor, so the
+    /// motor turns freely and does not heat up while stopped.
+    pub fn disable_outputs(&mut self) {
+        if let Some((pin, active_low)) = self.enable {
+            let mut enable = make_pin(pin as _);
+            if active_low {
+                enable.high();
+            } else {
+                enable.low();
+            }
+        }
+        if let Outputs::FourWire { pins, .. } = self.outputs {
+            for &pin in pins.iter() {
+                make_pin(pin as _).low();
+            }
+        }
+    }
+
+    /// Sets the highest speed of the moves.
+    /// # Arguments
+    /// * `speed` - a f32, the speed in steps per second.
+    pub fn set_max_speed(&mut self, speed: f32) {
+        let speed = if speed < 0.0 { -speed } else { speed };
+        if speed == 0.0 || speed == self.max_speed {
+            return;
+        }
+        self.max_speed = speed;
+        self.cmin = 1_000_000.0 / speed;
+        // A move already at full speed has to slow down to the new speed.
+        if self.n > 0 {
+            self.n = (self.speed * self.speed / (2.0 * self.acceleration)) as i32;
+            self.compute_new_speed();
+        }
+    }
+
+    /// Gives the highest speed of the moves in steps per second.
+    pub fn max_speed(&self) -> f32 {
+        self.max_speed
+    }
+
+    /// Sets the acceleration and deceleration of the moves.
+    /// # Arguments
+    /// * `acceleration` - a f32, the acceleration in steps per second squared.
+    pub fn set_acceleration(&mut self, acceleration: f32) {
+        let acceleration = if acceleration < 0.0 {
+            -acceleration
+        } else {
+            acceleration
+        };
+        if acceleration == 0.0 || acceleration == self.acceleration {
+            return;
+        }
+        // The same speed is reached at another step of the new ramp.
+        if self.acceleration != 0.0 {
+            self.n = (self.n as f32 * (self.acceleration / acceleration)) as i32;
+        }
+        // Equation 15 of Austin, corrected by 0.676 for the error of the first step.
+        self.c0 = 0.676 * sqrt(2.0 / acceleration) * 1_000_000.0;
+        self.acceleration = acceleration;
+        self.compute_new_speed();
+    }
+
+    /// Gives the present position in steps.
+    pub fn position(&self) -> i32 {
+        self.position
+    }
+
+    /// Sets the present position, which stops the motor at once.
+    /// # Arguments
+    /// * `position` - a i32, the new position in steps.
+    pub fn set_position(&mut self, position: i32) {
+        self.position = position;
+        self.target = position;
+        self.n = 0;
+        self.step_interval = 0;
+        self.speed = 0.0;
+    }
+
+    /// Gives the position the motor moves to.
+    pub fn target(&self) -> i32 {
+        self.target
+    }
+
+    /// Gives the number of steps to the target, negative backwards.
+    pub fn distance_to_go(&self) -> i32 {
+        self.target.wrapping_sub(self.position)
+    }
+
+    /// Gives the present speed in steps per second, negative backwards.
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    /// Checks whether the motor is moving or has a move to do.
+    pub fn is_running(&self) -> bool {
+        self.speed != 0.0 || self.distance_to_go() != 0
+    }
+
+    /// Starts a move to a position, the move is made by `run()`.
+    /// # Arguments
+    /// * `position` - a i32, the position in steps.
+    pub fn move_to(&mut self, position: i32) {
+        if self.target != position {
+            self.target = position;
+            self.compute_new_speed();
+        }
+    }
+
+    /// Starts a move relative to the present position, the move is made by `run()`.
+    /// # Arguments
+    /// * `steps` - a i32, the number of steps, negative backwards.
+    pub fn move_by(&mut self, steps: i32) {
+        self.move_to(self.position.wrapping_add(steps));
+    }
+
+    /// Slows down to a stop as fast as the acceleration allows, by moving the target.
+    pub fn stop(&mut self) {
+        if self.speed != 0.0 {
+            let steps = (self.speed * self.speed / (2.0 * self.acceleration)) as i32 + 1;
+            if self.speed > 0.0 {
+                self.move_by(steps);
+            } else {
+                self.move_by(-steps);
+            }
+        }
+    }
+
+    /// Takes a step if one is due, and computes the speed of the next step.
+    /// It has to be called at least once per step, more often gives a smoother motion.
+    /// # Returns
+    /// * `a bool` - true while the motor has not reached its target.
+    pub fn run(&mut self) -> bool {
+        if self.run_speed() {
+            self.compute_new_speed();
+        }
+        self.is_running()
+    }
+
+    /// Moves to the target, only returning once it is reached.
+    pub fn run_to_position(&mut self) {
+        while self.run() {}
+    }
+
+    /// Moves to a position, only returning once it is reached.
+    /// # Arguments
+    /// * `position` - a i32, the position in steps.
+    pub fn run_to_new_position(&mut self, position: i32) {
+        self.move_to(position);
+        self.run_to_position();
+    }
+
+    /// Sets a constant speed, without acceleration, used by `run_speed()`.
+    /// # Arguments
+    /// * `speed` - a f32, the speed in steps per second, negative backwards, limited to the
+    ///   highest speed.
+    pub fn set_speed(&mut self, speed: f32) {
+        let speed = speed.clamp(-self.max_speed, self.max_speed);
+        if speed == 0.0 {
+            self.step_interval = 0;
+        } else {
+            let magnitude = if speed < 0.0 { -speed } else { speed };
+            self.step_interval = (1_000_000.0 / magnitude) as u32;
+            self.forward = speed > 0.0;
+        }
+        self.speed = speed;
+    }
+
+    /// Takes a step at the present speed if one is due, without acceleration nor target.
+    /// # Returns
+    /// * `a bool` - true if a step was taken.
+    pub fn run_speed(&mut self) -> bool {
+        if self.step_interval == 0 {
+            return false;
+        }
+        let now = micros();
+        if now.wrapping_sub(self.last_step) < self.step_interval {
+            return false;
+        }
+        self.position = if self.forward {
+            self.position.wrapping_add(1)
+        } else {
+            self.position.wrapping_sub(1)
+        };
+        self.step();
+        self.last_step = now;
+        true
+    }
+
+    /// Computes the interval before the next step of the move to the target.
+    fn compute_new_speed(&mut self) {
+        let distance = self.distance_to_go();
+        let steps_to_stop = (self.speed * self.speed / (2.0 * self.acceleration)) as i32;
+
+        if distance == 0 && steps_to_stop <= 1 {
+            self.step_interval = 0;
+            self.speed = 0.0;
+            self.n = 0;
+            return;
+        }
+
+        if distance > 0 {
+            if self.n > 0 {
+                // Slow down when the target comes near or the motor goes the wrong way.
+                if steps_to_stop >= distance || !self.forward {
+                    self.n = -steps_to_stop;
+                }
+            } else if self.n < 0 && steps_to_stop < distance && self.forward {
+                // Speed up again when the target has moved away.
+                self.n = -self.n;
+            }
+        } else if distance < 0 {
+            if self.n > 0 {
+                if steps_to_stop >= -distance || self.forward {
+                    self.n = -steps_to_stop;
+                }
+            } else if self.n < 0 && steps_to_stop < -distance && !self.forward {
+                self.n = -self.n;
+            }
+        }
+
+        if self.n == 0 {
+            // The first step of a move from rest.
+            self.cn = self.c0;
+            self.forward = distance > 0;
+        } else {
+            // Equation 13 of Austin.
+            self.cn -= 2.0 * self.cn / (4.0 * self.n as f32 + 1.0);
+            if self.cn < self.cmin {
+                self.cn = self.cmin;
+            }
+        }
+        self.n += 1;
+        self.step_interval = self.cn as u32;
+        self.speed = 1_000_000.0 / self.cn;
+        if !self.forward {
+            self.speed = -self.speed;
+        }
+    }
+
+    /// Sets the outputs for the present position.
+    fn step(&mut self) {
+        match self.outputs {
+            Outputs::FourWire { .. } => self.energize(),
+            Outputs::StepDir { step, dir } => {
+                let mut dir = make_pin(dir as _);
+                if self.forward != self.invert_direction {
+                    dir.high();
+                } else {
+                    dir.low();
+                }
+                let mut step = make_pin(step as _);
+                step.high();
+                delay_us(self.pulse_width_us as u32);
+                step.low();
+            }
+        }
+    }
+
+    /// Energizes the coils of a four wire motor for the present position.
+    fn energize(&mut self) {
+        if let Outputs::FourWire { pins, mode } = self.outputs {
+            let position = if self.invert_direction {
+                self.position.wrapping_neg()
+            } else {
+                self.position
+            };
+            let coils = match mode {
+                StepMode::FullStep => FULL_STEPS[position.rem_euclid(4) as usize],
+                StepMode::HalfStep => HALF_STEPS[position.rem_euclid(8) as usize],
+            };
+            for (&pin, &on) in pins.iter().zip(coils.iter()) {
+                let mut output = make_pin(pin as _);
+                if on {
+                    output.high();
+                } else {
+                    output.low();
+                }
+            }
+        }
+    }
+}
+
+/// Square root by Newton's method, since `core` has none.
+fn sqrt(x: f32) -> f32 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    // Halving the exponent gives a first guess within a few percent.
+    let mut y = f32::from_bits((x.to_bits() >> 1) + 0x1FBD_1DF5);
+    for _ in 0..3 {
+        y = 0.5 * (y + x / y);
+    }
+    y
+}
@@ -0,0 +1,280 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! A DHCP client for the W5500, which asks the router of the network for an address.
+//! The client sends a DISCOVER, takes the first OFFER, sends a REQUEST for it and
+//! configures the W5500 with the address, mask and router of the ACK.
+//! The lease is not renewed automatically, `request_lease` should be called again
+//! before `lease_time` runs out.
+//! See `<https://www.rfc-editor.org/rfc/rfc2131>`.
+
+// Source codes required.
+use crate::common::SpiOps;
+use crate::delay::delay_ms;
+use crate::net::w5500::{NetError, Protocol, W5500};
+
+const CLIENT_PORT: u16 = 68;
+const SERVER_PORT: u16 = 67;
+const BROADCAST: [u8; 4] = [255, 255, 255, 255];
+
+// Offsets in a DHCP message.
+const OP: u16 = 0;
+const XID: u16 = 4;
+const YIADDR: u16 = 16;
+const CHADDR: u16 = 28;
+const COOKIE: u16 = 236;
+const OPTIONS: u16 = 240;
+const MAGIC_COOKIE: [u8; 4] = [99, 130, 83, 99];
+// Messages are padded to the minimum size of a BOOTP message.
+const MESSAGE_SIZE: u16 = 300;
+
+// Options used by the client.
+const OPT_PAD: u8 = 0;
+const OPT_SUBNET: u8 = 1;
+const OPT_ROUTER: u8 = 3;
+const OPT_DNS: u8 = 6;
+const OPT_REQUESTED_IP: u8 = 50;
+const OPT_LEASE_TIME: u8 = 51;
+const OPT_MESSAGE_TYPE: u8 = 53;
+const OPT_SERVER_ID: u8 = 54;
+const OPT_PARAMETERS: u8 = 55;
+const OPT_CLIENT_ID: u8 = 61;
+const OPT_END: u8 = 255;
+
+// Types of DHCP messages.
+const DISCOVER: u8 = 1;
+const OFFER: u8 = 2;
+const REQUEST: u8 = 3;
+const ACK: u8 = 5;
+const NAK: u8 = 6;
+
+/// The configuration given by the DHCP server.
+/// # Elements
+/// * `ip` - an array of 4 u8, the IP address given to the W5500.
+/// * `subnet` - an array of 4 u8, the subnet mask.
+/// * `gateway` - an array of 4 u8, the IP address of the router.
+/// * `dns` - an array of 4 u8, the IP address of the name server.
+/// * `server` - an array of 4 u8, the IP address of the DHCP server.
+/// * `lease_time` - a u32, the number of seconds the address may be used.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct DhcpLease {
+    pub ip: [u8; 4],
+    pub subnet: [u8; 4],
+    pub gateway: [u8; 4],
+    pub dns: [u8; 4],
+    pub server: [u8; 4],
+    pub lease_time: u32,
+}
+
+/// Gets an address from the DHCP server and configures the W5500 with it.
+/// The hardware address must be set before, and the socket is closed afterwards.
+/// # Arguments
+/// * `w5500` - a mutable reference to a `W5500` object.
+/// * `socket` - a u8, a free socket used for the exchange.
+/// * `timeout_ms` - a u16, the time in milliseconds to wait for each answer.
+/// # Returns
+/// * `a Result<DhcpLease, NetError>` - the lease, `Timeout` if no server answered,
+///   or `Rejected` if the server refused the request.
+pub fn request_lease<T: SpiOps>(
+    w5500: &mut W5500<T>,
+    socket: u8,
+    timeout_ms: u16,
+) -> Result<DhcpLease, NetError> {
+    let mac = w5500.mac();
+    // The transaction id only has to differ between the clients of the network.
+    let xid = [mac[2] ^ 0x5A, mac[3], mac[4], mac[5]];
+
+    w5500.set_ip([0; 4]);
+    w5500.open(socket, Protocol::Udp, CLIENT_PORT)?;
+    let result = exchange(w5500, socket, mac, xid, timeout_ms);
+    w5500.close(socket)?;
+
+    let lease = result?;
+    w5500.configure(mac, lease.ip, lease.subnet, lease.gateway);
+    Ok(lease)
+}
+
+/// Runs the DISCOVER, OFFER, REQUEST and ACK exchange.
+fn exchange<T: SpiOps>(
+    w5500: &mut W5500<T>,
+    socket: u8,
+    mac: [u8; 6],
+    xid: [u8; 4],
+    timeout_ms: u16,
+) -> Result<DhcpLease, NetError> {
+    send_message(w5500, socket, mac, xid, DISCOVER, None)?;
+    let offer = wait_reply(w5500, socket, xid, OFFER, timeout_ms)?;
+    send_message(w5500, socket, mac, xid, REQUEST, Some(&offer))?;
+    wait_reply(w5500, socket, xid, ACK, timeout_ms)
+}
+
+/// Builds a message in the transmit buffer of the socket and broadcasts it.
+fn send_message<T: SpiOps>(
+    w5500: &mut W5500<T>,
+    socket: u8,
+    mac: [u8; 6],
+    xid: [u8; 4],
+    message_type: u8,
+    offer: Option<&DhcpLease>,
+) -> Result<(), NetError> {
+    // Request, Ethernet, 6 byte address, no hops, then the transaction id and the
+    // broadcast flag, since the W5500 cannot receive unicast without an address yet.
+    let mut header = [0u8; 16];
+    header[..4].copy_from_slice(&[1, 1, 6, 0]);
+    header[4..8].copy_from_slice(&xid);
+    header[10] = 0x80;
+    w5500.buffer_data(socket, OP, &header);
+
+    // The address fields, the hardware address and the unused names are zeros.
+    let zeros = [0u8; 20];
+    let mut offset = 16;
+    while offset < COOKIE {
+        let length = (COOKIE - offset).min(zeros.len() as u16);
+        w5500.buffer_data(socket, offset, &zeros[..length as usize]);
+        offset += length;
+    }
+    w5500.buffer_data(socket, CHADDR, &mac);
+    w5500.buffer_data(socket, COOKIE, &MAGIC_COOKIE);
+
+    let mut options = [OPT_PAD; MESSAGE_SIZE as usize - OPTIONS as usize];
+    let mut length = 0;
+    let mut push = |bytes: &[u8]| {
+        options[length..length + bytes.len()].copy_from_slice(bytes);
+        length += bytes.len();
+    };
+    push(&[OPT_MESSAGE_TYPE, 1, message_type]);
+    push(&[OPT_CLIENT_ID, 7, 1]);
+    push(&mac);
+    if let Some(offer) = offer {
+        push(&[OPT_REQUESTED_IP, 4]);
+        push(&offer.ip);
+        push(&[OPT_SERVER_ID, 4]);
+        push(&offer.server);
+    }
+    push(&[OPT_PARAMETERS, 3, OPT_SUBNET, OPT_ROUTER, OPT_DNS]);
+    push(&[OPT_END]);
+    w5500.buffer_data(socket, OPTIONS, &options);
+
+    w5500.set_destination(socket, BROADCAST, SERVER_PORT);
+    w5500.send_buffered(socket, MESSAGE_SIZE)
+}
+
+/// Waits for a reply of the given type, dropping every other datagram.
+fn wait_reply<T: SpiOps>(
+    w5500: &mut W5500<T>,
+    socket: u8,
+    xid: [u8; 4],
+    message_type: u8,
+    timeout_ms: u16,
+) -> Result<DhcpLease, NetError> {
+    let mut waited = 0;
+    loop {
+        if let Some((length, server, port)) = w5500.peek_datagram(socket) {
+            let reply = if port == SERVER_PORT {
+                parse_reply(w5500, socket, length, xid)
+            } else {
+                None
+            };
+            w5500.drop_datagram(socket, length);
+            if let Some((kind, mut lease)) = reply {
+                if kind == NAK {
+                    return Err(NetError::Rejected);
+                }
+                if kind == message_type {
+                    if lease.server == [0; 4] {
+                        lease.server = server;
+                    }
+                    return Ok(lease);
+                }
+            }
+            continue;
+        }
+        waited += 1;
+        if waited > timeout_ms {
+            return Err(NetError::Timeout);
+        }
+        delay_ms(1);
+    }
+}
+
+/// Reads a reply from the receive buffer.
+/// # Returns
+/// * `a Option<(u8, DhcpLease)>` - the type of message and the lease, or None if the
+///   datagram is not a reply to this client.
+fn parse_reply<T: SpiOps>(
+    w5500: &mut W5500<T>,
+    socket: u8,
+    length: u16,
+    xid: [u8; 4],
+) -> Option<(u8, DhcpLease)> {
+    if length < OPTIONS {
+        return None;
+    }
+    let mut header = [0u8; 20];
+    w5500.read_datagram(socket, OP, &mut header);
+    let mut cookie = [0u8; 4];
+    w5500.read_datagram(socket, COOKIE, &mut cookie);
+    // A reply (op 2) to this transaction.
+    if header[OP as usize] != 2 || header[XID as usize..XID as usize + 4] != xid {
+        return None;
+    }
+    if cookie != MAGIC_COOKIE {
+        return None;
+    }
+
+    let mut lease = DhcpLease {
+        ip: [0; 4],
+        subnet: [0; 4],
+        gateway: [0; 4],
+        dns: [0; 4],
+        server: [0; 4],
+        lease_time: 0,
+    };
+    lease
+        .ip
+        .copy_from_slice(&header[YIADDR as usize..YIADDR as usize + 4]);
+
+    let mut kind = 0;
+    let mut offset = OPTIONS;
+    while offset < length {
+        let mut code = [0u8; 2];
+        w5500.read_datagram(socket, offset, &mut code[..1]);
+        if code[0] == OPT_END {
+            break;
+        }
+        if code[0] == OPT_PAD {
+            offset += 1;
+            continue;
+        }
+        w5500.read_datagram(socket, offset + 1, &mut code[1..]);
+        let size = code[1] as u16;
+        // Only the first 4 bytes of an option are needed.
+        let mut value = [0u8; 4];
+        let count = size.min(4) as usize;
+        w5500.read_datagram(socket, offset + 2, &mut value[..count]);
+        match code[0] {
+            OPT_MESSAGE_TYPE => kind = value[0],
+            OPT_SUBNET => lease.subnet = value,
+            OPT_ROUTER => lease.gateway = value,
+            OPT_DNS => lease.dns = value,
+            OPT_SERVER_ID => lease.server = value,
+            OPT_LEASE_TIME => lease.lease_time = u32::from_be_bytes(value),
+            _ => {}
+        }
+        offset += 2 + size;
+    }
+    Some((kind, lease))
+}
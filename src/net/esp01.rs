@@ -0,0 +1,562 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! This code controls an ESP8266 module such as the ESP-01 running the AT firmware.
+//! The module is connected to a USART, driven through the interrupt buffered serial
+//! port so that no reply is lost while the program is busy, and is used as a station
+//! of a WiFi network with up to 5 TCP or UDP connections, called links 0 to 4.
+//! Data received on a link comes as `+IPD,<link>,<length>:<data>` between the other
+//! replies of the module; `receive` reads it straight from the serial port, and data
+//! arriving while a command waits for its reply is kept in a small buffer.
+//! `time::init()` must be called before the module is used, for the timeouts.
+//! See `<https://www.espressif.com/sites/default/files/documentation/4a-esp8266_at_instruction_set_en.pdf>`.
+
+// Source codes required.
+use crate::com::usart_interrupt::BufferedSerial;
+use crate::common::UsartOps;
+use crate::error::Error;
+use crate::time::millis;
+
+/// Number of links the module can open at once.
+pub const LINKS: u8 = 5;
+
+// Size of the buffer for a line of reply, longer lines are cut.
+const LINE_SIZE: usize = 64;
+// Size of the buffer for data received while waiting for a reply.
+const STASH_SIZE: usize = 64;
+// Largest block of data sent by one AT+CIPSEND.
+const SEND_CHUNK: usize = 2048;
+
+// Timeouts of the commands in milliseconds.
+const COMMAND_TIMEOUT: u32 = 2000;
+const RESET_TIMEOUT: u32 = 5000;
+const JOIN_TIMEOUT: u32 = 20000;
+const CONNECT_TIMEOUT: u32 = 10000;
+const SEND_TIMEOUT: u32 = 5000;
+
+/// Errors which can happen while using the module.
+/// * `Timeout` - the module did not reply in time.
+/// * `Error` - the module replied ERROR or FAIL.
+/// * `JoinFailed` - the network could not be joined, with the reason given by the module
+///   (1 time out, 2 wrong password, 3 network not found, 4 connection failed).
+/// * `InvalidLink` - the link number is not between 0 and 4.
+/// * `Closed` - the link is not connected.
+/// * `SendFailed` - the module could not send the data.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum EspError {
+    Timeout,
+    Error,
+    JoinFailed(u8),
+    InvalidLink,
+    Closed,
+    SendFailed,
+}
+
+impl From<EspError> for Error {
+    fn from(error: EspError) -> Error {
+        match error {
+            EspError::Timeout => Error::Timeout,
+            EspError::Error | EspError::JoinFailed(_) | EspError::SendFailed => Error::Device,
+            EspError::InvalidLink => Error::InvalidParam,
+            EspError::Closed => Error::Closed,
+        }
+    }
+}
+
+/// Used to control an ESP8266 module over a serial port.
+/// # Elements
+/// * `serial` - a `UsartOps` object, the serial port of the module, by default a `BufferedSerial`.
+/// * `line` - an array of u8, the line of reply being read.
+/// * `line_len` - a usize, the number of bytes in `line`.
+/// * `line_done` - a boolean, true once `line` holds a whole line which was handed out.
+/// * `ipd_link` - a u8, the link of the data being received.
+/// * `ipd_remaining` - a usize, the bytes of data still to come from the serial port.
+/// * `stash` - an array of u8, the data received while waiting for a reply.
+/// * `stash_len` - a usize, the number of bytes in `stash`.
+/// * `stash_link` - a u8, the link the data in `stash` came from.
+/// * `connected` - a u8, one bit for each link, set while the link is connected.
+pub struct Esp01<U = BufferedSerial> {
+    serial: U,
+    line: [u8; LINE_SIZE],
+    line_len: usize,
+    line_done: bool,
+    ipd_link: u8,
+    ipd_remaining: usize,
+    stash: [u8; STASH_SIZE],
+    stash_len: usize,
+    stash_link: u8,
+    connected: u8,
+}
+
+/// A line of reply, or the prompt for data of AT+CIPSEND.
+enum Reply {
+    Line,
+    Prompt,
+}
+
+impl<U: UsartOps> Esp01<U> {
+    /// Creates the driver on a serial port which was started with `begin` at the baud
+    /// rate of the module, usually 115200.
+    /// # Arguments
+    /// * `serial` - a `UsartOps` object, the serial port of the module.
+    /// # Returns
+    /// * `a Esp01 object` - the driver, on which `init` must be called.
+    pub fn new(serial: U) -> Esp01<U> {
+        Esp01 {
+            serial,
+            line: [0; LINE_SIZE],
+            line_len: 0,
+            line_done: false,
+            ipd_link: 0,
+            ipd_remaining: 0,
+            stash: [0; STASH_SIZE],
+            stash_len: 0,
+            stash_link: 0,
+            connected: 0,
+        }
+    }
+
+    /// Checks that the module answers and sets it up: echo off, station mode
+    /// and multiple links.
+    /// # Returns
+    /// * `a Result<(), EspError>` - the error if the module did not accept a command.
+    pub fn init(&mut self) -> Result<(), EspError> {
+        self.command(&[b"AT"], COMMAND_TIMEOUT)?;
+        self.command(&[b"ATE0"], COMMAND_TIMEOUT)?;
+        self.command(&[b"AT+CWMODE=1"], COMMAND_TIMEOUT)?;
+        self.command(&[b"AT+CIPMUX=1"], COMMAND_TIMEOUT)
+    }
+
+    /// Restarts the module, after which `init` must be called again.
+    pub fn reset(&mut self) -> Result<(), EspError> {
+        self.send_command(&[b"AT+RST"]);
+        self.connected = 0;
+        self.wait_for(b"ready", RESET_TIMEOUT)
+    }
+
+    /// Joins a WiFi network.
+    /// # Arguments
+    /// * `ssid` - a string slice, the name of the network.
+    /// * `password` - a string slice, the password of the network.
+    /// # Returns
+    /// * `a Result<(), EspError>` - `JoinFailed` with the reason if the network was not joined.
+    pub fn join(&mut self, ssid: &str, password: &str) -> Result<(), EspError> {
+        self.send_command(&[
+            b"AT+CWJAP=\"",
+            ssid.as_bytes(),
+            b"\",\"",
+            password.as_bytes(),
+            b"\"",
+        ]);
+        let deadline = millis().wrapping_add(JOIN_TIMEOUT);
+        let mut reason = 0;
+        loop {
+            match self.read_reply(deadline)? {
+                Reply::Prompt => {}
+                Reply::Line => {
+                    let line = &self.line[..self.line_len];
+                    if line == b"OK" {
+                        return Ok(());
+                    }
+                    if line.starts_with(b"+CWJAP:") {
+                        reason = parse_number(&line[7..]).unwrap_or(0) as u8;
+                    }
+                    if line == b"FAIL" || line == b"ERROR" {
+                        return Err(EspError::JoinFailed(reason));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Leaves the WiFi network.
+    pub fn leave(&mut self) -> Result<(), EspError> {
+        self.connected = 0;
+        self.command(&[b"AT+CWQAP"], COMMAND_TIMEOUT)
+    }
+
+    /// Gives the IP address the module got from the network.
+    /// # Returns
+    /// * `a Result<[u8; 4], EspError>` - the address, 0.0.0.0 if the network is not joined.
+    pub fn local_ip(&mut self) -> Result<[u8; 4], EspError> {
+        self.send_command(&[b"AT+CIFSR"]);
+        let deadline = millis().wrapping_add(COMMAND_TIMEOUT);
+        let mut ip = [0; 4];
+        loop {
+            if let Reply::Line = self.read_reply(deadline)? {
+                let line = &self.line[..self.line_len];
+                if line.starts_with(b"+CIFSR:STAIP,\"") {
+                    ip = parse_ip(&line[14..]);
+                } else if line == b"OK" {
+                    return Ok(ip);
+                } else if line == b"ERROR" {
+                    return Err(EspError::Error);
+                }
+            }
+        }
+    }
+
+    /// Opens a TCP connection to a server.
+    /// # Arguments
+    /// * `link` - a u8, the link from 0 to 4.
+    /// * `host` - a string slice, the name or IP address of the server.
+    /// * `port` - a u16, the port of the server.
+    /// # Returns
+    /// * `a Result<(), EspError>` - the error if the connection failed.
+    pub fn connect_tcp(&mut self, link: u8, host: &str, port: u16) -> Result<(), EspError> {
+        check_link(link)?;
+        let mut digits = [0; 5];
+        self.command(
+            &[
+                b"AT+CIPSTART=",
+                &[b'0' + link],
+                b",\"TCP\",\"",
+                host.as_bytes(),
+                b"\",",
+                format_number(port, &mut digits),
+            ],
+            CONNECT_TIMEOUT,
+        )?;
+        self.connected |= 1 << link;
+        Ok(())
+    }
+
+    /// Opens a UDP link to a host.
+    /// # Arguments
+    /// * `link` - a u8, the link from 0 to 4.
+    /// * `host` - a string slice, the name or IP address of the host.
+    /// * `remote_port` - a u16, the port the datagrams are sent to.
+    /// * `local_port` - a u16, the port the datagrams are received on.
+    /// # Returns
+    /// * `a Result<(), EspError>` - the error if the link could not be opened.
+    pub fn connect_udp(
+        &mut self,
+        link: u8,
+        host: &str,
+        remote_port: u16,
+        local_port: u16,
+    ) -> Result<(), EspError> {
+        check_link(link)?;
+        let mut remote = [0; 5];
+        let mut local = [0; 5];
+        self.command(
+            &[
+                b"AT+CIPSTART=",
+                &[b'0' + link],
+                b",\"UDP\",\"",
+                host.as_bytes(),
+                b"\",",
+                format_number(remote_port, &mut remote),
+                b",",
+                format_number(local_port, &mut local),
+                b",0",
+            ],
+            CONNECT_TIMEOUT,
+        )?;
+        self.connected |= 1 << link;
+        Ok(())
+    }
+
+    /// Starts a TCP server, whose clients get the free links as they connect.
+    /// # Arguments
+    /// * `port` - a u16, the port the server listens on.
+    pub fn listen(&mut self, port: u16) -> Result<(), EspError> {
+        let mut digits = [0; 5];
+        self.command(
+            &[b"AT+CIPSERVER=1,", format_number(port, &mut digits)],
+            COMMAND_TIMEOUT,
+        )
+    }
+
+    /// Closes a link.
+    pub fn close(&mut self, link: u8) -> Result<(), EspError> {
+        check_link(link)?;
+        self.connected &= !(1 << link);
+        self.command(&[b"AT+CIPCLOSE=", &[b'0' + link]], COMMAND_TIMEOUT)
+    }
+
+    /// Checks whether a link is connected, as last reported by the module.
+    /// The reports are read by the other functions, like `receive`.
+    pub fn is_connected(&self, link: u8) -> bool {
+        link < LINKS && self.connected & (1 << link) != 0
+    }
+
+    /// Sends data on a link, waiting until the module has sent it.
+    /// # Arguments
+    /// * `link` - a u8, the link from 0 to 4.
+    /// * `data` - a slice of u8, the data to be sent.
+    /// # Returns
+    /// * `a Result<(), EspError>` - `SendFailed` or `Closed` if the data was not sent.
+    pub fn send(&mut self, link: u8, data: &[u8]) -> Result<(), EspError> {
+        check_link(link)?;
+        for chunk in data.chunks(SEND_CHUNK) {
+            let mut digits = [0; 5];
+            self.send_command(&[
+                b"AT+CIPSEND=",
+                &[b'0' + link],
+                b",",
+                format_number(chunk.len() as u16, &mut digits),
+            ]);
+            let deadline = millis().wrapping_add(SEND_TIMEOUT);
+            loop {
+                match self.read_reply(deadline)? {
+                    Reply::Prompt => break,
+                    Reply::Line => {
+                        let line = &self.line[..self.line_len];
+                        if line == b"link is not valid" {
+                            return Err(EspError::Closed);
+                        }
+                        if line == b"ERROR" {
+                            return Err(EspError::Error);
+                        }
+                    }
+                }
+            }
+
+            self.serial.write_bytes(chunk);
+            loop {
+                if let Reply::Line = self.read_reply(deadline)? {
+                    let line = &self.line[..self.line_len];
+                    if line == b"SEND OK" {
+                        break;
+                    }
+                    if line == b"SEND FAIL" || line == b"ERROR" {
+                        return Err(EspError::SendFailed);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads data received on any link.
+    /// # Arguments
+    /// * `buffer` - a mutable slice of u8, which will be filled with the data.
+    /// * `timeout_ms` - a u32, the time in milliseconds to wait for data, 0 to only
+    ///   read what has already arrived.
+    /// # Returns
+    /// * `a Result<Option<(u8, usize)>, EspError>` - the link and the number of bytes
+    ///   read, or None if nothing came in time.
+    pub fn receive(
+        &mut self,
+        buffer: &mut [u8],
+        timeout_ms: u32,
+    ) -> Result<Option<(u8, usize)>, EspError> {
+        if self.stash_len > 0 {
+            let count = self.stash_len.min(buffer.len());
+            buffer[..count].copy_from_slice(&self.stash[..count]);
+            self.stash.copy_within(count..self.stash_len, 0);
+            self.stash_len -= count;
+            return Ok(Some((self.stash_link, count)));
+        }
+
+        let deadline = millis().wrapping_add(timeout_ms);
+        // Lines are read until data starts, which leaves the header consumed.
+        while self.ipd_remaining == 0 {
+            if !self.serial.available() && expired(deadline) {
+                return Ok(None);
+            }
+            if self.read_reply(deadline).is_err() {
+                return Ok(None);
+            }
+        }
+
+        let mut count = 0;
+        while count < buffer.len() && self.ipd_remaining > 0 {
+            match self.serial.read_byte() {
+                Some(byte) => {
+                    buffer[count] = byte;
+                    count += 1;
+                    self.ipd_remaining -= 1;
+                }
+                // The data is already on its way, so the wait is not limited by the deadline.
+                None => {
+                    if expired(deadline.wrapping_add(COMMAND_TIMEOUT)) {
+                        return Err(EspError::Timeout);
+                    }
+                }
+            }
+        }
+        Ok(Some((self.ipd_link, count)))
+    }
+
+    /// Sends a command and waits for OK.
+    fn command(&mut self, parts: &[&[u8]], timeout_ms: u32) -> Result<(), EspError> {
+        self.send_command(parts);
+        self.wait_for(b"OK", timeout_ms)
+    }
+
+    /// Writes the parts of a command followed by CR LF.
+    fn send_command(&mut self, parts: &[&[u8]]) {
+        for part in parts {
+            self.serial.write_bytes(part);
+        }
+        self.serial.write_bytes(b"\r\n");
+    }
+
+    /// Waits for a line, failing on ERROR or FAIL.
+    fn wait_for(&mut self, expected: &[u8], timeout_ms: u32) -> Result<(), EspError> {
+        let deadline = millis().wrapping_add(timeout_ms);
+        loop {
+            if let Reply::Line = self.read_reply(deadline)? {
+                let line = &self.line[..self.line_len];
+                if line == expected || (expected == b"OK" && line == b"ALREADY CONNECTED") {
+                    return Ok(());
+                }
+                if line == b"ERROR" || line == b"FAIL" {
+                    return Err(EspError::Error);
+                }
+            }
+        }
+    }
+
+    /// Reads the serial port until a whole line or the prompt `>` is received.
+    /// The connection reports are handled here, the data of `+IPD` received while no
+    /// data was expected is kept in the stash, and the start of new data stops the
+    /// reading with an empty line.
+    fn read_reply(&mut self, deadline: u32) -> Result<Reply, EspError> {
+        // A line cut by the deadline is continued by the next call.
+        if self.line_done {
+            self.line_len = 0;
+            self.line_done = false;
+        }
+        loop {
+            let byte = match self.serial.read_byte() {
+                Some(byte) => byte,
+                None => {
+                    if expired(deadline) {
+                        return Err(EspError::Timeout);
+                    }
+                    continue;
+                }
+            };
+
+            // Data of a `+IPD` which nobody is reading.
+            if self.ipd_remaining > 0 {
+                self.ipd_remaining -= 1;
+                if (self.stash_len == 0 || self.stash_link == self.ipd_link)
+                    && self.stash_len < STASH_SIZE
+                {
+                    self.stash_link = self.ipd_link;
+                    self.stash[self.stash_len] = byte;
+                    self.stash_len += 1;
+                }
+                continue;
+            }
+
+            match byte {
+                b'\r' => {}
+                b'\n' => {
+                    if self.line_len > 0 {
+                        self.handle_report();
+                        self.line_done = true;
+                        return Ok(Reply::Line);
+                    }
+                }
+                b'>' if self.line_len == 0 => return Ok(Reply::Prompt),
+                b':' if self.line_len > 5 && self.line[..self.line_len].starts_with(b"+IPD,") => {
+                    // `+IPD,<link>,<length>` then the data.
+                    let header = &self.line[5..self.line_len];
+                    self.ipd_link = header[0].wrapping_sub(b'0');
+                    self.ipd_remaining = match header.iter().position(|&c| c == b',') {
+                        Some(comma) => parse_number(&header[comma + 1..]).unwrap_or(0) as usize,
+                        None => 0,
+                    };
+                    self.line_len = 0;
+                    self.line_done = true;
+                    // A reader of data stops here, others store it in the stash.
+                    return Ok(Reply::Line);
+                }
+                _ => {
+                    if self.line_len < LINE_SIZE {
+                        self.line[self.line_len] = byte;
+                        self.line_len += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Keeps track of the links from the `<link>,CONNECT` and `<link>,CLOSED` reports.
+    fn handle_report(&mut self) {
+        let line = &self.line[..self.line_len];
+        if line.len() > 2 && line[1] == b',' && line[0].is_ascii_digit() {
+            let link = line[0] - b'0';
+            if link >= LINKS {
+                return;
+            }
+            if &line[2..] == b"CONNECT" {
+                self.connected |= 1 << link;
+            } else if &line[2..] == b"CLOSED" || &line[2..] == b"CONNECT FAIL" {
+                self.connected &= !(1 << link);
+            }
+        }
+    }
+}
+
+/// Checks the number of a link.
+fn check_link(link: u8) -> Result<(), EspError> {
+    if link < LINKS {
+        Ok(())
+    } else {
+        Err(EspError::InvalidLink)
+    }
+}
+
+/// Checks whether the time given by `millis` has passed a deadline, across the overflow.
+fn expired(deadline: u32) -> bool {
+    (millis().wrapping_sub(deadline) as i32) >= 0
+}
+
+/// Reads the decimal number at the start of a slice.
+fn parse_number(text: &[u8]) -> Option<u32> {
+    let mut value: u32 = 0;
+    let mut digits = 0;
+    for &c in text.iter().take_while(|c| c.is_ascii_digit()) {
+        value = value.wrapping_mul(10).wrapping_add((c - b'0') as u32);
+        digits += 1;
+    }
+    if digits > 0 {
+        Some(value)
+    } else {
+        None
+    }
+}
+
+/// Reads an IP address like `192.168.1.5`.
+fn parse_ip(text: &[u8]) -> [u8; 4] {
+    let mut ip = [0; 4];
+    for (i, part) in text.split(|&c| c == b'.').take(4).enumerate() {
+        ip[i] = parse_number(part).unwrap_or(0) as u8;
+    }
+    ip
+}
+
+/// Writes a number in decimal.
+/// # Returns
+/// * `a slice of u8` - the digits, at the end of the given buffer.
+fn format_number(mut value: u16, digits: &mut [u8; 5]) -> &[u8] {
+    let mut i = digits.len();
+    loop {
+        i -= 1;
+        digits[i] = b'0' + (value % 10) as u8;
+        value /= 10;
+        if value == 0 {
+            break;
+        }
+    }
+    &digits[i..]
+}
@@ -0,0 +1,634 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! This code controls the WIZnet W5500 Ethernet controller over SPI.
+//! The W5500 runs the TCP/IP stack itself and offers 8 hardware sockets, numbered
+//! 0 to 7, each with 2 KB for sending and 2 KB for receiving. A socket is opened as
+//! TCP or UDP on a local port, after which a TCP socket either connects to a server
+//! or listens for a client, and a UDP socket sends and receives datagrams.
+//! The chip select of the W5500 can be any digital pin, but the hardware SS pin is
+//! still an output as required by the SPI master.
+//! See `<https://docs.wiznet.io/img/products/w5500/W5500_ds_v110e.pdf>`.
+
+// Source codes required.
+use crate::bus::{SharedSpi, SpiDevice};
+use crate::common::SpiOps;
+use crate::delay::delay_ms;
+use crate::error::Error;
+
+/// Number of hardware sockets of the W5500.
+pub const SOCKETS: u8 = 8;
+
+// Highest SCK frequency used with the W5500.
+const SPI_FREQUENCY: u32 = 8_000_000;
+
+// Addresses of the common registers.
+const MR: u16 = 0x0000;
+const GAR: u16 = 0x0001;
+const SUBR: u16 = 0x0005;
+const SHAR: u16 = 0x0009;
+const SIPR: u16 = 0x000F;
+const RTR: u16 = 0x0019;
+const RCR: u16 = 0x001B;
+const PHYCFGR: u16 = 0x002E;
+const VERSIONR: u16 = 0x0039;
+
+// Addresses of the socket registers.
+const SN_MR: u16 = 0x0000;
+const SN_CR: u16 = 0x0001;
+const SN_IR: u16 = 0x0002;
+const SN_SR: u16 = 0x0003;
+const SN_PORT: u16 = 0x0004;
+const SN_DIPR: u16 = 0x000C;
+const SN_DPORT: u16 = 0x0010;
+const SN_TX_FSR: u16 = 0x0020;
+const SN_TX_WR: u16 = 0x0024;
+const SN_RX_RSR: u16 = 0x0026;
+const SN_RX_RD: u16 = 0x0028;
+
+// Protocols of the mode register of a socket.
+const SN_MR_TCP: u8 = 0x01;
+const SN_MR_UDP: u8 = 0x02;
+
+// Commands of a socket.
+const CMD_OPEN: u8 = 0x01;
+const CMD_LISTEN: u8 = 0x02;
+const CMD_CONNECT: u8 = 0x04;
+const CMD_DISCON: u8 = 0x08;
+const CMD_CLOSE: u8 = 0x10;
+const CMD_SEND: u8 = 0x20;
+const CMD_RECV: u8 = 0x40;
+
+// Bits of the interrupt register of a socket.
+const IR_SEND_OK: u8 = 0x10;
+const IR_TIMEOUT: u8 = 0x08;
+
+// Size of the header the W5500 puts before each received UDP datagram.
+const UDP_HEADER: u16 = 8;
+
+/// Version number read from a working W5500.
+const VERSION: u8 = 0x04;
+
+/// Errors which can happen while using the sockets.
+/// * `NoChip` - the W5500 did not answer with its version number.
+/// * `InvalidSocket` - the socket number is not between 0 and 7.
+/// * `Timeout` - the other side did not answer in time.
+/// * `Closed` - the connection is closed, or the socket is not in the right state.
+/// * `Rejected` - a server refused the request, like a DHCP server sending a NAK.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum NetError {
+    NoChip,
+    InvalidSocket,
+    Timeout,
+    Closed,
+    Rejected,
+}
+
+impl From<NetError> for Error {
+    fn from(error: NetError) -> Error {
+        match error {
+            NetError::NoChip => Error::NotFound,
+            NetError::InvalidSocket => Error::InvalidParam,
+            NetError::Timeout => Error::Timeout,
+            NetError::Closed => Error::Closed,
+            NetError::Rejected => Error::Nack,
+        }
+    }
+}
+
+/// Protocol of a socket.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Protocol {
+    Tcp,
+    Udp,
+}
+
+/// State of a socket, as given by its status register.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SocketStatus {
+    Closed,
+    Init,
+    Listen,
+    SynSent,
+    SynReceived,
+    Established,
+    FinWait,
+    Closing,
+    TimeWait,
+    CloseWait,
+    LastAck,
+    Udp,
+    Other(u8),
+}
+
+impl SocketStatus {
+    fn from_register(value: u8) -> SocketStatus {
+        match value {
+            0x00 => SocketStatus::Closed,
+            0x13 => SocketStatus::Init,
+            0x14 => SocketStatus::Listen,
+            0x15 => SocketStatus::SynSent,
+            0x16 => SocketStatus::SynReceived,
+            0x17 => SocketStatus::Established,
+            0x18 => SocketStatus::FinWait,
+            0x1A => SocketStatus::Closing,
+            0x1B => SocketStatus::TimeWait,
+            0x1C => SocketStatus::CloseWait,
+            0x1D => SocketStatus::LastAck,
+            0x22 => SocketStatus::Udp,
+            other => SocketStatus::Other(other),
+        }
+    }
+}
+
+/// Used to control a W5500 on the SPI bus.
+/// # Elements
+/// * `device` - a `SpiDevice` object, the handle of the W5500 on the SPI bus.
+pub struct W5500<T = SharedSpi> {
+    device: SpiDevice<T>,
+}
+
+impl W5500 {
+    /// Resets the W5500 on the SPI of the chip and checks that it answers.
+    /// # Arguments
+    /// * `cs` - a u8, the digital pin connected to the chip select of the W5500.
+    /// # Returns
+    /// * `a Result<W5500, NetError>` - the controller, or `NoChip` if it did not answer.
+    pub fn new(cs: u8) -> Result<W5500, NetError> {
+        W5500::with_bus(SharedSpi::new(), cs)
+    }
+}
+
+impl<T: SpiOps> W5500<T> {
+    /// Resets the W5500 on the given SPI bus and checks that it answers.
+    /// The SPI runs at 8 MHz at most, well below the limit of the W5500.
+    /// # Arguments
+    /// * `bus` - a `SpiOps` object, the SPI bus of the W5500.
+    /// * `cs` - a u8, the digital pin connected to the chip select of the W5500.
+    /// # Returns
+    /// * `a Result<W5500, NetError>` - the controller, or `NoChip` if it did not answer.
+    pub fn with_bus(bus: T, cs: u8) -> Result<W5500<T>, NetError> {
+        let mut device = SpiDevice::with_bus(bus, cs, 0, false, SPI_FREQUENCY);
+        device.init();
+
+        let mut w5500 = W5500 { device };
+        w5500.reset();
+        if w5500.version() != VERSION {
+            return Err(NetError::NoChip);
+        }
+        Ok(w5500)
+    }
+
+    /// Resets all the registers of the W5500, which closes every socket.
+    pub fn reset(&mut self) {
+        self.write_u8(0, MR, 0x80);
+        // The reset bit clears itself when the reset is done.
+        while self.read_u8(0, MR) & 0x80 != 0 {}
+    }
+
+    /// Gives the version number of the chip, which is 4 for the W5500.
+    pub fn version(&mut self) -> u8 {
+        self.read_u8(0, VERSIONR)
+    }
+
+    /// Checks whether the Ethernet cable is connected to a working link.
+    /// # Returns
+    /// * `a boolean` - true if the link is up.
+    pub fn link_up(&mut self) -> bool {
+        self.read_u8(0, PHYCFGR) & 0x01 != 0
+    }
+
+    /// Sets the addresses used by the W5500 on the network.
+    /// # Arguments
+    /// * `mac` - an array of 6 u8, the hardware address.
+    /// * `ip` - an array of 4 u8, the IP address.
+    /// * `subnet` - an array of 4 u8, the subnet mask.
+    /// * `gateway` - an array of 4 u8, the IP address of the router.
+    pub fn configure(&mut self, mac: [u8; 6], ip: [u8; 4], subnet: [u8; 4], gateway: [u8; 4]) {
+        self.set_mac(mac);
+        self.set_ip(ip);
+        self.set_subnet(subnet);
+        self.set_gateway(gateway);
+    }
+
+    /// Sets the hardware address.
+    pub fn set_mac(&mut self, mac: [u8; 6]) {
+        self.write(0, SHAR, &mac);
+    }
+
+    /// Gives the hardware address.
+    pub fn mac(&mut self) -> [u8; 6] {
+        let mut mac = [0; 6];
+        self.read(0, SHAR, &mut mac);
+        mac
+    }
+
+    /// Sets the IP address.
+    pub fn set_ip(&mut self, ip: [u8; 4]) {
+        self.write(0, SIPR, &ip);
+    }
+
+    /// Gives the IP address.
+    pub fn ip(&mut self) -> [u8; 4] {
+        let mut ip = [0; 4];
+        self.read(0, SIPR, &mut ip);
+        ip
+    }
+
+    /// Sets the subnet mask.
+    pub fn set_subnet(&mut self, subnet: [u8; 4]) {
+        self.write(0, SUBR, &subnet);
+    }
+
+    /// Sets the IP address of the router.
+    pub fn set_gateway(&mut self, gateway: [u8; 4]) {
+        self.write(0, GAR, &gateway);
+    }
+
+    /// Sets how long and how many times the W5500 retransmits before giving up.
+    /// # Arguments
+    /// * `time` - a u16, the time before a retransmission in units of 100 us.
+    /// * `count` - a u8, the number of retransmissions.
+    pub fn set_retransmission(&mut self, time: u16, count: u8) {
+        self.write(0, RTR, &time.to_be_bytes());
+        self.write_u8(0, RCR, count);
+    }
+
+    /// Opens a socket on a local port.
+    /// # Arguments
+    /// * `socket` - a u8, the number of the socket from 0 to 7.
+    /// * `protocol` - a `Protocol` object, TCP or UDP.
+    /// * `port` - a u16, the local port.
+    /// # Returns
+    /// * `a Result<(), NetError>` - the error if the socket did not open.
+    pub fn open(&mut self, socket: u8, protocol: Protocol, port: u16) -> Result<(), NetError> {
+        self.check(socket)?;
+        self.close(socket)?;
+        let (mode, status) = match protocol {
+            Protocol::Tcp => (SN_MR_TCP, SocketStatus::Init),
+            Protocol::Udp => (SN_MR_UDP, SocketStatus::Udp),
+        };
+        self.write_u8(sreg(socket), SN_MR, mode);
+        self.write(sreg(socket), SN_PORT, &port.to_be_bytes());
+        self.command(socket, CMD_OPEN);
+        if self.status(socket) != status {
+            return Err(NetError::Closed);
+        }
+        Ok(())
+    }
+
+    /// Closes a socket at once, without telling the other side.
+    pub fn close(&mut self, socket: u8) -> Result<(), NetError> {
+        self.check(socket)?;
+        self.command(socket, CMD_CLOSE);
+        // All the interrupt flags are cleared by writing ones.
+        self.write_u8(sreg(socket), SN_IR, 0xFF);
+        Ok(())
+    }
+
+    /// Ends a TCP connection properly, then closes the socket.
+    pub fn disconnect(&mut self, socket: u8) -> Result<(), NetError> {
+        self.check(socket)?;
+        self.command(socket, CMD_DISCON);
+        let mut waited = 0;
+        while self.status(socket) != SocketStatus::Closed {
+            waited += 1;
+            if waited > 1000 {
+                break;
+            }
+            delay_ms(1);
+        }
+        self.close(socket)
+    }
+
+    /// Gives the state of a socket.
+    pub fn status(&mut self, socket: u8) -> SocketStatus {
+        SocketStatus::from_register(self.read_u8(sreg(socket), SN_SR))
+    }
+
+    /// Checks whether a TCP socket has a client or server connected.
+    pub fn is_connected(&mut self, socket: u8) -> bool {
+        self.status(socket) == SocketStatus::Established
+    }
+
+    /// Makes an opened TCP socket wait for a client.
+    /// The socket becomes `Established` once a client connects.
+    /// # Returns
+    /// * `a Result<(), NetError>` - the error if the socket was not opened as TCP.
+    pub fn listen(&mut self, socket: u8) -> Result<(), NetError> {
+        self.check(socket)?;
+        if self.status(socket) != SocketStatus::Init {
+            return Err(NetError::Closed);
+        }
+        self.command(socket, CMD_LISTEN);
+        if self.status(socket) != SocketStatus::Listen {
+            return Err(NetError::Closed);
+        }
+        Ok(())
+    }
+
+    /// Connects an opened TCP socket to a server and waits for the connection.
+    /// # Arguments
+    /// * `socket` - a u8, the number of the socket.
+    /// * `ip` - an array of 4 u8, the IP address of the server.
+    /// * `port` - a u16, the port of the server.
+    /// # Returns
+    /// * `a Result<(), NetError>` - `Timeout` if the server did not answer, `Closed` if it refused.
+    pub fn connect(&mut self, socket: u8, ip: [u8; 4], port: u16) -> Result<(), NetError> {
+        self.check(socket)?;
+        if self.status(socket) != SocketStatus::Init {
+            return Err(NetError::Closed);
+        }
+        self.write(sreg(socket), SN_DIPR, &ip);
+        self.write(sreg(socket), SN_DPORT, &port.to_be_bytes());
+        self.command(socket, CMD_CONNECT);
+        loop {
+            match self.status(socket) {
+                SocketStatus::Established => return Ok(()),
+                SocketStatus::Closed => {
+                    let timeout = self.read_u8(sreg(socket), SN_IR) & IR_TIMEOUT != 0;
+                    self.write_u8(sreg(socket), SN_IR, 0xFF);
+                    return Err(if timeout {
+                        NetError::Timeout
+                    } else {
+                        NetError::Closed
+                    });
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Gives the number of bytes received and not read yet.
+    /// For a UDP socket this includes the 8 byte header of every datagram.
+    pub fn available(&mut self, socket: u8) -> u16 {
+        self.read_stable_u16(sreg(socket), SN_RX_RSR)
+    }
+
+    /// Sends data on a connected TCP socket, waiting for free space and for the
+    /// other side to acknowledge it.
+    /// # Arguments
+    /// * `socket` - a u8, the number of the socket.
+    /// * `data` - a slice of u8, the data to be sent.
+    /// # Returns
+    /// * `a Result<usize, NetError>` - the number of bytes sent, or the error which stopped it.
+    pub fn send(&mut self, socket: u8, data: &[u8]) -> Result<usize, NetError> {
+        self.check(socket)?;
+        let mut sent = 0;
+        while sent < data.len() {
+            let free = loop {
+                match self.status(socket) {
+                    SocketStatus::Established | SocketStatus::CloseWait => {}
+                    _ => return Err(NetError::Closed),
+                }
+                let free = self.read_stable_u16(sreg(socket), SN_TX_FSR) as usize;
+                if free > 0 {
+                    break free;
+                }
+            };
+            let length = free.min(data.len() - sent);
+            self.buffer_data(socket, 0, &data[sent..sent + length]);
+            self.send_buffered(socket, length as u16)?;
+            sent += length;
+        }
+        Ok(sent)
+    }
+
+    /// Reads the data received on a TCP socket, without waiting.
+    /// # Arguments
+    /// * `socket` - a u8, the number of the socket.
+    /// * `buffer` - a mutable slice of u8, which will be filled with the data.
+    /// # Returns
+    /// * `a Result<usize, NetError>` - the number of bytes read, which may be 0,
+    ///   or `Closed` once the other side has closed and all the data has been read.
+    pub fn recv(&mut self, socket: u8, buffer: &mut [u8]) -> Result<usize, NetError> {
+        self.check(socket)?;
+        let available = self.available(socket) as usize;
+        if available == 0 {
+            return match self.status(socket) {
+                SocketStatus::Established
+                | SocketStatus::Listen
+                | SocketStatus::SynSent
+                | SocketStatus::SynReceived => Ok(0),
+                _ => Err(NetError::Closed),
+            };
+        }
+        let length = available.min(buffer.len());
+        self.read_received(socket, 0, &mut buffer[..length]);
+        self.consume(socket, length as u16);
+        Ok(length)
+    }
+
+    /// Sends a datagram on a UDP socket.
+    /// # Arguments
+    /// * `socket` - a u8, the number of the socket.
+    /// * `data` - a slice of u8, the datagram, at most 2 KB.
+    /// * `ip` - an array of 4 u8, the IP address of the destination.
+    /// * `port` - a u16, the port of the destination.
+    /// # Returns
+    /// * `a Result<usize, NetError>` - the number of bytes sent.
+    pub fn send_to(
+        &mut self,
+        socket: u8,
+        data: &[u8],
+        ip: [u8; 4],
+        port: u16,
+    ) -> Result<usize, NetError> {
+        self.check(socket)?;
+        if self.status(socket) != SocketStatus::Udp {
+            return Err(NetError::Closed);
+        }
+        while (self.read_stable_u16(sreg(socket), SN_TX_FSR) as usize) < data.len() {}
+        self.buffer_data(socket, 0, data);
+        self.set_destination(socket, ip, port);
+        self.send_buffered(socket, data.len() as u16)?;
+        Ok(data.len())
+    }
+
+    /// Reads a datagram received on a UDP socket, without waiting.
+    /// The part of the datagram which does not fit in the buffer is dropped.
+    /// # Arguments
+    /// * `socket` - a u8, the number of the socket.
+    /// * `buffer` - a mutable slice of u8, which will be filled with the datagram.
+    /// # Returns
+    /// * `a Result<Option<(usize, [u8; 4], u16)>, NetError>` - the number of bytes read,
+    ///   the IP address and the port of the sender, or None if nothing was received.
+    pub fn recv_from(
+        &mut self,
+        socket: u8,
+        buffer: &mut [u8],
+    ) -> Result<Option<(usize, [u8; 4], u16)>, NetError> {
+        self.check(socket)?;
+        let (length, ip, port) = match self.peek_datagram(socket) {
+            Some(header) => header,
+            None => return Ok(None),
+        };
+        let count = (length as usize).min(buffer.len());
+        self.read_received(socket, UDP_HEADER, &mut buffer[..count]);
+        self.consume(socket, UDP_HEADER + length);
+        Ok(Some((count, ip, port)))
+    }
+
+    /// Reads the header of the next datagram of a UDP socket, leaving it in the buffer.
+    /// # Returns
+    /// * `a Option<(u16, [u8; 4], u16)>` - the length of the data, the IP address and
+    ///   the port of the sender, or None if nothing was received.
+    pub(crate) fn peek_datagram(&mut self, socket: u8) -> Option<(u16, [u8; 4], u16)> {
+        if self.available(socket) < UDP_HEADER {
+            return None;
+        }
+        let mut header = [0; UDP_HEADER as usize];
+        self.read_received(socket, 0, &mut header);
+        Some((
+            u16::from_be_bytes([header[6], header[7]]),
+            [header[0], header[1], header[2], header[3]],
+            u16::from_be_bytes([header[4], header[5]]),
+        ))
+    }
+
+    /// Reads the data of the next datagram of a UDP socket, leaving it in the buffer.
+    pub(crate) fn read_datagram(&mut self, socket: u8, offset: u16, buffer: &mut [u8]) {
+        self.read_received(socket, UDP_HEADER + offset, buffer);
+    }
+
+    /// Drops the next datagram of a UDP socket.
+    pub(crate) fn drop_datagram(&mut self, socket: u8, length: u16) {
+        self.consume(socket, UDP_HEADER + length);
+    }
+
+    /// Sets the destination of the datagrams of a UDP socket.
+    pub(crate) fn set_destination(&mut self, socket: u8, ip: [u8; 4], port: u16) {
+        self.write(sreg(socket), SN_DIPR, &ip);
+        self.write(sreg(socket), SN_DPORT, &port.to_be_bytes());
+    }
+
+    /// Copies data into the transmit buffer at an offset from the write pointer,
+    /// without sending it, so that a packet can be built in parts.
+    pub(crate) fn buffer_data(&mut self, socket: u8, offset: u16, data: &[u8]) {
+        let pointer = self.read_u16(sreg(socket), SN_TX_WR).wrapping_add(offset);
+        // The W5500 wraps addresses inside the buffer by itself.
+        self.write(txbuf(socket), pointer, data);
+    }
+
+    /// Sends the given number of bytes of the transmit buffer and waits until they are sent.
+    pub(crate) fn send_buffered(&mut self, socket: u8, length: u16) -> Result<(), NetError> {
+        let pointer = self.read_u16(sreg(socket), SN_TX_WR).wrapping_add(length);
+        self.write(sreg(socket), SN_TX_WR, &pointer.to_be_bytes());
+        self.command(socket, CMD_SEND);
+        loop {
+            let flags = self.read_u8(sreg(socket), SN_IR);
+            if flags & IR_SEND_OK != 0 {
+                self.write_u8(sreg(socket), SN_IR, IR_SEND_OK);
+                return Ok(());
+            }
+            if flags & IR_TIMEOUT != 0 {
+                self.write_u8(sreg(socket), SN_IR, IR_TIMEOUT);
+                return Err(NetError::Timeout);
+            }
+            if self.status(socket) == SocketStatus::Closed {
+                return Err(NetError::Closed);
+            }
+        }
+    }
+
+    /// Reads the receive buffer at an offset from the read pointer.
+    fn read_received(&mut self, socket: u8, offset: u16, buffer: &mut [u8]) {
+        let pointer = self.read_u16(sreg(socket), SN_RX_RD).wrapping_add(offset);
+        self.read(rxbuf(socket), pointer, buffer);
+    }
+
+    /// Frees the given number of bytes of the receive buffer.
+    fn consume(&mut self, socket: u8, length: u16) {
+        let pointer = self.read_u16(sreg(socket), SN_RX_RD).wrapping_add(length);
+        self.write(sreg(socket), SN_RX_RD, &pointer.to_be_bytes());
+        self.command(socket, CMD_RECV);
+    }
+
+    /// Checks the number of a socket.
+    fn check(&self, socket: u8) -> Result<(), NetError> {
+        if socket < SOCKETS {
+            Ok(())
+        } else {
+            Err(NetError::InvalidSocket)
+        }
+    }
+
+    /// Gives a command to a socket and waits until the W5500 takes it.
+    fn command(&mut self, socket: u8, command: u8) {
+        self.write_u8(sreg(socket), SN_CR, command);
+        while self.read_u8(sreg(socket), SN_CR) != 0 {}
+    }
+
+    /// Writes registers or buffer memory, starting at an address of a block.
+    fn write(&mut self, block: u8, address: u16, data: &[u8]) {
+        let _ = self.device.transaction(|spi| {
+            spi.write(&[(address >> 8) as u8, address as u8, block << 3 | 0x04]);
+            spi.write(data);
+        });
+    }
+
+    /// Reads registers or buffer memory, starting at an address of a block.
+    fn read(&mut self, block: u8, address: u16, buffer: &mut [u8]) {
+        let _ = self.device.transaction(|spi| {
+            spi.write(&[(address >> 8) as u8, address as u8, block << 3]);
+            spi.read(buffer);
+        });
+    }
+
+    fn write_u8(&mut self, block: u8, address: u16, value: u8) {
+        self.write(block, address, &[value]);
+    }
+
+    fn read_u8(&mut self, block: u8, address: u16) -> u8 {
+        let mut value = [0];
+        self.read(block, address, &mut value);
+        value[0]
+    }
+
+    fn read_u16(&mut self, block: u8, address: u16) -> u16 {
+        let mut value = [0; 2];
+        self.read(block, address, &mut value);
+        u16::from_be_bytes(value)
+    }
+
+    /// Reads a 16 bit register which may change between its two bytes,
+    /// until the same value is read twice.
+    fn read_stable_u16(&mut self, block: u8, address: u16) -> u16 {
+        let mut value = self.read_u16(block, address);
+        loop {
+            let again = self.read_u16(block, address);
+            if again == value {
+                return value;
+            }
+            value = again;
+        }
+    }
+}
+
+/// Block of the registers of a socket.
+fn sreg(socket: u8) -> u8 {
+    socket << 2 | 1
+}
+
+/// Block of the transmit buffer of a socket.
+fn txbuf(socket: u8) -> u8 {
+    socket << 2 | 2
+}
+
+/// Block of the receive buffer of a socket.
+fn rxbuf(socket: u8) -> u8 {
+    socket << 2 | 3
+}
@@ -0,0 +1,95 @@
+// RustDuino : A generic HAL implementation for Arduino Boards in Rust
+// Copyright (C) 2021 Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Panic handler of the library, enabled with the `panic` feature, so a program needs
+//! no `#[panic_handler]` of its own.
+//! On a panic the interrupts are disabled and the file, line and column of the panic
+//! are printed through USART0, at the baud rate it was set to, if it was. The message
+//! itself is not printed, since formatting it would take in `core::fmt`.
+//! Then the on-board LED of digital pin 13 blinks SOS for ever, or a few times before
+//! the chip is reset by the watchdog, as chosen with `set_panic_action`.
+
+// Source codes required.
+use crate::delay::delay_ms;
+use crate::hal::pin::make_pin;
+use crate::hal::port::Pin;
+use crate::hal::watchdog::{WatchDog, WatchdogMode, WatchdogPrescaler};
+use crate::print::{write_format, SerialWrite};
+use crate::sync::AtomicByte;
+
+use core::panic::PanicInfo;
+
+/// Digital pin of the on-board LED, on both the Uno and the Mega.
+const LED_PIN: u8 = 13;
+
+/// Number of SOS signals blinked before the reset.
+const BLINKS_BEFORE_RESET: u8 = 3;
+
+/// Selection of what is done after the panic is printed.
+/// * `Blink` - the LED blinks SOS for ever, this is the default.
+/// * `Reset` - the LED blinks SOS three times, then the watchdog resets the chip.
+#[derive(Clone, Copy, PartialEq)]
+pub enum PanicAction {
+    Blink,
+    Reset,
+}
+
+static ACTION: AtomicByte = AtomicByte::new(PanicAction::Blink as u8);
+
+/// Chooses what is done after a panic.
+/// # Arguments
+/// * `action` - a `PanicAction` object, blinking for ever or resetting the chip.
+pub fn set_panic_action(action: PanicAction) {
+    ACTION.store(action as u8);
+}
+
+#[panic_handler]
+fn panic(info: &PanicInfo) -> ! {
+    unsafe { llvm_asm!("cli" :::: "volatile") };
+
+    let mut serial = crate::print::serial();
+    match info.location() {
+        Some(location) => write_format(
+            &mut serial,
+            "\r\npanicked at {}:{}:{}\r\n",
+            &[&location.file(), &location.line(), &location.column()],
+        ),
+        None => serial.write_bytes(b"\r\npanicked\r\n"),
+    }
+
+    let mut led = make_pin(LED_PIN as _);
+    led.set_output();
+    let reset = ACTION.load() == PanicAction::Reset as u8;
+    let mut blinks: u8 = 0;
+    loop {
+        blink_sos(&mut led);
+        blinks = blinks.saturating_add(1);
+        if reset && blinks == BLINKS_BEFORE_RESET {
+            unsafe { WatchDog::new() }.enable(WatchdogMode::Reset, WatchdogPrescaler::Ms16);
+        }
+    }
+}
+
+// Blinks ... --- ... with a pause after it.
+fn blink_sos(led: &mut Pin) {
+    for length in [200, 200, 200, 600, 600, 600, 200, 200, 200].iter() {
+        led.high();
+        delay_ms(*length);
+        led.low();
+        delay_ms(200);
+    }
+    delay_ms(1000);
+}
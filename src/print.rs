@@ -0,0 +1,420 @@
+// RustDuino : A generic HAL implementation for Arduino Boards in Rust
+// Copyright (C) 2021 Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Formatted printing over the serial ports with the `serial_print!` and `serial_println!`
+//! macros, without `core::fmt`, which takes several kilobytes of flash on the AVR chips.
+//! The format string is read when the program runs. `{}` prints the next argument, and
+//! a placeholder can give a width, zero padding, a base and a number of decimals, as
+//! `{:4}`, `{:02x}`, `{:X}`, `{:b}` or `{:.3}`. `{{` and `}}` print the braces.
+//! Integers, floats, booleans, characters and strings can be printed. The code to print
+//! floats is only linked in when a float is printed.
+//! The macros write to USART0, which must be initialized with `begin()` first, and
+//! `serial_write!` writes to any port implementing `SerialWrite`.
+//! # Example
+//! ```ignore
+//! serial_println!("temperature = {}.{} C", whole, tenths);
+//! serial_println!("status = 0x{:02X}, voltage = {:.2}", status, voltage);
+//! serial_write!(buffered_serial, "{} bytes\n", count);
+//! ```
+
+/// Port to which the bytes are printed.
+pub trait SerialWrite {
+    /// Sends a byte.
+    fn write_byte(&mut self, byte: u8);
+
+    /// Sends all the bytes of a slice.
+    fn write_bytes(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.write_byte(*byte);
+        }
+    }
+}
+
+impl<W: SerialWrite + ?Sized> SerialWrite for &mut W {
+    fn write_byte(&mut self, byte: u8) {
+        (**self).write_byte(byte);
+    }
+}
+
+/// Options of a placeholder, as `{:08.2x}`.
+/// # Elements
+/// * `width` - a u8, the smallest number of characters printed.
+/// * `zero` - a bool, true to pad numbers with zeros instead of spaces.
+/// * `radix` - a u8, the base of integers, 10, 16 or 2.
+/// * `upper` - a bool, true for the hexadecimal digits in upper case.
+/// * `precision` - a `Option<u8>`, the number of decimals of floats.
+#[derive(Clone, Copy, Default)]
+pub struct Spec {
+    pub width: u8,
+    pub zero: bool,
+    pub radix: u8,
+    pub upper: bool,
+    pub precision: Option<u8>,
+}
+
+/// Value which can be printed.
+pub trait Print {
+    /// Prints the value with the options of its placeholder.
+    fn print(&self, out: &mut dyn SerialWrite, spec: &Spec);
+}
+
+/// Prints a format string, replacing the placeholders by the arguments in order.
+/// Placeholders without argument print nothing.
+/// # Arguments
+/// * `out` - a `SerialWrite` object, the port.
+/// * `format` - a string slice, the format string.
+/// * `args` - a slice of `Print` objects, the arguments.
+pub fn write_format(out: &mut dyn SerialWrite, format: &str, args: &[&dyn Print]) {
+    let bytes = format.as_bytes();
+    let mut args = args.iter();
+    let mut start = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'{' if bytes.get(i + 1) == Some(&b'{') => {
+                out.write_bytes(&bytes[start..=i]);
+                i += 2;
+                start = i;
+            }
+            b'}' if bytes.get(i + 1) == Some(&b'}') => {
+                out.write_bytes(&bytes[start..=i]);
+                i += 2;
+                start = i;
+            }
+            b'{' => {
+                out.write_bytes(&bytes[start..i]);
+                let end = match bytes[i..].iter().position(|b| *b == b'}') {
+                    Some(length) => i + length,
+                    None => bytes.len(),
+                };
+                let spec = parse_spec(&bytes[i + 1..end]);
+                if let Some(arg) = args.next() {
+                    arg.print(out, &spec);
+                }
+                i = end + 1;
+                start = i;
+            }
+            _ => i += 1,
+        }
+    }
+    if start < bytes.len() {
+        out.write_bytes(&bytes[start..]);
+    }
+}
+
+// Reads the options between the braces of a placeholder, as `:08.2x`.
+fn parse_spec(text: &[u8]) -> Spec {
+    let mut spec = Spec {
+        radix: 10,
+        ..Spec::default()
+    };
+    let mut text = match text.split_first() {
+        Some((b':', rest)) => rest,
+        _ => return spec,
+    };
+    if let Some((b'0', rest)) = text.split_first() {
+        spec.zero = true;
+        text = rest;
+    }
+    let (width, rest) = parse_number(text);
+    spec.width = width;
+    text = rest;
+    if let Some((b'.', rest)) = text.split_first() {
+        let (precision, rest) = parse_number(rest);
+        spec.precision = Some(precision);
+        text = rest;
+    }
+    match text.first() {
+        Some(b'x') => spec.radix = 16,
+        Some(b'X') => {
+            spec.radix = 16;
+            spec.upper = true;
+        }
+        Some(b'b') => spec.radix = 2,
+        _ => {}
+    }
+    spec
+}
+
+// Reads the decimal number at the start of the text, 0 if there is none.
+fn parse_number(text: &[u8]) -> (u8, &[u8]) {
+    let mut value: u8 = 0;
+    let mut length = 0;
+    for byte in text.iter().take_while(|b| b.is_ascii_digit()) {
+        value = value.saturating_mul(10).saturating_add(byte - b'0');
+        length += 1;
+    }
+    (value, &text[length..])
+}
+
+// Prints the characters of a value, padded to the width of the placeholder.
+// Numbers are right aligned and the zeros go after the sign, the text is left aligned.
+fn write_padded(out: &mut dyn SerialWrite, spec: &Spec, negative: bool, body: &[u8], number: bool) {
+    let length = body.len() + negative as usize;
+    let padding = (spec.width as usize).saturating_sub(length);
+    if !number {
+        out.write_bytes(body);
+        pad(out, b' ', padding);
+        return;
+    }
+    if spec.zero {
+        if negative {
+            out.write_byte(b'-');
+        }
+        pad(out, b'0', padding);
+    } else {
+        pad(out, b' ', padding);
+        if negative {
+            out.write_byte(b'-');
+        }
+    }
+    out.write_bytes(body);
+}
+
+fn pad(out: &mut dyn SerialWrite, byte: u8, count: usize) {
+    for _ in 0..count {
+        out.write_byte(byte);
+    }
+}
+
+// Gives the digit of a value from 0 to 35.
+fn digit(value: u8, upper: bool) -> u8 {
+    match value {
+        0..=9 => b'0' + value,
+        _ if upper => b'A' + value - 10,
+        _ => b'a' + value - 10,
+    }
+}
+
+// Integers up to 32 bits are printed with 32 bit divisions, which are much faster than
+// the 64 bit ones on the AVR chips.
+fn write_u32(out: &mut dyn SerialWrite, spec: &Spec, negative: bool, mut value: u32) {
+    let radix = spec.radix as u32;
+    let mut buffer = [0u8; 32];
+    let mut position = buffer.len();
+    loop {
+        position -= 1;
+        buffer[position] = digit((value % radix) as u8, spec.upper);
+        value /= radix;
+        if value == 0 {
+            break;
+        }
+    }
+    write_padded(out, spec, negative, &buffer[position..], true);
+}
+
+fn write_u64(out: &mut dyn SerialWrite, spec: &Spec, negative: bool, mut value: u64) {
+    if value <= u32::MAX as u64 {
+        return write_u32(out, spec, negative, value as u32);
+    }
+    let radix = spec.radix as u64;
+    let mut buffer = [0u8; 64];
+    let mut position = buffer.len();
+    while value != 0 {
+        position -= 1;
+        buffer[position] = digit((value % radix) as u8, spec.upper);
+        value /= radix;
+    }
+    write_padded(out, spec, negative, &buffer[position..], true);
+}
+
+macro_rules! print_unsigned {
+    ($($type:ty => $write:ident),*) => {
+        $(
+            impl Print for $type {
+                fn print(&self, out: &mut dyn SerialWrite, spec: &Spec) {
+                    $write(out, spec, false, *self as _);
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! print_signed {
+    ($($type:ty => $unsigned:ty, $write:ident),*) => {
+        $(
+            impl Print for $type {
+                fn print(&self, out: &mut dyn SerialWrite, spec: &Spec) {
+                    // Hexadecimal and binary numbers are printed as two's complement.
+                    if spec.radix != 10 {
+                        $write(out, spec, false, *self as $unsigned as _);
+                    } else {
+                        $write(out, spec, *self < 0, self.wrapping_abs() as $unsigned as _);
+                    }
+                }
+            }
+        )*
+    };
+}
+
+print_unsigned!(u8 => write_u32, u16 => write_u32, u32 => write_u32, usize => write_u32, u64 => write_u64);
+print_signed!(
+    i8 => u8, write_u32,
+    i16 => u16, write_u32,
+    i32 => u32, write_u32,
+    isize => usize, write_u32,
+    i64 => u64, write_u64
+);
+
+// Floats are printed with 2 decimals unless the placeholder gives a precision, like
+// `Serial.print()` of Arduino.
+fn write_float(out: &mut dyn SerialWrite, spec: &Spec, value: f64) {
+    let precision = spec.precision.unwrap_or(2).min(9);
+    if value.is_nan() {
+        return write_padded(out, spec, false, b"nan", false);
+    }
+    let negative = value < 0.0;
+    let mut magnitude = if negative { -value } else { value };
+    // Rounds at the last decimal printed.
+    let mut rounding = 0.5;
+    for _ in 0..precision {
+        rounding /= 10.0;
+    }
+    magnitude += rounding;
+    if magnitude > u32::MAX as f64 {
+        return write_padded(out, spec, negative, b"inf", true);
+    }
+
+    let mut whole = magnitude as u32;
+    let mut fraction = magnitude - whole as f64;
+    let mut buffer = [0u8; 20];
+    let mut position = 10;
+    loop {
+        position -= 1;
+        buffer[position] = b'0' + (whole % 10) as u8;
+        whole /= 10;
+        if whole == 0 {
+            break;
+        }
+    }
+    let mut end = 10;
+    if precision > 0 {
+        buffer[end] = b'.';
+        end += 1;
+        for _ in 0..precision {
+            fraction *= 10.0;
+            let decimal = (fraction as u8).min(9);
+            fraction -= decimal as f64;
+            buffer[end] = b'0' + decimal;
+            end += 1;
+        }
+    }
+    write_padded(out, spec, negative, &buffer[position..end], true);
+}
+
+impl Print for f32 {
+    fn print(&self, out: &mut dyn SerialWrite, spec: &Spec) {
+        write_float(out, spec, *self as f64);
+    }
+}
+
+impl Print for f64 {
+    fn print(&self, out: &mut dyn SerialWrite, spec: &Spec) {
+        write_float(out, spec, *self);
+    }
+}
+
+impl Print for bool {
+    fn print(&self, out: &mut dyn SerialWrite, spec: &Spec) {
+        let text: &[u8] = if *self { b"true" } else { b"false" };
+        write_padded(out, spec, false, text, false);
+    }
+}
+
+impl Print for char {
+    fn print(&self, out: &mut dyn SerialWrite, spec: &Spec) {
+        write_padded(
+            out,
+            spec,
+            false,
+            self.encode_utf8(&mut [0; 4]).as_bytes(),
+            false,
+        );
+    }
+}
+
+impl Print for str {
+    fn print(&self, out: &mut dyn SerialWrite, spec: &Spec) {
+        write_padded(out, spec, false, self.as_bytes(), false);
+    }
+}
+
+impl<const N: usize> Print for crate::collections::String<N> {
+    fn print(&self, out: &mut dyn SerialWrite, spec: &Spec) {
+        write_padded(out, spec, false, self.as_bytes(), false);
+    }
+}
+
+impl<T: Print + ?Sized> Print for &T {
+    fn print(&self, out: &mut dyn SerialWrite, spec: &Spec) {
+        (**self).print(out, spec);
+    }
+}
+
+/// Gives USART0, the port of the `serial_print!` and `serial_println!` macros.
+#[cfg(feature = "atmega328p")]
+pub fn serial() -> &'static mut crate::atmega328p::com::usart_initialize::Usart {
+    use crate::atmega328p::com::usart_initialize::{Usart, UsartNum};
+    unsafe { Usart::new(UsartNum::Usart0) }
+}
+
+/// Gives USART0, the port of the `serial_print!` and `serial_println!` macros.
+#[cfg(all(feature = "atmega2560p", not(feature = "atmega328p")))]
+pub fn serial() -> crate::atmega2560p::com::usart_initialize::UsartObject {
+    use crate::atmega2560p::com::usart_initialize::{UsartNum, UsartObject};
+    unsafe { UsartObject::new(UsartNum::Usart0) }
+}
+
+/// Prints to USART0, with the same format string as `serial_println!`.
+/// # Example
+/// ```ignore
+/// serial_print!("x = {}, y = {}", x, y);
+/// ```
+#[macro_export]
+macro_rules! serial_print {
+    ($format:expr $(, $arg:expr)* $(,)?) => {
+        $crate::print::write_format(&mut $crate::print::serial(), $format, &[$(&$arg),*])
+    };
+}
+
+/// Prints to USART0 and ends the line with `\r\n`.
+/// # Example
+/// ```ignore
+/// serial_println!("count = {:5}, flags = {:08b}", count, flags);
+/// ```
+#[macro_export]
+macro_rules! serial_println {
+    () => {
+        $crate::print::SerialWrite::write_bytes(&mut $crate::print::serial(), b"\r\n")
+    };
+    ($format:expr $(, $arg:expr)* $(,)?) => {{
+        let mut serial = $crate::print::serial();
+        $crate::print::write_format(&mut serial, $format, &[$(&$arg),*]);
+        $crate::print::SerialWrite::write_bytes(&mut serial, b"\r\n");
+    }};
+}
+
+/// Prints to any port implementing `SerialWrite`.
+/// # Example
+/// ```ignore
+/// serial_write!(soft_serial, "{} {}\r\n", a, b);
+/// ```
+#[macro_export]
+macro_rules! serial_write {
+    ($port:expr, $format:expr $(, $arg:expr)* $(,)?) => {
+        $crate::print::write_format(&mut $port, $format, &[$(&$arg),*])
+    };
+}
@@ -0,0 +1,305 @@
+// RustDuino : A generic HAL implementation for Arduino Boards in Rust
+// Copyright (C) 2021 Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Constant data kept in the flash memory and read with LPM, so that lookup tables,
+//! fonts and strings do not take any of the 2 to 8 kB of RAM.
+//! A `static` is normally copied from the flash to the RAM at start up, as loads read
+//! the data space. The `progmem!` and `progmem_str!` macros place it in the
+//! `.progmem.data` section instead, which stays in the flash, and wrap it in a type
+//! which can only be read through LPM.
+//!
+//! ```ignore
+//! progmem! {
+//!     static SINE: [u8; 4] = [128, 218, 255, 218];
+//! }
+//!
+//! let third = SINE.get(2);
+//! let message = progmem_str!("Hello from the flash");
+//! write!(serial, "{}", message);
+//! ```
+//!
+//! The section is placed by the linker right after the interrupt vectors, so on
+//! ATMEGA2560P it stays within the first 64 kB of the flash reached by LPM.
+
+// Source codes required.
+use core::fmt;
+use core::mem::{size_of, MaybeUninit};
+
+/// Places statics in the flash memory, each wrapped in a `ProgMem`.
+/// The statics are written as usual, with any attribute and visibility.
+#[macro_export]
+macro_rules! progmem {
+    ($(#[$attr:meta])* $vis:vis static $name:ident : $ty:ty = $value:expr ; $($rest:tt)*) => {
+        $(#[$attr])*
+        #[cfg_attr(target_arch = "avr", link_section = ".progmem.data")]
+        $vis static $name: $crate::progmem::ProgMem<$ty> = {
+            const VALUE: $ty = $value;
+            unsafe { $crate::progmem::ProgMem::new(VALUE) }
+        };
+        $crate::progmem! { $($rest)* }
+    };
+    () => {};
+}
+
+/// Places a string literal in the flash memory.
+/// It gives a `&'static ProgMemStr`, which can be printed with `{}`.
+#[macro_export]
+macro_rules! progmem_str {
+    ($text:expr) => {{
+        const TEXT: &str = $text;
+        #[cfg_attr(target_arch = "avr", link_section = ".progmem.data")]
+        static STRING: $crate::progmem::ProgMemStr<{ TEXT.len() }> =
+            unsafe { $crate::progmem::ProgMemStr::new($crate::progmem::str_bytes(TEXT)) };
+        &STRING
+    }};
+}
+
+/// A value stored in the flash memory, which is copied to the RAM when it is read.
+#[repr(transparent)]
+pub struct ProgMem<T> {
+    value: T,
+}
+
+impl<T> ProgMem<T> {
+    /// Wraps a value, which is used by the `progmem!` macro.
+    /// # Safety
+    /// The object must be placed in the `.progmem.data` section, otherwise the
+    /// flash is read at the address of the object in RAM.
+    /// # Arguments
+    /// * `value` - the value to be stored.
+    /// # Returns
+    /// * `a ProgMem object` - the stored value.
+    pub const unsafe fn new(value: T) -> ProgMem<T> {
+        ProgMem { value }
+    }
+}
+
+impl<T: Copy> ProgMem<T> {
+    /// Copies the value from the flash.
+    /// # Returns
+    /// * `the value`.
+    pub fn load(&self) -> T {
+        unsafe { load(&self.value) }
+    }
+
+    /// Gives the address of the value in the flash.
+    /// # Returns
+    /// * `a u16` - the byte address.
+    pub fn address(&self) -> u16 {
+        &self.value as *const T as u16
+    }
+}
+
+impl<T: Copy, const N: usize> ProgMem<[T; N]> {
+    /// Gives the number of elements of the array.
+    /// # Returns
+    /// * `a usize` - the number of elements.
+    pub fn len(&self) -> usize {
+        N
+    }
+
+    /// Checks whether the array has no element.
+    /// # Returns
+    /// * `a boolean` - which is true if the array is empty.
+    pub fn is_empty(&self) -> bool {
+        N == 0
+    }
+
+    /// Copies an element of the array from the flash, without copying the others.
+    /// # Arguments
+    /// * `index` - a usize, the index of the element.
+    /// # Returns
+    /// * `an Option` - the element, or `None` if the index is out of the array.
+    pub fn get(&self, index: usize) -> Option<T> {
+        if index < N {
+            Some(unsafe { load(&self.value[index]) })
+        } else {
+            None
+        }
+    }
+
+    /// Copies consecutive elements of the array from the flash.
+    /// # Arguments
+    /// * `start` - a usize, the index of the first element.
+    /// * `buffer` - a mutable slice, which is filled with the elements.
+    /// # Returns
+    /// * `a usize` - the number of elements copied, less than the length of the
+    ///   buffer at the end of the array.
+    pub fn load_slice(&self, start: usize, buffer: &mut [T]) -> usize {
+        let count = buffer.len().min(N.saturating_sub(start));
+        for (i, element) in buffer[..count].iter_mut().enumerate() {
+            *element = unsafe { load(&self.value[start + i]) };
+        }
+        count
+    }
+
+    /// Gives an iterator copying the elements one by one from the flash.
+    /// # Returns
+    /// * `a ProgMemIter object` - the iterator.
+    pub fn iter(&self) -> ProgMemIter<'_, T, N> {
+        ProgMemIter {
+            array: self,
+            index: 0,
+        }
+    }
+}
+
+/// Iterator over the elements of an array in the flash memory.
+/// # Elements
+/// * `array` - a reference to the `ProgMem` array.
+/// * `index` - a usize, the index of the next element.
+pub struct ProgMemIter<'a, T, const N: usize> {
+    array: &'a ProgMem<[T; N]>,
+    index: usize,
+}
+
+impl<'a, T: Copy, const N: usize> Iterator for ProgMemIter<'a, T, N> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let element = self.array.get(self.index)?;
+        self.index += 1;
+        Some(element)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (N - self.index, Some(N - self.index))
+    }
+}
+
+/// A string stored in the flash memory, given by the `progmem_str!` macro.
+/// # Elements
+/// * `bytes` - a `ProgMem` array, the UTF-8 bytes of the string.
+#[repr(transparent)]
+pub struct ProgMemStr<const N: usize> {
+    bytes: ProgMem<[u8; N]>,
+}
+
+impl<const N: usize> ProgMemStr<N> {
+    /// Wraps the bytes of a string, which is used by the `progmem_str!` macro.
+    /// # Safety
+    /// The bytes must be valid UTF-8 and the object must be placed in the
+    /// `.progmem.data` section.
+    /// # Arguments
+    /// * `bytes` - an array of u8, the bytes of the string.
+    /// # Returns
+    /// * `a ProgMemStr object` - the stored string.
+    pub const unsafe fn new(bytes: [u8; N]) -> ProgMemStr<N> {
+        ProgMemStr {
+            bytes: ProgMem::new(bytes),
+        }
+    }
+
+    /// Gives the length of the string, in bytes.
+    /// # Returns
+    /// * `a usize` - the number of bytes.
+    pub fn len(&self) -> usize {
+        N
+    }
+
+    /// Checks whether the string is empty.
+    /// # Returns
+    /// * `a boolean` - which is true if the string has no byte.
+    pub fn is_empty(&self) -> bool {
+        N == 0
+    }
+
+    /// Gives an iterator copying the bytes of the string one by one from the flash.
+    /// # Returns
+    /// * `a ProgMemIter object` - the iterator.
+    pub fn bytes(&self) -> ProgMemIter<'_, u8, N> {
+        self.bytes.iter()
+    }
+
+    /// Copies the string to a buffer, as much of it as fits without cutting a character.
+    /// # Arguments
+    /// * `buffer` - a mutable slice of u8, which receives the bytes.
+    /// # Returns
+    /// * `a str` - the part of the string copied.
+    pub fn load<'a>(&self, buffer: &'a mut [u8]) -> &'a str {
+        let count = self.bytes.load_slice(0, buffer);
+        valid_prefix(&buffer[..count])
+    }
+}
+
+impl<const N: usize> fmt::Display for ProgMemStr<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // The string is copied by chunks, and the bytes of a character cut at the
+        // end of a chunk are kept for the next one.
+        let mut buffer = [0; 16];
+        let mut kept = 0;
+        let mut start = 0;
+        while start < N {
+            let count = self.bytes.load_slice(start, &mut buffer[kept..]);
+            start += count;
+            let filled = kept + count;
+            let text = valid_prefix(&buffer[..filled]);
+            f.write_str(text)?;
+            let written = text.len();
+            buffer.copy_within(written..filled, 0);
+            kept = filled - written;
+        }
+        Ok(())
+    }
+}
+
+/// Copies the bytes of a string to an array, which is used by the `progmem_str!` macro.
+/// # Arguments
+/// * `text` - a str, whose length is `N`.
+/// # Returns
+/// * `an array of u8` - the bytes of the string.
+pub const fn str_bytes<const N: usize>(text: &str) -> [u8; N] {
+    let bytes = text.as_bytes();
+    let mut array = [0; N];
+    let mut i = 0;
+    while i < N {
+        array[i] = bytes[i];
+        i += 1;
+    }
+    array
+}
+
+// Gives the longest part of the bytes which is valid UTF-8.
+fn valid_prefix(bytes: &[u8]) -> &str {
+    match core::str::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(error) => unsafe { core::str::from_utf8_unchecked(&bytes[..error.valid_up_to()]) },
+    }
+}
+
+// Copies a value from the flash byte by byte.
+unsafe fn load<T: Copy>(source: *const T) -> T {
+    let mut value = MaybeUninit::<T>::uninit();
+    let destination = value.as_mut_ptr() as *mut u8;
+    let address = source as u16;
+    for i in 0..size_of::<T>() {
+        *destination.add(i) = read_byte(address.wrapping_add(i as u16));
+    }
+    value.assume_init()
+}
+
+// Reads a byte of the flash with LPM.
+fn read_byte(address: u16) -> u8 {
+    let value: u8;
+    unsafe {
+        llvm_asm!("lpm $0, Z"
+                 : "=r" (value)
+                 : "z" (address)
+                 :
+                 : "volatile");
+    }
+    value
+}
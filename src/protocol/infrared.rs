@@ -0,0 +1,353 @@
+// RustDuino : A generic HAL implementation for Arduino Boards in Rust
+// Copyright (C) 2021 Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Decoding of the infrared remote control codes of the NEC, Sony (SIRC) and Philips RC5
+//! protocols from the lengths of the marks and spaces of a frame, as recorded by a
+//! receiver such as the TSOP38238, which gives low while it sees the carrier (a mark)
+//! and high otherwise (a space).
+//! The receiver and the sender, which need a pin change interrupt and a timer, are in the
+//! `infrared` module in the `com` of the chip.
+//! See `<https://www.sbprojects.net/knowledge/ir/index.php>`.
+
+// Timings of the protocols in microseconds.
+pub(crate) const NEC_LEADER_MARK: u16 = 9000;
+pub(crate) const NEC_LEADER_SPACE: u16 = 4500;
+pub(crate) const NEC_REPEAT_SPACE: u16 = 2250;
+pub(crate) const NEC_BIT_MARK: u16 = 562;
+pub(crate) const NEC_ZERO_SPACE: u16 = 562;
+pub(crate) const NEC_ONE_SPACE: u16 = 1687;
+pub(crate) const SONY_LEADER_MARK: u16 = 2400;
+pub(crate) const SONY_SPACE: u16 = 600;
+pub(crate) const SONY_ZERO_MARK: u16 = 600;
+pub(crate) const SONY_ONE_MARK: u16 = 1200;
+pub(crate) const RC5_HALF_BIT: u16 = 889;
+
+/// Protocols of the codes.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum IrProtocol {
+    Nec,
+    Sony,
+    Rc5,
+}
+
+/// Code received or to be sent.
+/// # Elements
+/// * `protocol` - a `IrProtocol` object, the protocol of the code.
+/// * `address` - a u16, the device address, 8 or 16 bits for NEC, 5, 8 or 13 bits for Sony
+///   and 5 bits for RC5.
+/// * `command` - a u8, the key, 8 bits for NEC, 7 bits for Sony and 6 bits for RC5.
+/// * `repeat` - a bool, true for the repeat frame sent by NEC remotes while a key is held,
+///   which gives the last code received.
+/// * `toggle` - a bool, the RC5 toggle bit, which changes at each new press of a key.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct IrCode {
+    pub protocol: IrProtocol,
+    pub address: u16,
+    pub command: u8,
+    pub repeat: bool,
+    pub toggle: bool,
+}
+
+/// Decodes the marks and spaces of a frame, trying each protocol.
+/// # Arguments
+/// * `durations` - a slice of u16, the lengths in microseconds, starting with a mark.
+/// * `last` - a `Option<IrCode>`, the last NEC code, given again for a repeat frame.
+/// # Returns
+/// * `a Option<IrCode>` - the code, None if the frame was not understood.
+pub fn decode(durations: &[u16], last: Option<IrCode>) -> Option<IrCode> {
+    decode_nec(durations, last)
+        .or_else(|| decode_sony(durations))
+        .or_else(|| decode_rc5(durations))
+}
+
+/// Checks whether a measured length is within 30% of the expected one.
+fn matches(measured: u16, expected: u16) -> bool {
+    let tolerance = expected as u32 * 3 / 10;
+    (measured as u32 + tolerance) >= expected as u32
+        && measured as u32 <= expected as u32 + tolerance
+}
+
+/// Decodes a NEC frame: a leader, 32 bits sent least significant first as the lengths of
+/// the spaces, and a final mark.
+fn decode_nec(d: &[u16], last: Option<IrCode>) -> Option<IrCode> {
+    if d.len() < 3 || !matches(d[0], NEC_LEADER_MARK) {
+        return None;
+    }
+    if d.len() < 67 {
+        if matches(d[1], NEC_REPEAT_SPACE) {
+            return last.map(|code| IrCode {
+                repeat: true,
+                ..code
+            });
+        }
+        return None;
+    }
+    if !matches(d[1], NEC_LEADER_SPACE) {
+        return None;
+    }
+    let mut data: u32 = 0;
+    for i in 0..32 {
+        if !matches(d[2 + 2 * i], NEC_BIT_MARK) {
+            return None;
+        }
+        let space = d[3 + 2 * i];
+        if matches(space, NEC_ONE_SPACE) {
+            data |= 1 << i;
+        } else if !matches(space, NEC_ZERO_SPACE) {
+            return None;
+        }
+    }
+    let bytes = data.to_le_bytes();
+    if bytes[2] != !bytes[3] {
+        return None;
+    }
+    // The extended NEC uses the inverted address byte as a second address byte.
+    let address = if bytes[0] == !bytes[1] {
+        bytes[0] as u16
+    } else {
+        u16::from_le_bytes([bytes[0], bytes[1]])
+    };
+    Some(IrCode {
+        protocol: IrProtocol::Nec,
+        address,
+        command: bytes[2],
+        repeat: false,
+        toggle: false,
+    })
+}
+
+/// Decodes a Sony frame: a leader and 12, 15 or 20 bits sent least significant first
+/// as the lengths of the marks, 7 bits of command and then the address.
+fn decode_sony(d: &[u16]) -> Option<IrCode> {
+    if d.is_empty() {
+        return None;
+    }
+    let bits = (d.len() - 1) / 2;
+    if !matches(d[0], SONY_LEADER_MARK) || !(bits == 12 || bits == 15 || bits == 20) {
+        return None;
+    }
+    let mut data: u32 = 0;
+    for i in 0..bits {
+        if !matches(d[1 + 2 * i], SONY_SPACE) {
+            return None;
+        }
+        let mark = d[2 + 2 * i];
+        if matches(mark, SONY_ONE_MARK) {
+            data |= 1 << i;
+        } else if !matches(mark, SONY_ZERO_MARK) {
+            return None;
+        }
+    }
+    Some(IrCode {
+        protocol: IrProtocol::Sony,
+        address: (data >> 7) as u16,
+        command: (data & 0x7F) as u8,
+        repeat: false,
+        toggle: false,
+    })
+}
+
+/// Decodes a RC5 frame: 14 bits sent most significant first in Manchester code, where
+/// a 1 is a space then a mark and a 0 a mark then a space, each half bit 889 us long.
+fn decode_rc5(d: &[u16]) -> Option<IrCode> {
+    // Half bits, true for a mark, starting with the space of the first start bit.
+    let mut halves = [false; 28];
+    let mut n = 1;
+    for (i, &duration) in d.iter().enumerate() {
+        let length = if matches(duration, RC5_HALF_BIT) {
+            1
+        } else if matches(duration, 2 * RC5_HALF_BIT) {
+            2
+        } else {
+            return None;
+        };
+        for _ in 0..length {
+            if n >= halves.len() {
+                return None;
+            }
+            // The durations start with a mark and alternate.
+            halves[n] = i % 2 == 0;
+            n += 1;
+        }
+    }
+    // The final space of a 0 is not recorded.
+    if n < halves.len() - 1 {
+        return None;
+    }
+
+    let mut data: u16 = 0;
+    for bit in 0..14 {
+        let (first, second) = (halves[2 * bit], halves[2 * bit + 1]);
+        if first == second {
+            return None;
+        }
+        data = data << 1 | second as u16;
+    }
+    // The second start bit is the inverted seventh bit of the command in RC5X.
+    if data >> 13 != 1 {
+        return None;
+    }
+    let command = (data & 0x3F) as u8 | if data & 0x1000 == 0 { 0x40 } else { 0 };
+    Some(IrCode {
+        protocol: IrProtocol::Rc5,
+        address: (data >> 6) & 0x1F,
+        command,
+        repeat: false,
+        toggle: data & 0x0800 != 0,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Marks and spaces of a NEC frame, each stretched by `scale` percent.
+    fn nec(address: [u8; 2], command: u8, scale: u32, d: &mut [u16; 67]) {
+        let stretch = |us: u16| (us as u32 * scale / 100) as u16;
+        let data = u32::from_le_bytes([address[0], address[1], command, !command]);
+        d[0] = stretch(NEC_LEADER_MARK);
+        d[1] = stretch(NEC_LEADER_SPACE);
+        for i in 0..32 {
+            d[2 + 2 * i] = stretch(NEC_BIT_MARK);
+            d[3 + 2 * i] = if data & (1 << i) != 0 {
+                stretch(NEC_ONE_SPACE)
+            } else {
+                stretch(NEC_ZERO_SPACE)
+            };
+        }
+        d[66] = stretch(NEC_BIT_MARK);
+    }
+
+    #[test]
+    fn nec_frames() {
+        let mut d = [0; 67];
+        nec([0x04, !0x04], 0x08, 100, &mut d);
+        let code = decode(&d, None).unwrap();
+        assert_eq!(
+            code,
+            IrCode {
+                protocol: IrProtocol::Nec,
+                address: 0x04,
+                command: 0x08,
+                repeat: false,
+                toggle: false,
+            }
+        );
+
+        // Extended address, and timings off by 20%.
+        nec([0x34, 0x12], 0x5A, 120, &mut d);
+        assert_eq!(decode(&d, None).map(|code| code.address), Some(0x1234));
+        nec([0x34, 0x12], 0x5A, 80, &mut d);
+        assert_eq!(decode(&d, None).map(|code| code.command), Some(0x5A));
+        nec([0x34, 0x12], 0x5A, 140, &mut d);
+        assert_eq!(decode(&d, None), None);
+
+        // The inverted command does not match.
+        nec([0x04, !0x04], 0x08, 100, &mut d);
+        d[3 + 2 * 27] = NEC_ONE_SPACE;
+        assert_eq!(decode(&d, None), None);
+
+        let repeat = [NEC_LEADER_MARK, NEC_REPEAT_SPACE, NEC_BIT_MARK];
+        assert_eq!(decode(&repeat, None), None);
+        assert_eq!(
+            decode(&repeat, Some(code)),
+            Some(IrCode {
+                repeat: true,
+                ..code
+            })
+        );
+    }
+
+    #[test]
+    fn sony_frames() {
+        // 12 bits: command 0x15 (power) and address 1, least significant bit first.
+        let data: u32 = 1 << 7 | 0x15;
+        let mut d = [0; 25];
+        d[0] = SONY_LEADER_MARK;
+        for i in 0..12 {
+            d[1 + 2 * i] = SONY_SPACE;
+            d[2 + 2 * i] = if data & (1 << i) != 0 {
+                SONY_ONE_MARK
+            } else {
+                SONY_ZERO_MARK
+            };
+        }
+        assert_eq!(
+            decode(&d, None),
+            Some(IrCode {
+                protocol: IrProtocol::Sony,
+                address: 1,
+                command: 0x15,
+                repeat: false,
+                toggle: false,
+            })
+        );
+        // 11 bits are not a Sony frame.
+        assert_eq!(decode(&d[..23], None), None);
+    }
+
+    // Marks and spaces of a RC5 frame, as the receiver records them: from the mark of
+    // the first start bit, with the halves of the same level joined and no final space.
+    fn rc5(data: u16, d: &mut [u16; 28]) -> usize {
+        let mut count = 0;
+        let mut level = false;
+        for bit in (0..14).rev() {
+            let one = data & (1 << bit) != 0;
+            for &mark in [!one, one].iter() {
+                if count > 0 && mark == level {
+                    d[count - 1] += RC5_HALF_BIT;
+                } else if mark || count > 0 {
+                    d[count] = RC5_HALF_BIT;
+                    count += 1;
+                }
+                level = mark;
+            }
+        }
+        if !level {
+            count -= 1;
+        }
+        count
+    }
+
+    #[test]
+    fn rc5_frames() {
+        let mut d = [0; 28];
+        // Start bits, toggle set, address 5 and command 0x35.
+        let count = rc5(0b11 << 12 | 1 << 11 | 5 << 6 | 0x35, &mut d);
+        assert_eq!(
+            decode(&d[..count], None),
+            Some(IrCode {
+                protocol: IrProtocol::Rc5,
+                address: 5,
+                command: 0x35,
+                repeat: false,
+                toggle: true,
+            })
+        );
+
+        // RC5X: the second start bit is 0 for the commands above 63.
+        let count = rc5(0b10 << 12 | 0x1F << 6 | 0x01, &mut d);
+        let code = decode(&d[..count], None).unwrap();
+        assert_eq!(
+            (code.address, code.command, code.toggle),
+            (0x1F, 0x41, false)
+        );
+
+        // A half bit of the wrong length.
+        d[3] = 1200;
+        assert_eq!(decode(&d[..count], None), None);
+    }
+}
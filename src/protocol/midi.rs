@@ -0,0 +1,543 @@
+// RustDuino : A generic HAL implementation for Arduino Boards in Rust
+// Copyright (C) 2021 Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! MIDI messages and a MIDI port, at 31250 baud with 8 data bits, no parity and one stop bit.
+//! A message is a status byte, whose high bit is set and whose low nibble is the channel,
+//! followed by one or two data bytes below 0x80. With running status the status byte
+//! is left out while it does not change, which the parser accepts and `Midi` can send.
+//! Real time messages (clock, start, stop...) are single bytes which can come in between
+//! the bytes of any other message. System exclusive messages are skipped.
+//! `Midi` works on any `UsartOps` serial port, by default the `BufferedSerial` of the chip,
+//! which is created and started by the `midi` module in the `com` of the chip.
+//! See the MIDI 1.0 Detailed Specification.
+
+// Source codes required.
+use crate::com::usart_interrupt::BufferedSerial;
+use crate::common::UsartOps;
+
+/// Baud rate of MIDI.
+pub const MIDI_BAUD: i64 = 31250;
+
+/// Message of MIDI, the channels are from 0 to 15 and the data values from 0 to 127.
+/// The value of `PitchBend` is from -8192 to 8191, with 0 as the center.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MidiMessage {
+    NoteOff {
+        channel: u8,
+        note: u8,
+        velocity: u8,
+    },
+    NoteOn {
+        channel: u8,
+        note: u8,
+        velocity: u8,
+    },
+    PolyPressure {
+        channel: u8,
+        note: u8,
+        pressure: u8,
+    },
+    ControlChange {
+        channel: u8,
+        controller: u8,
+        value: u8,
+    },
+    ProgramChange {
+        channel: u8,
+        program: u8,
+    },
+    ChannelPressure {
+        channel: u8,
+        pressure: u8,
+    },
+    PitchBend {
+        channel: u8,
+        value: i16,
+    },
+    Clock,
+    Start,
+    Continue,
+    Stop,
+    ActiveSensing,
+    Reset,
+}
+
+impl MidiMessage {
+    /// Gives the bytes of the message.
+    /// # Arguments
+    /// * `buffer` - a mutable array of 3 u8, which will be filled with the bytes.
+    /// # Returns
+    /// * `a usize` - the number of bytes of the message, the first one being the status.
+    pub fn encode(&self, buffer: &mut [u8; 3]) -> usize {
+        let (status, channel, first, second) = match *self {
+            MidiMessage::NoteOff {
+                channel,
+                note,
+                velocity,
+            } => (0x80, channel, note, Some(velocity)),
+            MidiMessage::NoteOn {
+                channel,
+                note,
+                velocity,
+            } => (0x90, channel, note, Some(velocity)),
+            MidiMessage::PolyPressure {
+                channel,
+                note,
+                pressure,
+            } => (0xA0, channel, note, Some(pressure)),
+            MidiMessage::ControlChange {
+                channel,
+                controller,
+                value,
+            } => (0xB0, channel, controller, Some(value)),
+            MidiMessage::ProgramChange { channel, program } => (0xC0, channel, program, None),
+            MidiMessage::ChannelPressure { channel, pressure } => (0xD0, channel, pressure, None),
+            MidiMessage::PitchBend { channel, value } => {
+                let value = (value.clamp(-8192, 8191) + 8192) as u16;
+                (0xE0, channel, value as u8, Some((value >> 7) as u8))
+            }
+            MidiMessage::Clock => return Self::single(buffer, 0xF8),
+            MidiMessage::Start => return Self::single(buffer, 0xFA),
+            MidiMessage::Continue => return Self::single(buffer, 0xFB),
+            MidiMessage::Stop => return Self::single(buffer, 0xFC),
+            MidiMessage::ActiveSensing => return Self::single(buffer, 0xFE),
+            MidiMessage::Reset => return Self::single(buffer, 0xFF),
+        };
+        buffer[0] = status | (channel & 0x0F);
+        buffer[1] = first & 0x7F;
+        match second {
+            Some(second) => {
+                buffer[2] = second & 0x7F;
+                3
+            }
+            None => 2,
+        }
+    }
+
+    // Gives the single byte of a real time message.
+    fn single(buffer: &mut [u8; 3], status: u8) -> usize {
+        buffer[0] = status;
+        1
+    }
+
+    /// Checks whether this is a real time message, which does not change the running status.
+    /// # Returns
+    /// * `a boolean` - which is true for the single byte messages.
+    pub fn is_real_time(&self) -> bool {
+        matches!(
+            self,
+            MidiMessage::Clock
+                | MidiMessage::Start
+                | MidiMessage::Continue
+                | MidiMessage::Stop
+                | MidiMessage::ActiveSensing
+                | MidiMessage::Reset
+        )
+    }
+}
+
+/// Decoder of the MIDI messages from the received bytes, one byte at a time.
+/// # Elements
+/// * `status` - a u8, the running status, or 0 if there is none.
+/// * `data` - a array of 2 u8, the data bytes received for the current message.
+/// * `count` - a usize, the number of data bytes received.
+/// * `sysex` - a boolean, which is true while a system exclusive message is skipped.
+pub struct MidiParser {
+    status: u8,
+    data: [u8; 2],
+    count: usize,
+    sysex: bool,
+}
+
+impl MidiParser {
+    /// Creates the parser, which waits for a status byte.
+    /// # Returns
+    /// * `a MidiParser object` - which will be used to decode the bytes.
+    pub fn new() -> MidiParser {
+        MidiParser {
+            status: 0,
+            data: [0; 2],
+            count: 0,
+            sysex: false,
+        }
+    }
+
+    /// Adds a received byte.
+    /// A Note On with a velocity of 0 is given as a Note Off, as both mean the same.
+    /// # Arguments
+    /// * `byte` - a u8, the byte received.
+    /// # Returns
+    /// * `a Option<MidiMessage>` - the message completed by this byte, if any.
+    pub fn feed(&mut self, byte: u8) -> Option<MidiMessage> {
+        match byte {
+            0xF8 => Some(MidiMessage::Clock),
+            0xFA => Some(MidiMessage::Start),
+            0xFB => Some(MidiMessage::Continue),
+            0xFC => Some(MidiMessage::Stop),
+            0xFE => Some(MidiMessage::ActiveSensing),
+            0xFF => {
+                self.status = 0;
+                self.count = 0;
+                self.sysex = false;
+                Some(MidiMessage::Reset)
+            }
+            // Other real time bytes are undefined and ignored.
+            0xF9 | 0xFD => None,
+            // System common messages end the running status.
+            0xF0..=0xF7 => {
+                self.status = 0;
+                self.count = 0;
+                self.sysex = byte == 0xF0;
+                None
+            }
+            0x80..=0xEF => {
+                self.status = byte;
+                self.count = 0;
+                self.sysex = false;
+                None
+            }
+            _ => {
+                if self.sysex || self.status == 0 {
+                    return None;
+                }
+                self.data[self.count] = byte;
+                self.count += 1;
+                let length = match self.status & 0xF0 {
+                    0xC0 | 0xD0 => 1,
+                    _ => 2,
+                };
+                if self.count < length {
+                    return None;
+                }
+                self.count = 0;
+                Some(self.message())
+            }
+        }
+    }
+
+    // Gives the message of the running status and the data bytes received.
+    fn message(&self) -> MidiMessage {
+        let channel = self.status & 0x0F;
+        let [first, second] = self.data;
+        match self.status & 0xF0 {
+            0x80 => MidiMessage::NoteOff {
+                channel,
+                note: first,
+                velocity: second,
+            },
+            0x90 if second == 0 => MidiMessage::NoteOff {
+                channel,
+                note: first,
+                velocity: 0,
+            },
+            0x90 => MidiMessage::NoteOn {
+                channel,
+                note: first,
+                velocity: second,
+            },
+            0xA0 => MidiMessage::PolyPressure {
+                channel,
+                note: first,
+                pressure: second,
+            },
+            0xB0 => MidiMessage::ControlChange {
+                channel,
+                controller: first,
+                value: second,
+            },
+            0xC0 => MidiMessage::ProgramChange {
+                channel,
+                program: first,
+            },
+            0xD0 => MidiMessage::ChannelPressure {
+                channel,
+                pressure: first,
+            },
+            _ => MidiMessage::PitchBend {
+                channel,
+                value: ((second as i16) << 7 | first as i16) - 8192,
+            },
+        }
+    }
+}
+
+impl Default for MidiParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// MIDI port on a serial port, sending and receiving messages.
+/// # Elements
+/// * `serial` - a `UsartOps` object, the serial port connected to the MIDI in and out circuits,
+///   by default a `BufferedSerial`.
+/// * `parser` - a `MidiParser` object, decoding the received bytes.
+/// * `running_status` - a boolean, which is true if repeated status bytes are left out.
+/// * `last_status` - a u8, the last status byte sent, or 0 if the next one must be sent.
+pub struct Midi<U = BufferedSerial> {
+    pub(crate) serial: U,
+    parser: MidiParser,
+    running_status: bool,
+    pub(crate) last_status: u8,
+}
+
+impl<U: UsartOps> Midi<U> {
+    /// Creates the MIDI port on a serial port which was started at `MIDI_BAUD`,
+    /// sending every status byte.
+    /// # Arguments
+    /// * `serial` - a `UsartOps` object, the serial port connected to the MIDI in and out circuits.
+    /// # Returns
+    /// * `a Midi object` - which will be used to send and receive messages.
+    pub fn with_serial(serial: U) -> Midi<U> {
+        Midi {
+            serial,
+            parser: MidiParser::new(),
+            running_status: false,
+            last_status: 0,
+        }
+    }
+
+    /// Selects whether the status byte is left out when it is the same as the previous one.
+    /// # Arguments
+    /// * `enable` - a boolean, true to send with running status.
+    pub fn set_running_status(&mut self, enable: bool) {
+        self.running_status = enable;
+        self.last_status = 0;
+    }
+
+    /// Sends a message.
+    /// # Arguments
+    /// * `message` - a `MidiMessage` object, the message to be sent.
+    pub fn send(&mut self, message: MidiMessage) {
+        let mut buffer = [0; 3];
+        let length = message.encode(&mut buffer);
+        if message.is_real_time() {
+            self.serial.write_byte(buffer[0]);
+        } else if self.running_status && buffer[0] == self.last_status {
+            self.serial.write_bytes(&buffer[1..length]);
+        } else {
+            self.last_status = buffer[0];
+            self.serial.write_bytes(&buffer[..length]);
+        }
+    }
+
+    /// Sends a Note On message.
+    /// # Arguments
+    /// * `channel` - a u8, the channel from 0 to 15.
+    /// * `note` - a u8, the note from 0 to 127, where 60 is the middle C.
+    /// * `velocity` - a u8, the velocity from 0 to 127.
+    pub fn note_on(&mut self, channel: u8, note: u8, velocity: u8) {
+        self.send(MidiMessage::NoteOn {
+            channel,
+            note,
+            velocity,
+        });
+    }
+
+    /// Sends a Note Off message.
+    /// # Arguments
+    /// * `channel` - a u8, the channel from 0 to 15.
+    /// * `note` - a u8, the note from 0 to 127.
+    /// * `velocity` - a u8, the release velocity from 0 to 127.
+    pub fn note_off(&mut self, channel: u8, note: u8, velocity: u8) {
+        self.send(MidiMessage::NoteOff {
+            channel,
+            note,
+            velocity,
+        });
+    }
+
+    /// Sends a Control Change message.
+    /// # Arguments
+    /// * `channel` - a u8, the channel from 0 to 15.
+    /// * `controller` - a u8, the controller number from 0 to 127.
+    /// * `value` - a u8, the value from 0 to 127.
+    pub fn control_change(&mut self, channel: u8, controller: u8, value: u8) {
+        self.send(MidiMessage::ControlChange {
+            channel,
+            controller,
+            value,
+        });
+    }
+
+    /// Sends a Program Change message.
+    /// # Arguments
+    /// * `channel` - a u8, the channel from 0 to 15.
+    /// * `program` - a u8, the program number from 0 to 127.
+    pub fn program_change(&mut self, channel: u8, program: u8) {
+        self.send(MidiMessage::ProgramChange { channel, program });
+    }
+
+    /// Sends a Pitch Bend message.
+    /// # Arguments
+    /// * `channel` - a u8, the channel from 0 to 15.
+    /// * `value` - a i16, the bend from -8192 to 8191, where 0 is no bend.
+    pub fn pitch_bend(&mut self, channel: u8, value: i16) {
+        self.send(MidiMessage::PitchBend { channel, value });
+    }
+
+    /// Decodes the received bytes until a message is complete.
+    /// # Returns
+    /// * `a Option<MidiMessage>` - the message received, or None if no message is complete yet.
+    pub fn read(&mut self) -> Option<MidiMessage> {
+        while let Some(byte) = self.serial.read_byte() {
+            if let Some(message) = self.parser.feed(byte) {
+                return Some(message);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(bytes: &[u8], messages: &mut [Option<MidiMessage>]) -> usize {
+        let mut parser = MidiParser::new();
+        let mut count = 0;
+        for byte in bytes {
+            if let Some(message) = parser.feed(*byte) {
+                messages[count] = Some(message);
+                count += 1;
+            }
+        }
+        count
+    }
+
+    #[test]
+    fn running_status() {
+        let mut messages = [None; 4];
+        // A Note On, two more with running status, the last one with velocity 0.
+        let count = parse(&[0x91, 60, 100, 64, 90, 60, 0], &mut messages);
+        assert_eq!(count, 3);
+        assert_eq!(
+            messages[..3],
+            [
+                Some(MidiMessage::NoteOn {
+                    channel: 1,
+                    note: 60,
+                    velocity: 100
+                }),
+                Some(MidiMessage::NoteOn {
+                    channel: 1,
+                    note: 64,
+                    velocity: 90
+                }),
+                Some(MidiMessage::NoteOff {
+                    channel: 1,
+                    note: 60,
+                    velocity: 0
+                }),
+            ]
+        );
+
+        // Data bytes without a status are ignored.
+        let count = parse(&[60, 100, 0xC2, 5, 7], &mut messages);
+        assert_eq!(count, 2);
+        assert_eq!(
+            messages[..2],
+            [
+                Some(MidiMessage::ProgramChange {
+                    channel: 2,
+                    program: 5
+                }),
+                Some(MidiMessage::ProgramChange {
+                    channel: 2,
+                    program: 7
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn real_time_inside_message() {
+        let mut messages = [None; 4];
+        let count = parse(&[0xE0, 0xF8, 0x00, 0xFA, 0x40], &mut messages);
+        assert_eq!(count, 3);
+        assert_eq!(
+            messages[..3],
+            [
+                Some(MidiMessage::Clock),
+                Some(MidiMessage::Start),
+                Some(MidiMessage::PitchBend {
+                    channel: 0,
+                    value: 0
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn sysex_skipped() {
+        let mut messages = [None; 4];
+        // The SysEx ends the running status, and its data bytes give no message.
+        let count = parse(
+            &[
+                0xB0, 7, 100, 0xF0, 0x7E, 0x00, 0x09, 0xF8, 0x01, 0xF7, 7, 100, 0xB0, 7, 90,
+            ],
+            &mut messages,
+        );
+        assert_eq!(count, 3);
+        assert_eq!(
+            messages[..3],
+            [
+                Some(MidiMessage::ControlChange {
+                    channel: 0,
+                    controller: 7,
+                    value: 100
+                }),
+                Some(MidiMessage::Clock),
+                Some(MidiMessage::ControlChange {
+                    channel: 0,
+                    controller: 7,
+                    value: 90
+                }),
+            ]
+        );
+    }
+
+    #[cfg(feature = "mock")]
+    #[test]
+    fn send_with_running_status() {
+        use crate::mock::MockUsart;
+
+        let mut serial = MockUsart::new();
+        let mut midi = Midi::with_serial(&mut serial);
+        midi.set_running_status(true);
+        midi.note_on(0, 60, 100);
+        midi.send(MidiMessage::Clock);
+        midi.note_on(0, 64, 100);
+        midi.pitch_bend(0, 8191);
+        drop(midi);
+        assert_eq!(
+            serial.written(),
+            &[0x90, 60, 100, 0xF8, 64, 100, 0xE0, 0x7F, 0x7F]
+        );
+
+        serial.feed(&[0x80, 60, 0x40]).unwrap();
+        let mut midi = Midi::with_serial(&mut serial);
+        assert_eq!(
+            midi.read(),
+            Some(MidiMessage::NoteOff {
+                channel: 0,
+                note: 60,
+                velocity: 64
+            })
+        );
+        assert_eq!(midi.read(), None);
+    }
+}
@@ -0,0 +1,33 @@
+// RustDuino : A generic HAL implementation for Arduino Boards in Rust
+// Copyright (C) 2021 Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Encoding and decoding of the serial protocols, which do not depend on the chip.
+//! The chips only give the pins, interrupts and ports the protocols run on, in their `com`
+//! module, so the protocols are written and tested once.
+//! * `infrared` - the decoding of the NEC, Sony and RC5 infrared remote control codes.
+//! * `midi` - the messages of MIDI and a MIDI port on any `UsartOps` serial port.
+//! * `modbus` - a Modbus RTU master on any `HalfDuplex` transport.
+//! * `rs485` - the half-duplex transport of a serial port and a direction pin.
+//! * `wiegand` - the frames of Wiegand card readers and their parity checks.
+
+pub mod infrared;
+#[cfg(any(feature = "atmega2560p", feature = "atmega328p", feature = "mock"))]
+pub mod midi;
+#[cfg(any(feature = "atmega2560p", feature = "atmega328p", feature = "mock"))]
+pub mod modbus;
+#[cfg(any(feature = "atmega2560p", feature = "atmega328p", feature = "mock"))]
+pub mod rs485;
+pub mod wiegand;
@@ -0,0 +1,454 @@
+// RustDuino : A generic HAL implementation for Arduino Boards in Rust
+// Copyright (C) 2021 Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Modbus RTU master over any half-duplex transport, such as `Rs485`.
+//! A frame is the address of the slave, the function code, the data and a CRC16 sent
+//! low byte first. Frames are separated by a silent interval of 3.5 characters, which
+//! is fixed to 1750 microseconds above 19200 baud, and a frame ends when the bus has
+//! been silent for that interval.
+//! The timing uses `crate::time::micros`, so `crate::time::init` must have been called.
+//! See the Modbus over serial line specification, sections 2.5.1.1 and 6.2.2.
+
+// Source codes required.
+use crate::error::Error;
+use crate::protocol::rs485::Rs485;
+use crate::time::micros;
+use crate::util::crc::crc16_modbus;
+
+/// Largest frame of Modbus RTU, in bytes.
+const MAX_FRAME: usize = 256;
+
+/// Largest number of registers read in one request, as given by the specification.
+const MAX_READ: usize = 125;
+
+/// Largest number of registers written in one request, as given by the specification.
+const MAX_WRITE: usize = 123;
+
+/// Default time given to a slave to start its response, in microseconds.
+const RESPONSE_TIMEOUT: u32 = 100_000;
+
+/// Half-duplex transport of the frames, on which one device talks at a time.
+pub trait HalfDuplex {
+    /// Sends the bytes, returning once the last one has left and the bus is released.
+    fn send(&mut self, data: &[u8]);
+
+    /// Reads the oldest received byte without waiting.
+    /// # Returns
+    /// * `a Option<u8>` - the byte, or None if no byte was received.
+    fn read_byte(&mut self) -> Option<u8>;
+
+    /// Discards all the received bytes which have not been read.
+    fn clear(&mut self);
+
+    /// Gives the time taken by one character on the bus.
+    /// # Returns
+    /// * `a u32` - the time in microseconds.
+    fn character_time_us(&self) -> u32;
+}
+
+/// Errors of a Modbus transaction.
+/// * `Timeout` - the slave did not respond in time.
+/// * `Crc` - the CRC of the response did not match its bytes.
+/// * `Exception` - the slave responded with the given exception code.
+/// * `InvalidResponse` - the response was not the one expected for the request.
+/// * `InvalidRequest` - the request asked for more registers than a frame can hold.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ModbusError {
+    Timeout,
+    Crc,
+    Exception(u8),
+    InvalidResponse,
+    InvalidRequest,
+}
+
+impl From<ModbusError> for Error {
+    fn from(error: ModbusError) -> Error {
+        match error {
+            ModbusError::Timeout => Error::Timeout,
+            ModbusError::Crc => Error::CrcMismatch,
+            ModbusError::Exception(_) => Error::Device,
+            ModbusError::InvalidResponse => Error::InvalidData,
+            ModbusError::InvalidRequest => Error::InvalidParam,
+        }
+    }
+}
+
+/// Modbus RTU master sending requests to the slaves on a half-duplex bus.
+/// # Elements
+/// * `bus` - a `HalfDuplex` object, the transport of the frames, by default a `Rs485`.
+/// * `timeout` - a u32, the time in microseconds a slave is given to start its response.
+/// * `last` - a u32, the time in microseconds at which the bus was last active.
+pub struct ModbusMaster<B = Rs485> {
+    bus: B,
+    timeout: u32,
+    last: u32,
+}
+
+impl<B: HalfDuplex> ModbusMaster<B> {
+    /// Creates the master on a transport whose baud rate is already set.
+    /// # Arguments
+    /// * `bus` - a `HalfDuplex` object, the transport of the frames.
+    /// # Returns
+    /// * `a ModbusMaster object` - which will be used to send the requests.
+    pub fn new(bus: B) -> ModbusMaster<B> {
+        ModbusMaster {
+            bus,
+            timeout: RESPONSE_TIMEOUT,
+            last: micros(),
+        }
+    }
+
+    /// Sets the time a slave is given to start its response.
+    /// # Arguments
+    /// * `timeout` - a u32, the time in microseconds.
+    pub fn set_timeout(&mut self, timeout: u32) {
+        self.timeout = timeout;
+    }
+
+    /// Gives back the transport.
+    /// # Returns
+    /// * `a HalfDuplex object` - the transport of the frames.
+    pub fn release(self) -> B {
+        self.bus
+    }
+
+    /// Gives the silent interval of 3.5 characters separating the frames.
+    /// # Returns
+    /// * `a u32` - the interval in microseconds.
+    pub fn frame_gap_us(&self) -> u32 {
+        let gap = self.bus.character_time_us() * 7 / 2;
+        if gap < 1750 {
+            1750
+        } else {
+            gap
+        }
+    }
+
+    /// Reads holding registers of a slave, with the function code 0x03.
+    /// # Arguments
+    /// * `slave` - a u8, the address of the slave.
+    /// * `start` - a u16, the address of the first register.
+    /// * `registers` - a mutable slice of u16, which will be filled with up to 125 registers.
+    /// # Returns
+    /// * `a Result<(), ModbusError>` - which is an error if the transaction failed.
+    pub fn read_holding_registers(
+        &mut self,
+        slave: u8,
+        start: u16,
+        registers: &mut [u16],
+    ) -> Result<(), ModbusError> {
+        self.read_registers(0x03, slave, start, registers)
+    }
+
+    /// Reads input registers of a slave, with the function code 0x04.
+    /// # Arguments
+    /// * `slave` - a u8, the address of the slave.
+    /// * `start` - a u16, the address of the first register.
+    /// * `registers` - a mutable slice of u16, which will be filled with up to 125 registers.
+    /// # Returns
+    /// * `a Result<(), ModbusError>` - which is an error if the transaction failed.
+    pub fn read_input_registers(
+        &mut self,
+        slave: u8,
+        start: u16,
+        registers: &mut [u16],
+    ) -> Result<(), ModbusError> {
+        self.read_registers(0x04, slave, start, registers)
+    }
+
+    /// Writes a holding register of a slave, with the function code 0x06.
+    /// A slave address of 0 broadcasts the request, which is not answered.
+    /// # Arguments
+    /// * `slave` - a u8, the address of the slave.
+    /// * `address` - a u16, the address of the register.
+    /// * `value` - a u16, the value to be written.
+    /// # Returns
+    /// * `a Result<(), ModbusError>` - which is an error if the transaction failed.
+    pub fn write_single_register(
+        &mut self,
+        slave: u8,
+        address: u16,
+        value: u16,
+    ) -> Result<(), ModbusError> {
+        let mut frame = [0; MAX_FRAME];
+        let length = write_single_request(&mut frame, slave, address, value);
+        self.send(&frame[..length]);
+        if slave == 0 {
+            return Ok(());
+        }
+        let request = [frame[2], frame[3], frame[4], frame[5]];
+        let length = self.receive(&mut frame)?;
+        check(&frame[..length], slave, 0x06)?;
+        if length != 8 || frame[2..6] != request {
+            return Err(ModbusError::InvalidResponse);
+        }
+        Ok(())
+    }
+
+    /// Writes consecutive holding registers of a slave, with the function code 0x10.
+    /// A slave address of 0 broadcasts the request, which is not answered.
+    /// # Arguments
+    /// * `slave` - a u8, the address of the slave.
+    /// * `start` - a u16, the address of the first register.
+    /// * `values` - a slice of u16, up to 123 values to be written.
+    /// # Returns
+    /// * `a Result<(), ModbusError>` - which is an error if the transaction failed.
+    pub fn write_multiple_registers(
+        &mut self,
+        slave: u8,
+        start: u16,
+        values: &[u16],
+    ) -> Result<(), ModbusError> {
+        let mut frame = [0; MAX_FRAME];
+        let length = write_multiple_request(&mut frame, slave, start, values)?;
+        self.send(&frame[..length]);
+        if slave == 0 {
+            return Ok(());
+        }
+        let count = values.len() as u16;
+        let length = self.receive(&mut frame)?;
+        check(&frame[..length], slave, 0x10)?;
+        if length != 8 || frame[2..4] != start.to_be_bytes() || frame[4..6] != count.to_be_bytes() {
+            return Err(ModbusError::InvalidResponse);
+        }
+        Ok(())
+    }
+
+    // Sends a read request of holding or input registers and copies the registers received.
+    fn read_registers(
+        &mut self,
+        function: u8,
+        slave: u8,
+        start: u16,
+        registers: &mut [u16],
+    ) -> Result<(), ModbusError> {
+        let mut frame = [0; MAX_FRAME];
+        let length = read_request(&mut frame, function, slave, start, registers.len())?;
+        self.send(&frame[..length]);
+        let length = self.receive(&mut frame)?;
+        read_response(&frame[..length], function, slave, registers)
+    }
+
+    // Waits for the silent interval since the last frame and sends a frame.
+    fn send(&mut self, frame: &[u8]) {
+        let gap = self.frame_gap_us();
+        while micros().wrapping_sub(self.last) < gap {}
+        self.bus.clear();
+        self.bus.send(frame);
+        self.last = micros();
+    }
+
+    // Receives a frame, which ends once the bus has been silent for 3.5 characters.
+    fn receive(&mut self, frame: &mut [u8]) -> Result<usize, ModbusError> {
+        let gap = self.frame_gap_us();
+        let start = micros();
+        let mut length = 0;
+        loop {
+            if let Some(byte) = self.bus.read_byte() {
+                if length < frame.len() {
+                    frame[length] = byte;
+                }
+                length += 1;
+                self.last = micros();
+            } else if length == 0 {
+                if micros().wrapping_sub(start) > self.timeout {
+                    return Err(ModbusError::Timeout);
+                }
+            } else if micros().wrapping_sub(self.last) > gap {
+                break;
+            }
+        }
+        if length > frame.len() {
+            return Err(ModbusError::InvalidResponse);
+        }
+        Ok(length)
+    }
+}
+
+// Appends the CRC to the first `length` bytes of the frame.
+// Returns the length of the frame with its CRC.
+fn append_crc(frame: &mut [u8], length: usize) -> usize {
+    let crc = crc16_modbus(&frame[..length]);
+    frame[length..length + 2].copy_from_slice(&crc.to_le_bytes());
+    length + 2
+}
+
+// Builds the request reading `count` holding or input registers.
+fn read_request(
+    frame: &mut [u8],
+    function: u8,
+    slave: u8,
+    start: u16,
+    count: usize,
+) -> Result<usize, ModbusError> {
+    if slave == 0 || count == 0 || count > MAX_READ {
+        return Err(ModbusError::InvalidRequest);
+    }
+    frame[0] = slave;
+    frame[1] = function;
+    frame[2..4].copy_from_slice(&start.to_be_bytes());
+    frame[4..6].copy_from_slice(&(count as u16).to_be_bytes());
+    Ok(append_crc(frame, 6))
+}
+
+// Builds the request writing a single register.
+fn write_single_request(frame: &mut [u8], slave: u8, address: u16, value: u16) -> usize {
+    frame[0] = slave;
+    frame[1] = 0x06;
+    frame[2..4].copy_from_slice(&address.to_be_bytes());
+    frame[4..6].copy_from_slice(&value.to_be_bytes());
+    append_crc(frame, 6)
+}
+
+// Builds the request writing consecutive registers.
+fn write_multiple_request(
+    frame: &mut [u8],
+    slave: u8,
+    start: u16,
+    values: &[u16],
+) -> Result<usize, ModbusError> {
+    if values.is_empty() || values.len() > MAX_WRITE {
+        return Err(ModbusError::InvalidRequest);
+    }
+    frame[0] = slave;
+    frame[1] = 0x10;
+    frame[2..4].copy_from_slice(&start.to_be_bytes());
+    frame[4..6].copy_from_slice(&(values.len() as u16).to_be_bytes());
+    frame[6] = (values.len() * 2) as u8;
+    for (i, value) in values.iter().enumerate() {
+        frame[7 + 2 * i..9 + 2 * i].copy_from_slice(&value.to_be_bytes());
+    }
+    Ok(append_crc(frame, 7 + values.len() * 2))
+}
+
+// Checks the response to a read request and copies the registers it holds.
+fn read_response(
+    frame: &[u8],
+    function: u8,
+    slave: u8,
+    registers: &mut [u16],
+) -> Result<(), ModbusError> {
+    check(frame, slave, function)?;
+    let bytes = registers.len() * 2;
+    if frame[2] as usize != bytes || frame.len() != bytes + 5 {
+        return Err(ModbusError::InvalidResponse);
+    }
+    for (i, register) in registers.iter_mut().enumerate() {
+        *register = u16::from_be_bytes([frame[3 + 2 * i], frame[4 + 2 * i]]);
+    }
+    Ok(())
+}
+
+// Checks the CRC, the address and the function code of a response.
+fn check(frame: &[u8], slave: u8, function: u8) -> Result<(), ModbusError> {
+    let length = frame.len();
+    if length < 5 {
+        return Err(ModbusError::InvalidResponse);
+    }
+    let crc = u16::from_le_bytes([frame[length - 2], frame[length - 1]]);
+    if crc16_modbus(&frame[..length - 2]) != crc {
+        return Err(ModbusError::Crc);
+    }
+    if frame[0] != slave {
+        return Err(ModbusError::InvalidResponse);
+    }
+    if frame[1] == function | 0x80 {
+        return Err(ModbusError::Exception(frame[2]));
+    }
+    if frame[1] != function {
+        return Err(ModbusError::InvalidResponse);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn build_requests() {
+        let mut frame = [0; MAX_FRAME];
+        // The example of the specification: 3 holding registers from 0x006B of slave 17.
+        let length = read_request(&mut frame, 0x03, 0x11, 0x006B, 3).unwrap();
+        assert_eq!(
+            frame[..length],
+            [0x11, 0x03, 0x00, 0x6B, 0x00, 0x03, 0x76, 0x87]
+        );
+
+        let length = write_single_request(&mut frame, 0x11, 0x0001, 0x0003);
+        assert_eq!(
+            frame[..length],
+            [0x11, 0x06, 0x00, 0x01, 0x00, 0x03, 0x9A, 0x9B]
+        );
+
+        let length = write_multiple_request(&mut frame, 0x11, 0x0001, &[0x000A, 0x0102]).unwrap();
+        assert_eq!(
+            frame[..length],
+            [0x11, 0x10, 0x00, 0x01, 0x00, 0x02, 0x04, 0x00, 0x0A, 0x01, 0x02, 0xC6, 0xF0]
+        );
+
+        assert_eq!(
+            read_request(&mut frame, 0x03, 0, 0, 1),
+            Err(ModbusError::InvalidRequest)
+        );
+        assert_eq!(
+            read_request(&mut frame, 0x04, 1, 0, MAX_READ + 1),
+            Err(ModbusError::InvalidRequest)
+        );
+        assert_eq!(
+            write_multiple_request(&mut frame, 1, 0, &[]),
+            Err(ModbusError::InvalidRequest)
+        );
+    }
+
+    #[test]
+    fn parse_responses() {
+        let mut response = [0x11, 0x03, 0x06, 0x02, 0x2B, 0x00, 0x00, 0x00, 0x64, 0, 0];
+        let length = append_crc(&mut response, 9);
+        let mut registers = [0; 3];
+        read_response(&response[..length], 0x03, 0x11, &mut registers).unwrap();
+        assert_eq!(registers, [0x022B, 0x0000, 0x0064]);
+
+        // Another slave, or fewer registers than asked.
+        assert_eq!(
+            read_response(&response[..length], 0x03, 0x12, &mut registers),
+            Err(ModbusError::InvalidResponse)
+        );
+        assert_eq!(
+            read_response(&response[..length], 0x03, 0x11, &mut [0; 2]),
+            Err(ModbusError::InvalidResponse)
+        );
+
+        // Illegal data address.
+        let mut exception = [0x11, 0x83, 0x02, 0, 0];
+        let length = append_crc(&mut exception, 3);
+        assert_eq!(
+            check(&exception[..length], 0x11, 0x03),
+            Err(ModbusError::Exception(0x02))
+        );
+    }
+
+    #[test]
+    fn crc_rejected() {
+        let mut response = [0x11, 0x06, 0x00, 0x01, 0x00, 0x03, 0x9A, 0x9B];
+        assert_eq!(check(&response, 0x11, 0x06), Ok(()));
+        response[5] = 0x04;
+        assert_eq!(check(&response, 0x11, 0x06), Err(ModbusError::Crc));
+        response[5] = 0x03;
+        response[7] = 0x9C;
+        assert_eq!(check(&response, 0x11, 0x06), Err(ModbusError::Crc));
+    }
+}
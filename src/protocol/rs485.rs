@@ -0,0 +1,140 @@
+// RustDuino : A generic HAL implementation for Arduino Boards in Rust
+// Copyright (C) 2021 Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Half-duplex RS-485 over a serial port and a transceiver such as the MAX485,
+//! whose driver enable (DE) and receiver enable (RE, active low) pins are tied together
+//! to one digital pin. The pin is high while sending and low while receiving.
+//! The driver must stay enabled until the stop bit of the last byte has left the shift
+//! register, so after the bytes are queued the transport waits with `UsartOps::flush`,
+//! which for `BufferedSerial` waits for the Transmit Complete flag and not only for the
+//! data register to be empty, before releasing the bus.
+//! The port of the chip is created and started by the `rs485` module in the `com` of the chip.
+
+// Source codes required.
+use crate::com::usart_interrupt::BufferedSerial;
+use crate::common::{GpioOps, UsartOps};
+use crate::hal::port::Pin;
+use crate::protocol::modbus::HalfDuplex;
+
+/// Number of bits of a character on the bus, with the start bit, 8 data bits,
+/// the parity or second stop bit and the stop bit.
+const BITS_PER_CHARACTER: u32 = 11;
+
+/// Half-duplex RS-485 transport on a serial port and a direction control pin.
+/// # Elements
+/// * `serial` - a `UsartOps` object, the port connected to DI and RO of the transceiver,
+///   by default a `BufferedSerial`.
+/// * `direction` - a `GpioOps` object, the pin connected to DE and RE of the transceiver.
+/// * `baud` - a u32, the baud rate of the bus.
+pub struct Rs485<U = BufferedSerial, P = Pin> {
+    pub(crate) serial: U,
+    direction: P,
+    pub(crate) baud: u32,
+}
+
+impl<U: UsartOps, P: GpioOps> Rs485<U, P> {
+    /// Creates the transport on a serial port started at `baud`, with the transceiver
+    /// in receive mode.
+    /// # Arguments
+    /// * `serial` - a `UsartOps` object, the port connected to DI and RO.
+    /// * `direction` - a `GpioOps` object, the pin connected to DE and RE.
+    /// * `baud` - a u32, the baud rate of the bus.
+    /// # Returns
+    /// * `a Rs485 object` - which will be used to send and receive on the bus.
+    pub fn with_serial(serial: U, mut direction: P, baud: u32) -> Rs485<U, P> {
+        direction.low();
+        direction.set_output();
+        Rs485 {
+            serial,
+            direction,
+            baud,
+        }
+    }
+
+    /// Gives the time taken by one character on the bus.
+    /// # Returns
+    /// * `a u32` - the time in microseconds.
+    pub fn character_time_us(&self) -> u32 {
+        BITS_PER_CHARACTER * 1_000_000 / self.baud
+    }
+
+    /// Sends the bytes, enabling the driver only for the time of the transmission.
+    /// This returns once the last stop bit has been sent and the bus is released.
+    /// # Arguments
+    /// * `data` - a slice of u8, the bytes to be sent.
+    pub fn write(&mut self, data: &[u8]) {
+        self.direction.high();
+        self.serial.write_bytes(data);
+        self.serial.flush();
+        self.direction.low();
+    }
+
+    /// Checks whether a received byte is waiting to be read.
+    pub fn available(&mut self) -> bool {
+        self.serial.available()
+    }
+
+    /// Reads the oldest received byte.
+    /// # Returns
+    /// * `a Option<u8>` - which is None if no byte is available.
+    pub fn read(&mut self) -> Option<u8> {
+        self.serial.read_byte()
+    }
+
+    /// Discards all the received bytes which have not been read.
+    pub fn clear(&mut self) {
+        while self.serial.read_byte().is_some() {}
+    }
+}
+
+impl<U: UsartOps, P: GpioOps> HalfDuplex for Rs485<U, P> {
+    fn send(&mut self, data: &[u8]) {
+        self.write(data);
+    }
+
+    fn read_byte(&mut self) -> Option<u8> {
+        self.read()
+    }
+
+    fn clear(&mut self) {
+        Rs485::clear(self);
+    }
+
+    fn character_time_us(&self) -> u32 {
+        Rs485::character_time_us(self)
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod test {
+    use super::*;
+    use crate::mock::{MockPin, MockUsart, PinOp};
+
+    #[test]
+    fn driver_enabled_while_sending() {
+        let mut serial = MockUsart::new();
+        let mut direction = MockPin::new();
+        let mut bus = Rs485::with_serial(&mut serial, &mut direction, 19200);
+        assert_eq!(bus.character_time_us(), 572);
+        bus.write(&[0x01, 0x03]);
+        drop(bus);
+        assert_eq!(serial.written(), &[0x01, 0x03]);
+        assert_eq!(
+            direction.ops(),
+            &[PinOp::Low, PinOp::SetOutput, PinOp::High, PinOp::Low]
+        );
+    }
+}
@@ -0,0 +1,264 @@
+// RustDuino : A generic HAL implementation for Arduino Boards in Rust
+// Copyright (C) 2021 Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Frames of the Wiegand interface of RFID card and keypad readers.
+//! The reader sends a 0 by pulling DATA0 low and a 1 by pulling DATA1 low, with about
+//! 2 ms between the bits and the first bit first. `WiegandReceiver` collects the bits
+//! given by the interrupts of the lines, and a frame has ended once no bit has come
+//! for `WIEGAND_FRAME_GAP_US`.
+//! The 26 and 34 bit formats start with an even parity bit over the first half of the
+//! data and end with an odd parity bit over the second half. The data is a facility
+//! code of 8 or 16 bits followed by a card number of 16 bits.
+//! The lines and their interrupts are set up by the `wiegand` module in the `com` of the chip.
+//! See `<https://www.hidglobal.com/sites/default/files/hid-understanding_card_data_formats-wp-en.pdf>`.
+
+// Source codes required.
+use crate::error::Error;
+
+/// Microseconds without any bit which end a frame.
+pub const WIEGAND_FRAME_GAP_US: u32 = 25_000;
+
+/// Largest number of bits in a frame.
+const MAX_BITS: u8 = 64;
+
+/// Bits of a frame, as received.
+/// # Elements
+/// * `bits` - a u64, the bits with the last one received in bit 0.
+/// * `count` - a u8, the number of bits.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct WiegandFrame {
+    pub bits: u64,
+    pub count: u8,
+}
+
+/// Card read in the 26 or 34 bit format.
+/// # Elements
+/// * `format` - a u8, the number of bits of the frame, 26 or 34.
+/// * `facility` - a u16, the facility code, 8 bits in the 26 bit format.
+/// * `card` - a u16, the card number.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct WiegandCard {
+    pub format: u8,
+    pub facility: u16,
+    pub card: u16,
+}
+
+impl WiegandFrame {
+    /// Checks the parity bits of a 26 or 34 bit frame and gives its card.
+    /// # Returns
+    /// * `a Result<WiegandCard, Error>` - the card, `Error::InvalidData` if a parity bit is
+    ///   wrong, or `Error::Unsupported` for the other formats.
+    pub fn decode(&self) -> Result<WiegandCard, Error> {
+        if self.count != 26 && self.count != 34 {
+            return Err(Error::Unsupported);
+        }
+        let half = (self.count - 2) / 2;
+        let data = (self.bits >> 1) & ((1u64 << (self.count - 2)) - 1);
+        let first = (data >> half) as u32;
+        let second = (data & ((1u64 << half) - 1)) as u32;
+        let leading = (self.bits >> (self.count - 1)) as u32 & 1;
+        let trailing = self.bits as u32 & 1;
+        if (first.count_ones() + leading) % 2 != 0 || (second.count_ones() + trailing) % 2 != 1 {
+            return Err(Error::InvalidData);
+        }
+        Ok(WiegandCard {
+            format: self.count,
+            facility: (data >> 16) as u16,
+            card: data as u16,
+        })
+    }
+}
+
+/// Frame being received, filled by the interrupts of the lines.
+/// # Elements
+/// * `bits` - a u64, the bits received, with the last one in bit 0.
+/// * `count` - a u8, the number of bits received.
+/// * `overflow` - a boolean, true if more than 64 bits came in the frame.
+/// * `last_bit` - a u32, the time in microseconds of the last bit.
+pub struct WiegandReceiver {
+    bits: u64,
+    count: u8,
+    overflow: bool,
+    last_bit: u32,
+}
+
+impl WiegandReceiver {
+    /// Creates the receiver, without any bit.
+    pub const fn new() -> WiegandReceiver {
+        WiegandReceiver {
+            bits: 0,
+            count: 0,
+            overflow: false,
+            last_bit: 0,
+        }
+    }
+
+    /// Drops the bits received.
+    pub fn clear(&mut self) {
+        self.bits = 0;
+        self.count = 0;
+        self.overflow = false;
+    }
+
+    /// Adds a bit, starting a new frame if the last bit came `WIEGAND_FRAME_GAP_US` ago.
+    /// # Arguments
+    /// * `bit` - a boolean, true for a pulse on DATA1 and false for a pulse on DATA0.
+    /// * `now` - a u32, the time of the pulse in microseconds.
+    pub fn push(&mut self, bit: bool, now: u32) {
+        if self.count > 0 && now.wrapping_sub(self.last_bit) >= WIEGAND_FRAME_GAP_US {
+            // A new frame, the old one is dropped if it has not been read.
+            self.clear();
+        }
+        self.last_bit = now;
+        if self.count < MAX_BITS {
+            self.bits = self.bits << 1 | bit as u64;
+            self.count += 1;
+        } else {
+            self.overflow = true;
+        }
+    }
+
+    /// Gives the frame received once no bit has come for `WIEGAND_FRAME_GAP_US`, and
+    /// starts waiting for the next one. A frame of more than 64 bits is dropped.
+    /// # Arguments
+    /// * `now` - a u32, the time in microseconds.
+    /// # Returns
+    /// * `a Option<WiegandFrame>` - the frame, None if no frame has ended.
+    pub fn take(&mut self, now: u32) -> Option<WiegandFrame> {
+        if self.count == 0 || now.wrapping_sub(self.last_bit) < WIEGAND_FRAME_GAP_US {
+            return None;
+        }
+        let frame = WiegandFrame {
+            bits: self.bits,
+            count: self.count,
+        };
+        let overflow = self.overflow;
+        self.clear();
+        if overflow {
+            None
+        } else {
+            Some(frame)
+        }
+    }
+}
+
+impl Default for WiegandReceiver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Sends the bits of a frame, 2 ms apart, starting at `start`.
+    fn send(receiver: &mut WiegandReceiver, bits: u64, count: u8, start: u32) -> u32 {
+        let mut now = start;
+        for i in (0..count).rev() {
+            receiver.push(bits >> i & 1 == 1, now);
+            now = now.wrapping_add(2000);
+        }
+        now
+    }
+
+    #[test]
+    fn decode_26_bits() {
+        // Facility 0x12 and card 0x3456: the first 12 bits hold four ones and the
+        // last 12 bits hold five ones, so both parity bits are 0.
+        let bits = 0x12_3456u64 << 1;
+        let frame = WiegandFrame { bits, count: 26 };
+        assert_eq!(
+            frame.decode(),
+            Ok(WiegandCard {
+                format: 26,
+                facility: 0x12,
+                card: 0x3456
+            })
+        );
+
+        // A flipped data bit breaks the parity of its half.
+        let frame = WiegandFrame {
+            bits: bits ^ 1 << 20,
+            count: 26,
+        };
+        assert_eq!(frame.decode(), Err(Error::InvalidData));
+        let frame = WiegandFrame {
+            bits: bits ^ 1 << 3,
+            count: 26,
+        };
+        assert_eq!(frame.decode(), Err(Error::InvalidData));
+    }
+
+    #[test]
+    fn decode_34_bits() {
+        // Facility 0x8001 holds two ones, card 0x0007 holds three.
+        let data = 0x8001_0007u64;
+        let frame = WiegandFrame {
+            bits: data << 1,
+            count: 34,
+        };
+        assert_eq!(
+            frame.decode(),
+            Ok(WiegandCard {
+                format: 34,
+                facility: 0x8001,
+                card: 0x0007
+            })
+        );
+        let frame = WiegandFrame {
+            bits: 1 << 33 | data << 1,
+            count: 34,
+        };
+        assert_eq!(frame.decode(), Err(Error::InvalidData));
+        let frame = WiegandFrame { bits: 0, count: 37 };
+        assert_eq!(frame.decode(), Err(Error::Unsupported));
+    }
+
+    #[test]
+    fn receive_frames() {
+        let mut receiver = WiegandReceiver::new();
+        let bits = 0x12_3456u64 << 1;
+        let end = send(&mut receiver, bits, 26, 1000);
+        // Not yet silent for long enough.
+        assert_eq!(receiver.take(end), None);
+        let frame = receiver.take(end + WIEGAND_FRAME_GAP_US).unwrap();
+        assert_eq!(frame, WiegandFrame { bits, count: 26 });
+        assert_eq!(receiver.take(end + WIEGAND_FRAME_GAP_US), None);
+
+        // A frame which is not read is dropped when the next one starts, across the
+        // wrap of the time.
+        let end = send(&mut receiver, 0b101, 3, u32::MAX - 10_000);
+        let end = send(
+            &mut receiver,
+            0b11,
+            2,
+            end.wrapping_add(WIEGAND_FRAME_GAP_US),
+        );
+        assert_eq!(
+            receiver.take(end.wrapping_add(WIEGAND_FRAME_GAP_US)),
+            Some(WiegandFrame {
+                bits: 0b11,
+                count: 2
+            })
+        );
+
+        // More than 64 bits.
+        let end = send(&mut receiver, 0, 64, 0);
+        let end = send(&mut receiver, 0, 2, end);
+        assert_eq!(receiver.take(end + WIEGAND_FRAME_GAP_US), None);
+    }
+}
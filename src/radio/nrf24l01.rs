@@ -0,0 +1,667 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! This code controls the Nordic nRF24L01+ 2.4 GHz radio over SPI.
+//! The radio sends packets of up to 32 bytes to one address and receives on up to
+//! 6 pipes, each with its own address. With auto-acknowledgement the receiver
+//! answers every packet and the sender retransmits until it gets the answer, which
+//! needs pipe 0 of the sender to listen on the address it writes to, so the driver
+//! sets it while writing and restores it when listening again.
+//! Besides SPI the radio needs a CE pin, which enables sending and receiving, and
+//! optionally an IRQ pin, which goes low when a packet was sent, lost or received.
+//! See `<https://www.sparkfun.com/datasheets/Components/SMD/nRF24L01Pluss_Preliminary_Product_Specification_v1_0.pdf>`.
+
+// Source codes required.
+use crate::bus::{SharedSpi, SpiDevice};
+use crate::common::SpiOps;
+use crate::delay::{delay_ms, delay_us};
+use crate::error::Error;
+use crate::hal::pin::make_pin;
+
+/// Largest payload of a packet in bytes.
+pub const MAX_PAYLOAD: usize = 32;
+
+// Registers.
+const CONFIG: u8 = 0x00;
+const EN_AA: u8 = 0x01;
+const EN_RXADDR: u8 = 0x02;
+const SETUP_AW: u8 = 0x03;
+const SETUP_RETR: u8 = 0x04;
+const RF_CH: u8 = 0x05;
+const RF_SETUP: u8 = 0x06;
+const STATUS: u8 = 0x07;
+const OBSERVE_TX: u8 = 0x08;
+const RPD: u8 = 0x09;
+const RX_ADDR_P0: u8 = 0x0A;
+const TX_ADDR: u8 = 0x10;
+const RX_PW_P0: u8 = 0x11;
+const FIFO_STATUS: u8 = 0x17;
+const DYNPD: u8 = 0x1C;
+const FEATURE: u8 = 0x1D;
+
+// Commands.
+const R_REGISTER: u8 = 0x00;
+const W_REGISTER: u8 = 0x20;
+const R_RX_PL_WID: u8 = 0x60;
+const R_RX_PAYLOAD: u8 = 0x61;
+const W_TX_PAYLOAD: u8 = 0xA0;
+const W_ACK_PAYLOAD: u8 = 0xA8;
+const W_TX_PAYLOAD_NO_ACK: u8 = 0xB0;
+const FLUSH_TX: u8 = 0xE1;
+const FLUSH_RX: u8 = 0xE2;
+const NOP: u8 = 0xFF;
+
+// Bits of CONFIG.
+const MASK_RX_DR: u8 = 1 << 6;
+const MASK_TX_DS: u8 = 1 << 5;
+const MASK_MAX_RT: u8 = 1 << 4;
+const EN_CRC: u8 = 1 << 3;
+const CRCO: u8 = 1 << 2;
+const PWR_UP: u8 = 1 << 1;
+const PRIM_RX: u8 = 1 << 0;
+
+// Bits of STATUS.
+const RX_DR: u8 = 1 << 6;
+const TX_DS: u8 = 1 << 5;
+const MAX_RT: u8 = 1 << 4;
+
+// Bits of FIFO_STATUS.
+const RX_EMPTY: u8 = 1 << 0;
+
+// Bits of FEATURE.
+const EN_DPL: u8 = 1 << 2;
+const EN_ACK_PAY: u8 = 1 << 1;
+const EN_DYN_ACK: u8 = 1 << 0;
+
+// SCK frequency used with the radio, below its limit of 10 MHz.
+const SPI_FREQUENCY: u32 = 8_000_000;
+
+/// Errors which can happen while using the radio.
+/// * `NotFound` - the radio did not answer on the SPI bus.
+/// * `MaxRetries` - the packet was retransmitted without being acknowledged.
+/// * `InvalidPipe` - the pipe number is not between 0 and 5.
+/// * `InvalidLength` - the payload or the address has the wrong length.
+/// * `Bus` - the SPI bus was taken by another transfer.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Nrf24Error {
+    NotFound,
+    MaxRetries,
+    InvalidPipe,
+    InvalidLength,
+    Bus,
+}
+
+impl From<Nrf24Error> for Error {
+    fn from(error: Nrf24Error) -> Error {
+        match error {
+            Nrf24Error::NotFound => Error::NotFound,
+            Nrf24Error::MaxRetries => Error::Nack,
+            Nrf24Error::InvalidPipe | Nrf24Error::InvalidLength => Error::InvalidParam,
+            Nrf24Error::Bus => Error::Busy,
+        }
+    }
+}
+
+/// Speed of the radio link, slower links reach further.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DataRate {
+    Kbps250,
+    Mbps1,
+    Mbps2,
+}
+
+/// Output power of the radio, from -18 dBm for `Min` to 0 dBm for `Max`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PowerLevel {
+    Min,
+    Low,
+    High,
+    Max,
+}
+
+/// Length of the CRC of the packets, which is always on with auto-acknowledgement.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CrcLength {
+    Disabled,
+    OneByte,
+    TwoBytes,
+}
+
+/// Used to control a nRF24L01+ radio on the SPI bus.
+/// # Elements
+/// * `device` - a `SpiDevice` object, the handle of the radio on the SPI bus, whose chip
+///   select is the CSN pin.
+/// * `ce` - a u8, the digital pin connected to CE.
+/// * `irq` - a `Option<u8>`, the digital pin connected to IRQ if it is used.
+/// * `payload_size` - a u8, the length of the packets when dynamic payloads are off.
+/// * `dynamic_payloads` - a boolean, true if the packets have their own length.
+/// * `address_width` - a u8, the length of the addresses from 3 to 5.
+/// * `pipe0_address` - a `Option<[u8; 5]>`, the address pipe 0 listens on, if opened.
+pub struct NRF24L01<T = SharedSpi> {
+    device: SpiDevice<T>,
+    ce: u8,
+    irq: Option<u8>,
+    payload_size: u8,
+    dynamic_payloads: bool,
+    address_width: u8,
+    pipe0_address: Option<[u8; 5]>,
+}
+
+impl NRF24L01 {
+    /// Sets up the radio on the SPI of the chip with the default settings, see `with_bus`.
+    /// # Arguments
+    /// * `ce` - a u8, the digital pin connected to CE.
+    /// * `csn` - a u8, the digital pin connected to CSN.
+    /// # Returns
+    /// * `a Result<NRF24L01, Nrf24Error>` - the radio, or `NotFound` if it did not answer.
+    pub fn new(ce: u8, csn: u8) -> Result<NRF24L01, Nrf24Error> {
+        NRF24L01::with_bus(SharedSpi::new(), ce, csn)
+    }
+}
+
+impl<T: SpiOps> NRF24L01<T> {
+    /// Sets up the radio on the given SPI bus with the default settings: channel 76,
+    /// 1 Mbps, maximum power, 2 byte CRC, auto-acknowledgement on all pipes, 15 retries
+    /// 1.5 ms apart, 5 byte addresses and 32 byte payloads. The radio is left powered up
+    /// in TX mode.
+    /// # Arguments
+    /// * `bus` - a `SpiOps` object, the SPI bus of the radio.
+    /// * `ce` - a u8, the digital pin connected to CE.
+    /// * `csn` - a u8, the digital pin connected to CSN.
+    /// # Returns
+    /// * `a Result<NRF24L01, Nrf24Error>` - the radio, or `NotFound` if it did not answer.
+    pub fn with_bus(bus: T, ce: u8, csn: u8) -> Result<NRF24L01<T>, Nrf24Error> {
+        let mut ce_pin = make_pin(ce as _);
+        ce_pin.set_output();
+        ce_pin.low();
+        let mut device = SpiDevice::with_bus(bus, csn, 0, false, SPI_FREQUENCY);
+        device.init();
+        // Time for the radio to start after power on.
+        delay_ms(5);
+
+        let mut radio = NRF24L01 {
+            device,
+            ce,
+            irq: None,
+            payload_size: MAX_PAYLOAD as u8,
+            dynamic_payloads: false,
+            address_width: 5,
+            pipe0_address: None,
+        };
+
+        // The address width register only keeps values from 1 to 3.
+        radio.write_register(SETUP_AW, 0x03);
+        if radio.read_register(SETUP_AW) != 0x03 {
+            return Err(Nrf24Error::NotFound);
+        }
+
+        radio.write_register(CONFIG, EN_CRC | CRCO);
+        radio.set_retries(5, 15);
+        radio.set_data_rate(DataRate::Mbps1);
+        radio.set_power(PowerLevel::Max);
+        radio.set_channel(76);
+        radio.write_register(EN_AA, 0x3F);
+        radio.write_register(EN_RXADDR, 0x03);
+        radio.write_register(DYNPD, 0);
+        radio.write_register(FEATURE, 0);
+        for pipe in 0..6 {
+            radio.write_register(RX_PW_P0 + pipe, MAX_PAYLOAD as u8);
+        }
+        radio.clear_interrupts();
+        radio.flush_rx();
+        radio.flush_tx();
+        radio.power_up();
+        Ok(radio)
+    }
+
+    /// Uses the IRQ pin of the radio to wait for packets instead of asking over SPI.
+    /// `write` then waits for the pin, so the sent and lost events must stay enabled.
+    /// # Arguments
+    /// * `irq` - a u8, the digital pin connected to IRQ.
+    pub fn set_irq_pin(&mut self, irq: u8) {
+        let mut irq_pin = make_pin(irq as _);
+        irq_pin.set_input();
+        self.irq = Some(irq);
+    }
+
+    /// Checks whether the radio pulls its IRQ pin low, false if no pin was given.
+    pub fn irq_active(&mut self) -> bool {
+        match self.irq {
+            Some(irq) => !make_pin(irq as _).is_high(),
+            None => false,
+        }
+    }
+
+    /// Chooses which events pull the IRQ pin low.
+    /// # Arguments
+    /// * `rx_ready` - a boolean, true to signal received packets.
+    /// * `tx_sent` - a boolean, true to signal packets sent.
+    /// * `max_retries` - a boolean, true to signal packets lost.
+    pub fn set_interrupts(&mut self, rx_ready: bool, tx_sent: bool, max_retries: bool) {
+        let mut config = self.read_register(CONFIG) & !(MASK_RX_DR | MASK_TX_DS | MASK_MAX_RT);
+        if !rx_ready {
+            config |= MASK_RX_DR;
+        }
+        if !tx_sent {
+            config |= MASK_TX_DS;
+        }
+        if !max_retries {
+            config |= MASK_MAX_RT;
+        }
+        self.write_register(CONFIG, config);
+    }
+
+    /// Clears the received, sent and lost flags, which releases the IRQ pin.
+    pub fn clear_interrupts(&mut self) {
+        self.write_register(STATUS, RX_DR | TX_DS | MAX_RT);
+    }
+
+    /// Gives the status register.
+    pub fn status(&mut self) -> u8 {
+        self.command(NOP)
+    }
+
+    /// Sets the frequency to 2400 MHz plus the channel, from 0 to 125.
+    pub fn set_channel(&mut self, channel: u8) {
+        self.write_register(RF_CH, channel.min(125));
+    }
+
+    /// Gives the channel in use.
+    pub fn channel(&mut self) -> u8 {
+        self.read_register(RF_CH)
+    }
+
+    /// Sets the speed of the link.
+    pub fn set_data_rate(&mut self, rate: DataRate) {
+        let setup = self.read_register(RF_SETUP) & !(1 << 5 | 1 << 3);
+        let bits = match rate {
+            DataRate::Kbps250 => 1 << 5,
+            DataRate::Mbps1 => 0,
+            DataRate::Mbps2 => 1 << 3,
+        };
+        self.write_register(RF_SETUP, setup | bits);
+    }
+
+    /// Sets the output power.
+    pub fn set_power(&mut self, level: PowerLevel) {
+        let setup = self.read_register(RF_SETUP) & !0x06;
+        self.write_register(RF_SETUP, setup | (level as u8) << 1);
+    }
+
+    /// Sets the length of the CRC.
+    pub fn set_crc(&mut self, length: CrcLength) {
+        let config = self.read_register(CONFIG) & !(EN_CRC | CRCO);
+        let bits = match length {
+            CrcLength::Disabled => 0,
+            CrcLength::OneByte => EN_CRC,
+            CrcLength::TwoBytes => EN_CRC | CRCO,
+        };
+        self.write_register(CONFIG, config | bits);
+    }
+
+    /// Sets the retransmissions of unacknowledged packets.
+    /// # Arguments
+    /// * `delay` - a u8, the time between retransmissions in steps of 250 us, from 0 to 15.
+    /// * `count` - a u8, the number of retransmissions, from 0 to 15.
+    pub fn set_retries(&mut self, delay: u8, count: u8) {
+        self.write_register(SETUP_RETR, (delay.min(15) << 4) | count.min(15));
+    }
+
+    /// Gives the number of packets lost and of retransmissions of the last packet.
+    /// # Returns
+    /// * `a (u8, u8)` - the lost packets (up to 15) and the retransmissions.
+    pub fn observe(&mut self) -> (u8, u8) {
+        let observe = self.read_register(OBSERVE_TX);
+        (observe >> 4, observe & 0x0F)
+    }
+
+    /// Checks whether a signal stronger than -64 dBm is on the channel,
+    /// which is measured while listening.
+    pub fn carrier_detected(&mut self) -> bool {
+        self.read_register(RPD) & 0x01 != 0
+    }
+
+    /// Turns auto-acknowledgement on or off for all the pipes.
+    pub fn set_auto_ack(&mut self, enable: bool) {
+        self.write_register(EN_AA, if enable { 0x3F } else { 0 });
+    }
+
+    /// Turns auto-acknowledgement on or off for one pipe.
+    /// # Arguments
+    /// * `pipe` - a u8, the pipe from 0 to 5.
+    /// * `enable` - a boolean, true to acknowledge the packets of the pipe.
+    pub fn set_auto_ack_pipe(&mut self, pipe: u8, enable: bool) -> Result<(), Nrf24Error> {
+        check_pipe(pipe)?;
+        let mut en_aa = self.read_register(EN_AA);
+        if enable {
+            en_aa |= 1 << pipe;
+        } else {
+            en_aa &= !(1 << pipe);
+        }
+        self.write_register(EN_AA, en_aa);
+        Ok(())
+    }
+
+    /// Sets the length of the addresses, which must be the same on both radios.
+    /// # Arguments
+    /// * `width` - a u8, the length from 3 to 5 bytes.
+    pub fn set_address_width(&mut self, width: u8) -> Result<(), Nrf24Error> {
+        if !(3..=5).contains(&width) {
+            return Err(Nrf24Error::InvalidLength);
+        }
+        self.write_register(SETUP_AW, width - 2);
+        self.address_width = width;
+        Ok(())
+    }
+
+    /// Sets the length of the packets when dynamic payloads are off.
+    /// # Arguments
+    /// * `size` - a u8, the length from 1 to 32 bytes.
+    pub fn set_payload_size(&mut self, size: u8) -> Result<(), Nrf24Error> {
+        if size == 0 || size as usize > MAX_PAYLOAD {
+            return Err(Nrf24Error::InvalidLength);
+        }
+        for pipe in 0..6 {
+            self.write_register(RX_PW_P0 + pipe, size);
+        }
+        self.payload_size = size;
+        Ok(())
+    }
+
+    /// Turns dynamic payloads on or off, which lets every packet have its own length.
+    /// Dynamic payloads also allow payloads in the acknowledgements and packets
+    /// sent without acknowledgement.
+    pub fn set_dynamic_payloads(&mut self, enable: bool) {
+        if enable {
+            self.write_register(FEATURE, EN_DPL | EN_ACK_PAY | EN_DYN_ACK);
+            self.write_register(DYNPD, 0x3F);
+        } else {
+            self.write_register(DYNPD, 0);
+            self.write_register(FEATURE, 0);
+        }
+        self.dynamic_payloads = enable;
+    }
+
+    /// Sets the address the packets are written to.
+    /// # Arguments
+    /// * `address` - a slice of u8, the address, as long as the address width.
+    pub fn open_writing_pipe(&mut self, address: &[u8]) -> Result<(), Nrf24Error> {
+        self.check_address(address)?;
+        self.write_registers(TX_ADDR, address);
+        // The acknowledgements come back on pipe 0.
+        self.write_registers(RX_ADDR_P0, address);
+        Ok(())
+    }
+
+    /// Opens a pipe to receive the packets written to an address.
+    /// Pipes 2 to 5 share all but the first byte of their address with pipe 1,
+    /// so only the first byte of the address is used for them.
+    /// # Arguments
+    /// * `pipe` - a u8, the pipe from 0 to 5.
+    /// * `address` - a slice of u8, the address, as long as the address width.
+    pub fn open_reading_pipe(&mut self, pipe: u8, address: &[u8]) -> Result<(), Nrf24Error> {
+        check_pipe(pipe)?;
+        self.check_address(address)?;
+        match pipe {
+            0 => {
+                let mut stored = [0; 5];
+                stored[..address.len()].copy_from_slice(address);
+                self.pipe0_address = Some(stored);
+                self.write_registers(RX_ADDR_P0, address);
+            }
+            1 => self.write_registers(RX_ADDR_P0 + 1, address),
+            _ => self.write_register(RX_ADDR_P0 + pipe, address[0]),
+        }
+        let enabled = self.read_register(EN_RXADDR);
+        self.write_register(EN_RXADDR, enabled | 1 << pipe);
+        Ok(())
+    }
+
+    /// Stops receiving on a pipe.
+    pub fn close_reading_pipe(&mut self, pipe: u8) -> Result<(), Nrf24Error> {
+        check_pipe(pipe)?;
+        let enabled = self.read_register(EN_RXADDR);
+        self.write_register(EN_RXADDR, enabled & !(1 << pipe));
+        if pipe == 0 {
+            self.pipe0_address = None;
+        }
+        Ok(())
+    }
+
+    /// Wakes the radio up from power down, which takes 1.5 ms.
+    pub fn power_up(&mut self) {
+        let config = self.read_register(CONFIG);
+        if config & PWR_UP == 0 {
+            self.write_register(CONFIG, config | PWR_UP);
+            delay_ms(2);
+        }
+    }
+
+    /// Puts the radio in power down, where it uses less than 1 uA.
+    pub fn power_down(&mut self) {
+        make_pin(self.ce as _).low();
+        let config = self.read_register(CONFIG);
+        self.write_register(CONFIG, config & !PWR_UP);
+    }
+
+    /// Switches the radio to RX mode to receive on the open pipes.
+    pub fn start_listening(&mut self) {
+        self.power_up();
+        let config = self.read_register(CONFIG);
+        self.write_register(CONFIG, config | PRIM_RX);
+        self.clear_interrupts();
+        // Pipe 0 gets back its own address, which writing replaced.
+        if let Some(address) = self.pipe0_address {
+            let width = self.address_width as usize;
+            self.write_registers(RX_ADDR_P0, &address[..width]);
+        }
+        make_pin(self.ce as _).high();
+        // Time for the receiver to settle.
+        delay_us(130);
+    }
+
+    /// Switches the radio back to TX mode.
+    pub fn stop_listening(&mut self) {
+        make_pin(self.ce as _).low();
+        delay_us(100);
+        let config = self.read_register(CONFIG);
+        self.write_register(CONFIG, config & !PRIM_RX);
+    }
+
+    /// Checks whether a packet was received.
+    /// # Returns
+    /// * `a Option<u8>` - the pipe of the first packet waiting, or None.
+    pub fn available(&mut self) -> Option<u8> {
+        if self.read_register(FIFO_STATUS) & RX_EMPTY != 0 {
+            return None;
+        }
+        Some((self.status() >> 1) & 0x07)
+    }
+
+    /// Reads the first packet waiting.
+    /// # Arguments
+    /// * `buffer` - a mutable slice of u8, which will be filled with the payload.
+    /// # Returns
+    /// * `a usize` - the length of the payload, of which only what fits in the buffer is kept,
+    ///   or 0 if there was no packet.
+    pub fn read(&mut self, buffer: &mut [u8]) -> usize {
+        if self.available().is_none() {
+            return 0;
+        }
+        let length = if self.dynamic_payloads {
+            let length = self.command_read(R_RX_PL_WID);
+            // A length above 32 means a damaged packet, which must be flushed.
+            if length as usize > MAX_PAYLOAD {
+                self.flush_rx();
+                self.write_register(STATUS, RX_DR);
+                return 0;
+            }
+            length as usize
+        } else {
+            self.payload_size as usize
+        };
+
+        let mut payload = [0u8; MAX_PAYLOAD];
+        let _ = self
+            .device
+            .write_read(&[R_RX_PAYLOAD], &mut payload[..length]);
+        self.write_register(STATUS, RX_DR);
+
+        let count = length.min(buffer.len());
+        buffer[..count].copy_from_slice(&payload[..count]);
+        length
+    }
+
+    /// Sends a packet and waits until it is acknowledged or the retries run out.
+    /// # Arguments
+    /// * `data` - a slice of u8, the payload, padded with zeros to the payload size
+    ///   when dynamic payloads are off.
+    /// # Returns
+    /// * `a Result<(), Nrf24Error>` - `MaxRetries` if the packet was not acknowledged.
+    pub fn write(&mut self, data: &[u8]) -> Result<(), Nrf24Error> {
+        self.send(data, W_TX_PAYLOAD)
+    }
+
+    /// Sends a packet which the receiver will not acknowledge, which needs dynamic payloads.
+    pub fn write_no_ack(&mut self, data: &[u8]) -> Result<(), Nrf24Error> {
+        self.send(data, W_TX_PAYLOAD_NO_ACK)
+    }
+
+    /// Gives the payload the radio will put in the acknowledgement of the next packet
+    /// received on a pipe, which needs dynamic payloads.
+    /// # Arguments
+    /// * `pipe` - a u8, the pipe from 0 to 5.
+    /// * `data` - a slice of u8, the payload of up to 32 bytes.
+    pub fn write_ack_payload(&mut self, pipe: u8, data: &[u8]) -> Result<(), Nrf24Error> {
+        check_pipe(pipe)?;
+        if data.is_empty() || data.len() > MAX_PAYLOAD {
+            return Err(Nrf24Error::InvalidLength);
+        }
+        self.device
+            .transaction(|spi| {
+                spi.transfer(W_ACK_PAYLOAD | pipe);
+                spi.write(data);
+            })
+            .map_err(|_| Nrf24Error::Bus)
+    }
+
+    /// Empties the packets waiting to be sent.
+    pub fn flush_tx(&mut self) {
+        self.command(FLUSH_TX);
+    }
+
+    /// Empties the packets waiting to be read.
+    pub fn flush_rx(&mut self) {
+        self.command(FLUSH_RX);
+    }
+
+    /// Loads a packet, pulses CE and waits for the result.
+    fn send(&mut self, data: &[u8], command: u8) -> Result<(), Nrf24Error> {
+        if data.is_empty() || data.len() > MAX_PAYLOAD {
+            return Err(Nrf24Error::InvalidLength);
+        }
+        let length = if self.dynamic_payloads {
+            data.len()
+        } else {
+            self.payload_size as usize
+        };
+
+        self.device
+            .transaction(|spi| {
+                spi.transfer(command);
+                for byte in data
+                    .iter()
+                    .copied()
+                    .chain(core::iter::repeat(0))
+                    .take(length)
+                {
+                    spi.transfer(byte);
+                }
+            })
+            .map_err(|_| Nrf24Error::Bus)?;
+
+        // A pulse of more than 10 us starts the transmission.
+        make_pin(self.ce as _).high();
+        delay_us(15);
+        make_pin(self.ce as _).low();
+
+        let status = loop {
+            if self.irq.is_some() && !self.irq_active() {
+                continue;
+            }
+            let status = self.status();
+            if status & (TX_DS | MAX_RT) != 0 {
+                break status;
+            }
+        };
+        self.write_register(STATUS, TX_DS | MAX_RT);
+        if status & MAX_RT != 0 {
+            // The packet stays in the FIFO and would block the next ones.
+            self.flush_tx();
+            return Err(Nrf24Error::MaxRetries);
+        }
+        Ok(())
+    }
+
+    /// Checks the length of an address.
+    fn check_address(&self, address: &[u8]) -> Result<(), Nrf24Error> {
+        if address.len() != self.address_width as usize {
+            return Err(Nrf24Error::InvalidLength);
+        }
+        Ok(())
+    }
+
+    /// Sends a command without data.
+    /// # Returns
+    /// * `a u8` - the status register, which is shifted out with every command,
+    ///   or 0 if the bus was taken.
+    fn command(&mut self, command: u8) -> u8 {
+        self.device
+            .transaction(|spi| spi.transfer(command))
+            .unwrap_or(0)
+    }
+
+    /// Sends a command and reads one byte, 0 if the bus was taken.
+    fn command_read(&mut self, command: u8) -> u8 {
+        let mut value = [0];
+        let _ = self.device.write_read(&[command], &mut value);
+        value[0]
+    }
+
+    fn read_register(&mut self, register: u8) -> u8 {
+        self.command_read(R_REGISTER | register)
+    }
+
+    fn write_register(&mut self, register: u8, value: u8) {
+        self.write_registers(register, &[value]);
+    }
+
+    fn write_registers(&mut self, register: u8, data: &[u8]) {
+        let _ = self.device.transaction(|spi| {
+            spi.transfer(W_REGISTER | register);
+            spi.write(data);
+        });
+    }
+}
+
+/// Checks the number of a pipe.
+fn check_pipe(pipe: u8) -> Result<(), Nrf24Error> {
+    if pipe < 6 {
+        Ok(())
+    } else {
+        Err(Nrf24Error::InvalidPipe)
+    }
+}
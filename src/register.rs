@@ -0,0 +1,248 @@
+// RustDuino : A generic HAL implementation for Arduino Boards in Rust
+// Copyright (C) 2021 Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Typed access to the registers of the chips, by name instead of through structures
+//! laid over the memory, whose padding must match the gaps between the registers.
+//! The register map of each chip, in `registers`, is written with `register_map!`,
+//! which gives for each register a `Register` constant at its data space address and a
+//! module of the same name with its fields, as `WDTCSR` and `WDTCSR::WDE`.
+//! The 16 bit registers are accessed in the order required by their temporary register,
+//! the low byte first when reading and the high byte first when writing, with interrupts
+//! disabled in between.
+//! # Example
+//! ```ignore
+//! use rustduino::registers::{ADCSRA, ADMUX};
+//!
+//! ADMUX.write_field(ADMUX::MUX, 3);
+//! ADCSRA.set(ADCSRA::ADSC);
+//! while ADCSRA.is_set(ADCSRA::ADSC) {}
+//! ```
+
+// Crates which would be used in the implementation.
+use core::marker::PhantomData;
+use core::ptr::{read_volatile, write_volatile};
+
+/// Width of a register, 8 or 16 bits.
+pub trait RegisterValue: Copy {
+    /// Reads the register at a data space address.
+    /// # Safety
+    /// The address must be the one of a register of this width.
+    unsafe fn read(address: usize) -> Self;
+
+    /// Writes the register at a data space address.
+    /// # Safety
+    /// The address must be the one of a register of this width.
+    unsafe fn write(address: usize, value: Self);
+
+    /// Gives the value as 16 bits.
+    fn to_bits(self) -> u16;
+
+    /// Gives the value from 16 bits, dropping the bits above the width.
+    fn from_bits(bits: u16) -> Self;
+}
+
+impl RegisterValue for u8 {
+    unsafe fn read(address: usize) -> u8 {
+        read_volatile(address as *const u8)
+    }
+
+    unsafe fn write(address: usize, value: u8) {
+        write_volatile(address as *mut u8, value)
+    }
+
+    fn to_bits(self) -> u16 {
+        self as u16
+    }
+
+    fn from_bits(bits: u16) -> u8 {
+        bits as u8
+    }
+}
+
+impl RegisterValue for u16 {
+    unsafe fn read(address: usize) -> u16 {
+        // Reading the low byte latches the high byte in the temporary register.
+        crate::sync::free(|_| {
+            let low = read_volatile(address as *const u8);
+            let high = read_volatile((address + 1) as *const u8);
+            (high as u16) << 8 | low as u16
+        })
+    }
+
+    unsafe fn write(address: usize, value: u16) {
+        // Writing the low byte writes both bytes from the temporary register.
+        crate::sync::free(|_| {
+            write_volatile((address + 1) as *mut u8, (value >> 8) as u8);
+            write_volatile(address as *mut u8, value as u8);
+        })
+    }
+
+    fn to_bits(self) -> u16 {
+        self
+    }
+
+    fn from_bits(bits: u16) -> u16 {
+        bits
+    }
+}
+
+/// Bits of a register, from `offset` for `width` bits.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Field {
+    pub offset: u8,
+    pub width: u8,
+}
+
+impl Field {
+    /// Gives the field of a single bit.
+    pub const fn bit(offset: u8) -> Field {
+        Field { offset, width: 1 }
+    }
+
+    /// Gives the field of the bits from `start` up to `end`, not included, as the
+    /// ranges of `BitField`.
+    pub const fn range(start: u8, end: u8) -> Field {
+        Field {
+            offset: start,
+            width: end - start,
+        }
+    }
+
+    /// Gives the mask of the bits of the field in the register.
+    pub const fn mask(self) -> u16 {
+        (((1u32 << self.width) - 1) << self.offset) as u16
+    }
+}
+
+/// Register of the type `T`, u8 or u16, at an address of the data space.
+#[derive(Clone, Copy)]
+pub struct Register<T> {
+    address: usize,
+    _type: PhantomData<T>,
+}
+
+impl<T> Register<T> {
+    /// Creates a register.
+    /// # Safety
+    /// The address must be the one of a register of the type `T` on the chip.
+    pub const unsafe fn new(address: usize) -> Register<T> {
+        Register {
+            address,
+            _type: PhantomData,
+        }
+    }
+
+    /// Gives the address of the register in the data space.
+    pub const fn address(&self) -> usize {
+        self.address
+    }
+}
+
+impl<T: RegisterValue> Register<T> {
+    /// Reads the register.
+    pub fn read(&self) -> T {
+        unsafe { T::read(self.address) }
+    }
+
+    /// Writes the register.
+    pub fn write(&self, value: T) {
+        unsafe { T::write(self.address, value) }
+    }
+
+    /// Reads the register, changes the value and writes it back.
+    /// The flags cleared by writing a one, as in the TIFRn registers, must be written
+    /// with `write` instead, or all the flags which are set would be cleared.
+    /// # Arguments
+    /// * `f` - a closure, which gives the new value from the present one.
+    pub fn modify<F: FnOnce(T) -> T>(&self, f: F) {
+        self.write(f(self.read()));
+    }
+
+    /// Reads a field.
+    /// # Arguments
+    /// * `field` - a `Field` object, the bits of the register.
+    /// # Returns
+    /// * `the value of the field` - shifted down to bit 0.
+    pub fn read_field(&self, field: Field) -> T {
+        T::from_bits((self.read().to_bits() & field.mask()) >> field.offset)
+    }
+
+    /// Writes a field and keeps the other bits of the register.
+    /// # Arguments
+    /// * `field` - a `Field` object, the bits of the register.
+    /// * `value` - the value of the field, from bit 0, the bits beyond its width are dropped.
+    pub fn write_field(&self, field: Field, value: T) {
+        self.modify(|bits| {
+            let bits = bits.to_bits() & !field.mask();
+            T::from_bits(bits | (value.to_bits() << field.offset) & field.mask())
+        });
+    }
+
+    /// Sets all the bits of a field.
+    pub fn set(&self, field: Field) {
+        self.modify(|bits| T::from_bits(bits.to_bits() | field.mask()));
+    }
+
+    /// Clears all the bits of a field.
+    pub fn clear(&self, field: Field) {
+        self.modify(|bits| T::from_bits(bits.to_bits() & !field.mask()));
+    }
+
+    /// Checks whether any bit of a field is set.
+    pub fn is_set(&self, field: Field) -> bool {
+        self.read().to_bits() & field.mask() != 0
+    }
+}
+
+/// Declares the registers of a chip, each with its type, its data space address and
+/// its fields, a single bit or a range of bits as for `BitField`.
+/// ```ignore
+/// register_map! {
+///     /// Watchdog Timer Control Register.
+///     WDTCSR: u8 @ 0x60 { WDIF: 7, WDIE: 6, WDCE: 4, WDE: 3, WDP: 0..3 }
+/// }
+/// ```
+macro_rules! register_map {
+    ($(
+        $(#[$meta:meta])*
+        $name:ident : $type:ident @ $address:literal {
+            $($field:ident : $offset:literal $(.. $end:literal)?),* $(,)?
+        }
+    )*) => {
+        $(
+            $(#[$meta])*
+            pub const $name: $crate::register::Register<$type> =
+                unsafe { $crate::register::Register::new($address) };
+
+            $(#[$meta])*
+            #[allow(non_snake_case)]
+            pub mod $name {
+                $(
+                    pub const $field: $crate::register::Field =
+                        $crate::register::register_map!(@field $offset $(, $end)?);
+                )*
+            }
+        )*
+    };
+    (@field $offset:literal) => {
+        $crate::register::Field::bit($offset)
+    };
+    (@field $start:literal, $end:literal) => {
+        $crate::register::Field::range($start, $end)
+    };
+}
+
+pub(crate) use register_map;
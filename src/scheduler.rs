@@ -0,0 +1,264 @@
+// RustDuino : A generic HAL implementation for Arduino Boards in Rust
+// Copyright (C) 2021 Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Cooperative scheduler running periodic tasks from the main loop.
+//! The tasks are plain functions given in a table when the scheduler is created, each
+//! with a period in milliseconds and a priority. They are timed by the Timer0 tick of
+//! `time`, so `time::init` must be called. A task always runs to the end, and when
+//! several tasks are due, the one with the highest priority runs first.
+//! A task overruns when it is still due a whole period after it should have run, because
+//! it or the other tasks took too long. The missed runs are skipped and counted.
+
+// Source codes required.
+use crate::time::{micros, millis};
+
+/// A task of the scheduler.
+/// # Elements
+/// * `name` - a string slice, the name of the task.
+/// * `run` - a function, called each time the task runs.
+/// * `period_ms` - a u32, the time between two runs in milliseconds.
+/// * `priority` - a u8, larger numbers run first when several tasks are due.
+/// * `enabled` - a bool, false while the task does not run.
+/// * `next_run` - a u32, the time of the next run in milliseconds.
+/// * `stats` - a `TaskStats` object, the measures of the task.
+#[derive(Clone, Copy)]
+pub struct Task {
+    name: &'static str,
+    run: fn(),
+    period_ms: u32,
+    priority: u8,
+    enabled: bool,
+    next_run: u32,
+    stats: TaskStats,
+}
+
+/// Measures of a task.
+/// # Elements
+/// * `runs` - a u32, the number of runs.
+/// * `overruns` - a u32, the number of runs skipped because the task was late.
+/// * `last_duration_us` - a u32, the time taken by the last run in microseconds.
+/// * `max_duration_us` - a u32, the longest time taken by a run in microseconds.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct TaskStats {
+    pub runs: u32,
+    pub overruns: u32,
+    pub last_duration_us: u32,
+    pub max_duration_us: u32,
+}
+
+impl Task {
+    /// Creates a task, which is enabled and first runs when the scheduler starts.
+    /// # Arguments
+    /// * `name` - a string slice, the name of the task.
+    /// * `period_ms` - a u32, the time between two runs in milliseconds.
+    /// * `priority` - a u8, larger numbers run first when several tasks are due.
+    /// * `run` - a function, called each time the task runs.
+    /// # Returns
+    /// * `a Task object` - to be put in the table of the scheduler.
+    pub const fn new(name: &'static str, period_ms: u32, priority: u8, run: fn()) -> Task {
+        Task {
+            name,
+            run,
+            period_ms,
+            priority,
+            enabled: true,
+            next_run: 0,
+            stats: TaskStats {
+                runs: 0,
+                overruns: 0,
+                last_duration_us: 0,
+                max_duration_us: 0,
+            },
+        }
+    }
+
+    /// Gives the name of the task.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Gives the time between two runs in milliseconds.
+    pub fn period_ms(&self) -> u32 {
+        self.period_ms
+    }
+
+    /// Gives the priority of the task.
+    pub fn priority(&self) -> u8 {
+        self.priority
+    }
+
+    /// Checks whether the task runs.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Gives the measures of the task.
+    pub fn stats(&self) -> TaskStats {
+        self.stats
+    }
+}
+
+/// Used to run a table of `N` tasks.
+/// # Elements
+/// * `tasks` - a array of `Task` objects, the tasks, identified by their index.
+/// * `overrun_handler` - a `Option<fn(usize)>`, called with the index of a task which overruns.
+pub struct Scheduler<const N: usize> {
+    tasks: [Task; N],
+    overrun_handler: Option<fn(usize)>,
+}
+
+impl<const N: usize> Scheduler<N> {
+    /// Creates a scheduler, all the tasks are due at once.
+    /// # Arguments
+    /// * `tasks` - a array of `Task` objects, the tasks, identified by their index.
+    /// # Returns
+    /// * `a Scheduler object` - which will be used to run the tasks.
+    pub fn new(tasks: [Task; N]) -> Scheduler<N> {
+        let mut scheduler = Scheduler {
+            tasks,
+            overrun_handler: None,
+        };
+        let now = millis();
+        for task in scheduler.tasks.iter_mut() {
+            task.next_run = now;
+        }
+        scheduler
+    }
+
+    /// Sets a function called with the index of a task each time it overruns.
+    pub fn set_overrun_handler(&mut self, handler: fn(usize)) {
+        self.overrun_handler = Some(handler);
+    }
+
+    /// Gives a task.
+    /// # Arguments
+    /// * `id` - a usize, the index of the task in the table.
+    pub fn task(&self, id: usize) -> &Task {
+        &self.tasks[id]
+    }
+
+    /// Lets a task run again, it is due at once.
+    /// # Arguments
+    /// * `id` - a usize, the index of the task in the table.
+    pub fn enable(&mut self, id: usize) {
+        let task = &mut self.tasks[id];
+        if !task.enabled {
+            task.enabled = true;
+            task.next_run = millis();
+        }
+    }
+
+    /// Stops a task from running.
+    /// # Arguments
+    /// * `id` - a usize, the index of the task in the table.
+    pub fn disable(&mut self, id: usize) {
+        self.tasks[id].enabled = false;
+    }
+
+    /// Changes the period of a task, from its next run.
+    /// # Arguments
+    /// * `id` - a usize, the index of the task in the table.
+    /// * `period_ms` - a u32, the time between two runs in milliseconds.
+    pub fn set_period(&mut self, id: usize, period_ms: u32) {
+        self.tasks[id].period_ms = period_ms;
+    }
+
+    /// Changes the priority of a task.
+    /// # Arguments
+    /// * `id` - a usize, the index of the task in the table.
+    /// * `priority` - a u8, larger numbers run first.
+    pub fn set_priority(&mut self, id: usize, priority: u8) {
+        self.tasks[id].priority = priority;
+    }
+
+    /// Makes a task due at once.
+    /// # Arguments
+    /// * `id` - a usize, the index of the task in the table.
+    pub fn trigger(&mut self, id: usize) {
+        self.tasks[id].next_run = millis();
+    }
+
+    /// Clears the measures of all the tasks.
+    pub fn reset_stats(&mut self) {
+        for task in self.tasks.iter_mut() {
+            task.stats = TaskStats::default();
+        }
+    }
+
+    /// Gives the milliseconds until the next task is due, 0 if one is due now.
+    /// # Returns
+    /// * `a Option<u32>` - the time, None if no task is enabled.
+    pub fn time_to_next(&self) -> Option<u32> {
+        let now = millis();
+        self.tasks
+            .iter()
+            .filter(|task| task.enabled)
+            .map(|task| task.next_run.wrapping_sub(now) as i32)
+            .min()
+            .map(|wait| wait.max(0) as u32)
+    }
+
+    /// Runs the task with the highest priority among the tasks which are due, if any.
+    /// # Returns
+    /// * `a Option<usize>` - the index of the task which ran, None if no task was due.
+    pub fn run_pending(&mut self) -> Option<usize> {
+        let now = millis();
+        let id = self
+            .tasks
+            .iter()
+            .enumerate()
+            .filter(|(_, task)| task.enabled && now.wrapping_sub(task.next_run) as i32 >= 0)
+            // The first task of the table wins between equal priorities.
+            .fold(
+                None,
+                |best: Option<(usize, &Task)>, (id, task)| match best {
+                    Some((_, best_task)) if best_task.priority >= task.priority => best,
+                    _ => Some((id, task)),
+                },
+            )
+            .map(|(id, _)| id)?;
+
+        let start = micros();
+        (self.tasks[id].run)();
+        let duration = micros().wrapping_sub(start);
+
+        let task = &mut self.tasks[id];
+        task.stats.runs = task.stats.runs.wrapping_add(1);
+        task.stats.last_duration_us = duration;
+        task.stats.max_duration_us = task.stats.max_duration_us.max(duration);
+
+        // The runs which are already late by a whole period are skipped.
+        task.next_run = task.next_run.wrapping_add(task.period_ms);
+        let now = millis();
+        let late = now.wrapping_sub(task.next_run) as i32;
+        if task.period_ms != 0 && late >= 0 && late as u32 >= task.period_ms {
+            let missed = late as u32 / task.period_ms;
+            task.stats.overruns = task.stats.overruns.wrapping_add(missed);
+            task.next_run = now;
+            if let Some(handler) = self.overrun_handler {
+                handler(id);
+            }
+        }
+        Some(id)
+    }
+
+    /// Runs the tasks for ever.
+    pub fn run(&mut self) -> ! {
+        loop {
+            self.run_pending();
+        }
+    }
+}
@@ -16,154 +16,141 @@
 
 //! This code implements the I2C protocol to control the AHT10
 //! sensor which could be used to read the temperature and
-//! humidity and stored in a sliced vector which could be given
+//! humidity and stored in a buffer which could be given
 //! as an output.
 
-use crate::com::i2c;
+use crate::bus::{I2cDevice, SharedTwi};
+use crate::common::TwiOps;
 use crate::delay::delay_ms;
-use fixed_slice_vec::FixedSliceVec;
+use crate::error::Error;
 
 /// Used to control the AHT10 Arduino sensor
 /// # Elements
-/// * `address` - a u8, used to store the address to control the functioning AHT10 sensor.
-/// * `i2c` - a `twi` struct object, which would be used to interface the functioning of sensor with communication protocol I2C.
-/// * `vec` - a vector with u8 objects, It would be used to store the data read through the sensors.
-#[repr(C, packed)]
-pub struct AHT10<'a> {
-    address: u8,
-    i2c: i2c::Twi,
-    vec: FixedSliceVec<'a, u8>,
+/// * `device` - a `I2cDevice` object, the handle of the sensor on the I2C bus.
+/// * `data` - an array of 6 u8, It would be used to store the status and the data read through the sensors.
+pub struct AHT10<T = SharedTwi> {
+    device: I2cDevice<T>,
+    data: [u8; 6],
 }
 
 // Constant values for AHT10 temperature and humity sensor.
+const AHT10_ADDRESS: u8 = 0x38; //I2C address of the sensor
+const AHT10_I2C_FREQUENCY: u32 = 100_000;
 const AHT10_INIT_CMD: u8 = 0xE1; //initialization command for AHT10/AHT15
 const AHT10_START_MEASURMENT_CMD: u8 = 0xAC; //start measurment command
 const AHT10_SOFT_RESET_CMD: u8 = 0xBA; //soft reset command
 const AHT10_INIT_CAL_ENABLE: u8 = 0x08; //load factory calibration coeff
-const AHT10_INIT_BUSY: u8 = 0x08; //Status bit for busy
+const AHT10_INIT_BUSY: u8 = 0x80; //Status bit for busy
 
-impl<'a> AHT10<'a> {
-    /// Creates a new memory mapped IO at the correct location including a 20ms reset delay for wake-up.
+impl AHT10 {
+    /// Creates the sensor on the TWI of the chip, including a 20ms reset delay for wake-up.
     /// # Returns
-    /// * `a reference to AHT10 structure` - Which would be used to control the sensor.
-    pub fn new(&mut self) -> &'static mut Self {
+    /// * `a Result<AHT10, Error>` - Which would be used to control the sensor,
+    /// or the error of the bus, `Error::Device` if the sensor could not be initialised.
+    pub fn new() -> Result<AHT10, Error> {
+        AHT10::with_bus(SharedTwi::new())
+    }
+}
+
+impl<T: TwiOps> AHT10<T> {
+    /// Creates the sensor on the given I2C bus, including a 20ms reset delay for wake-up.
+    /// # Arguments
+    /// * `bus` - a `TwiOps` object, the I2C bus of the sensor.
+    /// # Returns
+    /// * `a Result<AHT10, Error>` - Which would be used to control the sensor,
+    /// or the error of the bus, `Error::Device` if the sensor could not be initialised.
+    pub fn with_bus(bus: T) -> Result<AHT10<T>, Error> {
+        let mut sensor = AHT10 {
+            device: I2cDevice::with_bus(bus, AHT10_ADDRESS, AHT10_I2C_FREQUENCY),
+            data: [0; 6],
+        };
         delay_ms(20);
 
-        self.soft_reset();
+        sensor.soft_reset()?;
+        delay_ms(20);
 
-        unsafe {
-            if !self.initialise() {
-                unreachable!();
-            }
+        if !sensor.initialise()? {
+            return Err(Error::Device);
         }
-        unsafe { &mut *(0x38 as *mut Self) }
-    }
-
-    /// Returns reference to the structure without any reset delay.
-    /// # Returns
-    /// * `a reference to AHT10 structure` - Which would be used to control the sensor.
-    pub fn get() -> &'static mut Self {
-        unsafe { &mut *(0x38 as *mut Self) }
+        Ok(sensor)
     }
 
     /// Initiates the transmission by self initiating the sensor.
     /// # Returns
-    /// * `a boolean` - Which is true if transmission initiated otherwise false.
-    pub unsafe fn initialise(&mut self) -> bool {
-        self.vec.clear();
-        self.vec.push(AHT10_INIT_CMD);
-        self.vec.push(0x33);
-        self.vec.push(0x00);
-
-        if !self.i2c.write_to_slave(self.address, &self.vec) {
-            unreachable!();
-        }
-        self.wait_for_idle();
-        if !(self.status() == 0 && AHT10_INIT_CAL_ENABLE == 0) {
-            return false;
-        }
-        return true;
+    /// * `a Result<bool, Error>` - Which is true if the sensor is calibrated otherwise false, or the error of the bus.
+    pub fn initialise(&mut self) -> Result<bool, Error> {
+        self.device.write(&[AHT10_INIT_CMD, 0x08, 0x00])?;
+        self.wait_for_idle()?;
+        Ok(self.status()? & AHT10_INIT_CAL_ENABLE != 0)
     }
 
     /// Restart sensor, without power off in around ~20ms with all registers restored to default.
-    pub fn soft_reset(&mut self) {
-        self.vec.clear();
-        self.vec.push(AHT10_SOFT_RESET_CMD);
-
-        if !self.i2c.write_to_slave(self.address, &self.vec) {
-            unreachable!()
-        }
+    /// # Returns
+    /// * `a Result<(), Error>` - Which is an error if the command could not be sent.
+    pub fn soft_reset(&mut self) -> Result<(), Error> {
+        self.device.write(&[AHT10_SOFT_RESET_CMD])
     }
 
-    /// Reads data from slave mode using the I2C protocol.
-    pub unsafe fn read_to_buffer(&mut self) {
-        if !self
-            .i2c
-            .read_from_slave(self.address, self.vec.len(), &mut self.vec)
-        {
-            unreachable!();
-        }
+    /// Reads the status and the data from the sensor using the I2C protocol.
+    /// # Returns
+    /// * `a Result<(), Error>` - Which is an error if the data could not be read.
+    pub fn read_to_buffer(&mut self) -> Result<(), Error> {
+        self.device.read(&mut self.data)
     }
 
     /// Triggers the AHT10 to read temperature/humidity.
-    pub unsafe fn trigger_slave(&mut self) {
-        self.vec.clear();
-        self.vec.push(AHT10_START_MEASURMENT_CMD);
-        self.vec.push(0x33);
-        self.vec.push(0x00);
-
-        if !self.i2c.write_to_slave(self.address, &self.vec) {
-            unreachable!();
-        }
+    /// # Returns
+    /// * `a Result<(), Error>` - Which is an error if the command could not be sent.
+    pub fn trigger_slave(&mut self) -> Result<(), Error> {
+        self.device.write(&[AHT10_START_MEASURMENT_CMD, 0x33, 0x00])
     }
 
-    /// Adds a delay of 5ms when the sensor is already busy with some processing.
-    pub unsafe fn wait_for_idle(&mut self) {
-        while (self.status() == 0 && AHT10_INIT_BUSY == 0) == true {
+    /// Adds a delay of 5ms while the sensor is busy with some processing.
+    /// # Returns
+    /// * `a Result<(), Error>` - Which is an error if the status could not be read.
+    pub fn wait_for_idle(&mut self) -> Result<(), Error> {
+        while self.status()? & AHT10_INIT_BUSY != 0 {
             delay_ms(5);
         }
+        Ok(())
     }
 
     /// Performs measurement of temperature using the functions `trigger_slave()` and `read_to_buffer()`.
-    pub unsafe fn perform_measurement(&mut self) {
-        self.trigger_slave();
-        self.wait_for_idle();
-        self.read_to_buffer();
+    /// # Returns
+    /// * `a Result<(), Error>` - Which is an error if the measurement could not be read.
+    pub fn perform_measurement(&mut self) -> Result<(), Error> {
+        self.trigger_slave()?;
+        self.wait_for_idle()?;
+        self.read_to_buffer()
     }
 
-    /// Reads value returned by the slave.
+    /// Reads the status byte of the sensor.
     /// # Returns
-    /// * `a u8` - The read value.
-    pub unsafe fn status(&mut self) -> u8 {
-        self.read_to_buffer();
-        return self.vec[0];
+    /// * `a Result<u8, Error>` - The read value, or the error of the bus.
+    pub fn status(&mut self) -> Result<u8, Error> {
+        self.read_to_buffer()?;
+        Ok(self.data[0])
     }
 
     /// Reads 20 bit raw humidity data.
     /// # Returns
-    /// * `a f64` - The relative humidity in percentage.
-    pub fn relative_humidity(&mut self) -> f64 {
-        unsafe {
-            self.perform_measurement();
-            let mut humid: f64 = (((self.vec[1] as u32) << 12)
-                | ((self.vec[2] as u32) << 4)
-                | ((self.vec[3] as u32) >> 4)) as f64;
-            humid = (humid * 100.0) / 0x100000 as f64;
-            return humid;
-        }
+    /// * `a Result<f64, Error>` - The relative humidity in percentage, or the error of the bus.
+    pub fn relative_humidity(&mut self) -> Result<f64, Error> {
+        self.perform_measurement()?;
+        let humid: f64 = (((self.data[1] as u32) << 12)
+            | ((self.data[2] as u32) << 4)
+            | ((self.data[3] as u32) >> 4)) as f64;
+        Ok((humid * 100.0) / 0x100000 as f64)
     }
 
     /// Reads 20 bit raw temperature data.
     /// # Returns
-    /// * `a f64` - The temperature in degree celsius.
-    pub fn temperature(&mut self) -> f64 {
-        unsafe {
-            self.perform_measurement();
-            let mut temp: f64 = ((((self.vec[3] as u32) & 0xF) << 16)
-                | (self.vec[4] as u32) << 8
-                | (self.vec[5]) as u32) as f64;
-            temp = ((temp as f64 * 200.0) / 0x100000 as f64) - 50.0;
-            return temp;
-        }
+    /// * `a Result<f64, Error>` - The temperature in degree celsius, or the error of the bus.
+    pub fn temperature(&mut self) -> Result<f64, Error> {
+        self.perform_measurement()?;
+        let temp: f64 = ((((self.data[3] as u32) & 0xF) << 16)
+            | (self.data[4] as u32) << 8
+            | (self.data[5]) as u32) as f64;
+        Ok(((temp * 200.0) / 0x100000 as f64) - 50.0)
     }
 }
@@ -0,0 +1,357 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! This code converts the readings of the analog sensors which are read with the ADC.
+//! `Thermistor` gives the temperature of a NTC thermistor in a voltage divider with the
+//! Steinhart-Hart equation, 1/T = A + B ln(R) + C ln(R)^3, computed in fixed point.
+//! The coefficients are given by the maker of the thermistor, or found from its beta value.
+//! `LinearTemperature` gives the temperature of the LM35 and TMP36 sensors, whose output
+//! rises by 10 mV per degree.
+//! `Acs712` gives the current through a ACS712 Hall effect sensor, whose output is at half
+//! of the supply with no current, which is measured by `calibrate_zero`.
+//! Each reading is the average of `SAMPLES` conversions, and the temperatures are given in
+//! hundredths of degree Celsius, as the other sensors of the library.
+
+// Source codes required.
+use crate::error::Error;
+use crate::hal::analog::Adc;
+
+/// Number of conversions averaged by a reading.
+pub const SAMPLES: u16 = 8;
+
+/// Largest result of the 10 bit ADC.
+const ADC_MAX: u32 = 1023;
+
+/// ln(2) in fixed point with 30 fractional bits.
+const LN2_Q30: i64 = 744_261_118;
+
+/// 0 degree Celsius in hundredths of kelvin.
+const ZERO_CELSIUS: i64 = 27_315;
+
+/// Used to read a NTC thermistor in a voltage divider with a fixed resistor.
+/// # Elements
+/// * `channel` - a u8, the ADC channel of the middle of the divider.
+/// * `series_ohms` - a u32, the resistance of the fixed resistor.
+/// * `high_side` - a bool, true if the thermistor is between the supply and the pin.
+/// * `a` - a i64, the coefficient A of the Steinhart-Hart equation, times 10^12.
+/// * `b` - a i64, the coefficient B of the Steinhart-Hart equation, times 10^12.
+/// * `c` - a i64, the coefficient C of the Steinhart-Hart equation, times 10^12.
+pub struct Thermistor {
+    channel: u8,
+    series_ohms: u32,
+    high_side: bool,
+    a: i64,
+    b: i64,
+    c: i64,
+}
+
+impl Thermistor {
+    /// Creates a thermistor from its resistance at 25 degree Celsius and its beta value,
+    /// which gives A = 1/T0 - ln(R0)/beta, B = 1/beta and C = 0. The thermistor is
+    /// between the pin and the ground.
+    /// # Arguments
+    /// * `channel` - a u8, the ADC channel of the middle of the divider.
+    /// * `series_ohms` - a u32, the resistance of the fixed resistor.
+    /// * `nominal_ohms` - a u32, the resistance of the thermistor at 25 degree Celsius.
+    /// * `beta` - a u16, the beta value of the thermistor in kelvin, as 3950.
+    /// # Returns
+    /// * `a Thermistor object` - which will be used to read the temperature.
+    pub fn new(channel: u8, series_ohms: u32, nominal_ohms: u32, beta: u16) -> Thermistor {
+        let beta = beta.max(1) as i64;
+        // 10^14 / 29815 is 1/T0 times 10^12, with T0 in hundredths of kelvin.
+        let inverse_t0 = 100_000_000_000_000 / (ZERO_CELSIUS + 2500);
+        let ln_r0 = ln_q16(nominal_ohms.max(1)) as i64;
+        Thermistor::with_coefficients(
+            channel,
+            series_ohms,
+            inverse_t0 - ln_r0 * 1_000_000_000_000 / (beta << 16),
+            1_000_000_000_000 / beta,
+            0,
+        )
+    }
+
+    /// Creates a thermistor from the coefficients of the Steinhart-Hart equation, each
+    /// times 10^12, so 1.129148e-3, 2.34125e-4 and 8.76741e-8 are given as 1_129_148_000,
+    /// 234_125_000 and 87_674. The thermistor is between the pin and the ground.
+    /// # Arguments
+    /// * `channel` - a u8, the ADC channel of the middle of the divider.
+    /// * `series_ohms` - a u32, the resistance of the fixed resistor.
+    /// * `a` - a i64, the coefficient A times 10^12.
+    /// * `b` - a i64, the coefficient B times 10^12.
+    /// * `c` - a i64, the coefficient C times 10^12.
+    /// # Returns
+    /// * `a Thermistor object` - which will be used to read the temperature.
+    pub fn with_coefficients(channel: u8, series_ohms: u32, a: i64, b: i64, c: i64) -> Thermistor {
+        Thermistor {
+            channel,
+            series_ohms,
+            high_side: false,
+            a,
+            b,
+            c,
+        }
+    }
+
+    /// Sets whether the thermistor is between the supply and the pin, with the fixed
+    /// resistor to the ground, instead of between the pin and the ground.
+    pub fn set_high_side(&mut self, high_side: bool) {
+        self.high_side = high_side;
+    }
+
+    /// Gives the resistance of the thermistor from a result of the ADC.
+    /// The divider and the ADC both use the supply, so its voltage does not matter.
+    /// # Arguments
+    /// * `raw` - a u16, the 10 bit result of the ADC.
+    /// # Returns
+    /// * `a Result<u32, Error>` - the resistance in ohms, or `Error::Overflow` if the
+    ///   thermistor is open or shorted.
+    pub fn resistance(&self, raw: u16) -> Result<u32, Error> {
+        let raw = raw as u32;
+        if raw == 0 || raw >= ADC_MAX {
+            return Err(Error::Overflow);
+        }
+        let (top, bottom) = if self.high_side {
+            (ADC_MAX - raw, raw)
+        } else {
+            (raw, ADC_MAX - raw)
+        };
+        Ok((self.series_ohms as u64 * top as u64 / bottom as u64) as u32)
+    }
+
+    /// Gives the temperature of the thermistor from its resistance.
+    /// # Arguments
+    /// * `ohms` - a u32, the resistance of the thermistor.
+    /// # Returns
+    /// * `a Result<i32, Error>` - the temperature in hundredths of degree Celsius, or
+    ///   `Error::Overflow` if it is out of the range of the equation.
+    pub fn temperature_from_resistance(&self, ohms: u32) -> Result<i32, Error> {
+        if ohms == 0 {
+            return Err(Error::Overflow);
+        }
+        let ln_r = ln_q16(ohms) as i64;
+        let ln_r3 = ((ln_r * ln_r) >> 16) * ln_r >> 16;
+        // 1/T in kelvin, times 10^12.
+        let inverse_t = self.a + ((self.b * ln_r) >> 16) + ((self.c * ln_r3) >> 16);
+        if inverse_t <= 0 {
+            return Err(Error::Overflow);
+        }
+        Ok((100_000_000_000_000 / inverse_t - ZERO_CELSIUS) as i32)
+    }
+
+    /// Reads the temperature of the thermistor.
+    /// # Arguments
+    /// * `adc` - a `Adc` object, with the supply as reference.
+    /// # Returns
+    /// * `a Result<i32, Error>` - the temperature in hundredths of degree Celsius,
+    ///   `Error::Overflow` if the thermistor is open or shorted, or `Error::InvalidParam`
+    ///   if the channel does not exist.
+    pub fn read(&self, adc: &mut Adc) -> Result<i32, Error> {
+        let ohms = self.resistance(sample(adc, self.channel)?)?;
+        self.temperature_from_resistance(ohms)
+    }
+}
+
+/// Used to read a temperature sensor whose output rises by 10 mV per degree Celsius.
+/// # Elements
+/// * `channel` - a u8, the ADC channel of the output of the sensor.
+/// * `offset_mv` - a u16, the output at 0 degree Celsius in millivolts.
+/// * `reference_mv` - a u16, the reference voltage of the ADC in millivolts.
+pub struct LinearTemperature {
+    channel: u8,
+    offset_mv: u16,
+    reference_mv: u16,
+}
+
+impl LinearTemperature {
+    /// Creates a LM35, whose output is 0 V at 0 degree Celsius. The reference is a 5 V supply.
+    /// # Arguments
+    /// * `channel` - a u8, the ADC channel of the output of the sensor.
+    /// # Returns
+    /// * `a LinearTemperature object` - which will be used to read the temperature.
+    pub fn lm35(channel: u8) -> LinearTemperature {
+        LinearTemperature {
+            channel,
+            offset_mv: 0,
+            reference_mv: 5000,
+        }
+    }
+
+    /// Creates a TMP36, whose output is 500 mV at 0 degree Celsius, so it can measure
+    /// down to -40 degree Celsius. The reference is a 5 V supply.
+    /// # Arguments
+    /// * `channel` - a u8, the ADC channel of the output of the sensor.
+    /// # Returns
+    /// * `a LinearTemperature object` - which will be used to read the temperature.
+    pub fn tmp36(channel: u8) -> LinearTemperature {
+        LinearTemperature {
+            channel,
+            offset_mv: 500,
+            reference_mv: 5000,
+        }
+    }
+
+    /// Sets the reference voltage of the ADC, as the supply measured by `Adc::read_vcc`, or
+    /// 1100 with the internal reference, which gives a finer reading up to 110 degree Celsius.
+    /// # Arguments
+    /// * `reference_mv` - a u16, the reference voltage in millivolts.
+    pub fn set_reference_mv(&mut self, reference_mv: u16) {
+        self.reference_mv = reference_mv;
+    }
+
+    /// Gives the temperature from a result of the ADC.
+    /// # Arguments
+    /// * `raw` - a u16, the 10 bit result of the ADC.
+    /// # Returns
+    /// * `a i32` - the temperature in hundredths of degree Celsius.
+    pub fn temperature_from_raw(&self, raw: u16) -> i32 {
+        let microvolts = raw as i64 * self.reference_mv as i64 * 1000 / (ADC_MAX as i64 + 1);
+        ((microvolts - self.offset_mv as i64 * 1000) / 100) as i32
+    }
+
+    /// Reads the temperature of the sensor.
+    /// # Arguments
+    /// * `adc` - a `Adc` object, with the reference given by `set_reference_mv`.
+    /// # Returns
+    /// * `a Result<i32, Error>` - the temperature in hundredths of degree Celsius, or
+    ///   `Error::InvalidParam` if the channel does not exist.
+    pub fn read(&self, adc: &mut Adc) -> Result<i32, Error> {
+        Ok(self.temperature_from_raw(sample(adc, self.channel)?))
+    }
+}
+
+/// Ranges of the ACS712, with their sensitivity.
+/// * `A5` - up to 5 A, 185 mV/A.
+/// * `A20` - up to 20 A, 100 mV/A.
+/// * `A30` - up to 30 A, 66 mV/A.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Acs712Range {
+    A5,
+    A20,
+    A30,
+}
+
+impl Acs712Range {
+    /// Gives the change of the output for one ampere.
+    /// # Returns
+    /// * `a u16` - the sensitivity in millivolts per ampere.
+    pub fn millivolts_per_amp(&self) -> u16 {
+        match self {
+            Acs712Range::A5 => 185,
+            Acs712Range::A20 => 100,
+            Acs712Range::A30 => 66,
+        }
+    }
+}
+
+/// Used to read a ACS712 current sensor.
+/// # Elements
+/// * `channel` - a u8, the ADC channel of the output of the sensor.
+/// * `range` - a `Acs712Range` object, the version of the sensor.
+/// * `reference_mv` - a u16, the reference voltage of the ADC in millivolts.
+/// * `zero` - a u16, the result of the ADC with no current, times `SAMPLES`.
+pub struct Acs712 {
+    channel: u8,
+    range: Acs712Range,
+    reference_mv: u16,
+    zero: u16,
+}
+
+impl Acs712 {
+    /// Creates a sensor with its output at half of the reference for no current, until
+    /// `calibrate_zero` is called. The reference is a 5 V supply.
+    /// # Arguments
+    /// * `channel` - a u8, the ADC channel of the output of the sensor.
+    /// * `range` - a `Acs712Range` object, the version of the sensor.
+    /// # Returns
+    /// * `a Acs712 object` - which will be used to read the current.
+    pub fn new(channel: u8, range: Acs712Range) -> Acs712 {
+        Acs712 {
+            channel,
+            range,
+            reference_mv: 5000,
+            zero: 512 * SAMPLES,
+        }
+    }
+
+    /// Sets the reference voltage of the ADC, as the supply measured by `Adc::read_vcc`.
+    /// # Arguments
+    /// * `reference_mv` - a u16, the reference voltage in millivolts.
+    pub fn set_reference_mv(&mut self, reference_mv: u16) {
+        self.reference_mv = reference_mv;
+    }
+
+    /// Measures the output of the sensor with no current, which is off from half of the
+    /// supply by some tens of millivolts. No current must flow while this is done.
+    /// # Arguments
+    /// * `adc` - a `Adc` object, with the supply as reference.
+    /// # Returns
+    /// * `a Result<(), Error>` - which is `Error::InvalidParam` if the channel does not exist.
+    pub fn calibrate_zero(&mut self, adc: &mut Adc) -> Result<(), Error> {
+        let mut sum: u32 = 0;
+        for _ in 0..SAMPLES {
+            sum += adc.read_channel(self.channel)? as u32;
+        }
+        self.zero = sum as u16;
+        Ok(())
+    }
+
+    /// Gives the result of the ADC with no current, found by `calibrate_zero`.
+    pub fn zero(&self) -> u16 {
+        self.zero / SAMPLES
+    }
+
+    /// Reads the current through the sensor.
+    /// # Arguments
+    /// * `adc` - a `Adc` object, with the supply as reference.
+    /// # Returns
+    /// * `a Result<i32, Error>` - the current in milliamperes, negative when it flows from
+    ///   IP- to IP+, or `Error::InvalidParam` if the channel does not exist.
+    pub fn read_milliamps(&self, adc: &mut Adc) -> Result<i32, Error> {
+        let mut sum: i64 = 0;
+        for _ in 0..SAMPLES {
+            sum += adc.read_channel(self.channel)? as i64;
+        }
+        let microvolts = (sum - self.zero as i64) * self.reference_mv as i64 * 1000
+            / ((ADC_MAX as i64 + 1) * SAMPLES as i64);
+        Ok((microvolts / self.range.millivolts_per_amp() as i64) as i32)
+    }
+}
+
+// Gives the average of `SAMPLES` conversions of a channel.
+fn sample(adc: &mut Adc, channel: u8) -> Result<u16, Error> {
+    let mut sum: u32 = 0;
+    for _ in 0..SAMPLES {
+        sum += adc.read_channel(channel)? as u32;
+    }
+    Ok((sum / SAMPLES as u32) as u16)
+}
+
+// Gives the natural logarithm of a number in fixed point with 16 fractional bits.
+// With x = m 2^k and m from 1 to 2, ln(x) = k ln(2) + ln(m), and ln(m) is the series
+// 2 (y + y^3/3 + y^5/5 + ...) with y = (m - 1)/(m + 1), which is at most 1/3.
+fn ln_q16(x: u32) -> i32 {
+    let k = 31 - x.leading_zeros() as i64;
+    let m = ((x as i64) << 30) >> k;
+    let y = ((m - (1 << 30)) << 30) / (m + (1 << 30));
+    let y2 = (y * y) >> 30;
+    let mut term = y;
+    let mut sum = y;
+    for n in [3, 5, 7, 9].iter() {
+        term = (term * y2) >> 30;
+        sum += term / n;
+    }
+    ((k * LN2_Q30 + 2 * sum) >> 14) as i32
+}
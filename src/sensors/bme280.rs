@@ -0,0 +1,521 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! This code controls the Bosch BME280 pressure, temperature and humidity sensor and
+//! the BMP280, which is the same sensor without humidity.
+//! The sensor is connected over I2C, at address 0x76 or 0x77, or over SPI with any
+//! digital pin as chip select. The raw readings are turned into units with the integer
+//! compensation formulas of the datasheet and the calibration stored in each sensor.
+//! In the forced mode the sensor measures once for each reading and sleeps in between,
+//! which suits slow weather stations, while in the normal mode it measures continuously.
+//! See `<https://www.bosch-sensortec.com/media/boschsensortec/downloads/datasheets/bst-bme280-ds002.pdf>`.
+
+// Source codes required.
+use crate::bus::{I2cDevice, SharedSpi, SharedTwi, SpiDevice};
+use crate::common::{SpiOps, TwiOps};
+use crate::delay::delay_ms;
+use crate::error::Error;
+
+/// I2C address with the SDO pin to ground, 0x77 with SDO to VDDIO.
+pub const BME280_I2C_ADDRESS: u8 = 0x76;
+
+// Registers.
+const REG_CALIB_00: u8 = 0x88;
+const REG_ID: u8 = 0xD0;
+const REG_RESET: u8 = 0xE0;
+const REG_CALIB_26: u8 = 0xE1;
+const REG_CTRL_HUM: u8 = 0xF2;
+const REG_STATUS: u8 = 0xF3;
+const REG_CTRL_MEAS: u8 = 0xF4;
+const REG_CONFIG: u8 = 0xF5;
+const REG_DATA: u8 = 0xF7;
+
+// Values of the ID register.
+const ID_BMP280: u8 = 0x58;
+const ID_BME280: u8 = 0x60;
+const RESET_WORD: u8 = 0xB6;
+
+// Bits of the status register.
+const STATUS_MEASURING: u8 = 0x08;
+const STATUS_IM_UPDATE: u8 = 0x01;
+
+/// Errors which can happen while using the sensor.
+/// * `NotFound` - no BME280 or BMP280 answered.
+/// * `Bus` - an I2C transfer failed.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Bme280Error {
+    NotFound,
+    Bus,
+}
+
+impl From<Bme280Error> for Error {
+    fn from(error: Bme280Error) -> Error {
+        match error {
+            Bme280Error::NotFound => Error::NotFound,
+            Bme280Error::Bus => Error::Bus,
+        }
+    }
+}
+
+/// Kind of sensor found.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Bme280Chip {
+    BMP280,
+    BME280,
+}
+
+/// Number of samples averaged for one measurement, more gives less noise but takes longer.
+/// `Skipped` turns the measurement off.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Bme280Oversampling {
+    Skipped,
+    X1,
+    X2,
+    X4,
+    X8,
+    X16,
+}
+
+/// Operating mode of the sensor.
+/// * `Sleep` - no measurements.
+/// * `Forced` - one measurement for each reading, then sleep.
+/// * `Normal` - measurements repeated after each standby time.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Bme280Mode {
+    Sleep,
+    Forced,
+    Normal,
+}
+
+/// Coefficient of the IIR filter, which smooths quick changes of pressure like
+/// those from wind or slamming doors.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Bme280Filter {
+    Off,
+    X2,
+    X4,
+    X8,
+    X16,
+}
+
+/// Time between measurements in the normal mode, in milliseconds.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Bme280Standby {
+    Ms0_5,
+    Ms10,
+    Ms20,
+    Ms62_5,
+    Ms125,
+    Ms250,
+    Ms500,
+    Ms1000,
+}
+
+/// One measurement of the sensor.
+/// # Elements
+/// * `temperature` - a i32, the temperature in hundredths of degree Celsius.
+/// * `pressure` - a u32, the pressure in pascal.
+/// * `humidity` - a `Option<u32>`, the relative humidity in hundredths of percent,
+///   None for the BMP280 or when skipped.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Bme280Reading {
+    pub temperature: i32,
+    pub pressure: u32,
+    pub humidity: Option<u32>,
+}
+
+/// Calibration coefficients stored in the sensor at the factory.
+#[derive(Clone, Copy, Default)]
+struct Calibration {
+    t1: u16,
+    t2: i16,
+    t3: i16,
+    p1: u16,
+    p2: i16,
+    p3: i16,
+    p4: i16,
+    p5: i16,
+    p6: i16,
+    p7: i16,
+    p8: i16,
+    p9: i16,
+    h1: u8,
+    h2: i16,
+    h3: u8,
+    h4: i16,
+    h5: i16,
+    h6: i8,
+}
+
+// Highest clock frequencies of the sensor.
+const I2C_FREQUENCY: u32 = 400_000;
+const SPI_FREQUENCY: u32 = 10_000_000;
+
+/// Connection between the microcontroller and the sensor.
+enum Bus<I, S> {
+    I2c(I2cDevice<I>),
+    Spi(SpiDevice<S>),
+}
+
+/// Used to control a BME280 or BMP280 sensor.
+/// # Elements
+/// * `bus` - a `Bus` object, the handle of the sensor on the I2C or SPI bus.
+/// * `chip` - a `Bme280Chip` object, the kind of sensor.
+/// * `calibration` - a `Calibration` object, the coefficients of the sensor.
+/// * `ctrl_meas` - a u8, the value of the measurement control register.
+/// * `humidity` - a boolean, true if the humidity is measured.
+pub struct BME280<I = SharedTwi, S = SharedSpi> {
+    bus: Bus<I, S>,
+    chip: Bme280Chip,
+    calibration: Calibration,
+    ctrl_meas: u8,
+    humidity: bool,
+}
+
+impl BME280 {
+    /// Creates a sensor connected to the TWI of the chip and initializes it.
+    /// # Arguments
+    /// * `address` - a u8, `BME280_I2C_ADDRESS` or 0x77.
+    /// # Returns
+    /// * `a Result<BME280, Bme280Error>` - the sensor, or the error if it was not found.
+    pub fn new_i2c(address: u8) -> Result<BME280, Bme280Error> {
+        BME280::with_i2c(SharedTwi::new(), address)
+    }
+
+    /// Creates a sensor connected to the SPI of the chip and initializes it.
+    /// # Arguments
+    /// * `cs` - a u8, the digital pin connected to CSB.
+    /// # Returns
+    /// * `a Result<BME280, Bme280Error>` - the sensor, or the error if it was not found.
+    pub fn new_spi(cs: u8) -> Result<BME280, Bme280Error> {
+        BME280::with_spi(SharedSpi::new(), cs)
+    }
+}
+
+impl<I: TwiOps> BME280<I> {
+    /// Creates a sensor connected to the given I2C bus and initializes it.
+    /// # Arguments
+    /// * `bus` - a `TwiOps` object, the I2C bus of the sensor.
+    /// * `address` - a u8, `BME280_I2C_ADDRESS` or 0x77.
+    /// # Returns
+    /// * `a Result<BME280, Bme280Error>` - the sensor, or the error if it was not found.
+    pub fn with_i2c(bus: I, address: u8) -> Result<BME280<I>, Bme280Error> {
+        BME280::init(Bus::I2c(I2cDevice::with_bus(bus, address, I2C_FREQUENCY)))
+    }
+}
+
+impl<S: SpiOps> BME280<SharedTwi, S> {
+    /// Creates a sensor connected to the given SPI bus and initializes it.
+    /// # Arguments
+    /// * `bus` - a `SpiOps` object, the SPI bus of the sensor.
+    /// * `cs` - a u8, the digital pin connected to CSB.
+    /// # Returns
+    /// * `a Result<BME280, Bme280Error>` - the sensor, or the error if it was not found.
+    pub fn with_spi(bus: S, cs: u8) -> Result<BME280<SharedTwi, S>, Bme280Error> {
+        let mut device = SpiDevice::with_bus(bus, cs, 0, false, SPI_FREQUENCY);
+        device.init();
+        BME280::init(Bus::Spi(device))
+    }
+}
+
+impl<I: TwiOps, S: SpiOps> BME280<I, S> {
+    /// Resets the sensor, reads its calibration and sets the forced mode with
+    /// one sample for each measurement and no filter.
+    fn init(bus: Bus<I, S>) -> Result<BME280<I, S>, Bme280Error> {
+        let mut sensor = BME280 {
+            bus,
+            chip: Bme280Chip::BME280,
+            calibration: Calibration::default(),
+            ctrl_meas: 0,
+            humidity: true,
+        };
+
+        sensor.chip = match sensor.read_register(REG_ID)? {
+            ID_BME280 => Bme280Chip::BME280,
+            ID_BMP280 => Bme280Chip::BMP280,
+            _ => return Err(Bme280Error::NotFound),
+        };
+        sensor.humidity = sensor.chip == Bme280Chip::BME280;
+
+        sensor.write_register(REG_RESET, RESET_WORD)?;
+        delay_ms(2);
+        // The calibration is copied from the NVM after the reset.
+        while sensor.read_register(REG_STATUS)? & STATUS_IM_UPDATE != 0 {
+            delay_ms(1);
+        }
+        sensor.read_calibration()?;
+
+        sensor.set_oversampling(
+            Bme280Oversampling::X1,
+            Bme280Oversampling::X1,
+            Bme280Oversampling::X1,
+        )?;
+        sensor.set_mode(Bme280Mode::Forced)?;
+        Ok(sensor)
+    }
+
+    /// Gives the kind of sensor.
+    pub fn chip(&self) -> Bme280Chip {
+        self.chip
+    }
+
+    /// Sets the oversampling of each measurement. The humidity is ignored on the BMP280.
+    /// # Arguments
+    /// * `temperature` - a `Bme280Oversampling` object, which should not be skipped since the
+    ///   other measurements are compensated with the temperature.
+    /// * `pressure` - a `Bme280Oversampling` object.
+    /// * `humidity` - a `Bme280Oversampling` object.
+    pub fn set_oversampling(
+        &mut self,
+        temperature: Bme280Oversampling,
+        pressure: Bme280Oversampling,
+        humidity: Bme280Oversampling,
+    ) -> Result<(), Bme280Error> {
+        if self.chip == Bme280Chip::BME280 {
+            // The humidity setting only takes effect after the measurement control register is written.
+            self.write_register(REG_CTRL_HUM, humidity as u8)?;
+            self.humidity = humidity != Bme280Oversampling::Skipped;
+        }
+        self.ctrl_meas = (temperature as u8) << 5 | (pressure as u8) << 2 | (self.ctrl_meas & 0x03);
+        self.write_register(REG_CTRL_MEAS, self.ctrl_meas)
+    }
+
+    /// Sets the operating mode.
+    pub fn set_mode(&mut self, mode: Bme280Mode) -> Result<(), Bme280Error> {
+        let bits = match mode {
+            Bme280Mode::Sleep => 0b00,
+            Bme280Mode::Forced => 0b01,
+            Bme280Mode::Normal => 0b11,
+        };
+        self.ctrl_meas = (self.ctrl_meas & !0x03) | bits;
+        self.write_register(REG_CTRL_MEAS, self.ctrl_meas)
+    }
+
+    /// Sets the IIR filter and the standby time of the normal mode.
+    /// The sensor should be in the sleep mode, since the register may be ignored otherwise.
+    pub fn set_config(
+        &mut self,
+        filter: Bme280Filter,
+        standby: Bme280Standby,
+    ) -> Result<(), Bme280Error> {
+        let t_sb = match standby {
+            Bme280Standby::Ms0_5 => 0,
+            Bme280Standby::Ms62_5 => 1,
+            Bme280Standby::Ms125 => 2,
+            Bme280Standby::Ms250 => 3,
+            Bme280Standby::Ms500 => 4,
+            Bme280Standby::Ms1000 => 5,
+            Bme280Standby::Ms10 => 6,
+            Bme280Standby::Ms20 => 7,
+        };
+        self.write_register(REG_CONFIG, t_sb << 5 | (filter as u8) << 2)
+    }
+
+    /// Reads the temperature, pressure and humidity. In the forced mode a measurement is
+    /// started and waited for, in the normal mode the last measurement is read.
+    /// # Returns
+    /// * `a Result<Bme280Reading, Bme280Error>` - the measurement.
+    pub fn read(&mut self) -> Result<Bme280Reading, Bme280Error> {
+        if self.ctrl_meas & 0x03 == 0b01 {
+            self.write_register(REG_CTRL_MEAS, self.ctrl_meas)?;
+            // The status shows the conversion only once it has started.
+            delay_ms(1);
+            while self.read_register(REG_STATUS)? & STATUS_MEASURING != 0 {
+                delay_ms(1);
+            }
+        }
+
+        let mut data = [0u8; 8];
+        let length = if self.chip == Bme280Chip::BME280 {
+            8
+        } else {
+            6
+        };
+        self.read_registers(REG_DATA, &mut data[..length])?;
+        let adc_p = (data[0] as i32) << 12 | (data[1] as i32) << 4 | (data[2] as i32) >> 4;
+        let adc_t = (data[3] as i32) << 12 | (data[4] as i32) << 4 | (data[5] as i32) >> 4;
+        let adc_h = (data[6] as i32) << 8 | data[7] as i32;
+
+        let t_fine = self.t_fine(adc_t);
+        Ok(Bme280Reading {
+            temperature: (t_fine * 5 + 128) >> 8,
+            pressure: self.compensate_pressure(adc_p, t_fine),
+            humidity: if self.humidity {
+                Some(self.compensate_humidity(adc_h, t_fine))
+            } else {
+                None
+            },
+        })
+    }
+
+    /// Gives the fine temperature used by the other formulas, in 1/5120 degree Celsius.
+    fn t_fine(&self, adc_t: i32) -> i32 {
+        let c = &self.calibration;
+        let var1 = (((adc_t >> 3) - ((c.t1 as i32) << 1)) * c.t2 as i32) >> 11;
+        let delta = (adc_t >> 4) - c.t1 as i32;
+        let var2 = (((delta * delta) >> 12) * c.t3 as i32) >> 14;
+        var1 + var2
+    }
+
+    /// Gives the pressure in pascal with the 64 bit formula of the datasheet.
+    fn compensate_pressure(&self, adc_p: i32, t_fine: i32) -> u32 {
+        let c = &self.calibration;
+        let mut var1 = t_fine as i64 - 128000;
+        let mut var2 = var1 * var1 * c.p6 as i64;
+        var2 += (var1 * c.p5 as i64) << 17;
+        var2 += (c.p4 as i64) << 35;
+        var1 = ((var1 * var1 * c.p3 as i64) >> 8) + ((var1 * c.p2 as i64) << 12);
+        var1 = (((1i64 << 47) + var1) * c.p1 as i64) >> 33;
+        if var1 == 0 {
+            // Avoids a division by zero when the calibration is missing.
+            return 0;
+        }
+        let mut p = 1048576 - adc_p as i64;
+        p = (((p << 31) - var2) * 3125) / var1;
+        var1 = (c.p9 as i64 * (p >> 13) * (p >> 13)) >> 25;
+        var2 = (c.p8 as i64 * p) >> 19;
+        p = ((p + var1 + var2) >> 8) + ((c.p7 as i64) << 4);
+        // The result has 8 fractional bits, rounded here.
+        ((p + 128) >> 8) as u32
+    }
+
+    /// Gives the relative humidity in hundredths of percent.
+    fn compensate_humidity(&self, adc_h: i32, t_fine: i32) -> u32 {
+        let c = &self.calibration;
+        let mut v = t_fine - 76800;
+        v = (((adc_h << 14) - ((c.h4 as i32) << 20) - (c.h5 as i32 * v) + 16384) >> 15)
+            * (((((((v * c.h6 as i32) >> 10) * (((v * c.h3 as i32) >> 11) + 32768)) >> 10)
+                + 2097152)
+                * c.h2 as i32
+                + 8192)
+                >> 14);
+        v -= ((((v >> 15) * (v >> 15)) >> 7) * c.h1 as i32) >> 4;
+        let v = v.clamp(0, 419430400);
+        // The value has 10 fractional bits before the conversion to hundredths.
+        (((v >> 12) as u32) * 100) >> 10
+    }
+
+    /// Reads the calibration coefficients.
+    fn read_calibration(&mut self) -> Result<(), Bme280Error> {
+        let mut b = [0u8; 26];
+        self.read_registers(REG_CALIB_00, &mut b)?;
+        let u = |i: usize| u16::from_le_bytes([b[i], b[i + 1]]);
+        let s = |i: usize| i16::from_le_bytes([b[i], b[i + 1]]);
+        let mut c = Calibration {
+            t1: u(0),
+            t2: s(2),
+            t3: s(4),
+            p1: u(6),
+            p2: s(8),
+            p3: s(10),
+            p4: s(12),
+            p5: s(14),
+            p6: s(16),
+            p7: s(18),
+            p8: s(20),
+            p9: s(22),
+            h1: b[25],
+            ..Calibration::default()
+        };
+
+        if self.chip == Bme280Chip::BME280 {
+            let mut h = [0u8; 7];
+            self.read_registers(REG_CALIB_26, &mut h)?;
+            c.h2 = i16::from_le_bytes([h[0], h[1]]);
+            c.h3 = h[2];
+            // H4 and H5 are 12 bit values sharing the byte at 0xE5.
+            c.h4 = (h[3] as i8 as i16) << 4 | (h[4] & 0x0F) as i16;
+            c.h5 = (h[5] as i8 as i16) << 4 | (h[4] >> 4) as i16;
+            c.h6 = h[6] as i8;
+        }
+        self.calibration = c;
+        Ok(())
+    }
+
+    fn read_register(&mut self, register: u8) -> Result<u8, Bme280Error> {
+        let mut value = [0];
+        self.read_registers(register, &mut value)?;
+        Ok(value[0])
+    }
+
+    /// Reads consecutive registers.
+    fn read_registers(&mut self, register: u8, buffer: &mut [u8]) -> Result<(), Bme280Error> {
+        let result = match &mut self.bus {
+            Bus::I2c(device) => device.read_registers(register, buffer),
+            // The top bit of the register address is set for reading.
+            Bus::Spi(device) => device.write_read(&[register | 0x80], buffer),
+        };
+        result.map_err(|_| Bme280Error::Bus)
+    }
+
+    fn write_register(&mut self, register: u8, value: u8) -> Result<(), Bme280Error> {
+        let result = match &mut self.bus {
+            Bus::I2c(device) => device.write_register(register, value),
+            // The top bit of the register address is cleared for writing.
+            Bus::Spi(device) => device.write(&[register & 0x7F, value]),
+        };
+        result.map_err(|_| Bme280Error::Bus)
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod test {
+    use super::*;
+    use crate::mock::MockTwi;
+
+    // The example of the datasheet of the BMP280, section 8.2.
+    const CALIBRATION: [i32; 12] = [
+        27504, 26435, -1000, 36477, -10685, 3024, 2855, 140, -7, 15500, -14600, 6000,
+    ];
+
+    #[test]
+    fn i2c_compensation() {
+        let mut twi = MockTwi::new();
+        twi.add_device(BME280_I2C_ADDRESS).unwrap();
+        twi.set_register(BME280_I2C_ADDRESS, REG_ID, ID_BMP280);
+        for (i, value) in CALIBRATION.iter().enumerate() {
+            let register = REG_CALIB_00 + 2 * i as u8;
+            twi.set_registers(BME280_I2C_ADDRESS, register, &(*value as u16).to_le_bytes());
+        }
+        // Raw pressure 415148 and temperature 519888.
+        twi.set_registers(
+            BME280_I2C_ADDRESS,
+            REG_DATA,
+            &[0x65, 0x5A, 0xC0, 0x7E, 0xED, 0x00],
+        );
+
+        let mut sensor = BME280::with_i2c(&mut twi, BME280_I2C_ADDRESS).unwrap();
+        assert_eq!(sensor.chip(), Bme280Chip::BMP280);
+        assert_eq!(
+            sensor.read(),
+            Ok(Bme280Reading {
+                temperature: 2508,
+                pressure: 100653,
+                humidity: None,
+            })
+        );
+        // One sample of the temperature and of the pressure, in the forced mode.
+        drop(sensor);
+        assert_eq!(
+            twi.register(BME280_I2C_ADDRESS, REG_CTRL_MEAS),
+            Some(0b0010_0101)
+        );
+        assert_eq!(
+            BME280::with_i2c(&mut twi, 0x77).err(),
+            Some(Bme280Error::Bus)
+        );
+    }
+}
@@ -0,0 +1,187 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! This code reads the DHT11 and DHT22 (AM2302) temperature and humidity sensors.
+//! The sensor is woken up by pulling its data line low, after which it answers with
+//! 40 bits, where a high pulse of about 27 us is a 0 and one of about 70 us is a 1.
+//! The bits are the humidity, the temperature and a checksum, and they are timed by
+//! counting loops with interrupts disabled, so the sensor can be read without a timer.
+//! The data line needs a pull-up resistor, and the sensor should not be read more than
+//! once a second (DHT11) or every two seconds (DHT22).
+
+// Source codes required.
+use crate::delay::{delay_ms, delay_us};
+use crate::error::Error;
+use crate::hal::interrupts::without_interrupts;
+use crate::hal::pin::make_pin;
+use crate::hal::port::Pin;
+
+// Number of loop iterations after which a level is taken as stuck, which is well
+// above the 80 us of the longest pulse at 16 MHz.
+const DHT_TIMEOUT_LOOPS: u16 = 1000;
+
+/// Selection of the sensor, which decides the start signal and the data format.
+#[derive(Clone, Copy, PartialEq)]
+pub enum DhtType {
+    Dht11,
+    Dht22,
+}
+
+/// Errors which can happen while reading the sensor.
+/// * `Timeout` - the sensor did not answer or stopped in the middle of the data.
+/// * `Checksum` - the checksum did not match, so the data was corrupted.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DhtError {
+    Timeout,
+    Checksum,
+}
+
+impl From<DhtError> for Error {
+    fn from(error: DhtError) -> Error {
+        match error {
+            DhtError::Timeout => Error::Timeout,
+            DhtError::Checksum => Error::CrcMismatch,
+        }
+    }
+}
+
+/// A reading of the sensor.
+/// # Elements
+/// * `temperature` - a i16, the temperature in tenths of a degree Celsius.
+/// * `humidity` - a u16, the relative humidity in tenths of a percent.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct DhtReading {
+    pub temperature: i16,
+    pub humidity: u16,
+}
+
+/// Used to control a DHT11 or DHT22 sensor on one digital pin.
+/// # Elements
+/// * `pin` - a u8, the digital pin connected to the data line.
+/// * `kind` - a `DhtType` object, the sensor connected.
+pub struct DHT {
+    pin: u8,
+    kind: DhtType,
+}
+
+/// Waits while the pin is at the given level.
+/// # Returns
+/// * `a Result<u16, DhtError>` - the number of loops waited, or a time-out.
+fn wait_while(pin: &Pin, level: bool) -> Result<u16, DhtError> {
+    let mut loops = 0;
+    while pin.is_high() == level {
+        loops += 1;
+        if loops >= DHT_TIMEOUT_LOOPS {
+            return Err(DhtError::Timeout);
+        }
+    }
+    Ok(loops)
+}
+
+impl DHT {
+    /// Creates a sensor on a pin, which is left as an input.
+    /// # Arguments
+    /// * `pin` - a u8, the digital pin connected to the data line.
+    /// * `kind` - a `DhtType` object, the sensor connected.
+    /// # Returns
+    /// * `a DHT object` - which will be used to read the sensor.
+    pub fn new(pin: u8, kind: DhtType) -> DHT {
+        let mut data = make_pin(pin as _);
+        data.set_input();
+        data.high();
+        DHT { pin, kind }
+    }
+
+    /// Reads the 5 bytes sent by the sensor.
+    /// # Returns
+    /// * `a Result<[u8; 5], DhtError>` - the raw bytes with a valid checksum.
+    pub fn read_raw(&mut self) -> Result<[u8; 5], DhtError> {
+        let mut data = make_pin(self.pin as _);
+
+        // The start signal is a low level of at least 18 ms for the DHT11 and 1 ms for the DHT22.
+        data.set_output();
+        data.low();
+        match self.kind {
+            DhtType::Dht11 => delay_ms(20),
+            DhtType::Dht22 => delay_ms(2),
+        }
+
+        let mut bytes = [0u8; 5];
+        without_interrupts(|| -> Result<(), DhtError> {
+            // The pull-up brings the line high until the sensor answers.
+            data.set_input();
+            data.high();
+            delay_us(40);
+
+            // The answer is 80 us low and 80 us high.
+            wait_while(&data, false)?;
+            wait_while(&data, true)?;
+
+            for i in 0..40 {
+                // Every bit starts with 50 us low, which is used as the reference
+                // for the length of the high pulse.
+                let low = wait_while(&data, false)?;
+                let high = wait_while(&data, true)?;
+                bytes[i / 8] <<= 1;
+                if high > low {
+                    bytes[i / 8] |= 1;
+                }
+            }
+            Ok(())
+        })?;
+
+        let sum = bytes[0]
+            .wrapping_add(bytes[1])
+            .wrapping_add(bytes[2])
+            .wrapping_add(bytes[3]);
+        if sum != bytes[4] {
+            return Err(DhtError::Checksum);
+        }
+        Ok(bytes)
+    }
+
+    /// Reads the temperature and the humidity.
+    /// # Returns
+    /// * `a Result<DhtReading, DhtError>` - the reading, or the error which happened.
+    pub fn read(&mut self) -> Result<DhtReading, DhtError> {
+        let bytes = self.read_raw()?;
+        let reading = match self.kind {
+            // The DHT11 sends whole numbers with a decimal byte.
+            DhtType::Dht11 => {
+                let mut temperature = (bytes[2] & 0x7F) as i16 * 10 + bytes[3] as i16 % 10;
+                if bytes[2] & 0x80 != 0 {
+                    temperature = -temperature;
+                }
+                DhtReading {
+                    temperature,
+                    humidity: bytes[0] as u16 * 10 + bytes[1] as u16 % 10,
+                }
+            }
+            // The DHT22 sends tenths, with the sign of the temperature in the top bit.
+            DhtType::Dht22 => {
+                let mut temperature = (((bytes[2] & 0x7F) as i16) << 8) | bytes[3] as i16;
+                if bytes[2] & 0x80 != 0 {
+                    temperature = -temperature;
+                }
+                DhtReading {
+                    temperature,
+                    humidity: ((bytes[0] as u16) << 8) | bytes[1] as u16,
+                }
+            }
+        };
+        Ok(reading)
+    }
+}
@@ -0,0 +1,224 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! This code reads DS18B20 temperature sensors on a 1-Wire bus.
+//! Any number of sensors can share one pin, each of them is addressed by its ROM code
+//! which is found with `devices`, or the only sensor of a bus can be used without it.
+//! A conversion takes up to 750 ms at 12 bit resolution, and can be started on all
+//! sensors of the bus at once.
+
+// Source codes required.
+use crate::com::onewire::{crc8, OneWire};
+use crate::delay::delay_ms;
+use crate::error::Error;
+
+/// Family code in the first byte of the ROM code of every DS18B20.
+pub const DS18B20_FAMILY_CODE: u8 = 0x28;
+
+// Function commands of the DS18B20.
+const CONVERT_T: u8 = 0x44;
+const WRITE_SCRATCHPAD: u8 = 0x4E;
+const READ_SCRATCHPAD: u8 = 0xBE;
+const COPY_SCRATCHPAD: u8 = 0x48;
+
+/// Errors which can happen while talking to the sensor.
+/// * `NoDevice` - no device answered the reset pulse.
+/// * `Crc` - the CRC of the scratchpad did not match, so the data was corrupted.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Ds18b20Error {
+    NoDevice,
+    Crc,
+}
+
+impl From<Ds18b20Error> for Error {
+    fn from(error: Ds18b20Error) -> Error {
+        match error {
+            Ds18b20Error::NoDevice => Error::NotFound,
+            Ds18b20Error::Crc => Error::CrcMismatch,
+        }
+    }
+}
+
+/// Selection of the resolution of the conversion, finer ones take longer.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Ds18b20Resolution {
+    Bits9,
+    Bits10,
+    Bits11,
+    Bits12,
+}
+
+impl Ds18b20Resolution {
+    /// Gives the longest time taken by a conversion.
+    /// # Returns
+    /// * `a u32` - the time in milliseconds.
+    pub fn conversion_time(&self) -> u32 {
+        match self {
+            Ds18b20Resolution::Bits9 => 94,
+            Ds18b20Resolution::Bits10 => 188,
+            Ds18b20Resolution::Bits11 => 375,
+            Ds18b20Resolution::Bits12 => 750,
+        }
+    }
+}
+
+/// Used to control the DS18B20 sensors on one 1-Wire bus.
+/// # Elements
+/// * `bus` - a `OneWire` object, the bus the sensors are connected to.
+/// * `resolution` - a `Ds18b20Resolution` object, used to wait for conversions.
+pub struct DS18B20 {
+    bus: OneWire,
+    resolution: Ds18b20Resolution,
+}
+
+impl DS18B20 {
+    /// Creates a 1-Wire bus on a pin for the sensors, which start at 12 bit resolution.
+    /// # Arguments
+    /// * `pin` - a u8, the digital pin connected to the DQ line.
+    /// # Returns
+    /// * `a DS18B20 object` - which will be used to read the sensors.
+    pub fn new(pin: u8) -> DS18B20 {
+        DS18B20 {
+            bus: OneWire::new(pin as _),
+            resolution: Ds18b20Resolution::Bits12,
+        }
+    }
+
+    /// Gives the bus, for talking to other 1-Wire devices on the same pin.
+    pub fn bus(&mut self) -> &mut OneWire {
+        &mut self.bus
+    }
+
+    /// Finds the DS18B20 sensors on the bus.
+    /// # Arguments
+    /// * `roms` - a mutable slice of arrays of 8 u8, which will be filled with the ROM codes.
+    /// # Returns
+    /// * `a usize` - the number of sensors found, at most the length of `roms`.
+    pub fn devices(&mut self, roms: &mut [[u8; 8]]) -> usize {
+        let mut count = 0;
+        self.bus.reset_search();
+        while count < roms.len() {
+            match self.bus.search() {
+                Some(rom) => {
+                    if rom[0] == DS18B20_FAMILY_CODE {
+                        roms[count] = rom;
+                        count += 1;
+                    }
+                }
+                None => break,
+            }
+        }
+        self.bus.reset_search();
+        count
+    }
+
+    /// Addresses one sensor, or all of them if no ROM code is given.
+    fn address(&mut self, rom: Option<&[u8; 8]>) -> Result<(), Ds18b20Error> {
+        let present = match rom {
+            Some(rom) => self.bus.select(rom),
+            None => self.bus.skip(),
+        };
+        if present {
+            Ok(())
+        } else {
+            Err(Ds18b20Error::NoDevice)
+        }
+    }
+
+    /// Starts a temperature conversion without waiting for it.
+    /// # Arguments
+    /// * `rom` - a `Option<&[u8; 8]>`, the sensor to be started, or None for all sensors.
+    pub fn start_conversion(&mut self, rom: Option<&[u8; 8]>) -> Result<(), Ds18b20Error> {
+        self.address(rom)?;
+        self.bus.write_byte(CONVERT_T);
+        Ok(())
+    }
+
+    /// Reads the 9 bytes of the scratchpad and checks their CRC.
+    /// # Arguments
+    /// * `rom` - a `Option<&[u8; 8]>`, the sensor to be read, or None for the only sensor.
+    /// # Returns
+    /// * `a Result<[u8; 9], Ds18b20Error>` - the scratchpad.
+    pub fn read_scratchpad(&mut self, rom: Option<&[u8; 8]>) -> Result<[u8; 9], Ds18b20Error> {
+        self.address(rom)?;
+        self.bus.write_byte(READ_SCRATCHPAD);
+        let mut scratchpad = [0; 9];
+        self.bus.read_bytes(&mut scratchpad);
+        // A bus with no device reads as all ones, which would pass the CRC check.
+        if crc8(&scratchpad) != 0 || scratchpad.iter().all(|byte| *byte == 0xFF) {
+            return Err(Ds18b20Error::Crc);
+        }
+        Ok(scratchpad)
+    }
+
+    /// Reads the result of the last conversion.
+    /// # Arguments
+    /// * `rom` - a `Option<&[u8; 8]>`, the sensor to be read, or None for the only sensor.
+    /// # Returns
+    /// * `a Result<i16, Ds18b20Error>` - the temperature in hundredths of a degree Celsius.
+    pub fn read_temperature(&mut self, rom: Option<&[u8; 8]>) -> Result<i16, Ds18b20Error> {
+        let scratchpad = self.read_scratchpad(rom)?;
+        // The temperature is in sixteenths of a degree.
+        let raw = ((scratchpad[1] as i16) << 8) | scratchpad[0] as i16;
+        Ok((raw as i32 * 100 / 16) as i16)
+    }
+
+    /// Starts a conversion, waits for it and reads the temperature.
+    /// # Arguments
+    /// * `rom` - a `Option<&[u8; 8]>`, the sensor to be read, or None for the only sensor.
+    /// # Returns
+    /// * `a Result<i16, Ds18b20Error>` - the temperature in hundredths of a degree Celsius.
+    pub fn measure(&mut self, rom: Option<&[u8; 8]>) -> Result<i16, Ds18b20Error> {
+        self.start_conversion(rom)?;
+        delay_ms(self.resolution.conversion_time());
+        self.read_temperature(rom)
+    }
+
+    /// Sets the resolution of a sensor, or of all of them, and stores it in their EEPROM.
+    /// # Arguments
+    /// * `rom` - a `Option<&[u8; 8]>`, the sensor to be set, or None for all sensors.
+    /// * `resolution` - a `Ds18b20Resolution` object, the new resolution.
+    pub fn set_resolution(
+        &mut self,
+        rom: Option<&[u8; 8]>,
+        resolution: Ds18b20Resolution,
+    ) -> Result<(), Ds18b20Error> {
+        let config = match resolution {
+            Ds18b20Resolution::Bits9 => 0x1F,
+            Ds18b20Resolution::Bits10 => 0x3F,
+            Ds18b20Resolution::Bits11 => 0x5F,
+            Ds18b20Resolution::Bits12 => 0x7F,
+        };
+        // The alarm registers TH and TL are written back unchanged.
+        let (th, tl) = match rom {
+            Some(_) => {
+                let scratchpad = self.read_scratchpad(rom)?;
+                (scratchpad[2], scratchpad[3])
+            }
+            None => (0x4B, 0x46),
+        };
+
+        self.address(rom)?;
+        self.bus.write_byte(WRITE_SCRATCHPAD);
+        self.bus.write_bytes(&[th, tl, config]);
+        self.address(rom)?;
+        self.bus.write_byte(COPY_SCRATCHPAD);
+        // The copy to EEPROM takes up to 10 ms.
+        delay_ms(10);
+        self.resolution = resolution;
+        Ok(())
+    }
+}
@@ -0,0 +1,392 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! This code reads the NMEA 0183 sentences sent by GPS receivers such as the NEO-6M
+//! over a serial port, either a USART or a software serial port.
+//! The GGA and RMC sentences of any talker (GP, GN, GL...) are parsed into the position,
+//! altitude, speed, course and UTC date and time, after their checksum is checked.
+//! The characters are collected in a fixed buffer and the values are kept as integers,
+//! so nothing is allocated and no float is parsed.
+//! See `<https://www.gpsinformation.org/dale/nmea.htm>`.
+
+// Source codes required.
+use crate::common::UsartOps;
+
+/// Maximum length of a NMEA sentence, from `$` to the checksum.
+const MAX_SENTENCE: usize = 82;
+
+/// Maximum number of fields in a parsed sentence.
+const MAX_FIELDS: usize = 20;
+
+/// UTC time of a fix.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct GpsTime {
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    pub centisecond: u8,
+}
+
+/// UTC date of a fix, only given by the RMC sentence.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct GpsDate {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+}
+
+/// Last values received from the GPS.
+/// # Elements
+/// * `valid` - a bool, true if the last RMC sentence reported a valid fix.
+/// * `quality` - a u8, the fix quality of the GGA sentence, 0 without fix, 1 for GPS, 2 for DGPS.
+/// * `satellites` - a u8, the number of satellites used.
+/// * `time` - a `GpsTime` object, the UTC time of the last sentence.
+/// * `date` - a `GpsDate` object, the UTC date of the last RMC sentence.
+/// * `latitude` - a i32, in millionths of degree, positive to the north.
+/// * `longitude` - a i32, in millionths of degree, positive to the east.
+/// * `altitude` - a i32, above the mean sea level in centimeters.
+/// * `speed` - a u32, the speed over ground in hundredths of knot.
+/// * `course` - a u16, the course over ground in hundredths of degree from true north.
+/// * `hdop` - a u16, the horizontal dilution of precision in hundredths.
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub struct GpsFix {
+    pub valid: bool,
+    pub quality: u8,
+    pub satellites: u8,
+    pub time: GpsTime,
+    pub date: GpsDate,
+    pub latitude: i32,
+    pub longitude: i32,
+    pub altitude: i32,
+    pub speed: u32,
+    pub course: u16,
+    pub hdop: u16,
+}
+
+impl GpsFix {
+    /// Gives the latitude in degrees.
+    pub fn latitude_degrees(&self) -> f32 {
+        self.latitude as f32 / 1_000_000.0
+    }
+
+    /// Gives the longitude in degrees.
+    pub fn longitude_degrees(&self) -> f32 {
+        self.longitude as f32 / 1_000_000.0
+    }
+
+    /// Gives the speed over ground in hundredths of kilometer per hour.
+    pub fn speed_kmh(&self) -> u32 {
+        self.speed * 1852 / 1000
+    }
+}
+
+/// Used to read the sentences of a GPS receiver.
+/// # Elements
+/// * `buffer` - a array of u8, the characters of the sentence being received.
+/// * `length` - a usize, the number of characters in the buffer.
+/// * `receiving` - a bool, true between a `$` and the end of the line.
+/// * `fix` - a `GpsFix` object, the last values received.
+/// * `checksum_errors` - a u16, the number of sentences dropped for a wrong checksum.
+pub struct GPS {
+    buffer: [u8; MAX_SENTENCE],
+    length: usize,
+    receiving: bool,
+    fix: GpsFix,
+    checksum_errors: u16,
+}
+
+impl Default for GPS {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GPS {
+    /// Creates the reader without any value received.
+    pub fn new() -> GPS {
+        GPS {
+            buffer: [0; MAX_SENTENCE],
+            length: 0,
+            receiving: false,
+            fix: GpsFix::default(),
+            checksum_errors: 0,
+        }
+    }
+
+    /// Gives one received character to the reader.
+    /// # Arguments
+    /// * `byte` - a u8, the character received from the GPS.
+    /// # Returns
+    /// * `a bool` - true if a GGA or RMC sentence has just been parsed.
+    pub fn feed(&mut self, byte: u8) -> bool {
+        match byte {
+            b'$' => {
+                self.receiving = true;
+                self.length = 0;
+                false
+            }
+            b'\r' | b'\n' => {
+                if !self.receiving {
+                    return false;
+                }
+                self.receiving = false;
+                self.parse()
+            }
+            _ => {
+                if self.receiving {
+                    if self.length < MAX_SENTENCE {
+                        self.buffer[self.length] = byte;
+                        self.length += 1;
+                    } else {
+                        // Too long to be a sentence, wait for the next `$`.
+                        self.receiving = false;
+                    }
+                }
+                false
+            }
+        }
+    }
+
+    /// Reads all the characters waiting in a serial port, a USART or a software serial port.
+    /// # Arguments
+    /// * `serial` - a `UsartOps` object, the port connected to the TX pin of the GPS.
+    /// # Returns
+    /// * `a bool` - true if a GGA or RMC sentence has been parsed.
+    pub fn update_from_serial<T: UsartOps>(&mut self, serial: &mut T) -> bool {
+        let mut updated = false;
+        while let Some(byte) = serial.read_byte() {
+            updated |= self.feed(byte);
+        }
+        updated
+    }
+
+    /// Gives the last values received.
+    pub fn fix(&self) -> &GpsFix {
+        &self.fix
+    }
+
+    /// Checks whether the GPS has a valid position.
+    pub fn has_fix(&self) -> bool {
+        self.fix.valid && self.fix.quality > 0
+    }
+
+    /// Gives the number of sentences dropped because their checksum was wrong.
+    pub fn checksum_errors(&self) -> u16 {
+        self.checksum_errors
+    }
+
+    /// Checks the sentence in the buffer and parses it if it is a GGA or RMC sentence.
+    fn parse(&mut self) -> bool {
+        let sentence = &self.buffer[..self.length];
+        let star = match sentence.iter().position(|&c| c == b'*') {
+            Some(star) => star,
+            None => return false,
+        };
+        let (body, checksum) = (&sentence[..star], &sentence[star + 1..]);
+        let expected = match (checksum.first(), checksum.get(1)) {
+            (Some(&high), Some(&low)) => match (hex_digit(high), hex_digit(low)) {
+                (Some(high), Some(low)) => high << 4 | low,
+                _ => return false,
+            },
+            _ => return false,
+        };
+        if body.iter().fold(0, |sum, &c| sum ^ c) != expected {
+            self.checksum_errors = self.checksum_errors.wrapping_add(1);
+            return false;
+        }
+
+        let mut fields: [&[u8]; MAX_FIELDS] = [&[]; MAX_FIELDS];
+        for (field, value) in fields.iter_mut().zip(body.split(|&c| c == b',')) {
+            *field = value;
+        }
+        // The talker is ignored, only the type of the sentence matters.
+        if fields[0].len() != 5 {
+            return false;
+        }
+        let fix = &mut self.fix;
+        match &fields[0][2..] {
+            b"GGA" => {
+                if let Some(time) = parse_time(fields[1]) {
+                    fix.time = time;
+                }
+                fix.quality = parse_uint(fields[6]).unwrap_or(0) as u8;
+                fix.satellites = parse_uint(fields[7]).unwrap_or(0) as u8;
+                if let Some(hdop) = parse_fixed(fields[8], 2) {
+                    fix.hdop = hdop as u16;
+                }
+                if let Some(altitude) = parse_fixed(fields[9], 2) {
+                    fix.altitude = altitude;
+                }
+                if let Some(latitude) = parse_coordinate(fields[2], fields[3]) {
+                    fix.latitude = latitude;
+                }
+                if let Some(longitude) = parse_coordinate(fields[4], fields[5]) {
+                    fix.longitude = longitude;
+                }
+                true
+            }
+            b"RMC" => {
+                if let Some(time) = parse_time(fields[1]) {
+                    fix.time = time;
+                }
+                fix.valid = fields[2] == b"A";
+                if let Some(latitude) = parse_coordinate(fields[3], fields[4]) {
+                    fix.latitude = latitude;
+                }
+                if let Some(longitude) = parse_coordinate(fields[5], fields[6]) {
+                    fix.longitude = longitude;
+                }
+                if let Some(speed) = parse_fixed(fields[7], 2) {
+                    fix.speed = speed as u32;
+                }
+                if let Some(course) = parse_fixed(fields[8], 2) {
+                    fix.course = course as u16;
+                }
+                if let Some(date) = parse_uint(fields[9]) {
+                    fix.date = GpsDate {
+                        day: (date / 10000) as u8,
+                        month: (date / 100 % 100) as u8,
+                        year: 2000 + (date % 100) as u16,
+                    };
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Gives the value of a hexadecimal digit in upper case.
+fn hex_digit(c: u8) -> Option<u8> {
+    match c {
+        b'0'..=b'9' => Some(c - b'0'),
+        b'A'..=b'F' => Some(c - b'A' + 10),
+        _ => None,
+    }
+}
+
+/// Parses a field made only of decimal digits.
+fn parse_uint(field: &[u8]) -> Option<u32> {
+    if field.is_empty() {
+        return None;
+    }
+    field.iter().try_fold(0u32, |value, &c| {
+        if c.is_ascii_digit() {
+            Some(value * 10 + (c - b'0') as u32)
+        } else {
+            None
+        }
+    })
+}
+
+/// Parses a decimal number into an integer of the given number of decimals,
+/// dropping the further decimals.
+fn parse_fixed(field: &[u8], decimals: u8) -> Option<i32> {
+    let (negative, digits) = match field.split_first() {
+        Some((b'-', rest)) => (true, rest),
+        Some(_) => (false, field),
+        None => return None,
+    };
+    let mut parts = digits.splitn(2, |&c| c == b'.');
+    let mut value = parse_uint(parts.next()?)? as i32;
+    let fraction = parts.next().unwrap_or(&[]);
+    for i in 0..decimals as usize {
+        let digit = match fraction.get(i) {
+            Some(c) if c.is_ascii_digit() => (c - b'0') as i32,
+            Some(_) => return None,
+            None => 0,
+        };
+        value = value * 10 + digit;
+    }
+    Some(if negative { -value } else { value })
+}
+
+/// Parses a `ddmm.mmmmm` or `dddmm.mmmmm` coordinate and its hemisphere into
+/// millionths of degree.
+fn parse_coordinate(field: &[u8], hemisphere: &[u8]) -> Option<i32> {
+    // Five decimals of minutes are about 2 cm.
+    let value = parse_fixed(field, 5)? as u32;
+    let degrees = value / 10_000_000;
+    let minutes = value % 10_000_000;
+    // A hundred thousandth of minute is a sixth of a millionth of degree.
+    let coordinate = (degrees * 1_000_000 + minutes / 6) as i32;
+    match hemisphere {
+        b"N" | b"E" => Some(coordinate),
+        b"S" | b"W" => Some(-coordinate),
+        _ => None,
+    }
+}
+
+/// Parses a `hhmmss.ss` time.
+fn parse_time(field: &[u8]) -> Option<GpsTime> {
+    let value = parse_fixed(field, 2)? as u32;
+    Some(GpsTime {
+        hour: (value / 1_000_000) as u8,
+        minute: (value / 10_000 % 100) as u8,
+        second: (value / 100 % 100) as u8,
+        centisecond: (value % 100) as u8,
+    })
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod test {
+    use super::*;
+    use crate::mock::MockUsart;
+
+    const GGA: &[u8] = b"$GPGGA,123519,4807.038,N,01131.000,E,1,08,0.9,545.4,M,46.9,M,,*47\r\n";
+    const RMC: &[u8] = b"$GPRMC,123519,A,4807.038,N,01131.000,E,022.4,084.4,230394,003.1,W*6A\r\n";
+
+    #[test]
+    fn gga_and_rmc() {
+        let mut gps = GPS::new();
+        let parsed: usize = GGA.iter().map(|&c| gps.feed(c) as usize).sum();
+        assert_eq!(parsed, 1);
+        let fix = gps.fix();
+        assert_eq!(
+            fix.time,
+            GpsTime {
+                hour: 12,
+                minute: 35,
+                second: 19,
+                centisecond: 0
+            }
+        );
+        assert_eq!((fix.quality, fix.satellites, fix.hdop), (1, 8, 90));
+        assert_eq!(fix.altitude, 54540);
+        assert_eq!((fix.latitude, fix.longitude), (48_117_300, 11_516_666));
+        assert!(!gps.has_fix());
+
+        let mut serial = MockUsart::new();
+        serial.feed(RMC).unwrap();
+        assert!(gps.update_from_serial(&mut serial));
+        let fix = gps.fix();
+        assert!(gps.has_fix());
+        assert_eq!((fix.speed, fix.course), (2240, 8440));
+        assert_eq!((fix.date.day, fix.date.month), (23, 3));
+    }
+
+    #[test]
+    fn wrong_checksum() {
+        let mut gps = GPS::new();
+        let mut sentence = [0; 67];
+        sentence.copy_from_slice(GGA);
+        // Latitude 4907.038 instead of 4807.038.
+        sentence[15] = b'9';
+        assert!(!sentence.iter().any(|&c| gps.feed(c)));
+        assert_eq!(gps.checksum_errors(), 1);
+        assert_eq!(gps.fix(), &GpsFix::default());
+    }
+}
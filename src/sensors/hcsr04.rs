@@ -0,0 +1,92 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! This code controls the HC-SR04 ultrasonic distance sensor.
+//! A 10 us pulse on the trigger pin makes the sensor send a burst of ultrasound,
+//! after which the echo pin stays high for as long as the sound took to come back.
+//! The width of the echo pulse is measured with `hal::capture::pulse_in`, so
+//! `time::init()` must be called before the sensor is used.
+
+// Source codes required.
+use crate::delay::delay_us;
+use crate::hal::capture::pulse_in;
+use crate::hal::pin::make_pin;
+
+/// Default time to wait for the echo, enough for about 5 m.
+pub const HCSR04_DEFAULT_TIMEOUT: u32 = 30000;
+
+/// Used to control the HC-SR04 ultrasonic sensor.
+/// # Elements
+/// * `trigger` - a u8, the digital pin connected to the TRIG pin of the sensor.
+/// * `echo` - a u8, the digital pin connected to the ECHO pin of the sensor.
+/// * `timeout` - a u32, the time in microseconds to wait for the echo.
+pub struct HCSR04 {
+    trigger: u8,
+    echo: u8,
+    timeout: u32,
+}
+
+impl HCSR04 {
+    /// Sets up the trigger pin as a low output and the echo pin as an input.
+    /// # Arguments
+    /// * `trigger` - a u8, the digital pin connected to TRIG.
+    /// * `echo` - a u8, the digital pin connected to ECHO.
+    /// # Returns
+    /// * `a HCSR04 object` - which will be used to measure distances.
+    pub fn new(trigger: u8, echo: u8) -> HCSR04 {
+        let mut trigger_pin = make_pin(trigger as _);
+        trigger_pin.set_output();
+        trigger_pin.low();
+        make_pin(echo as _).set_input();
+        HCSR04 {
+            trigger,
+            echo,
+            timeout: HCSR04_DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Sets the time to wait for the echo, which limits the largest distance measured.
+    /// # Arguments
+    /// * `timeout` - a u32, the time in microseconds.
+    pub fn set_timeout(&mut self, timeout: u32) {
+        self.timeout = timeout;
+    }
+
+    /// Triggers a measurement and gives the time of flight of the sound.
+    /// # Returns
+    /// * `a Option<u32>` - the width of the echo pulse in microseconds, None on time-out.
+    pub fn echo_us(&mut self) -> Option<u32> {
+        let mut trigger = make_pin(self.trigger as _);
+        trigger.low();
+        delay_us(2);
+        trigger.high();
+        delay_us(10);
+        trigger.low();
+
+        match pulse_in(self.echo as _, true, self.timeout) {
+            0 => None,
+            width => Some(width),
+        }
+    }
+
+    /// Measures the distance to the nearest object.
+    /// The sound travels at 343 m/s, that is 0.343 mm/us, to the object and back.
+    /// # Returns
+    /// * `a Option<u32>` - the distance in millimeters, None if no echo came back in time.
+    pub fn distance_mm(&mut self) -> Option<u32> {
+        self.echo_us().map(|width| width * 343 / 2000)
+    }
+}
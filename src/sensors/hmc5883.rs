@@ -0,0 +1,445 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! This code controls the HMC5883L three axis magnetometer and the QMC5883L, which is
+//! sold on most modules labelled HMC5883L, over I2C.
+//! The field is given in gauss along the axes printed on the module, and turned into a
+//! compass heading either with the module flat or compensated for the tilt given by
+//! the `Orientation` of the MPU6050 driver, whose axes must point the same way.
+//! See `<https://cdn-shop.adafruit.com/datasheets/HMC5883L_3-Axis_Digital_Compass_IC.pdf>`.
+
+// Source codes required.
+use super::mpu6050::{atan2, Orientation};
+use crate::bus::{I2cDevice, SharedTwi};
+use crate::common::TwiOps;
+use crate::delay::delay_ms;
+use crate::error::Error;
+use core::f32::consts::{FRAC_PI_2, PI};
+
+/// I2C address of the HMC5883L.
+pub const HMC5883L_I2C_ADDRESS: u8 = 0x1E;
+
+/// I2C address of the QMC5883L.
+pub const QMC5883L_I2C_ADDRESS: u8 = 0x0D;
+
+// SCL frequency used with both magnetometers.
+const I2C_FREQUENCY: u32 = 400_000;
+
+// Registers of the HMC5883L.
+const HMC_CONFIG_A: u8 = 0x00;
+const HMC_CONFIG_B: u8 = 0x01;
+const HMC_MODE: u8 = 0x02;
+const HMC_DATA: u8 = 0x03;
+const HMC_STATUS: u8 = 0x09;
+const HMC_ID: u8 = 0x0A;
+
+// Registers of the QMC5883L.
+const QMC_DATA: u8 = 0x00;
+const QMC_STATUS: u8 = 0x06;
+const QMC_CONTROL_1: u8 = 0x09;
+const QMC_CONTROL_2: u8 = 0x0A;
+const QMC_SET_RESET: u8 = 0x0B;
+const QMC_ID: u8 = 0x0D;
+
+// Bits of the status registers.
+const STATUS_READY: u8 = 0x01;
+const QMC_STATUS_OVERFLOW: u8 = 0x02;
+
+/// Value of an axis of the HMC5883L whose field is beyond the range of the gain.
+const HMC_OVERFLOW: i16 = -4096;
+
+/// Milliseconds waited for a measurement before giving up.
+const TIMEOUT_MS: u8 = 200;
+
+/// Errors which can happen while using the magnetometer.
+/// * `NotFound` - no magnetometer of the chosen kind answered.
+/// * `Bus` - an I2C transfer failed.
+/// * `Overflow` - the field is beyond the range of the gain.
+/// * `Timeout` - no measurement was ready in time, as in the idle mode.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MagError {
+    NotFound,
+    Bus,
+    Overflow,
+    Timeout,
+}
+
+impl From<MagError> for Error {
+    fn from(error: MagError) -> Error {
+        match error {
+            MagError::NotFound => Error::NotFound,
+            MagError::Bus => Error::Bus,
+            MagError::Overflow => Error::Overflow,
+            MagError::Timeout => Error::Timeout,
+        }
+    }
+}
+
+/// Kind of magnetometer.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MagChip {
+    HMC5883L,
+    QMC5883L,
+}
+
+/// Range of the field measured, in gauss. The QMC5883L only has the ranges
+/// of 2 and 8 gauss, used for the gains up to and above 1.9 gauss.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MagGain {
+    Gauss0_88,
+    Gauss1_3,
+    Gauss1_9,
+    Gauss2_5,
+    Gauss4_0,
+    Gauss4_7,
+    Gauss5_6,
+    Gauss8_1,
+}
+
+/// Way the magnetometer measures.
+/// * `Continuous` - it measures all the time, and a reading gives the last measurement.
+/// * `Single` - it measures once for each reading, then rests.
+/// * `Idle` - it does not measure.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum MagMode {
+    Continuous,
+    Single,
+    Idle,
+}
+
+/// Magnetic field along each axis, in gauss.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct MagField {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+}
+
+/// Used to control a HMC5883L or QMC5883L magnetometer.
+/// # Elements
+/// * `device` - a `I2cDevice` object, the handle of the magnetometer on the I2C bus.
+/// * `chip` - a `MagChip` object, the kind of magnetometer.
+/// * `gain` - a `MagGain` object, the range of the field measured.
+/// * `mode` - a `MagMode` object, the way it measures.
+/// * `declination` - a f32, the angle in degrees from true north to magnetic north,
+///   positive to the east, added to the headings.
+pub struct HMC5883<T = SharedTwi> {
+    device: I2cDevice<T>,
+    chip: MagChip,
+    gain: MagGain,
+    mode: MagMode,
+    declination: f32,
+}
+
+impl HMC5883 {
+    /// Creates the magnetometer on the TWI of the chip, checks that it answers and sets
+    /// it to measure continuously in the range of 1.3 gauss.
+    /// # Arguments
+    /// * `chip` - a `MagChip` object, the kind of magnetometer.
+    /// # Returns
+    /// * `a Result<HMC5883, MagError>` - the magnetometer, or the error if it was not found.
+    pub fn new(chip: MagChip) -> Result<HMC5883, MagError> {
+        HMC5883::with_bus(SharedTwi::new(), chip)
+    }
+}
+
+impl<T: TwiOps> HMC5883<T> {
+    /// Creates the magnetometer on the given I2C bus, checks that it answers and sets
+    /// it to measure continuously in the range of 1.3 gauss.
+    /// # Arguments
+    /// * `bus` - a `TwiOps` object, the I2C bus of the magnetometer.
+    /// * `chip` - a `MagChip` object, the kind of magnetometer.
+    /// # Returns
+    /// * `a Result<HMC5883, MagError>` - the magnetometer, or the error if it was not found.
+    pub fn with_bus(bus: T, chip: MagChip) -> Result<HMC5883<T>, MagError> {
+        let address = match chip {
+            MagChip::HMC5883L => HMC5883L_I2C_ADDRESS,
+            MagChip::QMC5883L => QMC5883L_I2C_ADDRESS,
+        };
+        let mut sensor = HMC5883 {
+            device: I2cDevice::with_bus(bus, address, I2C_FREQUENCY),
+            chip,
+            gain: MagGain::Gauss1_3,
+            mode: MagMode::Continuous,
+            declination: 0.0,
+        };
+
+        match chip {
+            MagChip::HMC5883L => {
+                let mut id = [0u8; 3];
+                sensor.read_registers(HMC_ID, &mut id)?;
+                if &id != b"H43" {
+                    return Err(MagError::NotFound);
+                }
+                // 8 samples averaged for each measurement at 15 Hz.
+                sensor.write_register(HMC_CONFIG_A, 0x70)?;
+            }
+            MagChip::QMC5883L => {
+                if sensor.read_register(QMC_ID)? != 0xFF {
+                    return Err(MagError::NotFound);
+                }
+                sensor.write_register(QMC_CONTROL_2, 0x80)?;
+                delay_ms(1);
+                // The datasheet asks for this period of the set and reset pulses.
+                sensor.write_register(QMC_SET_RESET, 0x01)?;
+            }
+        }
+        sensor.set_gain(MagGain::Gauss1_3)?;
+        sensor.set_mode(MagMode::Continuous)?;
+        Ok(sensor)
+    }
+
+    /// Gives the kind of magnetometer.
+    pub fn chip(&self) -> MagChip {
+        self.chip
+    }
+
+    /// Sets the range of the field measured. A smaller range is more precise,
+    /// but the earth's field of about 0.6 gauss needs at least 0.88 gauss.
+    pub fn set_gain(&mut self, gain: MagGain) -> Result<(), MagError> {
+        self.gain = gain;
+        match self.chip {
+            MagChip::HMC5883L => self.write_register(HMC_CONFIG_B, (gain as u8) << 5),
+            MagChip::QMC5883L => self.write_control(),
+        }
+    }
+
+    /// Gives the range of the field measured.
+    pub fn gain(&self) -> MagGain {
+        self.gain
+    }
+
+    /// Sets the way the magnetometer measures.
+    pub fn set_mode(&mut self, mode: MagMode) -> Result<(), MagError> {
+        self.mode = mode;
+        match self.chip {
+            MagChip::HMC5883L => match mode {
+                MagMode::Continuous => self.write_register(HMC_MODE, 0x00),
+                // In the single mode each reading starts its own measurement.
+                MagMode::Single | MagMode::Idle => self.write_register(HMC_MODE, 0x03),
+            },
+            MagChip::QMC5883L => self.write_control(),
+        }
+    }
+
+    /// Gives the way the magnetometer measures.
+    pub fn mode(&self) -> MagMode {
+        self.mode
+    }
+
+    /// Sets the magnetic declination of the place, added to the headings so that
+    /// they are from true north.
+    /// # Arguments
+    /// * `degrees` - a f32, the declination, positive to the east.
+    pub fn set_declination(&mut self, degrees: f32) {
+        self.declination = degrees;
+    }
+
+    /// Reads the raw values of the three axes, waiting for a measurement.
+    /// # Returns
+    /// * `a Result<(i16, i16, i16), MagError>` - the x, y and z values.
+    pub fn read_raw(&mut self) -> Result<(i16, i16, i16), MagError> {
+        match self.chip {
+            MagChip::HMC5883L => {
+                if self.mode == MagMode::Single {
+                    self.write_register(HMC_MODE, 0x01)?;
+                }
+                self.wait_ready(HMC_STATUS)?;
+                let mut data = [0u8; 6];
+                self.read_registers(HMC_DATA, &mut data)?;
+                // The axes are in the order x, z and y, most significant byte first.
+                let x = i16::from_be_bytes([data[0], data[1]]);
+                let z = i16::from_be_bytes([data[2], data[3]]);
+                let y = i16::from_be_bytes([data[4], data[5]]);
+                if x == HMC_OVERFLOW || y == HMC_OVERFLOW || z == HMC_OVERFLOW {
+                    return Err(MagError::Overflow);
+                }
+                Ok((x, y, z))
+            }
+            MagChip::QMC5883L => {
+                // There is no single mode, so it measures continuously for one reading.
+                if self.mode == MagMode::Single {
+                    self.write_register(QMC_CONTROL_1, self.control(MagMode::Continuous))?;
+                }
+                let status = self.wait_ready(QMC_STATUS)?;
+                let mut data = [0u8; 6];
+                let read = self.read_registers(QMC_DATA, &mut data);
+                if self.mode == MagMode::Single {
+                    self.write_control()?;
+                }
+                read?;
+                if status & QMC_STATUS_OVERFLOW != 0 {
+                    return Err(MagError::Overflow);
+                }
+                Ok((
+                    i16::from_le_bytes([data[0], data[1]]),
+                    i16::from_le_bytes([data[2], data[3]]),
+                    i16::from_le_bytes([data[4], data[5]]),
+                ))
+            }
+        }
+    }
+
+    /// Reads the magnetic field, waiting for a measurement.
+    /// # Returns
+    /// * `a Result<MagField, MagError>` - the field in gauss.
+    pub fn read(&mut self) -> Result<MagField, MagError> {
+        let (x, y, z) = self.read_raw()?;
+        let scale = 1.0 / self.lsb_per_gauss();
+        Ok(MagField {
+            x: x as f32 * scale,
+            y: y as f32 * scale,
+            z: z as f32 * scale,
+        })
+    }
+
+    /// Gives the compass heading of a field measured with the module flat.
+    /// # Arguments
+    /// * `field` - a `MagField` object, the field read.
+    /// # Returns
+    /// * `a f32` - the heading in degrees from north, from 0 to 360, clockwise.
+    pub fn heading(&self, field: &MagField) -> f32 {
+        self.to_heading(atan2(field.y, field.x))
+    }
+
+    /// Gives the compass heading of a field measured with the module tilted, using
+    /// the roll and pitch of the MPU6050 to bring the field back to the horizontal.
+    /// # Arguments
+    /// * `field` - a `MagField` object, the field read.
+    /// * `orientation` - a `Orientation` object, the roll and pitch of the module in degrees.
+    /// # Returns
+    /// * `a f32` - the heading in degrees from north, from 0 to 360, clockwise.
+    pub fn tilt_compensated_heading(&self, field: &MagField, orientation: &Orientation) -> f32 {
+        let (sin_roll, cos_roll) = sin_cos(orientation.roll * PI / 180.0);
+        let (sin_pitch, cos_pitch) = sin_cos(orientation.pitch * PI / 180.0);
+        let x =
+            field.x * cos_pitch + field.y * sin_pitch * sin_roll + field.z * sin_pitch * cos_roll;
+        let y = field.y * cos_roll - field.z * sin_roll;
+        self.to_heading(atan2(y, x))
+    }
+
+    /// Turns an angle in radians into a heading in degrees from 0 to 360,
+    /// adding the declination.
+    fn to_heading(&self, angle: f32) -> f32 {
+        let mut heading = angle * 180.0 / PI + self.declination;
+        while heading < 0.0 {
+            heading += 360.0;
+        }
+        while heading >= 360.0 {
+            heading -= 360.0;
+        }
+        heading
+    }
+
+    /// Gives the number of counts for one gauss at the present gain.
+    fn lsb_per_gauss(&self) -> f32 {
+        match self.chip {
+            MagChip::HMC5883L => match self.gain {
+                MagGain::Gauss0_88 => 1370.0,
+                MagGain::Gauss1_3 => 1090.0,
+                MagGain::Gauss1_9 => 820.0,
+                MagGain::Gauss2_5 => 660.0,
+                MagGain::Gauss4_0 => 440.0,
+                MagGain::Gauss4_7 => 390.0,
+                MagGain::Gauss5_6 => 330.0,
+                MagGain::Gauss8_1 => 230.0,
+            },
+            MagChip::QMC5883L => {
+                if self.qmc_wide_range() {
+                    3000.0
+                } else {
+                    12000.0
+                }
+            }
+        }
+    }
+
+    fn qmc_wide_range(&self) -> bool {
+        self.gain as u8 > MagGain::Gauss1_9 as u8
+    }
+
+    /// Gives the first control register of the QMC5883L for a mode, with 512 times
+    /// oversampling at 50 Hz.
+    fn control(&self, mode: MagMode) -> u8 {
+        let range = if self.qmc_wide_range() { 0x10 } else { 0x00 };
+        let mode = if mode == MagMode::Continuous {
+            0x01
+        } else {
+            0x00
+        };
+        range | 0x04 | mode
+    }
+
+    fn write_control(&mut self) -> Result<(), MagError> {
+        self.write_register(QMC_CONTROL_1, self.control(self.mode))
+    }
+
+    /// Waits until a measurement is ready.
+    /// # Returns
+    /// * `a Result<u8, MagError>` - the status register.
+    fn wait_ready(&mut self, status_register: u8) -> Result<u8, MagError> {
+        for _ in 0..TIMEOUT_MS {
+            let status = self.read_register(status_register)?;
+            if status & STATUS_READY != 0 {
+                return Ok(status);
+            }
+            delay_ms(1);
+        }
+        Err(MagError::Timeout)
+    }
+
+    fn read_register(&mut self, register: u8) -> Result<u8, MagError> {
+        let mut value = [0];
+        self.read_registers(register, &mut value)?;
+        Ok(value[0])
+    }
+
+    /// Reads consecutive registers.
+    fn read_registers(&mut self, register: u8, buffer: &mut [u8]) -> Result<(), MagError> {
+        self.device
+            .read_registers(register, buffer)
+            .map_err(|_| MagError::Bus)
+    }
+
+    fn write_register(&mut self, register: u8, value: u8) -> Result<(), MagError> {
+        self.device
+            .write_register(register, value)
+            .map_err(|_| MagError::Bus)
+    }
+}
+
+/// Sine and cosine of an angle in radians, within 0.0002, since `core` has none.
+fn sin_cos(angle: f32) -> (f32, f32) {
+    (sin(angle), sin(angle + FRAC_PI_2))
+}
+
+fn sin(angle: f32) -> f32 {
+    let mut x = angle;
+    while x > PI {
+        x -= 2.0 * PI;
+    }
+    while x < -PI {
+        x += 2.0 * PI;
+    }
+    // The sine is symmetric around a quarter turn, where the series is most precise.
+    if x > FRAC_PI_2 {
+        x = PI - x;
+    } else if x < -FRAC_PI_2 {
+        x = -PI - x;
+    }
+    let x2 = x * x;
+    x * (1.0 - x2 / 6.0 * (1.0 - x2 / 20.0 * (1.0 - x2 / 42.0)))
+}
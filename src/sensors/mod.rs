@@ -14,12 +14,35 @@
 // You should have received a copy of the GNU Affero General Public License
 // along with this program.  If not, see <https://www.gnu.org/licenses/>
 
-mod aht10;
-mod display;
+mod bme280;
+mod gps;
+mod hmc5883;
 mod mpu6050;
-mod servo;
+mod rtc;
 
-pub use aht10::*;
-pub use display::*;
+pub use bme280::*;
+pub use gps::*;
+pub use hmc5883::*;
 pub use mpu6050::*;
-pub use servo::*;
+pub use rtc::*;
+
+// The other drivers need peripherals the mock backend does not fake.
+cfg_if::cfg_if! {
+    if #[cfg(any(feature = "atmega2560p", feature = "atmega328p"))] {
+        mod aht10;
+        mod analog;
+        mod dht;
+        mod display;
+        mod ds18b20;
+        mod hcsr04;
+        mod servo;
+
+        pub use aht10::*;
+        pub use analog::*;
+        pub use dht::*;
+        pub use display::*;
+        pub use ds18b20::*;
+        pub use hcsr04::*;
+        pub use servo::*;
+    }
+}
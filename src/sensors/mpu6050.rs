@@ -17,13 +17,18 @@
 //! Source code for implementation of MPU6050 Gyroscopic Sensor
 //! which might be attached or in-built to the current
 //! AVR Micro-controller.
+//! Besides the raw readings, the offsets of the sensor can be calibrated into its
+//! offset registers, samples can be collected through its FIFO, and roll and pitch
+//! are estimated with a complementary filter, without the DMP of the sensor.
 
-use crate::{com::i2c, delay::delay_ms};
+use crate::bus::{I2cDevice, SharedTwi};
+use crate::common::TwiOps;
+use crate::{delay::delay_ms, time::micros};
 use bit_field::BitField;
-use fixed_slice_vec::FixedSliceVec;
 
 const MPU6050_ADDRESS: u8 = 0x68; // 0x69 when AD0 pin to Vcc
-const _MPU6050_REG_ACCEL_XOFFS_H: u8 = 0x06; //defining registers for accelerometer X,Y & Z axis for high(H) and low(L).
+const MPU6050_I2C_FREQUENCY: u32 = 400_000;
+const MPU6050_REG_ACCEL_XOFFS_H: u8 = 0x06; //defining registers for accelerometer X,Y & Z axis for high(H) and low(L).
 const _MPU6050_REG_ACCEL_XOFFS_L: u8 = 0x07;
 const _MPU6050_REG_ACCEL_YOFFS_H: u8 = 0x08;
 const _MPU6050_REG_ACCEL_YOFFS_L: u8 = 0x09;
@@ -31,7 +36,7 @@ const _MPU6050_REG_ACCEL_ZOFFS_H: u8 = 0x0A;
 const _MPU6050_REG_ACCEL_ZOFFS_L: u8 = 0x0B;
 // Register for sample rate division
 const _MPU6050_REG_ACCEL_SMPLRT_DIV: u8 = 0x0C;
-const _MPU6050_REG_GYRO_XOFFS_H: u8 = 0x13; //Defining registers for gyroscope X,Y & Z axis for high(H) and low(L).
+const MPU6050_REG_GYRO_XOFFS_H: u8 = 0x13; //Defining registers for gyroscope X,Y & Z axis for high(H) and low(L).
 const _MPU6050_REG_GYRO_XOFFS_L: u8 = 0x14;
 const _MPU6050_REG_GYRO_YOFFS_H: u8 = 0x15;
 const _MPU6050_REG_GYRO_YOFFS_L: u8 = 0x16;
@@ -56,8 +61,12 @@ const MPU6050_REG_MOT_DURATION: u8 = 0x20;
 const MPU6050_REG_ZMOT_THRESHOLD: u8 = 0x21;
 const MPU6050_REG_ZMOT_DURATION: u8 = 0x22;
 
+// This register divides the gyroscope output rate to give the sample rate.
+// Used in function : `set_sample_rate_divider()`
+const MPU6050_REG_SMPLRT_DIV: u8 = 0x19;
+
 // This register determines which sensor measurements are loaded into the FIFO buffer.
-const _MPU6050_REG_FIFO_EN: u8 = 0x23;
+const MPU6050_REG_FIFO_EN: u8 = 0x23;
 
 // This register configures the auxiliary I2C bus for single-master or multi-master control.
 const _MPU6050_REG_I2C_MST_CTRL: u8 = 0x24;
@@ -150,9 +159,9 @@ const MPU6050_REG_MOT_DETECT_CTRL: u8 = 0x69;
 const MPU6050_REG_USER_CTRL: u8 = 0x6A; // User Control
 const MPU6050_REG_PWR_MGMT_1: u8 = 0x6B; // Power Management 1
 const _MPU6050_REG_PWR_MGMT_2: u8 = 0x6C;
-const _MPU6050_REG_FIFO_COUNTH: u8 = 0x72;
+const MPU6050_REG_FIFO_COUNTH: u8 = 0x72;
 const _MPU6050_REG_FIFO_COUNTL: u8 = 0x73;
-const _MPU6050_REG_FIFO_R_W: u8 = 0x74;
+const MPU6050_REG_FIFO_R_W: u8 = 0x74;
 const _MPU6050_REG_WHO_AM_I: u8 = 0x75; // Who Am I
 
 /// Selection of Source of the clock.
@@ -176,6 +185,18 @@ pub enum MPUdpsT {
     MPU6050Scale250DPS,
 }
 
+impl MPUdpsT {
+    /// Gives the number of LSB of the gyroscope output for one degree per second.
+    pub fn lsb_per_dps(&self) -> f32 {
+        match self {
+            MPUdpsT::MPU6050Scale2000DPS => 16.4,
+            MPUdpsT::MPU6050Scale1000DPS => 32.8,
+            MPUdpsT::MPU6050Scale500DPS => 65.5,
+            MPUdpsT::MPU6050Scale250DPS => 131.0,
+        }
+    }
+}
+
 /// Selection of bandwidth range of clock for MPU6050.
 #[derive(Clone, Copy)]
 pub enum MPURangeT {
@@ -185,6 +206,18 @@ pub enum MPURangeT {
     MPU6050Range16G,
 }
 
+impl MPURangeT {
+    /// Gives the number of LSB of the accelerometer output for one g.
+    pub fn lsb_per_g(&self) -> f32 {
+        match self {
+            MPURangeT::MPU6050Range2G => 16384.0,
+            MPURangeT::MPU6050Range4G => 8192.0,
+            MPURangeT::MPU6050Range8G => 4096.0,
+            MPURangeT::MPU6050Range16G => 2048.0,
+        }
+    }
+}
+
 /// One cycle delay time selection.
 #[derive(Clone, Copy)]
 pub enum MPUOnDelayT {
@@ -219,38 +252,79 @@ pub enum MPUdlpfT {
 
 /// Controls the MPU6050 Gyroscopic Sensor.
 /// # Elements
-/// * `address` - a u8, used to store the address to control the functioning AHT10 sensor.
-/// * `accel_output` - a vector with u8 objects, It would be used to store the two byte accelerometer data read through the sensors.
-/// * `gyro_output` - a vector with u8 objects, It would be used to store the two byte gyroscopic data read through the sensors.
-#[repr(C, packed)]
-pub struct MPU6050<'a> {
-    pub address: u8,
-    pub accel_output: FixedSliceVec<'a, f32>,
-    pub gyro_output: FixedSliceVec<'a, f32>,
+/// * `device` - a `I2cDevice` object, the handle of the sensor on the I2C bus.
+/// * `accel_output` - an array of 3 f32, the last raw accelerometer values of the x, y and z axes.
+/// * `gyro_output` - an array of 3 f32, the last raw gyroscope values of the x, y and z axes.
+pub struct MPU6050<T = SharedTwi> {
+    device: I2cDevice<T>,
+    pub accel_output: [f32; 3],
+    pub gyro_output: [f32; 3],
+}
+
+impl MPU6050 {
+    /// Creates the sensor on the TWI of the chip, with AD0 to ground.
+    /// # Returns
+    /// * `a MPU6050 object` - To control the sensor through I2C data protocol.
+    pub fn new() -> MPU6050 {
+        MPU6050::with_bus(SharedTwi::new())
+    }
+}
+
+impl Default for MPU6050 {
+    fn default() -> MPU6050 {
+        MPU6050::new()
+    }
 }
 
-impl<'a> MPU6050<'a> {
-    /// Creates a mutable refernce to the struct to be used in the implementations.
+impl<T: TwiOps> MPU6050<T> {
+    /// Creates the sensor on the given I2C bus, with AD0 to ground.
+    /// # Arguments
+    /// * `bus` - a `TwiOps` object, the I2C bus of the sensor.
     /// # Returns
     /// * `a MPU6050 object` - To control the sensor through I2C data protocol.
-    pub fn new() -> &'static mut Self {
-        unsafe { &mut *(0x00 as *mut Self) }
+    pub fn with_bus(bus: T) -> MPU6050<T> {
+        MPU6050 {
+            device: I2cDevice::with_bus(bus, MPU6050_ADDRESS, MPU6050_I2C_FREQUENCY),
+            accel_output: [0.0; 3],
+            gyro_output: [0.0; 3],
+        }
     }
 
     fn readregister(&mut self, reg: u8) -> u8 {
-        let mut vec1: FixedSliceVec<u8> = FixedSliceVec::new(&mut []);
-        vec1.push(reg);
-        let i2c = i2c::Twi::new();
-        i2c.read_from_slave(MPU6050_ADDRESS, 1, &mut vec1);
-        return vec1[1];
+        let mut value = [0];
+        self.readregisters(reg, &mut value);
+        return value[0];
     }
 
     fn writeregister(&mut self, reg: u8, value: u8) {
-        let mut vec2: FixedSliceVec<u8> = FixedSliceVec::new(&mut []);
-        vec2.push(reg);
-        vec2.push(value);
-        let i2c = i2c::Twi::new();
-        i2c.write_to_slave(MPU6050_ADDRESS, &vec2);
+        let _ = self.device.write_register(reg, value);
+    }
+
+    /// Reads consecutive registers starting from `reg`, returns false if the transfer failed.
+    fn readregisters(&mut self, reg: u8, buffer: &mut [u8]) -> bool {
+        self.device.read_registers(reg, buffer).is_ok()
+    }
+
+    /// Reads consecutive big endian 16 bit registers starting from `reg`.
+    fn readregisters_i16(&mut self, reg: u8) -> Option<[i16; 3]> {
+        let mut v = [0u8; 6];
+        if !self.readregisters(reg, &mut v) {
+            return None;
+        }
+        Some([
+            i16::from_be_bytes([v[0], v[1]]),
+            i16::from_be_bytes([v[2], v[3]]),
+            i16::from_be_bytes([v[4], v[5]]),
+        ])
+    }
+
+    /// Writes three big endian 16 bit registers starting from `reg`.
+    fn writeregisters_i16(&mut self, reg: u8, values: [i16; 3]) {
+        for (i, value) in values.iter().enumerate() {
+            let bytes = value.to_be_bytes();
+            self.writeregister(reg + 2 * i as u8, bytes[0]);
+            self.writeregister(reg + 2 * i as u8 + 1, bytes[1]);
+        }
     }
 
     fn writeregister_bit(&mut self, reg: u8, pos: u8, state: bool) {
@@ -536,35 +610,269 @@ impl<'a> MPU6050<'a> {
 
     /// Reads the three, two-byte accelerometer values from the sensor.
     /// Returns the two-byte raw accelerometer values as a 32-bit float.
-    /// The vec accel_output stores the raw values of the accelerometer where `accel_output[0]` is the x-axis, `accel_output[1]` is the y-axis and `accel_output[2]` is the z-axis output respectively. These raw values are then converted to g's per second according to the scale given as input in `begin()` function.
+    /// The array accel_output stores the raw values of the accelerometer where `accel_output[0]` is the x-axis, `accel_output[1]` is the y-axis and `accel_output[2]` is the z-axis output respectively. These raw values are then converted to g's per second according to the scale given as input in `begin()` function.
     pub fn read_accel(&mut self) {
-        let mut v: FixedSliceVec<u8> = FixedSliceVec::new(&mut []);
-        v.push(MPU6050_REG_ACCEL_XOUT_H);
-        let i2c = i2c::Twi::new();
-        i2c.read_from_slave(MPU6050_ADDRESS, 6, &mut v); //input from slave
-        self.accel_output
-            .push((((v[1] as u16) << 8) | (v[2] as u16)) as f32); //input of X axis
-        self.accel_output
-            .push((((v[3] as u16) << 8) | (v[4] as u16)) as f32); //input of Y axis
-        self.accel_output
-            .push((((v[5] as u16) << 8) | (v[6] as u16)) as f32); //input of Z axis
+        if let Some(v) = self.readregisters_i16(MPU6050_REG_ACCEL_XOUT_H) {
+            self.accel_output = [v[0] as f32, v[1] as f32, v[2] as f32];
+        }
     }
 
     /// Reads the three, two-byte gyroscope values from the sensor.
     /// Returns the two-byte raw gyroscope values as a 32-bit float.
-    /// The vec gyro_output stores the raw values of the gyroscope where `gyro_output[0]` is the x-axis, `gyro_output[1]` is the y-axis and `gyro_output[2]` is the z-axis output respectively. These raw values are then converted to degrees per second according to the scale given as input in `begin()` function.
+    /// The array gyro_output stores the raw values of the gyroscope where `gyro_output[0]` is the x-axis, `gyro_output[1]` is the y-axis and `gyro_output[2]` is the z-axis output respectively. These raw values are then converted to degrees per second according to the scale given as input in `begin()` function.
     pub fn read_gyro(&mut self) {
-        let mut v: FixedSliceVec<u8> = FixedSliceVec::new(&mut []);
-        v.push(MPU6050_REG_GYRO_XOUT_H);
-        let i2c = i2c::Twi::new();
+        if let Some(v) = self.readregisters_i16(MPU6050_REG_GYRO_XOUT_H) {
+            self.gyro_output = [v[0] as f32, v[1] as f32, v[2] as f32];
+        }
+    }
 
-        i2c.read_from_slave(MPU6050_ADDRESS, 6, &mut v); //input from slave
-        self.gyro_output
-            .push((((v[1] as u16) << 8) | (v[2] as u16)) as f32); //input of X axis
-        self.gyro_output
-            .push((((v[3] as u16) << 8) | (v[4] as u16)) as f32); //input of Y axis
-        self.gyro_output
-            .push((((v[5] as u16) << 8) | (v[6] as u16)) as f32); //input of Z axis
+    /// Reads the accelerometer and gyroscope together, so that both belong to the same sample.
+    /// # Returns
+    /// * `a Option<([i16; 3], [i16; 3])>` - the raw accelerometer and gyroscope values of the
+    ///   x, y and z axes, or None if the I2C transfer failed.
+    pub fn read_raw(&mut self) -> Option<([i16; 3], [i16; 3])> {
+        let mut v = [0u8; 14];
+        if !self.readregisters(MPU6050_REG_ACCEL_XOUT_H, &mut v) {
+            return None;
+        }
+        // Bytes 6 and 7 hold the temperature.
+        let word = |i: usize| i16::from_be_bytes([v[i], v[i + 1]]);
+        Some(([word(0), word(2), word(4)], [word(8), word(10), word(12)]))
+    }
+
+    /// Reads the accelerometer in g and the gyroscope in degrees per second,
+    /// using the range and scale currently set.
+    /// # Returns
+    /// * `a Option<([f32; 3], [f32; 3])>` - the acceleration and the rotation rate of the
+    ///   x, y and z axes, or None if the I2C transfer failed.
+    pub fn read_scaled(&mut self) -> Option<([f32; 3], [f32; 3])> {
+        let accel_lsb = self.get_range().lsb_per_g();
+        let gyro_lsb = self.get_scale().lsb_per_dps();
+        let (accel, gyro) = self.read_raw()?;
+        let mut a = [0.0; 3];
+        let mut g = [0.0; 3];
+        for i in 0..3 {
+            a[i] = accel[i] as f32 / accel_lsb;
+            g[i] = gyro[i] as f32 / gyro_lsb;
+        }
+        Some((a, g))
+    }
+
+    /// Sets the divider of the gyroscope output rate, which is 8 kHz, or 1 kHz when the
+    /// DLPF is enabled. The sample rate is the output rate divided by (1 + divider).
+    pub fn set_sample_rate_divider(&mut self, divider: u8) {
+        self.writeregister(MPU6050_REG_SMPLRT_DIV, divider);
+    }
+
+    /// Get the divider of the gyroscope output rate currently set.
+    pub fn get_sample_rate_divider(&mut self) -> u8 {
+        return self.readregister(MPU6050_REG_SMPLRT_DIV);
+    }
+
+    /// Sets the values in the gyroscope offset registers, which are subtracted from the
+    /// output by the sensor, in units of the 1000 DPS scale.
+    pub fn set_gyro_offsets(&mut self, offsets: [i16; 3]) {
+        self.writeregisters_i16(MPU6050_REG_GYRO_XOFFS_H, offsets);
+    }
+
+    /// Get the values in the gyroscope offset registers.
+    pub fn get_gyro_offsets(&mut self) -> Option<[i16; 3]> {
+        self.readregisters_i16(MPU6050_REG_GYRO_XOFFS_H)
+    }
+
+    /// Sets the values in the accelerometer offset registers, in units of the 16G range.
+    /// These registers hold factory trimmed values, whose lowest bit must be kept.
+    pub fn set_accel_offsets(&mut self, offsets: [i16; 3]) {
+        self.writeregisters_i16(MPU6050_REG_ACCEL_XOFFS_H, offsets);
+    }
+
+    /// Get the values in the accelerometer offset registers.
+    pub fn get_accel_offsets(&mut self) -> Option<[i16; 3]> {
+        self.readregisters_i16(MPU6050_REG_ACCEL_XOFFS_H)
+    }
+
+    /// Averages a number of samples, 2 milliseconds apart, at the 250 DPS scale and 2G range.
+    fn average_raw(&mut self, samples: u16) -> Option<([i32; 3], [i32; 3])> {
+        let mut accel = [0i32; 3];
+        let mut gyro = [0i32; 3];
+        for _ in 0..samples {
+            let (a, g) = self.read_raw()?;
+            for i in 0..3 {
+                accel[i] += a[i] as i32;
+                gyro[i] += g[i] as i32;
+            }
+            delay_ms(2);
+        }
+        for i in 0..3 {
+            accel[i] /= samples.max(1) as i32;
+            gyro[i] /= samples.max(1) as i32;
+        }
+        Some((accel, gyro))
+    }
+
+    /// Measures the gyroscope while the sensor is kept still and corrects the offset
+    /// registers so that the output at rest is zero. The scale is restored afterwards.
+    /// The offsets returned can be stored, in EEPROM for example, and given back with
+    /// `set_gyro_offsets` at the next start instead of calibrating again.
+    /// # Arguments
+    /// * `samples` - a u16, the number of samples averaged, a few hundred is usual.
+    /// # Returns
+    /// * `a Option<[i16; 3]>` - the new offsets, or None if the I2C transfer failed.
+    pub fn calibrate_gyro(&mut self, samples: u16) -> Option<[i16; 3]> {
+        let scale = self.get_scale();
+        self.set_scale(MPUdpsT::MPU6050Scale250DPS);
+        let result = self.calibrate_gyro_at_250dps(samples);
+        self.set_scale(scale);
+        result
+    }
+
+    fn calibrate_gyro_at_250dps(&mut self, samples: u16) -> Option<[i16; 3]> {
+        let mut offsets = self.get_gyro_offsets()?;
+        let (_, gyro) = self.average_raw(samples)?;
+        for i in 0..3 {
+            // The registers count in steps of the 1000 DPS scale, 4 times coarser than 250 DPS.
+            offsets[i] = offsets[i].wrapping_sub((gyro[i] / 4) as i16);
+        }
+        self.set_gyro_offsets(offsets);
+        Some(offsets)
+    }
+
+    /// Measures the accelerometer while the sensor lies still and level with the z axis
+    /// up, and corrects the offset registers so that the output is 0, 0 and 1 g.
+    /// The range is restored afterwards.
+    /// # Arguments
+    /// * `samples` - a u16, the number of samples averaged, a few hundred is usual.
+    /// # Returns
+    /// * `a Option<[i16; 3]>` - the new offsets, or None if the I2C transfer failed.
+    pub fn calibrate_accel(&mut self, samples: u16) -> Option<[i16; 3]> {
+        let range = self.get_range();
+        self.set_range(MPURangeT::MPU6050Range2G);
+        let result = self.calibrate_accel_at_2g(samples);
+        self.set_range(range);
+        result
+    }
+
+    fn calibrate_accel_at_2g(&mut self, samples: u16) -> Option<[i16; 3]> {
+        let mut offsets = self.get_accel_offsets()?;
+        let (mut accel, _) = self.average_raw(samples)?;
+        // 1 g is expected on the z axis.
+        accel[2] -= MPURangeT::MPU6050Range2G.lsb_per_g() as i32;
+        for i in 0..3 {
+            // The registers count in steps of the 16G range, 8 times coarser than 2G,
+            // and their lowest bit is kept.
+            let corrected = offsets[i].wrapping_sub((accel[i] / 8) as i16);
+            offsets[i] = (corrected & !1) | (offsets[i] & 1);
+        }
+        self.set_accel_offsets(offsets);
+        Some(offsets)
+    }
+
+    /// Chooses the measurements written to the FIFO at each sample and enables it.
+    /// Each sample takes 6 bytes for the accelerometer, then 6 bytes for the gyroscope,
+    /// in the order of the output registers.
+    /// # Arguments
+    /// * `accel` - a boolean, true to store the accelerometer.
+    /// * `gyro` - a boolean, true to store the three gyroscope axes.
+    pub fn enable_fifo(&mut self, accel: bool, gyro: bool) {
+        let mut fifo_en = 0u8;
+        fifo_en.set_bit(3, accel);
+        fifo_en.set_bits(4..7, if gyro { 0b111 } else { 0 });
+        self.writeregister(MPU6050_REG_FIFO_EN, fifo_en);
+        self.reset_fifo();
+        self.writeregister_bit(MPU6050_REG_USER_CTRL, 6, accel || gyro);
+    }
+
+    /// Stops writing to the FIFO.
+    pub fn disable_fifo(&mut self) {
+        self.writeregister_bit(MPU6050_REG_USER_CTRL, 6, false);
+        self.writeregister(MPU6050_REG_FIFO_EN, 0);
+    }
+
+    /// Empties the FIFO, which is also needed after it overflowed.
+    pub fn reset_fifo(&mut self) {
+        self.writeregister_bit(MPU6050_REG_USER_CTRL, 2, true);
+    }
+
+    /// Gives the number of bytes waiting in the FIFO, which holds up to 1024.
+    pub fn get_fifo_count(&mut self) -> u16 {
+        let mut v = [0u8; 2];
+        self.readregisters(MPU6050_REG_FIFO_COUNTH, &mut v);
+        u16::from_be_bytes(v)
+    }
+
+    /// Checks whether the FIFO overflowed, which clears the flag with the other
+    /// interrupt flags.
+    pub fn get_fifo_overflow(&mut self) -> bool {
+        self.get_int_status().get_bit(4)
+    }
+
+    /// Reads bytes from the FIFO.
+    /// # Arguments
+    /// * `buffer` - a mutable slice of u8, which will be filled with the bytes.
+    /// # Returns
+    /// * `a usize` - the number of bytes read, limited by the bytes waiting.
+    pub fn read_fifo(&mut self, buffer: &mut [u8]) -> usize {
+        let count = (self.get_fifo_count() as usize).min(buffer.len());
+        if count == 0 || !self.readregisters(MPU6050_REG_FIFO_R_W, &mut buffer[..count]) {
+            return 0;
+        }
+        count
+    }
+
+    /// Reads one sample of accelerometer and gyroscope from the FIFO, once it was enabled
+    /// with `enable_fifo(true, true)`.
+    /// # Returns
+    /// * `a Option<([i16; 3], [i16; 3])>` - the raw values, or None if no whole sample is waiting.
+    pub fn read_fifo_sample(&mut self) -> Option<([i16; 3], [i16; 3])> {
+        if self.get_fifo_count() < 12 {
+            return None;
+        }
+        let mut v = [0u8; 12];
+        if !self.readregisters(MPU6050_REG_FIFO_R_W, &mut v) {
+            return None;
+        }
+        let word = |i: usize| i16::from_be_bytes([v[i], v[i + 1]]);
+        Some(([word(0), word(2), word(4)], [word(6), word(8), word(10)]))
+    }
+
+    /// Estimates roll and pitch by fusing the gyroscope with the direction of gravity
+    /// measured by the accelerometer. The time since the last call is measured with
+    /// `time::micros()`, so `time::init()` must be called first, and the function should
+    /// be called regularly, every 10 milliseconds for example.
+    /// # Arguments
+    /// * `filter` - a mutable reference to a `ComplementaryFilter`, the state of the estimate.
+    /// # Returns
+    /// * `a Option<Orientation>` - the roll and pitch in degrees, or None if the I2C transfer failed.
+    pub fn get_orientation(&mut self, filter: &mut ComplementaryFilter) -> Option<Orientation> {
+        let gyro_lsb = self.get_scale().lsb_per_dps();
+        let (accel, gyro) = self.read_raw()?;
+        let now = micros();
+        let (ax, ay, az) = (accel[0] as f32, accel[1] as f32, accel[2] as f32);
+
+        // Angles given by gravity alone, right whenever the sensor is not accelerating.
+        let accel_roll = atan2(ay, az) * RAD_TO_DEG;
+        let accel_pitch = atan2(-ax, sqrt(ay * ay + az * az)) * RAD_TO_DEG;
+
+        match filter.last_micros {
+            None => {
+                filter.orientation = Orientation {
+                    roll: accel_roll,
+                    pitch: accel_pitch,
+                };
+            }
+            Some(last) => {
+                let dt = now.wrapping_sub(last) as f32 / 1_000_000.0;
+                let roll_rate = gyro[0] as f32 / gyro_lsb;
+                let pitch_rate = gyro[1] as f32 / gyro_lsb;
+                let alpha = filter.alpha;
+                filter.orientation.roll =
+                    alpha * (filter.orientation.roll + roll_rate * dt) + (1.0 - alpha) * accel_roll;
+                filter.orientation.pitch = alpha * (filter.orientation.pitch + pitch_rate * dt)
+                    + (1.0 - alpha) * accel_pitch;
+            }
+        }
+        filter.last_micros = Some(now);
+        Some(filter.orientation)
     }
 
     /// Starts the sensor by setting the device to active mode ,setting the accelerometer range and gyroscope scale.
@@ -586,3 +894,142 @@ impl<'a> MPU6050<'a> {
         return true;
     }
 }
+
+const RAD_TO_DEG: f32 = 180.0 / core::f32::consts::PI;
+
+/// Roll and pitch of the sensor in degrees.
+/// # Elements
+/// * `roll` - a f32, the rotation around the x axis.
+/// * `pitch` - a f32, the rotation around the y axis.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Orientation {
+    pub roll: f32,
+    pub pitch: f32,
+}
+
+/// State of the complementary filter used by `get_orientation`.
+/// # Elements
+/// * `alpha` - a f32, the weight of the gyroscope, between 0 and 1.
+/// * `orientation` - a `Orientation` object, the last estimate.
+/// * `last_micros` - a `Option<u32>`, the time of the last estimate, None before the first.
+pub struct ComplementaryFilter {
+    pub alpha: f32,
+    orientation: Orientation,
+    last_micros: Option<u32>,
+}
+
+impl ComplementaryFilter {
+    /// Creates the filter.
+    /// # Arguments
+    /// * `alpha` - a f32, the weight of the gyroscope. 0.98 is usual, higher values follow
+    ///   the gyroscope longer and are less disturbed by vibrations, but let it drift more.
+    /// # Returns
+    /// * `a ComplementaryFilter object` - which starts from the accelerometer at its first use.
+    pub fn new(alpha: f32) -> ComplementaryFilter {
+        ComplementaryFilter {
+            alpha,
+            orientation: Orientation {
+                roll: 0.0,
+                pitch: 0.0,
+            },
+            last_micros: None,
+        }
+    }
+
+    /// Starts again from the accelerometer at the next estimate.
+    pub fn reset(&mut self) {
+        self.last_micros = None;
+    }
+}
+
+/// Square root by Newton's method, since `core` has none.
+fn sqrt(x: f32) -> f32 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    // Halving the exponent gives a first guess within a few percent.
+    let mut y = f32::from_bits((x.to_bits() >> 1) + 0x1FBD_1DF5);
+    for _ in 0..3 {
+        y = 0.5 * (y + x / y);
+    }
+    y
+}
+
+/// Arc tangent of y / x in the right quadrant, in radians, within 0.1 degree.
+pub(super) fn atan2(y: f32, x: f32) -> f32 {
+    use core::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+    let atan = |z: f32| {
+        let abs = if z < 0.0 { -z } else { z };
+        FRAC_PI_4 * z - z * (abs - 1.0) * (0.2447 + 0.0663 * abs)
+    };
+    let (abs_x, abs_y) = (if x < 0.0 { -x } else { x }, if y < 0.0 { -y } else { y });
+    if x == 0.0 && y == 0.0 {
+        return 0.0;
+    }
+    if abs_x >= abs_y {
+        let angle = atan(y / x);
+        if x > 0.0 {
+            angle
+        } else if y >= 0.0 {
+            angle + PI
+        } else {
+            angle - PI
+        }
+    } else {
+        let angle = -atan(x / y);
+        if y > 0.0 {
+            angle + FRAC_PI_2
+        } else {
+            angle - FRAC_PI_2
+        }
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod test {
+    use super::*;
+    use crate::mock::MockTwi;
+
+    #[test]
+    fn begin_and_read_scaled() {
+        let mut twi = MockTwi::new();
+        twi.add_device(MPU6050_ADDRESS).unwrap();
+        // The sensor starts asleep.
+        twi.set_register(MPU6050_ADDRESS, MPU6050_REG_PWR_MGMT_1, 0x40);
+
+        let mut sensor = MPU6050::with_bus(&mut twi);
+        assert!(sensor.begin(MPUdpsT::MPU6050Scale500DPS, MPURangeT::MPU6050Range4G));
+        drop(sensor);
+        assert_eq!(
+            twi.register(MPU6050_ADDRESS, MPU6050_REG_PWR_MGMT_1),
+            Some(0x01)
+        );
+        assert_eq!(
+            twi.register(MPU6050_ADDRESS, MPU6050_REG_GYRO_CONFIG),
+            Some(0x08)
+        );
+        assert_eq!(
+            twi.register(MPU6050_ADDRESS, MPU6050_REG_ACCEL_CONFIG),
+            Some(0x08)
+        );
+
+        // Lying flat and turning: 1 g on z, -0.5 g on y, 10 and -2 degrees per second.
+        twi.set_registers(
+            MPU6050_ADDRESS,
+            MPU6050_REG_ACCEL_XOUT_H,
+            &[
+                0x00, 0x00, 0xF0, 0x00, 0x20, 0x00, 0x00, 0x00, 0x02, 0x8F, 0x00, 0x00, 0xFF, 0xBE,
+            ],
+        );
+        let mut sensor = MPU6050::with_bus(&mut twi);
+        assert_eq!(sensor.read_raw(), Some(([0, -4096, 8192], [655, 0, -66])));
+        let (accel, gyro) = sensor.read_scaled().unwrap();
+        assert_eq!(accel, [0.0, -0.5, 1.0]);
+        assert!((gyro[0] - 10.0).abs() < 0.01);
+        assert_eq!(gyro[1], 0.0);
+        assert!((gyro[2] + 1.0).abs() < 0.01);
+
+        sensor.read_accel();
+        assert_eq!(sensor.accel_output, [0.0, -4096.0, 8192.0]);
+    }
+}
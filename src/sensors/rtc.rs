@@ -0,0 +1,476 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! This code controls the DS3231 and DS1307 real-time clocks over I2C.
+//! Both keep the date and time in BCD registers at the same addresses and run from a
+//! backup battery while the board is off. The DS3231 also has two alarms, which can pull
+//! its INT/SQW pin low, and a temperature sensor used to compensate its oscillator.
+//! The DS1307 has instead 56 bytes of battery backed RAM.
+//! The time is always kept in the 24 hour format, and years from 2000 to 2099.
+//! See `<https://datasheets.maximintegrated.com/en/ds/DS3231.pdf>` and
+//! `<https://datasheets.maximintegrated.com/en/ds/DS1307.pdf>`.
+
+// Source codes required.
+use crate::bus::{I2cDevice, SharedTwi};
+use crate::common::TwiOps;
+use crate::error::Error;
+
+/// I2C address of both clocks.
+pub const RTC_I2C_ADDRESS: u8 = 0x68;
+
+// SCL frequency used with the clocks, which the DS1307 limits to 100 kHz.
+const I2C_FREQUENCY: u32 = 100_000;
+
+// Registers of both clocks.
+const REG_SECONDS: u8 = 0x00;
+
+// Registers of the DS3231.
+const REG_ALARM1: u8 = 0x07;
+const REG_ALARM2: u8 = 0x0B;
+const REG_CONTROL: u8 = 0x0E;
+const REG_STATUS: u8 = 0x0F;
+const REG_TEMPERATURE: u8 = 0x11;
+
+// Registers of the DS1307.
+const REG_DS1307_CONTROL: u8 = 0x07;
+const REG_DS1307_RAM: u8 = 0x08;
+
+/// Size of the RAM of the DS1307 in bytes.
+pub const DS1307_RAM_SIZE: u8 = 56;
+
+// Bits of the registers.
+const CLOCK_HALT: u8 = 0x80;
+const HOURS_12: u8 = 0x40;
+const CONTROL_INTCN: u8 = 0x04;
+const STATUS_OSF: u8 = 0x80;
+const ALARM_MASK: u8 = 0x80;
+const ALARM_WEEKDAY: u8 = 0x40;
+
+/// Errors which can happen while using the clock.
+/// * `Bus` - an I2C transfer failed.
+/// * `InvalidDateTime` - a field of the date or time is out of its range.
+/// * `Unsupported` - the feature does not exist on this clock.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RtcError {
+    Bus,
+    InvalidDateTime,
+    Unsupported,
+}
+
+impl From<RtcError> for Error {
+    fn from(error: RtcError) -> Error {
+        match error {
+            RtcError::Bus => Error::Bus,
+            RtcError::InvalidDateTime => Error::InvalidParam,
+            RtcError::Unsupported => Error::Unsupported,
+        }
+    }
+}
+
+/// Kind of clock.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RtcChip {
+    DS1307,
+    DS3231,
+}
+
+/// Date and time kept by the clock.
+/// # Elements
+/// * `year` - a u16, from 2000 to 2099.
+/// * `month` - a u8, from 1 to 12.
+/// * `day` - a u8, the day of the month from 1 to 31.
+/// * `weekday` - a u8, from 1 to 7, whose meaning is chosen by the user.
+/// * `hour` - a u8, from 0 to 23.
+/// * `minute` - a u8, from 0 to 59.
+/// * `second` - a u8, from 0 to 59.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct DateTime {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub weekday: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl DateTime {
+    /// Checks that every field is in its range.
+    pub fn is_valid(&self) -> bool {
+        (2000..=2099).contains(&self.year)
+            && (1..=12).contains(&self.month)
+            && self.day >= 1
+            && self.day <= days_in_month(self.year, self.month)
+            && (1..=7).contains(&self.weekday)
+            && self.hour < 24
+            && self.minute < 60
+            && self.second < 60
+    }
+
+    /// Gives the number of seconds since 1 January 1970 at midnight, the Unix time,
+    /// taking the date and time as UTC.
+    /// # Returns
+    /// * `a u32` - the Unix time.
+    pub fn to_unix(&self) -> u32 {
+        let mut days: u32 = 0;
+        for year in 1970..self.year {
+            days += if is_leap_year(year) { 366 } else { 365 };
+        }
+        for month in 1..self.month {
+            days += days_in_month(self.year, month) as u32;
+        }
+        days += self.day as u32 - 1;
+        ((days * 24 + self.hour as u32) * 60 + self.minute as u32) * 60 + self.second as u32
+    }
+}
+
+/// Checks whether a year has 366 days.
+fn is_leap_year(year: u16) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+/// Gives the number of days of a month.
+fn days_in_month(year: u16, month: u8) -> u8 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Alarms of the DS3231.
+/// * `Alarm1` - the alarm with seconds.
+/// * `Alarm2` - the alarm without seconds, which always fires at second 0.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Alarm {
+    Alarm1,
+    Alarm2,
+}
+
+/// Fields compared with the time to fire an alarm.
+/// * `EveryMinute` - once a minute for alarm 2, or every second for alarm 1.
+/// * `Second` - when the seconds match, once a minute (alarm 1 only).
+/// * `Minute` - when the minutes (and seconds) match, once an hour.
+/// * `Hour` - when the hours and minutes (and seconds) match, once a day.
+/// * `Day` - when the day of the month and the time match, once a month.
+/// * `Weekday` - when the day of the week and the time match, once a week.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AlarmMatch {
+    EveryMinute,
+    Second,
+    Minute,
+    Hour,
+    Day,
+    Weekday,
+}
+
+/// Used to control a DS3231 or DS1307 real-time clock.
+/// # Elements
+/// * `device` - a `I2cDevice` object, the handle of the clock on the I2C bus.
+/// * `chip` - a `RtcChip` object, the kind of clock.
+pub struct RTC<T = SharedTwi> {
+    device: I2cDevice<T>,
+    chip: RtcChip,
+}
+
+impl RTC {
+    /// Creates the clock on the TWI of the chip, which is initialized by the first transfer.
+    /// # Arguments
+    /// * `chip` - a `RtcChip` object, the kind of clock.
+    /// # Returns
+    /// * `a RTC object` - which will be used to read and set the time.
+    pub fn new(chip: RtcChip) -> RTC {
+        RTC::with_bus(SharedTwi::new(), chip)
+    }
+}
+
+impl<T: TwiOps> RTC<T> {
+    /// Creates the clock on the given I2C bus.
+    /// # Arguments
+    /// * `bus` - a `TwiOps` object, the I2C bus of the clock.
+    /// * `chip` - a `RtcChip` object, the kind of clock.
+    /// # Returns
+    /// * `a RTC object` - which will be used to read and set the time.
+    pub fn with_bus(bus: T, chip: RtcChip) -> RTC<T> {
+        RTC {
+            device: I2cDevice::with_bus(bus, RTC_I2C_ADDRESS, I2C_FREQUENCY),
+            chip,
+        }
+    }
+
+    /// Gives the kind of clock.
+    pub fn chip(&self) -> RtcChip {
+        self.chip
+    }
+
+    /// Reads the date and time.
+    /// # Returns
+    /// * `a Result<DateTime, RtcError>` - the date and time.
+    pub fn now(&mut self) -> Result<DateTime, RtcError> {
+        // The seven registers are read at once, so that they belong to the same second.
+        let mut r = [0u8; 7];
+        self.read_registers(REG_SECONDS, &mut r)?;
+        Ok(DateTime {
+            second: bcd_to_bin(r[0] & 0x7F),
+            minute: bcd_to_bin(r[1] & 0x7F),
+            hour: hours_to_bin(r[2]),
+            weekday: bcd_to_bin(r[3] & 0x07),
+            day: bcd_to_bin(r[4] & 0x3F),
+            // The century bit of the DS3231 is ignored, the years are 2000 to 2099.
+            month: bcd_to_bin(r[5] & 0x1F),
+            year: 2000 + bcd_to_bin(r[6]) as u16,
+        })
+    }
+
+    /// Sets the date and time, which also starts a stopped DS1307 and clears the
+    /// oscillator stop flag of the DS3231.
+    /// # Arguments
+    /// * `time` - a `DateTime` object, the new date and time.
+    /// # Returns
+    /// * `a Result<(), RtcError>` - `InvalidDateTime` if a field is out of range.
+    pub fn set(&mut self, time: &DateTime) -> Result<(), RtcError> {
+        if !time.is_valid() {
+            return Err(RtcError::InvalidDateTime);
+        }
+        self.write_registers(
+            REG_SECONDS,
+            &[
+                bin_to_bcd(time.second),
+                bin_to_bcd(time.minute),
+                bin_to_bcd(time.hour),
+                bin_to_bcd(time.weekday),
+                bin_to_bcd(time.day),
+                bin_to_bcd(time.month),
+                bin_to_bcd((time.year - 2000) as u8),
+            ],
+        )?;
+        if self.chip == RtcChip::DS3231 {
+            let status = self.read_register(REG_STATUS)?;
+            self.write_register(REG_STATUS, status & !STATUS_OSF)?;
+        }
+        Ok(())
+    }
+
+    /// Checks whether the time has to be set again, because the DS1307 is stopped or
+    /// the oscillator of the DS3231 stopped, as after the first power on or a flat battery.
+    pub fn lost_power(&mut self) -> Result<bool, RtcError> {
+        match self.chip {
+            RtcChip::DS1307 => Ok(self.read_register(REG_SECONDS)? & CLOCK_HALT != 0),
+            RtcChip::DS3231 => Ok(self.read_register(REG_STATUS)? & STATUS_OSF != 0),
+        }
+    }
+
+    /// Reads the temperature of the DS3231, which is measured every 64 seconds.
+    /// # Returns
+    /// * `a Result<i16, RtcError>` - the temperature in hundredths of degree Celsius,
+    ///   in steps of 0.25 degree.
+    pub fn temperature(&mut self) -> Result<i16, RtcError> {
+        if self.chip != RtcChip::DS3231 {
+            return Err(RtcError::Unsupported);
+        }
+        let mut t = [0u8; 2];
+        self.read_registers(REG_TEMPERATURE, &mut t)?;
+        // A signed byte of whole degrees, then the quarters in the top two bits.
+        Ok(t[0] as i8 as i16 * 100 + (t[1] >> 6) as i16 * 25)
+    }
+
+    /// Sets an alarm of the DS3231. The fields of `time` which are not compared are ignored,
+    /// as are the seconds for alarm 2.
+    /// # Arguments
+    /// * `alarm` - a `Alarm` object, the alarm to be set.
+    /// * `time` - a `DateTime` object, the time of the alarm.
+    /// * `matching` - a `AlarmMatch` object, the fields compared.
+    /// # Returns
+    /// * `a Result<(), RtcError>` - `Unsupported` on the DS1307 or for `Second` on alarm 2.
+    pub fn set_alarm(
+        &mut self,
+        alarm: Alarm,
+        time: &DateTime,
+        matching: AlarmMatch,
+    ) -> Result<(), RtcError> {
+        if self.chip != RtcChip::DS3231 {
+            return Err(RtcError::Unsupported);
+        }
+        // The mask bits A1M1 to A1M4 (or A2M2 to A2M4) skip the seconds, minutes,
+        // hours and day, and DY/DT chooses the day of the week instead of the month.
+        let (mask, weekday) = match matching {
+            AlarmMatch::EveryMinute => (0b1111, false),
+            AlarmMatch::Second => (0b1110, false),
+            AlarmMatch::Minute => (0b1100, false),
+            AlarmMatch::Hour => (0b1000, false),
+            AlarmMatch::Day => (0b0000, false),
+            AlarmMatch::Weekday => (0b0000, true),
+        };
+        let bit = |n: u8| if mask & (1 << n) != 0 { ALARM_MASK } else { 0 };
+        let day = if weekday {
+            bin_to_bcd(time.weekday) | ALARM_WEEKDAY
+        } else {
+            bin_to_bcd(time.day)
+        };
+        let registers = [
+            bin_to_bcd(time.second) | bit(0),
+            bin_to_bcd(time.minute) | bit(1),
+            bin_to_bcd(time.hour) | bit(2),
+            day | bit(3),
+        ];
+        match alarm {
+            Alarm::Alarm1 => self.write_registers(REG_ALARM1, &registers),
+            Alarm::Alarm2 => {
+                if matching == AlarmMatch::Second {
+                    return Err(RtcError::Unsupported);
+                }
+                self.write_registers(REG_ALARM2, &registers[1..])
+            }
+        }
+    }
+
+    /// Enables or disables the INT/SQW pin of the DS3231 going low when an alarm fires.
+    /// The pin stays low until the alarm is cleared with `clear_alarm`.
+    pub fn enable_alarm_interrupt(&mut self, alarm: Alarm, enable: bool) -> Result<(), RtcError> {
+        if self.chip != RtcChip::DS3231 {
+            return Err(RtcError::Unsupported);
+        }
+        let mut control = self.read_register(REG_CONTROL)? | CONTROL_INTCN;
+        if enable {
+            control |= alarm_bit(alarm);
+        } else {
+            control &= !alarm_bit(alarm);
+        }
+        self.write_register(REG_CONTROL, control)
+    }
+
+    /// Checks whether an alarm of the DS3231 has fired, even with its interrupt disabled.
+    pub fn alarm_fired(&mut self, alarm: Alarm) -> Result<bool, RtcError> {
+        if self.chip != RtcChip::DS3231 {
+            return Err(RtcError::Unsupported);
+        }
+        Ok(self.read_register(REG_STATUS)? & alarm_bit(alarm) != 0)
+    }
+
+    /// Clears the flag of an alarm of the DS3231, which releases the INT/SQW pin.
+    pub fn clear_alarm(&mut self, alarm: Alarm) -> Result<(), RtcError> {
+        if self.chip != RtcChip::DS3231 {
+            return Err(RtcError::Unsupported);
+        }
+        let status = self.read_register(REG_STATUS)?;
+        self.write_register(REG_STATUS, status & !alarm_bit(alarm))
+    }
+
+    /// Turns on or off the 1 Hz square wave of the SQW pin, which on the DS3231
+    /// replaces the alarm interrupts.
+    pub fn set_square_wave(&mut self, enable: bool) -> Result<(), RtcError> {
+        match self.chip {
+            RtcChip::DS1307 => {
+                self.write_register(REG_DS1307_CONTROL, if enable { 0x10 } else { 0 })
+            }
+            RtcChip::DS3231 => {
+                // RS2 and RS1 at zero select 1 Hz.
+                let control = self.read_register(REG_CONTROL)? & !0x1C;
+                let intcn = if enable { 0 } else { CONTROL_INTCN };
+                self.write_register(REG_CONTROL, control | intcn)
+            }
+        }
+    }
+
+    /// Reads the battery backed RAM of the DS1307.
+    /// # Arguments
+    /// * `offset` - a u8, the first byte to be read, from 0 to 55.
+    /// * `buffer` - a mutable slice of u8, which will be filled with the bytes.
+    pub fn read_ram(&mut self, offset: u8, buffer: &mut [u8]) -> Result<(), RtcError> {
+        self.check_ram(offset, buffer.len())?;
+        self.read_registers(REG_DS1307_RAM + offset, buffer)
+    }
+
+    /// Writes the battery backed RAM of the DS1307.
+    /// # Arguments
+    /// * `offset` - a u8, the first byte to be written, from 0 to 55.
+    /// * `data` - a slice of u8, the bytes to be written.
+    pub fn write_ram(&mut self, offset: u8, data: &[u8]) -> Result<(), RtcError> {
+        self.check_ram(offset, data.len())?;
+        self.write_registers(REG_DS1307_RAM + offset, data)
+    }
+
+    fn check_ram(&self, offset: u8, length: usize) -> Result<(), RtcError> {
+        if self.chip != RtcChip::DS1307 || offset as usize + length > DS1307_RAM_SIZE as usize {
+            return Err(RtcError::Unsupported);
+        }
+        Ok(())
+    }
+
+    fn read_register(&mut self, register: u8) -> Result<u8, RtcError> {
+        let mut value = [0];
+        self.read_registers(register, &mut value)?;
+        Ok(value[0])
+    }
+
+    fn write_register(&mut self, register: u8, value: u8) -> Result<(), RtcError> {
+        self.write_registers(register, &[value])
+    }
+
+    /// Reads consecutive registers.
+    fn read_registers(&mut self, register: u8, buffer: &mut [u8]) -> Result<(), RtcError> {
+        self.device
+            .read_registers(register, buffer)
+            .map_err(|_| RtcError::Bus)
+    }
+
+    /// Writes consecutive registers.
+    fn write_registers(&mut self, register: u8, data: &[u8]) -> Result<(), RtcError> {
+        self.device
+            .transaction(|i2c, address| {
+                let result = i2c.start().and_then(|_| {
+                    i2c.address_write(address)?;
+                    i2c.write(register)?;
+                    data.iter().try_for_each(|&byte| i2c.write(byte))
+                });
+                i2c.stop();
+                result
+            })
+            .map_err(|_| RtcError::Bus)
+    }
+}
+
+/// Gives the bit of an alarm in the control and status registers.
+fn alarm_bit(alarm: Alarm) -> u8 {
+    match alarm {
+        Alarm::Alarm1 => 0x01,
+        Alarm::Alarm2 => 0x02,
+    }
+}
+
+fn bcd_to_bin(value: u8) -> u8 {
+    (value >> 4) * 10 + (value & 0x0F)
+}
+
+fn bin_to_bcd(value: u8) -> u8 {
+    (value / 10) << 4 | value % 10
+}
+
+/// Converts the hours register to 24 hour format, in case it was set to 12 hours.
+fn hours_to_bin(value: u8) -> u8 {
+    if value & HOURS_12 != 0 {
+        let hour = bcd_to_bin(value & 0x1F) % 12;
+        // Bit 5 is set for PM.
+        if value & 0x20 != 0 {
+            hour + 12
+        } else {
+            hour
+        }
+    } else {
+        bcd_to_bin(value & 0x3F)
+    }
+}
@@ -0,0 +1,800 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! A minimal FAT16 and FAT32 filesystem on a SD card, or any other `BlockDevice`,
+//! enough for data logging.
+//! Files in the root directory can be opened, read, created and appended to, using
+//! their short 8.3 names such as "LOG.TXT". Long file names are skipped, and
+//! subdirectories, deleting and renaming are not supported.
+//! All the work goes through one block buffer of 512 bytes, which is written back
+//! by `flush` and whenever another block is needed, so `flush` must be called before
+//! the card is removed or the power is switched off.
+//! See `<https://academy.cba.mit.edu/classes/networking_communications/SD/FAT.pdf>`.
+
+// Source codes required.
+use crate::error::Error;
+use crate::storage::sdcard::{SdCard, SdError, BLOCK_SIZE};
+
+// Offsets of the fields of a directory entry.
+const DIR_ENTRY_SIZE: usize = 32;
+const DIR_ATTRIBUTES: usize = 11;
+const DIR_CREATE_TIME: usize = 14;
+const DIR_CREATE_DATE: usize = 16;
+const DIR_ACCESS_DATE: usize = 18;
+const DIR_CLUSTER_HIGH: usize = 20;
+const DIR_WRITE_TIME: usize = 22;
+const DIR_WRITE_DATE: usize = 24;
+const DIR_CLUSTER_LOW: usize = 26;
+const DIR_SIZE: usize = 28;
+
+// Attributes of entries which are not files.
+const ATTR_VOLUME_ID: u8 = 0x08;
+const ATTR_DIRECTORY: u8 = 0x10;
+const ATTR_LONG_NAME: u8 = 0x0F;
+
+// First bytes of the name of free entries.
+const ENTRY_END: u8 = 0x00;
+const ENTRY_DELETED: u8 = 0xE5;
+
+// Date stamped on new files (1 January 2021), since there is no clock.
+const DEFAULT_DATE: u16 = (41 << 9) | (1 << 5) | 1;
+
+/// Storage read and written in blocks of 512 bytes, such as a SD card.
+pub trait BlockDevice {
+    /// Reads a block.
+    /// # Arguments
+    /// * `block` - a u32, the number of the block.
+    /// * `buffer` - a mutable array of 512 u8, which will be filled with the block.
+    /// # Returns
+    /// * `a Result<(), SdError>` - the error if the block could not be read.
+    fn read_block(&mut self, block: u32, buffer: &mut [u8; BLOCK_SIZE]) -> Result<(), SdError>;
+
+    /// Writes a block.
+    /// # Arguments
+    /// * `block` - a u32, the number of the block.
+    /// * `data` - an array of 512 u8, the data to be written.
+    /// # Returns
+    /// * `a Result<(), SdError>` - the error if the block could not be written.
+    fn write_block(&mut self, block: u32, data: &[u8; BLOCK_SIZE]) -> Result<(), SdError>;
+}
+
+/// Errors which can happen while using the filesystem.
+/// * `Card` - the card failed with the given error.
+/// * `NoFilesystem` - no FAT16 or FAT32 volume was found.
+/// * `NotFound` - no file has the given name.
+/// * `Exists` - a file with the given name already exists.
+/// * `InvalidName` - the name is not a valid 8.3 name.
+/// * `DirectoryFull` - there is no free entry in the root directory.
+/// * `DiskFull` - there is no free cluster.
+/// * `Corrupted` - a cluster chain ended before the end of the file.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FatError {
+    Card(SdError),
+    NoFilesystem,
+    NotFound,
+    Exists,
+    InvalidName,
+    DirectoryFull,
+    DiskFull,
+    Corrupted,
+}
+
+impl From<FatError> for Error {
+    fn from(error: FatError) -> Error {
+        match error {
+            FatError::Card(error) => error.into(),
+            FatError::NoFilesystem | FatError::Corrupted => Error::InvalidData,
+            FatError::NotFound => Error::NotFound,
+            FatError::Exists | FatError::InvalidName => Error::InvalidParam,
+            FatError::DirectoryFull | FatError::DiskFull => Error::Overflow,
+        }
+    }
+}
+
+impl From<SdError> for FatError {
+    fn from(error: SdError) -> FatError {
+        FatError::Card(error)
+    }
+}
+
+/// Version of the FAT of the volume.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FatType {
+    Fat16,
+    Fat32,
+}
+
+/// An open file of the root directory.
+/// # Elements
+/// * `dir_block` - a u32, the block holding the directory entry of the file.
+/// * `dir_offset` - a usize, the offset of the entry in that block.
+/// * `first_cluster` - a u32, the first cluster of the data, 0 for an empty file.
+/// * `size` - a u32, the size of the file in bytes.
+/// * `position` - a u32, the offset of the next byte to be read.
+/// * `cluster` - a u32, the cluster last walked to in the chain.
+/// * `cluster_index` - a u32, the index of that cluster in the chain.
+pub struct File {
+    dir_block: u32,
+    dir_offset: usize,
+    first_cluster: u32,
+    size: u32,
+    position: u32,
+    cluster: u32,
+    cluster_index: u32,
+}
+
+impl File {
+    /// Gives the size of the file.
+    /// # Returns
+    /// * `a u32` - the size in bytes.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    /// Gives the offset of the next byte to be read.
+    /// # Returns
+    /// * `a u32` - the offset in bytes.
+    pub fn position(&self) -> u32 {
+        self.position
+    }
+
+    /// Moves the read position, which is limited to the size of the file.
+    /// # Arguments
+    /// * `position` - a u32, the offset of the next byte to be read.
+    pub fn seek(&mut self, position: u32) {
+        self.position = if position > self.size {
+            self.size
+        } else {
+            position
+        };
+    }
+}
+
+/// A FAT volume on a SD card or another block device.
+/// # Elements
+/// * `card` - a `BlockDevice` object, the card holding the volume.
+/// * `buffer` - an array of 512 u8, the block last read.
+/// * `buffer_block` - a u32, the number of the block in the buffer.
+/// * `dirty` - a boolean, true if the buffer has to be written back.
+/// * `fat_type` - a `FatType` object, the version of the FAT.
+/// * `sectors_per_cluster` - a u32, the size of a cluster in blocks.
+/// * `fat_start` - a u32, the first block of the first FAT.
+/// * `fat_size` - a u32, the number of blocks of one FAT.
+/// * `fat_count` - a u32, the number of copies of the FAT.
+/// * `root_start` - a u32, the first block of the root directory of FAT16.
+/// * `root_blocks` - a u32, the number of blocks of the root directory of FAT16.
+/// * `root_cluster` - a u32, the first cluster of the root directory of FAT32.
+/// * `data_start` - a u32, the block of cluster 2.
+/// * `cluster_count` - a u32, the number of clusters for data.
+pub struct FatVolume<D = SdCard> {
+    card: D,
+    buffer: [u8; BLOCK_SIZE],
+    buffer_block: u32,
+    dirty: bool,
+    fat_type: FatType,
+    sectors_per_cluster: u32,
+    fat_start: u32,
+    fat_size: u32,
+    fat_count: u32,
+    root_start: u32,
+    root_blocks: u32,
+    root_cluster: u32,
+    data_start: u32,
+    cluster_count: u32,
+}
+
+/// Reads a little endian u16 from a buffer.
+fn read_u16(buffer: &[u8], offset: usize) -> u16 {
+    buffer[offset] as u16 | (buffer[offset + 1] as u16) << 8
+}
+
+/// Reads a little endian u32 from a buffer.
+fn read_u32(buffer: &[u8], offset: usize) -> u32 {
+    read_u16(buffer, offset) as u32 | (read_u16(buffer, offset + 2) as u32) << 16
+}
+
+/// Writes a little endian u16 into a buffer.
+fn write_u16(buffer: &mut [u8], offset: usize, value: u16) {
+    buffer[offset] = value as u8;
+    buffer[offset + 1] = (value >> 8) as u8;
+}
+
+/// Writes a little endian u32 into a buffer.
+fn write_u32(buffer: &mut [u8], offset: usize, value: u32) {
+    write_u16(buffer, offset, value as u16);
+    write_u16(buffer, offset + 2, (value >> 16) as u16);
+}
+
+/// Converts a name like "LOG.TXT" into the 11 characters of a directory entry.
+/// # Returns
+/// * `a Result<[u8; 11], FatError>` - the name padded with spaces, in capitals.
+fn short_name(name: &str) -> Result<[u8; 11], FatError> {
+    let mut short = [b' '; 11];
+    let (base, extension) = match name.find('.') {
+        Some(dot) => (&name[..dot], &name[dot + 1..]),
+        None => (name, ""),
+    };
+    if base.is_empty() || base.len() > 8 || extension.len() > 3 {
+        return Err(FatError::InvalidName);
+    }
+    for (i, c) in base.bytes().chain(extension.bytes()).enumerate() {
+        let c = c.to_ascii_uppercase();
+        if c <= b' ' || c > b'~' || b"\"*+,./:;<=>?[\\]|".contains(&c) {
+            return Err(FatError::InvalidName);
+        }
+        let index = if i < base.len() {
+            i
+        } else {
+            8 + i - base.len()
+        };
+        short[index] = c;
+    }
+    Ok(short)
+}
+
+impl<D: BlockDevice> FatVolume<D> {
+    /// Finds the FAT volume on the card, either at block 0 or in the first partition.
+    /// # Arguments
+    /// * `card` - a `BlockDevice` object, the card holding the volume.
+    /// # Returns
+    /// * `a Result<FatVolume, FatError>` - the volume, or the error if none was found.
+    pub fn mount(card: D) -> Result<FatVolume<D>, FatError> {
+        let mut volume = FatVolume {
+            card,
+            buffer: [0; BLOCK_SIZE],
+            buffer_block: u32::MAX,
+            dirty: false,
+            fat_type: FatType::Fat16,
+            sectors_per_cluster: 0,
+            fat_start: 0,
+            fat_size: 0,
+            fat_count: 0,
+            root_start: 0,
+            root_blocks: 0,
+            root_cluster: 0,
+            data_start: 0,
+            cluster_count: 0,
+        };
+
+        volume.load(0)?;
+        if read_u16(&volume.buffer, 510) != 0xAA55 {
+            return Err(FatError::NoFilesystem);
+        }
+        // A boot sector starts with a jump instruction and has 512 byte sectors,
+        // otherwise block 0 is a master boot record.
+        let mut start = 0;
+        let jump = volume.buffer[0];
+        if !((jump == 0xEB || jump == 0xE9) && read_u16(&volume.buffer, 11) == 512) {
+            start = read_u32(&volume.buffer, 446 + 8);
+            volume.load(start)?;
+            if read_u16(&volume.buffer, 510) != 0xAA55 || read_u16(&volume.buffer, 11) != 512 {
+                return Err(FatError::NoFilesystem);
+            }
+        }
+
+        let b = &volume.buffer;
+        let sectors_per_cluster = b[13] as u32;
+        let reserved = read_u16(b, 14) as u32;
+        let fat_count = b[16] as u32;
+        let root_entries = read_u16(b, 17) as u32;
+        let total = match read_u16(b, 19) {
+            0 => read_u32(b, 32),
+            total => total as u32,
+        };
+        let fat_size = match read_u16(b, 22) {
+            0 => read_u32(b, 36),
+            size => size as u32,
+        };
+        let root_cluster = read_u32(b, 44);
+        if sectors_per_cluster == 0 || fat_count == 0 || fat_size == 0 {
+            return Err(FatError::NoFilesystem);
+        }
+
+        let root_blocks =
+            (root_entries * DIR_ENTRY_SIZE as u32 + BLOCK_SIZE as u32 - 1) / BLOCK_SIZE as u32;
+        let fat_start = start + reserved;
+        let root_start = fat_start + fat_count * fat_size;
+        let data_start = root_start + root_blocks;
+        // A boot sector giving fewer blocks than its own FAT and root directory is damaged.
+        let cluster_count = match total.checked_sub(data_start - start) {
+            Some(blocks) => blocks / sectors_per_cluster,
+            None => return Err(FatError::NoFilesystem),
+        };
+
+        // The type is decided only by the number of clusters, FAT12 is not supported.
+        volume.fat_type = if cluster_count < 4085 {
+            return Err(FatError::NoFilesystem);
+        } else if cluster_count < 65525 {
+            FatType::Fat16
+        } else {
+            FatType::Fat32
+        };
+        volume.sectors_per_cluster = sectors_per_cluster;
+        volume.fat_start = fat_start;
+        volume.fat_size = fat_size;
+        volume.fat_count = fat_count;
+        volume.root_start = root_start;
+        volume.root_blocks = root_blocks;
+        volume.root_cluster = root_cluster;
+        volume.data_start = data_start;
+        volume.cluster_count = cluster_count;
+        Ok(volume)
+    }
+
+    /// Gives the version of the FAT.
+    pub fn fat_type(&self) -> FatType {
+        self.fat_type
+    }
+
+    /// Gives the card back, after writing the buffer.
+    /// # Returns
+    /// * `a Result<D, FatError>` - the card.
+    pub fn unmount(mut self) -> Result<D, FatError> {
+        self.flush()?;
+        Ok(self.card)
+    }
+
+    /// Writes the buffer back to the card if it was changed.
+    /// # Returns
+    /// * `a Result<(), FatError>` - the error if the block could not be written.
+    pub fn flush(&mut self) -> Result<(), FatError> {
+        if self.dirty {
+            self.card.write_block(self.buffer_block, &self.buffer)?;
+            self.dirty = false;
+        }
+        Ok(())
+    }
+
+    /// Brings a block into the buffer.
+    fn load(&mut self, block: u32) -> Result<(), FatError> {
+        if self.buffer_block != block {
+            self.flush()?;
+            // The buffer is marked invalid first in case the read fails.
+            self.buffer_block = u32::MAX;
+            self.card.read_block(block, &mut self.buffer)?;
+            self.buffer_block = block;
+        }
+        Ok(())
+    }
+
+    /// Gives the size of a cluster in bytes.
+    fn cluster_bytes(&self) -> u32 {
+        self.sectors_per_cluster * BLOCK_SIZE as u32
+    }
+
+    /// Gives the first block of a cluster.
+    fn cluster_block(&self, cluster: u32) -> u32 {
+        self.data_start + (cluster - 2) * self.sectors_per_cluster
+    }
+
+    /// Checks whether a FAT entry marks the end of a chain.
+    fn is_end_of_chain(&self, entry: u32) -> bool {
+        let end = match self.fat_type {
+            FatType::Fat16 => entry >= 0xFFF8,
+            FatType::Fat32 => entry >= 0x0FFF_FFF8,
+        };
+        end || entry < 2
+    }
+
+    /// Gives the block and the offset of the FAT entry of a cluster.
+    fn fat_position(&self, cluster: u32) -> (u32, usize) {
+        let offset = match self.fat_type {
+            FatType::Fat16 => cluster * 2,
+            FatType::Fat32 => cluster * 4,
+        };
+        (
+            self.fat_start + offset / BLOCK_SIZE as u32,
+            (offset % BLOCK_SIZE as u32) as usize,
+        )
+    }
+
+    /// Reads the FAT entry of a cluster, which is the next cluster of the chain.
+    fn fat_entry(&mut self, cluster: u32) -> Result<u32, FatError> {
+        let (block, offset) = self.fat_position(cluster);
+        self.load(block)?;
+        Ok(match self.fat_type {
+            FatType::Fat16 => read_u16(&self.buffer, offset) as u32,
+            FatType::Fat32 => read_u32(&self.buffer, offset) & 0x0FFF_FFFF,
+        })
+    }
+
+    /// Writes the FAT entry of a cluster in every copy of the FAT.
+    fn set_fat_entry(&mut self, cluster: u32, value: u32) -> Result<(), FatError> {
+        let (block, offset) = self.fat_position(cluster);
+        for copy in 0..self.fat_count {
+            self.load(block + copy * self.fat_size)?;
+            match self.fat_type {
+                FatType::Fat16 => write_u16(&mut self.buffer, offset, value as u16),
+                FatType::Fat32 => {
+                    // The top 4 bits of a FAT32 entry are reserved and kept.
+                    let old = read_u32(&self.buffer, offset) & 0xF000_0000;
+                    write_u32(&mut self.buffer, offset, old | (value & 0x0FFF_FFFF));
+                }
+            }
+            self.dirty = true;
+        }
+        Ok(())
+    }
+
+    /// Finds a free cluster, marks it as the end of a chain and links it after `previous`.
+    fn allocate(&mut self, previous: u32) -> Result<u32, FatError> {
+        let end = match self.fat_type {
+            FatType::Fat16 => 0xFFFF,
+            FatType::Fat32 => 0x0FFF_FFFF,
+        };
+        let start = if previous >= 2 { previous + 1 } else { 2 };
+        for i in 0..self.cluster_count {
+            let cluster = 2 + (start - 2 + i) % self.cluster_count;
+            if self.fat_entry(cluster)? == 0 {
+                self.set_fat_entry(cluster, end)?;
+                if previous >= 2 {
+                    self.set_fat_entry(previous, cluster)?;
+                }
+                return Ok(cluster);
+            }
+        }
+        Err(FatError::DiskFull)
+    }
+
+    /// Gives the block of the root directory with the given index.
+    /// # Returns
+    /// * `a Result<Option<u32>, FatError>` - the block, or None after the end of the directory.
+    fn root_block(&mut self, index: u32) -> Result<Option<u32>, FatError> {
+        match self.fat_type {
+            FatType::Fat16 => Ok(if index < self.root_blocks {
+                Some(self.root_start + index)
+            } else {
+                None
+            }),
+            FatType::Fat32 => {
+                let mut cluster = self.root_cluster;
+                for _ in 0..index / self.sectors_per_cluster {
+                    cluster = self.fat_entry(cluster)?;
+                    if self.is_end_of_chain(cluster) {
+                        return Ok(None);
+                    }
+                }
+                Ok(Some(
+                    self.cluster_block(cluster) + index % self.sectors_per_cluster,
+                ))
+            }
+        }
+    }
+
+    /// Looks for a file in the root directory.
+    /// # Returns
+    /// * `a Result<Result<File, Option<(u32, usize)>>, FatError>` - the file if it was found,
+    ///   otherwise the position of the first free entry if there is one.
+    fn find(&mut self, name: &[u8; 11]) -> Result<Result<File, Option<(u32, usize)>>, FatError> {
+        let mut free = None;
+        let mut index = 0;
+        while let Some(block) = self.root_block(index)? {
+            self.load(block)?;
+            for offset in (0..BLOCK_SIZE).step_by(DIR_ENTRY_SIZE) {
+                let entry = &self.buffer[offset..offset + DIR_ENTRY_SIZE];
+                if entry[0] == ENTRY_END || entry[0] == ENTRY_DELETED {
+                    if free.is_none() {
+                        free = Some((block, offset));
+                    }
+                    if entry[0] == ENTRY_END {
+                        return Ok(Err(free));
+                    }
+                    continue;
+                }
+                let attributes = entry[DIR_ATTRIBUTES];
+                if attributes & ATTR_LONG_NAME == ATTR_LONG_NAME
+                    || attributes & (ATTR_VOLUME_ID | ATTR_DIRECTORY) != 0
+                {
+                    continue;
+                }
+                if &entry[..11] == name {
+                    let first_cluster = (read_u16(entry, DIR_CLUSTER_HIGH) as u32) << 16
+                        | read_u16(entry, DIR_CLUSTER_LOW) as u32;
+                    return Ok(Ok(File {
+                        dir_block: block,
+                        dir_offset: offset,
+                        first_cluster,
+                        size: read_u32(entry, DIR_SIZE),
+                        position: 0,
+                        cluster: first_cluster,
+                        cluster_index: 0,
+                    }));
+                }
+            }
+            index += 1;
+        }
+        Ok(Err(free))
+    }
+
+    /// Opens a file of the root directory for reading and appending.
+    /// # Arguments
+    /// * `name` - a string slice, the 8.3 name of the file.
+    /// # Returns
+    /// * `a Result<File, FatError>` - the file, positioned at its start.
+    pub fn open(&mut self, name: &str) -> Result<File, FatError> {
+        let name = short_name(name)?;
+        match self.find(&name)? {
+            Ok(file) => Ok(file),
+            Err(_) => Err(FatError::NotFound),
+        }
+    }
+
+    /// Creates an empty file in the root directory.
+    /// # Arguments
+    /// * `name` - a string slice, the 8.3 name of the file.
+    /// # Returns
+    /// * `a Result<File, FatError>` - the new file.
+    pub fn create(&mut self, name: &str) -> Result<File, FatError> {
+        let name = short_name(name)?;
+        let (block, offset) = match self.find(&name)? {
+            Ok(_) => return Err(FatError::Exists),
+            Err(Some(free)) => free,
+            Err(None) => return Err(FatError::DirectoryFull),
+        };
+
+        self.load(block)?;
+        let entry = &mut self.buffer[offset..offset + DIR_ENTRY_SIZE];
+        for byte in entry.iter_mut() {
+            *byte = 0;
+        }
+        entry[..11].copy_from_slice(&name);
+        write_u16(entry, DIR_CREATE_TIME, 0);
+        write_u16(entry, DIR_CREATE_DATE, DEFAULT_DATE);
+        write_u16(entry, DIR_ACCESS_DATE, DEFAULT_DATE);
+        write_u16(entry, DIR_WRITE_TIME, 0);
+        write_u16(entry, DIR_WRITE_DATE, DEFAULT_DATE);
+        self.dirty = true;
+        self.flush()?;
+
+        Ok(File {
+            dir_block: block,
+            dir_offset: offset,
+            first_cluster: 0,
+            size: 0,
+            position: 0,
+            cluster: 0,
+            cluster_index: 0,
+        })
+    }
+
+    /// Walks the cluster chain of a file up to the cluster with the given index.
+    /// # Returns
+    /// * `a Result<bool, FatError>` - false if the chain ends before, with the file
+    ///   left at the last cluster of the chain.
+    fn walk(&mut self, file: &mut File, index: u32) -> Result<bool, FatError> {
+        if file.cluster_index > index || file.cluster < 2 {
+            file.cluster = file.first_cluster;
+            file.cluster_index = 0;
+        }
+        if file.cluster < 2 {
+            return Ok(false);
+        }
+        while file.cluster_index < index {
+            let next = self.fat_entry(file.cluster)?;
+            if self.is_end_of_chain(next) {
+                return Ok(false);
+            }
+            file.cluster = next;
+            file.cluster_index += 1;
+        }
+        Ok(true)
+    }
+
+    /// Reads from the position of a file.
+    /// # Arguments
+    /// * `file` - a mutable reference to a `File` object, the file to be read.
+    /// * `buffer` - a mutable slice of u8, which will be filled with the data read.
+    /// # Returns
+    /// * `a Result<usize, FatError>` - the number of bytes read, 0 at the end of the file.
+    pub fn read(&mut self, file: &mut File, buffer: &mut [u8]) -> Result<usize, FatError> {
+        let cluster_bytes = self.cluster_bytes();
+        let mut count = 0;
+        while count < buffer.len() && file.position < file.size {
+            if !self.walk(file, file.position / cluster_bytes)? {
+                return Err(FatError::Corrupted);
+            }
+            let in_cluster = file.position % cluster_bytes;
+            let block = self.cluster_block(file.cluster) + in_cluster / BLOCK_SIZE as u32;
+            let offset = (in_cluster % BLOCK_SIZE as u32) as usize;
+            let length = (BLOCK_SIZE - offset)
+                .min(buffer.len() - count)
+                .min((file.size - file.position) as usize);
+
+            self.load(block)?;
+            buffer[count..count + length].copy_from_slice(&self.buffer[offset..offset + length]);
+            count += length;
+            file.position += length as u32;
+        }
+        Ok(count)
+    }
+
+    /// Appends data at the end of a file and updates its directory entry.
+    /// # Arguments
+    /// * `file` - a mutable reference to a `File` object, the file to be written.
+    /// * `data` - a slice of u8, the bytes to be appended.
+    /// # Returns
+    /// * `a Result<usize, FatError>` - the number of bytes written, less than the length
+    ///   of `data` only if an error stopped the write.
+    pub fn append(&mut self, file: &mut File, data: &[u8]) -> Result<usize, FatError> {
+        let cluster_bytes = self.cluster_bytes();
+        let mut count = 0;
+        let mut result = Ok(());
+        while count < data.len() {
+            let index = file.size / cluster_bytes;
+            match self.walk(file, index) {
+                Ok(true) => {}
+                // The chain is one cluster short when the file ends on a cluster boundary.
+                Ok(false) => match self.allocate(file.cluster) {
+                    Ok(cluster) => {
+                        if file.first_cluster < 2 {
+                            file.first_cluster = cluster;
+                        }
+                        file.cluster = cluster;
+                        file.cluster_index = index;
+                    }
+                    Err(error) => {
+                        result = Err(error);
+                        break;
+                    }
+                },
+                Err(error) => {
+                    result = Err(error);
+                    break;
+                }
+            }
+
+            let in_cluster = file.size % cluster_bytes;
+            let block = self.cluster_block(file.cluster) + in_cluster / BLOCK_SIZE as u32;
+            let offset = (in_cluster % BLOCK_SIZE as u32) as usize;
+            let length = (BLOCK_SIZE - offset).min(data.len() - count);
+
+            if let Err(error) = self.load(block) {
+                result = Err(error);
+                break;
+            }
+            self.buffer[offset..offset + length].copy_from_slice(&data[count..count + length]);
+            self.dirty = true;
+            count += length;
+            file.size += length as u32;
+        }
+
+        // The directory entry is updated even after an error, for the data already written.
+        self.load(file.dir_block)?;
+        let entry = &mut self.buffer[file.dir_offset..file.dir_offset + DIR_ENTRY_SIZE];
+        write_u16(entry, DIR_CLUSTER_HIGH, (file.first_cluster >> 16) as u16);
+        write_u16(entry, DIR_CLUSTER_LOW, file.first_cluster as u16);
+        write_u32(entry, DIR_SIZE, file.size);
+        write_u16(entry, DIR_WRITE_DATE, DEFAULT_DATE);
+        self.dirty = true;
+        result?;
+        Ok(count)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    // Layout of the test volume: a FAT16 boot sector at block 0, two FATs of 32 blocks,
+    // 512 root entries in 32 blocks and clusters of one block.
+    const TOTAL_BLOCKS: u32 = 8192;
+    const FAT_BLOCKS: u32 = 32;
+    const DATA_START: u32 = 1 + 2 * FAT_BLOCKS + 32;
+
+    /// Disk in memory keeping only the blocks written, the others read as zeros.
+    struct RamDisk {
+        blocks: [(u32, [u8; BLOCK_SIZE]); 16],
+        used: usize,
+    }
+
+    impl RamDisk {
+        /// Formats the disk, with `total` blocks given in the boot sector.
+        fn format(total: u16) -> RamDisk {
+            let mut disk = RamDisk {
+                blocks: [(0, [0; BLOCK_SIZE]); 16],
+                used: 0,
+            };
+            let mut boot = [0; BLOCK_SIZE];
+            boot[0] = 0xEB;
+            write_u16(&mut boot, 11, BLOCK_SIZE as u16);
+            boot[13] = 1;
+            write_u16(&mut boot, 14, 1);
+            boot[16] = 2;
+            write_u16(&mut boot, 17, 512);
+            write_u16(&mut boot, 19, total);
+            write_u16(&mut boot, 22, FAT_BLOCKS as u16);
+            write_u16(&mut boot, 510, 0xAA55);
+            disk.write_block(0, &boot).unwrap();
+            disk
+        }
+
+        fn find(&self, block: u32) -> Option<usize> {
+            (0..self.used).find(|&i| self.blocks[i].0 == block)
+        }
+    }
+
+    impl BlockDevice for RamDisk {
+        fn read_block(&mut self, block: u32, buffer: &mut [u8; BLOCK_SIZE]) -> Result<(), SdError> {
+            *buffer = match self.find(block) {
+                Some(i) => self.blocks[i].1,
+                None => [0; BLOCK_SIZE],
+            };
+            Ok(())
+        }
+
+        fn write_block(&mut self, block: u32, data: &[u8; BLOCK_SIZE]) -> Result<(), SdError> {
+            let i = match self.find(block) {
+                Some(i) => i,
+                None if self.used < self.blocks.len() => {
+                    self.used += 1;
+                    self.used - 1
+                }
+                None => return Err(SdError::WriteRejected(0)),
+            };
+            self.blocks[i] = (block, *data);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn round_trip_across_clusters() {
+        let mut data = [0; 1300];
+        for (i, byte) in data.iter_mut().enumerate() {
+            *byte = (i * 7) as u8;
+        }
+
+        let mut volume = FatVolume::mount(RamDisk::format(TOTAL_BLOCKS as u16)).unwrap();
+        assert_eq!(volume.fat_type(), FatType::Fat16);
+        let mut file = volume.create("log.txt").unwrap();
+        // The second append starts on a cluster boundary.
+        assert_eq!(volume.append(&mut file, &data[..512]).unwrap(), 512);
+        assert_eq!(volume.append(&mut file, &data[512..]).unwrap(), 788);
+        assert_eq!(volume.create("LOG.TXT").err(), Some(FatError::Exists));
+        let mut disk = volume.unmount().unwrap();
+
+        // Three clusters chained in both FATs.
+        let mut fat = [0; BLOCK_SIZE];
+        for copy in 0..2 {
+            disk.read_block(1 + copy * FAT_BLOCKS, &mut fat).unwrap();
+            assert_eq!(read_u16(&fat, 4), 3);
+            assert_eq!(read_u16(&fat, 6), 4);
+            assert_eq!(read_u16(&fat, 8), 0xFFFF);
+        }
+        let mut block = [0; BLOCK_SIZE];
+        disk.read_block(DATA_START + 1, &mut block).unwrap();
+        assert_eq!(block[..], data[512..1024]);
+
+        let mut volume = FatVolume::mount(disk).unwrap();
+        let mut file = volume.open("LOG.TXT").unwrap();
+        assert_eq!(file.size(), 1300);
+        let mut read = [0; 1400];
+        assert_eq!(volume.read(&mut file, &mut read).unwrap(), 1300);
+        assert_eq!(read[..1300], data[..]);
+        assert_eq!(volume.read(&mut file, &mut read).unwrap(), 0);
+
+        file.seek(1000);
+        assert_eq!(volume.read(&mut file, &mut read[..10]).unwrap(), 10);
+        assert_eq!(read[..10], data[1000..1010]);
+        assert_eq!(volume.open("NONE.TXT").err(), Some(FatError::NotFound));
+    }
+
+    #[test]
+    fn too_small_volume() {
+        // Fewer blocks than the FATs and the root directory take.
+        let disk = RamDisk::format(DATA_START as u16 - 1);
+        assert_eq!(FatVolume::mount(disk).err(), Some(FatError::NoFilesystem));
+    }
+}
@@ -0,0 +1,443 @@
+//     RustDuino : A generic HAL implementation for Arduino Boards in Rust
+//     Copyright (C) 2021  Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+//     This program is free software: you can redistribute it and/or modify
+//     it under the terms of the GNU Affero General Public License as published
+//     by the Free Software Foundation, either version 3 of the License, or
+//     (at your option) any later version.
+//
+//     This program is distributed in the hope that it will be useful,
+//     but WITHOUT ANY WARRANTY; without even the implied warranty of
+//     MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+//     GNU Affero General Public License for more details.
+//
+//     You should have received a copy of the GNU Affero General Public License
+//     along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! This code controls SD and SDHC memory cards in the SPI mode.
+//! The card is started at a slow clock with the reset (CMD0), interface condition (CMD8)
+//! and initialization (ACMD41) commands, after which blocks of 512 bytes can be read
+//! and written at full speed. Standard capacity cards are addressed in bytes and high
+//! capacity cards in blocks, which is hidden by `read_block` and `write_block`.
+//! The chip select of the card can be any digital pin, but the hardware SS pin is
+//! still an output as required by the SPI master.
+//! See the Physical Layer Simplified Specification of the SD Association,
+//! `<https://www.sdcard.org/downloads/pls/>`.
+
+// Source codes required.
+use crate::bus::{SharedSpi, SpiDevice};
+use crate::common::SpiOps;
+use crate::delay::delay_ms;
+use crate::error::Error;
+use crate::storage::fat::BlockDevice;
+use crate::util::crc::crc7;
+
+/// Size of a block of the card in bytes.
+pub const BLOCK_SIZE: usize = 512;
+
+// Commands used in the SPI mode.
+const CMD0: u8 = 0; // GO_IDLE_STATE
+const CMD8: u8 = 8; // SEND_IF_COND
+const CMD9: u8 = 9; // SEND_CSD
+const CMD16: u8 = 16; // SET_BLOCKLEN
+const CMD17: u8 = 17; // READ_SINGLE_BLOCK
+const CMD24: u8 = 24; // WRITE_BLOCK
+const CMD55: u8 = 55; // APP_CMD
+const CMD58: u8 = 58; // READ_OCR
+const ACMD41: u8 = 41; // SD_SEND_OP_COND
+
+// Bits of the R1 response, and the tokens of the data blocks.
+const R1_IDLE: u8 = 0x01;
+const R1_ILLEGAL_COMMAND: u8 = 0x04;
+const DATA_START_TOKEN: u8 = 0xFE;
+const DATA_ACCEPTED: u8 = 0x05;
+
+// Number of milliseconds to wait for the card to become ready.
+const INIT_TIMEOUT_MS: u16 = 2000;
+const READ_TIMEOUT_MS: u16 = 300;
+const WRITE_TIMEOUT_MS: u16 = 600;
+
+// SCK frequencies during the initialization and afterwards.
+const INIT_FREQUENCY: u32 = 250_000;
+const FREQUENCY: u32 = 4_000_000;
+
+/// Errors which can happen while talking to the card.
+/// * `NoCard` - the card did not answer the reset command.
+/// * `Timeout` - the card stayed busy for too long.
+/// * `Command` - the card answered a command with the given error bits.
+/// * `DataToken` - the card sent the given error token instead of data.
+/// * `WriteRejected` - the card did not accept the data written.
+/// * `Unsupported` - the card does not work at 3.3 V or is not an SD card.
+/// * `Bus` - the SPI bus was taken by another transfer.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SdError {
+    NoCard,
+    Timeout,
+    Command(u8),
+    DataToken(u8),
+    WriteRejected(u8),
+    Unsupported,
+    Bus,
+}
+
+impl From<SdError> for Error {
+    fn from(error: SdError) -> Error {
+        match error {
+            SdError::NoCard => Error::NotFound,
+            SdError::Timeout => Error::Timeout,
+            SdError::Command(_) | SdError::DataToken(_) | SdError::WriteRejected(_) => {
+                Error::Device
+            }
+            SdError::Unsupported => Error::Unsupported,
+            SdError::Bus => Error::Busy,
+        }
+    }
+}
+
+/// Kind of the card found during initialization.
+/// * `SD1` - a standard capacity card of version 1.
+/// * `SD2` - a standard capacity card of version 2.
+/// * `SDHC` - a high or extended capacity card (SDHC or SDXC), addressed in blocks.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CardType {
+    SD1,
+    SD2,
+    SDHC,
+}
+
+/// Used to control a SD card on the SPI bus.
+/// # Elements
+/// * `device` - a `SpiDevice` object, the handle of the card on the SPI bus.
+/// * `card_type` - a `CardType` object, the kind of the card.
+pub struct SdCard<T = SharedSpi> {
+    device: SpiDevice<T>,
+    card_type: CardType,
+}
+
+impl SdCard {
+    /// Initializes the card on the SPI of the chip, then runs the transfers at 4 MHz.
+    /// # Arguments
+    /// * `cs` - a u8, the digital pin connected to the chip select of the card.
+    /// # Returns
+    /// * `a Result<SdCard, SdError>` - the card ready for use, or the error which stopped it.
+    pub fn new(cs: u8) -> Result<SdCard, SdError> {
+        SdCard::with_bus(SharedSpi::new(), cs)
+    }
+}
+
+impl<T: SpiOps> SdCard<T> {
+    /// Initializes the card on the given SPI bus, then runs the transfers at 4 MHz.
+    /// # Arguments
+    /// * `bus` - a `SpiOps` object, the SPI bus of the card.
+    /// * `cs` - a u8, the digital pin connected to the chip select of the card.
+    /// # Returns
+    /// * `a Result<SdCard, SdError>` - the card ready for use, or the error which stopped it.
+    pub fn with_bus(bus: T, cs: u8) -> Result<SdCard<T>, SdError> {
+        // The clock must be between 100 and 400 kHz during the initialization.
+        let mut device = SpiDevice::with_bus(bus, cs, 0, false, INIT_FREQUENCY);
+        device.init();
+        // At least 74 clocks with the card not selected, so that it enters the native mode.
+        device
+            .write_deselected(&[0xFF; 10])
+            .map_err(|_| SdError::Bus)?;
+
+        let mut card = SdCard {
+            device,
+            card_type: CardType::SD1,
+        };
+        card.card_type = card.run(init)?;
+        card.device.set_frequency(FREQUENCY);
+        Ok(card)
+    }
+
+    /// Gives the kind of the card.
+    pub fn card_type(&self) -> CardType {
+        self.card_type
+    }
+
+    /// Runs commands with the card selected, then releases the chip select with one more
+    /// byte so that the card frees MISO.
+    fn run<R, F>(&mut self, f: F) -> Result<R, SdError>
+    where
+        F: FnOnce(&mut T) -> Result<R, SdError>,
+    {
+        let result = self.device.transaction(f).map_err(|_| SdError::Bus)?;
+        let _ = self.device.write_deselected(&[0xFF]);
+        result
+    }
+
+    /// Gives the address of a block as used by the card.
+    fn address(&self, block: u32) -> u32 {
+        if self.card_type == CardType::SDHC {
+            block
+        } else {
+            block * BLOCK_SIZE as u32
+        }
+    }
+
+    /// Reads a block of the card.
+    /// # Arguments
+    /// * `block` - a u32, the number of the block.
+    /// * `buffer` - a mutable array of 512 u8, which will be filled with the block.
+    /// # Returns
+    /// * `a Result<(), SdError>` - the error if the block could not be read.
+    pub fn read_block(&mut self, block: u32, buffer: &mut [u8; BLOCK_SIZE]) -> Result<(), SdError> {
+        let address = self.address(block);
+        self.run(|spi| {
+            let r1 = command(spi, CMD17, address);
+            if r1 != 0 {
+                return Err(SdError::Command(r1));
+            }
+            read_data(spi, buffer)
+        })
+    }
+
+    /// Writes a block of the card and waits for the card to store it.
+    /// # Arguments
+    /// * `block` - a u32, the number of the block.
+    /// * `data` - an array of 512 u8, the data to be written.
+    /// # Returns
+    /// * `a Result<(), SdError>` - the error if the block could not be written.
+    pub fn write_block(&mut self, block: u32, data: &[u8; BLOCK_SIZE]) -> Result<(), SdError> {
+        let address = self.address(block);
+        self.run(|spi| {
+            let r1 = command(spi, CMD24, address);
+            if r1 != 0 {
+                return Err(SdError::Command(r1));
+            }
+            spi.transfer(DATA_START_TOKEN);
+            spi.write(data);
+            // A dummy CRC.
+            spi.transfer(0xFF);
+            spi.transfer(0xFF);
+            let response = spi.transfer(0xFF) & 0x1F;
+            if response != DATA_ACCEPTED {
+                Err(SdError::WriteRejected(response))
+            } else if !wait_ready(spi, WRITE_TIMEOUT_MS) {
+                Err(SdError::Timeout)
+            } else {
+                Ok(())
+            }
+        })
+    }
+
+    /// Reads the number of blocks of the card from its CSD register.
+    /// # Returns
+    /// * `a Result<u32, SdError>` - the number of blocks of 512 bytes.
+    pub fn block_count(&mut self) -> Result<u32, SdError> {
+        let mut csd = [0u8; 16];
+        self.run(|spi| {
+            let r1 = command(spi, CMD9, 0);
+            if r1 != 0 {
+                return Err(SdError::Command(r1));
+            }
+            read_data(spi, &mut csd)
+        })?;
+
+        if csd[0] >> 6 == 1 {
+            // CSD version 2, the size is (C_SIZE + 1) * 512 KB.
+            let c_size = ((csd[7] as u32 & 0x3F) << 16) | ((csd[8] as u32) << 8) | csd[9] as u32;
+            Ok((c_size + 1) << 10)
+        } else {
+            // CSD version 1, the size is (C_SIZE + 1) * 2^(C_SIZE_MULT + 2) * 2^READ_BL_LEN.
+            let read_bl_len = csd[5] as u32 & 0x0F;
+            let c_size =
+                ((csd[6] as u32 & 0x03) << 10) | ((csd[7] as u32) << 2) | ((csd[8] as u32) >> 6);
+            let c_size_mult = ((csd[9] as u32 & 0x03) << 1) | ((csd[10] as u32) >> 7);
+            Ok((c_size + 1) << (c_size_mult + 2 + read_bl_len - 9))
+        }
+    }
+}
+
+/// Runs the initialization sequence of the SPI mode, with the card selected.
+/// # Returns
+/// * `a Result<CardType, SdError>` - the kind of the card found.
+fn init<T: SpiOps>(spi: &mut T) -> Result<CardType, SdError> {
+    let mut retries = 0;
+    while command(spi, CMD0, 0) != R1_IDLE {
+        retries += 1;
+        if retries > 100 {
+            return Err(SdError::NoCard);
+        }
+    }
+
+    // Version 2 cards echo the check pattern 0xAA with the voltage range.
+    let mut card_type = CardType::SD1;
+    let r1 = command(spi, CMD8, 0x1AA);
+    if r1 & R1_ILLEGAL_COMMAND == 0 {
+        let mut r7 = [0; 4];
+        spi.read(&mut r7);
+        if r7[2] & 0x0F != 0x01 || r7[3] != 0xAA {
+            return Err(SdError::Unsupported);
+        }
+        card_type = CardType::SD2;
+    }
+
+    // High capacity support is announced to version 2 cards.
+    let arg = if card_type == CardType::SD2 {
+        0x4000_0000
+    } else {
+        0
+    };
+    let mut waited = 0;
+    loop {
+        match app_command(spi, ACMD41, arg) {
+            0 => break,
+            R1_IDLE => {}
+            r1 => return Err(SdError::Command(r1)),
+        }
+        waited += 1;
+        if waited > INIT_TIMEOUT_MS {
+            return Err(SdError::Timeout);
+        }
+        delay_ms(1);
+    }
+
+    if card_type == CardType::SD2 {
+        let r1 = command(spi, CMD58, 0);
+        if r1 != 0 {
+            return Err(SdError::Command(r1));
+        }
+        let mut ocr = [0; 4];
+        spi.read(&mut ocr);
+        // The card capacity status bit.
+        if ocr[0] & 0x40 != 0 {
+            card_type = CardType::SDHC;
+        }
+    }
+    if card_type != CardType::SDHC {
+        let r1 = command(spi, CMD16, BLOCK_SIZE as u32);
+        if r1 != 0 {
+            return Err(SdError::Command(r1));
+        }
+    }
+    Ok(card_type)
+}
+
+/// Waits until the card stops holding MISO low.
+fn wait_ready<T: SpiOps>(spi: &mut T, timeout_ms: u16) -> bool {
+    let mut waited = 0;
+    while spi.transfer(0xFF) != 0xFF {
+        waited += 1;
+        if waited > timeout_ms {
+            return false;
+        }
+        delay_ms(1);
+    }
+    true
+}
+
+/// Sends a command to the selected card.
+/// # Returns
+/// * `a u8` - the R1 response, 0xFF if the card did not answer.
+fn command<T: SpiOps>(spi: &mut T, command: u8, arg: u32) -> u8 {
+    if command != CMD0 {
+        wait_ready(spi, READ_TIMEOUT_MS);
+    }
+
+    let mut frame = [
+        0x40 | command,
+        (arg >> 24) as u8,
+        (arg >> 16) as u8,
+        (arg >> 8) as u8,
+        arg as u8,
+        0,
+    ];
+    // The CRC is only checked for CMD0 and CMD8 in the SPI mode, but is always sent.
+    frame[5] = crc7(&frame[..5]) << 1 | 0x01;
+    spi.write(&frame);
+
+    // The response comes within 8 bytes and has its top bit cleared.
+    for _ in 0..10 {
+        let r1 = spi.transfer(0xFF);
+        if r1 & 0x80 == 0 {
+            return r1;
+        }
+    }
+    0xFF
+}
+
+/// Sends an application specific command, which is preceded by CMD55.
+fn app_command<T: SpiOps>(spi: &mut T, acmd: u8, arg: u32) -> u8 {
+    command(spi, CMD55, 0);
+    command(spi, acmd, arg)
+}
+
+/// Waits for the start token of a data block and reads the block.
+fn read_data<T: SpiOps>(spi: &mut T, buffer: &mut [u8]) -> Result<(), SdError> {
+    let mut waited = 0;
+    let token = loop {
+        let token = spi.transfer(0xFF);
+        if token != 0xFF {
+            break token;
+        }
+        waited += 1;
+        if waited > READ_TIMEOUT_MS {
+            return Err(SdError::Timeout);
+        }
+        delay_ms(1);
+    };
+    if token != DATA_START_TOKEN {
+        return Err(SdError::DataToken(token));
+    }
+    spi.read(buffer);
+    // The CRC of the block is not checked.
+    spi.transfer(0xFF);
+    spi.transfer(0xFF);
+    Ok(())
+}
+
+/// Lets a `FatVolume` be mounted on the card.
+impl<T: SpiOps> BlockDevice for SdCard<T> {
+    fn read_block(&mut self, block: u32, buffer: &mut [u8; BLOCK_SIZE]) -> Result<(), SdError> {
+        SdCard::read_block(self, block, buffer)
+    }
+
+    fn write_block(&mut self, block: u32, data: &[u8; BLOCK_SIZE]) -> Result<(), SdError> {
+        SdCard::write_block(self, block, data)
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod test {
+    use super::*;
+    use crate::hal::port::with_mock_pin;
+    use crate::mock::MockSpi;
+
+    /// Queues the answer of the card to a command, after the bytes clocked while
+    /// waiting for the card to be ready and sending the command.
+    fn answer(spi: &mut MockSpi, command: u8, response: &[u8]) {
+        let clocked = if command == CMD0 { 6 } else { 7 };
+        spi.queue_responses(&[0xFF; 7][..clocked]).unwrap();
+        spi.queue_responses(response).unwrap();
+    }
+
+    #[test]
+    fn sdhc_init() {
+        let mut spi = MockSpi::new();
+        // The clocks sent before the first command.
+        spi.queue_responses(&[0xFF; 10]).unwrap();
+        answer(&mut spi, CMD0, &[R1_IDLE]);
+        answer(&mut spi, CMD8, &[R1_IDLE, 0x00, 0x00, 0x01, 0xAA]);
+        answer(&mut spi, CMD55, &[R1_IDLE]);
+        answer(&mut spi, ACMD41, &[0x00]);
+        // The card capacity status bit is set in the OCR.
+        answer(&mut spi, CMD58, &[0x00, 0xC0, 0xFF, 0x80, 0x00]);
+
+        let card = SdCard::with_bus(&mut spi, 10).unwrap();
+        assert_eq!(card.card_type(), CardType::SDHC);
+        drop(card);
+
+        // CMD0 and CMD8 carry the CRC given by the specification.
+        let sent = spi.sent();
+        assert_eq!(sent[..10], [0xFF; 10]);
+        assert_eq!(sent[10..16], [0x40, 0x00, 0x00, 0x00, 0x00, 0x95]);
+        assert_eq!(sent[18..24], [0x48, 0x00, 0x00, 0x01, 0xAA, 0x87]);
+
+        // The card is left deselected.
+        with_mock_pin(10, |cs| {
+            assert!(cs.is_output());
+            assert!(cs.driven());
+        });
+    }
+}
@@ -0,0 +1,218 @@
+// RustDuino : A generic HAL implementation for Arduino Boards in Rust
+// Copyright (C) 2021 Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Sharing data between the main program and the interrupt service routines.
+//! `free` runs a closure in a critical section: SREG is saved, interrupts are disabled,
+//! and SREG is written back afterwards, so the interrupts are only enabled again if they
+//! were enabled before, and critical sections can be nested. It is the critical section
+//! of the crate, `hal::interrupts::without_interrupts` being the same without the token.
+//! `Mutex` gives access to the data it holds only inside a critical section, which makes
+//! a `static Mutex<RefCell<T>>` or `static Mutex<Cell<T>>` safe to use from the main
+//! program and the interrupts.
+//! The AVR chips read and write single bytes atomically, so `AtomicFlag` and `AtomicByte`
+//! need no critical section, except to change a byte according to its value.
+
+// Crates which would be used in the implementation.
+use core::cell::UnsafeCell;
+use core::marker::PhantomData;
+use core::ptr::{read_volatile, write_volatile};
+use core::sync::atomic::{compiler_fence, Ordering};
+
+// Status register, at the same address on both chips.
+const SREG: *mut u8 = 0x5F as *mut u8;
+
+/// Proof that the code runs with interrupts disabled, given by `free`.
+pub struct CriticalSection<'cs> {
+    _marker: PhantomData<&'cs ()>,
+}
+
+impl<'cs> CriticalSection<'cs> {
+    /// Creates the proof of a critical section.
+    /// # Safety
+    /// Interrupts must be disabled for the whole lifetime of the token, as in an interrupt
+    /// service routine.
+    pub unsafe fn new() -> CriticalSection<'cs> {
+        CriticalSection {
+            _marker: PhantomData,
+        }
+    }
+}
+
+/// Runs a closure with interrupts disabled and restores SREG afterwards.
+/// # Arguments
+/// * `f` - a closure, which gets the `CriticalSection` token.
+/// # Returns
+/// * `the value returned by the closure`.
+pub fn free<F, R>(f: F) -> R
+where
+    F: FnOnce(&CriticalSection) -> R,
+{
+    unsafe {
+        let sreg = read_volatile(SREG);
+        llvm_asm!("cli" :::: "volatile");
+        // The accesses of the closure must not be moved out of the critical section.
+        compiler_fence(Ordering::SeqCst);
+        let result = f(&CriticalSection::new());
+        compiler_fence(Ordering::SeqCst);
+        write_volatile(SREG, sreg);
+        result
+    }
+}
+
+/// Checks whether global interrupts are enabled.
+pub fn interrupts_enabled() -> bool {
+    unsafe { read_volatile(SREG) & (1 << 7) != 0 }
+}
+
+/// Data which can only be reached inside a critical section.
+pub struct Mutex<T> {
+    inner: UnsafeCell<T>,
+}
+
+impl<T> Mutex<T> {
+    /// Creates a mutex, which can be put in a static.
+    pub const fn new(value: T) -> Mutex<T> {
+        Mutex {
+            inner: UnsafeCell::new(value),
+        }
+    }
+
+    /// Gives the data for the length of the critical section.
+    /// # Arguments
+    /// * `_cs` - a `CriticalSection` object, the proof that interrupts are disabled.
+    /// # Returns
+    /// * `a reference to the data` - to be used with `Cell` or `RefCell` for changes.
+    pub fn borrow<'cs>(&'cs self, _cs: &'cs CriticalSection) -> &'cs T {
+        unsafe { &*self.inner.get() }
+    }
+
+    /// Gives the data through a mutable reference, which needs no critical section since
+    /// nothing else can reach it.
+    pub fn get_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.inner.get() }
+    }
+
+    /// Gives back the data.
+    pub fn into_inner(self) -> T {
+        self.inner.into_inner()
+    }
+}
+
+// The data is only reached with interrupts disabled, on a chip with a single core.
+unsafe impl<T> Sync for Mutex<T> where T: Send {}
+
+/// Flag set by an interrupt service routine and read by the main program, or the opposite.
+pub struct AtomicFlag {
+    value: UnsafeCell<bool>,
+}
+
+impl AtomicFlag {
+    /// Creates a flag, which can be put in a static.
+    pub const fn new(value: bool) -> AtomicFlag {
+        AtomicFlag {
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    /// Raises the flag.
+    pub fn set(&self) {
+        unsafe { write_volatile(self.value.get(), true) }
+    }
+
+    /// Lowers the flag.
+    pub fn clear(&self) {
+        unsafe { write_volatile(self.value.get(), false) }
+    }
+
+    /// Checks whether the flag is raised.
+    pub fn is_set(&self) -> bool {
+        unsafe { read_volatile(self.value.get()) }
+    }
+
+    /// Lowers the flag and tells whether it was raised, without missing a flag raised
+    /// by an interrupt in between.
+    /// # Returns
+    /// * `a bool` - true if the flag was raised.
+    pub fn take(&self) -> bool {
+        free(|_| {
+            let value = self.is_set();
+            self.clear();
+            value
+        })
+    }
+}
+
+unsafe impl Sync for AtomicFlag {}
+
+/// Byte shared between interrupt service routines and the main program.
+pub struct AtomicByte {
+    value: UnsafeCell<u8>,
+}
+
+impl AtomicByte {
+    /// Creates a byte, which can be put in a static.
+    pub const fn new(value: u8) -> AtomicByte {
+        AtomicByte {
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    /// Reads the byte.
+    pub fn load(&self) -> u8 {
+        unsafe { read_volatile(self.value.get()) }
+    }
+
+    /// Writes the byte.
+    pub fn store(&self, value: u8) {
+        unsafe { write_volatile(self.value.get(), value) }
+    }
+
+    /// Writes the byte and gives its previous value.
+    pub fn swap(&self, value: u8) -> u8 {
+        self.update(|_| value)
+    }
+
+    /// Adds to the byte, wrapping around, and gives its previous value.
+    pub fn fetch_add(&self, value: u8) -> u8 {
+        self.update(|old| old.wrapping_add(value))
+    }
+
+    /// Subtracts from the byte, wrapping around, and gives its previous value.
+    pub fn fetch_sub(&self, value: u8) -> u8 {
+        self.update(|old| old.wrapping_sub(value))
+    }
+
+    /// Sets the bits of a mask and gives the previous value.
+    pub fn fetch_or(&self, mask: u8) -> u8 {
+        self.update(|old| old | mask)
+    }
+
+    /// Keeps only the bits of a mask and gives the previous value.
+    pub fn fetch_and(&self, mask: u8) -> u8 {
+        self.update(|old| old & mask)
+    }
+
+    /// Changes the byte in a critical section and gives its previous value.
+    fn update<F: FnOnce(u8) -> u8>(&self, f: F) -> u8 {
+        free(|_| {
+            let old = self.load();
+            self.store(f(old));
+            old
+        })
+    }
+}
+
+unsafe impl Sync for AtomicByte {}
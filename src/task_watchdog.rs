@@ -0,0 +1,211 @@
+// RustDuino : A generic HAL implementation for Arduino Boards in Rust
+// Copyright (C) 2021 Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Task watchdog, which feeds the watchdog timer only while every supervised task
+//! keeps checking in.
+//! The tasks are given in a table with a deadline in milliseconds, and each one calls
+//! `check_in` with its index when it makes progress. `service` is called from the main
+//! loop and feeds the watchdog timer as long as no enabled task has been silent for
+//! longer than its deadline. Once one has, the name of the task is printed over USART0
+//! when the `com` feature is enabled, and the watchdog timer is not fed any more, so it
+//! resets the chip. A `feed()` in every task would keep the chip running while one of
+//! them hangs, and would not tell which one did.
+//! The deadlines are timed by the Timer0 tick of `time`, so `time::init` must be called.
+
+// Source codes required.
+use crate::hal::watchdog::{WatchDog, WatchdogMode, WatchdogPrescaler};
+use crate::time::millis;
+
+/// A task supervised by the task watchdog.
+/// # Elements
+/// * `name` - a string slice, the name printed when the task misses its deadline.
+/// * `deadline_ms` - a u32, the longest time in milliseconds between two check-ins.
+/// * `enabled` - a bool, false while the task is not supervised.
+/// * `last_check_in` - a u32, the time of the last check-in in milliseconds.
+#[derive(Clone, Copy)]
+pub struct WatchedTask {
+    name: &'static str,
+    deadline_ms: u32,
+    enabled: bool,
+    last_check_in: u32,
+}
+
+impl WatchedTask {
+    /// Creates a task, which is supervised from the start of the task watchdog.
+    /// # Arguments
+    /// * `name` - a string slice, the name printed when the task misses its deadline.
+    /// * `deadline_ms` - a u32, the longest time in milliseconds between two check-ins.
+    /// # Returns
+    /// * `a WatchedTask object` - to be put in the table of the task watchdog.
+    pub const fn new(name: &'static str, deadline_ms: u32) -> WatchedTask {
+        WatchedTask {
+            name,
+            deadline_ms,
+            enabled: true,
+            last_check_in: 0,
+        }
+    }
+
+    /// Gives the name of the task.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Gives the longest time in milliseconds between two check-ins.
+    pub fn deadline_ms(&self) -> u32 {
+        self.deadline_ms
+    }
+
+    /// Checks whether the task is supervised.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+}
+
+/// Used to supervise a table of `N` tasks.
+/// # Elements
+/// * `tasks` - a array of `WatchedTask` objects, the tasks, identified by their index.
+/// * `watchdog` - a `WatchDog` object, the watchdog timer in the reset mode.
+/// * `expired` - a `Option<usize>`, the index of the first task which missed its deadline.
+/// * `expiry_handler` - a `Option<fn(usize)>`, called with the index of that task.
+pub struct TaskWatchdog<const N: usize> {
+    tasks: [WatchedTask; N],
+    watchdog: &'static mut WatchDog,
+    expired: Option<usize>,
+    expiry_handler: Option<fn(usize)>,
+}
+
+impl<const N: usize> TaskWatchdog<N> {
+    /// Starts the watchdog timer in the reset mode, all the tasks are counted as
+    /// having checked in now.
+    /// `service` must then be called more often than the time-out of the timer.
+    /// # Arguments
+    /// * `tasks` - a array of `WatchedTask` objects, the tasks, identified by their index.
+    /// * `prescaler` - a `WatchdogPrescaler` object, the time-out of the watchdog timer.
+    /// # Returns
+    /// * `a TaskWatchdog object` - which will be used to supervise the tasks.
+    pub fn new(tasks: [WatchedTask; N], prescaler: WatchdogPrescaler) -> TaskWatchdog<N> {
+        let mut supervisor = TaskWatchdog {
+            tasks,
+            watchdog: unsafe { WatchDog::new() },
+            expired: None,
+            expiry_handler: None,
+        };
+        let now = millis();
+        for task in supervisor.tasks.iter_mut() {
+            task.last_check_in = now;
+        }
+        supervisor.watchdog.enable(WatchdogMode::Reset, prescaler);
+        supervisor
+    }
+
+    /// Sets a function called with the index of the task which missed its deadline,
+    /// before the chip is reset. It may save the index in the EEPROM.
+    pub fn set_expiry_handler(&mut self, handler: fn(usize)) {
+        self.expiry_handler = Some(handler);
+    }
+
+    /// Gives a task.
+    /// # Arguments
+    /// * `id` - a usize, the index of the task in the table.
+    pub fn task(&self, id: usize) -> &WatchedTask {
+        &self.tasks[id]
+    }
+
+    /// Records that a task has made progress.
+    /// # Arguments
+    /// * `id` - a usize, the index of the task in the table.
+    pub fn check_in(&mut self, id: usize) {
+        self.tasks[id].last_check_in = millis();
+    }
+
+    /// Stops supervising a task, for example while it waits for an event on purpose.
+    /// # Arguments
+    /// * `id` - a usize, the index of the task in the table.
+    pub fn suspend(&mut self, id: usize) {
+        self.tasks[id].enabled = false;
+    }
+
+    /// Supervises a task again, it is counted as having checked in now.
+    /// # Arguments
+    /// * `id` - a usize, the index of the task in the table.
+    pub fn resume(&mut self, id: usize) {
+        let task = &mut self.tasks[id];
+        task.enabled = true;
+        task.last_check_in = millis();
+    }
+
+    /// Gives the time since a task last checked in.
+    /// # Arguments
+    /// * `id` - a usize, the index of the task in the table.
+    /// # Returns
+    /// * `a u32` - the time in milliseconds.
+    pub fn silent_ms(&self, id: usize) -> u32 {
+        millis().wrapping_sub(self.tasks[id].last_check_in)
+    }
+
+    /// Gives the task which missed its deadline, if any.
+    /// # Returns
+    /// * `a Option<usize>` - the index of the task, None while all the tasks are on time.
+    pub fn expired(&self) -> Option<usize> {
+        self.expired
+    }
+
+    /// Feeds the watchdog timer if every enabled task has checked in within its deadline.
+    /// The first time a task is late, its name is printed and the expiry handler is called,
+    /// then the watchdog timer is never fed again and resets the chip.
+    /// # Returns
+    /// * `a Option<usize>` - the index of the late task, None while all the tasks are on time.
+    pub fn service(&mut self) -> Option<usize> {
+        if self.expired.is_some() {
+            return self.expired;
+        }
+        let now = millis();
+        let late = self.tasks.iter().position(|task| {
+            task.enabled && now.wrapping_sub(task.last_check_in) > task.deadline_ms
+        });
+        // The last feed leaves a whole time-out to print the name and run the handler.
+        self.watchdog.feed();
+        if let Some(id) = late {
+            self.expired = Some(id);
+            self.report(id, now);
+            if let Some(handler) = self.expiry_handler {
+                handler(id);
+            }
+        }
+        late
+    }
+
+    // Prints the name of the late task and how long it has been silent.
+    #[cfg(feature = "com")]
+    fn report(&self, id: usize, now: u32) {
+        use crate::print::{serial, write_format};
+
+        let task = &self.tasks[id];
+        write_format(
+            &mut serial(),
+            "\r\ntask watchdog: {} silent for {} ms, deadline {} ms\r\n",
+            &[
+                &task.name,
+                &now.wrapping_sub(task.last_check_in),
+                &task.deadline_ms,
+            ],
+        );
+    }
+
+    #[cfg(not(feature = "com"))]
+    fn report(&self, _id: usize, _now: u32) {}
+}
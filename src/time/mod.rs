@@ -0,0 +1,317 @@
+// RustDuino : A generic HAL implementation for Arduino Boards in Rust
+// Copyright (C) 2021 Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! System tick keeping the time since start up, like `millis()` and `micros()` of Arduino.
+//! Timer0 runs in Fast PWM mode with a prescaler of 64, the same as the Arduino core,
+//! so the PWM outputs of Timer0 keep working. Its overflow interrupt counts the
+//! milliseconds, and the microseconds are found from the overflow count and TCNT0.
+//! `Instant` and `Duration` allow waiting for some time without blocking the program.
+//! `soft_timer` runs functions after some time or periodically from the main loop.
+//! Refer to section 16 of ATMEGA2560P datasheet and section 15 of ATMEGA328P datasheet.
+
+pub mod soft_timer;
+
+// Source codes required.
+use crate::config::CPU_FREQUENCY_HZ;
+use crate::hal::analog::{Timer8, TimerNo8};
+use crate::hal::interrupts::{without_interrupts, Interrupt};
+
+// Crates required in the code for reading and writing to registers.
+use bit_field::BitField;
+use core::ops::{Add, Sub};
+use core::ptr::{read_volatile, write_volatile};
+
+// Registers of Timer0 outside the Timer8 structure, at the same address on both chips.
+const TIFR0: *mut u8 = 0x35 as *mut u8;
+const TIMSK0: *mut u8 = 0x6E as *mut u8;
+const PRR0: *mut u8 = 0x64 as *mut u8;
+
+// Timer0 overflow flag and interrupt enable bit, and the power reduction bit.
+const TOV0: u8 = 0;
+const TOIE0: u8 = 0;
+const PRTIM0: u8 = 5;
+
+const PRESCALER: u32 = 64;
+// Microseconds taken by one tick and by one overflow (256 ticks) of Timer0.
+const MICROS_PER_TICK: u32 = PRESCALER * 1_000_000 / CPU_FREQUENCY_HZ;
+const MICROS_PER_OVERFLOW: u32 = MICROS_PER_TICK * 256;
+// Whole milliseconds per overflow, and the remaining fraction in units of 8 microseconds
+// so that it fits in a u8.
+const MILLIS_INC: u32 = MICROS_PER_OVERFLOW / 1000;
+const FRACT_INC: u8 = ((MICROS_PER_OVERFLOW % 1000) >> 3) as u8;
+const FRACT_MAX: u8 = (1000 >> 3) as u8;
+
+// The same values for the present system clock, changed by `clock_changed`.
+static mut TICK_MICROS: u32 = MICROS_PER_TICK;
+static mut OVERFLOW_MILLIS: u32 = MILLIS_INC;
+static mut OVERFLOW_FRACT: u8 = FRACT_INC;
+// Microseconds counted before the last change of the system clock.
+static mut MICROS_BASE: u32 = 0;
+
+static mut MILLIS: u32 = 0;
+static mut FRACT: u8 = 0;
+static mut OVERFLOWS: u32 = 0;
+
+// Function called on every overflow, after the counters are updated.
+static mut OVERFLOW_HOOK: Option<fn()> = None;
+
+/// Starts Timer0 and its overflow interrupt, and enables global interrupts.
+/// This must be called once before `millis`, `micros` or `Instant` are used.
+pub fn init() {
+    unsafe {
+        let mut prr = read_volatile(PRR0);
+        prr.set_bit(PRTIM0, false);
+        write_volatile(PRR0, prr);
+    }
+
+    let timer = Timer8::new(TimerNo8::Timer0);
+    // WGM02:0 = 011 for Fast PWM, CS02:0 = 011 for a prescaler of 64.
+    timer.tccra.update(|tccra| {
+        tccra.set_bits(0..2, 0b11);
+    });
+    timer.tccrb.update(|tccrb| {
+        tccrb.set_bit(3, false);
+        tccrb.set_bits(0..3, 0b011);
+    });
+
+    unsafe {
+        let mut timsk = read_volatile(TIMSK0);
+        timsk.set_bit(TOIE0, true);
+        write_volatile(TIMSK0, timsk);
+        Interrupt::new().enable();
+    }
+}
+
+/// Checks whether `init` was called, so that Timer0 must be kept as it is.
+/// # Returns
+/// * `a bool` - true if the overflow interrupt of Timer0 is counting the time.
+pub fn is_running() -> bool {
+    unsafe { read_volatile(TIMSK0).get_bit(TOIE0) }
+}
+
+/// Gives the number of milliseconds since `init` was called.
+/// The value overflows back to zero after about 49 days.
+/// # Returns
+/// * `a u32` - the number of milliseconds.
+pub fn millis() -> u32 {
+    without_interrupts(|| unsafe { MILLIS })
+}
+
+/// Gives the number of microseconds since `init` was called.
+/// The resolution is 4 microseconds at 16 MHz and the value overflows
+/// back to zero after about 71 minutes.
+/// # Returns
+/// * `a u32` - the number of microseconds.
+pub fn micros() -> u32 {
+    without_interrupts(|| unsafe {
+        let mut overflows = OVERFLOWS;
+        let ticks = Timer8::new(TimerNo8::Timer0).tcnt.read();
+        // An overflow which happened after interrupts were disabled is not counted yet.
+        if read_volatile(TIFR0).get_bit(TOV0) && ticks < 255 {
+            overflows = overflows.wrapping_add(1);
+        }
+        overflows
+            .wrapping_mul(256)
+            .wrapping_add(ticks as u32)
+            .wrapping_mul(TICK_MICROS)
+            .wrapping_add(MICROS_BASE)
+    })
+}
+
+/// Sets a function called from the overflow interrupt of Timer0, about once per
+/// millisecond, for checks which must run even when the program is stuck.
+/// The function must be short, as it delays the other interrupts.
+/// # Arguments
+/// * `hook` - an Option of a function, `None` to remove the former one.
+pub fn set_overflow_hook(hook: Option<fn()>) {
+    without_interrupts(|| unsafe {
+        OVERFLOW_HOOK = hook;
+    });
+}
+
+/// Computes the time taken by the ticks of Timer0 again, from the present frequency of
+/// the system clock. It is called by `crate::clock` after the clock has changed.
+/// The time counted so far is kept, and `micros` is only exact at frequencies which divide
+/// 64 MHz.
+/// # Arguments
+/// * `frequency` - a u32, the frequency of the system clock in Hertz.
+pub fn clock_changed(frequency: u32) {
+    let tick = PRESCALER * 1_000_000 / frequency;
+    let overflow = tick * 256;
+    without_interrupts(|| unsafe {
+        // The overflows counted so far are turned into microseconds at the former rate.
+        MICROS_BASE = OVERFLOWS
+            .wrapping_mul(256)
+            .wrapping_mul(TICK_MICROS)
+            .wrapping_add(MICROS_BASE);
+        OVERFLOWS = 0;
+        TICK_MICROS = tick;
+        OVERFLOW_MILLIS = overflow / 1000;
+        OVERFLOW_FRACT = ((overflow % 1000) >> 3) as u8;
+    });
+}
+
+/// Updates the counters on every overflow of Timer0.
+#[cfg(target_arch = "avr")]
+unsafe fn timer0_overflow() {
+    let mut millis = MILLIS.wrapping_add(OVERFLOW_MILLIS);
+    let mut fract = FRACT + OVERFLOW_FRACT;
+    if fract >= FRACT_MAX {
+        fract -= FRACT_MAX;
+        millis = millis.wrapping_add(1);
+    }
+    MILLIS = millis;
+    FRACT = fract;
+    OVERFLOWS = OVERFLOWS.wrapping_add(1);
+    if let Some(hook) = OVERFLOW_HOOK {
+        hook();
+    }
+}
+
+/// Timer/Counter0 Overflow of ATMEGA2560P.
+#[cfg(all(target_arch = "avr", feature = "atmega2560p"))]
+#[no_mangle]
+pub unsafe extern "avr-interrupt" fn __vector_23() {
+    timer0_overflow();
+}
+
+/// Timer/Counter0 Overflow of ATMEGA328P.
+#[cfg(all(target_arch = "avr", feature = "atmega328p"))]
+#[no_mangle]
+pub unsafe extern "avr-interrupt" fn __vector_16() {
+    timer0_overflow();
+}
+
+/// A span of time with a resolution of one microsecond, up to about 71 minutes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Duration {
+    micros: u32,
+}
+
+impl Duration {
+    /// Creates a duration from microseconds.
+    pub const fn from_micros(micros: u32) -> Duration {
+        Duration { micros }
+    }
+
+    /// Creates a duration from milliseconds.
+    pub const fn from_millis(millis: u32) -> Duration {
+        Duration {
+            micros: millis * 1000,
+        }
+    }
+
+    /// Creates a duration from seconds.
+    pub const fn from_secs(secs: u32) -> Duration {
+        Duration {
+            micros: secs * 1_000_000,
+        }
+    }
+
+    /// Gives the duration in microseconds.
+    pub const fn as_micros(&self) -> u32 {
+        self.micros
+    }
+
+    /// Gives the duration in whole milliseconds.
+    pub const fn as_millis(&self) -> u32 {
+        self.micros / 1000
+    }
+
+    /// Gives the duration in whole seconds.
+    pub const fn as_secs(&self) -> u32 {
+        self.micros / 1_000_000
+    }
+}
+
+impl Add for Duration {
+    type Output = Duration;
+
+    fn add(self, other: Duration) -> Duration {
+        Duration::from_micros(self.micros.wrapping_add(other.micros))
+    }
+}
+
+impl Sub for Duration {
+    type Output = Duration;
+
+    fn sub(self, other: Duration) -> Duration {
+        Duration::from_micros(self.micros.saturating_sub(other.micros))
+    }
+}
+
+/// A point in time, read from `micros()`.
+/// Comparisons between instants work correctly across the overflow of the counter
+/// as long as they are less than about 35 minutes apart.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Instant {
+    micros: u32,
+}
+
+impl Instant {
+    /// Gives the current instant.
+    pub fn now() -> Instant {
+        Instant { micros: micros() }
+    }
+
+    /// Gives the time passed from an earlier instant to this one.
+    /// # Arguments
+    /// * `earlier` - a `Instant` object, which is before this one.
+    /// # Returns
+    /// * `a Duration object` - the time between the two instants.
+    pub fn duration_since(&self, earlier: Instant) -> Duration {
+        Duration::from_micros(self.micros.wrapping_sub(earlier.micros))
+    }
+
+    /// Gives the time passed since this instant.
+    /// # Returns
+    /// * `a Duration object` - the time between this instant and now.
+    pub fn elapsed(&self) -> Duration {
+        Instant::now().duration_since(*self)
+    }
+
+    /// Checks without blocking whether the given time has passed since this instant.
+    /// # Arguments
+    /// * `duration` - a `Duration` object, the time to be checked.
+    /// # Returns
+    /// * `a boolean` - which is true if the time has passed.
+    pub fn has_elapsed(&self, duration: Duration) -> bool {
+        self.elapsed() >= duration
+    }
+
+    /// Checks whether this instant is after the other one.
+    pub fn is_after(&self, other: Instant) -> bool {
+        (self.micros.wrapping_sub(other.micros) as i32) > 0
+    }
+}
+
+impl Add<Duration> for Instant {
+    type Output = Instant;
+
+    fn add(self, duration: Duration) -> Instant {
+        Instant {
+            micros: self.micros.wrapping_add(duration.micros),
+        }
+    }
+}
+
+impl Sub<Instant> for Instant {
+    type Output = Duration;
+
+    fn sub(self, earlier: Instant) -> Duration {
+        self.duration_since(earlier)
+    }
+}
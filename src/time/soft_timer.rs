@@ -0,0 +1,223 @@
+// RustDuino : A generic HAL implementation for Arduino Boards in Rust
+// Copyright (C) 2021 Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Software timers calling a function once after some time, or again and again with a
+//! period, all counted on the millisecond tick of `time`, so `time::init` must be called.
+//! Timers are started and cancelled while the program runs, unlike the fixed table of
+//! tasks of the scheduler. The functions are called by `run`, from the main loop and not
+//! from an interrupt, so they may take their time, which delays the other timers.
+//! A started timer is identified by a `TimerId`. The place of a finished timer is used
+//! again by the next one, and the old id then no longer refers to any timer.
+
+// Source codes required.
+use crate::error::Error;
+use crate::time::millis;
+
+/// Identifies a started timer.
+/// # Elements
+/// * `slot` - a u8, the place of the timer in the list.
+/// * `generation` - a u8, the number of timers started in this place before.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct TimerId {
+    slot: u8,
+    generation: u8,
+}
+
+/// A started timer.
+/// # Elements
+/// * `callback` - a function, called when the timer expires.
+/// * `due` - a u32, the time of the next call in milliseconds.
+/// * `period_ms` - a u32, the time between two calls, 0 for a timer called once.
+#[derive(Clone, Copy)]
+struct Timer {
+    callback: fn(),
+    due: u32,
+    period_ms: u32,
+}
+
+/// Used to run up to `N` software timers.
+/// # Elements
+/// * `timers` - a array of `Option<Timer>`, the started timers.
+/// * `generations` - a array of u8, the generation of the timer in each place.
+pub struct SoftTimers<const N: usize> {
+    timers: [Option<Timer>; N],
+    generations: [u8; N],
+}
+
+impl<const N: usize> SoftTimers<N> {
+    /// Creates the timers, none of which is started.
+    pub const fn new() -> SoftTimers<N> {
+        SoftTimers {
+            timers: [None; N],
+            generations: [0; N],
+        }
+    }
+
+    /// Starts a timer calling a function once.
+    /// # Arguments
+    /// * `delay_ms` - a u32, the time before the call in milliseconds.
+    /// * `callback` - a function, called when the time has passed.
+    /// # Returns
+    /// * `a Result<TimerId, Error>` - the id of the timer, or `Error::Busy` if `N` timers
+    ///   are already started.
+    pub fn after(&mut self, delay_ms: u32, callback: fn()) -> Result<TimerId, Error> {
+        self.start(delay_ms, 0, callback)
+    }
+
+    /// Starts a timer calling a function periodically, first after one period.
+    /// # Arguments
+    /// * `period_ms` - a u32, the time between two calls in milliseconds, at least 1.
+    /// * `callback` - a function, called at each period.
+    /// # Returns
+    /// * `a Result<TimerId, Error>` - the id of the timer, `Error::InvalidParam` for a
+    ///   period of 0, or `Error::Busy` if `N` timers are already started.
+    pub fn every(&mut self, period_ms: u32, callback: fn()) -> Result<TimerId, Error> {
+        if period_ms == 0 {
+            return Err(Error::InvalidParam);
+        }
+        self.start(period_ms, period_ms, callback)
+    }
+
+    fn start(&mut self, delay_ms: u32, period_ms: u32, callback: fn()) -> Result<TimerId, Error> {
+        let slot = self
+            .timers
+            .iter()
+            .position(|timer| timer.is_none())
+            .ok_or(Error::Busy)?;
+        self.generations[slot] = self.generations[slot].wrapping_add(1);
+        self.timers[slot] = Some(Timer {
+            callback,
+            due: millis().wrapping_add(delay_ms),
+            period_ms,
+        });
+        Ok(TimerId {
+            slot: slot as u8,
+            generation: self.generations[slot],
+        })
+    }
+
+    // Gives the timer of an id, None if it has finished or was cancelled.
+    fn get(&mut self, id: TimerId) -> Option<&mut Timer> {
+        let slot = id.slot as usize;
+        if slot < N && self.generations[slot] == id.generation {
+            self.timers[slot].as_mut()
+        } else {
+            None
+        }
+    }
+
+    /// Stops a timer before its function is called.
+    /// # Arguments
+    /// * `id` - a `TimerId` object, the timer.
+    /// # Returns
+    /// * `a boolean` - which is true if the timer was running.
+    pub fn cancel(&mut self, id: TimerId) -> bool {
+        match self.get(id) {
+            Some(_) => {
+                self.timers[id.slot as usize] = None;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Stops all the timers.
+    pub fn cancel_all(&mut self) {
+        self.timers = [None; N];
+    }
+
+    /// Starts counting the time of a timer again from now, which puts off a timeout.
+    /// # Arguments
+    /// * `id` - a `TimerId` object, the timer.
+    /// * `delay_ms` - a u32, the time before the next call in milliseconds.
+    /// # Returns
+    /// * `a boolean` - which is false if the timer has finished or was cancelled.
+    pub fn restart(&mut self, id: TimerId, delay_ms: u32) -> bool {
+        match self.get(id) {
+            Some(timer) => {
+                timer.due = millis().wrapping_add(delay_ms);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Checks whether a timer is still running.
+    pub fn is_active(&mut self, id: TimerId) -> bool {
+        self.get(id).is_some()
+    }
+
+    /// Gives the milliseconds before the next call of a timer.
+    /// # Returns
+    /// * `a Option<u32>` - the time, 0 if the call is due, None if the timer has finished
+    ///   or was cancelled.
+    pub fn remaining_ms(&mut self, id: TimerId) -> Option<u32> {
+        let now = millis();
+        self.get(id)
+            .map(|timer| (timer.due.wrapping_sub(now) as i32).max(0) as u32)
+    }
+
+    /// Gives the number of running timers.
+    pub fn active(&self) -> usize {
+        self.timers.iter().filter(|timer| timer.is_some()).count()
+    }
+
+    /// Gives the milliseconds until the next call of any timer, 0 if one is due now.
+    /// # Returns
+    /// * `a Option<u32>` - the time, None if no timer is running.
+    pub fn time_to_next(&self) -> Option<u32> {
+        let now = millis();
+        self.timers
+            .iter()
+            .flatten()
+            .map(|timer| timer.due.wrapping_sub(now) as i32)
+            .min()
+            .map(|wait| wait.max(0) as u32)
+    }
+
+    /// Calls the functions of the timers which are due. A timer called once finishes,
+    /// and a periodic timer which is late by whole periods skips the calls it missed.
+    /// # Returns
+    /// * `a usize` - the number of functions called.
+    pub fn run(&mut self) -> usize {
+        let mut calls = 0;
+        for slot in 0..N {
+            let now = millis();
+            let timer = match self.timers[slot] {
+                Some(timer) if now.wrapping_sub(timer.due) as i32 >= 0 => timer,
+                _ => continue,
+            };
+            if timer.period_ms == 0 {
+                self.timers[slot] = None;
+            } else {
+                let late = now.wrapping_sub(timer.due);
+                let due = now.wrapping_add(timer.period_ms - late % timer.period_ms);
+                if let Some(timer) = self.timers[slot].as_mut() {
+                    timer.due = due;
+                }
+            }
+            (timer.callback)();
+            calls += 1;
+        }
+        calls
+    }
+}
+
+impl<const N: usize> Default for SoftTimers<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
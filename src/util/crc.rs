@@ -0,0 +1,184 @@
+// RustDuino : A generic HAL implementation for Arduino Boards in Rust
+// Copyright (C) 2021 Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Cyclic redundancy checks used by the buses and storage formats.
+//! They are computed bit by bit instead of from lookup tables, which would take
+//! 256 to 1024 bytes of flash each, as the blocks checked on AVR are short.
+//! The names and parameters are the ones of the catalogue of parametrised CRC algorithms,
+//! `<https://reveng.sourceforge.io/crc-catalogue/>`, whose check value is the CRC of
+//! the ASCII string "123456789".
+
+/// Gives the CRC7 of the commands of SD and MMC cards (polynomial 0x09).
+/// # Arguments
+/// * `data` - a slice of u8, the first 5 bytes of the command.
+/// # Returns
+/// * `a u8` - the 7 bit CRC, which is sent shifted left by one with the end bit set.
+pub fn crc7(data: &[u8]) -> u8 {
+    let mut crc = 0u8;
+    for byte in data {
+        crc ^= *byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ (0x09 << 1)
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc >> 1
+}
+
+/// Gives a CRC8 which shifts the most significant bit first, as the ones of SMBus
+/// (polynomial 0x07, initial value 0x00) and of the Sensirion sensors (polynomial 0x31,
+/// initial value 0xFF).
+/// # Arguments
+/// * `data` - a slice of u8, the bytes to be checked.
+/// * `polynomial` - a u8, the polynomial without its x^8 term.
+/// * `init` - a u8, the initial value.
+/// # Returns
+/// * `a u8` - the CRC.
+pub fn crc8(data: &[u8], polynomial: u8, init: u8) -> u8 {
+    let mut crc = init;
+    for byte in data {
+        crc ^= *byte;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 {
+                (crc << 1) ^ polynomial
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// Gives the Dallas/Maxim CRC8 (polynomial x^8 + x^5 + x^4 + 1) of the 1-Wire devices.
+/// The CRC of a ROM code or a scratchpad including its CRC byte is 0.
+/// # Arguments
+/// * `data` - a slice of u8, the bytes to be checked.
+/// # Returns
+/// * `a u8` - the CRC.
+pub fn crc8_maxim(data: &[u8]) -> u8 {
+    let mut crc = 0u8;
+    for byte in data {
+        crc ^= *byte;
+        for _ in 0..8 {
+            crc = if crc & 0x01 != 0 {
+                (crc >> 1) ^ 0x8C
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    crc
+}
+
+/// Gives the CRC16 of Modbus RTU, with the reflected polynomial 0xA001 and 0xFFFF as initial value.
+/// # Arguments
+/// * `data` - a slice of u8, the bytes of the frame without the CRC.
+/// # Returns
+/// * `a u16` - the CRC, which is sent low byte first.
+pub fn crc16_modbus(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0xFFFF;
+    for byte in data {
+        crc ^= *byte as u16;
+        for _ in 0..8 {
+            crc = if crc & 0x0001 != 0 {
+                (crc >> 1) ^ 0xA001
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    crc
+}
+
+/// Gives the CRC16 of XMODEM (polynomial 0x1021, initial value 0), which is the one
+/// of the data blocks of SD cards.
+/// # Arguments
+/// * `data` - a slice of u8, the bytes to be checked.
+/// # Returns
+/// * `a u16` - the CRC, which is sent high byte first.
+pub fn crc16_xmodem(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0;
+    for byte in data {
+        crc ^= (*byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// Continues a CRC32 over more bytes, for data which is not in memory at once.
+/// The CRC starts as 0 and each block is added with this function.
+/// # Arguments
+/// * `crc` - a u32, the CRC of the bytes before this block.
+/// * `data` - a slice of u8, the block to be added.
+/// # Returns
+/// * `a u32` - the CRC of all the bytes up to the end of the block.
+pub fn crc32_update(crc: u32, data: &[u8]) -> u32 {
+    let mut crc = !crc;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 0x0000_0001 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// Gives the CRC32 of Ethernet, zip and PNG (reflected polynomial 0xEDB88320).
+/// # Arguments
+/// * `data` - a slice of u8, the bytes to be checked.
+/// # Returns
+/// * `a u32` - the CRC.
+pub fn crc32(data: &[u8]) -> u32 {
+    crc32_update(0, data)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const CHECK: &[u8] = b"123456789";
+
+    #[test]
+    fn check_values() {
+        assert_eq!(crc7(CHECK), 0x75);
+        assert_eq!(crc8(CHECK, 0x07, 0x00), 0xF4);
+        assert_eq!(crc8_maxim(CHECK), 0xA1);
+        assert_eq!(crc16_modbus(CHECK), 0x4B37);
+        assert_eq!(crc16_xmodem(CHECK), 0x31C3);
+        assert_eq!(crc32(CHECK), 0xCBF4_3926);
+        assert_eq!(crc32_update(crc32(&CHECK[..4]), &CHECK[4..]), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn sd_command_crc() {
+        // CMD0 and CMD8 with the argument 0x1AA, whose CRC bytes are given by the specification.
+        assert_eq!(crc7(&[0x40, 0, 0, 0, 0]) << 1 | 1, 0x95);
+        assert_eq!(crc7(&[0x48, 0, 0, 0x01, 0xAA]) << 1 | 1, 0x87);
+    }
+}
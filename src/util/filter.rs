@@ -0,0 +1,279 @@
+// RustDuino : A generic HAL implementation for Arduino Boards in Rust
+// Copyright (C) 2021 Prateek Kumar Pandey, Indian Institute of Technology Kanpur
+//
+// This program is free software: you can redistribute it and/or modify
+// it under the terms of the GNU Affero General Public License as published
+// by the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// This program is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU Affero General Public License for more details.
+//
+// You should have received a copy of the GNU Affero General Public License
+// along with this program.  If not, see <https://www.gnu.org/licenses/>
+
+//! Filters smoothing the noisy readings of the ADC and of the sensors.
+//! `MovingAverage` gives the mean of the last `N` samples, `Median` gives their median,
+//! which ignores single spikes, and `Exponential` is a single pole low pass filter which
+//! needs no window. The samples are integers and no float is used.
+//! Adding a sample takes a short and fixed time, except for the sort done when the median
+//! is read, so the filters can be fed from an interrupt routine. Their `new` is a `const fn`,
+//! so they can be kept in a `static` inside a `sync::Mutex`.
+
+/// Mean of the last `N` samples, kept up to date with a running sum.
+/// # Elements
+/// * `window` - a array of i32, the last `N` samples.
+/// * `index` - a usize, the place of the next sample in the window.
+/// * `count` - a usize, the number of samples in the window, up to `N`.
+/// * `sum` - a i64, the sum of the samples in the window.
+#[derive(Clone, Copy)]
+pub struct MovingAverage<const N: usize> {
+    window: [i32; N],
+    index: usize,
+    count: usize,
+    sum: i64,
+}
+
+impl<const N: usize> MovingAverage<N> {
+    /// Creates an empty filter.
+    pub const fn new() -> MovingAverage<N> {
+        MovingAverage {
+            window: [0; N],
+            index: 0,
+            count: 0,
+            sum: 0,
+        }
+    }
+
+    /// Adds a sample, dropping the oldest one once the window is full.
+    /// # Arguments
+    /// * `sample` - a i32, the new sample.
+    /// # Returns
+    /// * `a i32` - the mean of the samples in the window.
+    pub fn push(&mut self, sample: i32) -> i32 {
+        if N == 0 {
+            return sample;
+        }
+        if self.count == N {
+            self.sum -= self.window[self.index] as i64;
+        } else {
+            self.count += 1;
+        }
+        self.window[self.index] = sample;
+        self.sum += sample as i64;
+        self.index = (self.index + 1) % N;
+        self.value()
+    }
+
+    /// Gives the mean of the samples in the window, rounded towards zero.
+    /// # Returns
+    /// * `a i32` - the mean, 0 if no sample was added.
+    pub fn value(&self) -> i32 {
+        if self.count == 0 {
+            return 0;
+        }
+        (self.sum / self.count as i64) as i32
+    }
+
+    /// Checks whether `N` samples have been added, before which the mean is the one
+    /// of fewer samples.
+    pub fn is_full(&self) -> bool {
+        self.count == N
+    }
+
+    /// Removes all the samples.
+    pub fn reset(&mut self) {
+        *self = MovingAverage::new();
+    }
+}
+
+impl<const N: usize> Default for MovingAverage<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Median of the last `N` samples, which an odd `N` of 3 or 5 makes cheap.
+/// # Elements
+/// * `window` - a array of i32, the last `N` samples.
+/// * `index` - a usize, the place of the next sample in the window.
+/// * `count` - a usize, the number of samples in the window, up to `N`.
+#[derive(Clone, Copy)]
+pub struct Median<const N: usize> {
+    window: [i32; N],
+    index: usize,
+    count: usize,
+}
+
+impl<const N: usize> Median<N> {
+    /// Creates an empty filter.
+    pub const fn new() -> Median<N> {
+        Median {
+            window: [0; N],
+            index: 0,
+            count: 0,
+        }
+    }
+
+    /// Adds a sample, dropping the oldest one once the window is full.
+    /// # Arguments
+    /// * `sample` - a i32, the new sample.
+    pub fn push(&mut self, sample: i32) {
+        if N == 0 {
+            return;
+        }
+        self.window[self.index] = sample;
+        self.index = (self.index + 1) % N;
+        if self.count < N {
+            self.count += 1;
+        }
+    }
+
+    /// Gives the median of the samples in the window. With an even number of samples
+    /// it is the mean of the two in the middle.
+    /// # Returns
+    /// * `a i32` - the median, 0 if no sample was added.
+    pub fn value(&self) -> i32 {
+        if self.count == 0 {
+            return 0;
+        }
+        let mut sorted = self.window;
+        let sorted = &mut sorted[..self.count];
+        // An insertion sort, which is the fastest for a few samples.
+        for i in 1..sorted.len() {
+            let mut j = i;
+            while j > 0 && sorted[j - 1] > sorted[j] {
+                sorted.swap(j - 1, j);
+                j -= 1;
+            }
+        }
+        let middle = self.count / 2;
+        if self.count % 2 == 1 {
+            sorted[middle]
+        } else {
+            ((sorted[middle - 1] as i64 + sorted[middle] as i64) / 2) as i32
+        }
+    }
+
+    /// Checks whether `N` samples have been added.
+    pub fn is_full(&self) -> bool {
+        self.count == N
+    }
+
+    /// Removes all the samples.
+    pub fn reset(&mut self) {
+        *self = Median::new();
+    }
+}
+
+impl<const N: usize> Default for Median<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Single pole low pass filter, y += (x - y) / 2^shift.
+/// The output is kept with 8 more fractional bits, so that it reaches the input after a
+/// step instead of stopping short of it, which limits the samples to about 8 million.
+/// # Elements
+/// * `shift` - a u8, the smoothing, the output moves by 1/2^shift of the difference.
+/// * `state` - a i32, the output times 256.
+/// * `primed` - a bool, false until the first sample, which is taken as it is.
+#[derive(Clone, Copy)]
+pub struct Exponential {
+    shift: u8,
+    state: i32,
+    primed: bool,
+}
+
+impl Exponential {
+    /// Creates a filter, which follows a step to 63% in about 2^shift samples.
+    /// # Arguments
+    /// * `shift` - a u8, from 1 for little smoothing to about 8 for a lot.
+    pub const fn new(shift: u8) -> Exponential {
+        Exponential {
+            shift,
+            state: 0,
+            primed: false,
+        }
+    }
+
+    /// Adds a sample.
+    /// # Arguments
+    /// * `sample` - a i32, the new sample.
+    /// # Returns
+    /// * `a i32` - the output of the filter.
+    pub fn push(&mut self, sample: i32) -> i32 {
+        let input = sample << 8;
+        if self.primed {
+            self.state += (input - self.state) >> self.shift;
+        } else {
+            self.state = input;
+            self.primed = true;
+        }
+        self.value()
+    }
+
+    /// Gives the output of the filter, rounded to the nearest integer.
+    pub fn value(&self) -> i32 {
+        (self.state + 128) >> 8
+    }
+
+    /// Forgets the samples, the next one is taken as it is.
+    pub fn reset(&mut self) {
+        self.primed = false;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn moving_average() {
+        let mut filter = MovingAverage::<4>::new();
+        assert_eq!(filter.value(), 0);
+        assert_eq!(filter.push(4), 4);
+        assert_eq!(filter.push(8), 6);
+        assert!(!filter.is_full());
+        filter.push(12);
+        assert_eq!(filter.push(16), 10);
+        assert!(filter.is_full());
+        // The 4 leaves the window.
+        assert_eq!(filter.push(-20), 4);
+        filter.reset();
+        assert_eq!(filter.push(-3), -3);
+    }
+
+    #[test]
+    fn median_ignores_spikes() {
+        let mut filter = Median::<5>::new();
+        for sample in [100, 101, 3000, 99, 102].iter() {
+            filter.push(*sample);
+        }
+        assert_eq!(filter.value(), 101);
+        filter.push(-5000);
+        assert_eq!(filter.value(), 101);
+
+        let mut even = Median::<4>::new();
+        for sample in [1, 9, 3, 7].iter() {
+            even.push(*sample);
+        }
+        assert_eq!(even.value(), 5);
+    }
+
+    #[test]
+    fn exponential_settles() {
+        let mut filter = Exponential::new(2);
+        assert_eq!(filter.push(100), 100);
+        assert_eq!(filter.push(200), 125);
+        for _ in 0..50 {
+            filter.push(200);
+        }
+        assert_eq!(filter.value(), 200);
+        filter.reset();
+        assert_eq!(filter.push(-7), -7);
+    }
+}